@@ -0,0 +1,171 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A small command line tool for TJSON documents.
+//!
+//! ```text
+//! cargo run --example tjson_tool -- validate doc.tjson
+//! cargo run --example tjson_tool -- pretty --indent 4 doc.tjson
+//! cat doc.json | cargo run --example tjson_tool -- from-json
+//! ```
+//!
+//! Every command reads the named file, or standard input if there is none,
+//! and writes its result to standard output. The exit status is 0 on
+//! success, 1 if the input isn't a valid document, and 2 for bad arguments
+//! or files which can't be read.
+
+extern crate serde;
+extern crate tjson;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde::Serialize;
+use tjson::Value;
+use tjson::ser::{PrettyFormatter, SerializeConfig, Serializer, TagInference};
+
+const USAGE: &str = "\
+usage: tjson_tool <command> [options] [file]
+
+commands:
+    validate              check that the input is valid TJSON
+    pretty [--indent N]   write the input indented by N spaces (default 2)
+    canonical             write the input in canonical form
+    from-json             tag the members of a plain JSON document
+    to-json               take the tags off the members of a document
+    digest                write a fingerprint of the input's canonical form";
+
+const COMMANDS: &[&str] = &["validate", "pretty", "canonical", "from-json", "to-json", "digest"];
+
+/// Why the tool stopped, and the exit status for it.
+enum Failure {
+    Usage(String),
+    Io(String, io::Error),
+    Invalid(String, tjson::Error),
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok(()) => 0,
+        Err(Failure::Usage(msg)) => {
+            eprintln!("tjson_tool: {}\n\n{}", msg, USAGE);
+            2
+        }
+        Err(Failure::Io(name, err)) => {
+            eprintln!("tjson_tool: {}: {}", name, err);
+            2
+        }
+        Err(Failure::Invalid(name, err)) => {
+            if err.line() == 0 {
+                eprintln!("{}: {} error ({}): {}", name, err.classify(), err.code(), err);
+            } else {
+                eprintln!(
+                    "{}:{}:{}: {} error ({}): {}",
+                    name,
+                    err.line(),
+                    err.column(),
+                    err.classify(),
+                    err.code(),
+                    err
+                );
+            }
+            1
+        }
+    };
+    process::exit(code);
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return Err(Failure::Usage("no command given".to_owned())),
+    };
+    if !COMMANDS.contains(&command) {
+        return Err(Failure::Usage(format!("unknown command `{}`", command)));
+    }
+
+    let mut indent = 2;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--indent" if command == "pretty" => {
+                indent = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => n,
+                    None => return Err(Failure::Usage("--indent needs a number".to_owned())),
+                };
+            }
+            "-" if file.is_none() => file = Some(None),
+            arg if !arg.starts_with('-') && file.is_none() => file = Some(Some(arg)),
+            arg => return Err(Failure::Usage(format!("unexpected argument `{}`", arg))),
+        }
+    }
+    let file = file.unwrap_or(None);
+
+    let name = file.unwrap_or("<stdin>").to_owned();
+    let input = try!(read_input(file).map_err(|err| Failure::Io(name.clone(), err)));
+    let invalid = |err| Failure::Invalid(name.clone(), err);
+
+    let output = match command {
+        "validate" => {
+            try!(tjson::from_str::<Value>(&input).map_err(&invalid));
+            return Ok(());
+        }
+        "pretty" => {
+            let value: Value = try!(tjson::from_str(&input).map_err(&invalid));
+            let spaces = vec![b' '; indent];
+            let formatter = PrettyFormatter::with_indent(&spaces).trailing_newline(true);
+            let mut ser =
+                Serializer::with_formatter(Vec::new(), formatter).infer_tags(TagInference::Off);
+            try!(value.serialize(&mut ser).map_err(&invalid));
+            String::from_utf8(ser.into_inner()).unwrap()
+        }
+        "canonical" => {
+            let value: Value = try!(tjson::from_str(&input).map_err(&invalid));
+            try!(tjson::to_string_canonical(&value).map_err(&invalid)) + "\n"
+        }
+        "from-json" => {
+            let value: Value = try!(tjson::from_str(&input).map_err(&invalid));
+            let value = try!(tjson::json::from_json(value).map_err(&invalid));
+            try!(tjson::to_string_canonical(&value).map_err(&invalid)) + "\n"
+        }
+        "to-json" => {
+            let value: Value = try!(tjson::from_str(&input).map_err(&invalid));
+            let value = try!(tjson::json::to_json(value).map_err(&invalid));
+            let config = SerializeConfig::new().infer_tags(TagInference::Off);
+            try!(tjson::to_string_with(&config, &value).map_err(&invalid)) + "\n"
+        }
+        "digest" => {
+            // Read back the canonical form, so the fingerprint doesn't depend
+            // on the order members were written in
+            let value: Value = try!(tjson::from_str(&input).map_err(&invalid));
+            let canonical = try!(tjson::to_string_canonical(&value).map_err(&invalid));
+            let value: Value = try!(tjson::from_str(&canonical).map_err(&invalid));
+            format!("{:016x}\n", value.hash_fingerprint())
+        }
+        _ => unreachable!(),
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    stdout
+        .write_all(output.as_bytes())
+        .map_err(|err| Failure::Io("<stdout>".to_owned(), err))
+}
+
+fn read_input(file: Option<&str>) -> io::Result<String> {
+    let mut input = String::new();
+    match file {
+        Some(path) => try!(try!(File::open(path)).read_to_string(&mut input)),
+        None => try!(io::stdin().read_to_string(&mut input)),
+    };
+    Ok(input)
+}
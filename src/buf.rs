@@ -0,0 +1,80 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading from and writing to `bytes::Bytes` (requires the `bytes` feature)
+
+use bytes::Bytes;
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+
+use de::from_slice;
+use error::Result;
+use ser::to_vec;
+
+/// Deserialize an instance of type `T` from a `Bytes` buffer of TJSON text.
+///
+/// The buffer is parsed in place without copying it. Types which borrow from
+/// their input, such as `&str` and `&[u8]` fields, point directly into the
+/// buffer's storage wherever the text needs no unescaping, and so must not
+/// outlive the `Bytes` handle they were parsed from.
+///
+/// Owned values, including `tjson::Value`, are always copied out of the
+/// buffer. In particular `Value::Data` never shares storage with the input,
+/// since no data tag holds its bytes as they are: they are decoded into
+/// storage of their own, or with `Deserializer::lazy_data` their text is
+/// copied. A `Data` made from a `Bytes` buffer does share its storage.
+///
+/// ```rust
+/// extern crate bytes;
+/// extern crate tjson;
+///
+/// # fn main() {
+/// let buf = bytes::Bytes::from(r#"["hello", "world"]"#);
+/// let words: Vec<&str> = tjson::from_bytes(&buf).unwrap();
+///
+/// assert_eq!(words, ["hello", "world"]);
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This conversion can fail for the same reasons as `tjson::from_slice`.
+pub fn from_bytes<'a, T>(buf: &'a Bytes) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_slice(buf)
+}
+
+/// Serialize the given data structure as TJSON into a `Bytes` buffer.
+///
+/// The buffer takes over the allocation the output was written into, so no
+/// copy is made after serialization.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let buf = tjson::to_bytes(&tjson!({ "a": [1, 2, 3] })).unwrap();
+///
+/// assert_eq!(&buf[..], br#"{"a:A<i>":["1","2","3"]}"#);
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+pub fn to_bytes<T: ?Sized>(value: &T) -> Result<Bytes>
+where
+    T: Serialize,
+{
+    to_vec(value).map(Bytes::from)
+}
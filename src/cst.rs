@@ -0,0 +1,443 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lossless parsing for tools which edit TJSON documents in place
+//!
+//! A `Document` keeps everything needed to write its input back out byte for
+//! byte: the whitespace between tokens, the order of members, and the exact
+//! text of every scalar and member name, including escapes. Nodes found by
+//! pointer can be replaced with any serializable value, which rewrites only
+//! that node's text and leaves the rest of the document untouched.
+//!
+//! ```rust
+//! let input = "{\n  \"name\": \"tjson\",\n  \"version\":   \"0.1\"\n}\n";
+//! let mut document = tjson::cst::parse(input).unwrap();
+//! assert_eq!(document.to_string(), input);
+//!
+//! document.pointer_mut("/version").unwrap().replace(&[0, 2]).unwrap();
+//! assert_eq!(
+//!     document.to_string(),
+//!     "{\n  \"name\": \"tjson\",\n  \"version\":   [0,2]\n}\n"
+//! );
+//! ```
+
+use std::fmt::{self, Display};
+
+use serde::de::IgnoredAny;
+use serde::ser::Serialize;
+
+use de::from_str;
+use error::Result;
+use ser::to_string;
+use value::{Value, parse_index, unescape_pointer_token};
+
+/// Parse a TJSON document, keeping its exact formatting.
+///
+/// # Errors
+///
+/// Fails on exactly the same input, with the same errors, as parsing it with
+/// `tjson::from_str`.
+pub fn parse(input: &str) -> Result<Document> {
+    // The main parser decides what is valid, so the syntax tree can't accept
+    // anything it wouldn't, and then builds the tree knowing the input is
+    // well formed
+    try!(from_str::<IgnoredAny>(input));
+
+    let mut lexer = Lexer {
+        input: input,
+        index: 0,
+    };
+    let leading = lexer.whitespace();
+    let root = try!(lexer.node());
+    let trailing = lexer.whitespace();
+
+    Ok(Document {
+        leading: leading,
+        root: root,
+        trailing: trailing,
+    })
+}
+
+/// A parsed TJSON document which remembers its exact formatting.
+///
+/// Use `to_string` (through `Display`) to write the document back out.
+#[derive(Clone, Debug)]
+pub struct Document {
+    leading: String,
+    root: Node,
+    trailing: String,
+}
+
+impl Document {
+    /// The document's toplevel value.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// The document's toplevel value, for editing.
+    pub fn root_mut(&mut self) -> &mut Node {
+        &mut self.root
+    }
+
+    /// Looks up a node by a JSON Pointer, like `Value::pointer`.
+    ///
+    /// If an object contains the same member name more than once, the last
+    /// one is found, as it would be the one kept when parsing into a `Value`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Node> {
+        self.root.pointer(pointer)
+    }
+
+    /// Looks up a node by a JSON Pointer for editing, like
+    /// `Value::pointer_mut`.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Node> {
+        self.root.pointer_mut(pointer)
+    }
+
+    /// Parses the document into a `Value`.
+    pub fn value(&self) -> Result<Value> {
+        self.root.value()
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(&self.leading));
+        try!(Display::fmt(&self.root, f));
+        f.write_str(&self.trailing)
+    }
+}
+
+/// A value within a `Document`, along with the whitespace inside it.
+///
+/// Whitespace before and after a node belongs to its parent, so replacing a
+/// node keeps the layout around it.
+#[derive(Clone, Debug)]
+pub struct Node {
+    repr: Repr,
+}
+
+#[derive(Clone, Debug)]
+enum Repr {
+    /// The exact text of a string, number, or literal
+    Scalar(String),
+    /// Elements, and the whitespace inside the brackets if there are none
+    Array(Vec<Element>, String),
+    /// Members, and the whitespace inside the braces if there are none
+    Object(Vec<Member>, String),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    before: String,
+    value: Node,
+    after: String,
+}
+
+#[derive(Clone, Debug)]
+struct Member {
+    before: String,
+    /// The member name as written, including quotes and escapes
+    raw_name: String,
+    name: String,
+    before_colon: String,
+    after_colon: String,
+    value: Node,
+    after: String,
+}
+
+impl Node {
+    /// Returns true if the node is an array.
+    pub fn is_array(&self) -> bool {
+        match self.repr {
+            Repr::Array(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the node is an object.
+    pub fn is_object(&self) -> bool {
+        match self.repr {
+            Repr::Object(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Number of elements or members in an array or object. Always zero for
+    /// other values.
+    pub fn len(&self) -> usize {
+        match self.repr {
+            Repr::Scalar(_) => 0,
+            Repr::Array(ref elements, _) => elements.len(),
+            Repr::Object(ref members, _) => members.len(),
+        }
+    }
+
+    /// Returns true if the node is not an array or object with elements or
+    /// members.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Names of an object's members in the order they are written. Empty for
+    /// other values.
+    pub fn member_names(&self) -> Vec<&str> {
+        match self.repr {
+            Repr::Object(ref members, _) => members.iter().map(|m| m.name.as_str()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Looks up a descendant by a JSON Pointer relative to this node.
+    pub fn pointer(&self, pointer: &str) -> Option<&Node> {
+        if pointer == "" {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+
+        let mut target = self;
+        for token in pointer.split('/').skip(1) {
+            let index = unescape_pointer_token(token).and_then(|token| target.child_index(&token));
+            target = match index {
+                Some(index) => target.child(index),
+                None => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Looks up a descendant for editing by a JSON Pointer relative to this
+    /// node.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Node> {
+        if pointer == "" {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+
+        let mut target = self;
+        for token in pointer.split('/').skip(1) {
+            let index = unescape_pointer_token(token).and_then(|token| target.child_index(&token));
+            target = match index {
+                Some(index) => target.child_mut(index),
+                None => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Replaces this node with the serialized form of `value`.
+    ///
+    /// The new text is written by `tjson::to_string`. Whitespace around the
+    /// node, and everything else in the document, is left as it was.
+    pub fn replace<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let text = try!(to_string(value));
+        let document = try!(parse(&text));
+        *self = document.root;
+        Ok(())
+    }
+
+    /// Parses the node into a `Value`.
+    pub fn value(&self) -> Result<Value> {
+        from_str(&self.to_string())
+    }
+
+    fn child_index(&self, token: &str) -> Option<usize> {
+        match self.repr {
+            Repr::Scalar(_) => None,
+            Repr::Array(ref elements, _) => parse_index(token).and_then(
+                |index| if index < elements.len() {
+                    Some(index)
+                } else {
+                    None
+                },
+            ),
+            Repr::Object(ref members, _) => members.iter().rposition(|m| m.name == token),
+        }
+    }
+
+    fn child(&self, index: usize) -> &Node {
+        match self.repr {
+            Repr::Scalar(_) => unreachable!(),
+            Repr::Array(ref elements, _) => &elements[index].value,
+            Repr::Object(ref members, _) => &members[index].value,
+        }
+    }
+
+    fn child_mut(&mut self, index: usize) -> &mut Node {
+        match self.repr {
+            Repr::Scalar(_) => unreachable!(),
+            Repr::Array(ref mut elements, _) => &mut elements[index].value,
+            Repr::Object(ref mut members, _) => &mut members[index].value,
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.repr {
+            Repr::Scalar(ref text) => f.write_str(text),
+            Repr::Array(ref elements, ref inside) => {
+                try!(f.write_str("["));
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(","));
+                    }
+                    try!(f.write_str(&element.before));
+                    try!(Display::fmt(&element.value, f));
+                    try!(f.write_str(&element.after));
+                }
+                try!(f.write_str(inside));
+                f.write_str("]")
+            }
+            Repr::Object(ref members, ref inside) => {
+                try!(f.write_str("{"));
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(","));
+                    }
+                    try!(f.write_str(&member.before));
+                    try!(f.write_str(&member.raw_name));
+                    try!(f.write_str(&member.before_colon));
+                    try!(f.write_str(":"));
+                    try!(f.write_str(&member.after_colon));
+                    try!(Display::fmt(&member.value, f));
+                    try!(f.write_str(&member.after));
+                }
+                try!(f.write_str(inside));
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Splits input already accepted by the main parser into tokens.
+struct Lexer<'a> {
+    input: &'a str,
+    index: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.index).cloned()
+    }
+
+    fn whitespace(&mut self) -> String {
+        let start = self.index;
+        while let Some(b' ') | Some(b'\n') | Some(b'\t') | Some(b'\r') = self.peek() {
+            self.index += 1;
+        }
+        self.input[start..self.index].to_owned()
+    }
+
+    /// Consumes a string, returning its text including the quotes.
+    fn string(&mut self) -> &'a str {
+        let start = self.index;
+        self.index += 1;
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.index += 1;
+                    break;
+                }
+                Some(b'\\') => self.index += 2,
+                Some(_) => self.index += 1,
+                None => break,
+            }
+        }
+        &self.input[start..self.index]
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let repr = match self.peek() {
+            Some(b'[') => {
+                self.index += 1;
+                let mut elements = Vec::new();
+                let mut before = self.whitespace();
+
+                if self.peek() != Some(b']') {
+                    loop {
+                        let value = try!(self.node());
+                        let after = self.whitespace();
+                        elements.push(
+                            Element {
+                                before: before,
+                                value: value,
+                                after: after,
+                            },
+                        );
+
+                        self.index += 1;
+                        if self.input.as_bytes()[self.index - 1] == b']' {
+                            return Ok(Node { repr: Repr::Array(elements, String::new()) });
+                        }
+                        before = self.whitespace();
+                    }
+                }
+
+                self.index += 1;
+                Repr::Array(elements, before)
+            }
+            Some(b'{') => {
+                self.index += 1;
+                let mut members = Vec::new();
+                let mut before = self.whitespace();
+
+                if self.peek() != Some(b'}') {
+                    loop {
+                        let raw_name = self.string();
+                        let name = try!(from_str(raw_name));
+                        let before_colon = self.whitespace();
+                        self.index += 1;
+                        let after_colon = self.whitespace();
+                        let value = try!(self.node());
+                        let after = self.whitespace();
+                        members.push(
+                            Member {
+                                before: before,
+                                raw_name: raw_name.to_owned(),
+                                name: name,
+                                before_colon: before_colon,
+                                after_colon: after_colon,
+                                value: value,
+                                after: after,
+                            },
+                        );
+
+                        self.index += 1;
+                        if self.input.as_bytes()[self.index - 1] == b'}' {
+                            return Ok(Node { repr: Repr::Object(members, String::new()) });
+                        }
+                        before = self.whitespace();
+                    }
+                }
+
+                self.index += 1;
+                Repr::Object(members, before)
+            }
+            Some(b'"') => Repr::Scalar(self.string().to_owned()),
+            _ => {
+                let start = self.index;
+                while let Some(b) = self.peek() {
+                    match b {
+                        b',' | b']' | b'}' | b' ' | b'\n' | b'\t' | b'\r' => break,
+                        _ => self.index += 1,
+                    }
+                }
+                Repr::Scalar(self.input[start..self.index].to_owned())
+            }
+        };
+
+        Ok(Node { repr: repr })
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The bytes of TJSON data
+
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+use serde::ser::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use encoding::{self, DecodeError};
+
+// Not public API. Should be pub(crate).
+/// Name of the newtype struct around the text of data held undecoded, which
+/// tjson's serializers write as it is and others see through, and of the
+/// one member of the map `Deserializer::lazy_data` hands such text to a
+/// `Value` in.
+#[doc(hidden)]
+pub const DATA_TOKEN: &str = "$tjson::private::Data";
+
+/// Represents TJSON binary data: the bytes of a `Value::Data`.
+///
+/// Data is usually held decoded. Data tagged `d` or `d64` which a
+/// `Deserializer` reads with `lazy_data` on is held as its base64url text
+/// instead, which is checked when it is read but only decoded the first
+/// time its bytes are needed: through `Deref`, `Value::as_data` or
+/// `Value::into_data`, or by comparing, ordering or hashing it, so that it
+/// is equal to the same bytes read eagerly. The bytes are kept alongside the
+/// text once decoded, so this happens at most once, and `len` doesn't need
+/// them. Until `to_mut` or `Value::as_data_mut` gives them out for changing,
+/// such data is written back out as the text it was read as, without
+/// encoding it again.
+///
+/// Serializers other than tjson's see data held as text as that string.
+///
+/// With the `bytes` feature, data made from a `bytes::Bytes` buffer shares
+/// its storage rather than copying it, and `into_bytes` gives that buffer
+/// back without a copy, or moves bytes held decoded into one. Data read from
+/// TJSON never shares the input's storage, even with `tjson::from_bytes`:
+/// every data tag is an encoding, so the bytes are decoded into storage of
+/// their own, or with `lazy_data` their text is copied into the `Data`.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use serde::Deserialize;
+/// use tjson::{Deserializer, Value};
+///
+/// let input = r#"{"raw:d":"QklOQVJZ"}"#;
+/// let mut de = Deserializer::from_str(input).lazy_data(true);
+/// let value = Value::deserialize(&mut de).unwrap();
+///
+/// let data = match value["raw:d"] {
+///     Value::Data(ref data) => data,
+///     _ => unreachable!(),
+/// };
+/// assert!(!data.is_decoded());
+/// assert_eq!(data.len(), 6);
+/// assert_eq!(tjson::to_string(&value).unwrap(), input);
+///
+/// assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+/// assert!(data.is_decoded());
+/// assert_eq!(&data[..], b"BINARY");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Data {
+    repr: Repr,
+}
+
+#[derive(Clone)]
+enum Repr {
+    Bytes(Vec<u8>),
+    #[cfg(feature = "bytes")]
+    Shared(Box<Bytes>),
+    Text(Box<Text>),
+}
+
+/// Checked base64url text, and its bytes once something has needed them.
+#[derive(Clone)]
+struct Text {
+    text: String,
+    bytes: OnceLock<Vec<u8>>,
+}
+
+impl Text {
+    fn bytes(&self) -> &[u8] {
+        self.bytes.get_or_init(|| decode(&self.text))
+    }
+}
+
+fn decode(text: &str) -> Vec<u8> {
+    encoding::b64url_decode(text).expect("data was checked when it was read")
+}
+
+impl Data {
+    /// Empty data.
+    pub fn new() -> Self {
+        Data { repr: Repr::Bytes(Vec::new()) }
+    }
+
+    // Not public API. Should be pub(crate).
+    /// Data held as base64url text, which is checked here and decoded when
+    /// its bytes are first needed.
+    #[doc(hidden)]
+    pub fn from_b64url(text: String) -> Result<Self, DecodeError> {
+        try!(encoding::b64url_check(text.as_bytes()));
+        let text = Text {
+            text: text,
+            bytes: OnceLock::new(),
+        };
+        Ok(Data { repr: Repr::Text(Box::new(text)) })
+    }
+
+    // Not public API. Should be pub(crate).
+    /// The base64url text the data was read as, if it is held as text.
+    #[doc(hidden)]
+    pub fn b64url_text(&self) -> Option<&str> {
+        match self.repr {
+            Repr::Text(ref text) => Some(&text.text),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes, decoding them if this is the first time they are
+    /// needed.
+    pub fn as_slice(&self) -> &[u8] {
+        match self.repr {
+            Repr::Bytes(ref bytes) => &bytes[..],
+            #[cfg(feature = "bytes")]
+            Repr::Shared(ref bytes) => &bytes[..],
+            Repr::Text(ref text) => text.bytes(),
+        }
+    }
+
+    /// Returns the bytes for changing them, decoding them if need be. Data
+    /// held as text is held decoded from then on, and is encoded again when
+    /// it is written. Data sharing a `Bytes` buffer gets its own copy.
+    pub fn to_mut(&mut self) -> &mut Vec<u8> {
+        let held = match self.repr {
+            Repr::Bytes(_) => true,
+            _ => false,
+        };
+        if !held {
+            let bytes = mem::replace(self, Data::new()).into_vec();
+            self.repr = Repr::Bytes(bytes);
+        }
+        match self.repr {
+            Repr::Bytes(ref mut bytes) => bytes,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the bytes, decoding them if need be.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.repr {
+            Repr::Bytes(bytes) => bytes,
+            #[cfg(feature = "bytes")]
+            Repr::Shared(bytes) => Vec::from(*bytes),
+            Repr::Text(text) => {
+                let Text { text, bytes } = *text;
+                bytes.into_inner().unwrap_or_else(|| decode(&text))
+            }
+        }
+    }
+
+    /// Returns the bytes as a `Bytes` buffer (requires the `bytes` feature):
+    /// the one the data was made from if there is one, which is not copied,
+    /// or otherwise the decoded bytes, which are moved into it rather than
+    /// copied.
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> Bytes {
+        match self.repr {
+            Repr::Shared(bytes) => *bytes,
+            repr => Bytes::from(Data { repr: repr }.into_vec()),
+        }
+    }
+
+    /// Returns false for data held as text which nothing has needed the
+    /// bytes of yet, and true otherwise.
+    pub fn is_decoded(&self) -> bool {
+        match self.repr {
+            Repr::Text(ref text) => text.bytes.get().is_some(),
+            _ => true,
+        }
+    }
+
+    /// The number of bytes, which data held as text gives without decoding
+    /// them.
+    pub fn len(&self) -> usize {
+        match self.repr {
+            Repr::Bytes(ref bytes) => bytes.len(),
+            #[cfg(feature = "bytes")]
+            Repr::Shared(ref bytes) => bytes.len(),
+            // Each character is six bits, and only whole bytes are encoded
+            Repr::Text(ref text) => text.text.len() * 3 / 4,
+        }
+    }
+
+    /// Returns true if there are no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Data::new()
+    }
+}
+
+impl Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsRef<[u8]> for Data {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl Debug for Data {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.as_slice(), formatter)
+    }
+}
+
+impl PartialEq for Data {
+    fn eq(&self, other: &Data) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Data {}
+
+impl PartialEq<[u8]> for Data {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<'a> PartialEq<&'a [u8]> for Data {
+    fn eq(&self, other: &&'a [u8]) -> bool {
+        self.as_slice() == *other
+    }
+}
+
+impl PartialEq<Vec<u8>> for Data {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == &other[..]
+    }
+}
+
+impl PartialOrd for Data {
+    fn partial_cmp(&self, other: &Data) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Data {
+    fn cmp(&self, other: &Data) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl Hash for Data {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
+
+impl From<Vec<u8>> for Data {
+    fn from(bytes: Vec<u8>) -> Self {
+        Data { repr: Repr::Bytes(bytes) }
+    }
+}
+
+impl<'a> From<&'a [u8]> for Data {
+    fn from(bytes: &'a [u8]) -> Self {
+        Data::from(bytes.to_vec())
+    }
+}
+
+/// Shares the buffer's storage rather than copying it (requires the `bytes`
+/// feature).
+#[cfg(feature = "bytes")]
+impl From<Bytes> for Data {
+    fn from(bytes: Bytes) -> Self {
+        Data { repr: Repr::Shared(Box::new(bytes)) }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<Data> for Bytes {
+    fn from(data: Data) -> Self {
+        data.into_bytes()
+    }
+}
+
+impl From<Data> for Vec<u8> {
+    fn from(data: Data) -> Self {
+        data.into_vec()
+    }
+}
+
+impl FromIterator<u8> for Data {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        Data::from(iter.into_iter().collect::<Vec<u8>>())
+    }
+}
+
+impl Serialize for Data {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.b64url_text() {
+            Some(text) => serializer.serialize_newtype_struct(DATA_TOKEN, text),
+            None => serializer.serialize_bytes(self),
+        }
+    }
+}
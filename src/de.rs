@@ -13,25 +13,836 @@
 
 //! Deserialize JSON data to a Rust data structure.
 
-use std::{i32, u64};
+use std::{cmp, i32, i64, u64};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::io;
+use std::iter;
 use std::marker::PhantomData;
+use std::mem;
+use std::result;
+use std::str;
+use std::sync::Arc;
 
-use serde::de::{self, Unexpected};
+use serde::de::{self, IgnoredAny, IntoDeserializer, Unexpected};
+use serde::de::value::{BorrowedStrDeserializer, MapDeserializer, SeqDeserializer};
 
-use super::error::{Error, ErrorCode, Result};
+use num_traits::{Bounded, ToPrimitive};
 
-use read::{self, Reference};
+use super::error::{Code, Construct, Encoding, Error, Result, Unterminated};
+use data::DATA_TOKEN;
+use encoding::{self, Decoder};
+use event::Event;
+use map::{KeyPolicy, Map};
+use set::{SET_TOKEN, Set};
+use tag::{self, Tag, TagMismatch};
+use transform::{self, Matcher, Transforms};
+use number::Number;
+use value::{Metric, NormalizedValue, StatViolation, Value, push_pointer_token};
+
+use read::{self, Mark, Reference};
+use timestamp;
+
+use itoa;
 
 pub use read::{Read, IoRead, SliceRead, StrRead};
 
 //////////////////////////////////////////////////////////////////////////////
 
+/// Arrays and objects may be nested at most this deep.
+const MAX_DEPTH: usize = 127;
+
 /// A structure that deserializes JSON into Rust values.
 pub struct Deserializer<R> {
     read: R,
     str_buf: Vec<u8>,
-    remaining_depth: u8,
+    open: Vec<Open>,
+    /// Member name buffers of closed objects, reused by the next ones opened
+    /// so that tracking member names doesn't allocate for every object.
+    spare_members: Vec<String>,
+    /// Field matchers of structs which have been read, reused by the next
+    /// ones in the same way.
+    spare_matchers: Vec<FieldMatcher>,
+    member_names: MemberNames,
+    options: Options,
+    /// The JSON pointer and description of each change made to the input by
+    /// a lenient option, if they are being kept for `repair::rewrite`.
+    repairs: Option<Vec<(String, String)>>,
+    /// Whether the number being parsed is going into an integer, with
+    /// `integral_float_coercion` on. Cleared on finding that the number
+    /// can't be one.
+    integer_target: bool,
+    /// The integer type the value being parsed is going into, so that a
+    /// quoted integer which doesn't fit it is out of range rather than
+    /// refused by the visitor.
+    integer_type: Option<IntegerType>,
+    counts: ParseReport,
+    warnings: LimitWarnings,
+}
+
+/// Measurements of the input taken while parsing it, for logging how close
+/// documents come to the limits an application sets.
+///
+/// The counts cover everything parsed so far, including values which failed
+/// to deserialize or were skipped.
+///
+/// ```rust
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let (value, report) = tjson::from_str_with_report::<tjson::Value>(
+///     r#"{"names:A<s>": ["ann", "bob\u00e9"]}"#,
+/// ).unwrap();
+///
+/// assert_eq!(value["names:A<s>"][1], "bob\u{e9}");
+/// assert_eq!(report.max_depth, 2);
+/// assert_eq!((report.members, report.elements), (1, 2));
+/// assert_eq!(report.escapes, 1);
+/// assert_eq!(report.longest_string, 5);
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    /// Deepest nesting of arrays and objects reached, or 0 if there were
+    /// none.
+    pub max_depth: usize,
+
+    /// Object members parsed, at every level of nesting.
+    pub members: usize,
+
+    /// Array elements parsed, at every level of nesting.
+    pub elements: usize,
+
+    /// Bytes of input consumed, including whitespace.
+    pub bytes: usize,
+
+    /// Backslash escape sequences in strings and member names. A surrogate
+    /// pair written as two `\u` escapes counts as one.
+    pub escapes: usize,
+
+    /// Length in bytes of the longest string value, once unescaped. Member
+    /// names don't count.
+    pub longest_string: usize,
+}
+
+/// The soft limits a document went over, from the `warn_` options of a
+/// `Deserializer`.
+///
+/// Only the first few are kept, as many as `Deserializer::max_warnings`
+/// allows, and the rest are counted. Displaying the warnings gives one per
+/// line, followed by how many more there were.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use serde::Deserialize;
+/// use tjson::{Deserializer, Value};
+/// use tjson::value::Metric;
+///
+/// let mut de = Deserializer::from_str(r#"{"a": {"b": [1], "c": 2}, "d": []}"#)
+///     .warn_member_count(1)
+///     .warn_depth(2);
+/// Value::deserialize(&mut de).unwrap();
+///
+/// let warnings = de.warnings();
+/// assert_eq!(warnings.as_slice()[0].metric(), Metric::Depth);
+/// assert_eq!(warnings.as_slice()[0].path(), Some("/a/b"));
+/// assert_eq!(
+///     warnings.to_string(),
+///     "depth 3 exceeds the limit of 2 at /a/b\n\
+///      container length 2 exceeds the limit of 1 at /a\n\
+///      container length 2 exceeds the limit of 1 at the root",
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitWarnings {
+    warnings: Vec<StatViolation>,
+    omitted: usize,
+}
+
+impl LimitWarnings {
+    fn new() -> Self {
+        LimitWarnings {
+            warnings: Vec::new(),
+            omitted: 0,
+        }
+    }
+
+    /// Keeps the warning if fewer than `cap` have been kept so far.
+    fn push(&mut self, warning: StatViolation, cap: usize) {
+        if self.warnings.len() < cap {
+            self.warnings.push(warning);
+        } else {
+            self.omitted += 1;
+        }
+    }
+
+    /// The warnings which were kept, in the order they were found. A limit
+    /// on the members of an object is checked once the object is closed, so
+    /// its warning comes after those from inside it.
+    pub fn as_slice(&self) -> &[StatViolation] {
+        &self.warnings
+    }
+
+    /// How many more warnings there were than could be kept.
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+
+    /// Returns true if no soft limit was gone over.
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty() && self.omitted == 0
+    }
+}
+
+impl fmt::Display for LimitWarnings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, warning) in self.warnings.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str("\n"));
+            }
+            try!(fmt::Display::fmt(warning, f));
+        }
+        if self.omitted > 0 {
+            if !self.warnings.is_empty() {
+                try!(f.write_str("\n"));
+            }
+            try!(write!(f, "{} more", self.omitted));
+        }
+        Ok(())
+    }
+}
+
+/// What to do with an object which has more than one member of the same
+/// name. TJSON forbids them, so by default they are an error; the other
+/// policies are for ingesting documents from producers which can't be fixed.
+///
+/// Names are compared without their tags, so `"id:s"` and `"id:u"` are the
+/// same member. Every policy applies at every level of nesting, both when
+/// deserializing into `Value` and into derived structs.
+///
+/// ```rust
+/// # extern crate serde;
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use serde::Deserialize;
+/// use tjson::Deserializer;
+/// use tjson::de::DuplicateKeys;
+///
+/// let input = r#"{"tag:s": "a", "tag:s": "b"}"#;
+///
+/// let mut de = Deserializer::from_str(input).duplicate_keys(DuplicateKeys::Collect);
+/// let value = tjson::Value::deserialize(&mut de).unwrap();
+/// assert_eq!(value, tjson!({ "tag:s": ["a", "b"] }));
+///
+/// let err = tjson::from_str::<tjson::Value>(input).unwrap_err();
+/// assert_eq!(err.to_string(), "duplicate member \"tag:s\" at line 1 column 22");
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with an error naming the repeated member (the default).
+    Error,
+
+    /// Keep the first occurrence and skip the rest.
+    FirstWins,
+
+    /// Keep the last occurrence, in the place of the first.
+    LastWins,
+
+    /// Replace the member with an array of every occurrence's value, in
+    /// order. Fails if the occurrences' tags differ, and when deserializing a
+    /// struct, since its fields can't change type.
+    Collect,
+}
+
+/// What to do with a set which has more than one equal element.
+///
+/// The same policy applies wherever a set is built: when deserializing a
+/// `Set<Value>` from text with `Deserializer::duplicate_elements` or from a
+/// `Value` with `tjson::value::from_value_with`, and when a value which
+/// serializes as a set is written with `SerializeConfig::duplicate_elements`
+/// or converted with `tjson::value::to_value_with`. Arrays under a member
+/// tagged `S<..>` are held to it too when read from text, whatever they are
+/// deserialized into. Either way a rejected set reports the first element
+/// equal to an earlier one and its index.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use serde::Deserialize;
+/// use tjson::{Deserializer, Set};
+/// use tjson::de::DuplicateElements;
+///
+/// let input = r#"["a", "b", "a"]"#;
+///
+/// let err = tjson::from_str::<Set<tjson::Value>>(input).unwrap_err();
+/// assert_eq!(err.to_string(), "duplicate set element \"a\" at index 2 at line 1 column 14");
+///
+/// let err = tjson::from_str::<tjson::Value>(r#"{"ids:S<u>": ["1", "2", "1"]}"#).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "duplicate set element 1 at index 2 of /ids:S<u> at line 1 column 27"
+/// );
+///
+/// let mut de = Deserializer::from_str(input).duplicate_elements(DuplicateElements::Merge);
+/// let set = Set::deserialize(&mut de).unwrap();
+/// assert_eq!(set.len(), 2);
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateElements {
+    /// Fail with an error giving the index of the repeated element (the
+    /// default).
+    Error,
+
+    /// Keep the first occurrence and drop the rest.
+    Merge,
+}
+
+/// How the elements of a set are remembered to find duplicates among them,
+/// for `Deserializer::set_uniqueness`.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use serde::Deserialize;
+/// use serde::de::IgnoredAny;
+/// use tjson::Deserializer;
+/// use tjson::de::SetUniqueness;
+///
+/// let input = r#"{"ids:S<s>": ["a", "b", "a"]}"#;
+/// let mut de = Deserializer::from_str(input).set_uniqueness(SetUniqueness::Hashed);
+/// let err = IgnoredAny::deserialize(&mut de).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "duplicate set element \"a\" at index 2 of /ids:S<s> at line 1 column 27"
+/// );
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SetUniqueness {
+    /// Keep a copy of every element (the default), which takes as much
+    /// memory again as the set.
+    Exact,
+
+    /// Keep a 128-bit hash of each element, keyed at random so that input
+    /// can't be made to collide, and its offset into the input. Memory per
+    /// element stays the same however large the elements are, so this suits
+    /// huge sets read into something which doesn't keep them, such as
+    /// `IgnoredAny` when only checking a document.
+    ///
+    /// An element whose hash is that of an earlier one is read again from
+    /// the input to compare the two when the input is a slice or string.
+    /// Readers can't go back, so from a reader such an element fails with
+    /// `ErrorCode::UnresolvedHashCollision` rather than being taken as a
+    /// duplicate or as distinct. Different elements collide with a chance of
+    /// about one in 2^64 for a set of four billion elements.
+    ///
+    /// Hooks added with `transform_data` or `transform_string` aren't run
+    /// again on an element read again. With any added, the two elements are
+    /// both read again untransformed, and fail the same way unless they
+    /// are equal as written.
+    Hashed,
+}
+
+/// Whether integers tagged `u` may be read into signed types and those
+/// tagged `i` into unsigned ones, for `Deserializer::integer_signedness`.
+///
+/// Either way an integer which doesn't fit the type it is read into fails
+/// with `ErrorCode::NumberOutOfRange`, as a negative `i` does in a `u32`.
+/// `tjson::from_value` always reads integers as `Coerce` does.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use std::collections::BTreeMap;
+///
+/// use serde::Deserialize;
+/// use tjson::Deserializer;
+/// use tjson::de::IntegerSignedness;
+/// use tjson::error::ErrorCode;
+///
+/// let input = r#"{"count:u": "7"}"#;
+///
+/// let mut de = Deserializer::from_str(input);
+/// let counts = BTreeMap::<String, i64>::deserialize(&mut de).unwrap();
+/// assert_eq!(counts["count:u"], 7);
+///
+/// let mut de = Deserializer::from_str(input).integer_signedness(IntegerSignedness::Strict);
+/// let err = BTreeMap::<String, i64>::deserialize(&mut de).unwrap_err();
+/// assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+/// assert_eq!(
+///     err.to_string(),
+///     "integer tagged u can't be read into i64 with IntegerSignedness::Strict at /count:u \
+///      at line 1 column 13"
+/// );
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegerSignedness {
+    /// Fail with `ErrorCode::SignednessMismatch`, naming the member, its tag
+    /// and the type.
+    Strict,
+
+    /// Read them into either kind of type when they fit (the default), since
+    /// producers differ over tagging integers which aren't negative `i` or
+    /// `u`.
+    Coerce,
+}
+
+/// An integer type a value is being deserialized into.
+#[derive(Copy, Clone)]
+struct IntegerType {
+    name: &'static str,
+    min: i64,
+    max: u64,
+}
+
+impl IntegerType {
+    fn of<T: Bounded + ToPrimitive>(name: &'static str) -> Self {
+        IntegerType {
+            name: name,
+            min: T::min_value().to_i64().unwrap_or(0),
+            max: T::max_value().to_u64().unwrap_or(0),
+        }
+    }
+
+    fn is_signed(self) -> bool {
+        self.min < 0
+    }
+
+    fn fits(self, n: &Number) -> bool {
+        match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u <= self.max,
+            (None, Some(i)) => i >= self.min,
+            (None, None) => false,
+        }
+    }
+}
+
+/// What to do with timestamps whose offset isn't `Z`, for
+/// `Deserializer::timestamp_offsets`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OffsetPolicy {
+    /// Reject them, and a lowercase `z`, since TJSON timestamps must be in UTC
+    /// (the default).
+    RejectNonUtc,
+
+    /// Accept any RFC 3339 offset, converting the timestamp to UTC.
+    NormalizeToUtc,
+}
+
+/// How member names are compared with the names of struct fields, for
+/// `Deserializer::member_name_normalization`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Normalization {
+    /// Names must match exactly.
+    Exact,
+
+    /// Names match if they only differ in case, but their tags must still
+    /// match exactly.
+    CaseInsensitive,
+
+    /// As `CaseInsensitive`, also ignoring whitespace around the name and
+    /// its tag, as in `" userId :s"`.
+    TrimAndCaseInsensitive,
+
+    /// Names must match exactly, but tags only need to be of the same kind:
+    /// `d`, `d16`, `d32` and `d64` all match each other, as do `i` and `u`,
+    /// so a field renamed to `"ids:A<u>"` matches a member `"ids:A<i>"`. This
+    /// reads documents written with `SerializeConfig::default_data_encoding`
+    /// or `IntegerTag::AlwaysSigned` into the types they were written from.
+    TagKinds,
+
+    /// Names must match exactly, and fields without a tag match members
+    /// with any tag, so a field `name` matches a member `"name:s"` (the
+    /// default). The member's tag still says how its value is read, as a
+    /// timestamp, data and so on. Fields with a tag must match it exactly,
+    /// as with `Exact`.
+    Untagged,
+}
+
+impl Normalization {
+    /// Whether a member name matches the name of a field. Compares the two
+    /// in place, without making a normalized copy of either, so that
+    /// matching members to fields doesn't allocate.
+    fn matches(&self, field: &str, name: &str) -> bool {
+        if field == name {
+            return true;
+        }
+        let trim = *self == Normalization::TrimAndCaseInsensitive;
+        let (field, field_tag) = split_tag(field, trim);
+        let (name, name_tag) = split_tag(name, trim);
+        let (field, name) = if trim { (field.trim(), name.trim()) } else { (field, name) };
+
+        let same_name = match *self {
+            Normalization::Exact | Normalization::TagKinds | Normalization::Untagged => {
+                field == name
+            }
+            Normalization::CaseInsensitive | Normalization::TrimAndCaseInsensitive => {
+                if field.is_ascii() && name.is_ascii() {
+                    field.eq_ignore_ascii_case(name)
+                } else {
+                    field.to_lowercase() == name.to_lowercase()
+                }
+            }
+        };
+        same_name &&
+            match (field_tag, name_tag) {
+                (Some(field_tag), Some(name_tag)) => {
+                    match *self {
+                        Normalization::TagKinds => same_tag_kind(field_tag, name_tag),
+                        _ => field_tag == name_tag,
+                    }
+                }
+                (None, None) => true,
+                (None, Some(_)) => *self == Normalization::Untagged,
+                _ => false,
+            }
+    }
+}
+
+/// Splits a member name into its name and tag at the last `:`, if what
+/// follows it is a tag, so that a field named `host:port` keeps its name.
+/// With `trim`, the tag may have whitespace around it.
+fn split_tag(name: &str, trim: bool) -> (&str, Option<&str>) {
+    if let Some(colon) = name.rfind(':') {
+        let tag = &name[colon + 1..];
+        let tag = if trim { tag.trim() } else { tag };
+        if tag::is_tag(tag) {
+            return (&name[..colon], Some(tag));
+        }
+    }
+    (name, None)
+}
+
+/// Whether two tags are of the same kind: equal once `d16`, `d32` and `d64`
+/// are taken as `d` and `u` as `i`, at any depth inside `A<..>` and `S<..>`.
+fn same_tag_kind(a: &str, b: &str) -> bool {
+    match (container_tag(a), container_tag(b)) {
+        (Some((a_kind, a_inner)), Some((b_kind, b_inner))) => {
+            a_kind == b_kind && same_tag_kind(a_inner, b_inner)
+        }
+        (None, None) => scalar_tag_kind(a) == scalar_tag_kind(b),
+        _ => false,
+    }
+}
+
+/// Splits `A<..>` or `S<..>` into its `A<` or `S<` and the inner tag.
+fn container_tag(tag: &str) -> Option<(&str, &str)> {
+    if (tag.starts_with("A<") || tag.starts_with("S<")) && tag.ends_with('>') {
+        Some((&tag[..2], &tag[2..tag.len() - 1]))
+    } else {
+        None
+    }
+}
+
+fn scalar_tag_kind(tag: &str) -> &str {
+    match tag {
+        "d16" | "d32" | "d64" => "d",
+        "u" => "i",
+        _ => tag,
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Matches the member names of one object to the fields of a struct by a
+/// `Normalization`, remembering which name matched each field.
+#[doc(hidden)]
+pub struct FieldMatcher {
+    fields: &'static [&'static str],
+    normalization: Normalization,
+    /// Whether each field has been matched, and the name which matched it
+    /// unless that was the field's own, in a buffer which is kept when the
+    /// matcher is reused.
+    matched: Vec<(bool, String)>,
+}
+
+impl FieldMatcher {
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn new(fields: &'static [&'static str], normalization: Normalization) -> Self {
+        let matcher = FieldMatcher {
+            fields: fields,
+            normalization: normalization,
+            matched: Vec::new(),
+        };
+        matcher.reuse(fields, normalization)
+    }
+
+    /// Takes the matcher for the fields of another struct, keeping its
+    /// buffers, so that a `Parser` matches names without allocating.
+    fn reuse(mut self, fields: &'static [&'static str], normalization: Normalization) -> Self {
+        for matched in &mut self.matched {
+            matched.0 = false;
+        }
+        if self.matched.len() < fields.len() {
+            self.matched.resize(fields.len(), (false, String::new()));
+        }
+        self.fields = fields;
+        self.normalization = normalization;
+        self
+    }
+
+    // Not public API. Should be pub(crate).
+    /// The field a member name matches, or `None` if it matches no field and
+    /// should be handed on unchanged.
+    #[doc(hidden)]
+    pub fn resolve(&mut self, name: &str) -> result::Result<Option<&'static str>, Code> {
+        let normalization = self.normalization;
+        let index = match self.fields.iter().position(|field| *field == name) {
+            Some(index) => index,
+            None => {
+                match self.fields.iter().position(|field| normalization.matches(field, name)) {
+                    Some(index) => index,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let field = self.fields[index];
+        let (ref mut seen, ref mut first) = self.matched[index];
+        if !*seen {
+            *seen = true;
+            first.clear();
+            if name != field {
+                first.push_str(name);
+            }
+        } else {
+            let first = if first.is_empty() { field } else { first.as_str() };
+            if first != name {
+                return Err(Code::AmbiguousMember(first.to_owned(), name.to_owned(), field));
+            }
+        }
+        Ok(Some(field))
+    }
+}
+
+/// The settings of a `Deserializer`, which a `Parser` hands to each one it
+/// creates.
+#[derive(Clone)]
+struct Options {
+    duplicate_keys: DuplicateKeys,
+    duplicate_elements: DuplicateElements,
+    set_uniqueness: SetUniqueness,
+    /// How many low bits of the hashes of `SetUniqueness::Hashed` are kept,
+    /// which is fewer only in tests.
+    set_hash_bits: u32,
+    field_order: Option<Arc<[String]>>,
+    map_as_pairs: bool,
+    integral_float_coercion: bool,
+    quoted_integers: bool,
+    integer_signedness: IntegerSignedness,
+    member_name_normalization: Normalization,
+    key_policy: Option<KeyPolicy>,
+    strict_tags: bool,
+    decode_data: bool,
+    lazy_data: bool,
+    timestamp_offsets: OffsetPolicy,
+    transforms: Arc<Transforms>,
+    /// The tag the toplevel value is read as, set by `root_tag`.
+    root_tag: Option<String>,
+    /// Soft limits set by the `warn_` options, which add to `warnings`
+    /// rather than failing.
+    warn_member_count: Option<usize>,
+    warn_key_length: Option<usize>,
+    warn_depth: Option<usize>,
+    max_warnings: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            duplicate_keys: DuplicateKeys::Error,
+            duplicate_elements: DuplicateElements::Error,
+            set_uniqueness: SetUniqueness::Exact,
+            set_hash_bits: 128,
+            field_order: None,
+            map_as_pairs: false,
+            integral_float_coercion: false,
+            quoted_integers: true,
+            integer_signedness: IntegerSignedness::Coerce,
+            member_name_normalization: Normalization::Untagged,
+            key_policy: None,
+            strict_tags: false,
+            decode_data: true,
+            lazy_data: false,
+            timestamp_offsets: OffsetPolicy::RejectNonUtc,
+            transforms: Arc::new(Transforms::default()),
+            root_tag: None,
+            warn_member_count: None,
+            warn_key_length: None,
+            warn_depth: None,
+            max_warnings: 100,
+        }
+    }
+}
+
+impl Options {
+    fn field_order(&mut self, names: &[&str]) {
+        let names: Vec<String> = names.iter().map(|&name| name.to_owned()).collect();
+        self.field_order = Some(names.into());
+    }
+}
+
+/// The buffers a `Deserializer` fills as it goes, which a `Parser` keeps
+/// between parses.
+struct Scratch {
+    str_buf: Vec<u8>,
+    open: Vec<Open>,
+    spare_members: Vec<String>,
+    spare_matchers: Vec<FieldMatcher>,
+    member_names: MemberNames,
+}
+
+impl Scratch {
+    fn new() -> Self {
+        Scratch {
+            str_buf: Vec::with_capacity(128),
+            open: Vec::new(),
+            spare_members: Vec::new(),
+            spare_matchers: Vec::new(),
+            member_names: MemberNames::new(),
+        }
+    }
+}
+
+/// An array or object which has been opened but not yet closed. Kept so that
+/// running out of input can be reported in terms of what was left open.
+struct Open {
+    object: bool,
+    start: Mark,
+    /// Name of the member whose value is being parsed, if `has_member`. The
+    /// buffer is reused for each member of the object.
+    member: String,
+    has_member: bool,
+    /// Index of the element being parsed, if an array.
+    index: usize,
+    /// Number of members parsed so far, if an object.
+    members: usize,
+    /// Index of the object's first name in `MemberNames`, and once it has
+    /// enough names to be worth one, the table of them by hash.
+    names: usize,
+    table: Option<HashMap<u64, usize>>,
+}
+
+impl Open {
+    fn new(object: bool, start: Mark, member: String, names: usize) -> Self {
+        Open {
+            object: object,
+            start: start,
+            member: member,
+            has_member: false,
+            index: 0,
+            members: 0,
+            names: names,
+            table: None,
+        }
+    }
+
+    fn set_member(&mut self, name: &str) {
+        self.member.clear();
+        self.member.push_str(name);
+        self.has_member = true;
+    }
+}
+
+/// Objects with this many members get a table of their names by hash, and
+/// smaller ones are searched from start to end.
+const NAME_TABLE_LEN: usize = 16;
+
+/// The untagged member names of every open object, when checking for
+/// duplicates. The names are kept end to end in one buffer, each object's
+/// after those of the objects it is inside, so that checking doesn't allocate
+/// for every object.
+struct MemberNames {
+    hasher: RandomState,
+    text: String,
+    /// The hash of each name, where it is in `text`, and the index of the
+    /// previous name of the same object with the same hash if the object has
+    /// a table.
+    names: Vec<(u64, usize, usize, Option<usize>)>,
+}
+
+impl MemberNames {
+    fn new() -> Self {
+        MemberNames {
+            hasher: RandomState::new(),
+            text: String::with_capacity(128),
+            names: Vec::with_capacity(16),
+        }
+    }
+
+    /// Adds a name to the object whose names start at `first`, returning
+    /// false if the object already has it.
+    fn insert(
+        &mut self,
+        first: usize,
+        table: &mut Option<HashMap<u64, usize>>,
+        name: &str,
+    ) -> bool {
+        let hash = self.hasher.hash_one(name);
+
+        let found = match *table {
+            Some(ref table) => {
+                let mut next = table.get(&hash).cloned();
+                let mut found = false;
+                while let Some(index) = next {
+                    let (_, start, end, previous) = self.names[index];
+                    found = &self.text[start..end] == name;
+                    next = if found { None } else { previous };
+                }
+                found
+            }
+            None => {
+                let text = &self.text;
+                self.names[first..]
+                    .iter()
+                    .any(|&(other, start, end, _)| other == hash && &text[start..end] == name)
+            }
+        };
+        if found {
+            return false;
+        }
+
+        let start = self.text.len();
+        self.text.push_str(name);
+        let index = self.names.len();
+        let previous = table.as_mut().and_then(|table| table.insert(hash, index));
+        self.names.push((hash, start, self.text.len(), previous));
+
+        if table.is_none() && self.names.len() - first == NAME_TABLE_LEN {
+            let mut by_hash = HashMap::new();
+            for (index, name) in self.names[first..].iter_mut().enumerate() {
+                name.3 = by_hash.insert(name.0, first + index);
+            }
+            *table = Some(by_hash);
+        }
+        true
+    }
+
+    /// Forgets the names from `first` on, those of an object being closed.
+    fn truncate(&mut self, first: usize) {
+        if let Some(&(_, start, _, _)) = self.names.get(first) {
+            self.text.truncate(start);
+            self.names.truncate(first);
+        }
+    }
 }
 
 impl<'de, R> Deserializer<R>
@@ -47,210 +858,1640 @@ where
     ///   - Deserializer::from_bytes
     ///   - Deserializer::from_reader
     pub fn new(read: R) -> Self {
+        Deserializer::with_scratch(read, Scratch::new(), Options::default())
+    }
+
+    fn with_scratch(read: R, scratch: Scratch, options: Options) -> Self {
         Deserializer {
             read: read,
-            str_buf: Vec::with_capacity(128),
-            remaining_depth: 128,
+            str_buf: scratch.str_buf,
+            open: scratch.open,
+            spare_members: scratch.spare_members,
+            spare_matchers: scratch.spare_matchers,
+            member_names: scratch.member_names,
+            options: options,
+            repairs: None,
+            integer_target: false,
+            integer_type: None,
+            counts: ParseReport::default(),
+            warnings: LimitWarnings::new(),
         }
     }
-}
 
-impl<R> Deserializer<read::IoRead<R>>
-where
-    R: io::Read,
-{
-    /// Creates a JSON deserializer from an `io::Read`.
-    pub fn from_reader(reader: R) -> Self {
-        Deserializer::new(read::IoRead::new(reader))
+    /// Handle objects with more than one member of the same name according
+    /// to the given policy.
+    ///
+    /// By default a duplicate is an error, as TJSON requires, reported where
+    /// the repeated name ends. `FirstWins`, `LastWins` and `Collect` read
+    /// each object in full before handing it on, so nothing inside an object
+    /// can be borrowed from the input.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.options.duplicate_keys = policy;
+        self
+    }
+
+    /// Handle sets with more than one equal element according to the given
+    /// policy, when deserializing a `Set<Value>` or an array under a member
+    /// tagged `S<..>`.
+    ///
+    /// By default a duplicate is an error, reported where the repeated
+    /// element ends. Merging drops it, so a `Value`, which keeps the array
+    /// under a tagged member as an array, has only the distinct elements.
+    pub fn duplicate_elements(mut self, policy: DuplicateElements) -> Self {
+        self.options.duplicate_elements = policy;
+        self
+    }
+
+    /// Set how the elements of sets are remembered to find duplicates among
+    /// them. Defaults to `SetUniqueness::Exact`; `SetUniqueness::Hashed`
+    /// keeps memory down for huge sets.
+    pub fn set_uniqueness(mut self, uniqueness: SetUniqueness) -> Self {
+        self.options.set_uniqueness = uniqueness;
+        self
+    }
+
+    // Not public API. Should be pub(crate).
+    //
+    // Keeps only the low `bits` of the hashes of `SetUniqueness::Hashed`, so
+    // that tests can make different elements collide.
+    #[doc(hidden)]
+    pub fn set_hash_bits(mut self, bits: u32) -> Self {
+        self.options.set_hash_bits = bits;
+        self
+    }
+
+    /// Deserialize tuples from objects by taking the members with these
+    /// names, in this order, as the tuple's elements.
+    ///
+    /// Names are compared without their tags, and members not named are
+    /// ignored. Without a field order, deserializing a tuple from an object
+    /// is an error. Tuples are always deserialized from arrays by position.
+    /// Serde treats fixed-size arrays like `[T; 3]` as tuples, so this
+    /// applies to them as well.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{DateTime, Deserializer};
+    ///
+    /// type Row = (String, u64, DateTime);
+    ///
+    /// let input = r#"{"ts:t": "2017-03-04T05:06:07Z", "name:s": "disk", "count:u": "3"}"#;
+    /// let mut de = Deserializer::from_str(input).field_order(&["name", "count", "ts"]);
+    /// let (name, count, ts) = Row::deserialize(&mut de).unwrap();
+    /// assert_eq!((name.as_str(), count), ("disk", 3));
+    /// assert_eq!(ts.to_rfc3339(), "2017-03-04T05:06:07+00:00");
+    /// # }
+    /// ```
+    pub fn field_order(mut self, names: &[&str]) -> Self {
+        self.options.field_order(names);
+        self
+    }
+
+    /// Also deserialize maps from arrays of `[key, value]` pairs, which is
+    /// how maps whose keys can't be member names are serialized.
+    ///
+    /// Objects are still accepted. Each key is read as a `Value` and checked
+    /// against the earlier keys, so a key repeated in a later pair is an
+    /// error naming that pair's index, and keys can't be borrowed from the
+    /// input. This is off by default so that an array given where a map is
+    /// expected is reported as the wrong type.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use std::collections::BTreeMap;
+    ///
+    /// use serde::Deserialize;
+    /// use tjson::Deserializer;
+    ///
+    /// let mut grid = BTreeMap::new();
+    /// grid.insert((0, 1), "a".to_owned());
+    /// grid.insert((2, 3), "b".to_owned());
+    ///
+    /// let text = tjson::to_string(&grid).unwrap();
+    /// assert_eq!(text, r#"[[[0,1],"a"],[[2,3],"b"]]"#);
+    ///
+    /// let mut de = Deserializer::from_str(&text).map_as_pairs(true);
+    /// assert_eq!(BTreeMap::<(u32, u32), String>::deserialize(&mut de).unwrap(), grid);
+    ///
+    /// let mut de = Deserializer::from_str(r#"[[1, "a"], [1, "b"]]"#).map_as_pairs(true);
+    /// let err = BTreeMap::<u32, String>::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.to_string(), "duplicate key in pair 1 at line 1 column 13");
+    /// # }
+    /// ```
+    pub fn map_as_pairs(mut self, enabled: bool) -> Self {
+        self.options.map_as_pairs = enabled;
+        self
+    }
+
+    /// Accept floating point numbers which are whole numbers, like `3.0`,
+    /// when deserializing integers, for data from producers which don't
+    /// distinguish the two.
+    ///
+    /// The number's text must be exactly an integer which a `f64` can hold,
+    /// so that the float the producer had meant the same integer. Otherwise
+    /// the error has `ErrorCode::InexactInteger`. Numbers with a fractional
+    /// part are still the wrong type for an integer. Off by default.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::Deserializer;
+    /// use tjson::error::ErrorCode;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Size {
+    ///     #[serde(rename = "x:f")]
+    ///     x: u32,
+    /// }
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"x:f": 3.0}"#).integral_float_coercion(true);
+    /// assert_eq!(Size::deserialize(&mut de).unwrap().x, 3);
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"x:f": 9007199254740993.0}"#)
+    ///     .integral_float_coercion(true);
+    /// let err = Size::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::InexactInteger);
+    /// # }
+    /// ```
+    pub fn integral_float_coercion(mut self, enabled: bool) -> Self {
+        self.options.integral_float_coercion = enabled;
+        self
+    }
+
+    /// Read integers quoted as strings, like `"42"`, as the values of
+    /// members tagged `i` or `u` and elements of ones tagged `A<i>` and so
+    /// on, which is how TJSON writes them. They are read as the integers
+    /// they spell, exactly however large, and fail with
+    /// `ErrorCode::NumberOutOfRange` if they don't fit the tag. Strings which
+    /// aren't integers, like `"4.0"`, don't match the tag and fail with
+    /// `ErrorCode::TagMismatch`. On by default; turning it off reads such
+    /// values as the strings they are.
+    /// `SerializeConfig::quoted_integers` writes integers this way.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::Deserializer;
+    /// use tjson::error::ErrorCode;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Counter {
+    ///     #[serde(rename = "count:u")]
+    ///     count: u64,
+    /// }
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"count:u": "9007199254740993"}"#);
+    /// assert_eq!(Counter::deserialize(&mut de).unwrap().count, 9007199254740993);
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"count:u": "-1"}"#);
+    /// let err = Counter::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"count:u": "7"}"#).quoted_integers(false);
+    /// assert!(Counter::deserialize(&mut de).is_err());
+    /// # }
+    /// ```
+    pub fn quoted_integers(mut self, enabled: bool) -> Self {
+        self.options.quoted_integers = enabled;
+        self
+    }
+
+    /// Set whether integers tagged `u` may be read into signed types such as
+    /// `i64`, and those tagged `i` into unsigned ones such as `u32`. Defaults
+    /// to `IntegerSignedness::Coerce`, which reads them whenever they fit.
+    /// See `IntegerSignedness` for an example.
+    pub fn integer_signedness(mut self, signedness: IntegerSignedness) -> Self {
+        self.options.integer_signedness = signedness;
+        self
+    }
+
+    // Not public API. Should be pub(crate).
+    //
+    // Keeps a note of each change a lenient option makes to the input, for
+    // `repair::rewrite`.
+    #[doc(hidden)]
+    pub fn keep_repairs(mut self) -> Self {
+        self.repairs = Some(Vec::new());
+        self
+    }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn take_repairs(&mut self) -> Vec<(String, String)> {
+        self.repairs.take().unwrap_or_default()
+    }
+
+    /// Notes a change made to the value at `pointer`, if repairs are kept.
+    fn repaired<F>(&mut self, pointer: String, description: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if let Some(ref mut repairs) = self.repairs {
+            repairs.push((pointer, description()));
+        }
+    }
+
+    /// Match member names to the fields of structs by the given
+    /// normalization, for producers whose names drift in case or spacing.
+    ///
+    /// Names which match a field exactly are always taken as that field.
+    /// Tags are only normalized by `Normalization::TagKinds`, so `"Id:s"`
+    /// never matches a field renamed to `"id:S<s>"`, and only left off of
+    /// field names by `Normalization::Untagged`. Members which match no
+    /// field are handed on unchanged, so `#[serde(deny_unknown_fields)]`
+    /// reports them as they were written.
+    /// This only applies to structs: the member names of maps, and of
+    /// objects read into a `Value`, are kept as they are.
+    ///
+    /// If two members of an object match the same field, the error has
+    /// `ErrorCode::AmbiguousMember` and names both. Defaults to
+    /// `Normalization::Untagged`, so that a field `name` matches a member
+    /// `"name:s"`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::Deserializer;
+    /// use tjson::de::Normalization;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct User {
+    ///     #[serde(rename = "userId:u")]
+    ///     user_id: u64,
+    /// }
+    ///
+    /// let mut de = Deserializer::from_str(r#"{" UserID:u ": "7"}"#)
+    ///     .member_name_normalization(Normalization::TrimAndCaseInsensitive);
+    /// assert_eq!(User::deserialize(&mut de).unwrap().user_id, 7);
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"userid:u": "7", "USERID:u": "8"}"#)
+    ///     .member_name_normalization(Normalization::CaseInsensitive);
+    /// let err = User::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "members \"userid:u\" and \"USERID:u\" both match field \"userId:u\" at line 1 column 28"
+    /// );
+    /// # }
+    /// ```
+    pub fn member_name_normalization(mut self, normalization: Normalization) -> Self {
+        self.options.member_name_normalization = normalization;
+        self
+    }
+
+    /// Reject member names which the given policy doesn't allow, with
+    /// `ErrorCode::InvalidMemberName`. This applies to every object, whether
+    /// it is read into a struct, a map or a `Value`.
+    ///
+    /// By default member names aren't checked, as `Map::insert` doesn't
+    /// check them.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Value};
+    /// use tjson::map::KeyPolicy;
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"ok:i": "1", "not ok:i": "2"}"#)
+    ///     .key_policy(KeyPolicy::Identifier);
+    /// let err = Value::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "member name \"not ok:i\" is not an identifier at line 1 column 24"
+    /// );
+    /// # }
+    /// ```
+    pub fn key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.options.key_policy = Some(policy);
+        self
+    }
+
+    /// Hold every document to the TJSON spec. It must be an object or an
+    /// array, failing with `ErrorCode::ExpectedObjectOrArray` otherwise, and
+    /// member names without a tag, such as `"foo"`, fail with
+    /// `ErrorCode::InvalidTag`. Off by default, so that documents with
+    /// untagged member names can still be read.
+    ///
+    /// Tags are checked whether or not this is on. A name whose tag is
+    /// malformed, such as `"foo:Z"` or `"foo:A<"`, fails with
+    /// `ErrorCode::InvalidTag`, and a value which doesn't hold what its tag
+    /// says fails with `ErrorCode::TagMismatch`: a timestamp under `t` which
+    /// isn't an RFC 3339 UTC timestamp, an integer under `i` which is a JSON
+    /// number rather than a string, an object under `A<s>` and so on.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Value};
+    /// use tjson::error::ErrorCode;
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"ids:A<u>": ["1", "2"], "name": "x"}"#)
+    ///     .strict_tags(true);
+    /// let err = Value::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::InvalidTag);
+    /// assert_eq!(err.to_string(), "member name \"name\" has no tag at line 1 column 31");
+    ///
+    /// let err = tjson::from_str::<Value>(r#"{"ids:A<u>": ["1", 2]}"#).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::TagMismatch);
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "expected a value tagged `u`, found number 2 at /ids:A<u>/1 at line 1 column 20"
+    /// );
+    ///
+    /// let err = tjson::from_str::<Value>(r#"{"name:Z": "x"}"#).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::InvalidTag);
+    ///
+    /// let mut de = Deserializer::from_str(r#""x""#).strict_tags(true);
+    /// let err = Value::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::ExpectedObjectOrArray);
+    /// # }
+    /// ```
+    pub fn strict_tags(mut self, enabled: bool) -> Self {
+        self.options.strict_tags = enabled;
+        self
+    }
+
+    /// Decode data wherever any value is expected, so that a `Value` holds
+    /// it as a `Value::Data` rather than a `Value::String` of its encoding.
+    /// Data is the value of a member tagged `d`, `d16`, `d32` or `d64`, or
+    /// an element of one tagged `A<d>` and so on, and is decoded as
+    /// `deserialize_bytes` decodes it whether or not this is on: text which
+    /// isn't validly encoded for its tag, such as padded or wrapped
+    /// base64url or uppercase hex, fails, giving the offset into the string
+    /// of the first character which is wrong. On by default; turning it off
+    /// reads data as the strings of its encoding wherever any value is
+    /// expected.
+    ///
+    /// With it off, a `Value` read this way writes data back out as the text
+    /// it was read as. `lazy_data` does that too while still reading it as
+    /// `Value::Data`.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Value};
+    ///
+    /// let input = r#"{"raw:d": "QklOQVJZ", "hex:A<d16>": ["fbff"]}"#;
+    /// let value: Value = tjson::from_str(input).unwrap();
+    /// assert_eq!(value["raw:d"], Value::Data(b"BINARY".to_vec().into()));
+    /// assert_eq!(value["hex:A<d16>"][0], Value::Data(vec![0xfb, 0xff].into()));
+    ///
+    /// let err = tjson::from_str::<Value>(r#"{"raw:d": "QklOQVJZ=="}"#).unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid padding at offset 8 at line 1 column 22");
+    ///
+    /// let mut de = Deserializer::from_str(input).decode_data(false);
+    /// let value = Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(value["raw:d"], "QklOQVJZ");
+    /// # }
+    /// ```
+    pub fn decode_data(mut self, enabled: bool) -> Self {
+        self.options.decode_data = enabled;
+        self
+    }
+
+    /// Hold base64url data, tagged `d` or `d64`, undecoded where
+    /// `decode_data` reads it into a `Value::Data`. Its text is only checked
+    /// to be validly encoded, failing as decoding it would, and its bytes
+    /// are decoded the first time they are needed, as `tjson::Data`
+    /// describes. Data which nothing looks at costs no decoding, and is
+    /// written back out as the text it was read as. Data in other
+    /// encodings, data which data hooks apply to, and data read as bytes
+    /// rather than as any value is decoded as usual. Off by default.
+    ///
+    /// Types other than `Value` which read any value where such data is see
+    /// a map of one private member instead, so this is for reading `Value`s.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Value};
+    ///
+    /// let input = r#"{"hex:d16":"fbff","raw:d":"QklOQVJZ"}"#;
+    /// let mut de = Deserializer::from_str(input).lazy_data(true);
+    /// let value = Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+    /// assert_eq!(value["raw:d"].as_data(), Some(&b"BINARY"[..]));
+    /// assert_eq!(tjson::to_string(&value).unwrap(), input);
+    ///
+    /// let mut de = Deserializer::from_str(r#"{"raw:d": "QklOQVJZ=="}"#).lazy_data(true);
+    /// let err = Value::deserialize(&mut de).unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid padding at offset 8 at line 1 column 22");
+    /// # }
+    /// ```
+    pub fn lazy_data(mut self, enabled: bool) -> Self {
+        self.options.lazy_data = enabled;
+        self
+    }
+
+    /// Read the toplevel value as the value of a member with the given tag
+    /// would be read, for documents which are a single value or an array of
+    /// them: data is decoded into bytes and timestamps are checked against
+    /// the `OffsetPolicy`.
+    ///
+    /// The value isn't checked against the tag. `tjson::from_str_as` does
+    /// that as well.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Tag};
+    /// use tjson::ser::DataEncoding;
+    ///
+    /// let mut de = Deserializer::from_str(r#""0102""#).root_tag(&Tag::Data(DataEncoding::Base16));
+    /// assert_eq!(Vec::<u8>::deserialize(&mut de).unwrap(), [1, 2]);
+    /// # }
+    /// ```
+    pub fn root_tag(mut self, tag: &Tag) -> Self {
+        self.options.root_tag = Some(tag.to_string());
+        self
+    }
+
+    /// Set what happens to timestamps with an offset other than `Z`.
+    ///
+    /// Timestamps are the RFC 3339 timestamps in members tagged `t`, or in
+    /// arrays and sets of them such as `A<t>`, in which the `T` may be
+    /// lowercase and the fraction of a second is optional. By default, any
+    /// offset other than `Z` is an error naming the offset and the JSON
+    /// pointer of the timestamp, including `+00:00`, `-00:00` and a
+    /// lowercase `z`. Other strings are read as they are.
+    ///
+    /// With `OffsetPolicy::NormalizeToUtc`, such timestamps are converted to
+    /// UTC, so that they are read as the equivalent timestamp ending in `Z`,
+    /// and `-00:00` is read as UTC as RFC 3339 intends. Timestamps already
+    /// ending in `Z` are read as they are written either way.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Deserialize;
+    /// use tjson::{Deserializer, Value};
+    /// use tjson::de::OffsetPolicy;
+    ///
+    /// let input = r#"{"at:t": "2017-03-04T07:06:07+02:00"}"#;
+    ///
+    /// let err = tjson::from_str::<Value>(input).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "timestamp has offset `+02:00` rather than `Z` at /at:t at line 1 column 36"
+    /// );
+    ///
+    /// let mut de = Deserializer::from_str(input).timestamp_offsets(OffsetPolicy::NormalizeToUtc);
+    /// let value = Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(value["at:t"], Value::Timestamp("2017-03-04T05:06:07Z".parse().unwrap()));
+    /// # }
+    /// ```
+    pub fn timestamp_offsets(mut self, policy: OffsetPolicy) -> Self {
+        self.options.timestamp_offsets = policy;
+        self
+    }
+
+    /// Run `transform` over the decoded bytes of each data value `matcher`
+    /// picks out, as it is parsed. See `tjson::transform`.
+    ///
+    /// The result is encoded again for the value's tag. Deserializing the
+    /// value as bytes, such as into a `Vec<u8>` field, gives the result
+    /// itself. Hooks run in the order they were added, each over the result
+    /// of the one before.
+    pub fn transform_data<F>(mut self, matcher: Matcher, transform: F) -> Self
+    where
+        F: Fn(Vec<u8>) -> Result<Vec<u8>> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.options.transforms).add_data(matcher, transform);
+        self
+    }
+
+    /// Run `transform` over each string tagged `s` or untagged which
+    /// `matcher` picks out, as it is parsed. See `tjson::transform`.
+    pub fn transform_string<F>(mut self, matcher: Matcher, transform: F) -> Self
+    where
+        F: Fn(String) -> Result<String> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.options.transforms).add_string(matcher, transform);
+        self
+    }
+
+    /// Measurements of the input parsed so far.
+    pub fn report(&self) -> ParseReport {
+        ParseReport {
+            bytes: self.read.byte_offset(),
+            escapes: self.read.escapes(),
+            ..self.counts
+        }
+    }
+
+    /// Warn about objects with more than `count` members, rather than
+    /// failing, for alerting on documents which are getting close to a hard
+    /// limit elsewhere. See `warnings`.
+    ///
+    /// Only the objects themselves are counted: a member holding an array
+    /// of ten elements is one member.
+    pub fn warn_member_count(mut self, count: usize) -> Self {
+        self.options.warn_member_count = Some(count);
+        self
+    }
+
+    /// Warn about member names longer than `len` bytes, including their
+    /// tags, once unescaped. See `warnings`.
+    pub fn warn_key_length(mut self, len: usize) -> Self {
+        self.options.warn_key_length = Some(len);
+        self
+    }
+
+    /// Warn about arrays and objects nested more than `depth` deep. See
+    /// `warnings`.
+    ///
+    /// Only the outermost container past the limit is warned about, not
+    /// each of those nested inside it. The hard limit on nesting is 127.
+    pub fn warn_depth(mut self, depth: usize) -> Self {
+        self.options.warn_depth = Some(depth);
+        self
+    }
+
+    /// Keep at most `cap` warnings from the `warn_` options, counting the
+    /// rest. Defaults to 100.
+    pub fn max_warnings(mut self, cap: usize) -> Self {
+        self.options.max_warnings = cap;
+        self
+    }
+
+    /// The soft limits set by the `warn_` options which the input parsed so
+    /// far went over, each with the path to the offending container or
+    /// member and how far over it was.
+    ///
+    /// Without any `warn_` options nothing is measured beyond what `report`
+    /// already counts, and this is always empty.
+    pub fn warnings(&self) -> &LimitWarnings {
+        &self.warnings
+    }
+
+    /// Counts the member whose name was just parsed towards the `warn_`
+    /// options.
+    fn warn_member(&mut self) {
+        let name_len = match self.open.last_mut() {
+            Some(open) => {
+                open.members += 1;
+                open.member.len()
+            }
+            None => return,
+        };
+        if let Some(limit) = self.options.warn_key_length {
+            if name_len > limit {
+                let path = Some(pointer(&self.open));
+                let warning = StatViolation::new(Metric::NameLen, limit, name_len, path);
+                self.warnings.push(warning, self.options.max_warnings);
+            }
+        }
+    }
+
+    /// Takes back the buffers for the next parse, emptied but still
+    /// allocated.
+    fn into_scratch(mut self) -> Scratch {
+        while !self.open.is_empty() {
+            self.close_container();
+        }
+        self.str_buf.clear();
+
+        Scratch {
+            str_buf: self.str_buf,
+            open: self.open,
+            spare_members: self.spare_members,
+            spare_matchers: self.spare_matchers,
+            member_names: self.member_names,
+        }
+    }
+}
+
+impl<R> Deserializer<read::IoRead<R>>
+where
+    R: io::Read,
+{
+    /// Creates a JSON deserializer from an `io::Read`.
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(read::IoRead::new(reader))
+    }
+}
+
+impl<'a> Deserializer<read::SliceRead<'a>> {
+    /// Creates a JSON deserializer from a `&[u8]`.
+    pub fn from_slice(bytes: &'a [u8]) -> Self {
+        Deserializer::new(read::SliceRead::new(bytes))
+    }
+}
+
+impl<'a> Deserializer<read::StrRead<'a>> {
+    /// Creates a JSON deserializer from a `&str`.
+    pub fn from_str(s: &'a str) -> Self {
+        Deserializer::new(read::StrRead::new(s))
+    }
+}
+
+macro_rules! overflow {
+    ($a:ident * 10 + $b:ident, $c:expr) => {
+        $a >= $c / 10 && ($a > $c / 10 || $b > $c % 10)
+    }
+}
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    /// The `Deserializer::end` method should be called after a value has been fully deserialized.
+    /// This allows the `Deserializer` to validate that the input stream is at the end or that it
+    /// only has trailing whitespace.
+    pub fn end(&mut self) -> Result<()> {
+        match try!(self.parse_whitespace()) {
+            Some(_) => Err(self.peek_error(Code::TrailingCharacters)),
+            None => Ok(()),
+        }
+    }
+
+    /// Turn a JSON deserializer into an iterator over values of type T.
+    pub fn into_iter<T>(self) -> StreamDeserializer<'de, R, T>
+    where
+        T: de::Deserialize<'de>,
+    {
+        // This cannot be an implementation of std::iter::IntoIterator because
+        // we need the caller to choose what T is.
+        let offset = self.read.byte_offset();
+        StreamDeserializer {
+            de: self,
+            offset: offset,
+            failed_on_newline: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.read.peek().map_err(Error::io)
+    }
+
+    fn peek_or_null(&mut self) -> Result<u8> {
+        Ok(try!(self.peek()).unwrap_or(b'\x00'))
+    }
+
+    fn eat_char(&mut self) {
+        self.read.discard();
+    }
+
+    fn next_char(&mut self) -> Result<Option<u8>> {
+        self.read.next().map_err(Error::io)
+    }
+
+    fn next_char_or_null(&mut self) -> Result<u8> {
+        Ok(try!(self.next_char()).unwrap_or(b'\x00'))
+    }
+
+    /// Error caused by a byte from next_char().
+    fn error(&mut self, reason: Code) -> Error {
+        let pos = self.read.position();
+        let offset = self.read.byte_offset().saturating_sub(1);
+        Error::syntax(reason, pos.line, pos.column).with_byte_offset(offset)
+    }
+
+    /// Error caused by a byte from peek().
+    fn peek_error(&mut self, reason: Code) -> Error {
+        let pos = self.read.peek_position();
+        Error::syntax(reason, pos.line, pos.column).with_byte_offset(self.read.byte_offset())
+    }
+
+    /// Error caused by peek() finding the end of the input while expecting
+    /// the given construct. Names the innermost unterminated construct and
+    /// counts the containers left open.
+    fn eof_error(&mut self, reason: Code) -> Error {
+        let construct = match (reason, self.open.last()) {
+            (Code::EofWhileParsingValue, Some(open)) if open.has_member => {
+                Construct::MemberValue(open.member.clone())
+            }
+            (_, Some(open)) => {
+                let start = self.read.mark_position(open.start);
+                if open.object {
+                    Construct::Object(start.line, start.column)
+                } else {
+                    Construct::Array(start.line, start.column)
+                }
+            }
+            (_, None) => Construct::Value,
+        };
+
+        let pos = self.read.peek_position();
+        self.unterminated(construct, pos.line, pos.column)
+            .with_byte_offset(self.read.byte_offset())
+    }
+
+    /// Adds the start of the string and the containers left open to an error
+    /// from parsing the string which started at the given mark, if it was
+    /// caused by running out of input.
+    fn string_error(&mut self, err: Error, start: Mark) -> Error {
+        if !err.is_eof() {
+            return err;
+        }
+
+        let start = self.read.mark_position(start);
+        let construct = Construct::String(start.line, start.column);
+        let unterminated = self.unterminated(construct, err.line(), err.column());
+        match err.byte_offset() {
+            Some(offset) => unterminated.with_byte_offset(offset),
+            None => unterminated,
+        }
+    }
+
+    fn unterminated(&self, construct: Construct, line: usize, column: usize) -> Error {
+        let objects = self.open.iter().filter(|open| open.object).count();
+        let unterminated = Unterminated {
+            construct: construct,
+            arrays: self.open.len() - objects,
+            objects: objects,
+        };
+
+        Error::syntax(Code::Unterminated(Box::new(unterminated)), line, column)
+    }
+
+    /// Consumes the peeked `[` or `{`, checking the recursion limit first.
+    fn open_container(&mut self, object: bool) -> Result<()> {
+        if self.open.len() == MAX_DEPTH {
+            return Err(self.peek_error(Code::RecursionLimitExceeded));
+        }
+
+        let start = self.read.peek_mark();
+        let member = self.spare_members.pop().unwrap_or_default();
+        let names = self.member_names.names.len();
+        self.open.push(Open::new(object, start, member, names));
+        let depth = self.open.len();
+        self.counts.max_depth = cmp::max(self.counts.max_depth, depth);
+        if let Some(limit) = self.options.warn_depth {
+            if depth == limit.saturating_add(1) {
+                let path = Some(pointer(&self.open[..depth - 1]));
+                let warning = StatViolation::new(Metric::Depth, limit, depth, path);
+                self.warnings.push(warning, self.options.max_warnings);
+            }
+        }
+        self.eat_char();
+        Ok(())
+    }
+
+    fn close_container(&mut self) {
+        if let Some(open) = self.open.pop() {
+            if let Some(limit) = self.options.warn_member_count {
+                if open.object && open.members > limit {
+                    let path = Some(pointer(&self.open));
+                    let warning =
+                        StatViolation::new(Metric::ContainerLen, limit, open.members, path);
+                    self.warnings.push(warning, self.options.max_warnings);
+                }
+            }
+            self.spare_members.push(open.member);
+            self.member_names.truncate(open.names);
+        }
+    }
+
+    /// Returns the first non-whitespace byte without consuming it, or `None` if
+    /// EOF is encountered.
+    fn parse_whitespace(&mut self) -> Result<Option<u8>> {
+        loop {
+            match try!(self.peek()) {
+                Some(b' ') | Some(b'\n') | Some(b'\t') | Some(b'\r') => {
+                    self.eat_char();
+                }
+                other => {
+                    return Ok(other);
+                }
+            }
+        }
+    }
+
+    fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        try!(self.check_root());
+        let peek = match try!(self.parse_whitespace()) {
+            Some(b) => b,
+            None => {
+                return Err(self.eof_error(Code::EofWhileParsingValue));
+            }
+        };
+
+        // This is not the TJSON tag parsing code, sorry.
+        let value = match peek {
+            b'n' => {
+                self.eat_char();
+                try!(self.parse_ident(b"ull"));
+                visitor.visit_unit()
+            }
+            b't' => {
+                self.eat_char();
+                try!(self.parse_ident(b"rue"));
+                visitor.visit_bool(true)
+            }
+            b'f' => {
+                self.eat_char();
+                try!(self.parse_ident(b"alse"));
+                visitor.visit_bool(false)
+            }
+            b'-' => {
+                self.eat_char();
+                self.parse_integer(false, visitor)
+            }
+            b'0'...b'9' => self.parse_integer(true, visitor),
+            b'"' => {
+                match self.data_tag() {
+                    Some(ref tag) if self.options.decode_data && self.reads_lazily(tag) => {
+                        let text = try!(self.parse_data_text());
+                        visitor.visit_map(MapDeserializer::new(iter::once((DATA_TOKEN, text))))
+                    }
+                    Some(ref tag) if self.options.decode_data => {
+                        let bytes = try!(self.parse_data(tag));
+                        visitor.visit_byte_buf(bytes)
+                    }
+                    _ => self.parse_string(visitor),
+                }
+            }
+            b'[' => {
+                let set = self.value_tag().map_or(false, |tag| tag.starts_with("S<"));
+                try!(self.open_container(false));
+                let ret = if set {
+                    visitor.visit_seq(SetAccess::new(self))
+                } else {
+                    visitor.visit_seq(SeqAccess::new(self))
+                };
+
+                // The array stays open until `]` is found so that running out
+                // of input here reports it.
+                let end = self.finish_seq(&ret);
+                self.close_container();
+
+                match (ret, end) {
+                    (Ok(ret), Ok(())) => Ok(ret),
+                    (Err(err), _) | (_, Err(err)) => Err(err),
+                }
+            }
+            b'{' => {
+                match self.options.duplicate_keys {
+                    DuplicateKeys::Error => self.parse_map(visitor, true),
+                    policy => self.parse_resolved_map(visitor, policy),
+                }
+            }
+            _ => Err(self.peek_error(Code::ExpectedSomeValue)),
+        };
+
+        match value {
+            Ok(value) => Ok(value),
+            // The de::Error and From<de::value::Error> impls both create errors
+            // with unknown line and column. Fill in the position here by
+            // looking at the current index in the input. There is no way to
+            // tell whether this should call `error` or `peek_error` so pick the
+            // one that seems correct more often. Worst case, the position is
+            // off by one character.
+            Err(err) => Err(err.fix_position(|code| self.error(code))),
+        }
+    }
+
+    /// Parses an object after its `{` has been peeked, handing each member
+    /// to the visitor as it is read.
+    fn parse_map<V>(&mut self, visitor: V, check_duplicates: bool) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse_struct(visitor, check_duplicates, None)
+    }
+
+    /// Parses an object after its `{` has been peeked, as `parse_map` does,
+    /// matching member names to struct fields with the given matcher.
+    fn parse_struct<V>(
+        &mut self,
+        visitor: V,
+        check_duplicates: bool,
+        fields: Option<FieldMatcher>,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        try!(self.open_container(true));
+        let mut access = MapAccess::new(self, check_duplicates);
+        access.fields = fields;
+        let ret = visitor.visit_map(&mut access);
+        if let Some(fields) = access.fields.take() {
+            self.spare_matchers.push(fields);
+        }
+
+        let end = self.finish_map(&ret);
+        self.close_container();
+
+        match (ret, end) {
+            (Ok(ret), Ok(())) => Ok(ret),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an array of `[key, value]` pairs after its `[` has been peeked,
+    /// handing it to the visitor as a map.
+    fn parse_pairs<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        try!(self.open_container(false));
+        let ret = visitor.visit_map(PairAccess::new(self));
+
+        let end = self.finish_seq(&ret);
+        self.close_container();
+
+        match (ret, end) {
+            (Ok(ret), Ok(())) => Ok(ret),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an object after its `{` has been peeked, resolving duplicate
+    /// members by the given policy before handing the object to the visitor.
+    fn parse_resolved_map<V>(&mut self, visitor: V, policy: DuplicateKeys) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        try!(self.open_container(true));
+        let members = self.parse_resolved_members(policy);
+
+        let end = self.finish_map(&members);
+        self.close_container();
+
+        match (members, end) {
+            (Ok(members), Ok(())) => {
+                de::Deserializer::deserialize_any(Value::Object(members), visitor)
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an object after its `{` has been peeked as `parse_resolved_map`
+    /// does, then matches its member names to the struct's fields by the
+    /// given normalization.
+    fn parse_resolved_struct<V>(
+        &mut self,
+        visitor: V,
+        fields: &'static [&'static str],
+        normalization: Normalization,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let policy = self.options.duplicate_keys;
+        try!(self.open_container(true));
+        let members = self.parse_resolved_members(policy);
+
+        let end = self.finish_map(&members);
+        self.close_container();
+
+        match (members, end) {
+            (Ok(members), Ok(())) => {
+                let value = NormalizedValue::new(
+                    Value::Object(members),
+                    normalization,
+                    self.options.duplicate_elements,
+                );
+                de::Deserializer::deserialize_struct(value, "", fields, visitor)
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an array after its `[` has been peeked as a tuple or array of
+    /// `len` elements, failing with both lengths if there are more.
+    fn parse_tuple_array<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        try!(self.open_container(false));
+        let ret = {
+            let mut seq = SeqAccess::new(self);
+            visitor.visit_seq(&mut seq).and_then(
+                |ret| {
+                    let mut extra = 0;
+                    while try!(de::SeqAccess::next_element::<IgnoredAny>(&mut seq)).is_some() {
+                        extra += 1;
+                    }
+                    if extra == 0 {
+                        Ok(ret)
+                    } else {
+                        let expected = format!("an array of {} elements", len);
+                        Err(de::Error::invalid_length(len + extra, &expected.as_str()))
+                    }
+                },
+            )
+        };
+
+        let end = self.finish_seq(&ret);
+        self.close_container();
+
+        match (ret, end) {
+            (Ok(ret), Ok(())) => Ok(ret),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an array as a set, handing its elements to the visitor as a
+    /// sequence once the `duplicate_elements` policy has been applied to
+    /// them, whether or not the array is tagged `S<..>`. Anything else is
+    /// handed on as it is, for the visitor to reject.
+    fn parse_set<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if try!(self.parse_whitespace()) != Some(b'[') {
+            return visitor.visit_newtype_struct(self);
+        }
+
+        try!(self.open_container(false));
+        let ret = visitor.visit_seq(SetAccess::new(self));
+        let end = self.finish_seq(&ret);
+        self.close_container();
+
+        match (ret, end) {
+            (Ok(ret), Ok(())) => Ok(ret),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Parses an object after its `{` has been peeked as a tuple of the
+    /// members named by the field order.
+    fn parse_tuple_object<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let order = match self.options.field_order.clone() {
+            Some(order) => order,
+            None => {
+                let msg = "cannot deserialize tuple from object without field_order";
+                return Err(self.peek_error(Code::Message(msg.to_owned())));
+            }
+        };
+        let elements = self.parse_ordered_members(&order[..cmp::min(len, order.len())]);
+
+        de::Deserializer::deserialize_any(Value::Array(try!(elements)), visitor)
+    }
+
+    fn parse_ordered_members(&mut self, names: &[String]) -> Result<Vec<Value>> {
+        try!(self.open_container(true));
+        let policy = self.options.duplicate_keys;
+        let members = self.parse_resolved_members(policy);
+
+        let end = self.finish_map(&members);
+        self.close_container();
+
+        let mut members = match (members, end) {
+            (Ok(members), Ok(())) => members,
+            (Err(err), _) | (_, Err(err)) => return Err(err),
+        };
+
+        let mut elements = Vec::with_capacity(names.len());
+        for name in names {
+            let found = members
+                .keys()
+                .find(|member| untagged(member) == untagged(name))
+                .cloned();
+            match found.and_then(|member| members.remove(&member)) {
+                Some(value) => elements.push(value),
+                None => {
+                    let code = Code::Message(format!("missing member `{}`", name));
+                    return Err(self.error(code));
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    fn parse_resolved_members(&mut self, policy: DuplicateKeys) -> Result<Map<String, Value>> {
+        let mut members: Vec<(String, Value)> = Vec::new();
+        // Position in `members` of each untagged name, and whether its value
+        // has become an array of collected values
+        let mut seen: HashMap<String, (usize, bool)> = HashMap::new();
+
+        let mut access = MapAccess::new(self, policy == DuplicateKeys::Error);
+        while let Some(name) = try!(de::MapAccess::next_key::<String>(&mut access)) {
+            let value: Value = try!(de::MapAccess::next_value(&mut access));
+
+            let (index, collected) = match seen.get_mut(untagged(&name)) {
+                Some(&mut (index, ref mut collected)) => (index, collected),
+                None => {
+                    seen.insert(untagged(&name).to_owned(), (members.len(), false));
+                    members.push((name, value));
+                    continue;
+                }
+            };
+
+            if access.de.repairs.is_some() {
+                let at = pointer(&access.de.open);
+                let kept = if policy == DuplicateKeys::FirstWins { "first" } else { "last" };
+                access.de.repaired(at, || format!("duplicate member, kept the {}", kept));
+            }
+
+            let member = &mut members[index];
+            match policy {
+                DuplicateKeys::FirstWins => {}
+                DuplicateKeys::LastWins => *member = (name, value),
+                DuplicateKeys::Collect if member.0 != name => {
+                    let code = Code::CollectedTagMismatch(member.0.clone(), name);
+                    return Err(access.de.error(code));
+                }
+                DuplicateKeys::Collect => {
+                    if !*collected {
+                        let first = mem::replace(&mut member.1, Value::Undefined);
+                        member.1 = Value::Array(vec![first]);
+                        *collected = true;
+                    }
+                    if let Value::Array(ref mut values) = member.1 {
+                        values.push(value);
+                    }
+                }
+                DuplicateKeys::Error => unreachable!(),
+            }
+        }
+
+        Ok(members.into_iter().collect())
+    }
+
+    /// Fails if the member name just parsed repeats one from earlier in the
+    /// same object.
+    fn check_duplicate_member(&mut self) -> Result<()> {
+        let names = &mut self.member_names;
+        let duplicate = match self.open.last_mut() {
+            Some(open) if open.has_member => {
+                !names.insert(open.names, &mut open.table, untagged(&open.member))
+            }
+            _ => false,
+        };
+
+        if duplicate {
+            let name = self.open.last().map_or_else(String::new, |open| open.member.clone());
+            Err(self.error(Code::DuplicateMember(name)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parses an object member name after its `"` has been peeked, keeping
+    /// the name in case the input ends before the member's value.
+    fn parse_member_name<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let start = self.read.peek_mark();
+        self.eat_char();
+        self.str_buf.clear();
+        let value: Result<V::Value> = match self.read.parse_str(&mut self.str_buf) {
+            Ok(name) => {
+                let policy = self.options.key_policy;
+                if let Some(err) = policy.and_then(|policy| policy.check(&name).err()) {
+                    return Err(self.error(Code::InvalidMemberName(err)));
+                }
+                let normalization = self.options.member_name_normalization;
+                if !has_valid_tag(&name, normalization) &&
+                    (self.options.strict_tags || name.contains(':'))
+                {
+                    let name = String::from(&*name);
+                    return Err(self.error(Code::InvalidTag(name)));
+                }
+                if let Some(open) = self.open.last_mut() {
+                    open.set_member(&name);
+                }
+                match name {
+                    Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+                    Reference::Copied(s) => visitor.visit_str(s),
+                }
+            }
+            Err(err) => return Err(self.string_error(err, start)),
+        };
+
+        match value {
+            Ok(value) => Ok(value),
+            Err(err) => Err(err.fix_position(|code| self.error(code))),
+        }
+    }
+
+    /// Parses a string value after its `"` has been peeked, checking it
+    /// against the `OffsetPolicy` if it's a timestamp and running any
+    /// transform hooks which apply to it otherwise.
+    fn parse_string<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let is_timestamp = in_timestamp(&self.open, self.options.root_tag.as_deref());
+        let checked_tag = self.checked_tag();
+        let integer_tag = match self.value_tag() {
+            Some("i") if self.options.quoted_integers => Some("i"),
+            Some("u") if self.options.quoted_integers => Some("u"),
+            _ => None,
+        };
+        let start = self.read.peek_mark();
+        self.eat_char();
+        self.str_buf.clear();
+        let normalized = match self.read.parse_str(&mut self.str_buf) {
+            Ok(s) => {
+                self.counts.longest_string = cmp::max(self.counts.longest_string, s.len());
+                if let Some(number) = integer_tag.and_then(|tag| Number::from_quoted(tag, &s)) {
+                    return self.visit_quoted_integer(number, visitor);
+                }
+                if let Some(tag) = checked_tag.filter(|tag| !string_matches_tag(tag, &s)) {
+                    let found = format!("string {}", ::ser::to_string(&*s).unwrap_or_default());
+                    return Err(self.error(self.tag_mismatch(tag, found)));
+                }
+                let normalized = if is_timestamp {
+                    let offsets = self.options.timestamp_offsets;
+                    let normalized = normalize_timestamp(&s, offsets, &self.open);
+                    if let (Ok(Some(ref utc)), Some(ref mut repairs)) =
+                        (normalized.as_ref(), self.repairs.as_mut())
+                    {
+                        if !utc.eq_ignore_ascii_case(&s) {
+                            let description = format!("timestamp {} converted to UTC", &*s);
+                            repairs.push((pointer(&self.open), description));
+                        }
+                    }
+                    normalized
+                } else if self.options.transforms.is_empty() {
+                    Ok(None)
+                } else {
+                    let tag = value_tag(&self.open, self.options.root_tag.as_deref());
+                    self.options.transforms.apply(&pointer(&self.open), tag, &s)
+                };
+                match (normalized, s) {
+                    (Ok(None), Reference::Borrowed(s)) => return visitor.visit_borrowed_str(s),
+                    (Ok(None), Reference::Copied(s)) => return visitor.visit_str(s),
+                    (Ok(Some(normalized)), _) => Ok(normalized),
+                    (Err(code), _) => Err(code),
+                }
+            }
+            Err(err) => return Err(self.string_error(err, start)),
+        };
+
+        match normalized {
+            Ok(normalized) => visitor.visit_string(normalized),
+            Err(code) => Err(self.error(code)),
+        }
+    }
+
+    /// Hands an integer read from a quoted string with `quoted_integers` to
+    /// the visitor, or fails if it didn't fit its tag.
+    fn visit_quoted_integer<V>(&mut self, number: Option<Number>, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let number = match (number, self.integer_type) {
+            (Some(ref number), Some(target)) if !target.fits(number) => {
+                return Err(self.error(Code::NumberOutOfRange));
+            }
+            (Some(number), _) => number,
+            (None, _) => return Err(self.error(Code::NumberOutOfRange)),
+        };
+        match de::Deserializer::deserialize_any(number, visitor) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(err.fix_position(|code| self.error(code))),
+        }
     }
-}
 
-impl<'a> Deserializer<read::SliceRead<'a>> {
-    /// Creates a JSON deserializer from a `&[u8]`.
-    pub fn from_slice(bytes: &'a [u8]) -> Self {
-        Deserializer::new(read::SliceRead::new(bytes))
+    /// The tag of the value about to be parsed, if it has one.
+    fn value_tag(&self) -> Option<&str> {
+        value_tag(&self.open, self.options.root_tag.as_deref())
     }
-}
 
-impl<'a> Deserializer<read::StrRead<'a>> {
-    /// Creates a JSON deserializer from a `&str`.
-    pub fn from_str(s: &'a str) -> Self {
-        Deserializer::new(read::StrRead::new(s))
+    /// The tag of the value about to be parsed, if it has one, which the
+    /// value is checked against.
+    fn checked_tag(&self) -> Option<Tag> {
+        self.value_tag().and_then(Tag::parse)
     }
-}
 
-macro_rules! overflow {
-    ($a:ident * 10 + $b:ident, $c:expr) => {
-        $a >= $c / 10 && ($a > $c / 10 || $b > $c % 10)
+    fn tag_mismatch(&self, expected: Tag, found: String) -> Code {
+        let mismatch = TagMismatch::from_parts(expected, found, pointer(&self.open));
+        Code::TagMismatch(Box::new(mismatch))
     }
-}
 
-impl<'de, R: Read<'de>> Deserializer<R> {
-    /// The `Deserializer::end` method should be called after a value has been fully deserialized.
-    /// This allows the `Deserializer` to validate that the input stream is at the end or that it
-    /// only has trailing whitespace.
-    pub fn end(&mut self) -> Result<()> {
+    /// Fails if a document given a tag of its own isn't what that tag says,
+    /// or with `strict_tags`, if one without isn't an object or an array.
+    fn check_root(&mut self) -> Result<()> {
+        if !self.open.is_empty() {
+            return Ok(());
+        }
+        if self.options.root_tag.is_some() {
+            return self.check_value_kind();
+        }
+        if !self.options.strict_tags {
+            return Ok(());
+        }
         match try!(self.parse_whitespace()) {
-            Some(_) => Err(self.peek_error(ErrorCode::TrailingCharacters)),
-            None => Ok(()),
+            Some(b'{') | Some(b'[') | None => Ok(()),
+            Some(_) => Err(self.peek_error(Code::ExpectedObjectOrArray)),
         }
     }
 
-    /// Turn a JSON deserializer into an iterator over values of type T.
-    pub fn into_iter<T>(self) -> StreamDeserializer<'de, R, T>
-    where
-        T: de::Deserialize<'de>,
-    {
-        // This cannot be an implementation of std::iter::IntoIterator because
-        // we need the caller to choose what T is.
-        let offset = self.read.byte_offset();
-        StreamDeserializer {
-            de: self,
-            offset: offset,
-            output: PhantomData,
-            lifetime: PhantomData,
+    /// Fails if the value about to be parsed can't be what its tag says,
+    /// going by its first byte.
+    fn check_value_kind(&mut self) -> Result<()> {
+        if !self.value_tag().map_or(false, tag::is_tag) {
+            return Ok(());
+        }
+        let next = try!(self.parse_whitespace());
+        let (matches, found) = {
+            // Matched as text, so that an `A<..>` doesn't allocate
+            let tag = self.value_tag().unwrap_or_default();
+            match next {
+                Some(b'"') => {
+                    let matches = match tag {
+                        "s" | "d" | "d16" | "d32" | "d64" | "t" | "i" | "u" => true,
+                        _ => false,
+                    };
+                    (matches, "string")
+                }
+                // Numbers under `i` or `u` are rejected once they are read
+                Some(b'-') | Some(b'0'...b'9') => {
+                    (tag == "i" || tag == "u" || tag == "f", "number")
+                }
+                Some(b't') | Some(b'f') => (tag == "b", "boolean"),
+                Some(b'{') => (tag == "O", "object"),
+                // Data may be written as an array of its bytes
+                Some(b'[') => {
+                    let matches = tag.starts_with("A<") || tag.starts_with("S<") ||
+                        tag.starts_with('d');
+                    (matches, "array")
+                }
+                Some(b'n') => (false, "null"),
+                // Not the start of a value, which parsing it reports
+                _ => return Ok(()),
+            }
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            let tag = self.checked_tag().expect("tag");
+            Err(self.peek_error(self.tag_mismatch(tag, found.to_owned())))
         }
     }
 
-    fn peek(&mut self) -> Result<Option<u8>> {
-        self.read.peek().map_err(Error::io)
+    /// Fails with `IntegerSignedness::Strict` if the value about to be parsed
+    /// is tagged `u` and going into a signed type, or `i` and an unsigned
+    /// one.
+    fn check_signedness(&mut self, target: IntegerType) -> Result<()> {
+        if self.options.integer_signedness == IntegerSignedness::Coerce {
+            return Ok(());
+        }
+        let tag = match self.value_tag() {
+            Some("u") if target.is_signed() => "u",
+            Some("i") if !target.is_signed() => "i",
+            _ => return Ok(()),
+        };
+        let code = Code::SignednessMismatch(pointer(&self.open), tag, target.name);
+        try!(self.parse_whitespace());
+        Err(self.peek_error(code))
     }
 
-    fn peek_or_null(&mut self) -> Result<u8> {
-        Ok(try!(self.peek()).unwrap_or(b'\x00'))
+    /// Fails if an integer is tagged `i` or `u`, which hold integers written
+    /// as strings, unless repairs are kept, in which case it notes that it
+    /// wasn't quoted.
+    fn check_integer_tag(&mut self, pos: bool, n: u64) -> Result<()> {
+        let sign = if pos { "" } else { "-" };
+        match self.checked_tag() {
+            Some(Tag::Int) | Some(Tag::UInt) if self.repairs.is_some() => {
+                let pointer = pointer(&self.open);
+                self.repaired(pointer, || format!("integer {}{} wasn't quoted", sign, n));
+                Ok(())
+            }
+            Some(tag @ Tag::Int) | Some(tag @ Tag::UInt) => {
+                let found = format!("number {}{}", sign, n);
+                Err(self.error(self.tag_mismatch(tag, found)))
+            }
+            _ => Ok(()),
+        }
     }
 
-    fn eat_char(&mut self) {
-        self.read.discard();
+    /// Fails if a number written with a fraction or an exponent, or too
+    /// large for any integer type, is tagged `i` or `u`.
+    fn check_float_tag(&mut self, f: f64) -> Result<()> {
+        match self.checked_tag() {
+            Some(tag @ Tag::Int) | Some(tag @ Tag::UInt) => {
+                Err(self.error(self.tag_mismatch(tag, format!("number {:?}", f))))
+            }
+            _ => Ok(()),
+        }
     }
 
-    fn next_char(&mut self) -> Result<Option<u8>> {
-        self.read.next().map_err(Error::io)
+    /// The tag of the value about to be parsed, if it is data.
+    fn data_tag(&self) -> Option<String> {
+        match self.value_tag() {
+            Some(tag) if transform::is_data(tag) => Some(tag.to_owned()),
+            _ => None,
+        }
     }
 
-    fn next_char_or_null(&mut self) -> Result<u8> {
-        Ok(try!(self.next_char()).unwrap_or(b'\x00'))
+    /// Parses data after its `"` has been peeked, giving its decoded bytes
+    /// once any data hooks which apply to it have run over them.
+    fn parse_data(&mut self, tag: &str) -> Result<Vec<u8>> {
+        let pointer = if self.options.transforms.is_empty() {
+            None
+        } else {
+            let transforms = &self.options.transforms;
+            Some(pointer(&self.open)).filter(|pointer| transforms.matches_data(pointer, tag))
+        };
+
+        let start = self.read.peek_mark();
+        self.eat_char();
+        self.str_buf.clear();
+        let bytes = match self.read.parse_str(&mut self.str_buf) {
+            Ok(s) => {
+                self.counts.longest_string = cmp::max(self.counts.longest_string, s.len());
+                match pointer {
+                    Some(pointer) => self.options.transforms.apply_data(&pointer, tag, &s),
+                    None => transform::decode(tag, &s).map_err(|err| Code::Custom(Arc::new(err))),
+                }
+            }
+            Err(err) => return Err(self.string_error(err, start)),
+        };
+        bytes.map_err(|code| self.error(code))
     }
 
-    /// Error caused by a byte from next_char().
-    fn error(&mut self, reason: ErrorCode) -> Error {
-        let pos = self.read.position();
-        Error::syntax(reason, pos.line, pos.column)
+    /// Whether data with this tag is held undecoded: base64url data which no
+    /// data hooks apply to, with `lazy_data` on.
+    fn reads_lazily(&self, tag: &str) -> bool {
+        let transforms = &self.options.transforms;
+        self.options.lazy_data && (tag == "d" || tag == "d64") &&
+            (transforms.is_empty() || !transforms.matches_data(&pointer(&self.open), tag))
     }
 
-    /// Error caused by a byte from peek().
-    fn peek_error(&mut self, reason: ErrorCode) -> Error {
-        let pos = self.read.peek_position();
-        Error::syntax(reason, pos.line, pos.column)
+    /// Parses base64url data after its `"` has been peeked, giving its text
+    /// once it is checked to be validly encoded.
+    fn parse_data_text(&mut self) -> Result<String> {
+        let start = self.read.peek_mark();
+        self.eat_char();
+        self.str_buf.clear();
+        let text = match self.read.parse_str(&mut self.str_buf) {
+            Ok(s) => {
+                self.counts.longest_string = cmp::max(self.counts.longest_string, s.len());
+                match encoding::b64url_check(s.as_bytes()) {
+                    Ok(()) => Ok(String::from(&*s)),
+                    Err(err) => Err(Code::Custom(Arc::new(err))),
+                }
+            }
+            Err(err) => return Err(self.string_error(err, start)),
+        };
+        text.map_err(|code| self.error(code))
     }
 
-    /// Returns the first non-whitespace byte without consuming it, or `None` if
-    /// EOF is encountered.
-    fn parse_whitespace(&mut self) -> Result<Option<u8>> {
-        loop {
-            match try!(self.peek()) {
-                Some(b' ') | Some(b'\n') | Some(b'\t') | Some(b'\r') => {
-                    self.eat_char();
+    /// Parses an object member name after its `"` has been peeked, as
+    /// `parse_member_name` does, handing the seed the name of the struct
+    /// field it matches instead if there is one.
+    fn parse_field_name<K>(&mut self, seed: K, fields: &mut FieldMatcher) -> Result<K::Value>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let start = self.read.peek_mark();
+        self.eat_char();
+        self.str_buf.clear();
+        let value: Result<K::Value> = match self.read.parse_str(&mut self.str_buf) {
+            Ok(name) => {
+                let policy = self.options.key_policy;
+                if let Some(err) = policy.and_then(|policy| policy.check(&name).err()) {
+                    return Err(self.error(Code::InvalidMemberName(err)));
                 }
-                other => {
-                    return Ok(other);
+                let normalization = self.options.member_name_normalization;
+                if !has_valid_tag(&name, normalization) &&
+                    (self.options.strict_tags || name.contains(':'))
+                {
+                    let name = String::from(&*name);
+                    return Err(self.error(Code::InvalidTag(name)));
+                }
+                if let Some(open) = self.open.last_mut() {
+                    open.set_member(&name);
+                }
+                match (fields.resolve(&name), name) {
+                    (Ok(Some(field)), _) => seed.deserialize(field.into_deserializer()),
+                    (Ok(None), Reference::Borrowed(s)) => {
+                        seed.deserialize(BorrowedStrDeserializer::new(s))
+                    }
+                    (Ok(None), Reference::Copied(s)) => seed.deserialize(s.into_deserializer()),
+                    (Err(code), _) => return Err(self.error(code)),
                 }
             }
+            Err(err) => return Err(self.string_error(err, start)),
+        };
+
+        match value {
+            Ok(value) => Ok(value),
+            Err(err) => Err(err.fix_position(|code| self.error(code))),
         }
     }
 
-    fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    /// Visits the text of a number exactly as it appears in the input, for
+    /// `tjson::ext::decimal`. Anything other than a number is parsed as usual.
+    #[cfg(feature = "decimal")]
+    fn parse_decimal_text<V>(&mut self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let peek = match try!(self.parse_whitespace()) {
-            Some(b) => b,
-            None => {
-                return Err(self.peek_error(ErrorCode::EofWhileParsingValue));
-            }
-        };
+        let mut text = String::new();
 
-        // This is not the TJSON tag parsing code, sorry.
-        let value = match peek {
-            b'n' => {
-                self.eat_char();
-                try!(self.parse_ident(b"ull"));
-                visitor.visit_unit()
-            }
-            b't' => {
-                self.eat_char();
-                try!(self.parse_ident(b"rue"));
-                visitor.visit_bool(true)
-            }
-            b'f' => {
-                self.eat_char();
-                try!(self.parse_ident(b"alse"));
-                visitor.visit_bool(false)
-            }
-            b'-' => {
-                self.eat_char();
-                self.parse_integer(false, visitor)
-            }
-            b'0'...b'9' => self.parse_integer(true, visitor),
-            b'"' => {
+        match try!(self.parse_whitespace()) {
+            Some(b'-') => {
                 self.eat_char();
-                self.str_buf.clear();
-                match try!(self.read.parse_str(&mut self.str_buf)) {
-                    Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
-                    Reference::Copied(s) => visitor.visit_str(s),
-                }
+                text.push('-');
             }
-            b'[' => {
-                self.remaining_depth -= 1;
-                if self.remaining_depth == 0 {
-                    return Err(self.peek_error(ErrorCode::RecursionLimitExceeded));
-                }
-
-                self.eat_char();
-                let ret = visitor.visit_seq(SeqAccess::new(self));
-
-                self.remaining_depth += 1;
+            Some(b'0'...b'9') => {}
+            _ => return self.parse_value(visitor),
+        }
 
-                match (ret, self.end_seq()) {
-                    (Ok(ret), Ok(())) => Ok(ret),
-                    (Err(err), _) | (_, Err(err)) => Err(err),
+        match try!(self.next_char_or_null()) {
+            b'0' => {
+                text.push('0');
+                // There can be only one leading '0'.
+                if let b'0'...b'9' = try!(self.peek_or_null()) {
+                    return Err(self.peek_error(Code::InvalidNumber));
                 }
             }
-            b'{' => {
-                self.remaining_depth -= 1;
-                if self.remaining_depth == 0 {
-                    return Err(self.peek_error(ErrorCode::RecursionLimitExceeded));
-                }
-
-                self.eat_char();
-                let ret = visitor.visit_map(MapAccess::new(self));
+            c @ b'1'...b'9' => {
+                text.push(c as char);
+                try!(self.scan_digits(&mut text));
+            }
+            _ => return Err(self.error(Code::InvalidNumber)),
+        }
 
-                self.remaining_depth += 1;
+        if try!(self.peek_or_null()) == b'.' {
+            self.eat_char();
+            text.push('.');
+            try!(self.scan_required_digits(&mut text));
+        }
 
-                match (ret, self.end_map()) {
-                    (Ok(ret), Ok(())) => Ok(ret),
-                    (Err(err), _) | (_, Err(err)) => Err(err),
-                }
+        if let c @ b'e' | c @ b'E' = try!(self.peek_or_null()) {
+            self.eat_char();
+            text.push(c as char);
+            if let c @ b'+' | c @ b'-' = try!(self.peek_or_null()) {
+                self.eat_char();
+                text.push(c as char);
             }
-            _ => Err(self.peek_error(ErrorCode::ExpectedSomeValue)),
-        };
+            try!(self.scan_required_digits(&mut text));
+        }
 
+        let value: Result<V::Value> = visitor.visit_str(&text);
         match value {
             Ok(value) => Ok(value),
-            // The de::Error and From<de::value::Error> impls both create errors
-            // with unknown line and column. Fill in the position here by
-            // looking at the current index in the input. There is no way to
-            // tell whether this should call `error` or `peek_error` so pick the
-            // one that seems correct more often. Worst case, the position is
-            // off by one character.
             Err(err) => Err(err.fix_position(|code| self.error(code))),
         }
     }
 
+    #[cfg(feature = "decimal")]
+    fn scan_required_digits(&mut self, text: &mut String) -> Result<()> {
+        match try!(self.next_char_or_null()) {
+            c @ b'0'...b'9' => {
+                text.push(c as char);
+                self.scan_digits(text)
+            }
+            _ => Err(self.error(Code::InvalidNumber)),
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    fn scan_digits(&mut self, text: &mut String) -> Result<()> {
+        while let c @ b'0'...b'9' = try!(self.peek_or_null()) {
+            self.eat_char();
+            text.push(c as char);
+        }
+        Ok(())
+    }
+
     fn parse_ident(&mut self, ident: &[u8]) -> Result<()> {
         for c in ident {
             if Some(*c) != try!(self.next_char()) {
-                return Err(self.error(ErrorCode::ExpectedSomeIdent));
+                return Err(self.error(Code::ExpectedSomeIdent));
             }
         }
 
@@ -265,7 +2506,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
             b'0' => {
                 // There can be only one leading '0'.
                 match try!(self.peek_or_null()) {
-                    b'0'...b'9' => Err(self.peek_error(ErrorCode::InvalidNumber)),
+                    b'0'...b'9' => Err(self.peek_error(Code::InvalidNumber)),
                     _ => self.parse_number(pos, 0, visitor),
                 }
             }
@@ -298,7 +2539,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                     }
                 }
             }
-            _ => Err(self.error(ErrorCode::InvalidNumber)),
+            _ => Err(self.error(Code::InvalidNumber)),
         }
     }
 
@@ -312,6 +2553,10 @@ impl<'de, R: Read<'de>> Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
+        // Too large for any integer type, and the digits after the first
+        // twenty are dropped
+        self.integer_target = false;
+
         loop {
             match try!(self.peek_or_null()) {
                 b'0'...b'9' => {
@@ -341,6 +2586,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
             b'.' => self.parse_decimal(pos, significand, 0, visitor),
             b'e' | b'E' => self.parse_exponent(pos, significand, 0, visitor),
             _ => {
+                try!(self.check_integer_tag(pos, significand));
                 if pos {
                     visitor.visit_u64(significand)
                 } else {
@@ -377,9 +2623,13 @@ impl<'de, R: Read<'de>> Deserializer<R> {
 
             if overflow!(significand * 10 + digit, u64::MAX) {
                 // The next multiply/add would overflow, so just ignore all
-                // further digits.
-                while let b'0'...b'9' = try!(self.peek_or_null()) {
+                // further digits. The number isn't an integer if any of them
+                // aren't zero.
+                while let c @ b'0'...b'9' = try!(self.peek_or_null()) {
                     self.eat_char();
+                    if c != b'0' {
+                        self.integer_target = false;
+                    }
                 }
                 break;
             }
@@ -389,7 +2639,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
         }
 
         if !at_least_one_digit {
-            return Err(self.peek_error(ErrorCode::InvalidNumber));
+            return Err(self.peek_error(Code::InvalidNumber));
         }
 
         match try!(self.peek_or_null()) {
@@ -426,7 +2676,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
         let mut exp = match try!(self.next_char_or_null()) {
             c @ b'0'...b'9' => (c - b'0') as i32,
             _ => {
-                return Err(self.error(ErrorCode::InvalidNumber));
+                return Err(self.error(Code::InvalidNumber));
             }
         };
 
@@ -466,52 +2716,111 @@ impl<'de, R: Read<'de>> Deserializer<R> {
     {
         // Error instead of +/- infinity.
         if significand != 0 && pos_exp {
-            return Err(self.error(ErrorCode::NumberOutOfRange));
+            return Err(self.error(Code::NumberOutOfRange));
         }
 
         while let b'0'...b'9' = try!(self.peek_or_null()) {
             self.eat_char();
         }
-        visitor.visit_f64(if pos { 0.0 } else { -0.0 })
+        let zero = if pos { 0.0 } else { -0.0 };
+        try!(self.check_float_tag(zero));
+        visitor.visit_f64(zero)
     }
 
     fn visit_f64_from_parts<V>(
         &mut self,
         pos: bool,
         significand: u64,
-        mut exponent: i32,
+        exponent: i32,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let mut f = significand as f64;
-        loop {
-            match POW10.get(exponent.abs() as usize) {
-                Some(&pow) => {
-                    if exponent >= 0 {
-                        f *= pow;
-                        if f.is_infinite() {
-                            return Err(self.error(ErrorCode::NumberOutOfRange));
-                        }
-                    } else {
-                        f /= pow;
-                    }
-                    break;
-                }
-                None => {
-                    if f == 0.0 {
-                        break;
-                    }
-                    if exponent >= 0 {
-                        return Err(self.error(ErrorCode::NumberOutOfRange));
-                    }
-                    f /= 1e308;
-                    exponent += 308;
+        if self.integer_target {
+            return self.visit_integral_float(pos, significand, exponent, visitor);
+        }
+
+        let f = match exponent {
+            // Both the significand and the power of ten are exact, so the
+            // one rounding is the correct one
+            0...22 if significand >> 53 == 0 => significand as f64 * POW10[exponent as usize],
+            -22...-1 if significand >> 53 == 0 => significand as f64 / POW10[-exponent as usize],
+            // Scaling by a power of ten in steps rounds more than once, which
+            // can land on a neighbouring float, so leave the rest to the
+            // standard library's correctly rounded parser
+            _ => {
+                match parse_f64_parts(significand, exponent) {
+                    Some(f) => f,
+                    None => return Err(self.error(Code::InvalidNumber)),
                 }
             }
+        };
+        if f.is_infinite() {
+            return Err(self.error(Code::NumberOutOfRange));
+        }
+        let f = if pos { f } else { -f };
+        try!(self.check_float_tag(f));
+        visitor.visit_f64(f)
+    }
+
+    /// Visits a number with a fractional part or exponent which is being
+    /// read into an integer as the integer it is, if it is one exactly.
+    fn visit_integral_float<V>(
+        &mut self,
+        pos: bool,
+        significand: u64,
+        exponent: i32,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.integer_target = false;
+
+        let integer = if exponent >= 0 {
+            10u64.checked_pow(exponent as u32).and_then(|pow| significand.checked_mul(pow))
+        } else {
+            match 10u64.checked_pow(exponent.unsigned_abs()) {
+                Some(pow) if significand / pow * pow == significand => Some(significand / pow),
+                Some(_) => None,
+                None if significand == 0 => Some(0),
+                None => None,
+            }
+        };
+
+        match integer {
+            // An f64 holds integers of up to 53 significant bits
+            Some(n) if n != 0 && n >> n.trailing_zeros() >= 1 << 53 => {
+                Err(self.error(Code::InexactInteger))
+            }
+            Some(n) if pos || n == 0 => {
+                try!(self.check_integer_tag(true, n));
+                visitor.visit_u64(n)
+            }
+            Some(n) if n <= i64::MAX as u64 + 1 => {
+                try!(self.check_integer_tag(false, n));
+                visitor.visit_i64((n as i64).wrapping_neg())
+            }
+            // A fraction, or out of range for any integer type
+            _ => self.visit_f64_from_parts(pos, significand, exponent, visitor),
+        }
+    }
+
+    /// Parses a number going into an integer with `integral_float_coercion`.
+    fn parse_integral<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match try!(self.parse_whitespace()) {
+            Some(b'-') | Some(b'0'...b'9') => {
+                self.integer_target = true;
+                let value = self.parse_value(visitor);
+                self.integer_target = false;
+                value
+            }
+            _ => self.parse_value(visitor),
         }
-        visitor.visit_f64(if pos { f } else { -f })
     }
 
     fn parse_object_colon(&mut self) -> Result<()> {
@@ -520,8 +2829,28 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                 self.eat_char();
                 Ok(())
             }
-            Some(_) => Err(self.peek_error(ErrorCode::ExpectedColon)),
-            None => Err(self.peek_error(ErrorCode::EofWhileParsingObject)),
+            Some(_) => Err(self.peek_error(Code::ExpectedColon)),
+            None => Err(self.eof_error(Code::EofWhileParsingObject)),
+        }
+    }
+
+    /// Consumes the `]` after the elements, unless reading them failed with
+    /// a syntax error. The input is then left where the error was found, for
+    /// `StreamDeserializer::skip_line` to go on from. Errors without a
+    /// position yet still take the one after the `]`.
+    fn finish_seq<T>(&mut self, elements: &Result<T>) -> Result<()> {
+        match *elements {
+            Err(ref err) if err.line() != 0 => Ok(()),
+            _ => self.end_seq(),
+        }
+    }
+
+    /// Consumes the `}` after the members, unless reading them failed with
+    /// a syntax error, as `finish_seq` does.
+    fn finish_map<T>(&mut self, members: &Result<T>) -> Result<()> {
+        match *members {
+            Err(ref err) if err.line() != 0 => Ok(()),
+            _ => self.end_map(),
         }
     }
 
@@ -531,8 +2860,19 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                 self.eat_char();
                 Ok(())
             }
-            Some(_) => Err(self.peek_error(ErrorCode::TrailingCharacters)),
-            None => Err(self.peek_error(ErrorCode::EofWhileParsingList)),
+            Some(_) => Err(self.peek_error(Code::TrailingCharacters)),
+            None => Err(self.eof_error(Code::EofWhileParsingList)),
+        }
+    }
+
+    fn end_variant(&mut self) -> Result<()> {
+        match try!(self.parse_whitespace()) {
+            Some(b'}') => {
+                self.eat_char();
+                Ok(())
+            }
+            Some(_) => Err(self.error(Code::ExpectedSomeValue)),
+            None => Err(self.eof_error(Code::EofWhileParsingObject)),
         }
     }
 
@@ -542,54 +2882,62 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                 self.eat_char();
                 Ok(())
             }
-            Some(_) => Err(self.peek_error(ErrorCode::TrailingCharacters)),
-            None => Err(self.peek_error(ErrorCode::EofWhileParsingObject)),
+            Some(_) => Err(self.peek_error(Code::TrailingCharacters)),
+            None => Err(self.eof_error(Code::EofWhileParsingObject)),
         }
     }
 }
 
+/// The powers of ten a float holds exactly
 #[cfg_attr(rustfmt, rustfmt_skip)]
-static POW10: [f64; 309] =
-    [1e000, 1e001, 1e002, 1e003, 1e004, 1e005, 1e006, 1e007, 1e008, 1e009,
-     1e010, 1e011, 1e012, 1e013, 1e014, 1e015, 1e016, 1e017, 1e018, 1e019,
-     1e020, 1e021, 1e022, 1e023, 1e024, 1e025, 1e026, 1e027, 1e028, 1e029,
-     1e030, 1e031, 1e032, 1e033, 1e034, 1e035, 1e036, 1e037, 1e038, 1e039,
-     1e040, 1e041, 1e042, 1e043, 1e044, 1e045, 1e046, 1e047, 1e048, 1e049,
-     1e050, 1e051, 1e052, 1e053, 1e054, 1e055, 1e056, 1e057, 1e058, 1e059,
-     1e060, 1e061, 1e062, 1e063, 1e064, 1e065, 1e066, 1e067, 1e068, 1e069,
-     1e070, 1e071, 1e072, 1e073, 1e074, 1e075, 1e076, 1e077, 1e078, 1e079,
-     1e080, 1e081, 1e082, 1e083, 1e084, 1e085, 1e086, 1e087, 1e088, 1e089,
-     1e090, 1e091, 1e092, 1e093, 1e094, 1e095, 1e096, 1e097, 1e098, 1e099,
-     1e100, 1e101, 1e102, 1e103, 1e104, 1e105, 1e106, 1e107, 1e108, 1e109,
-     1e110, 1e111, 1e112, 1e113, 1e114, 1e115, 1e116, 1e117, 1e118, 1e119,
-     1e120, 1e121, 1e122, 1e123, 1e124, 1e125, 1e126, 1e127, 1e128, 1e129,
-     1e130, 1e131, 1e132, 1e133, 1e134, 1e135, 1e136, 1e137, 1e138, 1e139,
-     1e140, 1e141, 1e142, 1e143, 1e144, 1e145, 1e146, 1e147, 1e148, 1e149,
-     1e150, 1e151, 1e152, 1e153, 1e154, 1e155, 1e156, 1e157, 1e158, 1e159,
-     1e160, 1e161, 1e162, 1e163, 1e164, 1e165, 1e166, 1e167, 1e168, 1e169,
-     1e170, 1e171, 1e172, 1e173, 1e174, 1e175, 1e176, 1e177, 1e178, 1e179,
-     1e180, 1e181, 1e182, 1e183, 1e184, 1e185, 1e186, 1e187, 1e188, 1e189,
-     1e190, 1e191, 1e192, 1e193, 1e194, 1e195, 1e196, 1e197, 1e198, 1e199,
-     1e200, 1e201, 1e202, 1e203, 1e204, 1e205, 1e206, 1e207, 1e208, 1e209,
-     1e210, 1e211, 1e212, 1e213, 1e214, 1e215, 1e216, 1e217, 1e218, 1e219,
-     1e220, 1e221, 1e222, 1e223, 1e224, 1e225, 1e226, 1e227, 1e228, 1e229,
-     1e230, 1e231, 1e232, 1e233, 1e234, 1e235, 1e236, 1e237, 1e238, 1e239,
-     1e240, 1e241, 1e242, 1e243, 1e244, 1e245, 1e246, 1e247, 1e248, 1e249,
-     1e250, 1e251, 1e252, 1e253, 1e254, 1e255, 1e256, 1e257, 1e258, 1e259,
-     1e260, 1e261, 1e262, 1e263, 1e264, 1e265, 1e266, 1e267, 1e268, 1e269,
-     1e270, 1e271, 1e272, 1e273, 1e274, 1e275, 1e276, 1e277, 1e278, 1e279,
-     1e280, 1e281, 1e282, 1e283, 1e284, 1e285, 1e286, 1e287, 1e288, 1e289,
-     1e290, 1e291, 1e292, 1e293, 1e294, 1e295, 1e296, 1e297, 1e298, 1e299,
-     1e300, 1e301, 1e302, 1e303, 1e304, 1e305, 1e306, 1e307, 1e308];
+static POW10: [f64; 23] =
+    [1e00, 1e01, 1e02, 1e03, 1e04, 1e05, 1e06, 1e07, 1e08, 1e09,
+     1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16, 1e17, 1e18, 1e19,
+     1e20, 1e21, 1e22];
+
+/// Parses integers as any other value, except for taking integral floating
+/// point numbers with `integral_float_coercion`.
+macro_rules! deserialize_integer {
+    ($deserialize:ident, $ty:ident) => {
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: de::Visitor<'de>,
+        {
+            let target = IntegerType::of::<$ty>(stringify!($ty));
+            try!(self.check_signedness(target));
+            self.integer_type = Some(target);
+            let value = if self.options.integral_float_coercion {
+                self.parse_integral(visitor)
+            } else {
+                self.parse_value(visitor)
+            };
+            self.integer_type = None;
+            value
+        }
+    }
+}
 
 impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     type Error = Error;
 
     #[inline]
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse_value(visitor)
+    }
+
+    /// Parses an array as `[key, value]` pairs if `map_as_pairs` is set, and
+    /// anything else as any other value.
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if self.options.map_as_pairs && try!(self.parse_whitespace()) == Some(b'[') {
+            return self.parse_pairs(visitor);
+        }
         self.parse_value(visitor)
     }
 
@@ -599,6 +2947,7 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
+        try!(self.check_root());
         match try!(self.parse_whitespace()) {
             Some(b'n') => {
                 self.eat_char();
@@ -611,10 +2960,20 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
 
     /// Parses a newtype struct as the underlying value.
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        #[cfg(feature = "decimal")]
+        {
+            if name == ::number::DECIMAL_TOKEN {
+                return self.parse_decimal_text(visitor);
+            }
+        }
+        if name == SET_TOKEN {
+            return self.parse_set(visitor);
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
@@ -632,28 +2991,20 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     {
         match try!(self.parse_whitespace()) {
             Some(b'{') => {
-                self.remaining_depth -= 1;
-                if self.remaining_depth == 0 {
-                    return Err(self.peek_error(ErrorCode::RecursionLimitExceeded));
-                }
+                try!(self.open_container(true));
+                let value = visitor.visit_enum(VariantAccess::new(self));
 
-                self.eat_char();
-                let value = try!(visitor.visit_enum(VariantAccess::new(self)));
-
-                self.remaining_depth += 1;
+                let end = if value.is_ok() { self.end_variant() } else { Ok(()) };
+                self.close_container();
 
-                match try!(self.parse_whitespace()) {
-                    Some(b'}') => {
-                        self.eat_char();
-                        Ok(value)
-                    }
-                    Some(_) => Err(self.error(ErrorCode::ExpectedSomeValue)),
-                    None => Err(self.error(ErrorCode::EofWhileParsingObject)),
+                match (value, end) {
+                    (Ok(value), Ok(())) => Ok(value),
+                    (Err(err), _) | (_, Err(err)) => Err(err),
                 }
             }
             Some(b'"') => visitor.visit_enum(UnitVariantAccess::new(self)),
-            Some(_) => Err(self.peek_error(ErrorCode::ExpectedSomeValue)),
-            None => Err(self.peek_error(ErrorCode::EofWhileParsingValue)),
+            Some(_) => Err(self.peek_error(Code::ExpectedSomeValue)),
+            None => Err(self.eof_error(Code::EofWhileParsingValue)),
         }
     }
 
@@ -718,6 +3069,11 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     /// to be valid, and `\u` escape sequences are required to represent valid
     /// Unicode code points.
     ///
+    /// The value of a member tagged `d`, `d16`, `d32` or `d64`, or an element
+    /// of one tagged `A<d>` and so on, is data rather than a string, and is
+    /// decoded for its tag instead. Text which isn't validly encoded is an
+    /// error.
+    ///
     /// ```rust
     /// extern crate tjson;
     /// extern crate serde_bytes;
@@ -744,11 +3100,23 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     {
         match try!(self.parse_whitespace()) {
             Some(b'"') => {
+                if let Some(tag) = self.data_tag() {
+                    let bytes = try!(self.parse_data(&tag));
+                    return visitor.visit_byte_buf(bytes);
+                }
+
+                let start = self.read.peek_mark();
                 self.eat_char();
                 self.str_buf.clear();
-                match try!(self.read.parse_str_raw(&mut self.str_buf)) {
-                    Reference::Borrowed(b) => visitor.visit_borrowed_bytes(b),
-                    Reference::Copied(b) => visitor.visit_bytes(b),
+                match self.read.parse_str_raw(&mut self.str_buf) {
+                    Ok(b) => {
+                        self.counts.longest_string = cmp::max(self.counts.longest_string, b.len());
+                        match b {
+                            Reference::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+                            Reference::Copied(b) => visitor.visit_bytes(b),
+                        }
+                    }
+                    Err(err) => Err(self.string_error(err, start)),
                 }
             }
             _ => self.deserialize_any(visitor),
@@ -764,15 +3132,116 @@ impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
         self.deserialize_bytes(visitor)
     }
 
+    /// Parses a sequence as any other value, except that data written as a
+    /// string is handed to the visitor as a sequence of its decoded bytes, so
+    /// that a `Vec<u8>` can be read from any encoding of it.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if try!(self.parse_whitespace()) == Some(b'"') {
+            if let Some(tag) = self.data_tag() {
+                let bytes = try!(self.parse_data(&tag));
+                let mut seq = SeqDeserializer::new(bytes.into_iter());
+                let value: Result<V::Value> = visitor.visit_seq(&mut seq).and_then(|value| {
+                    try!(seq.end());
+                    Ok(value)
+                });
+                return value.map_err(|err| err.fix_position(|code| self.error(code)));
+            }
+        }
+
+        self.deserialize_any(visitor)
+    }
+
+    /// Parses a struct like any other object, except that a struct's fields
+    /// can't be collected into arrays, so `DuplicateKeys::Collect` rejects
+    /// duplicates as `DuplicateKeys::Error` does.
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let normalization = self.options.member_name_normalization;
+        if self.options.duplicate_keys == DuplicateKeys::Collect ||
+            normalization != Normalization::Exact
+        {
+            if let Some(b'{') = try!(self.parse_whitespace()) {
+                let matcher = match normalization {
+                    Normalization::Exact => None,
+                    _ => {
+                        Some(
+                            match self.spare_matchers.pop() {
+                                Some(matcher) => matcher.reuse(fields, normalization),
+                                None => FieldMatcher::new(fields, normalization),
+                            },
+                        )
+                    }
+                };
+                let value = match self.options.duplicate_keys {
+                    DuplicateKeys::FirstWins | DuplicateKeys::LastWins => {
+                        self.parse_resolved_struct(visitor, fields, normalization)
+                    }
+                    DuplicateKeys::Error | DuplicateKeys::Collect => {
+                        self.parse_struct(visitor, true, matcher)
+                    }
+                };
+                return value.map_err(|err| err.fix_position(|code| self.error(code)));
+            }
+        }
+
+        self.parse_value(visitor)
+    }
+
+    /// Parses a tuple from an array by position, or from an object by the
+    /// configured field order.
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let value = match try!(self.parse_whitespace()) {
+            Some(b'[') => self.parse_tuple_array(len, visitor),
+            Some(b'{') => self.parse_tuple_object(len, visitor),
+            _ => return self.parse_value(visitor),
+        };
+        value.map_err(|err| err.fix_position(|code| self.error(code)))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    deserialize_integer!(deserialize_i8, i8);
+    deserialize_integer!(deserialize_i16, i16);
+    deserialize_integer!(deserialize_i32, i32);
+    deserialize_integer!(deserialize_i64, i64);
+    deserialize_integer!(deserialize_u8, u8);
+    deserialize_integer!(deserialize_u16, u16);
+    deserialize_integer!(deserialize_u32, u32);
+    deserialize_integer!(deserialize_u64, u64);
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string unit
-        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+        bool f32 f64 char str string unit unit_struct identifier ignored_any
     }
 }
 
 struct SeqAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     first: bool,
+    /// Index of the next element.
+    index: usize,
 }
 
 impl<'a, R: 'a> SeqAccess<'a, R> {
@@ -780,7 +3249,41 @@ impl<'a, R: 'a> SeqAccess<'a, R> {
         SeqAccess {
             de: de,
             first: true,
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> SeqAccess<'a, R> {
+    /// Steps past the comma before the next element, if there is one, and
+    /// readies the deserializer to read it.
+    fn has_next_element(&mut self) -> Result<bool> {
+        match try!(self.de.parse_whitespace()) {
+            Some(b']') => {
+                return Ok(false);
+            }
+            Some(b',') if !self.first => {
+                self.de.eat_char();
+            }
+            Some(_) => {
+                if self.first {
+                    self.first = false;
+                } else {
+                    return Err(self.de.peek_error(Code::ExpectedListCommaOrEnd));
+                }
+            }
+            None => {
+                return Err(self.de.eof_error(Code::EofWhileParsingList));
+            }
+        }
+
+        self.de.counts.elements += 1;
+        if let Some(open) = self.de.open.last_mut() {
+            open.index = self.index;
         }
+        self.index += 1;
+        try!(self.de.check_value_kind());
+        Ok(true)
     }
 }
 
@@ -791,89 +3294,620 @@ impl<'de, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SeqAccess<'a, R> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        match try!(self.de.parse_whitespace()) {
-            Some(b']') => {
+        if !try!(self.has_next_element()) {
+            return Ok(None);
+        }
+        let value = try!(seed.deserialize(&mut *self.de));
+        Ok(Some(value))
+    }
+}
+
+/// Reads the elements of an array tagged `S<..>`, or of one being read as a
+/// set, which must differ from each other.
+///
+/// Scalars under a scalar tag other than data are handed on as they are read,
+/// and only the value the deserializer gave for them is kept to compare
+/// later ones with. Anything else, or any element when repeated ones are
+/// merged, is read as a `Value` before being handed on, so that an element
+/// equal to an earlier one can be handled by the `duplicate_elements` policy
+/// whatever the array is being deserialized into. Elements are read as their
+/// tag says, so those of a set of sets are compared as sets.
+struct SetAccess<'a, R: 'a> {
+    seq: SeqAccess<'a, R>,
+    elements: SeenElements,
+    /// Offset into the input of the element being read.
+    start: usize,
+}
+
+/// The elements of a set read so far, as the `SetUniqueness` says to keep
+/// them.
+enum SeenElements {
+    Exact(Set<Value>),
+    /// The offset into the input of each element by its hash, with the keys
+    /// of the two halves of the hash.
+    Hashed(HashMap<u128, usize>, RandomState, RandomState),
+}
+
+impl<'a, R: 'a> SetAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        let elements = match de.options.set_uniqueness {
+            SetUniqueness::Exact => SeenElements::Exact(Set::new()),
+            SetUniqueness::Hashed => {
+                SeenElements::Hashed(HashMap::new(), RandomState::new(), RandomState::new())
+            }
+        };
+        SetAccess {
+            seq: SeqAccess::new(de),
+            elements: elements,
+            start: 0,
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> SetAccess<'a, R> {
+    /// Whether the element about to be read can be handed on without
+    /// reading it as a `Value` first.
+    fn is_scalar_element(&mut self) -> Result<bool> {
+        if self.seq.de.options.duplicate_elements != DuplicateElements::Error {
+            return Ok(false);
+        }
+        let scalar_tag = match self.seq.de.value_tag().and_then(Tag::parse) {
+            Some(Tag::Data(_)) | None => false,
+            Some(tag) => tag.is_scalar(),
+        };
+        Ok(scalar_tag && match try!(self.seq.de.parse_whitespace()) {
+            Some(b'[') | Some(b'{') => false,
+            _ => true,
+        })
+    }
+
+    /// Reads an element as its tag says, so that those of a set of sets are
+    /// sets.
+    fn read(&self, element: Value) -> Value {
+        match self.seq.de.value_tag() {
+            Some(tag) => ::value::read_tagged(tag, element),
+            None => element,
+        }
+    }
+
+    /// Notes where the element about to be read starts in the input.
+    fn mark_start(&mut self) -> Result<()> {
+        try!(self.seq.de.parse_whitespace());
+        self.start = self.seq.de.read.byte_offset();
+        Ok(())
+    }
+
+    /// Keeps an element, unless it equals an earlier one, in which case that
+    /// is handled by the `duplicate_elements` policy.
+    fn insert(&mut self, element: Value) -> Result<bool> {
+        let element = set_element(element);
+        match self.elements {
+            SeenElements::Exact(ref mut elements) => {
+                if !elements.contains(&element) {
+                    elements.insert(element);
+                    return Ok(true);
+                }
+            }
+            SeenElements::Hashed(..) => {
+                if try!(self.insert_hash(&element)) {
+                    return Ok(true);
+                }
+            }
+        }
+        if self.seq.de.options.duplicate_elements == DuplicateElements::Error {
+            let code = duplicate_element(&self.seq.de.open, self.seq.index - 1, &element);
+            return Err(self.seq.de.error(code));
+        }
+        Ok(false)
+    }
+
+    /// Keeps the hash of an element under `SetUniqueness::Hashed`, unless an
+    /// earlier element has the same hash and is equal to it. An earlier one
+    /// which only has the same hash moves this one on to the next hash.
+    fn insert_hash(&mut self, element: &Value) -> Result<bool> {
+        let mut hash = match self.elements {
+            SeenElements::Hashed(_, ref high, ref low) => {
+                let bits = self.seq.de.options.set_hash_bits;
+                let mask = if bits >= 128 { !0 } else { (1u128 << bits) - 1 };
+                element_hash(element, high, low) & mask
+            }
+            SeenElements::Exact(_) => return Ok(true),
+        };
+        loop {
+            let earlier = match self.elements {
+                SeenElements::Hashed(ref mut hashes, ..) => {
+                    match hashes.get(&hash) {
+                        Some(&earlier) => earlier,
+                        None => {
+                            hashes.insert(hash, self.start);
+                            return Ok(true);
+                        }
+                    }
+                }
+                SeenElements::Exact(_) => return Ok(true),
+            };
+            match self.equals_earlier(earlier, element) {
+                Some(false) => hash = hash.wrapping_add(1),
+                Some(true) => return Ok(false),
+                None => {
+                    let code = unresolved_collision(&self.seq.de.open, self.seq.index - 1);
+                    return Err(self.seq.de.error(code));
+                }
+            }
+        }
+    }
+
+    /// Whether the element just read equals the earlier one at this offset
+    /// into the input, if that can be told by reading the earlier one again.
+    fn equals_earlier(&self, offset: usize, element: &Value) -> Option<bool> {
+        let earlier = match self.reread(offset) {
+            Some(earlier) => earlier,
+            None => return None,
+        };
+        if self.seq.de.options.transforms.is_empty() {
+            return Some(earlier == *element);
+        }
+        // The earlier element was read again without its transforms, so it
+        // can only be compared with this one as written
+        match self.reread(self.start) {
+            Some(ref current) if *current == earlier => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Reads the element at this offset into the input again, if the input
+    /// is all at hand, without running any transform hooks on it.
+    fn reread(&self, offset: usize) -> Option<Value> {
+        let input = match self.seq.de.read.input_from(offset) {
+            Some(input) => input,
+            None => return None,
+        };
+        let mut options = self.seq.de.options.clone();
+        options.root_tag = self.seq.de.value_tag().map(str::to_owned);
+        options.transforms = Arc::new(Transforms::default());
+        let mut de = Deserializer::with_scratch(SliceRead::new(input), Scratch::new(), options);
+        match de::Deserialize::deserialize(&mut de) {
+            Ok(element) => Some(set_element(self.read(element))),
+            Err(_) => None,
+        }
+    }
+}
+
+/// A 128-bit hash of a set element, from two hashers keyed differently.
+fn element_hash(element: &Value, high: &RandomState, low: &RandomState) -> u128 {
+    let mut high = high.build_hasher();
+    let mut low = low.build_hasher();
+    element.hash_canonical(&mut high);
+    element.hash_canonical(&mut low);
+    (high.finish() as u128) << 64 | low.finish() as u128
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SetAccess<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if !try!(self.seq.has_next_element()) {
+            return Ok(None);
+        }
+        try!(self.mark_start());
+
+        if try!(self.is_scalar_element()) {
+            let mut scalar = None;
+            let value = try!(seed.deserialize(Recorder::new(&mut *self.seq.de, &mut scalar)));
+            if let Some(scalar) = scalar {
+                let element = self.read(scalar);
+                try!(self.insert(element));
+            }
+            return Ok(Some(value));
+        }
+
+        loop {
+            let element: Value = try!(de::Deserialize::deserialize(&mut *self.seq.de));
+            let element = self.read(element);
+            if try!(self.insert(element.clone())) {
+                let options = &self.seq.de.options;
+                let (normalization, policy) =
+                    (options.member_name_normalization, options.duplicate_elements);
+                let element = NormalizedValue::new(element, normalization, policy);
+                return seed.deserialize(element).map(Some);
+            }
+            if !try!(self.seq.has_next_element()) {
+                return Ok(None);
+            }
+            try!(self.mark_start());
+        }
+    }
+}
+
+/// Deserializes through another deserializer, keeping the scalar its
+/// visitor is given as the `Value` it would be.
+struct Recorder<'r, T> {
+    inner: T,
+    scalar: &'r mut Option<Value>,
+}
+
+impl<'r, T> Recorder<'r, T> {
+    fn new(inner: T, scalar: &'r mut Option<Value>) -> Self {
+        Recorder {
+            inner: inner,
+            scalar: scalar,
+        }
+    }
+}
+
+macro_rules! forward_recorded {
+    ($($deserialize:ident ($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $deserialize<V>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> result::Result<V::Value, D::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.inner.$deserialize($($arg,)* Recorder::new(visitor, self.scalar))
+            }
+        )*
+    }
+}
+
+impl<'de, 'r, D> de::Deserializer<'de> for Recorder<'r, D>
+where
+    D: de::Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_recorded! {
+        deserialize_any()
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_option()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_seq()
+        deserialize_tuple(len: usize)
+        deserialize_tuple_struct(name: &'static str, len: usize)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+        deserialize_ignored_any()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'de, 'r, V> de::Visitor<'de> for Recorder<'r, V>
+where
+    V: de::Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::Bool(value));
+        self.inner.visit_bool(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::Number(value.into()));
+        self.inner.visit_i64(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::Number(value.into()));
+        self.inner.visit_u64(value)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> result::Result<V::Value, E> {
+        *self.scalar = Number::from_f64(value).map(Value::Number);
+        self.inner.visit_f64(value)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::String(value.to_owned()));
+        self.inner.visit_str(value)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::String(value.to_owned()));
+        self.inner.visit_borrowed_str(value)
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::String(value.clone()));
+        self.inner.visit_string(value)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> result::Result<V::Value, E> {
+        self.inner.visit_bytes(value)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, value: &'de [u8]) -> result::Result<V::Value, E> {
+        self.inner.visit_borrowed_bytes(value)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> result::Result<V::Value, E> {
+        self.inner.visit_byte_buf(value)
+    }
+
+    fn visit_none<E: de::Error>(self) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::Undefined);
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> result::Result<V::Value, E> {
+        *self.scalar = Some(Value::Undefined);
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> result::Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.inner.visit_some(Recorder::new(deserializer, self.scalar))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> result::Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.inner.visit_newtype_struct(Recorder::new(deserializer, self.scalar))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> result::Result<V::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A>(self, map: A) -> result::Result<V::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A>(self, data: A) -> result::Result<V::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.inner.visit_enum(data)
+    }
+}
+
+struct MapAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    first: bool,
+    check_duplicates: bool,
+    /// Set when reading a struct with `member_name_normalization`.
+    fields: Option<FieldMatcher>,
+}
+
+impl<'a, R: 'a> MapAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, check_duplicates: bool) -> Self {
+        MapAccess {
+            de: de,
+            first: true,
+            check_duplicates: check_duplicates,
+            fields: None,
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapAccess<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if let Some(open) = self.de.open.last_mut() {
+            open.has_member = false;
+        }
+
+        let peek = match try!(self.de.parse_whitespace()) {
+            Some(b'}') => {
                 return Ok(None);
             }
             Some(b',') if !self.first => {
                 self.de.eat_char();
+                try!(self.de.parse_whitespace())
             }
-            Some(_) => {
+            Some(b) => {
                 if self.first {
                     self.first = false;
+                    Some(b)
                 } else {
-                    return Err(self.de.peek_error(ErrorCode::ExpectedListCommaOrEnd));
+                    return Err(self.de.peek_error(Code::ExpectedObjectCommaOrEnd));
                 }
             }
             None => {
-                return Err(self.de.peek_error(ErrorCode::EofWhileParsingList));
+                return Err(self.de.eof_error(Code::EofWhileParsingObject));
+            }
+        };
+
+        match peek {
+            Some(b'"') => {
+                self.de.counts.members += 1;
+                let key = match self.fields {
+                    Some(ref mut fields) => try!(self.de.parse_field_name(seed, fields)),
+                    None => try!(seed.deserialize(MapKey { de: &mut *self.de })),
+                };
+                self.de.warn_member();
+                if self.check_duplicates {
+                    try!(self.de.check_duplicate_member());
+                }
+                Ok(Some(key))
             }
+            Some(_) => Err(self.de.peek_error(Code::KeyMustBeAString)),
+            None => Err(self.de.eof_error(Code::EofWhileParsingValue)),
         }
+    }
 
-        let value = try!(seed.deserialize(&mut *self.de));
-        Ok(Some(value))
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        try!(self.de.parse_object_colon());
+        try!(self.de.check_value_kind());
+
+        seed.deserialize(&mut *self.de)
     }
 }
 
-struct MapAccess<'a, R: 'a> {
+/// Reads a map from an array of `[key, value]` pairs.
+struct PairAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     first: bool,
+    /// Index of the pair being read.
+    index: usize,
+    keys: HashSet<Value>,
 }
 
-impl<'a, R: 'a> MapAccess<'a, R> {
+impl<'a, R: 'a> PairAccess<'a, R> {
     fn new(de: &'a mut Deserializer<R>) -> Self {
-        MapAccess {
+        PairAccess {
             de: de,
             first: true,
+            index: 0,
+            keys: HashSet::new(),
         }
     }
 }
 
-impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapAccess<'a, R> {
+impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for PairAccess<'a, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: de::DeserializeSeed<'de>,
     {
-        let peek = match try!(self.de.parse_whitespace()) {
-            Some(b'}') => {
+        match try!(self.de.parse_whitespace()) {
+            Some(b']') => {
                 return Ok(None);
             }
             Some(b',') if !self.first => {
                 self.de.eat_char();
-                try!(self.de.parse_whitespace())
+                self.index += 1;
             }
-            Some(b) => {
+            Some(_) => {
                 if self.first {
                     self.first = false;
-                    Some(b)
                 } else {
-                    return Err(self.de.peek_error(ErrorCode::ExpectedObjectCommaOrEnd));
+                    return Err(self.de.peek_error(Code::ExpectedListCommaOrEnd));
                 }
             }
             None => {
-                return Err(self.de.peek_error(ErrorCode::EofWhileParsingObject));
+                return Err(self.de.eof_error(Code::EofWhileParsingList));
             }
-        };
+        }
 
-        match peek {
-            Some(b'"') => seed.deserialize(MapKey { de: &mut *self.de }).map(Some),
-            Some(_) => Err(self.de.peek_error(ErrorCode::KeyMustBeAString)),
-            None => Err(self.de.peek_error(ErrorCode::EofWhileParsingValue)),
+        match try!(self.de.parse_whitespace()) {
+            Some(b'[') => {}
+            Some(_) => return Err(self.de.peek_error(expected_pair())),
+            None => return Err(self.de.eof_error(Code::EofWhileParsingValue)),
+        }
+        self.de.counts.elements += 1;
+        if let Some(open) = self.de.open.last_mut() {
+            open.index = self.index;
         }
+        try!(self.de.open_container(false));
+
+        // The pair stays open until its value has been read
+        let key = self.pair_key(seed);
+        if key.is_err() {
+            self.de.close_container();
+        }
+        key.map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        try!(self.de.parse_object_colon());
+        let value = self.pair_value(seed);
+        self.de.close_container();
+        value
+    }
+}
 
-        seed.deserialize(&mut *self.de)
+impl<'de, 'a, R: Read<'de> + 'a> PairAccess<'a, R> {
+    fn pair_key<K>(&mut self, seed: K) -> Result<K::Value>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if try!(self.de.parse_whitespace()) == Some(b']') {
+            return Err(self.de.peek_error(expected_pair()));
+        }
+        self.de.counts.elements += 1;
+        let key: Value = try!(de::Deserialize::deserialize(&mut *self.de));
+        if self.keys.contains(&key) {
+            return Err(self.de.error(Code::DuplicatePairKey(self.index)));
+        }
+        self.keys.insert(key.clone());
+
+        seed.deserialize(key).map_err(|err| err.fix_position(|code| self.de.error(code)))
+    }
+
+    fn pair_value<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match try!(self.de.parse_whitespace()) {
+            Some(b',') => self.de.eat_char(),
+            Some(b']') => return Err(self.de.peek_error(expected_pair())),
+            Some(_) => return Err(self.de.peek_error(Code::ExpectedListCommaOrEnd)),
+            None => return Err(self.de.eof_error(Code::EofWhileParsingList)),
+        }
+
+        self.de.counts.elements += 1;
+        if let Some(open) = self.de.open.last_mut() {
+            open.index = 1;
+        }
+        let value = try!(seed.deserialize(&mut *self.de));
+
+        match try!(self.de.parse_whitespace()) {
+            Some(b']') => {
+                self.de.eat_char();
+                Ok(value)
+            }
+            Some(b',') => Err(self.de.peek_error(expected_pair())),
+            Some(_) => Err(self.de.peek_error(Code::ExpectedListCommaOrEnd)),
+            None => Err(self.de.eof_error(Code::EofWhileParsingList)),
+        }
     }
 }
 
+fn expected_pair() -> Code {
+    Code::Message("expected an array of a key and a value".to_owned())
+}
+
 struct VariantAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
 }
@@ -892,7 +3926,12 @@ impl<'de, 'a, R: Read<'de> + 'a> de::EnumAccess<'de> for VariantAccess<'a, R> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let val = try!(seed.deserialize(&mut *self.de));
+        // Variant names go through MapKey so that the name is reported if the
+        // input ends before the variant's value.
+        let val = match try!(self.de.parse_whitespace()) {
+            Some(b'"') => try!(seed.deserialize(MapKey { de: &mut *self.de })),
+            _ => try!(seed.deserialize(&mut *self.de)),
+        };
         try!(self.de.parse_object_colon());
         Ok((val, self))
     }
@@ -991,9 +4030,16 @@ macro_rules! deserialize_integer_key {
         where
             V: de::Visitor<'de>,
         {
+            let start = self.de.read.peek_mark();
             self.de.eat_char();
             self.de.str_buf.clear();
-            let string = try!(self.de.read.parse_str(&mut self.de.str_buf));
+            let string = match self.de.read.parse_str(&mut self.de.str_buf) {
+                Ok(string) => string,
+                Err(err) => return Err(self.de.string_error(err, start)),
+            };
+            if let Some(open) = self.de.open.last_mut() {
+                open.set_member(&string);
+            }
             match (string.parse(), string) {
                 (Ok(integer), _) => visitor.$visit(integer),
                 (Err(_), Reference::Borrowed(s)) => visitor.visit_borrowed_str(s),
@@ -1014,7 +4060,7 @@ where
     where
         V: de::Visitor<'de>,
     {
-        self.de.parse_value(visitor)
+        self.de.parse_member_name(visitor)
     }
 
     deserialize_integer_key!(deserialize_i8 => visit_i8);
@@ -1087,7 +4133,8 @@ where
 ///
 /// The data must consist of JSON arrays and JSON objects optionally separated
 /// by whitespace. A null, boolean, number, or string at the top level are all
-/// errors.
+/// errors. For documents a line each, `skip_line` goes on past a line which
+/// fails to parse.
 ///
 /// ```rust
 /// extern crate tjson;
@@ -1107,6 +4154,9 @@ where
 pub struct StreamDeserializer<'de, R, T> {
     de: Deserializer<R>,
     offset: usize,
+    /// Whether the last error was found on a newline, which has been read
+    /// already, so that `skip_line` has nothing left of the line to skip.
+    failed_on_newline: bool,
     output: PhantomData<T>,
     lifetime: PhantomData<&'de ()>,
 }
@@ -1129,6 +4179,7 @@ where
         StreamDeserializer {
             de: Deserializer::new(read),
             offset: offset,
+            failed_on_newline: false,
             output: PhantomData,
             lifetime: PhantomData,
         }
@@ -1168,16 +4219,51 @@ where
     pub fn byte_offset(&self) -> usize {
         self.offset
     }
-}
 
-impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
-where
-    R: Read<'de>,
-    T: de::Deserialize<'de>,
-{
-    type Item = Result<T>;
+    /// Discards the rest of the line the last error was found on, so that the
+    /// stream can go on with the next line's document.
+    ///
+    /// After an error the stream is stuck where the error was found, and
+    /// yields errors until this is called. If the error was found on a
+    /// newline, as with an unterminated string, nothing is discarded.
+    ///
+    /// ```rust
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::{Deserializer, Value};
+    ///
+    /// let data = "{\"a:i\":\"1\"}\n{\"b:i\" 2}\n[\"unterminated\n[]\n";
+    /// let mut stream = Deserializer::from_reader(data.as_bytes()).into_iter::<Value>();
+    ///
+    /// assert!(stream.next().unwrap().is_ok());
+    /// assert!(stream.next().unwrap().is_err());
+    /// stream.skip_line().unwrap();
+    /// assert!(stream.next().unwrap().is_err());
+    /// stream.skip_line().unwrap();
+    /// assert!(stream.next().unwrap().is_ok());
+    /// assert!(stream.next().is_none());
+    /// # }
+    /// ```
+    pub fn skip_line(&mut self) -> Result<()> {
+        while !self.de.open.is_empty() {
+            self.de.close_container();
+        }
 
-    fn next(&mut self) -> Option<Result<T>> {
+        if !self.failed_on_newline {
+            loop {
+                match try!(self.de.next_char()) {
+                    Some(b'\n') | None => break,
+                    Some(_) => {}
+                }
+            }
+        }
+        self.failed_on_newline = false;
+        self.offset = self.de.read.byte_offset();
+        Ok(())
+    }
+
+    fn parse_next(&mut self) -> Option<Result<T>> {
         // skip whitespaces, if any
         // this helps with trailing whitespaces, since whitespaces between
         // values are handled for us.
@@ -1192,11 +4278,512 @@ where
                 if result.is_ok() {
                     self.offset = self.de.read.byte_offset();
                 }
-                Some(result)
+                Some(result)
+            }
+            Ok(Some(_)) => Some(Err(self.de.peek_error(Code::ExpectedObjectOrArray))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        let result = self.parse_next();
+        if let Some(Err(ref err)) = result {
+            self.failed_on_newline = err.line() > 1 && err.column() == 0;
+        }
+        result
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// Iterator over the events of a single TJSON document, as returned by
+/// `tjson::event::parse`.
+///
+/// The document is parsed as the events are taken, so errors are reported
+/// when the iterator reaches them: every event before the error is still
+/// produced, followed by the error, after which the iterator is finished.
+pub struct Events<'de, R> {
+    de: Deserializer<R>,
+    expect: Expect,
+    /// Whether the innermost array or object has had no elements or members
+    /// yet.
+    first: bool,
+    /// The largest `DataChunk` to produce, with `chunked_data`.
+    chunk_size: Option<usize>,
+    /// The data being read a chunk at a time, while expecting `Data`.
+    data: Option<ChunkedData>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+/// A string of data partway through being read by `Events`.
+struct ChunkedData {
+    decoder: Decoder,
+    /// Bytes decoded but not yet produced.
+    decoded: Vec<u8>,
+    /// Whether the closing quotation mark has been read.
+    ended: bool,
+    /// Where the string started, for reporting it unterminated.
+    start: Mark,
+}
+
+/// What the input holds next, given what `Events` has produced so far.
+#[derive(Copy, Clone, PartialEq)]
+enum Expect {
+    Value,
+    ArrayElement,
+    ObjectMember,
+    Data,
+    End,
+    Done,
+}
+
+impl<'de, R> Events<'de, R>
+where
+    R: Read<'de>,
+{
+    /// Create an event iterator over one of the possible tjson input
+    /// sources.
+    pub fn new(read: R) -> Self {
+        Events {
+            // Data is a `Str` of its encoding unless it is chunked
+            de: Deserializer::new(read).decode_data(false),
+            expect: Expect::Value,
+            first: false,
+            chunk_size: None,
+            data: None,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Produce data as `DataStart`, `DataChunk`s of at most `chunk_size`
+    /// bytes, and `DataEnd`, rather than as a `Str` of its encoding.
+    ///
+    /// Data is any string tagged `d`, `d64`, `d32` or `d16`, or an element of
+    /// arrays and sets of them. It is decoded as it is read, so that only a
+    /// chunk of it is held in memory at a time, and invalid data is an error
+    /// in place of the chunk it would have been part of, giving the offset
+    /// into the string of the first invalid character. Escapes aren't
+    /// decoded, since no encoding's alphabet needs them, so a backslash in
+    /// data is invalid like any other character outside the alphabet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn chunked_data(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be at least 1");
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    fn step(&mut self) -> Result<Option<Event<'de>>> {
+        match self.expect {
+            Expect::Value => self.value().map(Some),
+            Expect::ArrayElement => {
+                match try!(self.de.parse_whitespace()) {
+                    Some(b']') => {
+                        self.de.eat_char();
+                        Ok(Some(self.close(Event::ArrayEnd)))
+                    }
+                    Some(b',') if !self.first => {
+                        self.de.eat_char();
+                        self.value().map(Some)
+                    }
+                    Some(_) if self.first => self.value().map(Some),
+                    Some(_) => Err(self.de.peek_error(Code::ExpectedListCommaOrEnd)),
+                    None => Err(self.de.eof_error(Code::EofWhileParsingList)),
+                }
+            }
+            Expect::ObjectMember => {
+                if let Some(open) = self.de.open.last_mut() {
+                    open.has_member = false;
+                }
+
+                let peek = match try!(self.de.parse_whitespace()) {
+                    Some(b'}') => {
+                        self.de.eat_char();
+                        return Ok(Some(self.close(Event::ObjectEnd)));
+                    }
+                    Some(b',') if !self.first => {
+                        self.de.eat_char();
+                        try!(self.de.parse_whitespace())
+                    }
+                    Some(b) if self.first => Some(b),
+                    Some(_) => return Err(self.de.peek_error(Code::ExpectedObjectCommaOrEnd)),
+                    None => return Err(self.de.eof_error(Code::EofWhileParsingObject)),
+                };
+
+                match peek {
+                    Some(b'"') => {
+                        let name = try!(self.de.parse_member_name(NameVisitor));
+                        try!(self.de.parse_object_colon());
+                        self.first = false;
+                        self.expect = Expect::Value;
+                        Ok(Some(Event::Key(name)))
+                    }
+                    Some(_) => Err(self.de.peek_error(Code::KeyMustBeAString)),
+                    None => Err(self.de.eof_error(Code::EofWhileParsingValue)),
+                }
+            }
+            Expect::Data => self.data_chunk().map(Some),
+            Expect::End => {
+                try!(self.de.end());
+                self.expect = Expect::Done;
+                Ok(None)
+            }
+            Expect::Done => Ok(None),
+        }
+    }
+
+    /// Parses the start of an array or object, or a whole scalar.
+    fn value(&mut self) -> Result<Event<'de>> {
+        try!(self.de.check_value_kind());
+        match try!(self.de.parse_whitespace()) {
+            Some(b'[') => {
+                try!(self.de.open_container(false));
+                self.first = true;
+                self.expect = Expect::ArrayElement;
+                Ok(Event::ArrayStart)
+            }
+            Some(b'{') => {
+                try!(self.de.open_container(true));
+                self.first = true;
+                self.expect = Expect::ObjectMember;
+                Ok(Event::ObjectStart)
+            }
+            Some(b'"') if self.chunk_size.is_some() &&
+                self.de.value_tag().map_or(false, transform::is_data) => {
+                let start = self.de.read.peek_mark();
+                self.de.eat_char();
+                let decoder = match self.de.value_tag() {
+                    Some("d16") => Decoder::hex(),
+                    Some("d32") => Decoder::b32(),
+                    _ => Decoder::b64url(),
+                };
+                self.data = Some(
+                    ChunkedData {
+                        decoder: decoder,
+                        decoded: Vec::new(),
+                        ended: false,
+                        start: start,
+                    },
+                );
+                self.expect = Expect::Data;
+                Ok(Event::DataStart(None))
+            }
+            _ => {
+                let event = try!(self.de.parse_value(EventVisitor));
+                self.after_value();
+                Ok(event)
+            }
+        }
+    }
+
+    /// Reads the next chunk of data, or its end.
+    fn data_chunk(&mut self) -> Result<Event<'de>> {
+        let chunk_size = self.chunk_size.unwrap_or(1);
+        let mut data = self.data.take().unwrap();
+        while !data.ended && data.decoded.len() < chunk_size {
+            let result = match try!(self.de.next_char()) {
+                Some(b'"') => {
+                    data.ended = true;
+                    data.decoder.finish(&mut data.decoded)
+                }
+                Some(byte) => data.decoder.decode(&[byte], &mut data.decoded),
+                None => {
+                    let err = self.de.eof_error(Code::EofWhileParsingString);
+                    return Err(self.de.string_error(err, data.start));
+                }
+            };
+            if let Err(err) = result {
+                return Err(self.de.error(Code::Custom(Arc::new(err))));
+            }
+        }
+
+        if data.decoded.is_empty() {
+            self.after_value();
+            return Ok(Event::DataEnd);
+        }
+        let chunk = if data.decoded.len() > chunk_size {
+            let rest = data.decoded.split_off(chunk_size);
+            mem::replace(&mut data.decoded, rest)
+        } else {
+            mem::take(&mut data.decoded)
+        };
+        self.data = Some(data);
+        Ok(Event::DataChunk(Cow::Owned(chunk)))
+    }
+
+    fn close(&mut self, event: Event<'de>) -> Event<'de> {
+        self.de.close_container();
+        self.after_value();
+        event
+    }
+
+    fn after_value(&mut self) {
+        self.first = false;
+        self.expect = match self.de.open.last() {
+            Some(open) if open.object => Expect::ObjectMember,
+            Some(_) => Expect::ArrayElement,
+            None => Expect::End,
+        };
+    }
+}
+
+impl<'de, R> Iterator for Events<'de, R>
+where
+    R: Read<'de>,
+{
+    type Item = Result<Event<'de>>;
+
+    fn next(&mut self) -> Option<Result<Event<'de>>> {
+        match self.step() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => None,
+            Err(err) => {
+                self.expect = Expect::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Turns a scalar into its event.
+struct EventVisitor;
+
+impl<'de> de::Visitor<'de> for EventVisitor {
+    type Value = Event<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a scalar")
+    }
+
+    fn visit_unit<E>(self) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Null)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Int(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::UInt(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Float(value))
+    }
+
+    fn visit_borrowed_str<E>(self, value: &'de str) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Str(Cow::Borrowed(value)))
+    }
+
+    fn visit_str<E>(self, value: &str) -> ::std::result::Result<Event<'de>, E> {
+        Ok(Event::Str(Cow::Owned(value.to_owned())))
+    }
+}
+
+/// Borrows an object member name from the input where possible.
+struct NameVisitor;
+
+impl<'de> de::Visitor<'de> for NameVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a member name")
+    }
+
+    fn visit_borrowed_str<E>(self, value: &'de str) -> ::std::result::Result<Cow<'de, str>, E> {
+        Ok(Cow::Borrowed(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> ::std::result::Result<Cow<'de, str>, E> {
+        Ok(Cow::Owned(value.to_owned()))
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// A member name without its tag, for comparing names.
+/// The tag of the value about to be parsed: that of the member it is the
+/// value of, or for an element of one tagged `A<t>`, `S<A<t>>` and so on,
+/// the `t` inside. Values outside of any object have the root tag, if any.
+fn value_tag<'a>(open: &'a [Open], root: Option<&'a str>) -> Option<&'a str> {
+    let depth = open.iter().rev().take_while(|open| !open.object).count();
+    let mut tag = match open.len().checked_sub(depth + 1) {
+        Some(index) if open[index].has_member => {
+            // Only tags read with `Normalization::TrimAndCaseInsensitive`
+            // have whitespace around them
+            let member = &open[index].member;
+            member.rfind(':').map(|colon| member[colon + 1..].trim())
+        }
+        Some(_) => None,
+        None => root,
+    };
+    for _ in 0..depth {
+        tag = tag.and_then(element_tag);
+    }
+    tag
+}
+
+/// Whether a string holds what a value with this tag must.
+fn string_matches_tag(tag: &Tag, text: &str) -> bool {
+    match *tag {
+        Tag::String => true,
+        Tag::Data(encoding) => transform::decode(encoding.tag(), text).is_ok(),
+        Tag::Timestamp => timestamp::parse(text).is_some(),
+        Tag::Int => Number::from_quoted("i", text).map_or(false, |n| n.is_some()),
+        Tag::UInt => Number::from_quoted("u", text).map_or(false, |n| n.is_some()),
+        _ => false,
+    }
+}
+
+/// Whether a member name ends with a `:` and a tag `Tag::parse` reads,
+/// which may have whitespace around it with
+/// `Normalization::TrimAndCaseInsensitive`.
+fn has_valid_tag(name: &str, normalization: Normalization) -> bool {
+    let tag = match name.rfind(':') {
+        Some(colon) => &name[colon + 1..],
+        None => return false,
+    };
+    match normalization {
+        Normalization::TrimAndCaseInsensitive => tag::is_tag(tag.trim()),
+        _ => tag::is_tag(tag),
+    }
+}
+
+/// The tag of the elements of an array or set with this tag.
+fn element_tag(tag: &str) -> Option<&str> {
+    if (tag.starts_with("A<") || tag.starts_with("S<")) && tag.ends_with('>') {
+        Some(&tag[2..tag.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Whether the string about to be parsed is a timestamp, being tagged `t`.
+fn in_timestamp(open: &[Open], root: Option<&str>) -> bool {
+    value_tag(open, root) == Some("t")
+}
+
+/// An element of a set as it is compared with the others. With
+/// `preserve_order` sets compare their elements in order, so the sets within
+/// it are rebuilt in sorted order to be equal whatever order they were
+/// written in.
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+fn set_element(element: Value) -> Value {
+    match element {
+        Value::Set(set) => {
+            let mut elements: Vec<_> = set.into_iter().map(set_element).collect();
+            elements.sort();
+            let mut set = Set::new();
+            for element in elements {
+                set.insert(element);
+            }
+            Value::Set(set)
+        }
+        Value::Array(elements) => Value::Array(elements.into_iter().map(set_element).collect()),
+        element => element,
+    }
+}
+
+/// An element of a set as it is compared with the others, which for sets
+/// without `preserve_order` is as it is.
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
+fn set_element(element: Value) -> Value {
+    element
+}
+
+/// The error for an element of the innermost open array, a set, which equals
+/// an earlier one.
+fn duplicate_element(open: &[Open], index: usize, element: &Value) -> Code {
+    let set = &open[..open.len().saturating_sub(1)];
+    Code::DuplicateElement(index, element.to_string(), pointer(set))
+}
+
+fn unresolved_collision(open: &[Open], index: usize) -> Code {
+    let set = &open[..open.len().saturating_sub(1)];
+    Code::UnresolvedHashCollision(index, pointer(set))
+}
+
+/// The JSON pointer to the value about to be parsed.
+fn pointer(open: &[Open]) -> String {
+    let mut pointer = String::new();
+    for open in open {
+        if open.object {
+            push_pointer_token(&mut pointer, &open.member);
+        } else {
+            push_pointer_token(&mut pointer, &open.index.to_string());
+        }
+    }
+    pointer
+}
+
+/// Parses `significand * 10^exponent` with the standard library's correctly
+/// rounded parser, writing its text out on the stack rather than allocating.
+fn parse_f64_parts(significand: u64, exponent: i32) -> Option<f64> {
+    use std::io::Write;
+
+    // Up to 20 digits, the `e`, and up to 11 characters of exponent
+    let mut buf = [0u8; 32];
+    let len = {
+        let mut rest = &mut buf[..];
+        let written = itoa::write(&mut rest, significand)
+            .and_then(|_| rest.write_all(b"e"))
+            .and_then(|_| itoa::write(&mut rest, exponent));
+        if written.is_err() {
+            return None;
+        }
+        32 - rest.len()
+    };
+    str::from_utf8(&buf[..len]).ok().and_then(|text| text.parse().ok())
+}
+
+/// Checks a timestamp inside the `open` containers against the offset
+/// policy, giving the text to read instead if it had to be converted to UTC,
+/// or had a lowercase `t` or `z` which chrono doesn't accept. Strings which
+/// aren't RFC 3339 timestamps are read as they are.
+fn normalize_timestamp(
+    text: &str,
+    policy: OffsetPolicy,
+    open: &[Open],
+) -> result::Result<Option<String>, Code> {
+    match timestamp::parse(text) {
+        Some((timestamp, offset)) if offset != "Z" => {
+            match policy {
+                OffsetPolicy::RejectNonUtc => {
+                    Err(Code::NonUtcTimestamp(offset.to_owned(), pointer(open)))
+                }
+                OffsetPolicy::NormalizeToUtc => Ok(Some(timestamp::format(&timestamp))),
             }
-            Ok(Some(_)) => Some(Err(self.de.peek_error(ErrorCode::ExpectedObjectOrArray))),
-            Err(e) => Some(Err(e)),
         }
+        Some(_) if text.ends_with('z') && policy == OffsetPolicy::RejectNonUtc => {
+            Err(Code::NonUtcTimestamp("z".to_owned(), pointer(open)))
+        }
+        Some(_) if text.as_bytes()[10] == b't' || text.ends_with('z') => {
+            let mut upper = text.to_owned();
+            upper.make_ascii_uppercase();
+            Ok(Some(upper))
+        }
+        Some(_) | None => Ok(None),
+    }
+}
+
+fn untagged(name: &str) -> &str {
+    match name.rfind(':') {
+        Some(colon) => &name[..colon],
+        None => name,
     }
 }
 
@@ -1215,6 +4802,17 @@ where
     Ok(value)
 }
 
+fn from_trait_with_report<'de, R, T>(read: R) -> Result<(T, ParseReport)>
+where
+    R: Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    let mut de = Deserializer::new(read);
+    let value = try!(de::Deserialize::deserialize(&mut de));
+    try!(de.end());
+    Ok((value, de.report()))
+}
+
 /// Deserialize an instance of type `T` from an IO stream of JSON.
 ///
 /// # Errors
@@ -1227,6 +4825,9 @@ where
 /// the JSON map or some number is too big to fit in the expected primitive
 /// type.
 ///
+/// Input which starts like UTF-16 is rejected with an error saying so, or
+/// transcoded to UTF-8 and parsed if the `utf16` feature is enabled.
+///
 /// ```rust
 /// #[macro_use]
 /// extern crate serde_derive;
@@ -1262,12 +4863,98 @@ where
 ///     println!("{:#?}", u);
 /// }
 /// ```
-pub fn from_reader<R, T>(rdr: R) -> Result<T>
+pub fn from_reader<R, T>(mut rdr: R) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut start = [0; 2];
+    let len = try!(read_start(&mut rdr, &mut start).map_err(Error::io));
+    let start_rdr = io::Read::take(io::Cursor::new(start), len as u64);
+    let rdr = io::Read::chain(start_rdr, rdr);
+
+    match sniff_encoding(&start[..len]) {
+        Some(encoding) => from_utf16_reader(rdr, encoding),
+        None => from_trait(read::IoRead::new(rdr)),
+    }
+}
+
+/// Fills `buf` from the reader unless the input ends first, returning how
+/// many bytes were read.
+fn read_start<R>(rdr: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: io::Read,
+{
+    let mut len = 0;
+    while len < buf.len() {
+        match rdr.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+/// Recognizes UTF-16 input from its first two bytes: either a byte order
+/// mark, or an ASCII character and a NUL byte. UTF-8 TJSON can't start with
+/// either, since NUL may only appear escaped inside strings.
+fn sniff_encoding(start: &[u8]) -> Option<Encoding> {
+    match start {
+        [0xFF, 0xFE, ..] => Some(Encoding::Utf16Le(true)),
+        [0xFE, 0xFF, ..] => Some(Encoding::Utf16Be(true)),
+        [b, 0, ..] if *b != 0 && *b < 0x80 => Some(Encoding::Utf16Le(false)),
+        [0, b, ..] if *b != 0 && *b < 0x80 => Some(Encoding::Utf16Be(false)),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "utf16"))]
+fn from_utf16_reader<R, T>(_rdr: R, encoding: Encoding) -> Result<T>
+where
+    R: io::Read,
+{
+    Err(Error::syntax(Code::UnsupportedEncoding(encoding), 0, 0))
+}
+
+/// Transcodes the whole input to UTF-8 and parses that. Positions in errors
+/// refer to the transcoded text.
+#[cfg(feature = "utf16")]
+fn from_utf16_reader<R, T>(mut rdr: R, encoding: Encoding) -> Result<T>
 where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    from_trait(read::IoRead::new(rdr))
+    use std::char;
+
+    let mut bytes = Vec::new();
+    try!(io::Read::read_to_end(&mut rdr, &mut bytes).map_err(Error::io));
+
+    let (big_endian, bom) = match encoding {
+        Encoding::Utf16Le(bom) => (false, bom),
+        Encoding::Utf16Be(bom) => (true, bom),
+    };
+    let bytes = if bom { &bytes[2..] } else { &bytes[..] };
+
+    // An odd trailing byte is decoded as an unpaired surrogate
+    let units = bytes.chunks(2).map(
+        |pair| match *pair {
+            [a, b] if big_endian => (a as u16) << 8 | b as u16,
+            [a, b] => (b as u16) << 8 | a as u16,
+            _ => 0xDC00,
+        },
+    );
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => text.push(c),
+            Err(_) => return Err(Error::syntax(Code::InvalidUnicodeCodePoint, 0, 0)),
+        }
+    }
+
+    from_str(&text)
 }
 
 /// Deserialize an instance of type `T` from bytes of JSON text.
@@ -1282,6 +4969,8 @@ where
 /// the JSON map or some number is too big to fit in the expected primitive
 /// type.
 ///
+/// Input which starts like UTF-16 is rejected with an error saying so.
+///
 /// ```rust
 /// #[macro_use]
 /// extern crate serde_derive;
@@ -1310,6 +4999,9 @@ pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
+    if let Some(encoding) = sniff_encoding(v) {
+        return Err(Error::syntax(Code::UnsupportedEncoding(encoding), 0, 0));
+    }
     from_trait(read::SliceRead::new(v))
 }
 
@@ -1355,3 +5047,528 @@ where
 {
     from_trait(read::StrRead::new(s))
 }
+
+/// Deserialize an instance of type `T` from a string of TJSON whose
+/// toplevel value is read as if it were tagged with `tag`.
+///
+/// A document which is a single value has no member name to carry its tag,
+/// so without one a timestamp or data can't be told from a string. The
+/// value is first checked against the tag with `Tag::check`, failing with
+/// `ErrorCode::TagMismatch` if it doesn't hold what the tag says, and then
+/// deserialized as `Deserializer::root_tag` reads it. The input is parsed
+/// twice to do so.
+///
+/// ```rust
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::Tag;
+/// use tjson::error::ErrorCode;
+///
+/// let ids: Vec<u64> = tjson::from_str_as(r#"["1", "2"]"#, &Tag::parse("A<u>").unwrap()).unwrap();
+/// assert_eq!(ids, [1, 2]);
+///
+/// let err = tjson::from_str_as::<i64>("1.5", &Tag::Int).unwrap_err();
+/// assert_eq!(err.code(), ErrorCode::TagMismatch);
+/// assert_eq!(err.to_string(), "expected a value tagged `i`, found number 1.5 at line 1 column 3");
+/// # }
+/// ```
+///
+/// The tag directs how `T` is read, but isn't kept anywhere: a `Value` is
+/// built as it would be from an untagged value, so timestamps and data are
+/// left as the strings they were written as.
+pub fn from_str_as<'a, T>(s: &'a str, tag: &Tag) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let value: Value = try!(from_trait_as(read::StrRead::new(s), tag));
+    let value = ::value::read_member(&format!(":{}", tag), value);
+    if let Err(mismatch) = tag.check(&value) {
+        return Err(Error::syntax(Code::TagMismatch(Box::new(mismatch)), 0, 0));
+    }
+
+    from_trait_as(read::StrRead::new(s), tag)
+}
+
+/// Deserialize an instance of type `T` from a string of TJSON which is a
+/// single scalar value, such as a timestamp, read as `tjson::from_str_as`
+/// reads it.
+///
+/// ```rust
+/// # extern crate chrono;
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use chrono::datetime::DateTime;
+/// use chrono::offset::utc::UTC;
+/// use tjson::Tag;
+///
+/// let at: DateTime<UTC> = tjson::from_str_scalar(r#""2016-11-06T22:27:34Z""#, Tag::Timestamp)
+///     .unwrap();
+/// assert_eq!(at.to_string(), "2016-11-06 22:27:34 UTC");
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `tag` is the tag of a container: `O`, `A<..>` or `S<..>`.
+/// Use `from_str_as` for those.
+pub fn from_str_scalar<'a, T>(s: &'a str, tag: Tag) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    assert!(tag.is_scalar(), "`{}` is not the tag of a scalar", tag);
+    from_str_as(s, &tag)
+}
+
+fn from_trait_as<'de, R, T>(read: R, tag: &Tag) -> Result<T>
+where
+    R: Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    let mut de = Deserializer::new(read).root_tag(tag);
+    let value = try!(de::Deserialize::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
+
+/// Deserialize an instance of type `T` from an IO stream of TJSON, like
+/// `tjson::from_reader`, along with measurements of the input.
+///
+/// The measurements are of UTF-8 input, so UTF-16 is always rejected, even
+/// with the `utf16` feature.
+pub fn from_reader_with_report<R, T>(mut rdr: R) -> Result<(T, ParseReport)>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut start = [0; 2];
+    let len = try!(read_start(&mut rdr, &mut start).map_err(Error::io));
+    if let Some(encoding) = sniff_encoding(&start[..len]) {
+        return Err(Error::syntax(Code::UnsupportedEncoding(encoding), 0, 0));
+    }
+
+    let start_rdr = io::Read::take(io::Cursor::new(start), len as u64);
+    from_trait_with_report(read::IoRead::new(io::Read::chain(start_rdr, rdr)))
+}
+
+/// Deserialize an instance of type `T` from bytes of TJSON text, like
+/// `tjson::from_slice`, along with measurements of the input.
+pub fn from_slice_with_report<'a, T>(v: &'a [u8]) -> Result<(T, ParseReport)>
+where
+    T: de::Deserialize<'a>,
+{
+    if let Some(encoding) = sniff_encoding(v) {
+        return Err(Error::syntax(Code::UnsupportedEncoding(encoding), 0, 0));
+    }
+    from_trait_with_report(read::SliceRead::new(v))
+}
+
+/// Deserialize an instance of type `T` from a string of TJSON text, like
+/// `tjson::from_str`, along with measurements of the input.
+pub fn from_str_with_report<'a, T>(s: &'a str) -> Result<(T, ParseReport)>
+where
+    T: de::Deserialize<'a>,
+{
+    from_trait_with_report(read::StrRead::new(s))
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// What to do with whitespace following a document parsed out of a larger
+/// input by `from_slice_prefix_with` or `from_reader_prefix_with`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrailingWhitespace {
+    /// Stop right after the document's closing `}` or `]`.
+    Leave,
+
+    /// Also consume any whitespace after the document. Finding where the
+    /// whitespace ends takes one byte of lookahead.
+    Consume,
+}
+
+fn from_trait_prefix<'de, R, T>(
+    read: R,
+    trailing: TrailingWhitespace,
+) -> Result<(T, usize, Option<u8>)>
+where
+    R: Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    Deserializer::new(read).parse_prefix(trailing)
+}
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    fn parse_prefix<T>(&mut self, trailing: TrailingWhitespace) -> Result<(T, usize, Option<u8>)>
+    where
+        T: de::Deserialize<'de>,
+    {
+        // Containers end at a closing bracket, so nothing after the document
+        // needs to be looked at to find its end, unlike a bare number
+        match try!(self.parse_whitespace()) {
+            Some(b'{') | Some(b'[') => {}
+            Some(_) => return Err(self.peek_error(Code::ExpectedObjectOrArray)),
+            None => return Err(self.eof_error(Code::EofWhileParsingValue)),
+        }
+        let value = try!(T::deserialize(&mut *self));
+
+        let peeked = match trailing {
+            TrailingWhitespace::Leave => None,
+            TrailingWhitespace::Consume => try!(self.parse_whitespace()),
+        };
+        Ok((value, self.read.byte_offset(), peeked))
+    }
+}
+
+/// Deserialize an instance of type `T` from the TJSON document at the start
+/// of `v`, returning it along with the number of bytes the document took.
+///
+/// Anything may follow the document, which must be an object or an array.
+/// Leading whitespace is skipped and counted, trailing whitespace is not.
+/// `Error::byte_offset` tells where in `v` parsing failed.
+///
+/// ```rust
+/// use tjson::Value;
+///
+/// let input = b"{\"a:u\": \"1\"} \xFF\xFE binary";
+/// let (value, len) = tjson::de::from_slice_prefix::<Value>(input).unwrap();
+/// assert_eq!(value["a:u"], 1);
+/// assert_eq!(len, 12);
+/// assert_eq!(input[len], b' ');
+/// ```
+pub fn from_slice_prefix<'a, T>(v: &'a [u8]) -> Result<(T, usize)>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_prefix_with(v, TrailingWhitespace::Leave)
+}
+
+/// Like `from_slice_prefix`, choosing whether whitespace after the document
+/// is counted as part of it.
+pub fn from_slice_prefix_with<'a, T>(
+    v: &'a [u8],
+    trailing: TrailingWhitespace,
+) -> Result<(T, usize)>
+where
+    T: de::Deserialize<'a>,
+{
+    from_trait_prefix(read::SliceRead::new(v), trailing).map(|(value, len, _)| (value, len))
+}
+
+/// Deserialize an instance of type `T` from the TJSON document at the
+/// current position of a reader, leaving the reader just past the document.
+///
+/// The reader is not buffered: it is read a byte at a time, and never past
+/// the document's closing `}` or `]`, so the next byte the caller reads from
+/// it is the first one after the document. Pass `&mut reader` to keep using
+/// the reader afterwards, and wrap slow readers such as files and sockets in
+/// an `io::BufReader` first.
+///
+/// Returns the value along with the number of bytes the document took,
+/// including leading whitespace. Unlike `from_reader`, the input must be
+/// UTF-8.
+///
+/// ```rust
+/// use std::io::{Cursor, Read};
+///
+/// use tjson::Value;
+///
+/// let mut reader = Cursor::new(&b"[1, 2]\xFF\x00"[..]);
+/// let (value, len) = tjson::de::from_reader_prefix::<_, Value>(&mut reader).unwrap();
+/// assert_eq!(value.len(), Some(2));
+/// assert_eq!(len, 6);
+///
+/// let mut rest = Vec::new();
+/// reader.read_to_end(&mut rest).unwrap();
+/// assert_eq!(rest, [0xFF, 0x00]);
+/// ```
+pub fn from_reader_prefix<R, T>(rdr: R) -> Result<(T, usize)>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    from_reader_prefix_with(rdr, TrailingWhitespace::Leave).map(|(value, len, _)| (value, len))
+}
+
+/// Like `from_reader_prefix`, choosing whether whitespace after the document
+/// is counted as part of it.
+///
+/// Consuming the whitespace reads one byte past it, unless the input ends
+/// first. That byte is not counted and is returned so the caller doesn't lose
+/// it. It is the only byte ever read past the document, and is always `None`
+/// with `TrailingWhitespace::Leave`.
+pub fn from_reader_prefix_with<R, T>(
+    rdr: R,
+    trailing: TrailingWhitespace,
+) -> Result<(T, usize, Option<u8>)>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    from_trait_prefix(read::IoRead::new(rdr), trailing)
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// A reusable parser for applications which parse many small documents.
+///
+/// The one-shot functions like `tjson::from_str` allocate the deserializer's
+/// scratch buffers afresh for every document. A `Parser` keeps them, emptied
+/// but still allocated, from one parse to the next, so after the first few
+/// documents parsing only allocates for the values being built.
+///
+/// Nothing but buffer capacity carries over between parses, including after
+/// a parse fails. A `Parser` is `Send` but not `Sync`, since parsing requires
+/// `&mut self`; keep one per thread.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// extern crate tjson;
+///
+/// #[derive(Deserialize)]
+/// struct Reading<'a> {
+///     sensor: &'a str,
+///     value: f64,
+/// }
+///
+/// fn main() {
+///     let messages = [
+///         r#"{"sensor": "a", "value": 1.5}"#,
+///         r#"{"sensor": "b", "value": 2.5}"#,
+///     ];
+///
+///     let mut parser = tjson::de::Parser::new();
+///     let mut total = 0.0;
+///     for message in &messages {
+///         let reading: Reading = parser.parse_str(message).unwrap();
+///         total += reading.value;
+///     }
+///
+///     assert_eq!(total, 4.0);
+/// }
+/// ```
+pub struct Parser {
+    scratch: Option<Scratch>,
+    options: Options,
+    warnings: LimitWarnings,
+}
+
+impl Parser {
+    /// Creates a parser with empty buffers.
+    pub fn new() -> Self {
+        Parser {
+            scratch: Some(Scratch::new()),
+            options: Options::default(),
+            warnings: LimitWarnings::new(),
+        }
+    }
+
+    /// Handle objects with more than one member of the same name according
+    /// to the given policy, as `Deserializer::duplicate_keys` does.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.options.duplicate_keys = policy;
+        self
+    }
+
+    /// Handle sets with more than one equal element according to the given
+    /// policy, as `Deserializer::duplicate_elements` does.
+    pub fn duplicate_elements(mut self, policy: DuplicateElements) -> Self {
+        self.options.duplicate_elements = policy;
+        self
+    }
+
+    /// Set how the elements of sets are remembered to find duplicates among
+    /// them, as `Deserializer::set_uniqueness` does.
+    pub fn set_uniqueness(mut self, uniqueness: SetUniqueness) -> Self {
+        self.options.set_uniqueness = uniqueness;
+        self
+    }
+
+    /// Deserialize tuples from objects by taking the members with these
+    /// names, in this order, as `Deserializer::field_order` does.
+    pub fn field_order(mut self, names: &[&str]) -> Self {
+        self.options.field_order(names);
+        self
+    }
+
+    /// Also deserialize maps from arrays of `[key, value]` pairs, as
+    /// `Deserializer::map_as_pairs` does.
+    pub fn map_as_pairs(mut self, enabled: bool) -> Self {
+        self.options.map_as_pairs = enabled;
+        self
+    }
+
+    /// Accept whole floating point numbers when deserializing integers, as
+    /// `Deserializer::integral_float_coercion` does.
+    pub fn integral_float_coercion(mut self, enabled: bool) -> Self {
+        self.options.integral_float_coercion = enabled;
+        self
+    }
+
+    /// Accept integers quoted as strings under members tagged `i` or `u`, as
+    /// `Deserializer::quoted_integers` does.
+    pub fn quoted_integers(mut self, enabled: bool) -> Self {
+        self.options.quoted_integers = enabled;
+        self
+    }
+
+    /// Set whether integers tagged `u` may be read into signed types and
+    /// those tagged `i` into unsigned ones, as
+    /// `Deserializer::integer_signedness` does.
+    pub fn integer_signedness(mut self, signedness: IntegerSignedness) -> Self {
+        self.options.integer_signedness = signedness;
+        self
+    }
+
+    /// Match member names to the fields of structs by the given
+    /// normalization, as `Deserializer::member_name_normalization` does.
+    pub fn member_name_normalization(mut self, normalization: Normalization) -> Self {
+        self.options.member_name_normalization = normalization;
+        self
+    }
+
+    /// Reject member names which the given policy doesn't allow, as
+    /// `Deserializer::key_policy` does.
+    pub fn key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.options.key_policy = Some(policy);
+        self
+    }
+
+    /// Hold every object to the tags of its member names, as
+    /// `Deserializer::strict_tags` does.
+    pub fn strict_tags(mut self, enabled: bool) -> Self {
+        self.options.strict_tags = enabled;
+        self
+    }
+
+    /// Decode data wherever any value is expected, as
+    /// `Deserializer::decode_data` does.
+    pub fn decode_data(mut self, enabled: bool) -> Self {
+        self.options.decode_data = enabled;
+        self
+    }
+
+    /// Hold base64url data undecoded until it is needed, as
+    /// `Deserializer::lazy_data` does.
+    pub fn lazy_data(mut self, enabled: bool) -> Self {
+        self.options.lazy_data = enabled;
+        self
+    }
+
+    /// Set what happens to timestamps with an offset other than `Z`, as
+    /// `Deserializer::timestamp_offsets` does.
+    pub fn timestamp_offsets(mut self, policy: OffsetPolicy) -> Self {
+        self.options.timestamp_offsets = policy;
+        self
+    }
+
+    /// Read the toplevel value as the value of a member with the given tag
+    /// would be read, as `Deserializer::root_tag` does.
+    pub fn root_tag(mut self, tag: &Tag) -> Self {
+        self.options.root_tag = Some(tag.to_string());
+        self
+    }
+
+    /// Warn about objects with more than `count` members, as
+    /// `Deserializer::warn_member_count` does. See `warnings`.
+    pub fn warn_member_count(mut self, count: usize) -> Self {
+        self.options.warn_member_count = Some(count);
+        self
+    }
+
+    /// Warn about member names longer than `len` bytes, as
+    /// `Deserializer::warn_key_length` does. See `warnings`.
+    pub fn warn_key_length(mut self, len: usize) -> Self {
+        self.options.warn_key_length = Some(len);
+        self
+    }
+
+    /// Warn about arrays and objects nested more than `depth` deep, as
+    /// `Deserializer::warn_depth` does. See `warnings`.
+    pub fn warn_depth(mut self, depth: usize) -> Self {
+        self.options.warn_depth = Some(depth);
+        self
+    }
+
+    /// Keep at most `cap` warnings from the `warn_` options for each parse,
+    /// as `Deserializer::max_warnings` does.
+    pub fn max_warnings(mut self, cap: usize) -> Self {
+        self.options.max_warnings = cap;
+        self
+    }
+
+    /// The soft limits set by the `warn_` options which the input of the
+    /// last parse went over, as `Deserializer::warnings` gives them. Empty
+    /// before the first parse.
+    pub fn warnings(&self) -> &LimitWarnings {
+        &self.warnings
+    }
+
+    /// Run `transform` over the decoded bytes of each data value `matcher`
+    /// picks out, as `Deserializer::transform_data` does.
+    pub fn transform_data<F>(mut self, matcher: Matcher, transform: F) -> Self
+    where
+        F: Fn(Vec<u8>) -> Result<Vec<u8>> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.options.transforms).add_data(matcher, transform);
+        self
+    }
+
+    /// Run `transform` over each string `matcher` picks out, as
+    /// `Deserializer::transform_string` does.
+    pub fn transform_string<F>(mut self, matcher: Matcher, transform: F) -> Self
+    where
+        F: Fn(String) -> Result<String> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.options.transforms).add_string(matcher, transform);
+        self
+    }
+
+    /// Deserialize an instance of type `T` from a string of TJSON text, like
+    /// `tjson::from_str`.
+    pub fn parse_str<'a, T>(&mut self, s: &'a str) -> Result<T>
+    where
+        T: de::Deserialize<'a>,
+    {
+        self.parse(read::StrRead::new(s))
+    }
+
+    /// Deserialize an instance of type `T` from bytes of TJSON text, like
+    /// `tjson::from_slice`.
+    pub fn parse_slice<'a, T>(&mut self, v: &'a [u8]) -> Result<T>
+    where
+        T: de::Deserialize<'a>,
+    {
+        self.parse(read::SliceRead::new(v))
+    }
+
+    fn parse<'de, R, T>(&mut self, read: R) -> Result<T>
+    where
+        R: Read<'de>,
+        T: de::Deserialize<'de>,
+    {
+        // If a previous parse panicked its buffers are gone; start over
+        let scratch = self.scratch.take().unwrap_or_else(Scratch::new);
+        let mut de = Deserializer::with_scratch(read, scratch, self.options.clone());
+
+        let result = de::Deserialize::deserialize(&mut de).and_then(
+            |value| {
+                // Make sure the whole input has been consumed.
+                try!(de.end());
+                Ok(value)
+            },
+        );
+
+        self.warnings = mem::replace(&mut de.warnings, LimitWarnings::new());
+        self.scratch = Some(de.into_scratch());
+        result
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
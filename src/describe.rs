@@ -0,0 +1,657 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Describing the wire shape of Rust types, for API documentation
+//!
+//! `describe` serializes a sample value through a serializer which records
+//! the structure of the output rather than writing it, and returns a TJSON
+//! document which gives every member name its tag and says what its value
+//! looks like:
+//!
+//! ```rust
+//! # extern crate serde;
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! use tjson::describe::describe;
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//!     age: u8,
+//!     email: Option<String>,
+//!     groups: Vec<Group>,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Group {
+//!     id: i64,
+//! }
+//!
+//! # fn main() {
+//! let sample = User {
+//!     name: "Alice".to_owned(),
+//!     age: 30,
+//!     email: Some("alice@example.com".to_owned()),
+//!     groups: vec![Group { id: 1 }],
+//! };
+//!
+//! assert_eq!(
+//!     describe(&sample).unwrap(),
+//!     tjson!({
+//!         "name:s": "string",
+//!         "age:u": "unsigned",
+//!         "email:s": "optional<string>",
+//!         "groups:A<O>": { "array": { "id:i": "integer" } },
+//!     })
+//! );
+//! # }
+//! ```
+//!
+//! Scalars are described as one of `"string"`, `"integer"`, `"unsigned"`,
+//! `"float"`, `"boolean"`, `"data"` and `"timestamp"`, structs and enum
+//! variants as objects of their members, and arrays, sets, maps and options
+//! as `"array<...>"`, `"set<...>"`, `"map<...>"` and `"optional<...>"` of
+//! what they contain. Where the contents are an object, the container is
+//! instead an object with the single member `array`, `set`, `map` or
+//! `optional`, which unlike the members of structs has no tag.
+//!
+//! Member names which already carry a tag, such as fields renamed to
+//! `"name:s"`, are kept as they are, and it is an error if the tag doesn't
+//! match the member's value.
+//!
+//! # Samples
+//!
+//! Since only the sample's serialized form is seen, the sample should be
+//! representative: every `Option` must be `Some` and every collection must
+//! have at least one element, or describing it fails as the type of their
+//! contents is unknown. Strings holding an RFC 3339 timestamp are described
+//! as timestamps, which is how `tjson::DateTime` fields serialize.
+//!
+//! `tjson::Set` is described as a set. Other sets, such as `BTreeSet`,
+//! serialize like any sequence and so are described as arrays, unless the
+//! field is marked with `#[serde(with = "tjson::set")]` or
+//! `#[serde(serialize_with = "tjson::describe::as_set")]`.
+
+use std::fmt;
+
+use serde::ser::{self, Error as SerError, Serialize};
+
+use data::DATA_TOKEN;
+use error::{Error, Result};
+use map::Map;
+#[cfg(feature = "decimal")]
+use number::DECIMAL_TOKEN;
+use ser::is_member_name;
+use set::SET_TOKEN;
+use value::{DateTime, Value, push_pointer_token, to_value};
+
+/// Describe the wire shape of the given sample value.
+///
+/// See the [module documentation](index.html) for the form of the
+/// description.
+pub fn describe<T: ?Sized>(sample: &T) -> Result<Value>
+where
+    T: Serialize,
+{
+    sample
+        .serialize(Prober { path: String::new() })
+        .map(|shape| shape.describe())
+}
+
+/// Types whose wire shape can be described from a sample value.
+///
+/// Implemented for every type which implements `Serialize`.
+pub trait Describe {
+    /// Describe the wire shape of `self`, taken as a sample of its type.
+    fn describe(&self) -> Result<Value>;
+}
+
+impl<T: ?Sized> Describe for T
+where
+    T: Serialize,
+{
+    fn describe(&self) -> Result<Value> {
+        describe(self)
+    }
+}
+
+/// Serialize a collection so that it is described as a set, for use with
+/// `#[serde(serialize_with = "tjson::describe::as_set")]`.
+///
+/// The collection is written as the same sequence as without the attribute,
+/// but becomes a `Value::Set` when converted with `to_value`. Equal elements
+/// are an error unless the `DuplicateElements` policy merges them.
+pub fn as_set<'a, T: ?Sized, S>(
+    value: &'a T,
+    serializer: S,
+) -> ::std::result::Result<S::Ok, S::Error>
+where
+    &'a T: IntoIterator,
+    <&'a T as IntoIterator>::Item: Serialize,
+    S: ser::Serializer,
+{
+    ::set::serialize(value, serializer)
+}
+
+/// The recorded shape of a serialized value.
+#[derive(Clone, Debug, PartialEq)]
+enum Shape {
+    Scalar(&'static str, &'static str),
+    Array(Box<Shape>),
+    Set(Box<Shape>),
+    Map(Box<Shape>),
+    Optional(Box<Shape>),
+    Object(Vec<(String, Shape)>),
+}
+
+impl Shape {
+    fn tag(&self) -> String {
+        match *self {
+            Shape::Scalar(_, tag) => tag.to_owned(),
+            Shape::Array(ref element) => format!("A<{}>", element.tag()),
+            Shape::Set(ref element) => format!("S<{}>", element.tag()),
+            Shape::Optional(ref inner) => inner.tag(),
+            Shape::Map(_) | Shape::Object(_) => "O".to_owned(),
+        }
+    }
+
+    fn describe(self) -> Value {
+        let (container, inner) = match self {
+            Shape::Scalar(name, _) => return Value::String(name.to_owned()),
+            Shape::Object(members) => {
+                let mut map = Map::new();
+                for (name, shape) in members {
+                    map.insert(name, shape.describe());
+                }
+                return Value::Object(map);
+            }
+            Shape::Array(inner) => ("array", inner),
+            Shape::Set(inner) => ("set", inner),
+            Shape::Map(inner) => ("map", inner),
+            Shape::Optional(inner) => ("optional", inner),
+        };
+
+        match inner.describe() {
+            Value::String(name) => Value::String(format!("{}<{}>", container, name)),
+            description => {
+                let mut map = Map::new();
+                map.insert(container.to_owned(), description);
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+/// A serializer which records the shape of its input. `path` is the JSON
+/// pointer of the value being serialized, for error messages.
+#[derive(Clone)]
+struct Prober {
+    path: String,
+}
+
+impl Prober {
+    fn child(&self, token: &str) -> Prober {
+        let mut path = self.path.clone();
+        push_pointer_token(&mut path, token);
+        Prober { path: path }
+    }
+
+    fn error<T: fmt::Display>(&self, msg: T) -> Error {
+        if self.path.is_empty() {
+            Error::custom(msg)
+        } else {
+            Error::custom(format_args!("{} at {}", msg, self.path))
+        }
+    }
+
+    /// Gives `name` the tag of `shape`, or checks the tag it already has.
+    fn member(&self, name: &str, shape: Shape) -> Result<(String, Shape)> {
+        let tag = shape.tag();
+        match name.rfind(':') {
+            Some(i) if name[i + 1..] == *tag => Ok((name.to_owned(), shape)),
+            Some(i) => {
+                Err(self.error(format_args!(
+                    "member is tagged `{}` but its value is `{}`",
+                    &name[i + 1..],
+                    tag
+                )))
+            }
+            None => Ok((format!("{}:{}", name, tag), shape)),
+        }
+    }
+}
+
+impl ser::Serializer for Prober {
+    type Ok = Shape;
+    type Error = Error;
+
+    type SerializeSeq = ProbeElements;
+    type SerializeTuple = ProbeElements;
+    type SerializeTupleStruct = ProbeElements;
+    type SerializeTupleVariant = ProbeElements;
+    type SerializeMap = ProbeMap;
+    type SerializeStruct = ProbeMembers;
+    type SerializeStructVariant = ProbeMembers;
+
+    fn serialize_bool(self, _value: bool) -> Result<Shape> {
+        Ok(Shape::Scalar("boolean", "b"))
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<Shape> {
+        Ok(Shape::Scalar("integer", "i"))
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<Shape> {
+        Ok(Shape::Scalar("integer", "i"))
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<Shape> {
+        Ok(Shape::Scalar("integer", "i"))
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<Shape> {
+        Ok(Shape::Scalar("integer", "i"))
+    }
+
+    fn serialize_u8(self, _value: u8) -> Result<Shape> {
+        Ok(Shape::Scalar("unsigned", "u"))
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<Shape> {
+        Ok(Shape::Scalar("unsigned", "u"))
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<Shape> {
+        Ok(Shape::Scalar("unsigned", "u"))
+    }
+
+    fn serialize_u64(self, _value: u64) -> Result<Shape> {
+        Ok(Shape::Scalar("unsigned", "u"))
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<Shape> {
+        Ok(Shape::Scalar("float", "f"))
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<Shape> {
+        Ok(Shape::Scalar("float", "f"))
+    }
+
+    fn serialize_char(self, _value: char) -> Result<Shape> {
+        Ok(Shape::Scalar("string", "s"))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Shape> {
+        if value.parse::<DateTime>().is_ok() {
+            Ok(Shape::Scalar("timestamp", "t"))
+        } else {
+            Ok(Shape::Scalar("string", "s"))
+        }
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Shape> {
+        Ok(Shape::Scalar("data", "d"))
+    }
+
+    fn serialize_none(self) -> Result<Shape> {
+        Err(self.error("cannot describe `None`, the sample needs `Some` value"))
+    }
+
+    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Shape>
+    where
+        T: Serialize,
+    {
+        value
+            .serialize(self)
+            .map(|shape| Shape::Optional(Box::new(shape)))
+    }
+
+    fn serialize_unit(self) -> Result<Shape> {
+        Err(self.error("cannot describe a unit value, which has no tag"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Shape> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Shape> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Shape>
+    where
+        T: Serialize,
+    {
+        #[cfg(feature = "decimal")]
+        {
+            if name == DECIMAL_TOKEN {
+                return Ok(Shape::Scalar("float", "f"));
+            }
+        }
+
+        if name == DATA_TOKEN {
+            return Ok(Shape::Scalar("data", "d"));
+        }
+
+        match try!(value.serialize(self)) {
+            Shape::Array(element) if name == SET_TOKEN => Ok(Shape::Set(element)),
+            shape => Ok(shape),
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Shape>
+    where
+        T: Serialize,
+    {
+        let shape = try!(value.serialize(self.child(variant)));
+        let member = try!(self.member(variant, shape));
+        Ok(Shape::Object(vec![member]))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ProbeElements> {
+        Ok(ProbeElements {
+            prober: self,
+            variant: None,
+            element: None,
+            index: 0,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ProbeElements> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ProbeElements> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ProbeElements> {
+        Ok(ProbeElements {
+            prober: self.child(variant),
+            variant: Some((self, variant)),
+            element: None,
+            index: 0,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ProbeMap> {
+        Ok(ProbeMap {
+            prober: self,
+            key: None,
+            value: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<ProbeMembers> {
+        Ok(ProbeMembers {
+            prober: self,
+            variant: None,
+            members: Vec::new(),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ProbeMembers> {
+        Ok(ProbeMembers {
+            prober: self.child(variant),
+            variant: Some((self, variant)),
+            members: Vec::new(),
+        })
+    }
+}
+
+/// Wraps the shape of the contents of an enum variant in an object with the
+/// variant's tagged name.
+fn variant_shape(variant: Option<(Prober, &'static str)>, shape: Shape) -> Result<Shape> {
+    match variant {
+        Some((prober, name)) => prober
+            .member(name, shape)
+            .map(|member| Shape::Object(vec![member])),
+        None => Ok(shape),
+    }
+}
+
+struct ProbeElements {
+    prober: Prober,
+    variant: Option<(Prober, &'static str)>,
+    element: Option<Shape>,
+    index: usize,
+}
+
+impl ProbeElements {
+    fn add<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let prober = self.prober.child(&self.index.to_string());
+        self.index += 1;
+
+        let shape = try!(value.serialize(prober.clone()));
+        match self.element {
+            Some(ref element) if *element != shape => Err(prober.error(format_args!(
+                "elements have different tags, `{}` and `{}`",
+                element.tag(),
+                shape.tag()
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.element = Some(shape);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> Result<Shape> {
+        let element = match self.element {
+            Some(element) => element,
+            None => {
+                return Err(self.prober.error(
+                    "cannot describe an empty array, the sample needs an element",
+                ))
+            }
+        };
+        variant_shape(self.variant, Shape::Array(Box::new(element)))
+    }
+}
+
+impl ser::SerializeSeq for ProbeElements {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ProbeElements {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ProbeElements {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ProbeElements {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        self.finish()
+    }
+}
+
+struct ProbeMap {
+    prober: Prober,
+    key: Option<String>,
+    value: Option<Shape>,
+}
+
+impl ser::SerializeMap for ProbeMap {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        if !is_member_name(key) {
+            return Err(self.prober.error(
+                "cannot describe a map whose keys are not strings, which serializes as pairs",
+            ));
+        }
+        self.key = Some(match try!(to_value(key)) {
+            Value::String(key) => key,
+            key => key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        let prober = self.prober.child(&key);
+        let shape = try!(value.serialize(prober.clone()));
+        match self.value {
+            Some(ref value) if *value != shape => Err(prober.error(format_args!(
+                "values have different tags, `{}` and `{}`",
+                value.tag(),
+                shape.tag()
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.value = Some(shape);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<Shape> {
+        match self.value {
+            Some(value) => Ok(Shape::Map(Box::new(value))),
+            None => Err(self.prober.error(
+                "cannot describe an empty map, the sample needs an entry",
+            )),
+        }
+    }
+}
+
+struct ProbeMembers {
+    prober: Prober,
+    variant: Option<(Prober, &'static str)>,
+    members: Vec<(String, Shape)>,
+}
+
+impl ProbeMembers {
+    fn add<T: ?Sized>(&mut self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let prober = self.prober.child(name);
+        let shape = try!(value.serialize(prober.clone()));
+        let member = try!(prober.member(name, shape));
+        self.members.push(member);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for ProbeMembers {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(name, value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(Shape::Object(self.members))
+    }
+}
+
+impl ser::SerializeStructVariant for ProbeMembers {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.add(name, value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        variant_shape(self.variant, Shape::Object(self.members))
+    }
+}
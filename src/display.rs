@@ -0,0 +1,295 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Readable renderings of values for logs and operator tooling.
+//!
+//! The layout is indented like YAML: one object member per line as
+//! `name: value`, array elements prefixed by `- ` and set elements by `+ `.
+//! Names and strings are only quoted when they would otherwise be misread,
+//! timestamps are shown as `!timestamp 2017-03-04T05:06:07Z` and data as
+//! its length and first bytes in hex.
+//!
+//! The output is for people to read. It is not TJSON, nor YAML, and can't be
+//! parsed back: data is summarized, and long strings and deep containers are
+//! cut short if the config says so.
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::display::{self, DisplayConfig};
+//!
+//! let value = tjson!({
+//!     "name:s": "web-1",
+//!     "ports:A<u>": [80, 443],
+//!     "motd:s": "Welcome! Maintenance is scheduled for Sunday.",
+//! });
+//!
+//! let config = DisplayConfig::new().max_string_len(16).sorted(true);
+//! assert_eq!(
+//!     display::to_human_string_with(&config, &value),
+//!     "motd:s: Welcome! Mainten… (45 chars)\n\
+//!      name:s: web-1\n\
+//!      ports:A<u>:\n\
+//!      \x20 - 80\n\
+//!      \x20 - 443\n"
+//! );
+//! # }
+//! ```
+
+use std::fmt::Write;
+
+use ser::to_string;
+use timestamp;
+use value::Value;
+
+/// How many bytes of data to show before summarizing the rest.
+const DATA_PREVIEW: usize = 16;
+
+/// Settings for `to_human_string_with`.
+#[derive(Clone, Debug, Default)]
+pub struct DisplayConfig {
+    max_depth: Option<usize>,
+    max_string_len: Option<usize>,
+    sorted: bool,
+}
+
+impl DisplayConfig {
+    /// Settings which show everything, in the order of the map.
+    pub fn new() -> Self {
+        DisplayConfig::default()
+    }
+
+    /// Show arrays, sets and objects nested more than this deep as just
+    /// their number of entries. At depth 0 the value itself is summarized if
+    /// it is a container.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Show only the first `len` characters of longer strings, followed by
+    /// `…` and the string's full length.
+    pub fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = Some(len);
+        self
+    }
+
+    /// Show object members in name order and set elements in the order of
+    /// their compact TJSON text, whatever the order of the map or set, so
+    /// that the output is the same with and without the `preserve_order`
+    /// feature.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    fn summarizes(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth >= max)
+    }
+}
+
+/// Render a value for people to read, showing everything. See the
+/// [module documentation](index.html) for the layout.
+pub fn to_human_string(value: &Value) -> String {
+    to_human_string_with(&DisplayConfig::new(), value)
+}
+
+/// Render a value for people to read, with the given settings.
+///
+/// Every line, including the last, ends in a newline.
+pub fn to_human_string_with(config: &DisplayConfig, value: &Value) -> String {
+    let mut renderer = Renderer {
+        config: config,
+        out: String::new(),
+    };
+    renderer.value(value, 0, 0);
+    renderer.out.push('\n');
+    renderer.out
+}
+
+struct Renderer<'a> {
+    config: &'a DisplayConfig,
+    out: String,
+}
+
+impl<'a> Renderer<'a> {
+    /// Writes a value whose first line has already been started. Any further
+    /// lines are indented by `indent`.
+    fn value(&mut self, value: &Value, indent: usize, depth: usize) {
+        let summarize = self.config.summarizes(depth);
+
+        match *value {
+            Value::Undefined => self.out.push_str("null"),
+            Value::Bool(b) => self.out.push_str(if b { "true" } else { "false" }),
+            Value::Number(ref n) => {
+                let _ = write!(self.out, "{}", n);
+            }
+            Value::String(ref s) => self.string(s),
+            Value::Timestamp(ref timestamp) => {
+                let _ = write!(self.out, "!timestamp {}", timestamp::format(timestamp));
+            }
+            Value::Data(ref data) => {
+                let _ = write!(self.out, "!data ({} bytes)", data.len());
+                for byte in data.iter().take(DATA_PREVIEW) {
+                    let _ = write!(self.out, " {:02x}", byte);
+                }
+                if data.len() > DATA_PREVIEW {
+                    self.out.push_str(" …");
+                }
+            }
+            Value::Array(_) | Value::Set(_) | Value::Object(_)
+                if summarize || value.is_empty() == Some(true) => self.summary(value),
+            Value::Array(ref array) => self.elements("- ", array.iter(), indent, depth),
+            Value::Set(ref set) => {
+                let mut elements: Vec<_> = set.iter().collect();
+                if self.config.sorted {
+                    elements.sort_by_key(|element| to_string(element).unwrap_or_default());
+                }
+                self.elements("+ ", elements.into_iter(), indent, depth);
+            }
+            Value::Object(ref map) => {
+                let mut members: Vec<_> = map.iter().collect();
+                if self.config.sorted {
+                    members.sort_by(|a, b| a.0.cmp(b.0));
+                }
+
+                for (i, (name, value)) in members.into_iter().enumerate() {
+                    if i > 0 {
+                        self.newline(indent);
+                    }
+                    self.name(name);
+                    self.out.push(':');
+                    if is_block(value, depth + 1, self.config) {
+                        self.newline(indent + 2);
+                        self.value(value, indent + 2, depth + 1);
+                    } else {
+                        self.out.push(' ');
+                        self.value(value, indent + 2, depth + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes an array, set or object as just its number of entries, which
+    /// for an empty one is just its brackets.
+    fn summary(&mut self, value: &Value) {
+        let (open, noun, close) = match *value {
+            Value::Set(_) => ("!set [", "element", "]"),
+            Value::Object(_) => ("{", "member", "}"),
+            _ => ("[", "element", "]"),
+        };
+        self.out.push_str(open);
+        match value.len() {
+            Some(len) if len > 0 => {
+                let _ = write!(self.out, "… {}", count(len, noun));
+            }
+            _ => {}
+        }
+        self.out.push_str(close);
+    }
+
+    /// Writes the elements of an array or set, the first on the line already
+    /// started.
+    fn elements<'v, I>(&mut self, prefix: &str, elements: I, indent: usize, depth: usize)
+    where
+        I: Iterator<Item = &'v Value>,
+    {
+        for (i, element) in elements.enumerate() {
+            if i > 0 {
+                self.newline(indent);
+            }
+            self.out.push_str(prefix);
+            self.value(element, indent + 2, depth + 1);
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        for _ in 0..indent {
+            self.out.push(' ');
+        }
+    }
+
+    fn name(&mut self, name: &str) {
+        if is_plain(name) {
+            self.out.push_str(name);
+        } else {
+            self.quoted(name);
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        let (shown, total) = match self.config.max_string_len {
+            Some(max) => {
+                match s.char_indices().nth(max) {
+                    Some((end, _)) => (&s[..end], Some(s.chars().count())),
+                    None => (s, None),
+                }
+            }
+            None => (s, None),
+        };
+
+        if is_plain(shown) && !looks_like_scalar(shown) {
+            self.out.push_str(shown);
+        } else {
+            self.quoted(shown);
+        }
+        if let Some(total) = total {
+            let _ = write!(self.out, "… ({} chars)", total);
+        }
+    }
+
+    fn quoted(&mut self, s: &str) {
+        match to_string(s) {
+            Ok(quoted) => self.out.push_str(&quoted),
+            Err(_) => self.out.push_str("\"\""),
+        }
+    }
+}
+
+/// Whether a value goes on the lines after its member name rather than
+/// after the colon.
+fn is_block(value: &Value, depth: usize, config: &DisplayConfig) -> bool {
+    if config.summarizes(depth) {
+        return false;
+    }
+    match *value {
+        Value::Array(_) | Value::Set(_) | Value::Object(_) => value.is_empty() == Some(false),
+        _ => false,
+    }
+}
+
+/// Whether a name or string can be shown without quotes without being
+/// mistaken for something else.
+fn is_plain(s: &str) -> bool {
+    let first = match s.chars().next() {
+        Some(first) => first,
+        None => return false,
+    };
+
+    !"-+?:,[]{}#&*!|>'\"%@`~".contains(first) && !s.ends_with(':') &&
+        !s.starts_with(' ') && !s.ends_with(' ') && !s.contains(": ") &&
+        !s.contains(" #") && !s.chars().any(char::is_control)
+}
+
+/// Whether a string would read as some other kind of value if it weren't
+/// quoted.
+fn looks_like_scalar(s: &str) -> bool {
+    s == "null" || s == "true" || s == "false" || s.parse::<f64>().is_ok()
+}
+
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
@@ -0,0 +1,502 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The binary-to-text encodings of TJSON's data tags
+//!
+//! Base64url, base32 and hex as RFC 4648 defines them, for encoding values
+//! ahead of time or checking fragments of documents. TJSON writes base32
+//! and hex in lowercase. Decoding is strict: whitespace, characters of other
+//! alphabets (including `+` and `/` of standard base64, and uppercase base32
+//! and hex digits), misplaced padding, and unused bits which aren't zero are
+//! all rejected, so each byte string has exactly one encoding.
+//!
+//! ```rust
+//! use tjson::encoding::{self, DecodeErrorKind};
+//!
+//! assert_eq!(encoding::b64url_encode(b"\xfb\xff"), "-_8");
+//! assert_eq!(encoding::b64url_decode("-_8").unwrap(), b"\xfb\xff");
+//! assert_eq!(encoding::b32_encode(b"\xfb\xff"), "7p7q");
+//!
+//! let err = encoding::hex_decode("4A").unwrap_err();
+//! assert_eq!(err.kind(), DecodeErrorKind::InvalidByte);
+//! assert_eq!(err.offset(), 1);
+//! ```
+
+use std::error;
+use std::fmt;
+
+const B64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const B32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// Why decoding failed, and at which byte of the input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    offset: usize,
+    kind: DecodeErrorKind,
+}
+
+/// The reasons decoding can fail.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// The byte isn't in the encoding's alphabet. For the base64url and
+    /// base32 decoders this doesn't include `=`, which is `InvalidPadding`.
+    InvalidByte,
+
+    /// The input ends partway through a group. The offset is where the
+    /// incomplete group starts.
+    InvalidLength,
+
+    /// A `=` where there must not be one: anywhere in unpadded base64url
+    /// or base32, or anywhere but completing the last group in padded
+    /// base64url.
+    InvalidPadding,
+
+    /// The last character of base64url or base32 encodes bits beyond the
+    /// end of the data which aren't zero.
+    TrailingBits,
+}
+
+impl DecodeError {
+    fn new(offset: usize, kind: DecodeErrorKind) -> Self {
+        DecodeError {
+            offset: offset,
+            kind: kind,
+        }
+    }
+
+    /// Position of the byte of the input at which decoding failed,
+    /// counting from 0.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Why decoding failed.
+    pub fn kind(&self) -> DecodeErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.kind {
+            DecodeErrorKind::InvalidByte => "invalid byte",
+            DecodeErrorKind::InvalidLength => "incomplete group",
+            DecodeErrorKind::InvalidPadding => "invalid padding",
+            DecodeErrorKind::TrailingBits => "nonzero trailing bits",
+        };
+        write!(f, "{} at offset {}", reason, self.offset)
+    }
+}
+
+impl error::Error for DecodeError {
+    fn description(&self) -> &str {
+        "invalid encoded data"
+    }
+}
+
+/// Encodes bytes as base64url without padding, as the `d` and `d64` tags
+/// require.
+pub fn b64url_encode(data: &[u8]) -> String {
+    encode(Alphabet::Base64Url, data, false)
+}
+
+/// Encodes bytes as base64url padded with `=` to a multiple of four
+/// characters.
+pub fn b64url_pad_encode(data: &[u8]) -> String {
+    encode(Alphabet::Base64Url, data, true)
+}
+
+/// Decodes base64url without padding.
+pub fn b64url_decode<T>(input: &T) -> Result<Vec<u8>, DecodeError>
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    decode(Alphabet::Base64Url, input.as_ref())
+}
+
+// Not public API. Should be pub(crate).
+/// Checks that `b64url_decode` would decode the input, failing as it would,
+/// without decoding it.
+#[doc(hidden)]
+pub fn b64url_check(input: &[u8]) -> Result<(), DecodeError> {
+    let alphabet = Alphabet::Base64Url;
+    let mut last = 0;
+    for (offset, &byte) in input.iter().enumerate() {
+        last = try!(alphabet.digit(byte, offset));
+    }
+
+    // Only the last group may be short, and its last digit says how it ends
+    let digits = input.len() % alphabet.group_chars();
+    let unused = match digits {
+        0 => return Ok(()),
+        1 => {
+            let start = input.len() - digits;
+            return Err(DecodeError::new(start, DecodeErrorKind::InvalidLength));
+        }
+        2 => 4,
+        _ => 2,
+    };
+    if last & ((1 << unused) - 1) != 0 {
+        return Err(DecodeError::new(input.len() - 1, DecodeErrorKind::TrailingBits));
+    }
+    Ok(())
+}
+
+/// Decodes base64url padded with `=` to a multiple of four characters.
+pub fn b64url_pad_decode<T>(input: &T) -> Result<Vec<u8>, DecodeError>
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    let input = input.as_ref();
+    let data_len = input.iter().position(|&b| b == b'=').unwrap_or(input.len());
+    let data = try!(decode(Alphabet::Base64Url, &input[..data_len]));
+
+    // Only the last group may be padded, and only to complete it
+    let padding = &input[data_len..];
+    if let Some(position) = padding.iter().position(|&b| b != b'=') {
+        // Either data follows the padding, or something which isn't data
+        try!(Alphabet::Base64Url.digit(padding[position], data_len + position));
+        return Err(DecodeError::new(data_len, DecodeErrorKind::InvalidPadding));
+    }
+    let expected = (4 - data_len % 4) % 4;
+    if padding.len() > expected {
+        return Err(DecodeError::new(data_len + expected, DecodeErrorKind::InvalidPadding));
+    }
+    if padding.len() < expected {
+        let offset = data_len - data_len % 4;
+        return Err(DecodeError::new(offset, DecodeErrorKind::InvalidLength));
+    }
+    Ok(data)
+}
+
+/// Encodes bytes as lowercase base32 without padding, as the `d32` tag
+/// requires.
+pub fn b32_encode(data: &[u8]) -> String {
+    encode(Alphabet::Base32, data, false)
+}
+
+/// Decodes lowercase base32 without padding.
+pub fn b32_decode<T>(input: &T) -> Result<Vec<u8>, DecodeError>
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    decode(Alphabet::Base32, input.as_ref())
+}
+
+/// Encodes bytes as lowercase hex, as the `d16` tag requires.
+pub fn hex_encode(data: &[u8]) -> String {
+    encode(Alphabet::Base16, data, false)
+}
+
+/// Decodes lowercase hex.
+pub fn hex_decode<T>(input: &T) -> Result<Vec<u8>, DecodeError>
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    decode(Alphabet::Base16, input.as_ref())
+}
+
+/// The encodings, each of which turns groups of bytes into groups of
+/// characters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Alphabet {
+    Base16,
+    Base32,
+    Base64Url,
+}
+
+impl Alphabet {
+    /// Bits encoded by each character.
+    fn bits(self) -> usize {
+        match self {
+            Alphabet::Base16 => 4,
+            Alphabet::Base32 => 5,
+            Alphabet::Base64Url => 6,
+        }
+    }
+
+    /// Bytes in a whole group.
+    fn group_len(self) -> usize {
+        match self {
+            Alphabet::Base16 => 1,
+            Alphabet::Base32 => 5,
+            Alphabet::Base64Url => 3,
+        }
+    }
+
+    /// Characters in a whole group.
+    fn group_chars(self) -> usize {
+        self.group_len() * 8 / self.bits()
+    }
+
+    fn chars(self) -> &'static [u8] {
+        match self {
+            Alphabet::Base16 => HEX,
+            Alphabet::Base32 => B32,
+            Alphabet::Base64Url => B64URL,
+        }
+    }
+
+    fn digit(self, byte: u8, offset: usize) -> Result<u64, DecodeError> {
+        let digit = match (self, byte) {
+            (Alphabet::Base16, b'0'...b'9') => byte - b'0',
+            (Alphabet::Base16, b'a'...b'f') => byte - b'a' + 10,
+            (Alphabet::Base32, b'a'...b'z') => byte - b'a',
+            (Alphabet::Base32, b'2'...b'7') => byte - b'2' + 26,
+            (Alphabet::Base64Url, b'A'...b'Z') => byte - b'A',
+            (Alphabet::Base64Url, b'a'...b'z') => byte - b'a' + 26,
+            (Alphabet::Base64Url, b'0'...b'9') => byte - b'0' + 52,
+            (Alphabet::Base64Url, b'-') => 62,
+            (Alphabet::Base64Url, b'_') => 63,
+            (Alphabet::Base32, b'=') | (Alphabet::Base64Url, b'=') => {
+                return Err(DecodeError::new(offset, DecodeErrorKind::InvalidPadding))
+            }
+            _ => return Err(DecodeError::new(offset, DecodeErrorKind::InvalidByte)),
+        };
+        Ok(digit as u64)
+    }
+}
+
+fn encode(alphabet: Alphabet, data: &[u8], pad: bool) -> String {
+    let group_len = alphabet.group_len();
+    let mut out = String::with_capacity((data.len() / group_len + 1) * alphabet.group_chars());
+    for chunk in data.chunks(group_len) {
+        push_group(alphabet, chunk, pad, &mut out);
+    }
+    out
+}
+
+/// Encodes up to a whole group of bytes, as however many characters the
+/// bits of those bytes are spread over.
+fn push_group(alphabet: Alphabet, chunk: &[u8], pad: bool, out: &mut String) {
+    let group_len = alphabet.group_len();
+    let bits = chunk.iter().enumerate().fold(0u64, |bits, (i, &byte)| {
+        bits | (byte as u64) << (8 * (group_len - 1 - i))
+    });
+    let group_chars = alphabet.group_chars();
+    let width = alphabet.bits();
+    let chars = (chunk.len() * 8 + width - 1) / width;
+    for i in 0..group_chars {
+        if i < chars {
+            let digit = bits >> (width * (group_chars - 1 - i)) & ((1 << width) - 1);
+            out.push(alphabet.chars()[digit as usize] as char);
+        } else if pad {
+            out.push('=');
+        }
+    }
+}
+
+/// Decodes unpadded input, reporting any `=` as invalid padding.
+fn decode(alphabet: Alphabet, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut data = Vec::with_capacity(input.len() * alphabet.bits() / 8);
+    let mut decoder = Decoder::new(alphabet);
+    try!(decoder.decode(input, &mut data));
+    try!(decoder.finish(&mut data));
+    Ok(data)
+}
+
+/// Encodes data a piece at a time, as base64url or base32 without padding
+/// or as hex, for data too large to hold in memory all at once.
+///
+/// Pieces may be any length: base64url encodes three bytes at a time and
+/// base32 five, so the bytes of an incomplete group are held back until the
+/// next piece or `finish`.
+///
+/// ```rust
+/// use tjson::encoding::{self, Encoder};
+///
+/// let mut encoder = Encoder::b64url();
+/// let mut out = String::new();
+/// for piece in b"streamed data".chunks(2) {
+///     encoder.encode(piece, &mut out);
+/// }
+/// encoder.finish(&mut out);
+/// assert_eq!(out, encoding::b64url_encode(b"streamed data"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    alphabet: Alphabet,
+    /// The bytes of the group in progress.
+    held: [u8; 5],
+    held_len: usize,
+}
+
+impl Encoder {
+    fn new(alphabet: Alphabet) -> Self {
+        Encoder {
+            alphabet: alphabet,
+            held: [0; 5],
+            held_len: 0,
+        }
+    }
+
+    /// An encoder to unpadded base64url, for the `d` and `d64` tags.
+    pub fn b64url() -> Self {
+        Encoder::new(Alphabet::Base64Url)
+    }
+
+    /// An encoder to unpadded lowercase base32, for the `d32` tag.
+    pub fn b32() -> Self {
+        Encoder::new(Alphabet::Base32)
+    }
+
+    /// An encoder to lowercase hex, for the `d16` tag.
+    pub fn hex() -> Self {
+        Encoder::new(Alphabet::Base16)
+    }
+
+    /// Appends the encoding of as much of `data` as can be encoded so far.
+    pub fn encode(&mut self, mut data: &[u8], out: &mut String) {
+        let group_len = self.alphabet.group_len();
+
+        // Complete the group started by earlier pieces
+        while self.held_len > 0 && !data.is_empty() {
+            self.held[self.held_len] = data[0];
+            self.held_len += 1;
+            data = &data[1..];
+            if self.held_len == group_len {
+                push_group(self.alphabet, &self.held[..group_len], false, out);
+                self.held_len = 0;
+            }
+        }
+
+        let whole = data.len() - data.len() % group_len;
+        for group in data[..whole].chunks(group_len) {
+            push_group(self.alphabet, group, false, out);
+        }
+        for &byte in &data[whole..] {
+            self.held[self.held_len] = byte;
+            self.held_len += 1;
+        }
+    }
+
+    /// Appends the encoding of any bytes held back, leaving the encoder
+    /// ready to start again.
+    pub fn finish(&mut self, out: &mut String) {
+        if self.held_len > 0 {
+            push_group(self.alphabet, &self.held[..self.held_len], false, out);
+            self.held_len = 0;
+        }
+    }
+}
+
+/// Decodes data a piece at a time, from unpadded base64url or base32 or
+/// from hex, for data too large to hold in memory all at once.
+///
+/// Pieces may be any length, and errors have the same offsets as they
+/// would decoding the whole input at once.
+///
+/// ```rust
+/// use tjson::encoding::{Decoder, DecodeErrorKind};
+///
+/// let mut decoder = Decoder::b64url();
+/// let mut out = Vec::new();
+/// for piece in [&b"c3Ry"[..], b"ZWF", b"tZ", b"WQ"].iter() {
+///     decoder.decode(piece, &mut out).unwrap();
+/// }
+/// decoder.finish(&mut out).unwrap();
+/// assert_eq!(out, b"streamed");
+///
+/// let mut decoder = Decoder::b64url();
+/// decoder.decode(b"c3Ry", &mut out).unwrap();
+/// let err = decoder.decode(b"ZW+", &mut out).unwrap_err();
+/// assert_eq!(err.kind(), DecodeErrorKind::InvalidByte);
+/// assert_eq!(err.offset(), 6);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    alphabet: Alphabet,
+    /// The bits of the digits of the group in progress.
+    bits: u64,
+    /// How many digits of the group in progress have been seen.
+    digits: usize,
+    /// How many bytes of input have been decoded.
+    offset: usize,
+}
+
+impl Decoder {
+    fn new(alphabet: Alphabet) -> Self {
+        Decoder {
+            alphabet: alphabet,
+            bits: 0,
+            digits: 0,
+            offset: 0,
+        }
+    }
+
+    /// A decoder from unpadded base64url, for the `d` and `d64` tags.
+    pub fn b64url() -> Self {
+        Decoder::new(Alphabet::Base64Url)
+    }
+
+    /// A decoder from unpadded lowercase base32, for the `d32` tag.
+    pub fn b32() -> Self {
+        Decoder::new(Alphabet::Base32)
+    }
+
+    /// A decoder from lowercase hex, for the `d16` tag.
+    pub fn hex() -> Self {
+        Decoder::new(Alphabet::Base16)
+    }
+
+    /// Appends the bytes of each group of `input` completed so far. On
+    /// failure, bytes before the group which failed may have been appended.
+    pub fn decode(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let width = self.alphabet.bits();
+        let group_chars = self.alphabet.group_chars();
+        for &byte in input {
+            let digit = try!(self.alphabet.digit(byte, self.offset));
+            self.bits |= digit << (width * (group_chars - 1 - self.digits));
+            self.digits += 1;
+            if self.digits == group_chars {
+                self.push_bytes(self.alphabet.group_len(), out);
+            }
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    /// Checks that the input ended on a complete group, appending the bytes
+    /// of a final short group of base64url or base32.
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let start = self.offset - self.digits;
+        let width = self.alphabet.bits();
+        let digits = self.digits;
+        if digits == 0 {
+            return Ok(());
+        }
+
+        // The last digit must start a byte, and may only end one with zeros
+        let len = digits * width / 8;
+        if len == (digits - 1) * width / 8 {
+            self.bits = 0;
+            self.digits = 0;
+            return Err(DecodeError::new(start, DecodeErrorKind::InvalidLength));
+        }
+        let unused = 8 * (self.alphabet.group_len() - len);
+        if self.bits & ((1 << unused) - 1) != 0 {
+            self.bits = 0;
+            self.digits = 0;
+            return Err(DecodeError::new(start + digits - 1, DecodeErrorKind::TrailingBits));
+        }
+        self.push_bytes(len, out);
+        Ok(())
+    }
+
+    /// Appends the first `len` bytes of the group in progress, and starts
+    /// the next.
+    fn push_bytes(&mut self, len: usize, out: &mut Vec<u8>) {
+        let group_len = self.alphabet.group_len();
+        for i in 0..len {
+            out.push((self.bits >> (8 * (group_len - 1 - i))) as u8);
+        }
+        self.bits = 0;
+        self.digits = 0;
+    }
+}
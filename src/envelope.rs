@@ -0,0 +1,324 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Versioned document envelopes
+//!
+//! An envelope wraps a payload object together with the kind of document it
+//! holds and the version of that kind's schema:
+//!
+//! ```text
+//! {"v:u": "1", "kind:s": "user", "payload:O": {...}}
+//! ```
+//!
+//! Receivers typically parse an `Envelope<Value>`, then open it with
+//! `Envelope::open_expecting` to check the kind and version before
+//! deserializing the payload into its concrete type:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! extern crate tjson;
+//!
+//! use tjson::Value;
+//! use tjson::envelope::Envelope;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! fn main() {
+//!     let sent = Envelope::new(1, "user", User { name: "alice".to_owned() });
+//!     let bytes = tjson::to_vec(&sent).unwrap();
+//!
+//!     let received: Envelope<Value> = tjson::from_slice(&bytes).unwrap();
+//!     let user: Envelope<User> = received.open_expecting("user", 2).unwrap();
+//!     assert_eq!(user.payload.name, "alice");
+//! }
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, IgnoredAny, MapAccess,
+                Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use de::from_slice;
+use error::Result;
+use value::{Value, from_value};
+
+const VERSION: &str = "v:u";
+const KIND: &str = "kind:s";
+const PAYLOAD: &str = "payload:O";
+const FIELDS: &[&str] = &[VERSION, KIND, PAYLOAD];
+
+/// A payload tagged with its kind and schema version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope<T> {
+    /// Version of the payload's schema, written as a `:u` string.
+    pub version: u64,
+
+    /// What kind of document the payload is.
+    pub kind: String,
+
+    /// The enveloped document, which should serialize as an object.
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wraps a payload in an envelope.
+    pub fn new<K>(version: u64, kind: K, payload: T) -> Self
+    where
+        K: Into<String>,
+    {
+        Envelope {
+            version: version,
+            kind: kind.into(),
+            payload: payload,
+        }
+    }
+}
+
+impl Envelope<Value> {
+    /// Checks that the envelope holds the expected kind of document at a
+    /// version no newer than `max_version`, then deserializes its payload.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the kind is not the one expected, if the version is newer than
+    /// `max_version`, or if the payload cannot be deserialized as a `T`, in
+    /// which case the message names the kind and includes the payload's error.
+    pub fn open_expecting<T>(self, kind: &str, max_version: u64) -> Result<Envelope<T>>
+    where
+        T: DeserializeOwned,
+    {
+        if self.kind != kind {
+            return Err(de::Error::custom(format_args!(
+                "unexpected envelope kind {:?}, expected {:?}",
+                self.kind,
+                kind
+            )));
+        }
+
+        if self.version > max_version {
+            return Err(de::Error::custom(format_args!(
+                "{:?} envelope version {} is newer than the newest supported version {}",
+                self.kind,
+                self.version,
+                max_version
+            )));
+        }
+
+        match from_value(self.payload) {
+            Ok(payload) => Ok(Envelope::new(self.version, self.kind, payload)),
+            Err(err) => Err(de::Error::custom(format_args!(
+                "invalid {:?} envelope payload: {}",
+                self.kind,
+                err
+            ))),
+        }
+    }
+}
+
+/// Reads the version and kind of an enveloped TJSON document without building
+/// its payload.
+///
+/// The payload is still scanned to find the end of the document, and must be
+/// syntactically valid, but none of it is kept.
+///
+/// ```rust
+/// let (version, kind) = tjson::envelope::peek_kind(
+///     br#"{"v:u": "3", "kind:s": "user", "payload:O": {"name:s": "alice"}}"#,
+/// ).unwrap();
+///
+/// assert_eq!(version, 3);
+/// assert_eq!(kind, "user");
+/// ```
+pub fn peek_kind(slice: &[u8]) -> Result<(u64, String)> {
+    let envelope: Envelope<IgnoredAny> = try!(from_slice(slice));
+    Ok((envelope.version, envelope.kind))
+}
+
+impl<T> Serialize for Envelope<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = try!(serializer.serialize_struct("Envelope", 3));
+        try!(state.serialize_field(VERSION, &self.version));
+        try!(state.serialize_field(KIND, &self.kind));
+        try!(state.serialize_field(PAYLOAD, &self.payload));
+        state.end()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Envelope<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Envelope", FIELDS, EnvelopeVisitor(PhantomData))
+    }
+}
+
+struct EnvelopeVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for EnvelopeVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Envelope<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an envelope object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<Envelope<T>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut version = None;
+        let mut kind = None;
+        let mut payload = None;
+
+        while let Some(field) = try!(map.next_key()) {
+            match field {
+                Field::Version => {
+                    if version.is_some() {
+                        return Err(de::Error::duplicate_field(VERSION));
+                    }
+                    let Version(value) = try!(map.next_value());
+                    version = Some(value);
+                }
+                Field::Kind => {
+                    if kind.is_some() {
+                        return Err(de::Error::duplicate_field(KIND));
+                    }
+                    kind = Some(try!(map.next_value()));
+                }
+                Field::Payload => {
+                    if payload.is_some() {
+                        return Err(de::Error::duplicate_field(PAYLOAD));
+                    }
+                    payload = Some(try!(map.next_value()));
+                }
+                Field::Other => {
+                    try!(map.next_value::<IgnoredAny>());
+                }
+            }
+        }
+
+        Ok(Envelope {
+            version: try!(version.ok_or_else(|| de::Error::missing_field(VERSION))),
+            kind: try!(kind.ok_or_else(|| de::Error::missing_field(KIND))),
+            payload: try!(payload.ok_or_else(|| de::Error::missing_field(PAYLOAD))),
+        })
+    }
+}
+
+/// Versions are unsigned integers, which TJSON writes as strings. Those are
+/// read back as integers under the `u` tag, but a deserializer which leaves
+/// them as strings is handled too, provided they have no sign or leading
+/// zeros.
+struct Version(u64);
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Version, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct VersionVisitor;
+
+        impl<'de> Visitor<'de> for VersionVisitor {
+            type Value = Version;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an unsigned integer string")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> ::std::result::Result<Version, E>
+            where
+                E: de::Error,
+            {
+                Ok(Version(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> ::std::result::Result<Version, E>
+            where
+                E: de::Error,
+            {
+                if value < 0 {
+                    return Err(de::Error::invalid_value(de::Unexpected::Signed(value), &self));
+                }
+                Ok(Version(value as u64))
+            }
+
+            fn visit_str<E>(self, text: &str) -> ::std::result::Result<Version, E>
+            where
+                E: de::Error,
+            {
+                let canonical = !text.is_empty() && (text == "0" || !text.starts_with('0')) &&
+                    text.bytes().all(|b| b.is_ascii_digit());
+
+                match text.parse() {
+                    Ok(version) if canonical => Ok(Version(version)),
+                    _ => Err(de::Error::invalid_value(de::Unexpected::Str(text), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(VersionVisitor)
+    }
+}
+
+enum Field {
+    Version,
+    Kind,
+    Payload,
+    Other,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Field, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FieldVisitor;
+
+        impl<'de> Visitor<'de> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an envelope member name")
+            }
+
+            fn visit_str<E>(self, value: &str) -> ::std::result::Result<Field, E>
+            where
+                E: de::Error,
+            {
+                Ok(match value {
+                    VERSION => Field::Version,
+                    KIND => Field::Kind,
+                    PAYLOAD => Field::Payload,
+                    _ => Field::Other,
+                })
+            }
+        }
+
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
@@ -11,18 +11,29 @@
 use std::error;
 use std::fmt::{self, Debug, Display};
 use std::io;
+use std::path::{Path, PathBuf};
 use std::result;
+use std::sync::Arc;
+use std::u32;
 
 use serde::de;
-use serde::ser;
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+use map::InvalidKey;
+use schema::SchemaViolation;
+use ser::OutputBudgetExceeded;
+use tag::TagMismatch;
+use value::PointerError;
 
 /// This type represents all possible errors that can occur when serializing or
 /// deserializing JSON data.
+#[derive(Clone)]
 pub struct Error {
-    /// This `Box` allows us to keep the size of `Error` as small as possible. A
-    /// larger `Error` type was substantially slower due to all the functions
-    /// that pass around `Result<T, Error>`.
-    err: Box<ErrorImpl>,
+    /// Errors which are no more than a code and a position are kept inline,
+    /// so that making one doesn't allocate, and the rest are boxed. Either
+    /// way `Error` stays two words: a larger `Error` type was substantially
+    /// slower due to all the functions that pass around `Result<T, Error>`.
+    repr: Repr,
 }
 
 /// Alias for a `Result` with the error type `tjson::Error`.
@@ -34,7 +45,10 @@ impl Error {
     /// Characters in the first line of the input (before the first newline
     /// character) are in line 1.
     pub fn line(&self) -> usize {
-        self.err.line
+        match self.repr {
+            Repr::Simple(simple) => simple.line as usize,
+            Repr::Boxed(ref err) => err.line,
+        }
     }
 
     /// One-based column number at which the error was detected.
@@ -45,7 +59,58 @@ impl Error {
     /// Note that errors may occur in column 0, for example if a read from an IO
     /// stream fails immediately following a previously read newline character.
     pub fn column(&self) -> usize {
-        self.err.column
+        match self.repr {
+            Repr::Simple(simple) => simple.column as usize,
+            Repr::Boxed(ref err) => err.column,
+        }
+    }
+
+    /// The file being read when the error occurred, for errors from functions
+    /// which open files themselves such as `tjson::from_file_mmap`.
+    ///
+    /// The path is also included at the start of the error's message.
+    pub fn path(&self) -> Option<&Path> {
+        match self.repr {
+            Repr::Simple(_) => None,
+            Repr::Boxed(ref err) => err.path.as_deref(),
+        }
+    }
+
+    /// Zero-based offset in bytes from the start of the input to the byte at
+    /// which a parsing error was detected, the same place that `line` and
+    /// `column` refer to.
+    ///
+    /// This is `None` for errors without a position, such as IO errors and
+    /// errors from serializing, and for UTF-16 input.
+    ///
+    /// ```rust
+    /// use tjson::Value;
+    ///
+    /// let input = b"[1,\n x]\xFF";
+    /// let err = tjson::de::from_slice_prefix::<Value>(input).unwrap_err();
+    /// assert_eq!((err.line(), err.column()), (2, 2));
+    /// assert_eq!(err.byte_offset(), Some(5));
+    /// assert_eq!(input[5], b'x');
+    /// ```
+    pub fn byte_offset(&self) -> Option<usize> {
+        match self.repr {
+            Repr::Simple(simple) => simple.offset(),
+            Repr::Boxed(ref err) => err.offset,
+        }
+    }
+
+    /// A stable, machine-readable identifier for the cause of this error.
+    ///
+    /// ```rust
+    /// use tjson::Value;
+    /// use tjson::error::ErrorCode;
+    ///
+    /// let err = tjson::from_str::<Value>("[1] 2").unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::TrailingCharacters);
+    /// assert_eq!(err.code().as_str(), "trailing_characters");
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        self.with_impl(|err| err.code.error_code())
     }
 
     /// Categorizes the cause of this error.
@@ -55,30 +120,7 @@ impl Error {
     /// - `Category::Data` - input data that is semantically incorrect
     /// - `Category::Eof` - unexpected end of the input data
     pub fn classify(&self) -> Category {
-        match self.err.code {
-            ErrorCode::Message(_) => Category::Data,
-            ErrorCode::Io(_) => Category::Io,
-            ErrorCode::EofWhileParsingList |
-            ErrorCode::EofWhileParsingObject |
-            ErrorCode::EofWhileParsingString |
-            ErrorCode::EofWhileParsingValue => Category::Eof,
-            ErrorCode::ExpectedColon |
-            ErrorCode::ExpectedListCommaOrEnd |
-            ErrorCode::ExpectedObjectCommaOrEnd |
-            ErrorCode::ExpectedObjectOrArray |
-            ErrorCode::ExpectedSomeIdent |
-            ErrorCode::ExpectedSomeValue |
-            ErrorCode::ExpectedSomeString |
-            ErrorCode::InvalidEscape |
-            ErrorCode::InvalidNumber |
-            ErrorCode::NumberOutOfRange |
-            ErrorCode::InvalidUnicodeCodePoint |
-            ErrorCode::KeyMustBeAString |
-            ErrorCode::LoneLeadingSurrogateInHexEscape |
-            ErrorCode::TrailingCharacters |
-            ErrorCode::UnexpectedEndOfHexEscape |
-            ErrorCode::RecursionLimitExceeded => Category::Syntax,
-        }
+        self.with_impl(|err| err.code.category())
     }
 
     /// Returns true if this error was caused by a failure to read or write
@@ -110,6 +152,112 @@ impl Error {
     pub fn is_eof(&self) -> bool {
         self.classify() == Category::Eof
     }
+
+    /// Returns true if this error was caused by an HTTP request body whose
+    /// `Content-Type` is not TJSON, in which case the body was not parsed.
+    pub fn is_unsupported_media_type(&self) -> bool {
+        match self.boxed_code() {
+            Some(&Code::UnsupportedMediaType(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if this error was caused by an HTTP request body longer
+    /// than the configured limit, in which case the body was not parsed.
+    pub fn is_body_too_large(&self) -> bool {
+        match self.boxed_code() {
+            Some(&Code::BodyTooLarge(..)) => true,
+            _ => false,
+        }
+    }
+
+    /// The place where a value didn't match the schema it was being written
+    /// against, if that is what caused this error.
+    pub fn schema_violation(&self) -> Option<&SchemaViolation> {
+        match self.boxed_code() {
+            Some(&Code::SchemaViolation(ref violation)) => Some(violation),
+            _ => None,
+        }
+    }
+
+    /// Where writing stopped because the output would have been longer than
+    /// the configured budget, if that is what caused this error.
+    pub fn output_budget_exceeded(&self) -> Option<&OutputBudgetExceeded> {
+        match self.boxed_code() {
+            Some(&Code::OutputBudgetExceeded(ref exceeded)) => Some(exceeded),
+            _ => None,
+        }
+    }
+
+    /// Where a document or member didn't hold what its tag says, if that is
+    /// what caused this error.
+    pub fn tag_mismatch(&self) -> Option<&TagMismatch> {
+        match self.boxed_code() {
+            Some(&Code::TagMismatch(ref mismatch)) => Some(mismatch),
+            _ => None,
+        }
+    }
+
+    /// The JSON pointer to the value a `tjson::transform` hook failed on,
+    /// if that is what caused this error. The hook's own error is the
+    /// cause of this one.
+    pub fn transform_pointer(&self) -> Option<&str> {
+        match self.boxed_code() {
+            Some(&Code::TransformFailed(ref pointer, _)) => Some(pointer),
+            _ => None,
+        }
+    }
+
+    /// Which token of a JSON pointer couldn't be followed, and why, if that
+    /// is what caused this error.
+    pub fn pointer_error(&self) -> Option<&PointerError> {
+        match self.boxed_code() {
+            Some(&Code::InvalidPointer(_, ref err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Position in the patch of the operation `Value::patch` stopped at, if
+    /// that is what caused this error.
+    pub fn patch_operation(&self) -> Option<usize> {
+        match self.boxed_code() {
+            Some(&Code::PatchFailed(index, _)) |
+            Some(&Code::PatchTestFailed(index, _)) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Wrap an error from outside the crate, such as one returned by a
+    /// fallible expression in `try_tjson!`.
+    ///
+    /// The new error has `ErrorCode::Data`, displays as `err` does, and keeps
+    /// `err` as its cause. If `err` is itself a `tjson::Error` it is returned
+    /// unchanged, so its code and position are not lost.
+    ///
+    /// ```rust
+    /// use std::error::Error as StdError;
+    /// use std::io;
+    ///
+    /// use tjson::Error;
+    /// use tjson::error::ErrorCode;
+    ///
+    /// let io = io::Error::new(io::ErrorKind::NotFound, "no such user");
+    /// let err = Error::custom_boxed(Box::new(io));
+    /// assert_eq!(err.code(), ErrorCode::Data);
+    /// assert_eq!(err.to_string(), "no such user");
+    /// # #[allow(deprecated)]
+    /// assert!(err.cause().is_some());
+    ///
+    /// let syntax = tjson::from_str::<tjson::Value>("[1] 2").unwrap_err();
+    /// let err = Error::custom_boxed(Box::new(syntax));
+    /// assert_eq!(err.code(), ErrorCode::TrailingCharacters);
+    /// ```
+    pub fn custom_boxed(err: Box<error::Error + Send + Sync>) -> Self {
+        match err.downcast::<Error>() {
+            Ok(err) => *err,
+            Err(err) => Error::syntax(Code::Custom(Arc::from(err)), 0, 0),
+        }
+    }
 }
 
 /// Categorizes the cause of a `tjson::Error`.
@@ -135,6 +283,282 @@ pub enum Category {
     Eof,
 }
 
+impl Display for Category {
+    /// Writes the category in lowercase, as `io`, `syntax`, `data` or `eof`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Category::Io => "io",
+            Category::Syntax => "syntax",
+            Category::Data => "data",
+            Category::Eof => "eof",
+        })
+    }
+}
+
+/// Stable, machine-readable identifiers for the causes of `tjson::Error`, as
+/// returned by `Error::code`.
+///
+/// The names returned by `as_str` are part of the crate's stable API: they
+/// will not be changed or reused for a different cause, so they are suitable
+/// for error responses and logs which other programs consume. New codes may
+/// be added in any release, so matches on `ErrorCode` need a wildcard arm:
+///
+/// ```rust,compile_fail
+/// # use tjson::error::ErrorCode;
+/// fn describe(code: ErrorCode) -> &'static str {
+///     match code {
+///         ErrorCode::Io => "could not read the input",
+///         ErrorCode::Data => "the input did not match the expected type",
+///     }
+/// }
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// `"io"`: reading or writing the underlying IO stream failed.
+    Io,
+
+    /// `"data"`: the input was valid TJSON but did not match the type being
+    /// deserialized, or a `Serialize` or `Deserialize` implementation
+    /// reported an error of its own.
+    Data,
+
+    /// `"eof_while_parsing_list"`: the input ended inside an array.
+    EofWhileParsingList,
+
+    /// `"eof_while_parsing_object"`: the input ended inside an object.
+    EofWhileParsingObject,
+
+    /// `"eof_while_parsing_string"`: the input ended inside a string.
+    EofWhileParsingString,
+
+    /// `"eof_while_parsing_value"`: the input ended where a value was
+    /// expected.
+    EofWhileParsingValue,
+
+    /// `"expected_colon"`: an object member name was not followed by `:`.
+    ExpectedColon,
+
+    /// `"expected_list_comma_or_end"`: an array element was not followed by
+    /// `,` or `]`.
+    ExpectedListCommaOrEnd,
+
+    /// `"expected_object_comma_or_end"`: an object member was not followed by
+    /// `,` or `}`.
+    ExpectedObjectCommaOrEnd,
+
+    /// `"expected_object_or_array"`: the input did not start with `{` or `[`.
+    ExpectedObjectOrArray,
+
+    /// `"expected_some_ident"`: a misspelled `true`, `false` or `null`.
+    ExpectedSomeIdent,
+
+    /// `"expected_some_value"`: something other than a value where one was
+    /// expected.
+    ExpectedSomeValue,
+
+    /// `"expected_some_string"`: something other than a string where one was
+    /// expected.
+    ExpectedSomeString,
+
+    /// `"invalid_escape"`: an unknown escape sequence in a string.
+    InvalidEscape,
+
+    /// `"invalid_number"`: a malformed number.
+    InvalidNumber,
+
+    /// `"number_out_of_range"`: a number too large for its type.
+    NumberOutOfRange,
+
+    /// `"inexact_integer"`: a floating point number read into an integer
+    /// with `integral_float_coercion`, whose text is not exactly the integer
+    /// it rounds to.
+    InexactInteger,
+
+    /// `"invalid_unicode_code_point"`: a string escape or input byte
+    /// sequence which is not a valid Unicode code point.
+    InvalidUnicodeCodePoint,
+
+    /// `"key_must_be_a_string"`: an object member name which is not a
+    /// string, or a key of a map being serialized which can't be written as
+    /// one although the map's first key could.
+    KeyMustBeAString,
+
+    /// `"lone_leading_surrogate_in_hex_escape"`: a `\u` escape of a leading
+    /// surrogate without the trailing one.
+    LoneLeadingSurrogateInHexEscape,
+
+    /// `"trailing_characters"`: more input after the end of the value.
+    TrailingCharacters,
+
+    /// `"unexpected_end_of_hex_escape"`: a `\u` escape with fewer than four
+    /// hex digits.
+    UnexpectedEndOfHexEscape,
+
+    /// `"recursion_limit_exceeded"`: arrays and objects nested too deeply.
+    RecursionLimitExceeded,
+
+    /// `"unsupported_encoding"`: the input is not UTF-8.
+    UnsupportedEncoding,
+
+    /// `"unsupported_media_type"`: an HTTP request body whose `Content-Type`
+    /// is not TJSON.
+    UnsupportedMediaType,
+
+    /// `"body_too_large"`: an HTTP request body over the configured limit.
+    BodyTooLarge,
+
+    /// `"invalid_event_sequence"`: events passed to `tjson::event::write`
+    /// which don't form a single well-formed document.
+    InvalidEventSequence,
+
+    /// `"duplicate_member"`: an object with two members of the same name,
+    /// when the `DuplicateKeys` policy doesn't allow it, or a map read from
+    /// `[key, value]` pairs with two of the same key.
+    DuplicateMember,
+
+    /// `"duplicate_element"`: a set with two equal elements, when the
+    /// `DuplicateElements` policy doesn't allow it. The message gives the
+    /// repeated element, and the JSON pointer of the set when it was read
+    /// from text.
+    DuplicateElement,
+
+    /// `"unresolved_hash_collision"`: a set element read with
+    /// `SetUniqueness::Hashed` whose hash is that of an earlier element,
+    /// where the two can't be compared since the earlier one can't be read
+    /// again, as from a reader. The message gives the index of the element,
+    /// and the JSON pointer of the set.
+    UnresolvedHashCollision,
+
+    /// `"collected_tag_mismatch"`: members of the same name but different
+    /// tags, which `DuplicateKeys::Collect` can't put in one array.
+    CollectedTagMismatch,
+
+    /// `"ambiguous_member"`: two members whose names only differ in ways
+    /// which `Normalization` ignores, so that both match the same field.
+    AmbiguousMember,
+
+    /// `"non_utc_timestamp"`: a timestamp with an offset other than `Z`,
+    /// including a lowercase `z`, when the `OffsetPolicy` doesn't allow it.
+    /// The message names the JSON pointer of the timestamp.
+    NonUtcTimestamp,
+
+    /// `"schema_violation"`: a value being written which doesn't match the
+    /// schema it was to be checked against.
+    SchemaViolation,
+
+    /// `"output_budget_exceeded"`: a document being written which would be
+    /// longer than `SerializeConfig::max_output_bytes` allows.
+    OutputBudgetExceeded,
+
+    /// `"transform_failed"`: a hook registered with
+    /// `Deserializer::transform_data` or `transform_string` failed, or the
+    /// data given to one wasn't validly encoded for its tag.
+    TransformFailed,
+
+    /// `"invalid_member_name"`: a member name which the `KeyPolicy` given to
+    /// `Deserializer::key_policy` doesn't allow, or one containing NUL in
+    /// `try_tjson!`.
+    InvalidMemberName,
+
+    /// `"tag_mismatch"`: a member, or a document read with
+    /// `tjson::from_str_as`, which doesn't hold what its tag says.
+    TagMismatch,
+
+    /// `"signedness_mismatch"`: an integer tagged `u` read into a signed
+    /// type, or one tagged `i` into an unsigned type, with
+    /// `IntegerSignedness::Strict`. The message names the member, its tag
+    /// and the type.
+    SignednessMismatch,
+
+    /// `"invalid_tag"`: a member name with a tag which isn't a TJSON tag, as
+    /// in `"foo:Z"`, or one read with `Deserializer::strict_tags` which has
+    /// no tag.
+    InvalidTag,
+
+    /// `"missing_member"`: a member or element looked up with `get_typed`
+    /// which isn't there.
+    MissingMember,
+
+    /// `"invalid_pointer"`: a JSON pointer given to `Value::pointer_insert`
+    /// which can't be followed, such as one going through a string.
+    InvalidPointer,
+
+    /// `"patch_failed"`: an operation given to `Value::patch` which is
+    /// malformed or can't be applied. Its cause says why.
+    PatchFailed,
+
+    /// `"patch_test_failed"`: a `test` operation given to `Value::patch`
+    /// which found a different value.
+    PatchTestFailed,
+
+    /// `"control_character_while_parsing_string"`: a raw control character
+    /// (U+0000 to U+001F) in a string or member name, where it must be
+    /// escaped.
+    ControlCharacterWhileParsingString,
+}
+
+impl ErrorCode {
+    /// The stable name of this code, in `snake_case`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorCode::Io => "io",
+            ErrorCode::Data => "data",
+            ErrorCode::EofWhileParsingList => "eof_while_parsing_list",
+            ErrorCode::EofWhileParsingObject => "eof_while_parsing_object",
+            ErrorCode::EofWhileParsingString => "eof_while_parsing_string",
+            ErrorCode::EofWhileParsingValue => "eof_while_parsing_value",
+            ErrorCode::ExpectedColon => "expected_colon",
+            ErrorCode::ExpectedListCommaOrEnd => "expected_list_comma_or_end",
+            ErrorCode::ExpectedObjectCommaOrEnd => "expected_object_comma_or_end",
+            ErrorCode::ExpectedObjectOrArray => "expected_object_or_array",
+            ErrorCode::ExpectedSomeIdent => "expected_some_ident",
+            ErrorCode::ExpectedSomeValue => "expected_some_value",
+            ErrorCode::ExpectedSomeString => "expected_some_string",
+            ErrorCode::InvalidEscape => "invalid_escape",
+            ErrorCode::InvalidNumber => "invalid_number",
+            ErrorCode::NumberOutOfRange => "number_out_of_range",
+            ErrorCode::InexactInteger => "inexact_integer",
+            ErrorCode::InvalidUnicodeCodePoint => "invalid_unicode_code_point",
+            ErrorCode::KeyMustBeAString => "key_must_be_a_string",
+            ErrorCode::LoneLeadingSurrogateInHexEscape => "lone_leading_surrogate_in_hex_escape",
+            ErrorCode::TrailingCharacters => "trailing_characters",
+            ErrorCode::UnexpectedEndOfHexEscape => "unexpected_end_of_hex_escape",
+            ErrorCode::RecursionLimitExceeded => "recursion_limit_exceeded",
+            ErrorCode::UnsupportedEncoding => "unsupported_encoding",
+            ErrorCode::UnsupportedMediaType => "unsupported_media_type",
+            ErrorCode::BodyTooLarge => "body_too_large",
+            ErrorCode::InvalidEventSequence => "invalid_event_sequence",
+            ErrorCode::DuplicateMember => "duplicate_member",
+            ErrorCode::DuplicateElement => "duplicate_element",
+            ErrorCode::UnresolvedHashCollision => "unresolved_hash_collision",
+            ErrorCode::CollectedTagMismatch => "collected_tag_mismatch",
+            ErrorCode::AmbiguousMember => "ambiguous_member",
+            ErrorCode::NonUtcTimestamp => "non_utc_timestamp",
+            ErrorCode::SchemaViolation => "schema_violation",
+            ErrorCode::OutputBudgetExceeded => "output_budget_exceeded",
+            ErrorCode::TransformFailed => "transform_failed",
+            ErrorCode::InvalidMemberName => "invalid_member_name",
+            ErrorCode::TagMismatch => "tag_mismatch",
+            ErrorCode::SignednessMismatch => "signedness_mismatch",
+            ErrorCode::InvalidTag => "invalid_tag",
+            ErrorCode::MissingMember => "missing_member",
+            ErrorCode::InvalidPointer => "invalid_pointer",
+            ErrorCode::PatchFailed => "patch_failed",
+            ErrorCode::PatchTestFailed => "patch_test_failed",
+            ErrorCode::ControlCharacterWhileParsingString => {
+                "control_character_while_parsing_string"
+            }
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl From<Error> for io::Error {
     /// Convert a `tjson::Error` into an `io::Error`.
     ///
@@ -164,8 +588,11 @@ impl From<Error> for io::Error {
     /// }
     /// ```
     fn from(j: Error) -> Self {
-        if let ErrorCode::Io(err) = j.err.code {
-            err
+        if let Some(&Code::Io(_)) = j.boxed_code() {
+            match j.into_code() {
+                Code::Io(IoError(err)) => err,
+                _ => unreachable!(),
+            }
         } else {
             match j.classify() {
                 Category::Io => unreachable!(),
@@ -176,22 +603,121 @@ impl From<Error> for io::Error {
     }
 }
 
-#[derive(Debug)]
+enum Repr {
+    Simple(SimpleError),
+    Boxed(Box<ErrorImpl>),
+}
+
+impl Clone for Repr {
+    fn clone(&self) -> Self {
+        match *self {
+            Repr::Simple(simple) => Repr::Simple(simple),
+            Repr::Boxed(ref err) => Repr::Boxed(err.clone()),
+        }
+    }
+}
+
+/// An error with one of the codes which carry nothing else, at a position
+/// which fits in 32 bits.
+#[derive(Copy, Clone)]
+struct SimpleError {
+    code: SimpleCode,
+    line: u32,
+    column: u32,
+    /// `NO_OFFSET` if there is none.
+    offset: u32,
+}
+
+const NO_OFFSET: u32 = u32::MAX;
+
+impl SimpleError {
+    fn offset(self) -> Option<usize> {
+        if self.offset == NO_OFFSET {
+            None
+        } else {
+            Some(self.offset as usize)
+        }
+    }
+
+    fn to_impl(self) -> ErrorImpl {
+        ErrorImpl {
+            code: self.code.code(),
+            line: self.line as usize,
+            column: self.column as usize,
+            path: None,
+            offset: self.offset(),
+        }
+    }
+}
+
+macro_rules! simple_codes {
+    ($($name:ident,)*) => {
+        /// The codes of `SimpleError`s: those of `Code` which carry nothing.
+        #[derive(Copy, Clone)]
+        enum SimpleCode {
+            $($name,)*
+        }
+
+        impl SimpleCode {
+            fn of(code: &Code) -> Option<SimpleCode> {
+                match *code {
+                    $(Code::$name => Some(SimpleCode::$name),)*
+                    _ => None,
+                }
+            }
+
+            fn code(self) -> Code {
+                match self {
+                    $(SimpleCode::$name => Code::$name,)*
+                }
+            }
+        }
+    }
+}
+
+simple_codes! {
+    EofWhileParsingList,
+    EofWhileParsingObject,
+    EofWhileParsingString,
+    EofWhileParsingValue,
+    ExpectedColon,
+    ExpectedListCommaOrEnd,
+    ExpectedObjectCommaOrEnd,
+    ExpectedObjectOrArray,
+    ExpectedSomeIdent,
+    ExpectedSomeValue,
+    ExpectedSomeString,
+    InvalidEscape,
+    InvalidNumber,
+    NumberOutOfRange,
+    InexactInteger,
+    InvalidUnicodeCodePoint,
+    ControlCharacterWhileParsingString,
+    KeyMustBeAString,
+    LoneLeadingSurrogateInHexEscape,
+    TrailingCharacters,
+    UnexpectedEndOfHexEscape,
+    RecursionLimitExceeded,
+}
+
+#[derive(Clone, Debug)]
 struct ErrorImpl {
-    code: ErrorCode,
+    code: Code,
     line: usize,
     column: usize,
+    path: Option<PathBuf>,
+    offset: Option<usize>,
 }
 
 // Not public API. Should be pub(crate).
 #[doc(hidden)]
-#[derive(Debug)]
-pub enum ErrorCode {
+#[derive(Clone, Debug)]
+pub enum Code {
     /// Catchall for syntax error messages
     Message(String),
 
     /// Some IO error occurred while serializing or deserializing.
-    Io(io::Error),
+    Io(IoError),
 
     /// EOF while parsing a list.
     EofWhileParsingList,
@@ -205,6 +731,9 @@ pub enum ErrorCode {
     /// EOF while parsing a JSON value.
     EofWhileParsingValue,
 
+    /// EOF while parsing, along with what was left unterminated.
+    Unterminated(Box<Unterminated>),
+
     /// Expected this character to be a `':'`.
     ExpectedColon,
 
@@ -235,9 +764,15 @@ pub enum ErrorCode {
     /// Number is bigger than the maximum value of its type.
     NumberOutOfRange,
 
+    /// Floating point number read as an integer is not exactly one.
+    InexactInteger,
+
     /// Invalid unicode code point.
     InvalidUnicodeCodePoint,
 
+    /// Control character found while parsing a string.
+    ControlCharacterWhileParsingString,
+
     /// Object key is not a string.
     KeyMustBeAString,
 
@@ -252,86 +787,608 @@ pub enum ErrorCode {
 
     /// Encountered nesting of JSON maps and arrays more than 128 layers deep.
     RecursionLimitExceeded,
+
+    /// The input is not UTF-8.
+    UnsupportedEncoding(Encoding),
+
+    /// An HTTP request body was sent with this `Content-Type`, which isn't
+    /// TJSON.
+    UnsupportedMediaType(String),
+
+    /// An HTTP request body of this many bytes exceeded the given limit.
+    BodyTooLarge(usize, usize),
+
+    /// Events written out of order, and what was wrong with them.
+    InvalidEventSequence(String),
+
+    /// An object has more than one member with this name, ignoring tags.
+    DuplicateMember(String),
+
+    /// The key of the pair at this index of a map read from `[key, value]`
+    /// pairs was also the key of an earlier pair.
+    DuplicatePairKey(usize),
+
+    /// The element at this index of a set, written out, equals an earlier
+    /// one. The set is at this JSON pointer, if it is known.
+    DuplicateElement(usize, String, String),
+
+    /// The element at this index of the set at this JSON pointer has the
+    /// hash of an earlier one, which can't be read again to compare them.
+    UnresolvedHashCollision(usize, String),
+
+    /// Members with these names can't be collected since their tags differ.
+    CollectedTagMismatch(String, String),
+
+    /// Members with these two names both match the named struct field once
+    /// normalized.
+    AmbiguousMember(String, String, &'static str),
+
+    /// A timestamp has this offset rather than `Z`, at this JSON pointer.
+    NonUtcTimestamp(String, String),
+
+    /// A value being written doesn't match its schema here.
+    SchemaViolation(Box<SchemaViolation>),
+
+    /// A document being written would be longer than its budget.
+    OutputBudgetExceeded(Box<OutputBudgetExceeded>),
+
+    /// A transform hook failed on the value at this JSON pointer.
+    TransformFailed(String, Error),
+
+    /// A member name isn't allowed by the key policy.
+    InvalidMemberName(InvalidKey),
+
+    /// A document doesn't hold what the tag it was read with says.
+    TagMismatch(Box<TagMismatch>),
+
+    /// The integer at this JSON pointer has this tag, of the other
+    /// signedness than the named integer type it is read into.
+    SignednessMismatch(String, &'static str, &'static str),
+
+    /// This member name has no tag, or an invalid one.
+    InvalidTag(String),
+
+    /// Nothing at this member or element, as in `member "count"`, looked up
+    /// with `get_typed`.
+    MissingMember(String),
+
+    /// The value at this member or element, looked up with `get_typed`,
+    /// couldn't be deserialized.
+    InvalidMember(String, Error),
+
+    /// This JSON pointer can't be followed to a place for a value.
+    InvalidPointer(String, PointerError),
+
+    /// The operation at this position in a JSON Patch failed.
+    PatchFailed(usize, Error),
+
+    /// The `test` operation at this position in a JSON Patch, with this
+    /// path, found a different value.
+    PatchTestFailed(usize, String),
+
+    /// An error from outside the crate, kept as the error's cause.
+    Custom(Arc<error::Error + Send + Sync>),
+}
+
+impl Code {
+    fn error_code(&self) -> ErrorCode {
+        match *self {
+            Code::Message(_) => ErrorCode::Data,
+            Code::Io(_) => ErrorCode::Io,
+            Code::EofWhileParsingList => ErrorCode::EofWhileParsingList,
+            Code::EofWhileParsingObject => ErrorCode::EofWhileParsingObject,
+            Code::EofWhileParsingString => ErrorCode::EofWhileParsingString,
+            Code::EofWhileParsingValue => ErrorCode::EofWhileParsingValue,
+            Code::Unterminated(ref unterminated) => {
+                match unterminated.construct {
+                    Construct::Value |
+                    Construct::MemberValue(_) => ErrorCode::EofWhileParsingValue,
+                    Construct::String(..) => ErrorCode::EofWhileParsingString,
+                    Construct::Array(..) => ErrorCode::EofWhileParsingList,
+                    Construct::Object(..) => ErrorCode::EofWhileParsingObject,
+                }
+            }
+            Code::ExpectedColon => ErrorCode::ExpectedColon,
+            Code::ExpectedListCommaOrEnd => ErrorCode::ExpectedListCommaOrEnd,
+            Code::ExpectedObjectCommaOrEnd => ErrorCode::ExpectedObjectCommaOrEnd,
+            Code::ExpectedObjectOrArray => ErrorCode::ExpectedObjectOrArray,
+            Code::ExpectedSomeIdent => ErrorCode::ExpectedSomeIdent,
+            Code::ExpectedSomeValue => ErrorCode::ExpectedSomeValue,
+            Code::ExpectedSomeString => ErrorCode::ExpectedSomeString,
+            Code::InvalidEscape => ErrorCode::InvalidEscape,
+            Code::InvalidNumber => ErrorCode::InvalidNumber,
+            Code::NumberOutOfRange => ErrorCode::NumberOutOfRange,
+            Code::InexactInteger => ErrorCode::InexactInteger,
+            Code::InvalidUnicodeCodePoint => ErrorCode::InvalidUnicodeCodePoint,
+            Code::ControlCharacterWhileParsingString => {
+                ErrorCode::ControlCharacterWhileParsingString
+            }
+            Code::KeyMustBeAString => ErrorCode::KeyMustBeAString,
+            Code::LoneLeadingSurrogateInHexEscape => ErrorCode::LoneLeadingSurrogateInHexEscape,
+            Code::TrailingCharacters => ErrorCode::TrailingCharacters,
+            Code::UnexpectedEndOfHexEscape => ErrorCode::UnexpectedEndOfHexEscape,
+            Code::RecursionLimitExceeded => ErrorCode::RecursionLimitExceeded,
+            Code::UnsupportedEncoding(_) => ErrorCode::UnsupportedEncoding,
+            Code::UnsupportedMediaType(_) => ErrorCode::UnsupportedMediaType,
+            Code::BodyTooLarge(..) => ErrorCode::BodyTooLarge,
+            Code::InvalidEventSequence(_) => ErrorCode::InvalidEventSequence,
+            Code::DuplicateMember(_) |
+            Code::DuplicatePairKey(_) => ErrorCode::DuplicateMember,
+            Code::DuplicateElement(..) => ErrorCode::DuplicateElement,
+            Code::UnresolvedHashCollision(..) => ErrorCode::UnresolvedHashCollision,
+            Code::CollectedTagMismatch(..) => ErrorCode::CollectedTagMismatch,
+            Code::AmbiguousMember(..) => ErrorCode::AmbiguousMember,
+            Code::NonUtcTimestamp(..) => ErrorCode::NonUtcTimestamp,
+            Code::SchemaViolation(_) => ErrorCode::SchemaViolation,
+            Code::OutputBudgetExceeded(_) => ErrorCode::OutputBudgetExceeded,
+            Code::TransformFailed(..) => ErrorCode::TransformFailed,
+            Code::InvalidMemberName(_) => ErrorCode::InvalidMemberName,
+            Code::TagMismatch(_) => ErrorCode::TagMismatch,
+            Code::SignednessMismatch(..) => ErrorCode::SignednessMismatch,
+            Code::InvalidTag(_) => ErrorCode::InvalidTag,
+            Code::MissingMember(_) => ErrorCode::MissingMember,
+            Code::InvalidPointer(..) => ErrorCode::InvalidPointer,
+            Code::PatchFailed(..) => ErrorCode::PatchFailed,
+            Code::PatchTestFailed(..) => ErrorCode::PatchTestFailed,
+            Code::InvalidMember(_, ref err) => err.code(),
+            Code::Custom(_) => ErrorCode::Data,
+        }
+    }
+
+    fn category(&self) -> Category {
+        match *self {
+            Code::Message(_) |
+            Code::Custom(_) |
+            Code::InexactInteger |
+            Code::AmbiguousMember(..) |
+            Code::InvalidEventSequence(_) |
+            Code::SchemaViolation(_) |
+            Code::OutputBudgetExceeded(_) |
+            Code::TransformFailed(..) |
+            Code::InvalidMemberName(_) |
+            Code::TagMismatch(_) |
+            Code::SignednessMismatch(..) |
+            Code::UnresolvedHashCollision(..) |
+            Code::MissingMember(_) |
+            Code::InvalidPointer(..) |
+            Code::PatchFailed(..) |
+            Code::PatchTestFailed(..) |
+            Code::UnsupportedMediaType(_) |
+            Code::BodyTooLarge(..) => Category::Data,
+            Code::InvalidMember(_, ref err) => err.classify(),
+            Code::Io(_) => Category::Io,
+            Code::EofWhileParsingList |
+            Code::EofWhileParsingObject |
+            Code::EofWhileParsingString |
+            Code::EofWhileParsingValue |
+            Code::Unterminated(_) => Category::Eof,
+            Code::ExpectedColon |
+            Code::ExpectedListCommaOrEnd |
+            Code::ExpectedObjectCommaOrEnd |
+            Code::ExpectedObjectOrArray |
+            Code::ExpectedSomeIdent |
+            Code::ExpectedSomeValue |
+            Code::ExpectedSomeString |
+            Code::InvalidEscape |
+            Code::InvalidNumber |
+            Code::NumberOutOfRange |
+            Code::InvalidUnicodeCodePoint |
+            Code::ControlCharacterWhileParsingString |
+            Code::KeyMustBeAString |
+            Code::LoneLeadingSurrogateInHexEscape |
+            Code::TrailingCharacters |
+            Code::UnexpectedEndOfHexEscape |
+            Code::RecursionLimitExceeded |
+            Code::UnsupportedEncoding(_) |
+            Code::DuplicateMember(_) |
+            Code::DuplicatePairKey(_) |
+            Code::DuplicateElement(..) |
+            Code::CollectedTagMismatch(..) |
+            Code::InvalidTag(_) |
+            Code::NonUtcTimestamp(..) => Category::Syntax,
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// An IO error, which is cloned by its kind and message since `io::Error`
+// can't be cloned itself.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct IoError(pub io::Error);
+
+impl Clone for IoError {
+    fn clone(&self) -> Self {
+        IoError(io::Error::new(self.0.kind(), self.0.to_string()))
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Encoding {
+    /// UTF-16, little endian, and whether it started with a byte order mark.
+    Utf16Le(bool),
+
+    /// UTF-16, big endian, and whether it started with a byte order mark.
+    Utf16Be(bool),
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct Unterminated {
+    /// The innermost construct being parsed when the input ended.
+    pub construct: Construct,
+
+    /// Number of arrays which were left open.
+    pub arrays: usize,
+
+    /// Number of objects which were left open.
+    pub objects: usize,
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub enum Construct {
+    /// A value outside of any array or object.
+    Value,
+
+    /// A string which started at the given line and column.
+    String(usize, usize),
+
+    /// An array which started at the given line and column.
+    Array(usize, usize),
+
+    /// An object which started at the given line and column.
+    Object(usize, usize),
+
+    /// The value of the object member with the given name.
+    MemberValue(String),
 }
 
 impl Error {
     // Not public API. Should be pub(crate).
     #[doc(hidden)]
-    pub fn syntax(code: ErrorCode, line: usize, column: usize) -> Self {
-        Error {
-            err: Box::new(
-                ErrorImpl {
-                    code: code,
-                    line: line,
-                    column: column,
-                },
-            ),
-        }
+    pub fn syntax(code: Code, line: usize, column: usize) -> Self {
+        let simple = match SimpleCode::of(&code) {
+            Some(simple) if line <= u32::MAX as usize && column <= u32::MAX as usize => {
+                SimpleError {
+                    code: simple,
+                    line: line as u32,
+                    column: column as u32,
+                    offset: NO_OFFSET,
+                }
+            }
+            _ => {
+                return Error::boxed(
+                    ErrorImpl {
+                        code: code,
+                        line: line,
+                        column: column,
+                        path: None,
+                        offset: None,
+                    },
+                )
+            }
+        };
+        Error { repr: Repr::Simple(simple) }
     }
 
     // Not public API. Should be pub(crate).
     #[doc(hidden)]
     pub fn io(error: io::Error) -> Self {
-        Error {
-            err: Box::new(
-                ErrorImpl {
-                    code: ErrorCode::Io(error),
-                    line: 0,
-                    column: 0,
-                },
-            ),
-        }
+        Error::boxed(
+            ErrorImpl {
+                code: Code::Io(IoError(error)),
+                line: 0,
+                column: 0,
+                path: None,
+                offset: None,
+            },
+        )
     }
 
     // Not public API. Should be pub(crate).
     #[doc(hidden)]
     pub fn fix_position<F>(self, f: F) -> Self
     where
-        F: FnOnce(ErrorCode) -> Error,
+        F: FnOnce(Code) -> Error,
     {
-        if self.err.line == 0 {
-            f(self.err.code)
+        if self.line() == 0 {
+            f(self.into_code())
         } else {
             self
         }
     }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn with_path<P>(self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let mut err = self.into_impl();
+        err.path = Some(path.as_ref().to_owned());
+        Error { repr: Repr::Boxed(err) }
+    }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn with_byte_offset(mut self, offset: usize) -> Self {
+        match self.repr {
+            Repr::Simple(ref mut simple) if offset < NO_OFFSET as usize => {
+                simple.offset = offset as u32;
+                return self;
+            }
+            Repr::Boxed(ref mut err) => {
+                err.offset = Some(offset);
+                return self;
+            }
+            Repr::Simple(_) => {}
+        }
+        let mut err = self.into_impl();
+        err.offset = Some(offset);
+        Error { repr: Repr::Boxed(err) }
+    }
+
+    fn boxed(err: ErrorImpl) -> Self {
+        Error { repr: Repr::Boxed(Box::new(err)) }
+    }
+
+    fn into_impl(self) -> Box<ErrorImpl> {
+        match self.repr {
+            Repr::Simple(simple) => Box::new(simple.to_impl()),
+            Repr::Boxed(err) => err,
+        }
+    }
+
+    fn into_code(self) -> Code {
+        match self.repr {
+            Repr::Simple(simple) => simple.code.code(),
+            Repr::Boxed(err) => err.code,
+        }
+    }
+
+    /// The code of an error which was boxed. The codes of simple errors
+    /// carry nothing to borrow.
+    fn boxed_code(&self) -> Option<&Code> {
+        match self.repr {
+            Repr::Simple(_) => None,
+            Repr::Boxed(ref err) => Some(&err.code),
+        }
+    }
+
+    /// Calls `f` with the error's details, made up on the spot for a simple
+    /// error, which doesn't allocate since its code carries nothing.
+    fn with_impl<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&ErrorImpl) -> R,
+    {
+        match self.repr {
+            Repr::Simple(simple) => f(&simple.to_impl()),
+            Repr::Boxed(ref err) => f(err),
+        }
+    }
 }
 
-impl Display for ErrorCode {
+impl Display for Code {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ErrorCode::Message(ref msg) => f.write_str(msg),
-            ErrorCode::Io(ref err) => Display::fmt(err, f),
-            ErrorCode::EofWhileParsingList => f.write_str("EOF while parsing a list"),
-            ErrorCode::EofWhileParsingObject => f.write_str("EOF while parsing an object"),
-            ErrorCode::EofWhileParsingString => f.write_str("EOF while parsing a string"),
-            ErrorCode::EofWhileParsingValue => f.write_str("EOF while parsing a value"),
-            ErrorCode::ExpectedColon => f.write_str("expected `:`"),
-            ErrorCode::ExpectedListCommaOrEnd => f.write_str("expected `,` or `]`"),
-            ErrorCode::ExpectedObjectCommaOrEnd => f.write_str("expected `,` or `}`"),
-            ErrorCode::ExpectedObjectOrArray => f.write_str("expected `{` or `[`"),
-            ErrorCode::ExpectedSomeIdent => f.write_str("expected ident"),
-            ErrorCode::ExpectedSomeValue => f.write_str("expected value"),
-            ErrorCode::ExpectedSomeString => f.write_str("expected string"),
-            ErrorCode::InvalidEscape => f.write_str("invalid escape"),
-            ErrorCode::InvalidNumber => f.write_str("invalid number"),
-            ErrorCode::NumberOutOfRange => f.write_str("number out of range"),
-            ErrorCode::InvalidUnicodeCodePoint => f.write_str("invalid unicode code point"),
-            ErrorCode::KeyMustBeAString => f.write_str("key must be a string"),
-            ErrorCode::LoneLeadingSurrogateInHexEscape => {
+            Code::Message(ref msg) => f.write_str(msg),
+            Code::Io(IoError(ref err)) => Display::fmt(err, f),
+            Code::EofWhileParsingList => f.write_str("EOF while parsing a list"),
+            Code::EofWhileParsingObject => f.write_str("EOF while parsing an object"),
+            Code::EofWhileParsingString => f.write_str("EOF while parsing a string"),
+            Code::EofWhileParsingValue => f.write_str("EOF while parsing a value"),
+            Code::Unterminated(ref unterminated) => Display::fmt(unterminated, f),
+            Code::ExpectedColon => f.write_str("expected `:`"),
+            Code::ExpectedListCommaOrEnd => f.write_str("expected `,` or `]`"),
+            Code::ExpectedObjectCommaOrEnd => f.write_str("expected `,` or `}`"),
+            Code::ExpectedObjectOrArray => f.write_str("expected `{` or `[`"),
+            Code::ExpectedSomeIdent => f.write_str("expected ident"),
+            Code::ExpectedSomeValue => f.write_str("expected value"),
+            Code::ExpectedSomeString => f.write_str("expected string"),
+            Code::InvalidEscape => f.write_str("invalid escape"),
+            Code::InvalidNumber => f.write_str("invalid number"),
+            Code::NumberOutOfRange => f.write_str("number out of range"),
+            Code::InexactInteger => f.write_str("number is not exactly an integer"),
+            Code::InvalidUnicodeCodePoint => f.write_str("invalid unicode code point"),
+            Code::ControlCharacterWhileParsingString => {
+                f.write_str("control character (\\u0000-\\u001F) found while parsing a string")
+            }
+            Code::KeyMustBeAString => f.write_str("key must be a string"),
+            Code::LoneLeadingSurrogateInHexEscape => {
                 f.write_str("lone leading surrogate in hex escape")
             }
-            ErrorCode::TrailingCharacters => f.write_str("trailing characters"),
-            ErrorCode::UnexpectedEndOfHexEscape => f.write_str("unexpected end of hex escape"),
-            ErrorCode::RecursionLimitExceeded => f.write_str("recursion limit exceeded"),
+            Code::TrailingCharacters => f.write_str("trailing characters"),
+            Code::UnexpectedEndOfHexEscape => f.write_str("unexpected end of hex escape"),
+            Code::RecursionLimitExceeded => f.write_str("recursion limit exceeded"),
+            Code::UnsupportedEncoding(encoding) => {
+                let (name, bom) = match encoding {
+                    Encoding::Utf16Le(bom) => ("UTF-16LE", bom),
+                    Encoding::Utf16Be(bom) => ("UTF-16BE", bom),
+                };
+                let evidence = if bom {
+                    "it starts with a byte order mark"
+                } else {
+                    "it has NUL bytes between ASCII characters"
+                };
+                write!(
+                    f,
+                    "input looks like {} ({}) but TJSON must be UTF-8; transcode it to UTF-8 \
+                     before parsing",
+                    name,
+                    evidence
+                )
+            }
+            Code::UnsupportedMediaType(ref content_type) => {
+                write!(
+                    f,
+                    "unsupported media type {:?}, expected \"application/tjson\"",
+                    content_type
+                )
+            }
+            Code::InvalidEventSequence(ref message) => f.write_str(message),
+            Code::SchemaViolation(ref violation) => write!(f, "schema violation: {}", violation),
+            Code::OutputBudgetExceeded(ref exceeded) => Display::fmt(exceeded, f),
+            Code::TransformFailed(ref pointer, ref err) => {
+                write!(f, "transform of {:?} failed: {}", pointer, err)
+            }
+            Code::InvalidMemberName(ref err) => Display::fmt(err, f),
+            Code::TagMismatch(ref mismatch) => Display::fmt(mismatch, f),
+            Code::SignednessMismatch(ref pointer, tag, target) => {
+                try!(write!(
+                    f,
+                    "integer tagged {} can't be read into {} with IntegerSignedness::Strict",
+                    tag,
+                    target
+                ));
+                if !pointer.is_empty() {
+                    try!(write!(f, " at {}", pointer));
+                }
+                Ok(())
+            }
+            Code::InvalidTag(ref name) => {
+                match name.rfind(':') {
+                    Some(colon) => {
+                        write!(f, "member name {:?} has invalid tag {:?}", name, &name[colon + 1..])
+                    }
+                    None => write!(f, "member name {:?} has no tag", name),
+                }
+            }
+            Code::MissingMember(ref location) => write!(f, "missing {}", location),
+            Code::InvalidMember(ref location, ref err) => write!(f, "{}: {}", location, err),
+            Code::InvalidPointer(ref pointer, ref err) => {
+                write!(f, "pointer {:?}: {}", pointer, err)
+            }
+            Code::PatchFailed(index, ref err) => {
+                write!(f, "patch operation {} failed: {}", index, err)
+            }
+            Code::PatchTestFailed(index, ref pointer) => {
+                write!(f, "patch operation {} found a different value at {:?}", index, pointer)
+            }
+            Code::Custom(ref err) => Display::fmt(err, f),
+            Code::DuplicateMember(ref name) => write!(f, "duplicate member {:?}", name),
+            Code::DuplicatePairKey(index) => write!(f, "duplicate key in pair {}", index),
+            Code::DuplicateElement(index, ref element, ref pointer) => {
+                try!(write!(f, "duplicate set element {} at index {}", element, index));
+                if !pointer.is_empty() {
+                    try!(write!(f, " of {}", pointer));
+                }
+                Ok(())
+            }
+            Code::UnresolvedHashCollision(index, ref pointer) => {
+                try!(write!(f, "set element at index {}", index));
+                if !pointer.is_empty() {
+                    try!(write!(f, " of {}", pointer));
+                }
+                f.write_str(" hashes the same as an earlier one which can't be read again")
+            }
+            Code::CollectedTagMismatch(ref first, ref other) => {
+                write!(
+                    f,
+                    "cannot collect member {:?} with {:?} since their tags differ",
+                    other,
+                    first
+                )
+            }
+            Code::AmbiguousMember(ref first, ref other, field) => {
+                write!(f, "members {:?} and {:?} both match field {:?}", first, other, field)
+            }
+            Code::NonUtcTimestamp(ref offset, ref pointer) => {
+                try!(write!(f, "timestamp has offset `{}` rather than `Z`", offset));
+                if !pointer.is_empty() {
+                    try!(write!(f, " at {}", pointer));
+                }
+                Ok(())
+            }
+            Code::BodyTooLarge(len, max) => {
+                write!(f, "request body of {} bytes is larger than the limit of {} bytes", len, max)
+            }
+        }
+    }
+}
+
+impl Display for Unterminated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_at(f, None)
+    }
+}
+
+impl Unterminated {
+    /// Writes the message for an error at this line and column, which
+    /// leaves out where the construct started if it started there.
+    fn fmt_at(&self, f: &mut fmt::Formatter, at: Option<(usize, usize)>) -> fmt::Result {
+        try!(f.write_str("unexpected end of input while "));
+
+        let named = match self.construct {
+            Construct::Value => {
+                try!(f.write_str("parsing a value"));
+                0
+            }
+            Construct::String(line, column) => {
+                try!(f.write_str("parsing a string"));
+                try!(write_start(f, (line, column), at));
+                0
+            }
+            Construct::Array(line, column) => {
+                try!(f.write_str("parsing an array"));
+                try!(write_start(f, (line, column), at));
+                1
+            }
+            Construct::Object(line, column) => {
+                try!(f.write_str("parsing an object"));
+                try!(write_start(f, (line, column), at));
+                1
+            }
+            Construct::MemberValue(ref name) => {
+                try!(write!(f, "expecting the value for member {:?}", name));
+                0
+            }
+        };
+
+        // Only list the open containers if there are more than the one named
+        if self.arrays + self.objects > named {
+            try!(f.write_str(" ("));
+            if self.arrays > 0 {
+                try!(write_unclosed(f, self.arrays, "array"));
+            }
+            if self.arrays > 0 && self.objects > 0 {
+                try!(f.write_str(" and "));
+            }
+            if self.objects > 0 {
+                try!(write_unclosed(f, self.objects, "object"));
+            }
+            try!(f.write_str(")"));
         }
+
+        Ok(())
+    }
+}
+
+fn write_start(
+    f: &mut fmt::Formatter,
+    start: (usize, usize),
+    at: Option<(usize, usize)>,
+) -> fmt::Result {
+    if Some(start) == at {
+        return Ok(());
     }
+    write!(f, " started at line {} column {}", start.0, start.1)
+}
+
+fn write_unclosed(f: &mut fmt::Formatter, count: usize, container: &str) -> fmt::Result {
+    let plural = if count == 1 { "" } else { "s" };
+    write!(f, "{} unclosed {}{}", count, container, plural)
 }
 
 impl error::Error for Error {
     fn description(&self) -> &str {
-        match self.err.code {
-            ErrorCode::Io(ref err) => error::Error::description(err),
+        match self.boxed_code() {
+            Some(&Code::Io(IoError(ref err))) => error::Error::description(err),
             _ => {
                 // If you want a better message, use Display::fmt or to_string().
                 "JSON error"
@@ -340,8 +1397,12 @@ impl error::Error for Error {
     }
 
     fn cause(&self) -> Option<&error::Error> {
-        match self.err.code {
-            ErrorCode::Io(ref err) => Some(err),
+        match self.boxed_code() {
+            Some(&Code::Io(IoError(ref err))) => Some(err),
+            Some(&Code::Custom(ref err)) => Some(&**err),
+            Some(&Code::TransformFailed(_, ref err)) |
+            Some(&Code::InvalidMember(_, ref err)) |
+            Some(&Code::PatchFailed(_, ref err)) => Some(err),
             _ => None,
         }
     }
@@ -349,23 +1410,55 @@ impl error::Error for Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Display::fmt(&*self.err, f)
+        self.with_impl(|err| Display::fmt(err, f))
     }
 }
 
 impl Display for ErrorImpl {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref path) = self.path {
+            try!(write!(f, "{}: ", path.display()));
+        }
+
         if self.line == 0 {
-            Display::fmt(&self.code, f)
-        } else {
-            write!(
-                f,
-                "{} at line {} column {}",
-                self.code,
-                self.line,
-                self.column
-            )
+            return Display::fmt(&self.code, f);
         }
+
+        try!(match self.code {
+            Code::Unterminated(ref unterminated) => {
+                unterminated.fmt_at(f, Some((self.line, self.column)))
+            }
+            ref code => Display::fmt(code, f),
+        });
+        write!(f, " at line {} column {}", self.line, self.column)
+    }
+}
+
+/// Serializes the error as a TJSON object for structured error responses:
+///
+/// ```text
+/// {"code:s": "trailing_characters", "message:s": "trailing characters",
+///  "line:u": "1", "column:u": "5"}
+/// ```
+///
+/// `line:u` and `column:u` are left out for errors which have no position,
+/// and `path:s` is only present for errors from reading a file.
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = try!(serializer.serialize_map(None));
+        try!(map.serialize_entry("code:s", self.code().as_str()));
+        try!(map.serialize_entry("message:s", &self.with_impl(|err| err.code.to_string())));
+        if self.line() != 0 {
+            try!(map.serialize_entry("line:u", &self.line().to_string()));
+            try!(map.serialize_entry("column:u", &self.column().to_string()));
+        }
+        if let Some(path) = self.path() {
+            try!(map.serialize_entry("path:s", &path.to_string_lossy()));
+        }
+        map.end()
     }
 }
 
@@ -373,34 +1466,34 @@ impl Display for ErrorImpl {
 // end up seeing this representation because it is what unwrap() shows.
 impl Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Debug::fmt(&*self.err, f)
+        self.with_impl(|err| Debug::fmt(err, f))
     }
 }
 
 impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Error {
-        Error {
-            err: Box::new(
-                ErrorImpl {
-                    code: ErrorCode::Message(msg.to_string()),
-                    line: 0,
-                    column: 0,
-                },
-            ),
-        }
+        Error::boxed(
+            ErrorImpl {
+                code: Code::Message(msg.to_string()),
+                line: 0,
+                column: 0,
+                path: None,
+                offset: None,
+            },
+        )
     }
 }
 
 impl ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Error {
-        Error {
-            err: Box::new(
-                ErrorImpl {
-                    code: ErrorCode::Message(msg.to_string()),
-                    line: 0,
-                    column: 0,
-                },
-            ),
-        }
+        Error::boxed(
+            ErrorImpl {
+                code: Code::Message(msg.to_string()),
+                line: 0,
+                column: 0,
+                path: None,
+                offset: None,
+            },
+        )
     }
 }
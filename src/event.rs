@@ -0,0 +1,400 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading and writing TJSON as a flat stream of events
+//!
+//! This is for consumers which want neither a whole `Value` tree nor to
+//! implement serde's traits, such as converters to other formats. Parsing
+//! produces the events of a document one at a time as the input is read, and
+//! writing checks that the events it is given form exactly one well-formed
+//! document.
+//!
+//! ```rust
+//! use tjson::event::{self, Event};
+//!
+//! let input = r#"{"name": "tjson", "tags": [1, -2]}"#;
+//! let events = event::parse(input).collect::<Result<Vec<_>, _>>().unwrap();
+//!
+//! assert_eq!(
+//!     events,
+//!     [
+//!         Event::ObjectStart,
+//!         Event::Key("name".into()),
+//!         Event::Str("tjson".into()),
+//!         Event::Key("tags".into()),
+//!         Event::ArrayStart,
+//!         Event::UInt(1),
+//!         Event::Int(-2),
+//!         Event::ArrayEnd,
+//!         Event::ObjectEnd,
+//!     ]
+//! );
+//!
+//! let mut output = Vec::new();
+//! event::write(&mut output, events).unwrap();
+//! assert_eq!(output, br#"{"name":"tjson","tags":[1,-2]}"#);
+//! ```
+//!
+//! Member name tags are part of the name, as everywhere else in this crate,
+//! so there are no separate events for sets or timestamps, and binary data
+//! is a `Str` of its encoding. Data too large to hold in memory can be read
+//! and written a chunk at a time instead, with `Events::chunked_data` and
+//! the `DataStart`, `DataChunk` and `DataEnd` events:
+//!
+//! ```rust
+//! use tjson::event::{self, Event};
+//!
+//! let input = r#"{"blob:d": "AQIDBAU"}"#;
+//! let events = event::parse(input)
+//!     .chunked_data(2)
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     events,
+//!     [
+//!         Event::ObjectStart,
+//!         Event::Key("blob:d".into()),
+//!         Event::DataStart(None),
+//!         Event::DataChunk((&[1, 2][..]).into()),
+//!         Event::DataChunk((&[3, 4][..]).into()),
+//!         Event::DataChunk((&[5][..]).into()),
+//!         Event::DataEnd,
+//!         Event::ObjectEnd,
+//!     ]
+//! );
+//!
+//! let mut output = Vec::new();
+//! event::write(&mut output, events).unwrap();
+//! assert_eq!(output, input.replace(" ", "").as_bytes());
+//! ```
+
+use std::borrow::Cow;
+use std::io;
+use std::num::FpCategory;
+
+use de::{Events, IoRead, StrRead};
+use encoding::Encoder;
+use error::{Code, Error, Result};
+use ser::{CompactFormatter, Formatter, format_escaped_str};
+
+/// One step of a TJSON document.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// The start of an object, followed by a `Key` and a value for each
+    /// member and then `ObjectEnd`.
+    ObjectStart,
+
+    /// The name of an object member, followed by its value.
+    Key(Cow<'a, str>),
+
+    /// The end of an object.
+    ObjectEnd,
+
+    /// The start of an array, followed by its elements and then `ArrayEnd`.
+    ArrayStart,
+
+    /// The end of an array.
+    ArrayEnd,
+
+    /// `null`
+    Null,
+
+    /// `true` or `false`
+    Bool(bool),
+
+    /// A negative integer. Parsing produces `UInt` for every other integer.
+    Int(i64),
+
+    /// A non-negative integer.
+    UInt(u64),
+
+    /// A number with a fraction or exponent. Writing a NaN or infinite
+    /// `Float` writes `null`, as serializing one does.
+    Float(f64),
+
+    /// A string, borrowed from the input wherever it has no escapes.
+    Str(Cow<'a, str>),
+
+    /// The start of a string of data, followed by its bytes as `DataChunk`s
+    /// and then `DataEnd`. Holds the number of bytes to come if known,
+    /// which parsing never does and writing doesn't need.
+    ///
+    /// Parsing only produces these with `Events::chunked_data`. Writing
+    /// encodes the chunks as hex for a member tagged `d16`, or in arrays and
+    /// sets of them, and as base64url anywhere else.
+    DataStart(Option<u64>),
+
+    /// Some of the bytes of data. Chunks may be any length, regardless of
+    /// how their encoding lines up.
+    DataChunk(Cow<'a, [u8]>),
+
+    /// The end of a string of data.
+    DataEnd,
+}
+
+impl<'a> Event<'a> {
+    fn name(&self) -> &'static str {
+        match *self {
+            Event::ObjectStart => "ObjectStart",
+            Event::Key(_) => "Key",
+            Event::ObjectEnd => "ObjectEnd",
+            Event::ArrayStart => "ArrayStart",
+            Event::ArrayEnd => "ArrayEnd",
+            Event::Null => "Null",
+            Event::Bool(_) => "Bool",
+            Event::Int(_) => "Int",
+            Event::UInt(_) => "UInt",
+            Event::Float(_) => "Float",
+            Event::Str(_) => "Str",
+            Event::DataStart(_) => "DataStart",
+            Event::DataChunk(_) => "DataChunk",
+            Event::DataEnd => "DataEnd",
+        }
+    }
+}
+
+/// Parse a TJSON document into events.
+///
+/// The iterator produces each event as soon as it has been read, and an
+/// error in place of the first event which can't be. Trailing characters
+/// after the document are reported after its last event.
+pub fn parse<'a>(input: &'a str) -> Events<'a, StrRead<'a>> {
+    Events::new(StrRead::new(input))
+}
+
+/// Parse a TJSON document into events as it is read from an IO stream.
+///
+/// Only as much of the input is held in memory as the event being produced
+/// needs, so with `Events::chunked_data` even documents with data larger
+/// than memory can be read. The stream is read a byte at a time, so wrap
+/// anything but an in-memory reader in a `BufReader`.
+pub fn parse_reader<R>(reader: R) -> Events<'static, IoRead<R>>
+where
+    R: io::Read,
+{
+    Events::new(IoRead::new(reader))
+}
+
+/// Write a sequence of events as compact TJSON.
+///
+/// # Errors
+///
+/// Fails with an `ErrorCode::InvalidEventSequence` error if the events are
+/// not exactly one well-formed document, for example a `Key` outside of an
+/// object, an `ArrayEnd` closing an object, or events left over after the
+/// end of the document. Everything before the offending event has already
+/// been written by then. Also fails if writing to `writer` fails.
+pub fn write<'a, W, I>(mut writer: W, events: I) -> Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = Event<'a>>,
+{
+    let mut formatter = CompactFormatter;
+    let mut open: Vec<Open> = Vec::new();
+    let mut done = false;
+    let mut encoded = String::new();
+
+    for event in events {
+        // Check that the event can come next, and start it off
+        match (open.last_mut(), &event) {
+            (_, _) if done => return Err(invalid(&event, "after the end of the document")),
+            (Some(&mut Open::Data(_)), &Event::DataChunk(_)) |
+            (Some(&mut Open::Data(_)), &Event::DataEnd) => {}
+            (Some(&mut Open::Data(_)), _) => return Err(invalid(&event, "inside data")),
+            (_, &Event::DataChunk(_)) |
+            (_, &Event::DataEnd) => return Err(invalid(&event, "outside of data")),
+            (Some(&mut Open::Object { ref mut first, key: false, ref mut tag }),
+             &Event::Key(ref name)) => {
+                try!(formatter.begin_object_key(&mut writer, *first).map_err(Error::io));
+                try!(format_escaped_str(&mut writer, &mut formatter, name).map_err(Error::io));
+                try!(formatter.end_object_key(&mut writer).map_err(Error::io));
+                try!(formatter.begin_object_value(&mut writer).map_err(Error::io));
+                *first = false;
+                tag.clear();
+                if let Some(colon) = name.rfind(':') {
+                    tag.push_str(&name[colon + 1..]);
+                }
+            }
+            (Some(&mut Open::Object { key: false, .. }), &Event::ObjectEnd) => {}
+            (Some(&mut Open::Object { key: false, .. }), _) => {
+                return Err(invalid(&event, "where an object member name was expected"));
+            }
+            (Some(&mut Open::Object { key: true, .. }), &Event::Key(_)) |
+            (Some(&mut Open::Object { key: true, .. }), &Event::ObjectEnd) |
+            (Some(&mut Open::Object { key: true, .. }), &Event::ArrayEnd) => {
+                return Err(invalid(&event, "where an object member value was expected"));
+            }
+            (Some(&mut Open::Array { .. }), &Event::ArrayEnd) => {}
+            (Some(&mut Open::Array { ref mut first, .. }), _) => {
+                match event {
+                    Event::Key(_) => return Err(invalid(&event, "outside of an object")),
+                    Event::ObjectEnd => return Err(invalid(&event, "closing an array")),
+                    _ => {}
+                }
+                try!(formatter.begin_array_value(&mut writer, *first).map_err(Error::io));
+                *first = false;
+            }
+            (None, &Event::Key(_)) |
+            (None, &Event::ObjectEnd) |
+            (None, &Event::ArrayEnd) => {
+                return Err(invalid(&event, "outside of an object or array"));
+            }
+            _ => {}
+        }
+
+        let complete = match event {
+            Event::ObjectStart => {
+                try!(formatter.begin_object(&mut writer).map_err(Error::io));
+                open.push(
+                    Open::Object {
+                        first: true,
+                        key: false,
+                        tag: String::new(),
+                    },
+                );
+                false
+            }
+            Event::Key(_) => {
+                if let Some(&mut Open::Object { ref mut key, .. }) = open.last_mut() {
+                    *key = true;
+                }
+                false
+            }
+            Event::ObjectEnd => {
+                open.pop();
+                try!(formatter.end_object(&mut writer).map_err(Error::io));
+                true
+            }
+            Event::ArrayStart => {
+                try!(formatter.begin_array(&mut writer).map_err(Error::io));
+                let tag = element_tag(value_tag(&open)).to_owned();
+                open.push(Open::Array { first: true, tag: tag });
+                false
+            }
+            Event::ArrayEnd => {
+                open.pop();
+                try!(formatter.end_array(&mut writer).map_err(Error::io));
+                true
+            }
+            Event::Null => {
+                try!(formatter.write_null(&mut writer).map_err(Error::io));
+                true
+            }
+            Event::Bool(value) => {
+                try!(formatter.write_bool(&mut writer, value).map_err(Error::io));
+                true
+            }
+            Event::Int(value) => {
+                try!(formatter.write_i64(&mut writer, value).map_err(Error::io));
+                true
+            }
+            Event::UInt(value) => {
+                try!(formatter.write_u64(&mut writer, value).map_err(Error::io));
+                true
+            }
+            Event::Float(value) => {
+                let result = match value.classify() {
+                    FpCategory::Nan | FpCategory::Infinite => formatter.write_null(&mut writer),
+                    _ => formatter.write_f64(&mut writer, value),
+                };
+                try!(result.map_err(Error::io));
+                true
+            }
+            Event::Str(ref value) => {
+                try!(format_escaped_str(&mut writer, &mut formatter, value).map_err(Error::io));
+                true
+            }
+            Event::DataStart(_) => {
+                try!(formatter.begin_string(&mut writer).map_err(Error::io));
+                let encoder = match value_tag(&open) {
+                    "d16" => Encoder::hex(),
+                    "d32" => Encoder::b32(),
+                    _ => Encoder::b64url(),
+                };
+                open.push(Open::Data(encoder));
+                false
+            }
+            Event::DataChunk(ref chunk) => {
+                if let Some(&mut Open::Data(ref mut encoder)) = open.last_mut() {
+                    encoded.clear();
+                    encoder.encode(chunk, &mut encoded);
+                    try!(writer.write_all(encoded.as_bytes()).map_err(Error::io));
+                }
+                false
+            }
+            Event::DataEnd => {
+                if let Some(Open::Data(mut encoder)) = open.pop() {
+                    encoded.clear();
+                    encoder.finish(&mut encoded);
+                    try!(writer.write_all(encoded.as_bytes()).map_err(Error::io));
+                }
+                try!(formatter.end_string(&mut writer).map_err(Error::io));
+                true
+            }
+        };
+
+        // Finish off a complete value in whatever contains it
+        if complete {
+            match open.last_mut() {
+                Some(&mut Open::Object { ref mut key, .. }) => {
+                    try!(formatter.end_object_value(&mut writer).map_err(Error::io));
+                    *key = false;
+                }
+                Some(&mut Open::Array { .. }) => {
+                    try!(formatter.end_array_value(&mut writer).map_err(Error::io));
+                }
+                Some(&mut Open::Data(_)) => unreachable!(),
+                None => done = true,
+            }
+        }
+    }
+
+    if !open.is_empty() {
+        let plural = if open.len() == 1 { "" } else { "s" };
+        let message = format!("events ended with {} unclosed container{}", open.len(), plural);
+        return Err(Error::syntax(Code::InvalidEventSequence(message), 0, 0));
+    }
+    if !done {
+        let message = "events ended before any value".to_owned();
+        return Err(Error::syntax(Code::InvalidEventSequence(message), 0, 0));
+    }
+
+    Ok(())
+}
+
+/// An array, object or string of data which `write` has started but not yet
+/// ended. Arrays and objects keep the tag of the value being written in them.
+enum Open {
+    Object { first: bool, key: bool, tag: String },
+    Array { first: bool, tag: String },
+    Data(Encoder),
+}
+
+/// The tag of the next value to be written.
+fn value_tag(open: &[Open]) -> &str {
+    match open.last() {
+        Some(&Open::Object { ref tag, .. }) |
+        Some(&Open::Array { ref tag, .. }) => tag,
+        _ => "",
+    }
+}
+
+/// The tag of the elements of arrays and sets tagged `A<t>` or `S<t>`.
+fn element_tag(tag: &str) -> &str {
+    if (tag.starts_with("A<") || tag.starts_with("S<")) && tag.ends_with('>') {
+        &tag[2..tag.len() - 1]
+    } else {
+        ""
+    }
+}
+
+fn invalid(event: &Event, problem: &str) -> Error {
+    let message = format!("{} event {}", event.name(), problem);
+    Error::syntax(Code::InvalidEventSequence(message), 0, 0)
+}
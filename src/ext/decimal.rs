@@ -0,0 +1,120 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exact decimal fields (requires the `decimal` feature)
+//!
+//! Serializes a `rust_decimal::Decimal` as a TJSON number written with its
+//! exact digits, and deserializes one from the text of the number in the
+//! input without going through an `f64`, so values like `0.1` are read
+//! exactly.
+//!
+//! ```rust
+//! extern crate rust_decimal;
+//! extern crate tjson;
+//!
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! use rust_decimal::Decimal;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Payment {
+//!     #[serde(with = "tjson::ext::decimal")]
+//!     amount: Decimal,
+//! }
+//!
+//! fn main() {
+//!     let a: Payment = tjson::from_str(r#"{"amount": 0.1}"#).unwrap();
+//!     let b: Payment = tjson::from_str(r#"{"amount": 0.2}"#).unwrap();
+//!
+//!     let total = Payment { amount: a.amount + b.amount };
+//!     assert_eq!(tjson::to_string(&total).unwrap(), r#"{"amount:f":0.3}"#);
+//! }
+//! ```
+//!
+//! The exact text is only available when deserializing with tjson. Other
+//! deserializers, and `tjson::Value`s which hold a floating point number,
+//! provide the nearest `f64`, which is converted from its shortest decimal
+//! representation. Without the `decimal` feature, numbers like these can only
+//! be read as `f64` and are rounded to the nearest binary floating point
+//! value.
+
+use std::fmt;
+
+use rust_decimal::Decimal;
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use number::{self, DECIMAL_TOKEN, Number};
+
+/// Serialize a `Decimal` as a TJSON number with its exact digits.
+///
+/// Other serializers see a newtype struct wrapping the decimal's text.
+pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Number::from_decimal(*value).serialize(serializer)
+}
+
+/// Deserialize a `Decimal` from the text of a TJSON number.
+///
+/// Numbers with more significant digits than a `Decimal` can hold exactly,
+/// or outside of its range, are rejected with a "number out of range" error.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(DECIMAL_TOKEN, DecimalVisitor)
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal number")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        number::parse_decimal(value).map_err(
+            |_| if value.parse::<f64>().is_ok() {
+                // Well formed, but too large or too precise
+                de::Error::custom("number out of range")
+            } else {
+                de::Error::invalid_value(de::Unexpected::Str(value), &self)
+            },
+        )
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
@@ -0,0 +1,16 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Modules for use with serde's `#[serde(with = "...")]` field attribute,
+//! covering types which TJSON can represent better than their own `Serialize`
+//! and `Deserialize` implementations do.
+
+#[cfg(feature = "decimal")]
+pub mod decimal;
+
+pub mod pairs;
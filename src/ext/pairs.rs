@@ -0,0 +1,104 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Objects as ordered lists of members
+//!
+//! Serializes a `Vec<(String, V)>` as an object with a member for each pair,
+//! in order, rather than as an array of arrays, and deserializes one from an
+//! object in the order its members are written. This keeps the order of
+//! members without the `preserve_order` feature, and without committing to
+//! a map type.
+//!
+//! ```rust
+//! extern crate tjson;
+//!
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! use tjson::Value;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Headers {
+//!     #[serde(with = "tjson::ext::pairs")]
+//!     fields: Vec<(String, Value)>,
+//! }
+//!
+//! fn main() {
+//!     let input = r#"{"fields:O":{"z:s":"last","a:u":"1"}}"#;
+//!     let headers: Headers = tjson::from_str(input).unwrap();
+//!     assert_eq!(headers.fields[0].0, "z:s");
+//!
+//!     assert_eq!(tjson::to_string(&headers).unwrap(), input);
+//! }
+//! ```
+//!
+//! Two pairs with the same name can't be written as an object, so
+//! serializing them fails, as does deserializing an object with two members
+//! of the same name.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+/// Serialize pairs as the members of an object, in order.
+pub fn serialize<S, V>(pairs: &[(String, V)], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    let mut names = HashSet::with_capacity(pairs.len());
+    let mut map = try!(serializer.serialize_map(Some(pairs.len())));
+    for (index, &(ref name, ref value)) in pairs.iter().enumerate() {
+        if !names.insert(name.as_str()) {
+            return Err(ser::Error::custom(format_args!("duplicate key in pair {}", index)));
+        }
+        try!(map.serialize_entry(name, value));
+    }
+    map.end()
+}
+
+/// Deserialize the members of an object as pairs, in the order they are
+/// written.
+pub fn deserialize<'de, D, V>(deserializer: D) -> Result<Vec<(String, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    deserializer.deserialize_map(PairsVisitor(PhantomData))
+}
+
+struct PairsVisitor<V>(PhantomData<V>);
+
+impl<'de, V> Visitor<'de> for PairsVisitor<V>
+where
+    V: Deserialize<'de>,
+{
+    type Value = Vec<(String, V)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut names = HashSet::new();
+        let mut pairs = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some((name, value)) = try!(access.next_entry::<String, V>()) {
+            if !names.insert(name.clone()) {
+                return Err(de::Error::custom(format_args!("duplicate member {:?}", name)));
+            }
+            pairs.push((name, value));
+        }
+        Ok(pairs)
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Saving and loading TJSON files
+//!
+//! `save` replaces a file atomically: the new contents are written to a
+//! temporary file in the same directory, flushed to disk, and renamed over
+//! the old file, so that readers and crashes only ever see the old contents
+//! or the new ones, never a mix or a truncated file.
+//!
+//! ```rust,no_run
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! use tjson::Value;
+//! use tjson::fs::{Format, SaveOptions};
+//!
+//! # fn main() {
+//! let options = SaveOptions::new().format(Format::Pretty);
+//! tjson::fs::save("state.tjson", &tjson!({ "runs": 1 }), &options).unwrap();
+//!
+//! let state: Value = tjson::fs::load("state.tjson").unwrap();
+//! assert_eq!(state["runs:i"], 1);
+//! # }
+//! ```
+//!
+//! A document which is part of the program, such as default settings, can
+//! be embedded with `include_tjson!` from the `tjson_macros` crate. The file
+//! is parsed as the program compiles, so a malformed one is a compile error
+//! rather than a panic at startup, and the macro expands to code which
+//! builds the `Value` without parsing anything:
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #[macro_use]
+//! extern crate tjson_macros;
+//!
+//! # fn main() {
+//! let config = include_tjson!("tests/include/config.tjson");
+//! assert_eq!(config["retries:u"], tjson!(3));
+//! # }
+//! ```
+//!
+//! ```rust,compile_fail
+//! # extern crate tjson;
+//! #[macro_use]
+//! extern crate tjson_macros;
+//!
+//! # fn main() {
+//! // error: tests/include/malformed.tjson: expected `,` or `]` at line 4 column 22
+//! let config = include_tjson!("tests/include/malformed.tjson");
+//! # }
+//! ```
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use de::from_slice;
+use error::{Error, Result};
+use ser::{ArrayWrap, KeyOrder, KeySortRule, PrettyFormatter, SerializeConfig, Serializer,
+          canonicalize, to_writer_with};
+use value::to_value;
+
+/// How `save` writes the document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Without any whitespace, as `tjson::to_writer` writes it (the
+    /// default).
+    Compact,
+
+    /// Laid out as `tjson::to_string_pretty_stable` does, ending in a
+    /// newline.
+    Pretty,
+
+    /// Compact, with the members of every object sorted by name with the
+    /// options' `KeySortRule`, so that equal values are always saved as the
+    /// same bytes whatever order their members were inserted in.
+    Canonical,
+}
+
+/// Settings for `save`.
+#[derive(Clone, Debug)]
+pub struct SaveOptions {
+    format: Format,
+    mode: Option<u32>,
+    key_sort_rule: KeySortRule,
+}
+
+impl SaveOptions {
+    /// Settings which save the document compactly, with the permissions of
+    /// the file being replaced, or the default permissions for a new file.
+    pub fn new() -> Self {
+        SaveOptions {
+            format: Format::Compact,
+            mode: None,
+            key_sort_rule: KeySortRule::UntaggedName,
+        }
+    }
+
+    /// Set how the document is written.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set how `Format::Canonical` orders the members of objects, by default
+    /// `KeySortRule::UntaggedName`.
+    pub fn key_sort_rule(mut self, rule: KeySortRule) -> Self {
+        self.key_sort_rule = rule;
+        self
+    }
+
+    /// Set the permission bits of the saved file, such as `0o600`, which
+    /// are applied exactly rather than through the process's umask.
+    #[cfg(unix)]
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions::new()
+    }
+}
+
+/// Serialize the given data structure as TJSON into the file at the given
+/// path, atomically replacing the file if it exists.
+///
+/// The document is written to a new temporary file next to `path`, which is
+/// synced to disk and then renamed to `path`. On Unix, the directory is then
+/// synced as well so that the rename itself survives a crash. If anything
+/// fails, the temporary file is removed and any existing file at `path` is
+/// left as it was.
+///
+/// # Errors
+///
+/// Every error includes the path in its message and in `Error::path`.
+/// Saving fails if `T`'s implementation of `Serialize` decides to fail, if
+/// `T` contains a map with non-string keys, or if creating, writing or
+/// renaming the file fails.
+pub fn save<T: ?Sized, P>(path: P, value: &T, options: &SaveOptions) -> Result<()>
+where
+    T: Serialize,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    save_to(path, value, options).map_err(|err| err.with_path(path))
+}
+
+fn save_to<T: ?Sized>(path: &Path, value: &T, options: &SaveOptions) -> Result<()>
+where
+    T: Serialize,
+{
+    // Sort the sets before touching the file system
+    let canonical = match options.format {
+        Format::Canonical => Some(canonicalize(try!(to_value(value)))),
+        Format::Compact | Format::Pretty => None,
+    };
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let (temp_path, file) = try!(create_temp(path, dir, options).map_err(Error::io));
+
+    let written = match canonical {
+        Some(ref value) => write_synced(file, value, options),
+        None => write_synced(file, value, options),
+    };
+    let renamed = written.and_then(|()| fs::rename(&temp_path, path).map_err(Error::io));
+    if let Err(err) = renamed {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+
+    sync_dir(dir).map_err(Error::io)
+}
+
+/// Creates a temporary file in `dir` to be renamed to `path`, with the
+/// permissions the saved file should have.
+fn create_temp(path: &Path, dir: &Path, options: &SaveOptions) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let permissions = match options.mode {
+        #[cfg(unix)]
+        Some(mode) => Some(fs::Permissions::from_mode(mode)),
+        #[cfg(not(unix))]
+        Some(_) => None,
+        None => fs::metadata(path).ok().map(|metadata| metadata.permissions()),
+    };
+
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_else(|| "tjson".as_ref()));
+    name.push(format!(".{}.tmp", process::id()));
+
+    loop {
+        let mut temp_name = name.clone();
+        temp_name.push(COUNTER.fetch_add(1, Ordering::Relaxed).to_string());
+        let temp_path = dir.join(temp_name);
+
+        let mut open = OpenOptions::new();
+        open.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            // Not readable by anyone else until the permissions are set
+            open.mode(0o600);
+        }
+
+        let file = match open.open(&temp_path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        if let Some(permissions) = permissions {
+            if let Err(err) = file.set_permissions(permissions) {
+                let _ = fs::remove_file(&temp_path);
+                return Err(err);
+            }
+        }
+
+        return Ok((temp_path, file));
+    }
+}
+
+fn write_synced<T: ?Sized>(file: File, value: &T, options: &SaveOptions) -> Result<()>
+where
+    T: Serialize,
+{
+    let mut writer = BufWriter::new(file);
+    match options.format {
+        Format::Pretty => {
+            let formatter = PrettyFormatter::with_indent(b"  ")
+                .array_wrap(ArrayWrap::OnePerLine)
+                .trailing_newline(true);
+            try!(value.serialize(&mut Serializer::with_formatter(&mut writer, formatter)));
+        }
+        Format::Compact => try!(value.serialize(&mut Serializer::new(&mut writer))),
+        Format::Canonical => {
+            let sorted = KeyOrder::Sorted(options.key_sort_rule);
+            try!(to_writer_with(&mut writer, &SerializeConfig::new().key_order(sorted), value));
+        }
+    }
+
+    let file = try!(writer.into_inner().map_err(|err| Error::io(err.into())));
+    file.sync_all().map_err(Error::io)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir).and_then(|dir| dir.sync_all())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Deserialize an instance of type `T` from the TJSON file at the given path.
+///
+/// # Errors
+///
+/// Every error includes the path in its message and in `Error::path`.
+/// A missing file is an IO error which converts into an `io::Error` of kind
+/// `NotFound`, while a file which can't be parsed gives an error with the
+/// line and column of the problem, for the same reasons as
+/// `tjson::from_slice`.
+pub fn load<T, P>(path: P) -> Result<T>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let bytes = try!(fs::read(path).map_err(|err| Error::io(err).with_path(path)));
+    from_slice(&bytes).map_err(|err| err.with_path(path))
+}
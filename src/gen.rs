@@ -0,0 +1,390 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Seeded generators of pseudo-random documents for benchmarks, tests, and
+//! fuzzing corpora (requires the `testgen` feature)
+//!
+//! A `Generator` always builds the same document from the same seed and
+//! settings, on every platform, so measurements taken with its documents are
+//! comparable between machines and runs.
+//!
+//! ```rust
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::gen::Generator;
+//! use tjson::value::ValueKind;
+//!
+//! let generator = Generator::new(42)
+//!     .depth(3)
+//!     .breadth(5)
+//!     .kinds(&[ValueKind::Object, ValueKind::Array, ValueKind::String, ValueKind::Number])
+//!     .string_len(4..12);
+//!
+//! let value = generator.build();
+//! assert_eq!(value, generator.build());
+//! assert!(value.stats().max_depth <= 3);
+//!
+//! let telemetry = Generator::realistic_telemetry().breadth(100).build();
+//! assert_eq!(telemetry.as_array().unwrap().len(), 100);
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use chrono::offset::TimeZone;
+use chrono::offset::utc::UTC;
+
+use map::Map;
+use set::Set;
+use value::{Value, ValueKind};
+
+const ALL_KINDS: &[ValueKind] = &[
+    ValueKind::Undefined,
+    ValueKind::Bool,
+    ValueKind::Data,
+    ValueKind::Number,
+    ValueKind::String,
+    ValueKind::Timestamp,
+    ValueKind::Array,
+    ValueKind::Set,
+    ValueKind::Object,
+];
+
+/// Characters of generated strings. Mostly ASCII, with a few which need
+/// escaping or take more than one byte.
+const ALPHABET: &[char] = &[
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ' ', '-',
+    '_', '.', '"', '\\', '\n', '\u{e9}', '\u{20ac}', '\u{1f600}',
+];
+
+const HOSTS: &[&str] = &[
+    "ingest-worker-eu-west-1",
+    "ingest-worker-eu-west-2",
+    "ingest-worker-us-east-1",
+    "api-gateway-eu-west-1",
+    "api-gateway-us-east-1",
+    "batch-runner-ap-south-1",
+];
+
+const METRICS: &[(&str, &str, &str)] = &[
+    ("http.request.duration", "milliseconds", "histogram"),
+    ("http.request.count", "requests", "counter"),
+    ("cpu.utilization", "percent", "gauge"),
+    ("memory.resident", "bytes", "gauge"),
+    ("queue.depth", "messages", "gauge"),
+    ("disk.io.bytes_written", "bytes", "counter"),
+];
+
+const WORDS: &[&str] = &[
+    "server", "port", "host", "timeout", "retries", "enabled", "name", "path", "level", "workers",
+    "cache", "limit", "backend", "region", "tls", "auth", "pool", "interval", "format", "mode",
+];
+
+/// The kind of document a `Generator` builds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Shape {
+    Random,
+    Telemetry,
+    Config,
+    Binary,
+}
+
+/// Builds pseudo-random documents, deterministically for a given seed and
+/// settings.
+///
+/// `Generator::new` builds documents of arbitrary structure, controlled by
+/// every setting. The corpora, such as `Generator::realistic_telemetry`,
+/// build documents shaped like common real-world data, and use the settings
+/// as their documentation describes.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    seed: u64,
+    shape: Shape,
+    depth: usize,
+    breadth: usize,
+    kinds: Vec<ValueKind>,
+    string_len: Range<usize>,
+    data_len: Range<usize>,
+}
+
+impl Generator {
+    /// A generator of documents of arbitrary structure.
+    ///
+    /// By default documents nest containers at most 4 deep with at most 8
+    /// values in each, use every kind of value, and have strings of fewer
+    /// than 16 characters and data of fewer than 64 bytes.
+    pub fn new(seed: u64) -> Self {
+        Generator {
+            seed: seed,
+            shape: Shape::Random,
+            depth: 4,
+            breadth: 8,
+            kinds: ALL_KINDS.to_vec(),
+            string_len: 0..16,
+            data_len: 0..64,
+        }
+    }
+
+    /// A corpus of metrics, like a batch sent by a telemetry agent: an array
+    /// of `breadth` points, 1000 by default, each an object with a
+    /// timestamp, host, value, and metric description.
+    pub fn realistic_telemetry() -> Self {
+        Generator {
+            shape: Shape::Telemetry,
+            breadth: 1000,
+            ..Generator::new(0)
+        }
+    }
+
+    /// A corpus of configuration files: objects nested at most `depth` deep
+    /// with at most `breadth` members each, named after common settings,
+    /// whose values are booleans, numbers, strings of `string_len`
+    /// characters, and arrays of at least one string.
+    pub fn config_like() -> Self {
+        Generator {
+            shape: Shape::Config,
+            string_len: 1..24,
+            ..Generator::new(0)
+        }
+    }
+
+    /// A corpus of binary attachments: an array of `breadth` objects, 64 by
+    /// default, each with a name and checksum alongside `data_len` bytes of
+    /// data, 1 to 8KiB by default.
+    pub fn binary_heavy() -> Self {
+        Generator {
+            shape: Shape::Binary,
+            breadth: 64,
+            data_len: 1024..8192,
+            ..Generator::new(0)
+        }
+    }
+
+    /// Build documents from a different seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Nest arrays, sets, and objects at most this deep, as counted by
+    /// `ValueStats::max_depth`.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Put at most this many values in each array, set, and object.
+    pub fn breadth(mut self, breadth: usize) -> Self {
+        self.breadth = breadth;
+        self
+    }
+
+    /// Only build values of these kinds.
+    ///
+    /// Containers can't be built deeper than `depth`, so if no scalar kinds
+    /// are given, the deepest containers are empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kinds` is empty.
+    pub fn kinds(mut self, kinds: &[ValueKind]) -> Self {
+        assert!(!kinds.is_empty(), "Generator needs at least one kind of value");
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    /// Build strings and member names with a number of characters in this
+    /// range.
+    pub fn string_len(mut self, len: Range<usize>) -> Self {
+        self.string_len = len;
+        self
+    }
+
+    /// Build binary data with a number of bytes in this range.
+    pub fn data_len(mut self, len: Range<usize>) -> Self {
+        self.data_len = len;
+        self
+    }
+
+    /// Build a document. Every call with the same settings returns the same
+    /// document.
+    pub fn build(&self) -> Value {
+        let mut rng = Rng::new(self.seed);
+        match self.shape {
+            Shape::Random => self.random(&mut rng, 0),
+            Shape::Telemetry => self.telemetry(&mut rng),
+            Shape::Config => self.config(&mut rng, 1),
+            Shape::Binary => self.binary(&mut rng),
+        }
+    }
+
+    fn random(&self, rng: &mut Rng, depth: usize) -> Value {
+        let (containers, scalars): (Vec<ValueKind>, Vec<ValueKind>) =
+            self.kinds.iter().partition(|kind| is_container(**kind));
+
+        // The root is a container if it can be, so that documents aren't
+        // trivially small, and values below `self.depth` containers are
+        // scalars
+        let candidates = if depth == 0 && self.depth > 0 && !containers.is_empty() {
+            &containers
+        } else if depth >= self.depth && !scalars.is_empty() {
+            &scalars
+        } else {
+            &self.kinds
+        };
+        let kind = candidates[rng.below(candidates.len())];
+
+        // Leave out the children of containers which would have to be
+        // containers too deep
+        let len = if depth + 1 < self.depth || (depth < self.depth && !scalars.is_empty()) {
+            rng.below(self.breadth + 1)
+        } else {
+            0
+        };
+        match kind {
+            ValueKind::Undefined => Value::Undefined,
+            ValueKind::Bool => Value::Bool(rng.below(2) == 0),
+            ValueKind::Data => Value::Data(rng.data(&self.data_len).into()),
+            ValueKind::Number => rng.number(),
+            ValueKind::String => Value::String(rng.string(&self.string_len)),
+            ValueKind::Timestamp => {
+                let secs = rng.below(4_000_000_000) as i64;
+                Value::Timestamp(UTC.timestamp(secs, 0))
+            }
+            ValueKind::Array => {
+                Value::Array((0..len).map(|_| self.random(rng, depth + 1)).collect())
+            }
+            ValueKind::Set => {
+                let mut set = Set::new();
+                for _ in 0..len {
+                    set.insert(self.random(rng, depth + 1));
+                }
+                Value::Set(set)
+            }
+            ValueKind::Object => {
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let name = rng.string(&self.string_len);
+                    map.insert(name, self.random(rng, depth + 1));
+                }
+                Value::Object(map)
+            }
+        }
+    }
+
+    fn telemetry(&self, rng: &mut Rng) -> Value {
+        let start = 1_500_000_000 + rng.below(100_000_000) as u64;
+        let points = (0..self.breadth as u64)
+            .map(
+                |i| {
+                    let (name, unit, kind) = METRICS[rng.below(METRICS.len())];
+                    let host = HOSTS[rng.below(HOSTS.len())];
+                    let value = if kind == "counter" {
+                        Value::from(rng.below(1_000_000) as u64)
+                    } else {
+                        Value::from(rng.below(4_000_000) as f64 / 16.0)
+                    };
+                    tjson!({
+                        "timestamp": start + i * 10 + rng.below(10) as u64,
+                        "host": host,
+                        "value": value,
+                        "metric": { "name": name, "unit": unit, "type": kind },
+                    })
+                },
+            )
+            .collect();
+        Value::Array(points)
+    }
+
+    fn config(&self, rng: &mut Rng, depth: usize) -> Value {
+        let mut map = Map::new();
+        for i in 0..rng.below(self.breadth + 1) {
+            let name = format!("{}{}", WORDS[rng.below(WORDS.len())], i);
+            let value = match rng.below(if depth < self.depth { 6 } else { 4 }) {
+                0 => Value::Bool(rng.below(2) == 0),
+                1 => Value::from(rng.below(65_536) as u64),
+                2 | 3 => Value::String(rng.string(&self.string_len)),
+                // Not empty, so that the array's elements have a tag
+                4 => {
+                    let len = 1 + rng.below(self.breadth.max(1));
+                    let strings = (0..len).map(|_| Value::String(rng.string(&self.string_len)));
+                    Value::Array(strings.collect())
+                }
+                _ => self.config(rng, depth + 1),
+            };
+            map.insert(name, value);
+        }
+        Value::Object(map)
+    }
+
+    fn binary(&self, rng: &mut Rng) -> Value {
+        let records = (0..self.breadth)
+            .map(
+                |i| {
+                    tjson!({
+                        "name": format!("attachment-{}.bin", i),
+                        "checksum": rng.next(),
+                        "data": Value::Data(rng.data(&self.data_len).into()),
+                    })
+                },
+            )
+            .collect();
+        Value::Array(records)
+    }
+}
+
+fn is_container(kind: ValueKind) -> bool {
+    kind == ValueKind::Array || kind == ValueKind::Set || kind == ValueKind::Object
+}
+
+/// SplitMix64, which is small, fast, and good enough for test data, unlike
+/// the platform-dependent hashers in std.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, or 0 if `n` is 0.
+    fn below(&mut self, n: usize) -> usize {
+        if n == 0 { 0 } else { (self.next() % n as u64) as usize }
+    }
+
+    fn len(&mut self, range: &Range<usize>) -> usize {
+        range.start + self.below(range.end.saturating_sub(range.start))
+    }
+
+    fn string(&mut self, len: &Range<usize>) -> String {
+        (0..self.len(len)).map(|_| ALPHABET[self.below(ALPHABET.len())]).collect()
+    }
+
+    fn data(&mut self, len: &Range<usize>) -> Vec<u8> {
+        (0..self.len(len)).map(|_| self.next() as u8).collect()
+    }
+
+    fn number(&mut self) -> Value {
+        match self.below(3) {
+            0 => Value::from(self.next()),
+            1 => Value::from(-(self.below(1 << 40) as i64)),
+            // Multiples of 1/8 are exact in binary, so they print and parse
+            // back exactly
+            _ => Value::from(self.below(1 << 24) as f64 / 8.0 - 1_000_000.0),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Media type negotiation for TJSON over HTTP (requires the `http` feature)
+//!
+//! These helpers only deal with the `Content-Type` header and the body bytes,
+//! so they can be used with any HTTP library built on the `http` crate's
+//! types.
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::Value;
+//!
+//! let (content_type, body) = tjson::http::respond(&tjson!({ "ok": true })).unwrap();
+//! assert_eq!(content_type, "application/tjson");
+//!
+//! let value: Value = tjson::http::parse_request_body("application/tjson; charset=utf-8", &body)
+//!     .unwrap();
+//! assert_eq!(value, tjson!({ "ok:b": true }));
+//! # }
+//! ```
+
+use http_types::header::HeaderValue;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use de::from_slice;
+use error::{Code, Error, Result};
+use ser::to_vec;
+
+const MEDIA_TYPE: &str = "application/tjson";
+
+/// The media type of TJSON documents, for use in `Content-Type` and `Accept`
+/// headers.
+pub fn content_type() -> &'static str {
+    MEDIA_TYPE
+}
+
+/// Serialize the given data structure as a TJSON response body, along with
+/// the `Content-Type` header value to send with it.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn respond<T: ?Sized>(value: &T) -> Result<(HeaderValue, Vec<u8>)>
+where
+    T: Serialize,
+{
+    let body = try!(to_vec(value));
+    Ok((HeaderValue::from_static(MEDIA_TYPE), body))
+}
+
+/// Deserialize a request body sent with the given `Content-Type`, using the
+/// default `Options`.
+///
+/// See `Options::parse_request_body`.
+pub fn parse_request_body<T>(content_type: &str, body: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    Options::new().parse_request_body(content_type, body)
+}
+
+/// Settings for accepting TJSON request bodies.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::Value;
+/// use tjson::http::Options;
+///
+/// let options = Options::new().max_body_len(16);
+///
+/// let err = options
+///     .parse_request_body::<Value>("application/tjson", br#"{"name": "a long name"}"#)
+///     .unwrap_err();
+/// assert!(err.is_body_too_large());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    max_body_len: Option<usize>,
+}
+
+impl Options {
+    /// Options which accept request bodies of any length.
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Reject request bodies longer than `len` bytes without parsing them.
+    pub fn max_body_len(mut self, len: usize) -> Self {
+        self.max_body_len = Some(len);
+        self
+    }
+
+    /// Deserialize a request body sent with the given `Content-Type`.
+    ///
+    /// The media type must be `application/tjson`, compared
+    /// case-insensitively. Parameters are allowed, but a `charset` parameter
+    /// must name UTF-8 since that is the only encoding of TJSON.
+    ///
+    /// # Errors
+    ///
+    /// Fails with an error for which `is_unsupported_media_type` returns true
+    /// if the media type is not TJSON, and with one for which
+    /// `is_body_too_large` returns true if the body is longer than
+    /// `max_body_len`. Both are checked before the body is parsed. Otherwise
+    /// this can fail for the same reasons as `tjson::from_slice`.
+    pub fn parse_request_body<T>(&self, content_type: &str, body: &[u8]) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        if !is_tjson(content_type) {
+            let code = Code::UnsupportedMediaType(content_type.to_owned());
+            return Err(Error::syntax(code, 0, 0));
+        }
+
+        if let Some(max) = self.max_body_len {
+            if body.len() > max {
+                return Err(Error::syntax(Code::BodyTooLarge(body.len(), max), 0, 0));
+            }
+        }
+
+        from_slice(body)
+    }
+}
+
+/// Whether a `Content-Type` header value names TJSON encoded as UTF-8.
+fn is_tjson(content_type: &str) -> bool {
+    let mut parts = content_type.split(';');
+
+    let essence = parts.next().unwrap_or("").trim();
+    if !essence.eq_ignore_ascii_case(MEDIA_TYPE) {
+        return false;
+    }
+
+    for param in parts {
+        let param = param.trim();
+        if param.is_empty() {
+            continue;
+        }
+
+        let (name, value) = match param.find('=') {
+            Some(i) => (param[..i].trim(), param[i + 1..].trim()),
+            None => return false,
+        };
+        if name.eq_ignore_ascii_case("charset") {
+            let value = value.trim_matches('"');
+            if !value.eq_ignore_ascii_case("utf-8") && !value.eq_ignore_ascii_case("utf8") {
+                return false;
+            }
+        }
+    }
+
+    true
+}
@@ -0,0 +1,191 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `IndexMap` behind `Map` and `Set` with the `preserve_order_indexmap`
+//! feature, made to behave as the `LinkedHashMap` of `preserve_order` does:
+//! removing an entry keeps the others in order, and maps compare, hash and
+//! order entry by entry in their order, so the two features are
+//! interchangeable.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::ops;
+
+use indexmap::IndexMap;
+use indexmap::map::{Entry, IntoIter, Iter, IterMut, Keys, Values};
+
+#[derive(Clone)]
+pub struct IndexedMap<K: Hash + Eq, V> {
+    map: IndexMap<K, V>,
+}
+
+impl<K: Hash + Eq, V> IndexedMap<K, V> {
+    pub fn new() -> Self {
+        IndexedMap { map: IndexMap::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        IndexedMap { map: IndexMap::with_capacity(capacity) }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.get_mut(key)
+    }
+
+    pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.get_key_value(key)
+    }
+
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a new key at the end. An existing key is moved to the end,
+    /// as `LinkedHashMap::insert` does.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (index, old) = self.map.insert_full(key, value);
+        if old.is_some() {
+            let last = self.map.len() - 1;
+            self.map.move_index(index, last);
+        }
+        old
+    }
+
+    /// Removes a key, shifting the entries after it down so that they stay
+    /// in order.
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.shift_remove(key)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<K, V> {
+        self.map.entry(key)
+    }
+
+    pub fn iter(&self) -> Iter<K, V> {
+        self.map.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<K, V> {
+        self.map.iter_mut()
+    }
+
+    pub fn keys(&self) -> Keys<K, V> {
+        self.map.keys()
+    }
+
+    pub fn values(&self) -> Values<K, V> {
+        self.map.values()
+    }
+}
+
+impl<K: Hash + Eq, V> Default for IndexedMap<K, V> {
+    fn default() -> Self {
+        IndexedMap::new()
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for IndexedMap<K, V> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.map, formatter)
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for IndexedMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Eq for IndexedMap<K, V> {}
+
+impl<K: Hash + Eq + PartialOrd, V: PartialOrd> PartialOrd for IndexedMap<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Hash + Ord, V: Ord> Ord for IndexedMap<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: Hash + Eq, V: Hash> Hash for IndexedMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V, Q: ?Sized> ops::Index<&'a Q> for IndexedMap<K, V>
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for IndexedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> IntoIterator for IndexedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        self.map.into_iter()
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converting between TJSON and plain JSON
+//!
+//! `from_json` gives every member name of a plain JSON document the tag its
+//! value calls for, and `to_json` takes the tags off again for consumers
+//! which don't know about them. Both work on `Value`s, so documents are
+//! read and written as usual on either side:
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::Value;
+//!
+//! let plain: Value = tjson::from_str(r#"{"name": "ann", "scores": [1, 2.5]}"#).unwrap();
+//! let tagged = tjson::json::from_json(plain).unwrap();
+//! assert_eq!(tagged, tjson!({ "name:s": "ann", "scores:A<f>": [1.0, 2.5] }));
+//!
+//! let plain = tjson::json::to_json(tagged).unwrap();
+//! assert_eq!(plain, tjson!({ "name": "ann", "scores": [1.0, 2.5] }));
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::ser::Error as SerError;
+
+use encoding::b64url_encode;
+use error::{Error, Result};
+use map::Map;
+use timestamp;
+use number::Number;
+use value::{Set, Value, push_pointer_token};
+
+/// Tag the member names of a plain JSON value for the values they hold.
+///
+/// Strings are tagged `s`, booleans `b` and objects `O`. Numbers are tagged
+/// `i` if they are integers which fit in an `i64`, `u` for larger integers,
+/// and `f` otherwise. Arrays are tagged `A<...>` with the tag their elements
+/// share, except that integers and floats together are all floats. Names
+/// already containing a `:` get a tag of their own like any other, so they
+/// read back unchanged.
+///
+/// # Errors
+///
+/// Fails, naming the JSON pointer of the value, on `null`, which TJSON has
+/// no tag for, on an empty array, whose elements' tag is unknown, and on an
+/// array whose elements have different tags.
+pub fn from_json(value: Value) -> Result<Value> {
+    tagged(value, &mut String::new()).map(|(value, _)| value)
+}
+
+/// Take the tags off the member names of a TJSON value, for consumers of
+/// plain JSON.
+///
+/// Sets become arrays, timestamps RFC 3339 strings, and data base64url
+/// strings. Everything else is kept as it is.
+///
+/// # Errors
+///
+/// Fails, naming the JSON pointer of the object, if two members of an
+/// object have the same name once their tags are taken off.
+pub fn to_json(value: Value) -> Result<Value> {
+    untagged(value, &mut String::new())
+}
+
+/// The value with its members tagged, and its own tag.
+fn tagged(value: Value, path: &mut String) -> Result<(Value, String)> {
+    let value = match value {
+        Value::Object(map) => {
+            let mut members = Map::new();
+            for (name, member) in map {
+                let len = path.len();
+                push_pointer_token(path, &name);
+                let (member, tag) = try!(tagged(member, path));
+                path.truncate(len);
+                members.insert(format!("{}:{}", name, tag), member);
+            }
+            return Ok((Value::Object(members), "O".to_owned()));
+        }
+        Value::Array(elements) => Value::Array(try!(tagged_elements(elements, path))),
+        Value::Set(set) => {
+            let mut tagged_set = Set::new();
+            for element in try!(tagged_elements(set.into_iter().collect(), path)) {
+                tagged_set.insert(element);
+            }
+            Value::Set(tagged_set)
+        }
+        value => value,
+    };
+
+    let tag = try!(tag_of(&value, path));
+    Ok((widen(value, &tag), tag))
+}
+
+/// The elements with their members tagged.
+fn tagged_elements(elements: Vec<Value>, path: &mut String) -> Result<Vec<Value>> {
+    let mut tagged_elements = Vec::with_capacity(elements.len());
+    for (index, element) in elements.into_iter().enumerate() {
+        let len = path.len();
+        push_pointer_token(path, &index.to_string());
+        let (element, _) = try!(tagged(element, path));
+        path.truncate(len);
+        tagged_elements.push(element);
+    }
+    Ok(tagged_elements)
+}
+
+// Not public API. Should be pub(crate).
+/// The tag `from_json` gives a value at `path`. The members of objects in it
+/// are left as they are.
+#[doc(hidden)]
+pub fn tag_of(value: &Value, path: &mut String) -> Result<String> {
+    let tag = match *value {
+        Value::Undefined => return Err(error("TJSON has no null", path)),
+        Value::Bool(_) => "b",
+        Value::Number(ref n) if n.is_i64() => "i",
+        Value::Number(ref n) if n.is_u64() => "u",
+        Value::Number(_) => "f",
+        Value::String(_) => "s",
+        Value::Data(_) => "d",
+        Value::Timestamp(_) => "t",
+        Value::Object(_) => "O",
+        Value::Array(ref elements) => {
+            return shared_elements_tag(elements, path).map(|tag| format!("A<{}>", tag));
+        }
+        Value::Set(ref set) => {
+            return shared_elements_tag(set, path).map(|tag| format!("S<{}>", tag));
+        }
+    };
+    Ok(tag.to_owned())
+}
+
+/// The tag the elements of an array or set share.
+fn shared_elements_tag<'a, I>(elements: I, path: &mut String) -> Result<String>
+where
+    I: IntoIterator<Item = &'a Value>,
+{
+    let mut shared: Option<String> = None;
+    for (index, element) in elements.into_iter().enumerate() {
+        let len = path.len();
+        push_pointer_token(path, &index.to_string());
+        let tag = try!(tag_of(element, path));
+        path.truncate(len);
+
+        shared = Some(match shared {
+            None => tag,
+            Some(shared) => try!(common_tag(shared, tag, path)),
+        });
+    }
+
+    match shared {
+        Some(tag) => Ok(tag),
+        None => Err(error("cannot infer the tag of the elements of an empty array", path)),
+    }
+}
+
+/// The tag shared by values tagged `a` and `b`, if any.
+fn common_tag(a: String, b: String, path: &str) -> Result<String> {
+    match shared_tag(&a, &b) {
+        Some(tag) => Ok(tag),
+        None => Err(error(format_args!("array has elements tagged `{}` and `{}`", a, b), path)),
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// The tag of an array or set whose elements are tagged `a` and `b`, if
+/// they have one.
+#[doc(hidden)]
+pub fn shared_tag(a: &str, b: &str) -> Option<String> {
+    if a == b {
+        return Some(a.to_owned());
+    }
+    match (a, b) {
+        ("f", "i") | ("f", "u") | ("i", "f") | ("u", "f") => Some("f".to_owned()),
+        _ if a.len() > 3 && a.ends_with('>') && b.ends_with('>') && a[..2] == b[..2] => {
+            let inner = shared_tag(&a[2..a.len() - 1], &b[2..b.len() - 1]);
+            inner.map(|inner| format!("{}{}>", &a[..2], inner))
+        }
+        _ => None,
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Makes floats of the integers in a value with the tag `f`, or in the
+/// elements of an array or set of them.
+#[doc(hidden)]
+pub fn widen(value: Value, tag: &str) -> Value {
+    match value {
+        Value::Number(n) if tag == "f" => {
+            Value::Number(n.as_f64().and_then(Number::from_f64).unwrap_or(n))
+        }
+        Value::Array(elements) if tag.starts_with("A<") => {
+            let inner = &tag[2..tag.len() - 1];
+            Value::Array(elements.into_iter().map(|element| widen(element, inner)).collect())
+        }
+        Value::Set(set) if tag.starts_with("S<") => {
+            let inner = &tag[2..tag.len() - 1];
+            let mut widened = Set::new();
+            for element in set {
+                widened.insert(widen(element, inner));
+            }
+            Value::Set(widened)
+        }
+        value => value,
+    }
+}
+
+fn untagged(value: Value, path: &mut String) -> Result<Value> {
+    match value {
+        Value::Object(map) => {
+            let mut members = Map::new();
+            let mut tagged_names = HashMap::new();
+            for (name, member) in map {
+                let len = path.len();
+                push_pointer_token(path, &name);
+                let member = try!(untagged(member, path));
+                path.truncate(len);
+
+                let plain = match name.rfind(':') {
+                    Some(colon) => name[..colon].to_owned(),
+                    None => name.clone(),
+                };
+                if let Some(first) = tagged_names.insert(plain.clone(), name.clone()) {
+                    let msg = format_args!("members {:?} and {:?} have the same name", first, name);
+                    return Err(error(msg, path));
+                }
+                members.insert(plain, member);
+            }
+            Ok(Value::Object(members))
+        }
+        Value::Array(elements) => untagged_elements(elements, path).map(Value::Array),
+        Value::Set(set) => untagged_elements(set.into_iter().collect(), path).map(Value::Array),
+        Value::Timestamp(ref timestamp) => Ok(Value::String(timestamp::format(timestamp))),
+        Value::Data(ref data) => Ok(Value::String(b64url_encode(data))),
+        value => Ok(value),
+    }
+}
+
+fn untagged_elements(elements: Vec<Value>, path: &mut String) -> Result<Vec<Value>> {
+    let mut untagged_elements = Vec::with_capacity(elements.len());
+    for (index, element) in elements.into_iter().enumerate() {
+        let len = path.len();
+        push_pointer_token(path, &index.to_string());
+        untagged_elements.push(try!(untagged(element, path)));
+        path.truncate(len);
+    }
+    Ok(untagged_elements)
+}
+
+fn error<T: fmt::Display>(msg: T, path: &str) -> Error {
+    if path.is_empty() {
+        Error::custom(msg)
+    } else {
+        Error::custom(format_args!("{} at {}", msg, path))
+    }
+}
@@ -30,7 +30,7 @@
 //!       "boolean-example:b": true
 //!     }
 //!   ],
-//!   "set-example:S<i>": [1, 2, 3]
+//!   "set-example:S<i>": ["1", "2", "3"]
 //! }
 //! ```
 //!
@@ -57,14 +57,14 @@
 //! representation. This data structure is [`tjson::Value`][value].
 //!
 //! ```rust
-//! # use tjson::{Number, DateTime, Set, Map};
+//! # use tjson::{Data, Number, DateTime, Set, Map};
 //! #
 //! # #[allow(dead_code)]
 //! # #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
 //! enum Value {
 //!     Undefined,
 //!     Bool(bool),
-//!     Data(Vec<u8>),
+//!     Data(Data),
 //!     Number(Number),
 //!     String(String),
 //!     Timestamp(DateTime),
@@ -295,6 +295,15 @@
 //! `HashMap<K, V>`, as well as any structs or enums annotated with
 //! `#[derive(Serialize)]`.
 //!
+//! # Prelude and thread safety
+//!
+//! `use tjson::prelude::*;` imports `Value`, `Map`, `Set`, `Number`,
+//! `Error`, `from_str`, `to_string` and the `tjson!` macro.
+//!
+//! Values, numbers, errors and the other owned data types of this crate are
+//! `Send` and `Sync`. A `Deserializer` or `Serializer` is `Send` whenever the
+//! reader or writer it wraps is.
+//!
 //! [value]: https://docs.rs/tjson/value/enum.Value.html
 //! [from_str]: https://docs.rs/tjson/de/fn.from_str.html
 //! [from_slice]: https://docs.rs/tjson/de/fn.from_slice.html
@@ -318,29 +327,90 @@ extern crate ordered_float;
 #[macro_use]
 extern crate serde;
 
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "http")]
+extern crate http as http_types;
+#[cfg(feature = "preserve_order_indexmap")]
+extern crate indexmap;
 #[cfg(feature = "preserve_order")]
 extern crate linked_hash_map;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "decimal")]
+extern crate rust_decimal;
+
+#[cfg(all(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+compile_error!(
+    "the `preserve_order` and `preserve_order_indexmap` features both choose how `Map` and `Set` \
+     keep their order; enable only one of them"
+);
 
 #[doc(inline)]
-pub use self::de::{Deserializer, StreamDeserializer, from_reader, from_slice, from_str};
+pub use self::de::{Deserializer, StreamDeserializer, from_reader, from_reader_with_report,
+                   from_slice, from_slice_with_report, from_str, from_str_as, from_str_scalar,
+                   from_str_with_report};
 #[doc(inline)]
 pub use self::error::{Error, Result};
 #[doc(inline)]
-pub use self::ser::{Serializer, to_string, to_string_pretty, to_vec, to_vec_pretty, to_writer,
-                    to_writer_pretty};
+pub use self::ser::{Serializer, to_string, to_string_canonical, to_string_pretty,
+                    to_string_pretty_stable, to_string_with, to_vec, to_vec_pretty, to_vec_with,
+                    to_writer, to_writer_canonical, to_writer_pretty, to_writer_with};
+#[doc(inline)]
+pub use self::value::{Map, Set, Data, Number, DateTime, Value, from_value, to_value};
+#[doc(inline)]
+pub use self::tag::Tag;
+#[cfg(feature = "rayon")]
+#[doc(inline)]
+pub use self::parallel::to_vec_parallel;
+#[cfg(feature = "bytes")]
+#[doc(inline)]
+pub use self::buf::{from_bytes, to_bytes};
+#[cfg(feature = "mmap")]
 #[doc(inline)]
-pub use self::value::{Map, Set, Number, DateTime, Value, from_value, to_value};
+pub use self::mmap::{MappedDocument, from_file_mmap};
 
 #[macro_use]
 mod macros;
 
+pub mod cst;
 pub mod de;
+pub mod describe;
+pub mod display;
+pub mod encoding;
+pub mod envelope;
 pub mod error;
+pub mod event;
+pub mod ext;
+pub mod fs;
+#[cfg(feature = "testgen")]
+pub mod gen;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod json;
 pub mod map;
+pub mod pipeline;
+pub mod prelude;
+pub mod repair;
+pub mod schema;
 pub mod ser;
 pub mod set;
+pub mod tag;
+pub mod timestamp;
+pub mod transform;
 pub mod value;
 
+#[cfg(feature = "bytes")]
+mod buf;
+mod data;
+#[cfg(feature = "preserve_order_indexmap")]
+mod indexed_map;
 mod iter;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod number;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod read;
@@ -31,7 +31,8 @@
 /// `Serialize` trait, while any type interpolated into a object key must
 /// implement `Into<String>`. If the `Serialize` implementation of the
 /// interpolated type decides to fail, or if the interpolated type contains a
-/// map with non-string keys, the `tjson!` macro will panic.
+/// map whose first key can be written as a string but a later key can't, the
+/// `tjson!` macro will panic.
 ///
 /// ```rust
 /// # #[macro_use]
@@ -51,6 +52,19 @@
 /// # }
 /// ```
 ///
+/// Member names containing NUL are rejected, as `Map::try_insert` rejects
+/// them. A string literal which contains NUL fails to compile, while any
+/// other such name makes `tjson!` panic.
+///
+/// ```rust,compile_fail
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let value = tjson!({ "a\0b": 1 });
+/// # }
+/// ```
+///
 /// Trailing commas are allowed inside both arrays and objects.
 ///
 /// ```rust
@@ -140,15 +154,36 @@ macro_rules! tjson {
 
     // Insert the current entry followed by trailing comma.
     (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
-        $object.insert(($($key)+).into(), $value);
+        {
+            tjson!(@check_key $($key)+);
+            let key: ::std::string::String = ($($key)+).into();
+            let value = $crate::value::read_member(&key, $value);
+            $crate::map::insert_member(&mut $object, key, value);
+        }
         tjson!(@object $object () ($($rest)*) ($($rest)*));
     };
 
     // Insert the last entry without trailing comma.
     (@object $object:ident [$($key:tt)+] ($value:expr)) => {
-        $object.insert(($($key)+).into(), $value);
+        {
+            tjson!(@check_key $($key)+);
+            let key: ::std::string::String = ($($key)+).into();
+            let value = $crate::value::read_member(&key, $value);
+            $crate::map::insert_member(&mut $object, key, value);
+        }
+    };
+
+    // A literal key containing NUL fails to compile. Other keys are checked
+    // as they are inserted.
+    (@check_key $key:literal) => {
+        const _: () = assert!(
+            !$crate::map::literal_has_nul(stringify!($key)),
+            concat!("member name ", stringify!($key), " contains NUL")
+        );
     };
 
+    (@check_key $($key:tt)+) => {};
+
     // Next value is `null`.
     (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
         tjson!(@object $object [$($key)+] (tjson!(null)) $($rest)*);
@@ -265,3 +300,325 @@ macro_rules! tjson {
         $crate::to_value(&$other).unwrap()
     };
 }
+
+/// Construct a `tjson::Value` from a JSON literal whose interpolated
+/// expressions may fail, returning a `tjson::Result<Value>`.
+///
+/// Interpolated expressions are handled by their type:
+///
+/// - `Result<T, E>`: an `Ok` value is used as if it had been interpolated on
+///   its own, while an `Err` is returned from `try_tjson!` like `?` would,
+///   converted with `Error::custom_boxed`. `E` can be anything which converts
+///   into `Box<std::error::Error + Send + Sync>`, including `tjson::Error`,
+///   `String` and `io::Error`. `Result`s whose error doesn't convert are
+///   serialized as they would be by `tjson!`.
+/// - `Option<T>`: `Some` value is used as if it had been interpolated on its
+///   own. As the value of an object member, `None` leaves the member out
+///   altogether; anywhere else it is an error.
+/// - Anything else is serialized as `tjson!` would, except that
+///   serialization errors are returned rather than causing a panic.
+///
+/// A member name containing NUL is an error with
+/// `ErrorCode::InvalidMemberName`, unless it is a string literal, which fails
+/// to compile as it would in `tjson!`.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use std::collections::HashMap;
+///
+/// fn user(id: &str, emails: &HashMap<&str, &str>) -> tjson::Result<tjson::Value> {
+///     try_tjson!({
+///         "id": id.parse::<u64>().map_err(|err| format!("bad id {:?}: {}", id, err)),
+///         "email": emails.get(id),
+///     })
+/// }
+///
+/// let mut emails = HashMap::new();
+/// emails.insert("1", "ann@example.com");
+///
+/// assert_eq!(user("1", &emails).unwrap(), tjson!({ "id": 1, "email": "ann@example.com" }));
+/// assert_eq!(user("2", &emails).unwrap(), tjson!({ "id": 2 }));
+/// assert_eq!(
+///     user("x", &emails).unwrap_err().to_string(),
+///     "bad id \"x\": invalid digit found in string"
+/// );
+/// # }
+/// ```
+///
+/// Errors only propagate through `try_tjson!`, so nest `try_tjson!` inside
+/// `try_tjson!` rather than inside `tjson!`. `tjson!` would serialize the
+/// nested `Result` itself, as `{"Ok": ...}` or `{"Err": ...}`.
+#[macro_export]
+macro_rules! try_tjson {
+    //////////////////////////////////////////////////////////////////////////
+    // TT muncher for parsing the inside of an array [...]. Each element is
+    // pushed onto the given vec variable.
+    //
+    // Must be invoked as: try_tjson!(@array $array ($($tt)*))
+    //
+    // Like every rule below, this must be expanded inside the closure set up
+    // by the main implementation, which errors are returned from.
+    //////////////////////////////////////////////////////////////////////////
+
+    // Done.
+    (@array $array:ident ()) => {};
+
+    // Comma after the most recent element.
+    (@array $array:ident (, $($rest:tt)*)) => {
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is `null`.
+    (@array $array:ident (null $($rest:tt)*)) => {
+        $array.push(try_tjson!(@value null));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is `true`.
+    (@array $array:ident (true $($rest:tt)*)) => {
+        $array.push(try_tjson!(@value true));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is `false`.
+    (@array $array:ident (false $($rest:tt)*)) => {
+        $array.push(try_tjson!(@value false));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is an array.
+    (@array $array:ident ([$($inner:tt)*] $($rest:tt)*)) => {
+        $array.push(try_tjson!(@value [$($inner)*]));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is a map.
+    (@array $array:ident ({$($map:tt)*} $($rest:tt)*)) => {
+        $array.push(try_tjson!(@value {$($map)*}));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Next element is an expression followed by comma.
+    (@array $array:ident ($next:expr, $($rest:tt)*)) => {
+        $array.push(try_tjson!(@interpolate into_value $next));
+        try_tjson!(@array $array ($($rest)*));
+    };
+
+    // Last element is an expression with no trailing comma.
+    (@array $array:ident ($last:expr)) => {
+        $array.push(try_tjson!(@interpolate into_value $last));
+    };
+
+    //////////////////////////////////////////////////////////////////////////
+    // TT muncher for parsing the inside of an object {...}. Each entry is
+    // inserted into the given map variable, unless its value is `None`.
+    //
+    // Must be invoked as: try_tjson!(@object $map () ($($tt)*) ($($tt)*))
+    //
+    // We require two copies of the input tokens so that we can match on one
+    // copy and trigger errors on the other copy.
+    //////////////////////////////////////////////////////////////////////////
+
+    // Done.
+    (@object $object:ident () () ()) => {};
+
+    // Insert the current entry followed by trailing comma.
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        if let ::std::option::Option::Some(value) = $value {
+            tjson!(@check_key $($key)+);
+            let key: ::std::string::String = ($($key)+).into();
+            let value = $crate::value::read_member(&key, value);
+            if let ::std::result::Result::Err(err) =
+                $crate::map::try_insert_member(&mut $object, key, value)
+            {
+                return ::std::result::Result::Err(err);
+            }
+        }
+        try_tjson!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    // Insert the last entry without trailing comma.
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        if let ::std::option::Option::Some(value) = $value {
+            tjson!(@check_key $($key)+);
+            let key: ::std::string::String = ($($key)+).into();
+            let value = $crate::value::read_member(&key, value);
+            if let ::std::result::Result::Err(err) =
+                $crate::map::try_insert_member(&mut $object, key, value)
+            {
+                return ::std::result::Result::Err(err);
+            }
+        }
+    };
+
+    // Next value is `null`.
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+]
+            (::std::option::Option::Some(try_tjson!(@value null))) $($rest)*
+        );
+    };
+
+    // Next value is `true`.
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+]
+            (::std::option::Option::Some(try_tjson!(@value true))) $($rest)*
+        );
+    };
+
+    // Next value is `false`.
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+]
+            (::std::option::Option::Some(try_tjson!(@value false))) $($rest)*
+        );
+    };
+
+    // Next value is an array.
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+]
+            (::std::option::Option::Some(try_tjson!(@value [$($array)*]))) $($rest)*
+        );
+    };
+
+    // Next value is a map.
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+]
+            (::std::option::Option::Some(try_tjson!(@value {$($map)*}))) $($rest)*
+        );
+    };
+
+    // Next value is an expression followed by comma.
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        try_tjson!(
+            @object $object [$($key)+] (try_tjson!(@interpolate into_member $value)) , $($rest)*
+        );
+    };
+
+    // Last value is an expression with no trailing comma.
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        try_tjson!(@object $object [$($key)+] (try_tjson!(@interpolate into_member $value)));
+    };
+
+    // Missing value for last entry. Trigger a reasonable error message.
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        // "unexpected end of macro invocation"
+        try_tjson!();
+    };
+
+    // Missing colon and value for last entry. Trigger a reasonable error
+    // message.
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        // "unexpected end of macro invocation"
+        try_tjson!();
+    };
+
+    // Misplaced colon. Trigger a reasonable error message.
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        // Takes no arguments so "no rules expected the token `:`".
+        unimplemented!($colon);
+    };
+
+    // Found a comma inside a key. Trigger a reasonable error message.
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        // Takes no arguments so "no rules expected the token `,`".
+        unimplemented!($comma);
+    };
+
+    // Key is fully parenthesized. This avoids clippy double_parens false
+    // positives because the parenthesization may be necessary here.
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        try_tjson!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+
+    // Munch a token into the current key.
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        try_tjson!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    //////////////////////////////////////////////////////////////////////////
+    // Values, returning early with any error.
+    //////////////////////////////////////////////////////////////////////////
+
+    (@value []) => {
+        $crate::Value::Array(vec![])
+    };
+
+    (@value [ $($tt:tt)+ ]) => {
+        $crate::Value::Array({
+            let mut array = vec![];
+            try_tjson!(@array array ($($tt)+));
+            array
+        })
+    };
+
+    (@value {}) => {
+        $crate::Value::Object($crate::Map::new())
+    };
+
+    (@value { $($tt:tt)+ }) => {
+        $crate::Value::Object({
+            let mut object = $crate::Map::new();
+            try_tjson!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+
+    (@value $other:tt) => {
+        tjson!($other)
+    };
+
+    // Interpolate an expression with either `into_value` or `into_member`.
+    (@interpolate $method:ident $value:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::value::Interpolate;
+        match $crate::value::Interpolated($value).$method() {
+            ::std::result::Result::Ok(value) => value,
+            ::std::result::Result::Err(err) => return ::std::result::Result::Err(err),
+        }
+    }};
+
+    //////////////////////////////////////////////////////////////////////////
+    // The main implementation.
+    //
+    // Must be invoked as: try_tjson!($($json)+)
+    //////////////////////////////////////////////////////////////////////////
+
+    (null) => {
+        ::std::result::Result::Ok::<_, $crate::Error>(tjson!(null))
+    };
+
+    (true) => {
+        ::std::result::Result::Ok::<_, $crate::Error>(tjson!(true))
+    };
+
+    (false) => {
+        ::std::result::Result::Ok::<_, $crate::Error>(tjson!(false))
+    };
+
+    ([ $($tt:tt)* ]) => {
+        (|| -> $crate::Result<$crate::Value> {
+            ::std::result::Result::Ok(try_tjson!(@value [$($tt)*]))
+        })()
+    };
+
+    ({ $($tt:tt)* }) => {
+        (|| -> $crate::Result<$crate::Value> {
+            ::std::result::Result::Ok(try_tjson!(@value {$($tt)*}))
+        })()
+    };
+
+    // Any expression, handled as described above. Must be below every other
+    // rule.
+    ($other:expr) => {
+        (|| -> $crate::Result<$crate::Value> {
+            ::std::result::Result::Ok(try_tjson!(@interpolate into_value $other))
+        })()
+    };
+}
@@ -15,24 +15,34 @@
 //!
 //! By default the map is backed by a [`BTreeMap`]. To preserve insertion
 //! order, enable the `preserve_order` feature which will use the
-//! [`LinkedHashMap`] type instead.
+//! [`LinkedHashMap`] type instead, or the `preserve_order_indexmap` feature
+//! which will use [`IndexMap`]. The two behave the same, down to how they
+//! compare and hash, and only one may be enabled.
 //!
 //! [`BTreeMap`]: https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
 //! [`LinkedHashMap`]: https://docs.rs/linked-hash-map/*/linked_hash_map/struct.LinkedHashMap.html
+//! [`IndexMap`]: https://docs.rs/indexmap/2/indexmap/map/struct.IndexMap.html
 
 
+#[cfg(feature = "preserve_order_indexmap")]
+use indexmap;
+#[cfg(feature = "preserve_order_indexmap")]
+use indexed_map::IndexedMap;
 #[cfg(feature = "preserve_order")]
 use linked_hash_map::{self, LinkedHashMap};
 use serde::{ser, de};
 use std::borrow::Borrow;
+use std::error;
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 use std::collections::{BTreeMap, btree_map};
 use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::iter::FromIterator;
+use std::mem;
 use std::ops;
-use value::Value;
+use error::{Code, Error};
+use value::{Value, missing_member, read_member, typed_member};
 
 /// Represents a JSON key/value type.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -40,12 +50,15 @@ pub struct Map<K: Debug + Hash + Eq + PartialEq, V> {
     map: MapImpl<K, V>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type MapImpl<K, V> = BTreeMap<K, V>;
 
 #[cfg(feature = "preserve_order")]
 type MapImpl<K, V> = LinkedHashMap<K, V>;
 
+#[cfg(feature = "preserve_order_indexmap")]
+type MapImpl<K, V> = IndexedMap<K, V>;
+
 impl Map<String, Value> {
     /// Makes a new empty Map.
     #[inline]
@@ -53,7 +66,7 @@ impl Map<String, Value> {
         Map { map: MapImpl::new() }
     }
 
-    #[cfg(not(feature = "preserve_order"))]
+    #[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
     /// Makes a new empty Map with the given initial capacity.
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
@@ -62,11 +75,11 @@ impl Map<String, Value> {
         Map { map: BTreeMap::new() }
     }
 
-    #[cfg(feature = "preserve_order")]
+    #[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
     /// Makes a new empty Map with the given initial capacity.
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
-        Map { map: LinkedHashMap::with_capacity(capacity) }
+        Map { map: MapImpl::with_capacity(capacity) }
     }
 
     /// Clears the map, removing all values.
@@ -88,6 +101,33 @@ impl Map<String, Value> {
         self.map.get(key)
     }
 
+    /// Returns the key stored in the map along with its value, so that a
+    /// caller indexing the map can borrow the key rather than copy it.
+    ///
+    /// The key borrowed stays where it is while values are changed through
+    /// `get_mut`, `iter_mut` or `insert` of an existing key, but inserting
+    /// or removing members may move it, as may any reordering.
+    ///
+    /// With `preserve_order` this looks through the members in order, as
+    /// `LinkedHashMap` has no lookup which returns its key.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let value = tjson!({ "id:u": 7 });
+    /// let map = value.as_object().unwrap();
+    ///
+    /// let (key, id) = map.get_key_value("id:u").unwrap();
+    /// assert!(std::ptr::eq(key, map.keys().next().unwrap()));
+    /// assert_eq!(*id, 7);
+    /// # }
+    /// ```
+    pub fn get_key_value(&self, key: &str) -> Option<(&String, &Value)> {
+        get_key_value(&self.map, key)
+    }
+
     /// Returns true if the map contains a value for the specified key.
     ///
     /// The key may be any borrowed form of the map's key type, but the ordering
@@ -114,18 +154,125 @@ impl Map<String, Value> {
         self.map.get_mut(key)
     }
 
+    /// Deserializes the value of the member named `key`, borrowing from it
+    /// where `T` can.
+    ///
+    /// The name is matched as it is stored, tag included, so the member
+    /// read from `"count:u": "42"` is looked up as `"count:u"`. A member
+    /// holding `Value::Undefined` counts as missing.
+    ///
+    /// ```rust
+    /// # extern crate chrono;
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use chrono::datetime::DateTime;
+    /// use chrono::offset::utc::UTC;
+    ///
+    /// let value: tjson::Value =
+    ///     tjson::from_str(r#"{"count:u": "42", "created:t": "2017-03-04T05:06:07Z"}"#).unwrap();
+    /// let map = value.as_object().unwrap();
+    ///
+    /// assert_eq!(map.get_typed::<u64>("count:u").unwrap(), 42);
+    /// let created: DateTime<UTC> = map.get_typed("created:t").unwrap();
+    /// assert_eq!(created.to_string(), "2017-03-04 05:06:07 UTC");
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorCode::MissingMember` if there is no such member.
+    /// Fails with the error of deserializing the member otherwise, with the
+    /// same code, and a message which starts with the member's name, as in
+    /// `member "count:u": invalid type: ...`.
+    pub fn get_typed<'a, T>(&'a self, key: &str) -> Result<T, Error>
+    where
+        T: de::Deserialize<'a>,
+    {
+        typed_member(self.map.get(key), key)
+            .and_then(|found| found.ok_or_else(|| missing_member(key)))
+    }
+
+    /// Deserializes the value of the member named `key` as `get_typed`
+    /// does, or returns `default` if there is no such member.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is such a member but it isn't a `T`.
+    pub fn get_typed_or<'a, T>(&'a self, key: &str, default: T) -> Result<T, Error>
+    where
+        T: de::Deserialize<'a>,
+    {
+        typed_member(self.map.get(key), key).map(|found| found.unwrap_or(default))
+    }
+
+    /// Deserializes the value of the member named `key` as `get_typed`
+    /// does, or returns `None` if there is no such member.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is such a member but it isn't a `T`.
+    pub fn get_typed_opt<'a, T>(&'a self, key: &str) -> Result<Option<T>, Error>
+    where
+        T: de::Deserialize<'a>,
+    {
+        typed_member(self.map.get(key), key)
+    }
+
     /// Inserts a key-value pair into the map.
     ///
     /// If the map did not have this key present, `None` is returned.
     ///
     /// If the map did have this key present, the value is updated, and the old
     /// value is returned. The key is not updated, though; this matters for
-    /// types that can be `==` without being identical.
+    /// types that can be `==` without being identical. Nor is it moved: with
+    /// `preserve_order` it keeps its place in the map's order.
+    ///
+    /// The key is inserted as it is, even if it contains NUL or isn't a
+    /// member name some reader of the document would accept. Use
+    /// [`try_insert`] to check it against a [`KeyPolicy`] first.
+    ///
+    /// [`try_insert`]: #method.try_insert
+    /// [`KeyPolicy`]: enum.KeyPolicy.html
     #[inline]
     pub fn insert(&mut self, k: String, v: Value) -> Option<Value> {
+        // `LinkedHashMap::insert` moves an existing key to the end.
+        if let Some(slot) = self.map.get_mut(&k) {
+            return Some(mem::replace(slot, v));
+        }
         self.map.insert(k, v)
     }
 
+    /// Inserts a key-value pair into the map as `insert` does, if the key is
+    /// allowed by the given policy. Otherwise the map is left as it was.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::map::KeyPolicy;
+    ///
+    /// let mut map = tjson::Map::new();
+    /// assert!(map.try_insert("name:s".to_owned(), tjson!("x"), KeyPolicy::Identifier).is_ok());
+    ///
+    /// let err = map.try_insert("a\u{0}b".to_owned(), tjson!(1), KeyPolicy::AnyUnicode)
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "member name \"a\\0b\" contains NUL");
+    /// assert_eq!(map.len(), 1);
+    /// # }
+    /// ```
+    pub fn try_insert(
+        &mut self,
+        k: String,
+        v: Value,
+        policy: KeyPolicy,
+    ) -> Result<Option<Value>, InvalidKey> {
+        try!(policy.check(&k));
+        Ok(self.map.insert(k, v))
+    }
+
     /// Removes a key from the map, returning the value at the key if the key
     /// was previously in the map.
     ///
@@ -146,10 +293,12 @@ impl Map<String, Value> {
     where
         S: Into<String>,
     {
-        #[cfg(not(feature = "preserve_order"))]
+        #[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
         use std::collections::btree_map::Entry as EntryImpl;
         #[cfg(feature = "preserve_order")]
         use linked_hash_map::Entry as EntryImpl;
+        #[cfg(feature = "preserve_order_indexmap")]
+        use indexmap::map::Entry as EntryImpl;
 
         match self.map.entry(key.into()) {
             EntryImpl::Vacant(vacant) => Entry::Vacant(VacantEntry { vacant: vacant }),
@@ -157,6 +306,17 @@ impl Map<String, Value> {
         }
     }
 
+    /// Gets the given key's entry as `entry` does, if the key is allowed by
+    /// the given policy.
+    pub fn try_entry<S>(&mut self, key: S, policy: KeyPolicy) -> Result<Entry, InvalidKey>
+    where
+        S: Into<String>,
+    {
+        let key = key.into();
+        try!(policy.check(&key));
+        Ok(self.entry(key))
+    }
+
     /// Returns the number of elements in the map.
     #[inline]
     pub fn len(&self) -> usize {
@@ -181,7 +341,8 @@ impl Map<String, Value> {
         IterMut { iter: self.map.iter_mut() }
     }
 
-    /// Gets an iterator over the keys of the map.
+    /// Gets an iterator over the keys of the map, borrowed as they are
+    /// stored. See `get_key_value` for how long they stay in place.
     #[inline]
     pub fn keys(&self) -> Keys {
         Keys { iter: self.map.keys() }
@@ -192,6 +353,609 @@ impl Map<String, Value> {
     pub fn values(&self) -> Values {
         Values { iter: self.map.values() }
     }
+
+    /// Gets a mutable iterator over the values of the map.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut value = tjson!({ "a": 1, "b": 2 });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// for v in map.values_mut() {
+    ///     *v = (v.as_u64().unwrap() * 10).into();
+    /// }
+    /// assert_eq!(map["b"], tjson!(20));
+    /// # }
+    /// ```
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut {
+        ValuesMut { iter: self.map.iter_mut() }
+    }
+
+    /// Removes the entries for which `pred` returns true and returns them,
+    /// in the map's iteration order. The remaining entries keep their order.
+    ///
+    /// `pred` may modify the values it is given, whether or not it goes on
+    /// to remove them.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut value = tjson!({ "a": 1, "b": "x", "c": 2 });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// let drained = map.drain_filter(|_, value| value.is_number());
+    /// assert_eq!(drained, [("a".to_owned(), tjson!(1)), ("c".to_owned(), tjson!(2))]);
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), ["b"]);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `pred` panics, the panic is passed on after the map is left
+    /// holding every entry which had not been removed yet, including the one
+    /// `pred` was looking at. Entries removed before the panic are dropped.
+    pub fn drain_filter<F>(&mut self, pred: F) -> Vec<(String, Value)>
+    where
+        F: FnMut(&String, &mut Value) -> bool,
+    {
+        drain_filter(&mut self.map, pred)
+    }
+
+    /// Moves the member named `key` after all the others.
+    ///
+    /// Only a map which keeps its members in order can do this, so without
+    /// the `preserve_order` or `preserve_order_indexmap` feature this returns
+    /// `ReorderError::Sorted`, as do the other reordering methods. A missing
+    /// key is `ReorderError::MissingKey`. Either way the map is left as it
+    /// was.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut value = tjson!({ "a": 1, "b": 2, "c": 3 });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+    ///     map.move_to_end("a").unwrap();
+    ///     assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "c", "a"]);
+    /// } else {
+    ///     assert!(map.move_to_end("a").is_err());
+    /// }
+    /// # }
+    /// ```
+    pub fn move_to_end(&mut self, key: &str) -> Result<(), ReorderError> {
+        let map = try!(self.ordered());
+        let value = try!(map.remove(key).ok_or_else(|| ReorderError::missing(key)));
+        map.insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    /// Moves the member named `key` before all the others. Fails as
+    /// [`move_to_end`] does.
+    ///
+    /// [`move_to_end`]: #method.move_to_end
+    pub fn move_to_front(&mut self, key: &str) -> Result<(), ReorderError> {
+        let map = try!(self.ordered());
+        let value = try!(map.remove(key).ok_or_else(|| ReorderError::missing(key)));
+        let rest = mem::take(map);
+        map.insert(key.to_owned(), value);
+        map.extend(rest);
+        Ok(())
+    }
+
+    /// Inserts a member just before the member named `existing`, returning
+    /// the old value of `key` if there was one. That member is moved to the
+    /// new place, unless `key` is `existing` itself, whose value is then
+    /// replaced where it is.
+    ///
+    /// Fails as [`move_to_end`] does, with `MissingKey` naming `existing`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut value = tjson!({ "id:u": 7, "name:s": "x" });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+    ///     map.insert_before("name:s", "kind:s".to_owned(), tjson!("user")).unwrap();
+    ///     assert_eq!(map.keys().collect::<Vec<_>>(), ["id:u", "kind:s", "name:s"]);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`move_to_end`]: #method.move_to_end
+    pub fn insert_before(
+        &mut self,
+        existing: &str,
+        key: String,
+        value: Value,
+    ) -> Result<Option<Value>, ReorderError> {
+        let map = try!(self.ordered());
+        if !map.contains_key(existing) {
+            return Err(ReorderError::missing(existing));
+        }
+        if key == existing {
+            return Ok(map.get_mut(existing).map(|slot| mem::replace(slot, value)));
+        }
+
+        let old = map.remove(&key);
+        let entries = mem::take(map);
+        let mut value = Some(value);
+        for (k, v) in entries {
+            if k == existing {
+                if let Some(value) = value.take() {
+                    map.insert(key.clone(), value);
+                }
+            }
+            map.insert(k, v);
+        }
+        Ok(old)
+    }
+
+    /// Puts the members named in `keys` first, in that order, followed by
+    /// the rest in the order they were in. A key named twice is placed where
+    /// it is first named.
+    ///
+    /// Fails as [`move_to_end`] does, with `MissingKey` naming the first of
+    /// `keys` which isn't in the map.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut value = tjson!({ "a": 1, "b": 2, "c": 3, "d": 4 });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+    ///     map.reorder(&["c", "a"]).unwrap();
+    ///     assert_eq!(map.keys().collect::<Vec<_>>(), ["c", "a", "b", "d"]);
+    /// }
+    /// assert!(map.reorder(&["a", "e"]).is_err());
+    /// # }
+    /// ```
+    ///
+    /// [`move_to_end`]: #method.move_to_end
+    pub fn reorder(&mut self, keys: &[&str]) -> Result<(), ReorderError> {
+        let map = try!(self.ordered());
+        if let Some(key) = keys.iter().find(|key| !map.contains_key(**key)) {
+            return Err(ReorderError::missing(key));
+        }
+
+        let mut rest = mem::take(map);
+        for key in keys {
+            if let Some(value) = rest.remove(*key) {
+                map.insert((*key).to_owned(), value);
+            }
+        }
+        map.extend(rest);
+        Ok(())
+    }
+
+    /// Renames the member `old` to `new`, keeping its value and, with
+    /// `preserve_order`, its place in the map's order.
+    ///
+    /// Fails with `MapError::MissingKey` if there is no member `old`, and
+    /// with `MapError::KeyExists` if there is already a member `new`, in
+    /// which case the map is left as it was. Renaming a member to its own
+    /// name does nothing.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::map::MapError;
+    ///
+    /// let mut value = tjson!({ "name": "x", "id:u": 7 });
+    /// let map = value.as_object_mut().unwrap();
+    ///
+    /// map.replace_key("name", "name:s".to_owned()).unwrap();
+    /// assert_eq!(map["name:s"], "x");
+    ///
+    /// let err = map.replace_key("id:u", "name:s".to_owned()).unwrap_err();
+    /// assert_eq!(err, MapError::KeyExists("name:s".to_owned()));
+    /// # }
+    /// ```
+    pub fn replace_key(&mut self, old: &str, new: String) -> Result<(), MapError> {
+        if !self.map.contains_key(old) {
+            return Err(MapError::MissingKey(old.to_owned()));
+        }
+        if new == old {
+            return Ok(());
+        }
+        if self.map.contains_key(&new) {
+            return Err(MapError::KeyExists(new));
+        }
+
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            let entries = mem::take(&mut self.map);
+            let mut new = Some(new);
+            for (k, v) in entries {
+                let k = if k == old { new.take().unwrap_or(k) } else { k };
+                self.map.insert(k, v);
+            }
+        } else if let Some(value) = self.map.remove(old) {
+            self.map.insert(new, value);
+        }
+        Ok(())
+    }
+
+    /// Makes a map of the given members, failing if two have the same name.
+    ///
+    /// The error has `ErrorCode::DuplicateMember` and gives the index of the
+    /// second pair with the name. Without the `preserve_order` feature the
+    /// members are sorted by name, and the order of `pairs` is lost.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::Map;
+    ///
+    /// let map = Map::from_pairs(vec![("a".to_owned(), tjson!(1))]).unwrap();
+    /// assert_eq!(map["a"], 1);
+    ///
+    /// let pairs = vec![("a".to_owned(), tjson!(1)), ("a".to_owned(), tjson!(2))];
+    /// let err = Map::from_pairs(pairs).unwrap_err();
+    /// assert_eq!(err.to_string(), "duplicate key in pair 1");
+    /// # }
+    /// ```
+    pub fn from_pairs(pairs: Vec<(String, Value)>) -> Result<Self, Error> {
+        let mut map = Map::with_capacity(pairs.len());
+        for (index, (key, value)) in pairs.into_iter().enumerate() {
+            if map.contains_key(&key) {
+                return Err(Error::syntax(Code::DuplicatePairKey(index), 0, 0));
+            }
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+
+    /// The members of the map, in its order.
+    pub fn into_pairs(self) -> Vec<(String, Value)> {
+        self.into_iter().collect()
+    }
+
+    /// The backing map, if its order can be changed.
+    #[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+    fn ordered(&mut self) -> Result<&mut MapImpl<String, Value>, ReorderError> {
+        Ok(&mut self.map)
+    }
+
+    #[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
+    fn ordered(&mut self) -> Result<&mut MapImpl<String, Value>, ReorderError> {
+        Err(ReorderError::Sorted)
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+fn get_key_value<'a>(
+    map: &'a MapImpl<String, Value>,
+    key: &str,
+) -> Option<(&'a String, &'a Value)> {
+    map.get_key_value(key)
+}
+
+#[cfg(feature = "preserve_order")]
+fn get_key_value<'a>(
+    map: &'a MapImpl<String, Value>,
+    key: &str,
+) -> Option<(&'a String, &'a Value)> {
+    if !map.contains_key(key) {
+        return None;
+    }
+    map.iter().find(|&(k, _)| k == key)
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn drain_filter<K, V, F>(map: &mut MapImpl<K, V>, mut pred: F) -> Vec<(K, V)>
+where
+    K: Ord + Hash,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    // The entries are moved out and the ones which are kept inserted again
+    // in their original order, since neither backing can remove entries
+    // during iteration
+    let entries = mem::take(map).into_iter();
+    let mut undrained = Undrained {
+        map: map,
+        entries: entries,
+        current: None,
+    };
+    let mut drained = Vec::new();
+
+    for entry in &mut undrained.entries {
+        let remove = {
+            let &mut (ref key, ref mut value) = undrained.current.get_or_insert(entry);
+            pred(key, value)
+        };
+
+        let (key, value) = undrained.current.take().expect("entry being filtered");
+        if remove {
+            drained.push((key, value));
+        } else {
+            undrained.map.insert(key, value);
+        }
+    }
+
+    drained
+}
+
+/// Puts back whatever a `drain_filter` had not got to yet, even if its
+/// predicate panics.
+struct Undrained<'a, K: 'a + Ord + Hash, V: 'a> {
+    map: &'a mut MapImpl<K, V>,
+    entries: <MapImpl<K, V> as IntoIterator>::IntoIter,
+    current: Option<(K, V)>,
+}
+
+impl<'a, K: 'a + Ord + Hash, V: 'a> Drop for Undrained<'a, K, V> {
+    fn drop(&mut self) {
+        if let Some((key, value)) = self.current.take() {
+            self.map.insert(key, value);
+        }
+        for (key, value) in &mut self.entries {
+            self.map.insert(key, value);
+        }
+    }
+}
+
+/// Which member names `Map::try_insert`, `Map::try_entry` and
+/// `Deserializer::key_policy` accept.
+///
+/// Names containing NUL are rejected by every policy, since many systems a
+/// document is passed on to treat NUL as the end of a string. A `String`
+/// can't hold an unpaired surrogate, and the parser rejects `\u` escapes
+/// which would produce one, so there is nothing further to check for them.
+///
+/// ```rust
+/// use tjson::map::KeyPolicy;
+///
+/// assert!(KeyPolicy::AnyUnicode.check("line\nbreak").is_ok());
+/// assert!(KeyPolicy::NoControlChars.check("line\nbreak").is_err());
+/// assert!(KeyPolicy::Identifier.check("user_id:A<i>").is_ok());
+/// assert!(KeyPolicy::Identifier.check("user id:s").is_err());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KeyPolicy {
+    /// Any name without NUL. This is the default.
+    #[default]
+    AnyUnicode,
+
+    /// Any name without control characters (`Cc`), including DEL and the C1
+    /// controls as well as those JSON requires to be escaped.
+    NoControlChars,
+
+    /// A name made of ASCII letters, digits and underscores which doesn't
+    /// start with a digit, optionally followed by a tag made of ASCII
+    /// letters, digits, `<` and `>`, such as `user_id:A<i>`.
+    Identifier,
+}
+
+impl KeyPolicy {
+    /// Checks a member name against this policy.
+    pub fn check(&self, key: &str) -> Result<(), InvalidKey> {
+        let reason = if key.contains('\0') {
+            Some("contains NUL")
+        } else {
+            match *self {
+                KeyPolicy::AnyUnicode => None,
+                KeyPolicy::NoControlChars if key.chars().any(char::is_control) => {
+                    Some("contains a control character")
+                }
+                KeyPolicy::NoControlChars => None,
+                KeyPolicy::Identifier if !is_identifier(key) => Some("is not an identifier"),
+                KeyPolicy::Identifier => None,
+            }
+        };
+        match reason {
+            Some(reason) => {
+                Err(InvalidKey {
+                    key: key.to_owned(),
+                    policy: *self,
+                    reason: reason,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether a member name is an identifier with an optional tag, as
+/// `KeyPolicy::Identifier` requires.
+fn is_identifier(key: &str) -> bool {
+    let (name, tag) = match key.rfind(':') {
+        Some(colon) => (&key[..colon], Some(&key[colon + 1..])),
+        None => (key, None),
+    };
+    let name_ok = match name.as_bytes().split_first() {
+        Some((&first, rest)) => {
+            (first == b'_' || first.is_ascii_alphabetic()) &&
+                rest.iter().all(|&b| b == b'_' || b.is_ascii_alphanumeric())
+        }
+        None => false,
+    };
+    let tag_ok = match tag {
+        Some(tag) => {
+            !tag.is_empty() &&
+                tag.bytes().all(|b| b == b'<' || b == b'>' || b.is_ascii_alphanumeric())
+        }
+        None => true,
+    };
+    name_ok && tag_ok
+}
+
+/// A member name which a `KeyPolicy` doesn't allow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidKey {
+    key: String,
+    policy: KeyPolicy,
+    reason: &'static str,
+}
+
+impl InvalidKey {
+    /// The name which was rejected.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The policy which rejected it.
+    pub fn policy(&self) -> KeyPolicy {
+        self.policy
+    }
+}
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "member name {:?} {}", self.key, self.reason)
+    }
+}
+
+impl error::Error for InvalidKey {
+    fn description(&self) -> &str {
+        "invalid member name"
+    }
+}
+
+/// Why a member of a `Map` couldn't be renamed, from `Map::replace_key`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapError {
+    /// The map has no member with this name.
+    MissingKey(String),
+
+    /// The map already has a member with this name.
+    KeyExists(String),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MapError::MissingKey(ref key) => write!(f, "no member named {:?}", key),
+            MapError::KeyExists(ref key) => write!(f, "a member named {:?} already exists", key),
+        }
+    }
+}
+
+impl error::Error for MapError {
+    fn description(&self) -> &str {
+        "member can't be renamed"
+    }
+}
+
+/// Why a `Map` couldn't be reordered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReorderError {
+    /// The map is sorted by key, because neither the `preserve_order` nor
+    /// the `preserve_order_indexmap` feature is on, so its order can't be
+    /// changed.
+    Sorted,
+
+    /// The map has no member with this name.
+    MissingKey(String),
+}
+
+impl ReorderError {
+    fn missing(key: &str) -> Self {
+        ReorderError::MissingKey(key.to_owned())
+    }
+}
+
+impl fmt::Display for ReorderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReorderError::Sorted => {
+                f.write_str("map is sorted by key; enable `preserve_order` to reorder it")
+            }
+            ReorderError::MissingKey(ref key) => write!(f, "no member named {:?}", key),
+        }
+    }
+}
+
+impl error::Error for ReorderError {
+    fn description(&self) -> &str {
+        "map can't be reordered"
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// Used by `tjson!` to insert a member, panicking on a name containing NUL.
+#[doc(hidden)]
+pub fn insert_member(map: &mut Map<String, Value>, key: String, value: Value) {
+    if let Err(err) = map.try_insert(key, value, KeyPolicy::AnyUnicode) {
+        panic!("{}", err);
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// Used by `try_tjson!` to insert a member, failing on a name containing NUL.
+#[doc(hidden)]
+pub fn try_insert_member(
+    map: &mut Map<String, Value>,
+    key: String,
+    value: Value,
+) -> Result<(), Error> {
+    match map.try_insert(key, value, KeyPolicy::AnyUnicode) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::syntax(Code::InvalidMemberName(err), 0, 0)),
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// Used by `tjson!` to reject a literal member name containing NUL when the
+// crate using it is compiled. Takes the literal as it is written, from
+// `stringify!`, so looks for escapes which produce NUL.
+#[doc(hidden)]
+pub const fn literal_has_nul(literal: &str) -> bool {
+    let bytes = literal.as_bytes();
+    // Raw strings have no escapes
+    let escapes = bytes.is_empty() || bytes[0] != b'r';
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0 {
+            return true;
+        }
+        if escapes && bytes[i] == b'\\' && i + 1 < bytes.len() {
+            match bytes[i + 1] {
+                b'0' => return true,
+                b'x' => {
+                    if i + 3 < bytes.len() && bytes[i + 2] == b'0' && bytes[i + 3] == b'0' {
+                        return true;
+                    }
+                }
+                b'u' => {
+                    // \u{0}, \u{000_000} and so on
+                    let mut j = i + 3;
+                    while j < bytes.len() && (bytes[j] == b'0' || bytes[j] == b'_') {
+                        j += 1;
+                    }
+                    if j > i + 3 && j < bytes.len() && bytes[j] == b'}' {
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    false
 }
 
 impl Default for Map<String, Value> {
@@ -299,7 +1063,8 @@ impl<'de> de::Deserialize<'de> for Map<String, Value> {
             {
                 let mut values = Map::new();
 
-                while let Some((key, value)) = try!(visitor.next_entry()) {
+                while let Some((key, value)) = try!(visitor.next_entry::<String, Value>()) {
+                    let value = read_member(&key, value);
                     values.insert(key, value);
                 }
 
@@ -316,7 +1081,9 @@ impl FromIterator<(String, Value)> for Map<String, Value> {
     where
         T: IntoIterator<Item = (String, Value)>,
     {
-        Map { map: FromIterator::from_iter(iter) }
+        let mut map = Map::new();
+        map.extend(iter);
+        map
     }
 }
 
@@ -325,7 +1092,9 @@ impl Extend<(String, Value)> for Map<String, Value> {
     where
         T: IntoIterator<Item = (String, Value)>,
     {
-        self.map.extend(iter);
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
     }
 }
 
@@ -364,8 +1133,16 @@ macro_rules! delegate_iterator {
 /// A view into a single entry in a map, which may either be vacant or occupied.
 /// This enum is constructed from the [`entry`] method on [`Map`].
 ///
+/// With the `preserve_order` feature an entry is neither `Send` nor `Sync`,
+/// because the underlying `linked_hash_map` entries hold raw pointers into
+/// the map. An entry only lives as long as its borrow of the map, so this
+/// matters only when moving one into a scoped thread; use [`get_mut`] and
+/// [`insert`] there instead, which work the same in every configuration.
+///
 /// [`entry`]: struct.Map.html#method.entry
 /// [`Map`]: struct.Map.html
+/// [`get_mut`]: struct.Map.html#method.get_mut
+/// [`insert`]: struct.Map.html#method.insert
 pub enum Entry<'a> {
     /// A vacant Entry.
     Vacant(VacantEntry<'a>),
@@ -387,15 +1164,19 @@ pub struct OccupiedEntry<'a> {
     occupied: OccupiedEntryImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type VacantEntryImpl<'a> = btree_map::VacantEntry<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type VacantEntryImpl<'a> = linked_hash_map::VacantEntry<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type VacantEntryImpl<'a> = indexmap::map::VacantEntry<'a, String, Value>;
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type OccupiedEntryImpl<'a> = btree_map::OccupiedEntry<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type OccupiedEntryImpl<'a> = linked_hash_map::OccupiedEntry<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type OccupiedEntryImpl<'a> = indexmap::map::OccupiedEntry<'a, String, Value>;
 
 impl<'a> Entry<'a> {
     /// Returns a reference to this entry's key.
@@ -654,7 +1435,7 @@ impl<'a> OccupiedEntry<'a> {
     /// ```
     #[inline]
     pub fn insert(&mut self, value: Value) -> Value {
-        self.occupied.insert(value.into())
+        mem::replace(self.occupied.get_mut(), value)
     }
 
     /// Takes the value of the entry out of the map, and returns it.
@@ -681,10 +1462,21 @@ impl<'a> OccupiedEntry<'a> {
     /// ```
     #[inline]
     pub fn remove(self) -> Value {
-        self.occupied.remove()
+        remove_occupied(self.occupied)
     }
 }
 
+#[cfg(not(feature = "preserve_order_indexmap"))]
+fn remove_occupied(occupied: OccupiedEntryImpl) -> Value {
+    occupied.remove()
+}
+
+// `IndexMap`'s `remove` swaps the last entry into the gap
+#[cfg(feature = "preserve_order_indexmap")]
+fn remove_occupied(occupied: OccupiedEntryImpl) -> Value {
+    occupied.shift_remove()
+}
+
 //////////////////////////////////////////////////////////////////////////////
 
 impl<'a> IntoIterator for &'a Map<String, Value> {
@@ -701,10 +1493,12 @@ pub struct Iter<'a> {
     iter: IterImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type IterImpl<'a> = btree_map::Iter<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type IterImpl<'a> = linked_hash_map::Iter<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type IterImpl<'a> = indexmap::map::Iter<'a, String, Value>;
 
 delegate_iterator!((Iter<'a>) => (&'a String, &'a Value));
 
@@ -724,10 +1518,12 @@ pub struct IterMut<'a> {
     iter: IterMutImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type IterMutImpl<'a> = btree_map::IterMut<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type IterMutImpl<'a> = linked_hash_map::IterMut<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type IterMutImpl<'a> = indexmap::map::IterMut<'a, String, Value>;
 
 delegate_iterator!((IterMut<'a>) => (&'a String, &'a mut Value));
 
@@ -747,10 +1543,12 @@ pub struct IntoIter {
     iter: IntoIterImpl,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type IntoIterImpl = btree_map::IntoIter<String, Value>;
 #[cfg(feature = "preserve_order")]
 type IntoIterImpl = linked_hash_map::IntoIter<String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type IntoIterImpl = indexmap::map::IntoIter<String, Value>;
 
 delegate_iterator!((IntoIter) => (String, Value));
 
@@ -761,10 +1559,12 @@ pub struct Keys<'a> {
     iter: KeysImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type KeysImpl<'a> = btree_map::Keys<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type KeysImpl<'a> = linked_hash_map::Keys<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type KeysImpl<'a> = indexmap::map::Keys<'a, String, Value>;
 
 delegate_iterator!((Keys<'a>) => &'a String);
 
@@ -775,9 +1575,46 @@ pub struct Values<'a> {
     iter: ValuesImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type ValuesImpl<'a> = btree_map::Values<'a, String, Value>;
 #[cfg(feature = "preserve_order")]
 type ValuesImpl<'a> = linked_hash_map::Values<'a, String, Value>;
+#[cfg(feature = "preserve_order_indexmap")]
+type ValuesImpl<'a> = indexmap::map::Values<'a, String, Value>;
 
 delegate_iterator!((Values<'a>) => &'a Value);
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// A mutable iterator over a tjson::Map's values.
+pub struct ValuesMut<'a> {
+    iter: IterMutImpl<'a>,
+}
+
+// `LinkedHashMap` has no `values_mut`, so the values are taken from the
+// entries
+impl<'a> Iterator for ValuesMut<'a> {
+    type Item = &'a mut Value;
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for ValuesMut<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<'a> ExactSizeIterator for ValuesMut<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
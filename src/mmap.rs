@@ -0,0 +1,137 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing memory mapped files (requires the `mmap` feature)
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+use serde::de::{Deserialize, DeserializeOwned};
+
+use de::from_slice;
+use error::{Error, Result};
+use value::Value;
+
+/// Deserialize an instance of type `T` from the TJSON file at the given path,
+/// reading it through a memory map.
+///
+/// This is usually much faster than `tjson::from_reader` on a `File`, and
+/// strings are borrowed from the map while parsing wherever possible, but the
+/// result is owned so the map is released before this function returns. Use
+/// `MappedDocument` to borrow from the file instead.
+///
+/// The file must not be modified while it is being parsed; see
+/// `MappedDocument`.
+///
+/// # Errors
+///
+/// Any error, including failing to open or map the file, includes the path in
+/// its message and in `Error::path`. Otherwise this can fail for the same
+/// reasons as `tjson::from_slice`.
+pub fn from_file_mmap<T, P>(path: P) -> Result<T>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let document = try!(MappedDocument::open(path));
+    document.get()
+}
+
+/// A TJSON file mapped into memory, which values can borrow from.
+///
+/// Mapping a file is inherently unsafe, since nothing stops another process
+/// from truncating or rewriting it while it is mapped, which the parser would
+/// see as memory changing underneath it. `MappedDocument` accepts that risk on
+/// the caller's behalf, so it must only be used on files which are not
+/// modified while they are mapped.
+///
+/// ```rust,no_run
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// extern crate tjson;
+///
+/// #[derive(Deserialize)]
+/// struct Entry<'a> {
+///     name: &'a str,
+/// }
+///
+/// fn main() {
+///     let document = tjson::MappedDocument::open("entries.tjson").unwrap();
+///     let entries: Vec<Entry> = document.get().unwrap();
+///
+///     for entry in entries {
+///         println!("{}", entry.name);
+///     }
+/// }
+/// ```
+pub struct MappedDocument {
+    map: Option<Mmap>,
+    path: PathBuf,
+}
+
+impl MappedDocument {
+    /// Map the file at the given path into memory.
+    ///
+    /// The file must not be modified for as long as the `MappedDocument`
+    /// exists.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = try!(File::open(path).map_err(|err| Error::io(err).with_path(path)));
+        let len = try!(file.metadata().map_err(|err| Error::io(err).with_path(path))).len();
+
+        // Some platforms refuse to map empty files
+        let map = if len == 0 {
+            None
+        } else {
+            let map = unsafe { Mmap::map(&file) };
+            Some(try!(map.map_err(|err| Error::io(err).with_path(path))))
+        };
+
+        Ok(MappedDocument {
+            map: map,
+            path: path.to_owned(),
+        })
+    }
+
+    /// The path the document was mapped from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The contents of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self.map {
+            Some(ref map) => map,
+            None => &[],
+        }
+    }
+
+    /// Deserialize an instance of type `T` from the document, which may borrow
+    /// strings from the mapped file.
+    ///
+    /// # Errors
+    ///
+    /// Errors include the document's path, and can otherwise occur for the
+    /// same reasons as `tjson::from_slice`.
+    pub fn get<'a, T>(&'a self) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        from_slice(self.as_bytes()).map_err(|err| err.with_path(&self.path))
+    }
+
+    /// Parse the document into a `Value`.
+    pub fn value(&self) -> Result<Value> {
+        self.get()
+    }
+}
@@ -16,13 +16,59 @@
 use error::Error;
 use num_traits::NumCast;
 use ordered_float::OrderedFloat;
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
+#[cfg(feature = "decimal")]
+use rust_decimal::prelude::ToPrimitive;
 use serde::de::{self, Visitor, Unexpected};
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
 use std::i64;
 
 /// Represents a TJSON number, whether integer or floating point.
-#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+///
+/// Every finite float written by the serializer reads back as the same bits,
+/// subnormals and `-0.0` included.
+///
+/// `-0.0` is written with its sign, but compares, hashes and orders the same
+/// as `0.0`, so a `Set` holds at most one of the two. `-0` with no fraction
+/// or exponent reads as the integer `0`.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// assert_eq!(tjson::to_string(&-0.0).unwrap(), "-0.0");
+/// assert_eq!(tjson!(-0.0), tjson!(0.0));
+///
+/// let zero: f64 = tjson::from_str("-0.0").unwrap();
+/// assert!(zero.is_sign_negative());
+/// # }
+/// ```
+///
+/// Integers are stored as signed or unsigned. Converting from a Rust integer
+/// stores negative ones as signed and the rest as unsigned, while integers
+/// read as the value of a member tagged `i`, or in arrays of them, are
+/// stored as signed, so that they go on to serialize as `i64`s. Either way,
+/// integers with the same value compare, hash and order the same.
+///
+/// An integer is never equal to a float, though, even one with the same
+/// value, since they serialize differently. `eq_numeric` compares numbers
+/// by value regardless of kind.
+///
+/// ```rust
+/// # use tjson::Number;
+/// #
+/// let one = Number::from(1);
+/// let one_point_zero = Number::from_f64(1.0).unwrap();
+///
+/// assert_ne!(one, one_point_zero);
+/// assert!(one.eq_numeric(&one_point_zero));
+/// ```
+#[derive(Copy, Clone)]
 pub struct Number {
     n: N,
 }
@@ -37,8 +83,17 @@ enum N {
 
     /// Floating point (always finite).
     Float(OrderedFloat<f64>),
+
+    /// Exact decimal
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
 }
 
+/// Name of the newtype struct through which exact decimals pass their text
+/// to tjson's serializers and deserializers, which treat it specially.
+#[cfg(feature = "decimal")]
+pub const DECIMAL_TOKEN: &str = "$tjson::private::Decimal";
+
 impl Number {
     /// Returns true if the `Number` is an integer between `i64::MIN` and
     /// `i64::MAX`.
@@ -71,6 +126,8 @@ impl Number {
             N::Int(_) => true,
             N::UInt(v) => v <= i64::MAX as u64,
             N::Float(_) => false,
+            #[cfg(feature = "decimal")]
+            N::Decimal(_) => false,
         }
     }
 
@@ -99,7 +156,10 @@ impl Number {
     pub fn is_u64(&self) -> bool {
         match self.n {
             N::UInt(_) => true,
-            N::Int(_) | N::Float(_) => false,
+            N::Int(i) => i >= 0,
+            N::Float(_) => false,
+            #[cfg(feature = "decimal")]
+            N::Decimal(_) => false,
         }
     }
 
@@ -130,6 +190,8 @@ impl Number {
         match self.n {
             N::Float(_) => true,
             N::UInt(_) | N::Int(_) => false,
+            #[cfg(feature = "decimal")]
+            N::Decimal(_) => true,
         }
     }
 
@@ -157,6 +219,8 @@ impl Number {
             N::Int(n) => Some(n),
             N::UInt(n) => NumCast::from(n),
             N::Float(_) => None,
+            #[cfg(feature = "decimal")]
+            N::Decimal(_) => None,
         }
     }
 
@@ -181,6 +245,8 @@ impl Number {
             N::Int(n) => NumCast::from(n),
             N::UInt(n) => Some(n),
             N::Float(_) => None,
+            #[cfg(feature = "decimal")]
+            N::Decimal(_) => None,
         }
     }
 
@@ -204,6 +270,8 @@ impl Number {
             N::Int(n) => NumCast::from(n),
             N::UInt(n) => NumCast::from(n),
             N::Float(n) => Some(n.into()),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => d.to_f64(),
         }
     }
 
@@ -227,6 +295,101 @@ impl Number {
             None
         }
     }
+
+    /// Converts a floating point number which is a whole number to the
+    /// integer it equals, for data from producers which write integers as
+    /// `3.0`. Returns None if the number has a fractional part or is out of
+    /// the range of `i64` and `u64`. Integers are returned unchanged.
+    ///
+    /// Non-negative numbers, including `-0.0`, become unsigned integers, and
+    /// negative numbers signed ones, the same as `Number::from` would make
+    /// them.
+    ///
+    /// Digits a float couldn't hold were already lost when it was parsed,
+    /// so this can't tell `9007199254740993.0` from `9007199254740992.0`.
+    /// Parse with `Deserializer::integral_float_coercion` to reject the
+    /// former.
+    ///
+    /// ```rust
+    /// # use tjson::Number;
+    /// #
+    /// let n = Number::from_f64(-3.0).unwrap();
+    /// assert_eq!(n.to_integer_lossless(), Some(Number::from(-3)));
+    /// assert_eq!(n.to_integer_lossless().unwrap().as_i64(), Some(-3));
+    ///
+    /// assert_eq!(Number::from_f64(3.5).unwrap().to_integer_lossless(), None);
+    /// assert_eq!(Number::from_f64(1e20).unwrap().to_integer_lossless(), None);
+    /// ```
+    pub fn to_integer_lossless(&self) -> Option<Number> {
+        match self.n {
+            N::Int(_) | N::UInt(_) => Some(*self),
+            N::Float(f) => {
+                let f = f.into_inner();
+                if f.fract() != 0.0 {
+                    None
+                } else if (0.0..18446744073709551616.0).contains(&f) {
+                    Some(Number { n: N::UInt(f as u64) })
+                } else if (-9223372036854775808.0..0.0).contains(&f) {
+                    Some(Number { n: N::Int(f as i64) })
+                } else {
+                    None
+                }
+            }
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => {
+                if !d.fract().is_zero() {
+                    None
+                } else if d.is_sign_negative() && !d.is_zero() {
+                    d.to_i64().map(Number::from)
+                } else {
+                    d.to_u64().map(Number::from)
+                }
+            }
+        }
+    }
+}
+
+impl Number {
+    /// Whether the two numbers have the same mathematical value, whatever
+    /// kind of number each is, where `==` requires integers to equal integers
+    /// and floats floats.
+    ///
+    /// Integers compare exactly, and a float equals an integer only if it is
+    /// a whole number with exactly the integer's value, so `2^53 + 1` doesn't
+    /// equal the float `2^53` even though converting it to `f64` gives that.
+    /// `-0.0` equals `0`. With the `decimal` feature, exact decimals compare
+    /// exactly with each other and with integers, but with floats through
+    /// their nearest `f64`.
+    ///
+    /// ```rust
+    /// # use tjson::Number;
+    /// #
+    /// let float = |f| Number::from_f64(f).unwrap();
+    ///
+    /// assert!(Number::from(3).eq_numeric(&float(3.0)));
+    /// assert!(Number::from_i64_signed(3).eq_numeric(&Number::from(3u64)));
+    /// assert!(float(-0.0).eq_numeric(&Number::from(0)));
+    ///
+    /// assert!(!Number::from(3).eq_numeric(&float(3.5)));
+    /// assert!(!Number::from((1u64 << 53) + 1).eq_numeric(&float(9007199254740992.0)));
+    /// ```
+    pub fn eq_numeric(&self, other: &Number) -> bool {
+        match (self.n, other.n) {
+            (N::Float(a), N::Float(b)) => a == b,
+            #[cfg(feature = "decimal")]
+            (N::Decimal(a), N::Decimal(b)) => a == b,
+            #[cfg(feature = "decimal")]
+            (N::Decimal(_), N::Float(_)) | (N::Float(_), N::Decimal(_)) => {
+                self.as_f64() == other.as_f64()
+            }
+            _ => {
+                match (self.to_integer_lossless(), other.to_integer_lossless()) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => false,
+                }
+            }
+        }
+    }
 }
 
 impl fmt::Display for Number {
@@ -235,10 +398,49 @@ impl fmt::Display for Number {
             N::Int(i) => Display::fmt(&i, formatter),
             N::UInt(i) => Display::fmt(&i, formatter),
             N::Float(f) => Display::fmt(&f, formatter),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => Display::fmt(&d, formatter),
         }
     }
 }
 
+impl Number {
+    /// What the number compares, hashes and orders as: a signed integer
+    /// which isn't negative is the same as the unsigned one.
+    fn key(&self) -> N {
+        match self.n {
+            N::Int(i) if i >= 0 => N::UInt(i as u64),
+            n => n,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Number) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
 impl Debug for Number {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         Debug::fmt(&self.n, formatter)
@@ -255,6 +457,8 @@ impl Serialize for Number {
             N::Int(i) => serializer.serialize_i64(i),
             N::UInt(i) => serializer.serialize_u64(i),
             N::Float(f) => serializer.serialize_f64(f.into()),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => serializer.serialize_newtype_struct(DECIMAL_TOKEN, &decimal_text(d)),
         }
     }
 }
@@ -297,6 +501,74 @@ impl<'de> Deserialize<'de> for Number {
     }
 }
 
+/// Implements deserializing integer types from a `Number` by checked
+/// conversion, so that out of range values are reported along with the
+/// number. Floats are left to the visitor.
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident($ty:ident))*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                let converted: Option<$ty> = match self.n {
+                    N::Int(i) => NumCast::from(i),
+                    N::UInt(u) => NumCast::from(u),
+                    _ => return self.deserialize_any(visitor),
+                };
+                match converted {
+                    Some(n) => visitor.$visit(n),
+                    None => Err(de::Error::invalid_value(self.unexpected(), &stringify!($ty))),
+                }
+            }
+        )*
+    };
+}
+
+/// Implements deserializing float types from a `Number`, refusing integers
+/// which the float type can't represent exactly rather than rounding them.
+macro_rules! deserialize_float {
+    ($($method:ident => $visit:ident($ty:ident))*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                // Converting back saturates at the integer type's bounds, so
+                // floats at or above them have to be ruled out first
+                let converted = match self.n {
+                    N::Int(i) => {
+                        let f = i as $ty;
+                        if (f as f64) < 9223372036854775808.0 && f as i64 == i {
+                            Some(f)
+                        } else {
+                            None
+                        }
+                    }
+                    N::UInt(u) => {
+                        let f = u as $ty;
+                        if (f as f64) < 18446744073709551616.0 && f as u64 == u {
+                            Some(f)
+                        } else {
+                            None
+                        }
+                    }
+                    _ => return self.deserialize_any(visitor),
+                };
+                match converted {
+                    Some(f) => visitor.$visit(f),
+                    None => {
+                        let expected = concat!(stringify!($ty), " without loss of precision");
+                        Err(de::Error::invalid_value(self.unexpected(), &expected))
+                    }
+                }
+            }
+        )*
+    };
+}
+
 impl<'de> Deserializer<'de> for Number {
     type Error = Error;
 
@@ -309,12 +581,50 @@ impl<'de> Deserializer<'de> for Number {
             N::Int(i) => visitor.visit_i64(i),
             N::UInt(i) => visitor.visit_u64(i),
             N::Float(f) => visitor.visit_f64(f.into()),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => visitor.visit_f64(d.to_f64().unwrap_or(0.0)),
         }
     }
 
+    /// Exact decimals are only visited as their text when asked for by
+    /// `tjson::ext::decimal`. Everything else sees the nearest `f64`.
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        #[cfg(feature = "decimal")]
+        {
+            if let N::Decimal(d) = self.n {
+                if name == DECIMAL_TOKEN {
+                    return visitor.visit_str(&decimal_text(d));
+                }
+            }
+        }
+        #[cfg(not(feature = "decimal"))]
+        let _ = name;
+
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8(i8)
+        deserialize_i16 => visit_i16(i16)
+        deserialize_i32 => visit_i32(i32)
+        deserialize_i64 => visit_i64(i64)
+        deserialize_u8 => visit_u8(u8)
+        deserialize_u16 => visit_u16(u16)
+        deserialize_u32 => visit_u32(u32)
+        deserialize_u64 => visit_u64(u64)
+    }
+
+    deserialize_float! {
+        deserialize_f32 => visit_f32(f32)
+        deserialize_f64 => visit_f64(f64)
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple
+        bool char str string bytes byte_buf option unit unit_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
 }
@@ -331,12 +641,50 @@ impl<'de, 'a> Deserializer<'de> for &'a Number {
             N::Int(i) => visitor.visit_i64(i),
             N::UInt(i) => visitor.visit_u64(i),
             N::Float(f) => visitor.visit_f64(f.into()),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => visitor.visit_f64(d.to_f64().unwrap_or(0.0)),
+        }
+    }
+
+    /// Exact decimals are only visited as their text when asked for by
+    /// `tjson::ext::decimal`. Everything else sees the nearest `f64`.
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        #[cfg(feature = "decimal")]
+        {
+            if let N::Decimal(d) = self.n {
+                if name == DECIMAL_TOKEN {
+                    return visitor.visit_str(&decimal_text(d));
+                }
+            }
         }
+        #[cfg(not(feature = "decimal"))]
+        let _ = name;
+
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8(i8)
+        deserialize_i16 => visit_i16(i16)
+        deserialize_i32 => visit_i32(i32)
+        deserialize_i64 => visit_i64(i64)
+        deserialize_u8 => visit_u8(u8)
+        deserialize_u16 => visit_u16(u16)
+        deserialize_u32 => visit_u32(u32)
+        deserialize_u64 => visit_u64(u64)
+    }
+
+    deserialize_float! {
+        deserialize_f32 => visit_f32(f32)
+        deserialize_f64 => visit_f64(f64)
     }
 
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple
+        bool char str string bytes byte_buf option unit unit_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
 }
@@ -375,6 +723,73 @@ from_signed!(i8 i16 i32 i64 isize);
 from_unsigned!(u8 u16 u32 u64 usize);
 
 impl Number {
+    /// Stores an integer as signed even if it isn't negative, as reading a
+    /// member tagged `i` does. `From<i64>` stores it as unsigned unless it
+    /// is negative.
+    ///
+    /// ```rust
+    /// use tjson::Number;
+    ///
+    /// let signed = Number::from_i64_signed(5);
+    /// assert!(signed.is_signed());
+    /// assert!(!Number::from(5i64).is_signed());
+    /// assert_eq!(signed, Number::from(5u64));
+    /// ```
+    #[inline]
+    pub fn from_i64_signed(i: i64) -> Number {
+        Number { n: N::Int(i) }
+    }
+
+    /// Returns true if the `Number` is an integer stored as signed, which is
+    /// always the case for a negative integer.
+    ///
+    /// ```rust
+    /// use tjson::Value;
+    ///
+    /// fn is_signed(value: &Value) -> bool {
+    ///     match *value {
+    ///         Value::Number(ref n) => n.is_signed(),
+    ///         _ => false,
+    ///     }
+    /// }
+    ///
+    /// let v: Value = tjson::from_str(r#"{"a:i": "5", "b:u": "5", "c": -5}"#).unwrap();
+    /// assert!(is_signed(&v["a:i"]));
+    /// assert!(!is_signed(&v["b:u"]));
+    /// assert!(is_signed(&v["c"]));
+    /// assert_eq!(v["a:i"], v["b:u"]);
+    /// ```
+    #[inline]
+    pub fn is_signed(&self) -> bool {
+        match self.n {
+            N::Int(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Number {
+    // Not public API. Should be pub(crate).
+    //
+    // Reads an integer quoted as a string under a member tagged `i` or `u`
+    // from its digits rather than through a float, so that it is exact
+    // however large. `None` if the text isn't an integer, as `"4.0"` and
+    // `"+4"` aren't, and `Some(None)` if it is one outside the tag's range.
+    #[doc(hidden)]
+    pub fn from_quoted(tag: &str, text: &str) -> Option<Option<Number>> {
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        let integer = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) &&
+            (digits == "0" || !digits.starts_with('0'));
+        if !integer {
+            return None;
+        }
+        match tag {
+            "i" => Some(text.parse().ok().map(Number::from_i64_signed)),
+            "u" => Some(text.parse::<u64>().ok().map(Number::from)),
+            _ => None,
+        }
+    }
+
     // Not public API. Should be pub(crate).
     #[doc(hidden)]
     pub fn unexpected(&self) -> Unexpected {
@@ -382,6 +797,102 @@ impl Number {
             N::Int(i) => Unexpected::Signed(i),
             N::UInt(u) => Unexpected::Unsigned(u),
             N::Float(f) => Unexpected::Float(f.into()),
+            #[cfg(feature = "decimal")]
+            N::Decimal(d) => Unexpected::Float(d.to_f64().unwrap_or(0.0)),
         }
     }
 }
+
+#[cfg(feature = "decimal")]
+impl Number {
+    /// Converts an exact `Decimal` to a `Number` (requires the `decimal`
+    /// feature).
+    ///
+    /// The decimal is serialized as its exact text rather than as the nearest
+    /// `f64`, always including a decimal point.
+    ///
+    /// ```rust
+    /// # extern crate rust_decimal;
+    /// # extern crate tjson;
+    /// #
+    /// # use std::str::FromStr;
+    /// #
+    /// # use rust_decimal::Decimal;
+    /// # use tjson::Number;
+    /// #
+    /// # fn main() {
+    /// let d = Decimal::from_str("1234567890.123456789012345678").unwrap();
+    /// let n = Number::from_decimal(d);
+    ///
+    /// assert_eq!(tjson::to_string(&n).unwrap(), "1234567890.123456789012345678");
+    /// assert_eq!(tjson::to_string(&Number::from_decimal(Decimal::new(5, 0))).unwrap(), "5.0");
+    /// # }
+    /// ```
+    #[inline]
+    pub fn from_decimal(d: Decimal) -> Number {
+        Number { n: N::Decimal(d) }
+    }
+
+    /// Represents the number as an exact `Decimal` if possible (requires the
+    /// `decimal` feature). Returns None otherwise.
+    ///
+    /// Integers within the range of `Decimal` convert exactly. Floating point
+    /// numbers convert from their shortest decimal representation, which is
+    /// the same text they are serialized as.
+    ///
+    /// ```rust
+    /// # extern crate rust_decimal;
+    /// # extern crate tjson;
+    /// #
+    /// # use std::str::FromStr;
+    /// #
+    /// # use rust_decimal::Decimal;
+    /// # use tjson::Number;
+    /// #
+    /// # fn main() {
+    /// let a: Number = tjson::from_str("0.1").unwrap();
+    /// let b: Number = tjson::from_str("64").unwrap();
+    ///
+    /// assert_eq!(a.as_decimal(), Decimal::from_str("0.1").ok());
+    /// assert_eq!(b.as_decimal(), Some(Decimal::new(64, 0)));
+    /// # }
+    /// ```
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self.n {
+            N::Int(i) => Some(Decimal::from(i)),
+            N::UInt(u) => Some(Decimal::from(u)),
+            N::Float(f) => parse_decimal(&f.into_inner().to_string()).ok(),
+            N::Decimal(d) => Some(d),
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Decimal> for Number {
+    #[inline]
+    fn from(d: Decimal) -> Self {
+        Number::from_decimal(d)
+    }
+}
+
+/// The text a decimal is serialized as: exact, and always with a decimal
+/// point so that it reads back as a floating point number.
+#[cfg(feature = "decimal")]
+fn decimal_text(d: Decimal) -> String {
+    let mut text = d.to_string();
+    if !text.contains('.') {
+        text.push_str(".0");
+    }
+    text
+}
+
+// Not public API. Should be pub(crate).
+#[cfg(feature = "decimal")]
+#[doc(hidden)]
+pub fn parse_decimal(text: &str) -> Result<Decimal, ::rust_decimal::Error> {
+    if text.contains(&['e', 'E'][..]) {
+        Decimal::from_scientific(text)
+    } else {
+        Decimal::from_str_exact(text)
+    }
+}
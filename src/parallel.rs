@@ -0,0 +1,171 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parallel serialization of large documents (requires the `rayon` feature)
+
+use rayon::prelude::*;
+
+use error::Result;
+use ser::{tagged_member_name, to_vec, to_vec_in_member};
+use value::Value;
+
+/// Documents whose toplevel and second level containers hold fewer children
+/// than this in total are serialized sequentially, as the overhead of
+/// splitting them up outweighs any gains.
+const MIN_PARALLEL_CHILDREN: usize = 1024;
+
+/// How many levels of nested containers are split across the thread pool.
+const PARALLEL_LEVELS: usize = 2;
+
+/// Serialize the given `Value` as a TJSON byte vector, serializing the
+/// children of the toplevel (and second level) arrays, sets, and objects in
+/// parallel on the rayon thread pool.
+///
+/// The output is byte-for-byte identical to `tjson::to_vec`: children are
+/// serialized into their own buffers in the order the containers iterate them
+/// and concatenated afterward, so member ordering is decided before the work
+/// is split up, and each is written under the tag its member is given, as
+/// `to_vec` would. Small documents are serialized sequentially.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let value = tjson!({ "a": [1, 2, 3], "b": "foo" });
+///
+/// assert_eq!(tjson::to_vec_parallel(&value).unwrap(), tjson::to_vec(&value).unwrap());
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Serialization can only fail if writing to the in-memory buffer fails.
+pub fn to_vec_parallel(value: &Value) -> Result<Vec<u8>> {
+    if child_count(value, PARALLEL_LEVELS) < MIN_PARALLEL_CHILDREN {
+        return to_vec(value);
+    }
+
+    let mut writer = Vec::with_capacity(128);
+    try!(write_parallel(&mut writer, value, PARALLEL_LEVELS, None));
+    Ok(writer)
+}
+
+/// Count the children of containers down to the given depth
+fn child_count(value: &Value, levels: usize) -> usize {
+    if levels == 0 {
+        return 0;
+    }
+
+    match *value {
+        Value::Array(ref array) => {
+            array.len() + array.iter().map(|v| child_count(v, levels - 1)).sum::<usize>()
+        }
+        Value::Set(ref set) => {
+            set.len() + set.iter().map(|v| child_count(v, levels - 1)).sum::<usize>()
+        }
+        Value::Object(ref map) => {
+            map.len() + map.values().map(|v| child_count(v, levels - 1)).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+/// Serialize `value`, which is in the value of the member with the tagged
+/// name `member` if there is one, as `to_vec` would.
+fn write_parallel(
+    writer: &mut Vec<u8>,
+    value: &Value,
+    levels: usize,
+    member: Option<&str>,
+) -> Result<()> {
+    if levels == 0 {
+        writer.extend(try!(to_vec_under(value, member)));
+        return Ok(());
+    }
+
+    match *value {
+        Value::Array(ref array) => {
+            let elements: Vec<&Value> = array.iter().collect();
+            write_elements(writer, &elements, levels, member)
+        }
+        Value::Set(ref set) => {
+            let elements: Vec<&Value> = set.iter().collect();
+            write_elements(writer, &elements, levels, member)
+        }
+        Value::Object(ref map) => {
+            let members: Vec<(&String, &Value)> = map.iter().collect();
+            let buffers: Vec<Result<Vec<u8>>> = members
+                .par_iter()
+                .map(
+                    |&(key, value)| {
+                        let name = try!(tagged_member_name(key, value));
+                        let mut buffer = try!(to_vec(&name));
+                        buffer.push(b':');
+                        try!(write_parallel(&mut buffer, value, levels - 1, Some(&name)));
+                        Ok(buffer)
+                    },
+                )
+                .collect();
+
+            write_joined(writer, b'{', buffers, b'}')
+        }
+        _ => {
+            writer.extend(try!(to_vec_under(value, member)));
+            Ok(())
+        }
+    }
+}
+
+/// Serialize `value` as `to_vec` would, in the value of `member` if there
+/// is one, so that it is written as the member's tag says.
+fn to_vec_under(value: &Value, member: Option<&str>) -> Result<Vec<u8>> {
+    match member {
+        Some(name) => to_vec_in_member(name, value),
+        None => to_vec(value),
+    }
+}
+
+fn write_elements(
+    writer: &mut Vec<u8>,
+    elements: &[&Value],
+    levels: usize,
+    member: Option<&str>,
+) -> Result<()> {
+    let buffers: Vec<Result<Vec<u8>>> = elements
+        .par_iter()
+        .map(
+            |value| {
+                let mut buffer = Vec::new();
+                try!(write_parallel(&mut buffer, value, levels - 1, member));
+                Ok(buffer)
+            },
+        )
+        .collect();
+
+    write_joined(writer, b'[', buffers, b']')
+}
+
+fn write_joined(
+    writer: &mut Vec<u8>,
+    open: u8,
+    buffers: Vec<Result<Vec<u8>>>,
+    close: u8,
+) -> Result<()> {
+    writer.push(open);
+
+    for (i, buffer) in buffers.into_iter().enumerate() {
+        if i > 0 {
+            writer.push(b',');
+        }
+        writer.extend(try!(buffer));
+    }
+
+    writer.push(close);
+    Ok(())
+}
@@ -0,0 +1,462 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing, rewriting and writing many documents the same way
+//!
+//! A `Pipeline` reads each document with the same `Deserializer` options,
+//! runs the same transforms over the `Value`, and writes it with the same
+//! `SerializeConfig`. When a document fails, the error says which stage
+//! failed on which document:
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::{Error, Value};
+//! use tjson::de::DuplicateKeys;
+//! use tjson::pipeline::{ErrorPolicy, Pipeline, Stage};
+//! use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig};
+//!
+//! let pipeline = Pipeline::new()
+//!     .parse(|de| de.duplicate_keys(DuplicateKeys::LastWins))
+//!     .transform_paths("/users:A<O>/*/password:s", |value| {
+//!         *value = Value::from("<redacted>");
+//!         Ok(())
+//!     })
+//!     .transform(|value| {
+//!         match value.get("users:A<O>") {
+//!             Some(_) => Ok(()),
+//!             None => Err(Error::custom_boxed("no users".into())),
+//!         }
+//!     })
+//!     .serialize(SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName)))
+//!     .error_policy(ErrorPolicy::SkipAndCollect);
+//!
+//! let input = concat!(
+//!     r#"{"users:A<O>": [{"password:s": "hunter2", "name:s": "ann"}]}"#, "\n",
+//!     r#"{"groups:A<s>": []}"#, "\n",
+//! );
+//! let mut output = Vec::new();
+//! let report = pipeline.run_stream(input.as_bytes(), &mut output).unwrap();
+//!
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "{\"users:A<O>\":[{\"name:s\":\"ann\",\"password:s\":\"<redacted>\"}]}\n"
+//! );
+//! assert_eq!(report.documents(), 2);
+//! assert_eq!(report.failures()[0].document(), 1);
+//! assert_eq!(report.failures()[0].stage(), Stage::Transform(1));
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use serde::Deserialize;
+
+use de::{Deserializer, SliceRead};
+use error::Error;
+use ser::{SerializeConfig, to_writer_with};
+use value::{Value, parse_index, unescape_pointer_token};
+
+type Configure = Box<for<'b> Fn(Deserializer<SliceRead<'b>>) -> Deserializer<SliceRead<'b>>
+                         + Send
+                         + Sync>;
+type Transform = Box<Fn(&mut Value) -> Result<(), Error> + Send + Sync>;
+
+/// What a `Pipeline` reading a stream does when a document fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop at the first document which fails, and return its error (the
+    /// default).
+    FailFast,
+
+    /// Write nothing for a document which fails, carry on with the next,
+    /// and return the errors of every one which failed.
+    SkipAndCollect,
+}
+
+/// The stage of a `Pipeline` at which a document failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the stream failed.
+    Read,
+
+    /// The document couldn't be parsed.
+    Parse,
+
+    /// The transform at this index failed, counting those added with
+    /// `transform` and with `transform_paths` together, from `0`.
+    Transform(usize),
+
+    /// The value couldn't be written with the pipeline's `SerializeConfig`.
+    Serialize,
+
+    /// Writing to the output failed.
+    Write,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stage::Read => f.write_str("read"),
+            Stage::Parse => f.write_str("parse"),
+            Stage::Transform(index) => write!(f, "transform {}", index),
+            Stage::Serialize => f.write_str("serialize"),
+            Stage::Write => f.write_str("write"),
+        }
+    }
+}
+
+/// An error from a `Pipeline`, along with where it happened.
+#[derive(Clone, Debug)]
+pub struct PipelineError {
+    document: usize,
+    stage: Stage,
+    error: Error,
+}
+
+impl PipelineError {
+    fn new(document: usize, stage: Stage, error: Error) -> Self {
+        PipelineError {
+            document: document,
+            stage: stage,
+            error: error,
+        }
+    }
+
+    /// The index of the document which failed, counting from `0` and
+    /// skipping blank lines. Always `0` for `run_str` and `run_slice`.
+    pub fn document(&self) -> usize {
+        self.document
+    }
+
+    /// The stage which failed.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// The error the stage failed with.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the error the stage failed with.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "document {}: {} failed: {}", self.document, self.stage, self.error)
+    }
+}
+
+impl error::Error for PipelineError {
+    fn description(&self) -> &str {
+        "pipeline stage failed"
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        Some(&self.error)
+    }
+}
+
+/// What `Pipeline::run_stream` did.
+#[derive(Clone, Debug, Default)]
+pub struct StreamReport {
+    documents: usize,
+    written: usize,
+    failures: Vec<PipelineError>,
+}
+
+impl StreamReport {
+    /// The number of documents read, not counting blank lines.
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// The number of documents written.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// The documents which failed and were skipped, in order. Always empty
+    /// with `ErrorPolicy::FailFast`.
+    pub fn failures(&self) -> &[PipelineError] {
+        &self.failures
+    }
+}
+
+/// Parses, transforms and writes documents, as the `pipeline` module
+/// describes.
+pub struct Pipeline<'a> {
+    configure: Option<Configure>,
+    transforms: Vec<Transform>,
+    config: SerializeConfig<'a>,
+    policy: ErrorPolicy,
+}
+
+impl<'a> Pipeline<'a> {
+    /// A pipeline which parses documents as `tjson::from_slice` does and
+    /// writes them as `tjson::to_writer` does, failing fast.
+    pub fn new() -> Self {
+        Pipeline {
+            configure: None,
+            transforms: Vec::new(),
+            config: SerializeConfig::new(),
+            policy: ErrorPolicy::FailFast,
+        }
+    }
+
+    /// Set the options documents are parsed with. `configure` is given the
+    /// `Deserializer` for each document and returns it with its options
+    /// set, as in `|de| de.strict_tags(true)`.
+    pub fn parse<F>(mut self, configure: F) -> Self
+    where
+        F: for<'b> Fn(Deserializer<SliceRead<'b>>) -> Deserializer<SliceRead<'b>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+
+    /// Add a transform, run over each parsed document after the transforms
+    /// added before it.
+    pub fn transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Add a transform which runs over each value at a JSON pointer
+    /// matching `pattern`, in document order.
+    ///
+    /// As in `transform::Matcher::Path`, member names are written with
+    /// their tags and escaped as in any pointer, and a `*` token matches
+    /// any one member or element, so `/users:A<O>/*/password:s` matches the
+    /// `password:s` member of every user. Elements of sets aren't matched.
+    pub fn transform_paths<F>(self, pattern: &str, transform: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        let tokens: Vec<Option<String>> = pattern
+            .split('/')
+            .skip(1)
+            .map(
+                |token| match token {
+                    "*" => None,
+                    _ => {
+                        let unescaped = unescape_pointer_token(token);
+                        Some(unescaped.map_or_else(|| token.to_owned(), |t| t.into_owned()))
+                    }
+                },
+            )
+            .collect();
+        self.transform(move |value| visit_matching(value, &tokens, &transform))
+    }
+
+    /// Set how documents are written, by default as `tjson::to_writer`
+    /// writes them.
+    pub fn serialize(mut self, config: SerializeConfig<'a>) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set what `run_stream` does when a document fails, by default
+    /// `ErrorPolicy::FailFast`.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Runs a single document through the pipeline.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the first stage which fails.
+    pub fn run_str(&self, input: &str) -> Result<String, PipelineError> {
+        let output = try!(self.run_slice(input.as_bytes()));
+        // The serializer only writes UTF-8.
+        Ok(String::from_utf8(output).expect("serializer wrote invalid UTF-8"))
+    }
+
+    /// Runs a single document through the pipeline.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the first stage which fails.
+    pub fn run_slice(&self, input: &[u8]) -> Result<Vec<u8>, PipelineError> {
+        let mut output = Vec::with_capacity(input.len());
+        try!(self.run_document(0, input, &mut output));
+        Ok(output)
+    }
+
+    /// Runs each line of `reader` through the pipeline as a document, and
+    /// writes each output to `writer` followed by a newline. Blank lines
+    /// are skipped.
+    ///
+    /// One line and one output are held in memory at a time, in buffers
+    /// which are reused, so memory use is bounded by the longest document
+    /// rather than the length of the stream. A document which fails writes
+    /// nothing.
+    ///
+    /// # Errors
+    ///
+    /// With `ErrorPolicy::FailFast`, fails with the first document which
+    /// fails, once the documents before it have been written. Whatever the
+    /// policy, fails if reading from `reader` or writing to `writer` fails.
+    pub fn run_stream<R, W>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<StreamReport, PipelineError>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        let mut report = StreamReport::default();
+        let mut line = Vec::new();
+        let mut output = Vec::new();
+
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line);
+            let document = report.documents;
+            if try!(read.map_err(|err| io_error(document, Stage::Read, err))) == 0 {
+                break;
+            }
+            if line.iter().all(|b| b" \t\r\n".contains(b)) {
+                continue;
+            }
+            report.documents += 1;
+
+            output.clear();
+            match self.run_document(document, &line, &mut output) {
+                Ok(()) => {
+                    output.push(b'\n');
+                    let written = writer.write_all(&output);
+                    try!(written.map_err(|err| io_error(document, Stage::Write, err)));
+                    report.written += 1;
+                }
+                Err(err) => {
+                    match self.policy {
+                        ErrorPolicy::FailFast => return Err(err),
+                        ErrorPolicy::SkipAndCollect => report.failures.push(err),
+                    }
+                }
+            }
+        }
+
+        try!(writer.flush().map_err(|err| io_error(report.documents, Stage::Write, err)));
+        Ok(report)
+    }
+
+    fn run_document(
+        &self,
+        document: usize,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), PipelineError> {
+        let fail = |stage| move |err| PipelineError::new(document, stage, err);
+
+        let mut value = try!(self.parse_document(input).map_err(fail(Stage::Parse)));
+        for (index, transform) in self.transforms.iter().enumerate() {
+            try!(transform(&mut value).map_err(fail(Stage::Transform(index))));
+        }
+        try!(to_writer_with(output, &self.config, &value).map_err(fail(Stage::Serialize)));
+        Ok(())
+    }
+
+    fn parse_document(&self, input: &[u8]) -> Result<Value, Error> {
+        let mut de = Deserializer::from_slice(input);
+        if let Some(ref configure) = self.configure {
+            de = configure(de);
+        }
+        let value = try!(Value::deserialize(&mut de));
+        try!(de.end());
+        Ok(value)
+    }
+}
+
+impl<'a> Default for Pipeline<'a> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl<'a> fmt::Debug for Pipeline<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("parse", &self.configure.is_some())
+            .field("transforms", &self.transforms.len())
+            .field("config", &self.config)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+fn io_error(document: usize, stage: Stage, err: io::Error) -> PipelineError {
+    PipelineError::new(document, stage, Error::io(err))
+}
+
+/// Runs `transform` over each value under `value` at the pointer `tokens`
+/// lead to, where `None` matches any member or element.
+fn visit_matching<F>(
+    value: &mut Value,
+    tokens: &[Option<String>],
+    transform: &F,
+) -> Result<(), Error>
+where
+    F: Fn(&mut Value) -> Result<(), Error>,
+{
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return transform(value),
+    };
+
+    match *value {
+        Value::Object(ref mut map) => {
+            match *token {
+                Some(ref name) => {
+                    if let Some(member) = map.get_mut(name.as_str()) {
+                        try!(visit_matching(member, rest, transform));
+                    }
+                }
+                None => {
+                    for (_, member) in map.iter_mut() {
+                        try!(visit_matching(member, rest, transform));
+                    }
+                }
+            }
+        }
+        Value::Array(ref mut elements) => {
+            match *token {
+                Some(ref index) => {
+                    if let Some(element) = parse_index(index).and_then(|i| elements.get_mut(i)) {
+                        try!(visit_matching(element, rest, transform));
+                    }
+                }
+                None => {
+                    for element in elements {
+                        try!(visit_matching(element, rest, transform));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The most commonly used items, for glob importing
+//!
+//! The contents of this module only grow: an item is never removed or
+//! renamed without a major version bump, so `use tjson::prelude::*;` keeps
+//! compiling across minor releases.
+//!
+//! ```rust
+//! extern crate tjson;
+//!
+//! use tjson::prelude::*;
+//!
+//! # fn main() {
+//! let value: Value = from_str(r#"{"name:s": "Alice"}"#).unwrap();
+//! assert_eq!(value["name:s"], "Alice");
+//! assert_eq!(to_string(&value).unwrap(), r#"{"name:s":"Alice"}"#);
+//!
+//! let value = tjson!({ "a": [1, 2] });
+//! assert!(value.is_object());
+//! # }
+//! ```
+//!
+//! All of the types re-exported here are `Send` and `Sync`.
+
+#[doc(no_inline)]
+pub use {Error, Map, Number, Set, Value, from_str, to_string};
+#[doc(no_inline)]
+pub use tjson;
@@ -11,7 +11,7 @@ use std::ops::Deref;
 
 use iter::LineColIterator;
 
-use super::error::{Error, ErrorCode, Result};
+use super::error::{Code, Error, Result};
 
 /// Trait used by the deserializer for iterating over input. This is manually
 /// "specialized" for iterating over &[u8]. Once feature(specialization) is
@@ -54,6 +54,27 @@ pub trait Read<'de>: private::Sealed {
     #[doc(hidden)]
     fn byte_offset(&self) -> usize;
 
+    /// Number of backslash escape sequences in the strings parsed so far. A
+    /// surrogate pair counts as one.
+    #[doc(hidden)]
+    fn escapes(&self) -> usize;
+
+    /// The input from the given byte offset on, if all of it is at hand, as
+    /// it is for slices but not readers.
+    #[doc(hidden)]
+    fn input_from(&self, offset: usize) -> Option<&'de [u8]>;
+
+    /// Cheaply records the position of the most recent call to peek(), so
+    /// that an error can refer back to it later.
+    #[doc(hidden)]
+    fn peek_mark(&self) -> Mark;
+
+    /// Line and column of a mark returned by peek_mark().
+    ///
+    /// Only called in case of an error, so performance is not important.
+    #[doc(hidden)]
+    fn mark_position(&self, mark: Mark) -> Position;
+
     /// Assumes the previous byte was a quotation mark. Parses a JSON-escaped
     /// string until the next quotation mark using the given scratch space if
     /// necessary. The scratch space is initially empty.
@@ -78,6 +99,16 @@ pub struct Position {
     pub column: usize,
 }
 
+/// A position in the input recorded in whichever form is cheapest for the
+/// input source to produce.
+#[derive(Copy, Clone, Debug)]
+pub enum Mark {
+    /// Line and column, for sources which track them as they go.
+    LineCol(usize, usize),
+    /// Index into a slice, resolved by scanning for newlines.
+    Index(usize),
+}
+
 pub enum Reference<'b, 'c, T: ?Sized + 'static> {
     Borrowed(&'b T),
     Copied(&'c T),
@@ -102,6 +133,7 @@ where
     iter: LineColIterator<io::Bytes<R>>,
     /// Temporary storage of peeked byte.
     ch: Option<u8>,
+    escapes: usize,
 }
 
 /// JSON input source that reads from a slice of bytes.
@@ -112,6 +144,7 @@ pub struct SliceRead<'a> {
     slice: &'a [u8],
     /// Index of the *next* byte that will be returned by next() or peek().
     index: usize,
+    escapes: usize,
 }
 
 /// JSON input source that reads from a UTF-8 string.
@@ -137,6 +170,7 @@ where
         IoRead {
             iter: LineColIterator::new(reader.bytes()),
             ch: None,
+            escapes: 0,
         }
     }
 }
@@ -172,11 +206,12 @@ where
                     return result(self, scratch);
                 }
                 b'\\' => {
+                    self.escapes += 1;
                     try!(parse_escape(self, scratch));
                 }
                 _ => {
                     if validate {
-                        return error(self, ErrorCode::InvalidUnicodeCodePoint);
+                        return error(self, Code::ControlCharacterWhileParsingString);
                     }
                     scratch.push(ch);
                 }
@@ -245,6 +280,25 @@ where
         }
     }
 
+    fn escapes(&self) -> usize {
+        self.escapes
+    }
+
+    fn input_from(&self, _offset: usize) -> Option<&'de [u8]> {
+        None
+    }
+
+    fn peek_mark(&self) -> Mark {
+        Mark::LineCol(self.iter.line(), self.iter.col())
+    }
+
+    fn mark_position(&self, mark: Mark) -> Position {
+        match mark {
+            Mark::LineCol(line, column) => Position { line: line, column: column },
+            Mark::Index(_) => self.peek_position(),
+        }
+    }
+
     fn parse_str<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Reference<'de, 's, str>> {
         self.parse_str_bytes(scratch, true, as_str)
             .map(Reference::Copied)
@@ -267,6 +321,7 @@ impl<'a> SliceRead<'a> {
         SliceRead {
             slice: slice,
             index: 0,
+            escapes: 0,
         }
     }
 
@@ -307,7 +362,7 @@ impl<'a> SliceRead<'a> {
                 self.index += 1;
             }
             if self.index == self.slice.len() {
-                return error(self, ErrorCode::EofWhileParsingString);
+                return error(self, Code::EofWhileParsingString);
             }
             match self.slice[self.index] {
                 b'"' => {
@@ -328,14 +383,15 @@ impl<'a> SliceRead<'a> {
                 b'\\' => {
                     scratch.extend_from_slice(&self.slice[start..self.index]);
                     self.index += 1;
+                    self.escapes += 1;
                     try!(parse_escape(self, scratch));
                     start = self.index;
                 }
                 _ => {
+                    self.index += 1;
                     if validate {
-                        return error(self, ErrorCode::InvalidUnicodeCodePoint);
+                        return error(self, Code::ControlCharacterWhileParsingString);
                     }
-                    self.index += 1;
                 }
             }
         }
@@ -392,6 +448,25 @@ impl<'a> Read<'a> for SliceRead<'a> {
         self.index
     }
 
+    fn escapes(&self) -> usize {
+        self.escapes
+    }
+
+    fn input_from(&self, offset: usize) -> Option<&'a [u8]> {
+        self.slice.get(offset..)
+    }
+
+    fn peek_mark(&self) -> Mark {
+        Mark::Index(self.index + 1)
+    }
+
+    fn mark_position(&self, mark: Mark) -> Position {
+        match mark {
+            Mark::Index(i) => self.position_of_index(cmp::min(self.slice.len(), i)),
+            Mark::LineCol(line, column) => Position { line: line, column: column },
+        }
+    }
+
     fn parse_str<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Reference<'a, 's, str>> {
         self.parse_str_bytes(scratch, true, as_str)
     }
@@ -443,6 +518,22 @@ impl<'a> Read<'a> for StrRead<'a> {
         self.delegate.byte_offset()
     }
 
+    fn escapes(&self) -> usize {
+        self.delegate.escapes()
+    }
+
+    fn input_from(&self, offset: usize) -> Option<&'a [u8]> {
+        self.delegate.input_from(offset)
+    }
+
+    fn peek_mark(&self) -> Mark {
+        self.delegate.peek_mark()
+    }
+
+    fn mark_position(&self, mark: Mark) -> Position {
+        self.delegate.mark_position(mark)
+    }
+
     fn parse_str<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Reference<'a, 's, str>> {
         self.delegate
             .parse_str_bytes(
@@ -495,17 +586,18 @@ static ESCAPE: [bool; 256] = [
 fn next_or_eof<'de, R: Read<'de>>(read: &mut R) -> Result<u8> {
     match try!(read.next().map_err(Error::io)) {
         Some(b) => Ok(b),
-        None => error(read, ErrorCode::EofWhileParsingString),
+        None => error(read, Code::EofWhileParsingString),
     }
 }
 
-fn error<'de, R: Read<'de>, T>(read: &R, reason: ErrorCode) -> Result<T> {
+fn error<'de, R: Read<'de>, T>(read: &R, reason: Code) -> Result<T> {
     let pos = read.position();
-    Err(Error::syntax(reason, pos.line, pos.column))
+    let offset = read.byte_offset().saturating_sub(1);
+    Err(Error::syntax(reason, pos.line, pos.column).with_byte_offset(offset))
 }
 
 fn as_str<'de, 's, R: Read<'de>>(read: &R, slice: &'s [u8]) -> Result<&'s str> {
-    str::from_utf8(slice).or_else(|_| error(read, ErrorCode::InvalidUnicodeCodePoint))
+    str::from_utf8(slice).or_else(|_| error(read, Code::InvalidUnicodeCodePoint))
 }
 
 /// Parses a JSON escape sequence and appends it into the scratch space. Assumes
@@ -525,23 +617,23 @@ fn parse_escape<'de, R: Read<'de>>(read: &mut R, scratch: &mut Vec<u8>) -> Resul
         b'u' => {
             let c = match try!(decode_hex_escape(read)) {
                 0xDC00...0xDFFF => {
-                    return error(read, ErrorCode::LoneLeadingSurrogateInHexEscape);
+                    return error(read, Code::LoneLeadingSurrogateInHexEscape);
                 }
 
                 // Non-BMP characters are encoded as a sequence of
                 // two hex escapes, representing UTF-16 surrogates.
                 n1 @ 0xD800...0xDBFF => {
                     if try!(next_or_eof(read)) != b'\\' {
-                        return error(read, ErrorCode::UnexpectedEndOfHexEscape);
+                        return error(read, Code::UnexpectedEndOfHexEscape);
                     }
                     if try!(next_or_eof(read)) != b'u' {
-                        return error(read, ErrorCode::UnexpectedEndOfHexEscape);
+                        return error(read, Code::UnexpectedEndOfHexEscape);
                     }
 
                     let n2 = try!(decode_hex_escape(read));
 
                     if n2 < 0xDC00 || n2 > 0xDFFF {
-                        return error(read, ErrorCode::LoneLeadingSurrogateInHexEscape);
+                        return error(read, Code::LoneLeadingSurrogateInHexEscape);
                     }
 
                     let n = (((n1 - 0xD800) as u32) << 10 | (n2 - 0xDC00) as u32) + 0x1_0000;
@@ -549,7 +641,7 @@ fn parse_escape<'de, R: Read<'de>>(read: &mut R, scratch: &mut Vec<u8>) -> Resul
                     match char::from_u32(n as u32) {
                         Some(c) => c,
                         None => {
-                            return error(read, ErrorCode::InvalidUnicodeCodePoint);
+                            return error(read, Code::InvalidUnicodeCodePoint);
                         }
                     }
                 }
@@ -558,20 +650,17 @@ fn parse_escape<'de, R: Read<'de>>(read: &mut R, scratch: &mut Vec<u8>) -> Resul
                     match char::from_u32(n as u32) {
                         Some(c) => c,
                         None => {
-                            return error(read, ErrorCode::InvalidUnicodeCodePoint);
+                            return error(read, Code::InvalidUnicodeCodePoint);
                         }
                     }
                 }
             };
 
-            // FIXME: this allocation is required in order to be compatible with stable
-            // rust, which doesn't support encoding a `char` into a stack buffer.
-            let mut buf = String::new();
-            buf.push(c);
-            scratch.extend(buf.bytes());
+            let mut buf = [0; 4];
+            scratch.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
         }
         _ => {
-            return error(read, ErrorCode::InvalidEscape);
+            return error(read, Code::InvalidEscape);
         }
     }
 
@@ -590,7 +679,7 @@ fn decode_hex_escape<'de, R: Read<'de>>(read: &mut R) -> Result<u16> {
             b'e' | b'E' => n * 16_u16 + 14_u16,
             b'f' | b'F' => n * 16_u16 + 15_u16,
             _ => {
-                return error(read, ErrorCode::InvalidEscape);
+                return error(read, Code::InvalidEscape);
             }
         };
     }
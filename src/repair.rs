@@ -0,0 +1,109 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rewriting almost-TJSON documents as TJSON
+//!
+//! Some producers write documents which are nearly TJSON: integers written
+//! as JSON numbers rather than strings under members tagged `i` or `u`,
+//! timestamps with offsets other than `Z`, or objects with repeated member
+//! names. `rewrite` reads such a document with every lenient option which
+//! fixes one of these, and writes it out again as `to_string` would:
+//!
+//! ```rust
+//! let input = r#"{"at:t": "2017-03-04T05:06:07+01:00", "count:u": 42}"#;
+//!
+//! let (output, repairs) = tjson::repair::rewrite_with_report(input).unwrap();
+//! assert_eq!(output, r#"{"at:t":"2017-03-04T04:06:07Z","count:u":"42"}"#);
+//!
+//! assert_eq!(repairs.len(), 2);
+//! assert_eq!(repairs[1].pointer(), "/count:u");
+//! assert_eq!(repairs[1].description(), "integer 42 wasn't quoted");
+//! ```
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use de::{Deserializer, DuplicateKeys, OffsetPolicy};
+use error::Result;
+use ser;
+use value::Value;
+
+/// A change `rewrite_with_report` made to a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repair {
+    pointer: String,
+    description: String,
+}
+
+impl Repair {
+    /// JSON Pointer to the value which was changed, or to the member which
+    /// was dropped.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// What was wrong with it, as in `integer 42 wasn't quoted`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.description, self.pointer)
+    }
+}
+
+/// Reads a document leniently and writes it out again as TJSON, as
+/// `rewrite_with_report` does, without the report.
+///
+/// # Errors
+///
+/// Fails for the same reasons as `rewrite_with_report`.
+pub fn rewrite(input: &str) -> Result<String> {
+    rewrite_with_report(input).map(|(output, _)| output)
+}
+
+/// Reads a document leniently and writes it out again as TJSON, returning
+/// the output along with each change made to it, in document order.
+///
+/// The document is read as a `Value` as `Deserializer` reads it by default,
+/// so `"count:i": 42` is written `"count:i":"42"`, and with these options:
+///
+/// - `timestamp_offsets(OffsetPolicy::NormalizeToUtc)`, so timestamps with
+///   another offset are written in UTC.
+/// - `duplicate_keys(DuplicateKeys::LastWins)`, so only the last of the
+///   members with the same name is written, in the place of the first.
+///
+/// # Errors
+///
+/// Fails if the input isn't JSON, or has something none of these options
+/// can repair, such as a quoted integer too large for its tag.
+pub fn rewrite_with_report(input: &str) -> Result<(String, Vec<Repair>)> {
+    let mut de = Deserializer::from_str(input)
+        .timestamp_offsets(OffsetPolicy::NormalizeToUtc)
+        .duplicate_keys(DuplicateKeys::LastWins)
+        .keep_repairs();
+    let value = try!(Value::deserialize(&mut de));
+    try!(de.end());
+
+    let output = try!(ser::to_string(&value));
+    let repairs = de.take_repairs()
+        .into_iter()
+        .map(
+            |(pointer, description)| {
+                Repair {
+                    pointer: pointer,
+                    description: description,
+                }
+            },
+        )
+        .collect();
+    Ok((output, repairs))
+}
@@ -0,0 +1,465 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Inferring the shape of documents from samples
+//!
+//! `infer` records which kinds of value were seen at each place in a set of
+//! sample documents, and `Schema::check` then reports everywhere another
+//! document differs from them:
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::schema;
+//!
+//! let schema = schema::infer(&[
+//!     tjson!({ "id": 1, "tags": ["a"] }),
+//!     tjson!({ "id": "two", "tags": [], "note": "optional" }),
+//! ]);
+//!
+//! assert!(schema.check(&tjson!({ "id": "three", "tags": ["b", "c"] })).is_ok());
+//!
+//! let violations = schema.check(&tjson!({ "tags": [true], "extra": 1 })).unwrap_err();
+//! let mut messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+//! messages.sort();
+//! assert_eq!(
+//!     messages,
+//!     [
+//!         "expected string, found boolean at /tags/0",
+//!         "missing member at /id",
+//!         "unexpected member at /extra",
+//!     ]
+//! );
+//! # }
+//! ```
+//!
+//! A `Schema` serializes as a TJSON document of its own, so it can be stored
+//! alongside the data it describes and deserialized again later.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map::Entry;
+use std::error;
+use std::fmt::{self, Write};
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use map::Map;
+use value::{Value, ValueKind, push_pointer_token};
+
+const KINDS: &str = "kinds:A<s>";
+const MEMBERS: &str = "members:A<O>";
+const ITEMS: &str = "items:O";
+const NAME: &str = "name:s";
+const OPTIONAL: &str = "optional:b";
+const SCHEMA: &str = "schema:O";
+
+/// Infer a schema which accepts every one of the given sample documents.
+///
+/// Where samples disagree about the kind of a value, the schema accepts any
+/// of the kinds seen. Object members missing from some of the samples are
+/// optional, and the elements of arrays and sets are all described by a
+/// single schema covering every element seen.
+pub fn infer(values: &[Value]) -> Schema {
+    let mut schema = Schema::default();
+    for value in values {
+        schema.merge(Schema::of(value));
+    }
+    schema
+}
+
+/// The shape of a value: the kinds it may be, the members it may have if it
+/// is an object, and the shape of its elements if it is an array or set.
+///
+/// A schema which has seen no samples at all accepts any value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    kinds: BTreeSet<ValueKind>,
+    members: BTreeMap<String, Member>,
+    items: Option<Box<Schema>>,
+}
+
+/// An object member described by a `Schema`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    schema: Schema,
+    optional: bool,
+}
+
+impl Schema {
+    fn of(value: &Value) -> Schema {
+        let mut schema = Schema::default();
+        schema.kinds.insert(value.kind());
+
+        match *value {
+            Value::Object(ref map) => {
+                for (name, value) in map {
+                    let member = Member {
+                        schema: Schema::of(value),
+                        optional: false,
+                    };
+                    schema.members.insert(name.clone(), member);
+                }
+            }
+            Value::Array(ref array) => schema.items = items(array),
+            Value::Set(ref set) => schema.items = items(set),
+            _ => {}
+        }
+
+        schema
+    }
+
+    fn merge(&mut self, other: Schema) {
+        let object = ValueKind::Object;
+        if self.kinds.contains(&object) && other.kinds.contains(&object) {
+            // Members only seen on one side were missing from the other's
+            // samples
+            for (name, member) in &mut self.members {
+                if !other.members.contains_key(name) {
+                    member.optional = true;
+                }
+            }
+            for (name, mut member) in other.members {
+                match self.members.entry(name) {
+                    Entry::Occupied(mut entry) => {
+                        let existing = entry.get_mut();
+                        existing.schema.merge(member.schema);
+                        existing.optional |= member.optional;
+                    }
+                    Entry::Vacant(entry) => {
+                        member.optional = true;
+                        entry.insert(member);
+                    }
+                }
+            }
+        } else if other.kinds.contains(&object) {
+            self.members = other.members;
+        }
+
+        self.kinds.extend(other.kinds);
+
+        self.items = match (self.items.take(), other.items) {
+            (Some(mut items), Some(other)) => {
+                items.merge(*other);
+                Some(items)
+            }
+            (items, other) => items.or(other),
+        };
+    }
+
+    /// The kinds of value this schema accepts. Empty if it accepts anything.
+    pub fn kinds(&self) -> &BTreeSet<ValueKind> {
+        &self.kinds
+    }
+
+    /// The object member with the given name, if one was seen in any sample.
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.get(name)
+    }
+
+    /// The schema of array and set elements, if any samples had elements.
+    pub fn items(&self) -> Option<&Schema> {
+        self.items.as_deref()
+    }
+
+    /// Check a value against this schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns every violation found, in the order the value's members and
+    /// elements are iterated, with the missing members of each object after
+    /// its other violations. Each has the JSON Pointer of the offending value,
+    /// using iteration order for the elements of sets. Nothing inside a value
+    /// of the wrong kind is checked.
+    pub fn check(&self, value: &Value) -> Result<(), Vec<SchemaViolation>> {
+        let mut violations = Vec::new();
+        let mut path = String::new();
+        self.check_at(value, &mut path, &mut violations);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn check_at(&self, value: &Value, path: &mut String, violations: &mut Vec<SchemaViolation>) {
+        if self.kinds.is_empty() {
+            return;
+        }
+        if !self.kinds.contains(&value.kind()) {
+            let problem = Problem::Kind {
+                expected: self.kinds.iter().cloned().collect(),
+                found: value.kind(),
+            };
+            violations.push(SchemaViolation::new(path, problem));
+            return;
+        }
+
+        let len = path.len();
+        match *value {
+            Value::Object(ref map) => {
+                for (name, value) in map {
+                    push_pointer_token(path, name);
+                    match self.members.get(name) {
+                        Some(member) => member.schema.check_at(value, path, violations),
+                        None => {
+                            violations.push(SchemaViolation::new(path, Problem::UnexpectedMember))
+                        }
+                    }
+                    path.truncate(len);
+                }
+
+                for (name, member) in &self.members {
+                    if !member.optional && !map.contains_key(name) {
+                        push_pointer_token(path, name);
+                        violations.push(SchemaViolation::new(path, Problem::MissingMember));
+                        path.truncate(len);
+                    }
+                }
+            }
+            Value::Array(ref array) => self.check_items(array, path, violations),
+            Value::Set(ref set) => self.check_items(set, path, violations),
+            _ => {}
+        }
+    }
+
+    fn check_items<'a, I>(&self, elements: I, path: &mut String, violations: &mut Vec<SchemaViolation>)
+    where
+        I: IntoIterator<Item = &'a Value>,
+    {
+        let items = match self.items {
+            Some(ref items) => items,
+            None => return,
+        };
+
+        let len = path.len();
+        for (index, element) in elements.into_iter().enumerate() {
+            // Writing to a String can't fail
+            let _ = write!(path, "/{}", index);
+            items.check_at(element, path, violations);
+            path.truncate(len);
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = Map::new();
+
+        let kinds = self.kinds.iter().map(|kind| Value::String(kind.to_string())).collect();
+        map.insert(KINDS.to_owned(), Value::Array(kinds));
+
+        if !self.members.is_empty() {
+            let members = self.members
+                .iter()
+                .map(
+                    |(name, member)| {
+                        let mut map = Map::new();
+                        map.insert(NAME.to_owned(), Value::String(name.clone()));
+                        map.insert(OPTIONAL.to_owned(), Value::Bool(member.optional));
+                        map.insert(SCHEMA.to_owned(), member.schema.to_value());
+                        Value::Object(map)
+                    },
+                )
+                .collect();
+            map.insert(MEMBERS.to_owned(), Value::Array(members));
+        }
+
+        if let Some(ref items) = self.items {
+            map.insert(ITEMS.to_owned(), items.to_value());
+        }
+
+        Value::Object(map)
+    }
+
+    fn from_value(value: &Value) -> Result<Schema, String> {
+        let map = match value.as_object() {
+            Some(map) => map,
+            None => return Err(format!("expected a schema object, found {}", value.kind())),
+        };
+
+        let mut schema = Schema::default();
+
+        for kind in try!(array_member(map, KINDS)) {
+            let kind = try!(kind.as_str().and_then(parse_kind).ok_or_else(|| format!("unknown kind {}", kind)));
+            schema.kinds.insert(kind);
+        }
+
+        for member in try!(array_member(map, MEMBERS)) {
+            let name = match member.get(NAME).and_then(Value::as_str) {
+                Some(name) => name.to_owned(),
+                None => return Err(format!("member without a {:?}", NAME)),
+            };
+            let optional = match member.get(OPTIONAL).and_then(Value::as_bool) {
+                Some(optional) => optional,
+                None => return Err(format!("member {:?} without {:?}", name, OPTIONAL)),
+            };
+            let member = Member {
+                schema: try!(Schema::from_value(&member[SCHEMA])),
+                optional: optional,
+            };
+            schema.members.insert(name, member);
+        }
+
+        if let Some(items) = map.get(ITEMS) {
+            schema.items = Some(Box::new(try!(Schema::from_value(items))));
+        }
+
+        Ok(schema)
+    }
+}
+
+impl Member {
+    /// The schema of the member's value.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Whether some samples lacked this member.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+}
+
+fn items<'a, I>(elements: I) -> Option<Box<Schema>>
+where
+    I: IntoIterator<Item = &'a Value>,
+{
+    let mut items: Option<Schema> = None;
+    for element in elements {
+        let element = Schema::of(element);
+        match items {
+            Some(ref mut items) => items.merge(element),
+            None => items = Some(element),
+        }
+    }
+    items.map(Box::new)
+}
+
+fn array_member<'a>(map: &'a Map<String, Value>, name: &str) -> Result<&'a [Value], String> {
+    match map.get(name) {
+        Some(value) => {
+            value
+                .as_array()
+                .map(|array| &array[..])
+                .ok_or_else(|| format!("expected {:?} to be an array, found {}", name, value.kind()))
+        }
+        None => Ok(&[]),
+    }
+}
+
+fn parse_kind(name: &str) -> Option<ValueKind> {
+    let kind = match name {
+        "undefined" => ValueKind::Undefined,
+        "boolean" => ValueKind::Bool,
+        "data" => ValueKind::Data,
+        "number" => ValueKind::Number,
+        "string" => ValueKind::String,
+        "timestamp" => ValueKind::Timestamp,
+        "array" => ValueKind::Array,
+        "set" => ValueKind::Set,
+        "object" => ValueKind::Object,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+impl Serialize for Schema {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Schema {
+    fn deserialize<D>(deserializer: D) -> Result<Schema, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = try!(Value::deserialize(deserializer));
+        Schema::from_value(&value).map_err(|msg| de::Error::custom(format!("invalid schema: {}", msg)))
+    }
+}
+
+/// A place where a value does not match a `Schema`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaViolation {
+    path: String,
+    problem: Problem,
+}
+
+/// What is wrong at the path of a `SchemaViolation`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The value is of a kind not seen here in any sample.
+    Kind {
+        /// The kinds the schema accepts here.
+        expected: Vec<ValueKind>,
+        /// The kind of the value.
+        found: ValueKind,
+    },
+
+    /// A member present in every sample is missing.
+    MissingMember,
+
+    /// A member not seen in any sample is present.
+    UnexpectedMember,
+}
+
+impl SchemaViolation {
+    fn new(path: &str, problem: Problem) -> Self {
+        SchemaViolation {
+            path: path.to_owned(),
+            problem: problem,
+        }
+    }
+
+    /// JSON Pointer to the offending value, or to the missing member.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// What is wrong there.
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.problem {
+            Problem::Kind { ref expected, found } => {
+                try!(f.write_str("expected "));
+                for (i, kind) in expected.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(" or "));
+                    }
+                    try!(write!(f, "{}", kind));
+                }
+                try!(write!(f, ", found {}", found));
+            }
+            Problem::MissingMember => try!(f.write_str("missing member")),
+            Problem::UnexpectedMember => try!(f.write_str("unexpected member")),
+        }
+
+        if !self.path.is_empty() {
+            try!(write!(f, " at {}", self.path));
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for SchemaViolation {
+    fn description(&self) -> &str {
+        "TJSON schema violation"
+    }
+}
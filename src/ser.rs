@@ -7,22 +7,81 @@
 // except according to those terms.
 
 //! Serialize a Rust data structure into JSON data.
-
+//!
+//! # Pretty printing layout
+//!
+//! `PrettyFormatter` lays out values by these rules, which are pinned by the
+//! files in `tests/golden`:
+//!
+//! - Objects put each member on its own line, indented one level deeper than
+//!   the line of the opening `{`, and the closing `}` back at that line's
+//!   level. Arrays do the same with their elements unless `ArrayWrap::Fill`
+//!   is set. Empty objects and arrays are written as `{}` and `[]`.
+//! - A `Value::Set` is laid out exactly like an array of its elements,
+//!   in the set's iteration order. An empty set is `[]`.
+//! - A `Value::Data` in a member tagged `d`, `d16`, `d32` or `d64`, or an
+//!   array or set of them, is a single string in the tag's encoding.
+//!   Anywhere else it is a base64url string, as if it were tagged `d`.
+//! - A `Value::Timestamp` is a single RFC 3339 string, like any other string.
+//! - Nothing is written after the last character of a line, and line breaks
+//!   are a bare `\n` on every platform.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::error;
 use std::fmt;
 use std::io;
+use std::mem;
 use std::num::FpCategory;
+use std::result;
 use std::str;
 
 use serde::ser::{self, Impossible};
-use super::error::{Error, ErrorCode, Result};
+use super::error::{Code, Error, Result};
+use data::DATA_TOKEN;
+use de::DuplicateElements;
+use encoding;
+#[cfg(feature = "decimal")]
+use number::DECIMAL_TOKEN;
+use schema::Schema;
+use set::{self, SET_TOKEN};
+use map::Map;
+use tag;
+use timestamp;
+use transform;
+use value::{DateTime, Set, Value, push_pointer_token, to_value_with};
 
 use itoa;
 use dtoa;
 
 /// A structure for serializing Rust values into JSON.
 pub struct Serializer<W, F = CompactFormatter> {
-    writer: W,
+    writer: CountingWriter<W>,
     formatter: F,
+    duplicate_elements: DuplicateElements,
+    floats: FloatFormat,
+    /// The encoding of bytes in the value of the member being written, if
+    /// the tag of its name makes them data.
+    data: Option<DataEncoding>,
+    /// Whether integers tagged `i` or `u` are written as strings.
+    quote_integers: bool,
+    /// Whether the value of the member being written holds integers, the
+    /// tag of its name being `i` or `u` or an array or set of them.
+    integers: bool,
+    /// Whether the value of the member being written holds floats, so that
+    /// integers in it are written as floats, the tag of its name being `f`
+    /// or an array or set of them.
+    widen: bool,
+    /// How member names without a tag are tagged.
+    inference: TagInference,
+    /// Whether a member name which already has a tag is an error.
+    reject_tagged: bool,
+    /// The untagged name of the member being written, held back until its
+    /// value is seen.
+    name: String,
+    /// Whether `name` is waiting to be written.
+    pending_name: bool,
 }
 
 impl<W> Serializer<W>
@@ -57,15 +116,326 @@ where
     #[inline]
     pub fn with_formatter(writer: W, formatter: F) -> Self {
         Serializer {
-            writer: writer,
+            writer: CountingWriter { writer: writer, count: 0 },
             formatter: formatter,
+            duplicate_elements: DuplicateElements::Error,
+            floats: FloatFormat::default(),
+            data: None,
+            quote_integers: true,
+            integers: false,
+            widen: false,
+            inference: TagInference::WhereKnown,
+            reject_tagged: false,
+            name: String::new(),
+            pending_name: false,
         }
     }
 
+    /// Handle sets with more than one equal element according to the given
+    /// policy.
+    ///
+    /// A value which serializes as a set, like `Set`, is converted to a
+    /// `Value` before it is written so that its elements can be compared.
+    /// By default a duplicate is an error, and nothing of the set is
+    /// written.
+    pub fn duplicate_elements(mut self, policy: DuplicateElements) -> Self {
+        self.duplicate_elements = policy;
+        self
+    }
+
+    /// Write floats in exponent notation only as the given policy allows.
+    /// By default it is used as the shortest representation has it, for
+    /// magnitudes of `1e21` and above or below `1e-6`.
+    ///
+    /// The digits written are always the shortest which read back as the
+    /// same float; only where the decimal point goes changes.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    /// use tjson::ser::ExponentPolicy;
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).exponent_notation(ExponentPolicy::Never);
+    /// 1.5e-7.serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"0.00000015");
+    /// # }
+    /// ```
+    pub fn exponent_notation(mut self, policy: ExponentPolicy) -> Self {
+        self.floats.exponent = policy;
+        self
+    }
+
+    /// Fail rather than write a float without an exponent if that would
+    /// take more than `max` bytes, as `1e300` would. The default is 64.
+    pub fn max_float_len(mut self, max: usize) -> Self {
+        self.floats.max_len = max;
+        self
+    }
+
+    /// Write integers tagged `i` or `u` as strings, as TJSON does, or as
+    /// JSON numbers. On by default. Integers elsewhere, such as in an
+    /// array which isn't the value of a member, are always numbers.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// # extern crate serde;
+    /// #
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    ///
+    /// let value = tjson!({ "n:u": 7 });
+    ///
+    /// let mut ser = Serializer::new(Vec::new());
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{"n:u":"7"}"#);
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).quoted_integers(false);
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{"n:u":7}"#);
+    /// # }
+    /// ```
+    pub fn quoted_integers(mut self, enabled: bool) -> Self {
+        self.quote_integers = enabled;
+        self
+    }
+
+    /// Choose how member names without a tag are tagged. By default
+    /// (`TagInference::WhereKnown`) each is given the tag of its value, and
+    /// the name of a value with none, like `None` or a sequence of mixed
+    /// elements, is left untagged. `TagInference::Required` makes such a
+    /// value an error instead, so that what is written is always TJSON. An
+    /// empty sequence is tagged `A<O>`, or `S<O>` if it is a set, which
+    /// reads back as a sequence of anything.
+    ///
+    /// A name whose tag, after its last `:`, is one `Tag::parse` reads keeps
+    /// it, and its value must be what the tag says: an integer under `f` or
+    /// a string under `t` is, but an integer under `s` is an error. Any
+    /// other name, like `"host:port"`, is untagged and is tagged like one
+    /// without a colon. With `TagInference::Off` names are written as they
+    /// are and values aren't checked.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// # extern crate serde;
+    /// #
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    /// use tjson::ser::TagInference;
+    ///
+    /// let value = tjson!({ "host:port": "a:80", "n": 7, "tags": [], "until": null });
+    ///
+    /// let mut ser = Serializer::new(Vec::new());
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     ser.into_inner(),
+    ///     &br#"{"host:port:s":"a:80","n:i":"7","tags:A<O>":[],"until":null}"#[..]
+    /// );
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).infer_tags(TagInference::Required);
+    /// let err = value.serialize(&mut ser).unwrap_err();
+    /// assert_eq!(err.to_string(), "member \"until\" can't be tagged, as its value is null");
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).infer_tags(TagInference::Off);
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     ser.into_inner(),
+    ///     &br#"{"host:port":"a:80","n":7,"tags":[],"until":null}"#[..]
+    /// );
+    ///
+    /// let mut ser = Serializer::new(Vec::new());
+    /// let err = tjson!({ "n:s": 7 }).serialize(&mut ser).unwrap_err();
+    /// assert_eq!(err.to_string(), "member \"n:s\" is tagged `s` but its value is `i`");
+    /// # }
+    /// ```
+    pub fn infer_tags(mut self, inference: TagInference) -> Self {
+        self.inference = inference;
+        self
+    }
+
+    /// Fail on a member name which already has a tag, like a field renamed
+    /// to `"name:s"` for another TJSON library, rather than checking its
+    /// value against the tag. Off by default.
+    ///
+    /// Only a name whose text after its last `:` is a tag `Tag::parse`
+    /// reads has one; `"host:port"` has none, and is tagged as usual.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// # extern crate serde;
+    /// #
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).reject_tagged_names(true);
+    /// tjson!({ "host:port": "a:80" }).serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{"host:port:s":"a:80"}"#);
+    ///
+    /// let mut ser = Serializer::new(Vec::new()).reject_tagged_names(true);
+    /// let err = tjson!({ "name:s": "ann" }).serialize(&mut ser).unwrap_err();
+    /// assert_eq!(err.to_string(), "member name \"name:s\" already has a tag");
+    /// # }
+    /// ```
+    pub fn reject_tagged_names(mut self, enabled: bool) -> Self {
+        self.reject_tagged = enabled;
+        self
+    }
+
+    /// The number of bytes the writer has accepted so far, over every value
+    /// serialized with this `Serializer`.
+    ///
+    /// After an error this counts only what the writer accepted before it,
+    /// which may end partway through a value; nothing the serializer had
+    /// yet to hand over is counted. Bytes which a buffering writer holds
+    /// are counted, as the writer has accepted them.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    ///
+    /// let mut ser = Serializer::pretty(Vec::new());
+    /// vec![1, 2].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.bytes_written(), 12);
+    /// "x".serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.bytes_written(), 15);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn bytes_written(&self) -> usize {
+        self.writer.count
+    }
+
     /// Unwrap the `Writer` from the `Serializer`.
     #[inline]
     pub fn into_inner(self) -> W {
-        self.writer
+        self.writer.writer
+    }
+
+    /// Writes an integer with `write`, quoted if it is in the value of a
+    /// member tagged `i` or `u`.
+    #[inline]
+    fn write_integer<G>(&mut self, write: G) -> Result<()>
+    where
+        G: FnOnce(&mut F, &mut CountingWriter<W>) -> io::Result<()>,
+    {
+        let quote = self.integers && self.quote_integers;
+        if quote {
+            try!(
+                self.formatter
+                    .begin_string(&mut self.writer)
+                    .map_err(Error::io)
+            );
+        }
+        try!(write(&mut self.formatter, &mut self.writer).map_err(Error::io));
+        if quote {
+            try!(
+                self.formatter
+                    .end_string(&mut self.writer)
+                    .map_err(Error::io)
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the name of a member, setting up how its value is written
+    /// from the tag.
+    fn serialize_name(&mut self, name: &str) -> Result<()> {
+        self.data = data_encoding(name);
+        self.integers = is_integer(name);
+        self.widen = is_float(name);
+        ser::Serializer::serialize_str(self, name)
+    }
+
+    /// Gives `name` the tag of `value` if it has none, or checks that
+    /// `value` is what the tag it has says.
+    fn tag_name<T: ?Sized>(&self, name: &mut String, value: &T) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        let (probed, ranges) = try!(probe_tag(value));
+        if let Some(tag) = valid_tag(name) {
+            return match probed {
+                Ok(ref found) if tag_accepts(tag, found, &ranges) => Ok(()),
+                // An empty sequence, or empty data, is whatever it is tagged
+                Err(NoTag::Empty(_)) if element_tag(tag).is_some() || transform::is_data(tag) => {
+                    Ok(())
+                }
+                // Signed and unsigned integers together, which fit the tag
+                Err(NoTag::Mixed(ref a, ref b)) if mixed_accepted(tag, a, b, &ranges) => Ok(()),
+                Ok(found) => {
+                    let msg = format_args!(
+                        "member {:?} is tagged `{}` but its value is `{}`",
+                        name,
+                        tag,
+                        found
+                    );
+                    Err(ser::Error::custom(msg))
+                }
+                Err(found) => {
+                    let msg = format_args!(
+                        "member {:?} is tagged `{}` but its value is {}",
+                        name,
+                        tag,
+                        found
+                    );
+                    Err(ser::Error::custom(msg))
+                }
+            };
+        }
+        match probed {
+            Ok(tag) => {
+                name.push(':');
+                name.push_str(&tag);
+                Ok(())
+            }
+            Err(NoTag::Empty(tag)) => {
+                name.push(':');
+                name.push_str(&tag);
+                Ok(())
+            }
+            Err(_) if self.inference == TagInference::WhereKnown => Ok(()),
+            Err(found) => {
+                let msg =
+                    format_args!("member {:?} can't be tagged, as its value is {}", name, found);
+                Err(ser::Error::custom(msg))
+            }
+        }
+    }
+}
+
+/// Counts the bytes the writer accepts, for `Serializer::bytes_written`.
+struct CountingWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W> io::Write for CountingWriter<W>
+where
+    W: io::Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = try!(self.writer.write(buf));
+        self.count += written;
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
     }
 }
 
@@ -97,82 +467,66 @@ where
 
     #[inline]
     fn serialize_i8(self, value: i8) -> Result<()> {
-        try!(
-            self.formatter
-                .write_i8(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_i8(writer, value))
     }
 
     #[inline]
     fn serialize_i16(self, value: i16) -> Result<()> {
-        try!(
-            self.formatter
-                .write_i16(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_i16(writer, value))
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<()> {
-        try!(
-            self.formatter
-                .write_i32(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_i32(writer, value))
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> Result<()> {
-        try!(
-            self.formatter
-                .write_i64(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_i64(writer, value))
     }
 
     #[inline]
     fn serialize_u8(self, value: u8) -> Result<()> {
-        try!(
-            self.formatter
-                .write_u8(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_u8(writer, value))
     }
 
     #[inline]
     fn serialize_u16(self, value: u16) -> Result<()> {
-        try!(
-            self.formatter
-                .write_u16(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_u16(writer, value))
     }
 
     #[inline]
     fn serialize_u32(self, value: u32) -> Result<()> {
-        try!(
-            self.formatter
-                .write_u32(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_u32(writer, value))
     }
 
     #[inline]
     fn serialize_u64(self, value: u64) -> Result<()> {
-        try!(
-            self.formatter
-                .write_u64(&mut self.writer, value)
-                .map_err(Error::io)
-        );
-        Ok(())
+        if self.widen {
+            return self.serialize_f64(value as f64);
+        }
+        self.write_integer(|formatter, writer| formatter.write_u64(writer, value))
     }
 
     #[inline]
@@ -185,13 +539,21 @@ where
                         .map_err(Error::io)
                 );
             }
-            _ => {
+            _ if self.floats.exponent == ExponentPolicy::Allow => {
                 try!(
                     self.formatter
                         .write_f32(&mut self.writer, value)
                         .map_err(Error::io)
                 );
             }
+            _ => {
+                let text = try!(self.floats.format(value));
+                try!(
+                    self.formatter
+                        .write_number_str(&mut self.writer, &text)
+                        .map_err(Error::io)
+                );
+            }
         }
         Ok(())
     }
@@ -206,13 +568,21 @@ where
                         .map_err(Error::io)
                 );
             }
-            _ => {
+            _ if self.floats.exponent == ExponentPolicy::Allow => {
                 try!(
                     self.formatter
                         .write_f64(&mut self.writer, value)
                         .map_err(Error::io)
                 );
             }
+            _ => {
+                let text = try!(self.floats.format(value));
+                try!(
+                    self.formatter
+                        .write_number_str(&mut self.writer, &text)
+                        .map_err(Error::io)
+                );
+            }
         }
         Ok(())
     }
@@ -229,14 +599,18 @@ where
         Ok(())
     }
 
+    /// Writes bytes as a string in the encoding of the tag of the member
+    /// they are in, such as hex for `"key:d16"` or `"keys:A<d16>"`, as
+    /// integers if the tag is `A<u>` or the like, or otherwise as base64url
+    /// like data tagged `d`.
     #[inline]
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
-        use serde::ser::SerializeSeq;
-        let mut seq = try!(self.serialize_seq(Some(value.len())));
-        for byte in value {
-            try!(seq.serialize_element(byte));
+        if self.integers {
+            return ser::Serializer::collect_seq(self, value);
         }
-        seq.end()
+        let encoding = self.data.unwrap_or(DataEncoding::Plain);
+        format_escaped_str(&mut self.writer, &mut self.formatter, &encoding.encode(value))
+            .map_err(Error::io)
     }
 
     #[inline]
@@ -266,10 +640,50 @@ where
 
     /// Serialize newtypes without an object wrapper.
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ser::Serialize,
     {
+        // Exact decimals arrive as their text, which is written unquoted
+        #[cfg(feature = "decimal")]
+        {
+            if name == DECIMAL_TOKEN {
+                return match try!(::value::to_value(value)) {
+                    Value::String(text) => {
+                        self.formatter
+                            .write_number_str(&mut self.writer, &text)
+                            .map_err(Error::io)
+                    }
+                    _ => Err(ser::Error::custom("expected the text of a decimal")),
+                };
+            }
+        }
+
+        // Data held undecoded arrives as its base64url text, which is written
+        // as it is unless the data is to be written some other way
+        if name == DATA_TOKEN {
+            let base64url = match self.data {
+                None | Some(DataEncoding::Plain) | Some(DataEncoding::Base64Url) => true,
+                Some(DataEncoding::Base16) | Some(DataEncoding::Base32) => false,
+            };
+            if self.integers || !base64url {
+                let text = match try!(::value::to_value(value)) {
+                    Value::String(text) => text,
+                    _ => return Err(ser::Error::custom("expected the text of data")),
+                };
+                let data = try!(encoding::b64url_decode(&text).map_err(ser::Error::custom));
+                return ser::Serializer::serialize_bytes(self, &data);
+            }
+        }
+
+        if name == SET_TOKEN {
+            let elements = try!(to_value_with(value, self.duplicate_elements));
+            return match try!(set::from_elements(elements, self.duplicate_elements)) {
+                Value::Set(set) => ser::Serializer::collect_seq(self, &set),
+                other => ser::Serialize::serialize(&other, self),
+            };
+        }
+
         value.serialize(self)
     }
 
@@ -349,6 +763,7 @@ where
                 Compound {
                     ser: self,
                     state: State::Empty,
+                    pairs: false,
                 },
             )
         } else {
@@ -361,6 +776,7 @@ where
                 Compound {
                     ser: self,
                     state: State::First,
+                    pairs: false,
                 },
             )
         }
@@ -412,9 +828,26 @@ where
         self.serialize_seq(Some(len))
     }
 
+    /// Maps are written as objects if their keys can be written as strings
+    /// and otherwise as arrays of `[key, value]` pairs. Which one is decided
+    /// by the first key, so the map is only started once that is known.
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
         if len == Some(0) {
+            return self.serialize_struct("", 0);
+        }
+        Ok(
+            Compound {
+                ser: self,
+                state: State::Unopened,
+                pairs: false,
+            },
+        )
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        if len == 0 {
             try!(
                 self.formatter
                     .begin_object(&mut self.writer)
@@ -429,6 +862,7 @@ where
                 Compound {
                     ser: self,
                     state: State::Empty,
+                    pairs: false,
                 },
             )
         } else {
@@ -441,15 +875,12 @@ where
                 Compound {
                     ser: self,
                     state: State::First,
+                    pairs: false,
                 },
             )
         }
     }
 
-    #[inline]
-    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_map(Some(len))
-    }
 
     #[inline]
     fn serialize_struct_variant(
@@ -480,7 +911,7 @@ where
                 .begin_object_value(&mut self.writer)
                 .map_err(Error::io)
         );
-        self.serialize_map(Some(len))
+        self.serialize_struct(variant, len)
     }
 
     fn collect_str<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
@@ -545,12 +976,16 @@ pub enum State {
     Empty,
     First,
     Rest,
+    /// A map which hasn't been started because no key has been seen yet.
+    Unopened,
 }
 
 #[doc(hidden)]
 pub struct Compound<'a, W: 'a, F: 'a> {
     ser: &'a mut Serializer<W, F>,
     state: State,
+    /// Whether a map is being written as an array of `[key, value]` pairs.
+    pairs: bool,
 }
 
 impl<'a, W, F> ser::SerializeSeq for Compound<'a, W, F>
@@ -702,6 +1137,56 @@ where
     where
         T: ser::Serialize,
     {
+        if self.state == State::Unopened {
+            self.pairs = !is_member_name(key);
+            if self.pairs {
+                try!(
+                    self.ser
+                        .formatter
+                        .begin_array(&mut self.ser.writer)
+                        .map_err(Error::io)
+                );
+            } else {
+                try!(
+                    self.ser
+                        .formatter
+                        .begin_object(&mut self.ser.writer)
+                        .map_err(Error::io)
+                );
+            }
+            self.state = State::First;
+        }
+
+        if self.pairs {
+            try!(
+                self.ser
+                    .formatter
+                    .begin_array_value(&mut self.ser.writer, self.state == State::First)
+                    .map_err(Error::io)
+            );
+            self.state = State::Rest;
+            try!(
+                self.ser
+                    .formatter
+                    .begin_array(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+            try!(
+                self.ser
+                    .formatter
+                    .begin_array_value(&mut self.ser.writer, true)
+                    .map_err(Error::io)
+            );
+            try!(key.serialize(&mut *self.ser));
+            try!(
+                self.ser
+                    .formatter
+                    .end_array_value(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+            return Ok(());
+        }
+
         try!(
             self.ser
                 .formatter
@@ -711,6 +1196,10 @@ where
         self.state = State::Rest;
 
         try!(key.serialize(MapKeySerializer { ser: self.ser }));
+        if self.ser.pending_name {
+            // Written with its tag by `serialize_value`
+            return Ok(());
+        }
 
         try!(
             self.ser
@@ -726,6 +1215,51 @@ where
     where
         T: ser::Serialize,
     {
+        if self.pairs {
+            try!(
+                self.ser
+                    .formatter
+                    .begin_array_value(&mut self.ser.writer, false)
+                    .map_err(Error::io)
+            );
+            try!(value.serialize(&mut *self.ser));
+            try!(
+                self.ser
+                    .formatter
+                    .end_array_value(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+            try!(
+                self.ser
+                    .formatter
+                    .end_array(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+            try!(
+                self.ser
+                    .formatter
+                    .end_array_value(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+            return Ok(());
+        }
+
+        if self.ser.pending_name {
+            self.ser.pending_name = false;
+            let mut name = mem::replace(&mut self.ser.name, String::new());
+            let written = self.ser
+                .tag_name(&mut name, value)
+                .and_then(|()| self.ser.serialize_name(&name));
+            self.ser.name = name;
+            try!(written);
+            try!(
+                self.ser
+                    .formatter
+                    .end_object_key(&mut self.ser.writer)
+                    .map_err(Error::io)
+            );
+        }
+
         try!(
             self.ser
                 .formatter
@@ -733,6 +1267,9 @@ where
                 .map_err(Error::io)
         );
         try!(value.serialize(&mut *self.ser));
+        self.ser.data = None;
+        self.ser.integers = false;
+        self.ser.widen = false;
         try!(
             self.ser
                 .formatter
@@ -746,6 +1283,28 @@ where
     fn end(self) -> Result<()> {
         match self.state {
             State::Empty => {}
+            State::Unopened => {
+                try!(
+                    self.ser
+                        .formatter
+                        .begin_object(&mut self.ser.writer)
+                        .map_err(Error::io)
+                );
+                try!(
+                    self.ser
+                        .formatter
+                        .end_object(&mut self.ser.writer)
+                        .map_err(Error::io)
+                );
+            }
+            _ if self.pairs => {
+                try!(
+                    self.ser
+                        .formatter
+                        .end_array(&mut self.ser.writer)
+                        .map_err(Error::io)
+                )
+            }
             _ => {
                 try!(
                     self.ser
@@ -827,12 +1386,22 @@ where
     }
 }
 
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn is_member_name<T: ?Sized>(key: &T) -> bool
+where
+    T: ser::Serialize,
+{
+    let probe = &mut Serializer::new(io::sink());
+    key.serialize(MapKeySerializer { ser: probe }).is_ok()
+}
+
 struct MapKeySerializer<'a, W: 'a, F: 'a> {
     ser: &'a mut Serializer<W, F>,
 }
 
 fn key_must_be_a_string() -> Error {
-    Error::syntax(ErrorCode::KeyMustBeAString, 0, 0)
+    Error::syntax(Code::KeyMustBeAString, 0, 0)
 }
 
 impl<'a, W, F> ser::Serializer for MapKeySerializer<'a, W, F>
@@ -845,7 +1414,16 @@ where
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        self.ser.serialize_str(value)
+        if self.ser.reject_tagged && valid_tag(value).is_some() {
+            return Err(ser::Error::custom(already_tagged(value)));
+        }
+        if self.ser.inference == TagInference::Off {
+            return self.ser.serialize_name(value);
+        }
+        self.ser.name.clear();
+        self.ser.name.push_str(value);
+        self.ser.pending_name = true;
+        Ok(())
     }
 
     #[inline]
@@ -859,10 +1437,13 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ser::Serialize,
     {
+        if name == DATA_TOKEN {
+            return Err(key_must_be_a_string());
+        }
         value.serialize(self)
     }
 
@@ -879,6 +1460,9 @@ where
     }
 
     fn serialize_i8(self, value: i8) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -901,6 +1485,9 @@ where
     }
 
     fn serialize_i16(self, value: i16) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -923,6 +1510,9 @@ where
     }
 
     fn serialize_i32(self, value: i32) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -945,6 +1535,9 @@ where
     }
 
     fn serialize_i64(self, value: i64) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -967,6 +1560,9 @@ where
     }
 
     fn serialize_u8(self, value: u8) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -989,6 +1585,9 @@ where
     }
 
     fn serialize_u16(self, value: u16) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -1011,6 +1610,9 @@ where
     }
 
     fn serialize_u32(self, value: u32) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -1033,6 +1635,9 @@ where
     }
 
     fn serialize_u64(self, value: u64) -> Result<()> {
+        if self.ser.inference != TagInference::Off {
+            return self.serialize_str(&value.to_string());
+        }
         try!(
             self.ser
                 .formatter
@@ -1147,194 +1752,654 @@ where
     }
 }
 
-/// Represents a character escape code in a type-safe manner.
-pub enum CharEscape {
-    /// An escaped quote `"`
-    Quote,
-    /// An escaped reverse solidus `\`
-    ReverseSolidus,
-    /// An escaped solidus `/`
-    Solidus,
-    /// An escaped backspace character (usually escaped as `\b`)
-    Backspace,
-    /// An escaped form feed character (usually escaped as `\f`)
-    FormFeed,
-    /// An escaped line feed character (usually escaped as `\n`)
-    LineFeed,
-    /// An escaped carriage return character (usually escaped as `\r`)
-    CarriageReturn,
-    /// An escaped tab character (usually escaped as `\t`)
-    Tab,
-    /// An escaped ASCII plane control character (usually escaped as
-    /// `\u00XX` where `XX` are two hex characters)
-    AsciiControl(u8),
+/// The tag of a value which `infer_tags` gives its member name, or what
+/// the value is if it has none. Integers are tagged by their type, `i` for
+/// signed and `u` for unsigned ones, whatever their values, so a `Number`
+/// is tagged as it is stored. The contents of objects are not looked at.
+#[derive(Clone, Copy)]
+struct TagProbe<'a> {
+    ranges: &'a Ranges,
 }
 
-impl CharEscape {
-    #[inline]
-    fn from_escape_table(escape: u8, byte: u8) -> CharEscape {
-        match escape {
-            self::BB => CharEscape::Backspace,
-            self::TT => CharEscape::Tab,
-            self::NN => CharEscape::LineFeed,
-            self::FF => CharEscape::FormFeed,
-            self::RR => CharEscape::CarriageReturn,
-            self::QU => CharEscape::Quote,
-            self::BS => CharEscape::ReverseSolidus,
-            self::U => CharEscape::AsciiControl(byte),
-            _ => unreachable!(),
+/// Whether any integer a `TagProbe` saw is negative, above 255 or above
+/// `i64::MAX`, for checking integers against a tag other than their own.
+#[derive(Default)]
+struct Ranges {
+    negative: Cell<bool>,
+    above_u8: Cell<bool>,
+    above_i64: Cell<bool>,
+}
+
+type Probed = result::Result<Cow<'static, str>, NoTag>;
+
+fn probed(tag: &'static str) -> Result<Probed> {
+    Ok(Ok(Cow::Borrowed(tag)))
+}
+
+/// Probes the tag of a value with `TagProbe`, and the ranges of the
+/// integers in it.
+fn probe_tag<T: ?Sized>(value: &T) -> Result<(Probed, Ranges)>
+where
+    T: ser::Serialize,
+{
+    let ranges = Ranges::default();
+    let probed = try!(value.serialize(TagProbe { ranges: &ranges }));
+    Ok((probed, ranges))
+}
+
+impl<'a> TagProbe<'a> {
+    fn signed(self, value: i64) -> Result<Probed> {
+        if value < 0 {
+            self.ranges.negative.set(true);
         }
+        if value > u8::max_value() as i64 {
+            self.ranges.above_u8.set(true);
+        }
+        probed("i")
+    }
+
+    fn unsigned(self, value: u64) -> Result<Probed> {
+        if value > u8::max_value() as u64 {
+            self.ranges.above_u8.set(true);
+        }
+        if value > i64::max_value() as u64 {
+            self.ranges.above_i64.set(true);
+        }
+        probed("u")
     }
 }
 
-/// This trait abstracts away serializing the JSON control characters, which allows the user to
-/// optionally pretty print the JSON output.
-pub trait Formatter {
-    /// Writes a `null` value to the specified writer.
-    #[inline]
-    fn write_null<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b"null")
+/// A value which has no tag, for `TagProbe`.
+#[derive(Debug, PartialEq)]
+enum NoTag {
+    /// Written as `null`, which TJSON doesn't have.
+    Null,
+    /// A sequence without elements, or whose elements are all empty, with
+    /// the tag it is given under an untagged name: `A<O>`, or `S<O>` for a
+    /// set, nested as deep as its empty elements are. Having no elements
+    /// it reads back as a sequence of anything.
+    Empty(String),
+    /// A sequence with elements of these tags, which share none.
+    Mixed(String, String),
+    /// A map written as an array of `[key, value]` pairs.
+    Pairs,
+}
+
+impl fmt::Display for NoTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NoTag::Null => f.write_str("null"),
+            NoTag::Empty(_) => f.write_str("an empty array"),
+            NoTag::Mixed(ref a, ref b) => {
+                write!(f, "an array with elements tagged `{}` and `{}`", a, b)
+            }
+            NoTag::Pairs => f.write_str("an array of pairs"),
+        }
     }
+}
 
-    /// Writes a `true` or `false` value to the specified writer.
-    #[inline]
-    fn write_bool<W: ?Sized>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        let s = if value {
-            b"true" as &[u8]
+impl<'a> ser::Serializer for TagProbe<'a> {
+    type Ok = Probed;
+    type Error = Error;
+
+    type SerializeSeq = ElementsProbe<'a>;
+    type SerializeTuple = ElementsProbe<'a>;
+    type SerializeTupleStruct = ElementsProbe<'a>;
+    type SerializeTupleVariant = ObjectProbe;
+    type SerializeMap = MapProbe;
+    type SerializeStruct = ObjectProbe;
+    type SerializeStructVariant = ObjectProbe;
+
+    fn serialize_bool(self, _value: bool) -> Result<Probed> {
+        probed("b")
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Probed> {
+        self.signed(value as i64)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Probed> {
+        self.signed(value as i64)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Probed> {
+        self.signed(value as i64)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Probed> {
+        self.signed(value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Probed> {
+        self.unsigned(value as u64)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Probed> {
+        self.unsigned(value as u64)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Probed> {
+        self.unsigned(value as u64)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Probed> {
+        self.unsigned(value)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Probed> {
+        self.serialize_f64(value as f64)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Probed> {
+        if value.is_finite() {
+            probed("f")
         } else {
-            b"false" as &[u8]
-        };
-        writer.write_all(s)
+            Ok(Err(NoTag::Null))
+        }
     }
 
-    /// Writes an integer value like `-123` to the specified writer.
-    #[inline]
-    fn write_i8<W: ?Sized>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        itoa::write(writer, value).map(|_| ())
+    fn serialize_char(self, _value: char) -> Result<Probed> {
+        probed("s")
     }
 
-    /// Writes an integer value like `-123` to the specified writer.
-    #[inline]
-    fn write_i16<W: ?Sized>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        itoa::write(writer, value).map(|_| ())
+    fn serialize_str(self, _value: &str) -> Result<Probed> {
+        probed("s")
     }
 
-    /// Writes an integer value like `-123` to the specified writer.
-    #[inline]
-    fn write_i32<W: ?Sized>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        itoa::write(writer, value).map(|_| ())
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Probed> {
+        probed("d")
     }
 
-    /// Writes an integer value like `-123` to the specified writer.
-    #[inline]
-    fn write_i64<W: ?Sized>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        itoa::write(writer, value).map(|_| ())
+    fn serialize_none(self) -> Result<Probed> {
+        Ok(Err(NoTag::Null))
     }
 
-    /// Writes an integer value like `123` to the specified writer.
-    #[inline]
-    fn write_u8<W: ?Sized>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Probed>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        itoa::write(writer, value).map(|_| ())
+        value.serialize(self)
     }
 
-    /// Writes an integer value like `123` to the specified writer.
-    #[inline]
-    fn write_u16<W: ?Sized>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    fn serialize_unit(self) -> Result<Probed> {
+        Ok(Err(NoTag::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Probed> {
+        Ok(Err(NoTag::Null))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Probed> {
+        probed("s")
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Probed>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        itoa::write(writer, value).map(|_| ())
+        #[cfg(feature = "decimal")]
+        {
+            if name == DECIMAL_TOKEN {
+                return probed("f");
+            }
+        }
+        if name == timestamp::TIMESTAMP_TOKEN {
+            return probed("t");
+        }
+        if name == DATA_TOKEN {
+            return probed("d");
+        }
+        let tag = try!(value.serialize(self));
+        if name == SET_TOKEN {
+            return Ok(match tag {
+                Ok(ref tag) if tag.starts_with("A<") => Ok(Cow::Owned(format!("S{}", &tag[1..]))),
+                Err(NoTag::Empty(tag)) => Err(NoTag::Empty(format!("S{}", &tag[1..]))),
+                tag => tag,
+            });
+        }
+        Ok(tag)
     }
 
-    /// Writes an integer value like `123` to the specified writer.
-    #[inline]
-    fn write_u32<W: ?Sized>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Probed>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        itoa::write(writer, value).map(|_| ())
+        probed("O")
     }
 
-    /// Writes an integer value like `123` to the specified writer.
-    #[inline]
-    fn write_u64<W: ?Sized>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ElementsProbe<'a>> {
+        Ok(
+            ElementsProbe {
+                probe: self,
+                tag: None,
+                empty: None,
+                mixed: None,
+            },
+        )
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ElementsProbe<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ElementsProbe<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<ObjectProbe> {
+        Ok(ObjectProbe)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapProbe> {
+        Ok(MapProbe { keys: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<ObjectProbe> {
+        Ok(ObjectProbe)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<ObjectProbe> {
+        Ok(ObjectProbe)
+    }
+}
+
+/// Finds the tag the elements of a sequence share, for `TagProbe`.
+struct ElementsProbe<'a> {
+    probe: TagProbe<'a>,
+    tag: Option<String>,
+    /// The tag of the deepest element which is an empty sequence, which
+    /// any tag of a sequence fits.
+    empty: Option<String>,
+    /// What an element with no tag is, or that it shares none with the
+    /// others.
+    mixed: Option<NoTag>,
+}
+
+impl<'a> ser::SerializeSeq for ElementsProbe<'a> {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        itoa::write(writer, value).map(|_| ())
+        if self.mixed.is_some() {
+            return Ok(());
+        }
+        match (self.tag.take(), try!(value.serialize(self.probe))) {
+            (None, Ok(tag)) => self.tag = Some(tag.into_owned()),
+            // Most elements have the tag of the ones before, which is kept
+            // rather than worked out again
+            (Some(shared), Ok(ref tag)) if shared == **tag => self.tag = Some(shared),
+            (Some(shared), Ok(tag)) => match shared_element_tag(&shared, &tag, self.probe.ranges) {
+                Some(tag) => self.tag = Some(tag),
+                None => self.mixed = Some(NoTag::Mixed(shared, tag.into_owned())),
+            },
+            (shared, Err(NoTag::Empty(tag))) => {
+                self.tag = shared;
+                match self.empty {
+                    Some(ref deepest) if deepest.len() >= tag.len() => {}
+                    _ => self.empty = Some(tag),
+                }
+            }
+            (_, Err(found)) => self.mixed = Some(found),
+        }
+        Ok(())
     }
 
-    /// Writes a floating point value like `-31.26e+12` to the specified writer.
-    #[inline]
-    fn write_f32<W: ?Sized>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    fn end(self) -> Result<Probed> {
+        Ok(match (self.mixed, self.tag, self.empty) {
+            (Some(found), _, _) => Err(found),
+            (None, Some(tag), Some(empty)) if !holds_empty(&tag, &empty) => {
+                Err(NoTag::Mixed(tag, empty))
+            }
+            (None, Some(tag), _) => Ok(Cow::Owned(format!("A<{}>", tag))),
+            (None, None, Some(empty)) => Err(NoTag::Empty(format!("A<{}>", empty))),
+            (None, None, None) => Err(NoTag::Empty("A<O>".to_owned())),
+        })
+    }
+}
+
+impl<'a> ser::SerializeTuple for ElementsProbe<'a> {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        dtoa::write(writer, value).map(|_| ())
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
-    /// Writes a floating point value like `-31.26e+12` to the specified writer.
-    #[inline]
-    fn write_f64<W: ?Sized>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    fn end(self) -> Result<Probed> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for ElementsProbe<'a> {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        dtoa::write(writer, value).map(|_| ())
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
-    /// Called before each series of `write_string_fragment` and
-    /// `write_char_escape`.  Writes a `"` to the specified writer.
-    #[inline]
-    fn begin_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end(self) -> Result<Probed> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Passes over the contents of an object, for `TagProbe`.
+struct ObjectProbe;
+
+impl ser::SerializeTupleVariant for ObjectProbe {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        writer.write_all(b"\"")
+        Ok(())
     }
 
-    /// Called after each series of `write_string_fragment` and
-    /// `write_char_escape`.  Writes a `"` to the specified writer.
-    #[inline]
-    fn end_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end(self) -> Result<Probed> {
+        probed("O")
+    }
+}
+
+/// Tells whether a map is written as an object or as an array of pairs,
+/// for `TagProbe`.
+struct MapProbe {
+    /// Whether the keys are member names, once the first is seen.
+    keys: Option<bool>,
+}
+
+impl ser::SerializeMap for MapProbe {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        writer.write_all(b"\"")
+        if self.keys.is_none() {
+            self.keys = Some(is_member_name(key));
+        }
+        Ok(())
     }
 
-    /// Writes a string fragment that doesn't need any escaping to the
-    /// specified writer.
-    #[inline]
-    fn write_string_fragment<W: ?Sized>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
     where
-        W: io::Write,
+        T: ser::Serialize,
     {
-        writer.write_all(fragment.as_bytes())
+        Ok(())
     }
 
-    /// Writes a character escape code to the specified writer.
-    #[inline]
+    fn end(self) -> Result<Probed> {
+        match self.keys {
+            Some(false) => Ok(Err(NoTag::Pairs)),
+            _ => probed("O"),
+        }
+    }
+}
+
+impl ser::SerializeStruct for ObjectProbe {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Probed> {
+        probed("O")
+    }
+}
+
+impl ser::SerializeStructVariant for ObjectProbe {
+    type Ok = Probed;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Probed> {
+        probed("O")
+    }
+}
+
+/// Represents a character escape code in a type-safe manner.
+pub enum CharEscape {
+    /// An escaped quote `"`
+    Quote,
+    /// An escaped reverse solidus `\`
+    ReverseSolidus,
+    /// An escaped solidus `/`
+    Solidus,
+    /// An escaped backspace character (usually escaped as `\b`)
+    Backspace,
+    /// An escaped form feed character (usually escaped as `\f`)
+    FormFeed,
+    /// An escaped line feed character (usually escaped as `\n`)
+    LineFeed,
+    /// An escaped carriage return character (usually escaped as `\r`)
+    CarriageReturn,
+    /// An escaped tab character (usually escaped as `\t`)
+    Tab,
+    /// An escaped ASCII plane control character (usually escaped as
+    /// `\u00XX` where `XX` are two hex characters)
+    AsciiControl(u8),
+}
+
+impl CharEscape {
+    #[inline]
+    fn from_escape_table(escape: u8, byte: u8) -> CharEscape {
+        match escape {
+            self::BB => CharEscape::Backspace,
+            self::TT => CharEscape::Tab,
+            self::NN => CharEscape::LineFeed,
+            self::FF => CharEscape::FormFeed,
+            self::RR => CharEscape::CarriageReturn,
+            self::QU => CharEscape::Quote,
+            self::BS => CharEscape::ReverseSolidus,
+            self::U => CharEscape::AsciiControl(byte),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// This trait abstracts away serializing the JSON control characters, which allows the user to
+/// optionally pretty print the JSON output.
+pub trait Formatter {
+    /// Writes a `null` value to the specified writer.
+    #[inline]
+    fn write_null<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"null")
+    }
+
+    /// Writes a `true` or `false` value to the specified writer.
+    #[inline]
+    fn write_bool<W: ?Sized>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let s = if value {
+            b"true" as &[u8]
+        } else {
+            b"false" as &[u8]
+        };
+        writer.write_all(s)
+    }
+
+    /// Writes an integer value like `-123` to the specified writer.
+    #[inline]
+    fn write_i8<W: ?Sized>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `-123` to the specified writer.
+    #[inline]
+    fn write_i16<W: ?Sized>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `-123` to the specified writer.
+    #[inline]
+    fn write_i32<W: ?Sized>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `-123` to the specified writer.
+    #[inline]
+    fn write_i64<W: ?Sized>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `123` to the specified writer.
+    #[inline]
+    fn write_u8<W: ?Sized>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `123` to the specified writer.
+    #[inline]
+    fn write_u16<W: ?Sized>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `123` to the specified writer.
+    #[inline]
+    fn write_u32<W: ?Sized>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes an integer value like `123` to the specified writer.
+    #[inline]
+    fn write_u64<W: ?Sized>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        itoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes a floating point value like `-31.26e+12` to the specified writer.
+    #[inline]
+    fn write_f32<W: ?Sized>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        dtoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes a floating point value like `-31.26e+12` to the specified writer.
+    #[inline]
+    fn write_f64<W: ?Sized>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        dtoa::write(writer, value).map(|_| ())
+    }
+
+    /// Writes a number that has already been rendered to a string, such as an
+    /// exact decimal, to the specified writer.
+    #[inline]
+    fn write_number_str<W: ?Sized>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(value.as_bytes())
+    }
+
+    /// Called before each series of `write_string_fragment` and
+    /// `write_char_escape`.  Writes a `"` to the specified writer.
+    #[inline]
+    fn begin_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"\"")
+    }
+
+    /// Called after each series of `write_string_fragment` and
+    /// `write_char_escape`.  Writes a `"` to the specified writer.
+    #[inline]
+    fn end_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"\"")
+    }
+
+    /// Writes a string fragment that doesn't need any escaping to the
+    /// specified writer.
+    #[inline]
+    fn write_string_fragment<W: ?Sized>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(fragment.as_bytes())
+    }
+
+    /// Writes a character escape code to the specified writer.
+    #[inline]
     fn write_char_escape<W: ?Sized>(
         &mut self,
         writer: &mut W,
@@ -1368,442 +2433,2013 @@ pub trait Formatter {
             }
         };
 
-        writer.write_all(s)
+        writer.write_all(s)
+    }
+
+    /// Called before every array.  Writes a `[` to the specified
+    /// writer.
+    #[inline]
+    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"[")
+    }
+
+    /// Called after every array.  Writes a `]` to the specified
+    /// writer.
+    #[inline]
+    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"]")
+    }
+
+    /// Called before every array value.  Writes a `,` if needed to
+    /// the specified writer.
+    #[inline]
+    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    /// Called after every array value.
+    #[inline]
+    fn end_array_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
+
+    /// Called before every object.  Writes a `{` to the specified
+    /// writer.
+    #[inline]
+    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"{")
+    }
+
+    /// Called after every object.  Writes a `}` to the specified
+    /// writer.
+    #[inline]
+    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b"}")
+    }
+
+    /// Called before every object key.
+    #[inline]
+    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    /// Called after every object key.  A `:` should be written to the
+    /// specified writer by either this method or
+    /// `begin_object_value`.
+    #[inline]
+    fn end_object_key<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
+
+    /// Called before every object value.  A `:` should be written to
+    /// the specified writer by either this method or
+    /// `end_object_key`.
+    #[inline]
+    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b":")
+    }
+
+    /// Called after every object value.
+    #[inline]
+    fn end_object_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
+}
+
+/// This structure compacts a JSON value with no extra whitespace.
+#[derive(Clone, Debug)]
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {}
+
+/// How `PrettyFormatter` lays out the elements of arrays.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArrayWrap {
+    /// Put every array element on its own line (the default).
+    OnePerLine,
+
+    /// Pack the elements of arrays containing only scalars onto lines up to
+    /// the given column width (including indentation). Arrays containing any
+    /// nested array or object are laid out one element per line. Elements
+    /// which are wider than the limit by themselves get a line of their own.
+    Fill(usize),
+}
+
+/// This structure pretty prints a JSON value to make it human readable.
+#[derive(Clone, Debug)]
+pub struct PrettyFormatter<'a> {
+    current_indent: usize,
+    has_value: bool,
+    indent: &'a [u8],
+    array_wrap: ArrayWrap,
+    fill: Option<Vec<Vec<u8>>>,
+    trailing_newline: bool,
+}
+
+impl<'a> PrettyFormatter<'a> {
+    /// Construct a pretty printer formatter that defaults to using two spaces for indentation.
+    pub fn new() -> Self {
+        PrettyFormatter::with_indent(b"  ")
+    }
+
+    /// Construct a pretty printer formatter that uses the `indent` string for indentation.
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        PrettyFormatter {
+            current_indent: 0,
+            has_value: false,
+            indent: indent,
+            array_wrap: ArrayWrap::OnePerLine,
+            fill: None,
+            trailing_newline: false,
+        }
+    }
+
+    /// Set how the elements of arrays are laid out.
+    ///
+    /// ```rust
+    /// # extern crate serde;
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// use serde::Serialize;
+    /// use tjson::Serializer;
+    /// use tjson::ser::{ArrayWrap, PrettyFormatter};
+    ///
+    /// # fn main() {
+    /// let formatter = PrettyFormatter::new().array_wrap(ArrayWrap::Fill(16));
+    /// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    /// tjson!([1, 2, 3, 4, 5, 6, 7]).serialize(&mut ser).unwrap();
+    ///
+    /// let expected = "[\n  1, 2, 3, 4, 5,\n  6, 7\n]";
+    /// assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), expected);
+    /// # }
+    /// ```
+    pub fn array_wrap(mut self, array_wrap: ArrayWrap) -> Self {
+        self.array_wrap = array_wrap;
+        self
+    }
+
+    /// Set whether a newline is written after the toplevel value, so that
+    /// the output is a complete text file. Off by default.
+    ///
+    /// Line breaks are always a bare `\n`, on every platform.
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Called after writing any complete value, to end the output with a
+    /// newline if that value was the toplevel one.
+    fn end_value<W: ?Sized>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if self.trailing_newline && self.current_indent == 0 {
+            writer.write_all(b"\n")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes out the array currently being buffered for `ArrayWrap::Fill`
+    /// one element per line. Called when the array turns out to contain a
+    /// nested container.
+    fn flush_fill<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let elements = match self.fill.take() {
+            Some(elements) => elements,
+            None => return Ok(()),
+        };
+
+        try!(writer.write_all(b"["));
+
+        for (i, element) in elements.iter().enumerate() {
+            if i == 0 {
+                try!(writer.write_all(b"\n"));
+            } else {
+                try!(writer.write_all(b",\n"));
+            }
+            try!(indent(writer, self.current_indent, self.indent));
+            try!(writer.write_all(element));
+        }
+
+        Ok(())
+    }
+
+    /// Writes out a buffered array of scalars, packing its elements onto
+    /// lines no wider than `width` where possible.
+    fn write_filled<W: ?Sized>(
+        &self,
+        writer: &mut W,
+        elements: &[Vec<u8>],
+        width: usize,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if elements.is_empty() {
+            return writer.write_all(b"[]");
+        }
+
+        let prefix = self.indent.len() * (self.current_indent + 1);
+        let mut lines: Vec<&[Vec<u8>]> = Vec::new();
+        let mut start = 0;
+        let mut line_width = 0;
+
+        for (i, element) in elements.iter().enumerate() {
+            // Every line but the last ends with a trailing comma
+            let trailing = if i + 1 < elements.len() { 1 } else { 0 };
+
+            if i > start && line_width + 2 + element.len() + trailing > width {
+                lines.push(&elements[start..i]);
+                start = i;
+            }
+
+            if i == start {
+                line_width = prefix + element.len();
+            } else {
+                line_width += 2 + element.len();
+            }
+        }
+        lines.push(&elements[start..]);
+
+        try!(writer.write_all(b"[\n"));
+
+        for (i, line) in lines.iter().enumerate() {
+            try!(indent(writer, self.current_indent + 1, self.indent));
+
+            for (j, element) in line.iter().enumerate() {
+                if j > 0 {
+                    try!(writer.write_all(b", "));
+                }
+                try!(writer.write_all(element));
+            }
+
+            if i + 1 < lines.len() {
+                try!(writer.write_all(b","));
+            }
+            try!(writer.write_all(b"\n"));
+        }
+
+        try!(indent(writer, self.current_indent, self.indent));
+        writer.write_all(b"]")
+    }
+}
+
+impl<'a> Default for PrettyFormatter<'a> {
+    fn default() -> Self {
+        PrettyFormatter::new()
+    }
+}
+
+/// Forwards a scalar write to the default `Formatter` implementation, either
+/// directly to the writer or into the element currently being buffered for
+/// `ArrayWrap::Fill`. `$ends` is whether the write completes a value.
+macro_rules! pretty_scalar {
+    ($method:ident($($arg:ident: $ty:ty),*), $ends:expr) => {
+        #[inline]
+        fn $method<W: ?Sized>(&mut self, writer: &mut W $(, $arg: $ty)*) -> io::Result<()>
+        where
+            W: io::Write,
+        {
+            match self.fill {
+                Some(ref mut elements) => {
+                    let element = elements.last_mut().expect("array element buffer");
+                    CompactFormatter.$method(element $(, $arg)*)
+                }
+                None => {
+                    try!(CompactFormatter.$method(writer $(, $arg)*));
+                    if $ends { self.end_value(writer) } else { Ok(()) }
+                }
+            }
+        }
+    };
+}
+
+impl<'a> Formatter for PrettyFormatter<'a> {
+    pretty_scalar!(write_null(), true);
+    pretty_scalar!(write_bool(value: bool), true);
+    pretty_scalar!(write_i8(value: i8), true);
+    pretty_scalar!(write_i16(value: i16), true);
+    pretty_scalar!(write_i32(value: i32), true);
+    pretty_scalar!(write_i64(value: i64), true);
+    pretty_scalar!(write_u8(value: u8), true);
+    pretty_scalar!(write_u16(value: u16), true);
+    pretty_scalar!(write_u32(value: u32), true);
+    pretty_scalar!(write_u64(value: u64), true);
+    pretty_scalar!(write_f32(value: f32), true);
+    pretty_scalar!(write_f64(value: f64), true);
+    pretty_scalar!(write_number_str(value: &str), true);
+    pretty_scalar!(begin_string(), false);
+    pretty_scalar!(end_string(), true);
+    pretty_scalar!(write_string_fragment(fragment: &str), false);
+    pretty_scalar!(write_char_escape(char_escape: CharEscape), false);
+
+    #[inline]
+    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        try!(self.flush_fill(writer));
+
+        self.current_indent += 1;
+        self.has_value = false;
+
+        match self.array_wrap {
+            ArrayWrap::Fill(_) => {
+                self.fill = Some(Vec::new());
+                Ok(())
+            }
+            ArrayWrap::OnePerLine => writer.write_all(b"["),
+        }
+    }
+
+    #[inline]
+    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.current_indent -= 1;
+
+        if let Some(elements) = self.fill.take() {
+            let width = match self.array_wrap {
+                ArrayWrap::Fill(width) => width,
+                ArrayWrap::OnePerLine => unreachable!(),
+            };
+            try!(self.write_filled(writer, &elements, width));
+            return self.end_value(writer);
+        }
+
+        if self.has_value {
+            try!(writer.write_all(b"\n"));
+            try!(indent(writer, self.current_indent, self.indent));
+        }
+
+        try!(writer.write_all(b"]"));
+        self.end_value(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if let Some(ref mut elements) = self.fill {
+            elements.push(Vec::new());
+            return Ok(());
+        }
+
+        if first {
+            try!(writer.write_all(b"\n"));
+        } else {
+            try!(writer.write_all(b",\n"));
+        }
+        try!(indent(writer, self.current_indent, self.indent));
+        Ok(())
+    }
+
+    #[inline]
+    fn end_array_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+
+    #[inline]
+    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        try!(self.flush_fill(writer));
+
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    #[inline]
+    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.current_indent -= 1;
+
+        if self.has_value {
+            try!(writer.write_all(b"\n"));
+            try!(indent(writer, self.current_indent, self.indent));
+        }
+
+        try!(writer.write_all(b"}"));
+        self.end_value(writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if first {
+            try!(writer.write_all(b"\n"));
+        } else {
+            try!(writer.write_all(b",\n"));
+        }
+        indent(writer, self.current_indent, self.indent)
+    }
+
+    #[inline]
+    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_all(b": ")
+    }
+
+    #[inline]
+    fn end_object_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn format_escaped_str<W: ?Sized, F: ?Sized>(
+    writer: &mut W,
+    formatter: &mut F,
+    value: &str,
+) -> io::Result<()>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    try!(formatter.begin_string(writer));
+    try!(format_escaped_str_contents(writer, formatter, value));
+    try!(formatter.end_string(writer));
+    Ok(())
+}
+
+fn format_escaped_str_contents<W: ?Sized, F: ?Sized>(
+    writer: &mut W,
+    formatter: &mut F,
+    value: &str,
+) -> io::Result<()>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    let bytes = value.as_bytes();
+
+    let mut start = 0;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escape = ESCAPE[byte as usize];
+        if escape == 0 {
+            continue;
+        }
+
+        if start < i {
+            try!(formatter.write_string_fragment(writer, &value[start..i]));
+        }
+
+        let char_escape = CharEscape::from_escape_table(escape, byte);
+        try!(formatter.write_char_escape(writer, char_escape));
+
+        start = i + 1;
+    }
+
+    if start != bytes.len() {
+        try!(formatter.write_string_fragment(writer, &value[start..]));
+    }
+
+    Ok(())
+}
+
+const BB: u8 = b'b'; // \x08
+const TT: u8 = b't'; // \x09
+const NN: u8 = b'n'; // \x0A
+const FF: u8 = b'f'; // \x0C
+const RR: u8 = b'r'; // \x0D
+const QU: u8 = b'"'; // \x22
+const BS: u8 = b'\\'; // \x5C
+const U: u8 = b'u'; // \x00...\x1F except the ones above
+
+// Lookup table of escape sequences. A value of b'x' at index i means that byte
+// i is escaped as "\x" in JSON. A value of 0 means that byte i is not escaped.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static ESCAPE: [u8; 256] = [
+    //  1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
+    U,  U,  U,  U,  U,  U,  U,  U, BB, TT, NN,  U, FF, RR,  U,  U, // 0
+    U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U, // 1
+    0,  0, QU,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 2
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 3
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 4
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, BS,  0,  0,  0, // 5
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 6
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 7
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 8
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 9
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // A
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // B
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // C
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // D
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // E
+    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // F
+];
+
+#[inline]
+fn format_escaped_char<W: ?Sized, F: ?Sized>(
+    wr: &mut W,
+    formatter: &mut F,
+    value: char,
+) -> io::Result<()>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    use std::io::Write;
+    // A char encoded as UTF-8 takes 4 bytes at most.
+    let mut buf = [0; 4];
+    write!(&mut buf[..], "{}", value).expect("write char to 4-byte buffer");
+    // Writing a char successfully always produce valid UTF-8.
+    // Once we do not support Rust <1.15 we will be able to just use
+    // the method `char::encode_utf8`.
+    // See https://github.com/serde-rs/json/issues/270.
+    let slice = unsafe { str::from_utf8_unchecked(&buf[0..value.len_utf8()]) };
+    format_escaped_str(wr, formatter, slice)
+}
+
+/// Serialize the given data structure as JSON into the IO stream. Returns
+/// the number of bytes written, which is the length `to_vec` would give.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't. Part of the document may have been written by
+/// then; `Serializer::bytes_written` tells how much.
+#[inline]
+pub fn to_writer<W, T: ?Sized>(writer: W, value: &T) -> Result<usize>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::new(writer);
+    try!(value.serialize(&mut ser));
+    Ok(ser.bytes_written())
+}
+
+/// Serialize the given data structure as pretty-printed JSON into the IO
+/// stream. Returns the number of bytes written.
+///
+/// # Errors
+///
+/// Fails for the same reasons as `to_writer`.
+#[inline]
+pub fn to_writer_pretty<W, T: ?Sized>(writer: W, value: &T) -> Result<usize>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::pretty(writer);
+    try!(value.serialize(&mut ser));
+    Ok(ser.bytes_written())
+}
+
+/// Serialize the given data structure as a JSON byte vector.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+#[inline]
+pub fn to_vec<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+where
+    T: ser::Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer(&mut writer, value));
+    Ok(writer)
+}
+
+// Not public API. Should be pub(crate).
+/// The name `to_vec` writes for a member named `name` holding `value`,
+/// tagged as `Serializer::infer_tags` does by default.
+#[doc(hidden)]
+pub fn tagged_member_name<T: ?Sized>(name: &str, value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    let mut name = name.to_owned();
+    try!(Serializer::new(io::sink()).tag_name(&mut name, value));
+    Ok(name)
+}
+
+// Not public API. Should be pub(crate).
+/// Serializes `value` as `to_vec` does where it is the value of a member
+/// with this tagged name, or an element of it.
+#[doc(hidden)]
+pub fn to_vec_in_member<T: ?Sized>(name: &str, value: &T) -> Result<Vec<u8>>
+where
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::new(Vec::with_capacity(128));
+    ser.data = data_encoding(name);
+    ser.integers = is_integer(name);
+    ser.widen = is_float(name);
+    try!(value.serialize(&mut ser));
+    Ok(ser.into_inner())
+}
+
+/// Serialize the given data structure as a pretty-printed JSON byte vector.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+#[inline]
+pub fn to_vec_pretty<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+where
+    T: ser::Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer_pretty(&mut writer, value));
+    Ok(writer)
+}
+
+/// Serialize the given data structure as a String of JSON.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+#[inline]
+pub fn to_string<T: ?Sized>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    let vec = try!(to_vec(value));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Serialize the given data structure as a pretty-printed String of JSON.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+#[inline]
+pub fn to_string_pretty<T: ?Sized>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    let vec = try!(to_vec_pretty(value));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Serialize the given data structure as a pretty-printed String of JSON in a
+/// layout which is guaranteed not to change, for snapshot and golden file
+/// tests.
+///
+/// The output is what `to_string_pretty` produces today, plus a trailing
+/// newline: each array element and object member on its own line, indented
+/// by two spaces per level, with `": "` between names and values and empty
+/// containers written as `[]` and `{}`. Lines always end in a bare `\n`,
+/// never `\r\n`, and carriage returns inside strings are escaped as `\r`.
+///
+/// Any change to this output is a breaking change, whatever happens to the
+/// defaults of `to_string_pretty` and `PrettyFormatter`. The exact output is
+/// pinned by the golden files in the crate's `tests/golden` directory.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let output = tjson::to_string_pretty_stable(&tjson!({ "a": [{}, { "b": 1 }] })).unwrap();
+/// assert_eq!(
+///     output,
+///     "{\n  \"a:A<O>\": [\n    {},\n    {\n      \"b:i\": \"1\"\n    }\n  ]\n}\n"
+/// );
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't.
+pub fn to_string_pretty_stable<T: ?Sized>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    let formatter = PrettyFormatter::with_indent(b"  ")
+        .array_wrap(ArrayWrap::OnePerLine)
+        .trailing_newline(true);
+    let mut ser = Serializer::with_formatter(Vec::with_capacity(128), formatter);
+    try!(value.serialize(&mut ser));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(ser.into_inner())
+    };
+    Ok(string)
+}
+
+fn indent<W: ?Sized>(wr: &mut W, n: usize, s: &[u8]) -> io::Result<()>
+where
+    W: io::Write,
+{
+    for _ in 0..n {
+        try!(wr.write_all(s));
+    }
+
+    Ok(())
+}
+
+/// Settings for `to_writer_with`, `to_vec_with` and `to_string_with`.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::schema;
+/// use tjson::ser::SerializeConfig;
+///
+/// let schema = schema::infer(&[tjson!({ "id": 1, "name": "alice" })]);
+/// let config = SerializeConfig::new().validate_against(&schema);
+///
+/// let output = tjson::to_string_with(&config, &tjson!({ "id": 2, "name": "bob" })).unwrap();
+/// assert_eq!(output, r#"{"id:i":"2","name:s":"bob"}"#);
+///
+/// let mut writer = Vec::new();
+/// let err = tjson::to_writer_with(&mut writer, &config, &tjson!({ "id": "3" })).unwrap_err();
+/// assert_eq!(err.to_string(), "schema violation: expected number, found string at /id");
+/// assert!(writer.is_empty());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerializeConfig<'a> {
+    schema: Option<&'a Schema>,
+    max_output_bytes: Option<usize>,
+    sort_keys: Option<KeySortRule>,
+    duplicate_elements: Option<DuplicateElements>,
+    tags: TagPolicy,
+    floats: FloatFormat,
+    /// Whether integers tagged `i` or `u` are written as JSON numbers.
+    bare_integers: bool,
+}
+
+impl<'a> SerializeConfig<'a> {
+    /// Settings which write values as `to_writer` does.
+    pub fn new() -> Self {
+        SerializeConfig::default()
+    }
+
+    /// Check each value against the given schema before writing it, and fail
+    /// without writing anything if it doesn't match.
+    ///
+    /// The whole document is converted to a `Value` to be checked, and that
+    /// `Value` is what is written, so the bytes written are the ones which
+    /// were checked. Its members are in the order of the `Map` type, which
+    /// is name order unless the `preserve_order` feature is enabled. It is
+    /// serialized into a buffer which is only written out once complete.
+    /// Writing a document this way takes roughly ten times as long as
+    /// writing it unchecked, mostly spent building the `Value`.
+    pub fn validate_against(mut self, schema: &'a Schema) -> Self {
+        self.schema = Some(schema);
+        self
     }
 
-    /// Called before every array.  Writes a `[` to the specified
-    /// writer.
-    #[inline]
-    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b"[")
+    /// Fail with an `ErrorCode::OutputBudgetExceeded` error as soon as the
+    /// document would be longer than this many bytes, rather than once it
+    /// has all been written.
+    ///
+    /// The budget is checked on each write the serializer makes, and a write
+    /// which would take the output over it is not passed on, so no more
+    /// than `max` bytes ever reach the writer. A document of exactly `max`
+    /// bytes is written in full.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::SerializeConfig;
+    ///
+    /// let config = SerializeConfig::new().max_output_bytes(25);
+    /// let value = tjson!({ "logs": ["started", "stopped"] });
+    ///
+    /// let err = tjson::to_vec_with(&config, &value).unwrap_err();
+    /// let exceeded = err.output_budget_exceeded().unwrap();
+    /// assert_eq!(exceeded.path(), "/logs:A<s>/1");
+    /// assert_eq!(exceeded.written(), 25);
+    /// # }
+    /// ```
+    pub fn max_output_bytes(mut self, max: usize) -> Self {
+        self.max_output_bytes = Some(max);
+        self
     }
 
-    /// Called after every array.  Writes a `]` to the specified
-    /// writer.
-    #[inline]
-    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b"]")
+    /// Set the order the members of objects are written in.
+    ///
+    /// With `KeyOrder::Sorted`, the whole document is converted to a `Value`
+    /// and each object's members are sorted as they are written, so the
+    /// order doesn't depend on whether `Map` keeps insertion order or sorts
+    /// its keys as strings.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig};
+    ///
+    /// let value = tjson!({ "a:s": "x", "a-b:s": "y" });
+    ///
+    /// let config = SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName));
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"a:s":"x","a-b:s":"y"}"#);
+    ///
+    /// let config = SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::TaggedName));
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"a-b:s":"y","a:s":"x"}"#);
+    /// # }
+    /// ```
+    pub fn key_order(mut self, order: KeyOrder) -> Self {
+        self.sort_keys = match order {
+            KeyOrder::AsGiven => None,
+            KeyOrder::Sorted(rule) => Some(rule),
+        };
+        self
     }
 
-    /// Called before every array value.  Writes a `,` if needed to
-    /// the specified writer.
-    #[inline]
-    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        if first {
-            Ok(())
-        } else {
-            writer.write_all(b",")
-        }
+    /// Handle sets with more than one equal element according to the given
+    /// policy, as `Serializer::duplicate_elements` does. By default a
+    /// duplicate is an error.
+    pub fn duplicate_elements(mut self, policy: DuplicateElements) -> Self {
+        self.duplicate_elements = Some(policy);
+        self
     }
 
-    /// Called after every array value.
-    #[inline]
-    fn end_array_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        Ok(())
+    /// Write all data with this encoding and its tag, whatever data tag the
+    /// member it is in was given.
+    ///
+    /// Data is the value of a member tagged `d`, `d16`, `d32` or `d64`, or
+    /// an element of one tagged `A<d>` and so on. It may be a `Value::Data`, a
+    /// sequence of bytes such as a `Vec<u8>`, or a string already encoded
+    /// for its tag, which is decoded and encoded again. The tag is rewritten
+    /// in the member's name, so `"key:d16"` may be written as `"key:d64"`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{DataEncoding, SerializeConfig};
+    ///
+    /// let config = SerializeConfig::new().default_data_encoding(DataEncoding::Base64Url);
+    /// let value = tjson!({ "key:d16": "fbff", "raw:d": [251, 255] });
+    ///
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"key:d64":"-_8","raw:d64":"-_8"}"#);
+    /// # }
+    /// ```
+    pub fn default_data_encoding(mut self, encoding: DataEncoding) -> Self {
+        self.tags.data = Some(encoding);
+        self
     }
 
-    /// Called before every object.  Writes a `{` to the specified
-    /// writer.
-    #[inline]
-    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b"{")
+    /// Write data given as an array of bytes, such as a `Vec<u8>`, as a
+    /// string in the encoding of its tag, such as unpadded base64url for `d`
+    /// and lowercase hex for `d16`. Byte strings, such as a `Value::Data` or
+    /// a `serde_bytes::ByteBuf`, are written that way whether or not this is
+    /// on.
+    ///
+    /// Data is as `default_data_encoding` describes it, and a string already
+    /// encoded for its tag is checked and written as it is. Fails, naming
+    /// the JSON pointer of the value, on elements which aren't bytes or
+    /// text which isn't validly encoded.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::Value;
+    /// use tjson::ser::SerializeConfig;
+    ///
+    /// let config = SerializeConfig::new().encode_data(true);
+    /// let raw = Value::Data(b"BINARY".to_vec().into());
+    /// let value = tjson!({ "hex:d16": [251, 255], "raw:d": raw });
+    ///
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"hex:d16":"fbff","raw:d":"QklOQVJZ"}"#);
+    ///
+    /// // Without the option only byte strings are encoded
+    /// let output = tjson::to_string(&value).unwrap();
+    /// assert_eq!(output, r#"{"hex:d16":[251,255],"raw:d":"QklOQVJZ"}"#);
+    /// # }
+    /// ```
+    pub fn encode_data(mut self, enabled: bool) -> Self {
+        self.tags.encode = enabled;
+        self
     }
 
-    /// Called after every object.  Writes a `}` to the specified
-    /// writer.
-    #[inline]
-    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b"}")
+    /// Set how integers are tagged. With `IntegerTag::AlwaysSigned`, members
+    /// tagged `u` are written tagged `i`, and an integer above `i64::MAX`
+    /// tagged either way is an error naming its JSON pointer.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{IntegerTag, SerializeConfig};
+    ///
+    /// let config = SerializeConfig::new().integer_tag_policy(IntegerTag::AlwaysSigned);
+    ///
+    /// let output = tjson::to_string_with(&config, &tjson!({ "ids:A<u>": [1, 2] })).unwrap();
+    /// assert_eq!(output, r#"{"ids:A<i>":["1","2"]}"#);
+    ///
+    /// let err = tjson::to_string_with(&config, &tjson!({ "n:u": u64::max_value() }));
+    /// assert_eq!(
+    ///     err.unwrap_err().to_string(),
+    ///     "integer 18446744073709551615 is above i64::MAX and can't be tagged `i` at /n:u"
+    /// );
+    /// # }
+    /// ```
+    pub fn integer_tag_policy(mut self, policy: IntegerTag) -> Self {
+        self.tags.signed = policy == IntegerTag::AlwaysSigned;
+        self
     }
 
-    /// Called before every object key.
-    #[inline]
-    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        if first {
-            Ok(())
-        } else {
-            writer.write_all(b",")
-        }
+    /// Write integers tagged `i` or `u` as strings, like `"age:i": "42"`, as
+    /// TJSON does so that values beyond 2^53 survive consumers which read
+    /// all numbers as doubles. On by default, as `Serializer::quoted_integers`
+    /// is; turning it off writes them as JSON numbers.
+    /// `Deserializer::quoted_integers` reads them back. Floats are written as
+    /// numbers, and so are integers under `f`, as floats.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::SerializeConfig;
+    ///
+    /// let value = tjson!({ "id:u": u64::max_value(), "ids:A<i>": [-1], "ratio:f": 2 });
+    ///
+    /// let output = tjson::to_string(&value).unwrap();
+    /// assert_eq!(output, r#"{"id:u":"18446744073709551615","ids:A<i>":["-1"],"ratio:f":2.0}"#);
+    ///
+    /// let config = SerializeConfig::new().quoted_integers(false);
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"id:u":18446744073709551615,"ids:A<i>":[-1],"ratio:f":2.0}"#);
+    /// # }
+    /// ```
+    pub fn quoted_integers(mut self, enabled: bool) -> Self {
+        self.bare_integers = !enabled;
+        self
     }
 
-    /// Called after every object key.  A `:` should be written to the
-    /// specified writer by either this method or
-    /// `begin_object_value`.
-    #[inline]
-    fn end_object_key<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        Ok(())
+    /// Write timestamps with this many digits of the fraction of a second,
+    /// truncating any further digits. By default a timestamp has a fraction
+    /// only if it has a fraction of a second, with as many digits as that
+    /// takes.
+    ///
+    /// Timestamps are the values of members tagged `t`, or elements of ones
+    /// tagged `A<t>` and so on, which may be `Value::Timestamp`s or RFC 3339
+    /// strings such as `DateTime` fields serialize as.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{SerializeConfig, TimestampPrecision};
+    ///
+    /// let config = SerializeConfig::new().timestamp_precision(TimestampPrecision::Millis);
+    /// let value = tjson!({ "at:t": "2017-03-04T05:06:07.123456+01:00" });
+    ///
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, r#"{"at:t":"2017-03-04T04:06:07.123Z"}"#);
+    /// # }
+    /// ```
+    pub fn timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.tags.precision = Some(precision);
+        self
     }
 
-    /// Called before every object value.  A `:` should be written to
-    /// the specified writer by either this method or
-    /// `end_object_key`.
-    #[inline]
-    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b":")
+    /// Choose how member names without a tag are written.
+    ///
+    /// By default (`TagInference::WhereKnown`) each is given the tag its
+    /// value calls for, as `Serializer::infer_tags` does, so that `"age"`
+    /// holding a `u64` is written as `"age:u"` and `"ids"` holding an empty
+    /// array as `"ids:A<O>"`, and the name of a value with no tag, `null` or
+    /// an array whose elements have different tags, is left untagged. Names
+    /// which already have a tag keep it, and their values must be what it
+    /// says. Timestamps are tagged `t` if they are `Value::Timestamp`s or
+    /// fields written with `tjson::timestamp`; other timestamps are
+    /// serialized as strings and so tagged `s`.
+    ///
+    /// `TagInference::Required` makes a value with no tag an error, and
+    /// `TagInference::Off` leaves every name as it is.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde;
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{SerializeConfig, TagInference};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Person {
+    ///     age: u64,
+    ///     name: String,
+    ///     #[serde(rename = "scores:A<f>")]
+    ///     scores: Vec<f64>,
+    /// }
+    ///
+    /// let person = Person { age: 43, name: "John".to_owned(), scores: vec![1.0] };
+    /// let output = tjson::to_string(&person).unwrap();
+    /// assert_eq!(output, r#"{"age:u":"43","name:s":"John","scores:A<f>":[1.0]}"#);
+    ///
+    /// let config = SerializeConfig::new().infer_tags(TagInference::Off);
+    /// let output = tjson::to_string_with(&config, &person).unwrap();
+    /// assert_eq!(output, r#"{"age":43,"name":"John","scores:A<f>":[1.0]}"#);
+    ///
+    /// let ids = tjson!({ "ids": [1, "2"] });
+    /// assert_eq!(tjson::to_string(&ids).unwrap(), r#"{"ids":[1,"2"]}"#);
+    ///
+    /// let config = SerializeConfig::new().infer_tags(TagInference::Required);
+    /// let err = tjson::to_string_with(&config, &ids).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "member \"ids\" can't be tagged, as its value is an array with elements \
+    ///      tagged `i` and `s`"
+    /// );
+    /// # }
+    /// ```
+    pub fn infer_tags(mut self, inference: TagInference) -> Self {
+        self.tags.infer = Some(inference);
+        self
     }
 
-    /// Called after every object value.
-    #[inline]
-    fn end_object_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        Ok(())
+    /// Fail on a member name which already has a tag, as
+    /// `Serializer::reject_tagged_names` does, naming its JSON pointer
+    /// where values are rewritten to other tags. Off by default.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{IntegerTag, SerializeConfig};
+    ///
+    /// let value = tjson!({ "a": { "n:u": 1 } });
+    /// let config = SerializeConfig::new().reject_tagged_names(true);
+    /// let err = tjson::to_string_with(&config, &value).unwrap_err();
+    /// assert_eq!(err.to_string(), "member name \"n:u\" already has a tag");
+    ///
+    /// let config = config.integer_tag_policy(IntegerTag::AlwaysSigned);
+    /// let err = tjson::to_string_with(&config, &value).unwrap_err();
+    /// assert_eq!(err.to_string(), "member name \"n:u\" already has a tag at /a/n:u");
+    /// # }
+    /// ```
+    pub fn reject_tagged_names(mut self, enabled: bool) -> Self {
+        self.tags.reject_tagged = enabled;
+        self
+    }
+
+    /// Write floats in exponent notation only as the given policy allows, as
+    /// `Serializer::exponent_notation` does.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::ser::{ExponentPolicy, SerializeConfig};
+    ///
+    /// let value = tjson!([1e-7, 1e21, 2.5e-3]);
+    ///
+    /// let config = SerializeConfig::new().exponent_notation(ExponentPolicy::Never);
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, "[0.0000001,1000000000000000000000.0,0.0025]");
+    ///
+    /// let policy = ExponentPolicy::Threshold { min_exp: -2, max_exp: 3 };
+    /// let config = SerializeConfig::new().exponent_notation(policy);
+    /// let output = tjson::to_string_with(&config, &value).unwrap();
+    /// assert_eq!(output, "[1e-7,1e21,2.5e-3]");
+    /// # }
+    /// ```
+    pub fn exponent_notation(mut self, policy: ExponentPolicy) -> Self {
+        self.floats.exponent = policy;
+        self
+    }
+
+    /// Fail rather than write a float without an exponent if that would
+    /// take more than `max` bytes, as `Serializer::max_float_len` does.
+    pub fn max_float_len(mut self, max: usize) -> Self {
+        self.floats.max_len = max;
+        self
     }
 }
 
-/// This structure compacts a JSON value with no extra whitespace.
-#[derive(Clone, Debug)]
-pub struct CompactFormatter;
+/// The encoding of data written with `SerializeConfig::default_data_encoding`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataEncoding {
+    /// Base64url, tagged `d`.
+    Plain,
 
-impl Formatter for CompactFormatter {}
+    /// Hex, tagged `d16`.
+    Base16,
 
-/// This structure pretty prints a JSON value to make it human readable.
-#[derive(Clone, Debug)]
-pub struct PrettyFormatter<'a> {
-    current_indent: usize,
-    has_value: bool,
-    indent: &'a [u8],
+    /// Base32, tagged `d32`.
+    Base32,
+
+    /// Base64url, tagged `d64`.
+    Base64Url,
 }
 
-impl<'a> PrettyFormatter<'a> {
-    /// Construct a pretty printer formatter that defaults to using two spaces for indentation.
-    pub fn new() -> Self {
-        PrettyFormatter::with_indent(b"  ")
+impl DataEncoding {
+    /// The tag of data in this encoding.
+    pub fn tag(self) -> &'static str {
+        match self {
+            DataEncoding::Plain => "d",
+            DataEncoding::Base16 => "d16",
+            DataEncoding::Base32 => "d32",
+            DataEncoding::Base64Url => "d64",
+        }
     }
 
-    /// Construct a pretty printer formatter that uses the `indent` string for indentation.
-    pub fn with_indent(indent: &'a [u8]) -> Self {
-        PrettyFormatter {
-            current_indent: 0,
-            has_value: false,
-            indent: indent,
+    /// The encoding of data with this tag, one of `d`, `d16`, `d32` and
+    /// `d64`.
+    fn for_tag(tag: &str) -> Self {
+        match tag {
+            "d16" => DataEncoding::Base16,
+            "d32" => DataEncoding::Base32,
+            "d64" => DataEncoding::Base64Url,
+            _ => DataEncoding::Plain,
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> String {
+        match self {
+            DataEncoding::Base16 => encoding::hex_encode(data),
+            DataEncoding::Base32 => encoding::b32_encode(data),
+            DataEncoding::Plain | DataEncoding::Base64Url => encoding::b64url_encode(data),
         }
     }
 }
 
-impl<'a> Default for PrettyFormatter<'a> {
+/// How integers are tagged, for `SerializeConfig::integer_tag_policy`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegerTag {
+    /// With the tags given in member names (the default).
+    Natural,
+
+    /// Always tagged `i`, for consumers which don't read `u`.
+    AlwaysSigned,
+}
+
+/// How member names without a tag are tagged, for `Serializer::infer_tags`
+/// and `SerializeConfig::infer_tags`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TagInference {
+    /// With the tag of their value, failing if it has none.
+    Required,
+
+    /// With the tag of their value if it has one, leaving them untagged
+    /// otherwise (the default).
+    WhereKnown,
+
+    /// Not at all, writing every name as it is without checking its tag.
+    Off,
+}
+
+/// The digits of the fraction of a second written by
+/// `SerializeConfig::timestamp_precision`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimestampPrecision {
+    /// Whole seconds, with no fraction.
+    Seconds,
+
+    /// Three digits.
+    Millis,
+
+    /// Six digits.
+    Micros,
+
+    /// Nine digits.
+    Nanos,
+}
+
+impl TimestampPrecision {
+    fn format(self, timestamp: &DateTime) -> String {
+        let format = match self {
+            TimestampPrecision::Seconds => "%Y-%m-%dT%H:%M:%SZ",
+            TimestampPrecision::Millis => "%Y-%m-%dT%H:%M:%S%.3fZ",
+            TimestampPrecision::Micros => "%Y-%m-%dT%H:%M:%S%.6fZ",
+            TimestampPrecision::Nanos => "%Y-%m-%dT%H:%M:%S%.9fZ",
+        };
+        timestamp.format(format).to_string()
+    }
+}
+
+/// When floats are written in exponent notation, for
+/// `SerializeConfig::exponent_notation`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExponentPolicy {
+    /// Where the shortest representation uses it, as in `1e-7` and `1e21`
+    /// (the default).
+    Allow,
+
+    /// Never, so `1e-7` is written `0.0000001`.
+    Never,
+
+    /// For floats whose decimal exponent, `e` in `d.ddd * 10^e`, is outside
+    /// `min_exp..=max_exp`. Others are written without one.
+    Threshold {
+        /// The smallest exponent written without exponent notation.
+        min_exp: i32,
+
+        /// The largest exponent written without exponent notation.
+        max_exp: i32,
+    },
+}
+
+/// How `Serializer` writes floats.
+#[derive(Copy, Clone, Debug)]
+struct FloatFormat {
+    exponent: ExponentPolicy,
+    max_len: usize,
+}
+
+impl Default for FloatFormat {
     fn default() -> Self {
-        PrettyFormatter::new()
+        FloatFormat {
+            exponent: ExponentPolicy::Allow,
+            max_len: 64,
+        }
     }
 }
 
-impl<'a> Formatter for PrettyFormatter<'a> {
-    #[inline]
-    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.current_indent += 1;
-        self.has_value = false;
-        writer.write_all(b"[")
+impl FloatFormat {
+    /// Writes `value` with its shortest digits, as `dtoa` does, with or
+    /// without an exponent as the policy says.
+    fn format<V: dtoa::Floating>(&self, value: V) -> Result<String> {
+        let mut buf = Vec::with_capacity(32);
+        try!(dtoa::write(&mut buf, value).map_err(Error::io));
+        // dtoa writes only ASCII.
+        let text = String::from_utf8_lossy(&buf).into_owned();
+        let has_exponent = text.contains('e');
+
+        let decimal = ShortestDecimal::parse(&text);
+        let plain = match self.exponent {
+            ExponentPolicy::Allow => return Ok(text),
+            ExponentPolicy::Never => true,
+            ExponentPolicy::Threshold { min_exp, max_exp } => {
+                min_exp <= decimal.exponent && decimal.exponent <= max_exp
+            }
+        };
+        if !plain {
+            return Ok(if has_exponent { text } else { decimal.scientific() });
+        }
+
+        let expanded = if has_exponent { decimal.plain() } else { text };
+        if expanded.len() > self.max_len {
+            return Err(ser::Error::custom(format_args!(
+                "float {} would be {} bytes long without an exponent, more than the {} allowed",
+                String::from_utf8_lossy(&buf),
+                expanded.len(),
+                self.max_len
+            )));
+        }
+        Ok(expanded)
     }
+}
 
-    #[inline]
-    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.current_indent -= 1;
+/// The shortest digits of a float, as `dtoa` writes them, and where the
+/// decimal point goes among them.
+struct ShortestDecimal {
+    negative: bool,
+    /// Without leading or trailing zeros, so empty for zero.
+    digits: String,
+    /// The exponent of the first digit, so the value is `d.ddd * 10^exponent`.
+    exponent: i32,
+}
 
-        if self.has_value {
-            try!(writer.write_all(b"\n"));
-            try!(indent(writer, self.current_indent, self.indent));
+impl ShortestDecimal {
+    fn parse(text: &str) -> Self {
+        let negative = text.starts_with('-');
+        let text = text.trim_start_matches('-');
+        let (mantissa, exponent) = match text.find('e') {
+            Some(e) => (&text[..e], text[e + 1..].parse().unwrap_or(0)),
+            None => (text, 0),
+        };
+        let point = mantissa.find('.').unwrap_or(mantissa.len()) as i32;
+
+        let all: String = mantissa.chars().filter(|&c| c != '.').collect();
+        let leading = all.len() - all.trim_start_matches('0').len();
+        let digits = all[leading..].trim_end_matches('0').to_owned();
+        let exponent = if digits.is_empty() {
+            0
+        } else {
+            point - leading as i32 - 1 + exponent
+        };
+        ShortestDecimal {
+            negative: negative,
+            digits: digits,
+            exponent: exponent,
         }
+    }
 
-        writer.write_all(b"]")
+    /// Like `1.5e-7`, as `dtoa` writes exponent notation.
+    fn scientific(&self) -> String {
+        let mut out = String::new();
+        if self.negative {
+            out.push('-');
+        }
+        if self.digits.is_empty() {
+            out.push_str("0.0");
+            return out;
+        }
+        out.push_str(&self.digits[..1]);
+        if self.digits.len() > 1 {
+            out.push('.');
+            out.push_str(&self.digits[1..]);
+        }
+        out.push('e');
+        out.push_str(&self.exponent.to_string());
+        out
     }
 
-    #[inline]
-    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        if first {
-            try!(writer.write_all(b"\n"));
+    /// Like `0.00000015`, with `.0` after whole numbers so they still read
+    /// as floats.
+    fn plain(&self) -> String {
+        let mut out = String::new();
+        if self.negative {
+            out.push('-');
+        }
+        if self.digits.is_empty() {
+            out.push_str("0.0");
+        } else if self.exponent < 0 {
+            out.push_str("0.");
+            out.push_str(&"0".repeat((-self.exponent - 1) as usize));
+            out.push_str(&self.digits);
         } else {
-            try!(writer.write_all(b",\n"));
+            let whole = self.exponent as usize + 1;
+            if self.digits.len() > whole {
+                out.push_str(&self.digits[..whole]);
+                out.push('.');
+                out.push_str(&self.digits[whole..]);
+            } else {
+                out.push_str(&self.digits);
+                out.push_str(&"0".repeat(whole - self.digits.len()));
+                out.push_str(".0");
+            }
         }
-        try!(indent(writer, self.current_indent, self.indent));
-        Ok(())
+        out
     }
+}
 
-    #[inline]
-    fn end_array_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.has_value = true;
-        Ok(())
+/// The tags and encodings `SerializeConfig` rewrites values to.
+#[derive(Clone, Debug, Default)]
+struct TagPolicy {
+    data: Option<DataEncoding>,
+    signed: bool,
+    precision: Option<TimestampPrecision>,
+    /// How untagged member names are given the tag their value calls for,
+    /// if not as `TagInference::WhereKnown`.
+    infer: Option<TagInference>,
+    /// Whether data is written in the encoding of its tag.
+    encode: bool,
+    /// Whether a member name which already has a tag is an error.
+    reject_tagged: bool,
+}
+
+impl TagPolicy {
+    /// Whether values are written as they are. Names are tagged as they
+    /// are written, unless values are rewritten first.
+    fn is_empty(&self) -> bool {
+        self.data.is_none() && !self.signed && self.precision.is_none() && !self.encode
     }
 
-    #[inline]
-    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.current_indent += 1;
-        self.has_value = false;
-        writer.write_all(b"{")
+    fn inference(&self) -> TagInference {
+        self.infer.unwrap_or(TagInference::WhereKnown)
     }
 
-    #[inline]
-    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.current_indent -= 1;
+    /// The tag written in place of `tag`.
+    fn tag(&self, tag: &str) -> String {
+        match (tag, self.data) {
+            ("d", Some(encoding)) |
+            ("d16", Some(encoding)) |
+            ("d32", Some(encoding)) |
+            ("d64", Some(encoding)) => encoding.tag().to_owned(),
+            ("u", _) if self.signed => "i".to_owned(),
+            _ => match element_tag(tag) {
+                Some(element) => format!("{}{}>", &tag[..2], self.tag(element)),
+                None => tag.to_owned(),
+            },
+        }
+    }
 
-        if self.has_value {
-            try!(writer.write_all(b"\n"));
-            try!(indent(writer, self.current_indent, self.indent));
+    /// Rewrites a value with the given tag, and the members of objects in
+    /// it, which are at `path`.
+    fn apply(&self, value: Value, tag: &str, path: &mut String) -> Result<Value> {
+        match (value, tag) {
+            (Value::Object(map), _) => {
+                let mut members = Map::new();
+                for (name, member) in map {
+                    let len = path.len();
+                    push_pointer_token(path, &name);
+                    let inferred = match valid_tag(&name) {
+                        Some(_) if self.reject_tagged => {
+                            return Err(policy_error(already_tagged(&name), path));
+                        }
+                        Some(_) => None,
+                        None if self.inference() == TagInference::Off => None,
+                        None => match try!(probe_tag(&member)).0 {
+                            Ok(tag) => Some(tag.into_owned()),
+                            Err(NoTag::Empty(tag)) => Some(tag),
+                            Err(_) if self.inference() == TagInference::WhereKnown => None,
+                            Err(found) => {
+                                let msg = format_args!(
+                                    "member {:?} can't be tagged, as its value is {}",
+                                    name,
+                                    found
+                                );
+                                return Err(policy_error(msg, path));
+                            }
+                        },
+                    };
+                    let (member, written) = match (inferred, valid_tag(&name)) {
+                        (Some(tag), _) => {
+                            let member = try!(self.apply(::json::widen(member, &tag), &tag, path));
+                            (member, format!("{}:{}", name, self.tag(&tag)))
+                        }
+                        (None, Some(tag)) => {
+                            let untagged = &name[..name.len() - tag.len() - 1];
+                            let member = try!(self.apply(member, tag, path));
+                            (member, format!("{}:{}", untagged, self.tag(tag)))
+                        }
+                        (None, None) => (try!(self.apply(member, "", path)), name.clone()),
+                    };
+                    path.truncate(len);
+
+                    if members.contains_key(&written) {
+                        let msg = format_args!("two members would be written as {:?}", written);
+                        return Err(policy_error(msg, path));
+                    }
+                    members.insert(written, member);
+                }
+                Ok(Value::Object(members))
+            }
+            (value, "d") | (value, "d16") | (value, "d32") | (value, "d64")
+                if self.data.is_some() || self.encode => {
+                let encoding = self.data.unwrap_or_else(|| DataEncoding::for_tag(tag));
+                let data = match value {
+                    Value::Data(data) => data.into_vec(),
+                    Value::String(ref text) => {
+                        try!(transform::decode(tag, text).map_err(|err| policy_error(err, path)))
+                    }
+                    Value::Array(ref elements) => {
+                        let bytes = elements.iter().map(|element| match element.as_u64() {
+                            Some(b) if b <= 255 => Some(b as u8),
+                            _ => None,
+                        });
+                        match bytes.collect() {
+                            Some(data) => data,
+                            None => return Err(policy_error("data must be bytes", path)),
+                        }
+                    }
+                    value => return Ok(value),
+                };
+                Ok(Value::String(encoding.encode(&data)))
+            }
+            (Value::Number(n), "u") | (Value::Number(n), "i") if self.signed => {
+                match n.as_u64() {
+                    Some(u) if n.as_i64().is_none() => {
+                        let msg = format_args!(
+                            "integer {} is above i64::MAX and can't be tagged `i`",
+                            u
+                        );
+                        Err(policy_error(msg, path))
+                    }
+                    _ => Ok(Value::Number(n)),
+                }
+            }
+            (Value::Timestamp(timestamp), "t") if self.precision.is_some() => {
+                Ok(Value::String(self.precision.unwrap().format(&timestamp)))
+            }
+            (Value::String(text), "t") if self.precision.is_some() => {
+                match timestamp::parse(&text) {
+                    Some((timestamp, _)) => {
+                        Ok(Value::String(self.precision.unwrap().format(&timestamp)))
+                    }
+                    None => Ok(Value::String(text)),
+                }
+            }
+            (Value::Array(elements), tag) => {
+                let tag = element_tag(tag).unwrap_or("");
+                let mut rewritten = Vec::with_capacity(elements.len());
+                for (index, element) in elements.into_iter().enumerate() {
+                    let len = path.len();
+                    push_pointer_token(path, &index.to_string());
+                    rewritten.push(try!(self.apply(element, tag, path)));
+                    path.truncate(len);
+                }
+                Ok(Value::Array(rewritten))
+            }
+            (Value::Set(set), tag) => {
+                let tag = element_tag(tag).unwrap_or("");
+                let mut rewritten = Set::new();
+                for (index, element) in set.into_iter().enumerate() {
+                    let len = path.len();
+                    push_pointer_token(path, &index.to_string());
+                    rewritten.insert(try!(self.apply(element, tag, path)));
+                    path.truncate(len);
+                }
+                Ok(Value::Set(rewritten))
+            }
+            (value, _) => Ok(value),
         }
+    }
+}
 
-        writer.write_all(b"}")
+/// The encoding of data in the value of a member with this name, whose tag
+/// is `d`, `d16`, `d32` or `d64` or an array or set of them.
+fn data_encoding(name: &str) -> Option<DataEncoding> {
+    let (_, mut tag) = split_tag(name);
+    while let Some(element) = element_tag(tag) {
+        tag = element;
+    }
+    if transform::is_data(tag) {
+        Some(DataEncoding::for_tag(tag))
+    } else {
+        None
+    }
+}
+
+/// Whether the value of a member with this name holds integers, its tag
+/// being `i` or `u` or an array or set of them.
+fn is_integer(name: &str) -> bool {
+    let (_, mut tag) = split_tag(name);
+    while let Some(element) = element_tag(tag) {
+        tag = element;
+    }
+    tag == "i" || tag == "u"
+}
+
+/// Whether the value of a member with this name holds floats, its tag
+/// being `f` or an array or set of them.
+fn is_float(name: &str) -> bool {
+    let (_, mut tag) = split_tag(name);
+    while let Some(element) = element_tag(tag) {
+        tag = element;
+    }
+    tag == "f"
+}
+
+/// The tag of a member name, if what follows its last `:` is one
+/// `Tag::parse` reads.
+fn valid_tag(name: &str) -> Option<&str> {
+    match name.rfind(':') {
+        Some(colon) if tag::is_tag(&name[colon + 1..]) => Some(&name[colon + 1..]),
+        _ => None,
+    }
+}
+
+/// Whether a member tagged `tag` may hold a value which would be tagged
+/// `found`: integers are floats, signed ones are unsigned if none of them
+/// is negative and unsigned ones signed if none is above `i64::MAX`,
+/// strings may be the text of timestamps, integers or data, and data may
+/// be written as an array of its bytes.
+fn tag_accepts(tag: &str, found: &str, ranges: &Ranges) -> bool {
+    if tag == found {
+        return true;
+    }
+    match (tag, found) {
+        ("u", "i") => !ranges.negative.get(),
+        ("i", "u") => !ranges.above_i64.get(),
+        ("f", "i") | ("f", "u") => true,
+        ("t", "s") | ("i", "s") | ("u", "s") => true,
+        (data, "s") | (data, "d") if transform::is_data(data) => true,
+        // Integers which are all bytes, whatever their signedness
+        (data, "A<i>") | (data, "A<u>") if transform::is_data(data) => {
+            !ranges.negative.get() && !ranges.above_u8.get()
+        }
+        // Arrays and sets are written the same way
+        _ => match (element_tag(tag), element_tag(found)) {
+            (Some(element), Some(found)) => tag_accepts(element, found, ranges),
+            _ => false,
+        },
+    }
+}
+
+/// Whether a member tagged `tag` may hold a sequence, at any depth, whose
+/// elements are tagged `a` and `b`, such as signed and unsigned integers
+/// under `A<i>`.
+fn mixed_accepted(tag: &str, a: &str, b: &str, ranges: &Ranges) -> bool {
+    match element_tag(tag) {
+        Some(element) => {
+            tag_accepts(element, a, ranges) && tag_accepts(element, b, ranges) ||
+                mixed_accepted(element, a, b, ranges)
+        }
+        None => false,
     }
+}
 
-    #[inline]
-    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        if first {
-            try!(writer.write_all(b"\n"));
-        } else {
-            try!(writer.write_all(b",\n"));
-        }
-        indent(writer, self.current_indent, self.indent)
+/// The tag of an array or set whose elements are tagged `a` and `b`, if
+/// they have one. Signed and unsigned integers share `i` unless one of them
+/// is above `i64::MAX`.
+fn shared_element_tag(a: &str, b: &str, ranges: &Ranges) -> Option<String> {
+    match (a, b) {
+        ("i", "u") | ("u", "i") if !ranges.above_i64.get() => Some("i".to_owned()),
+        _ => match (element_tag(a), element_tag(b)) {
+            (Some(x), Some(y)) if a[..2] == b[..2] => {
+                shared_element_tag(x, y, ranges).map(|tag| format!("{}{}>", &a[..2], tag))
+            }
+            _ => ::json::shared_tag(a, b),
+        },
     }
+}
 
-    #[inline]
-    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        writer.write_all(b": ")
+/// Whether a sequence tagged `tag` can hold the empty sequences of one
+/// tagged `empty` by `TagProbe`, which is as deeply nested.
+fn holds_empty(tag: &str, empty: &str) -> bool {
+    match (element_tag(tag), element_tag(empty)) {
+        (Some(_), Some("O")) => true,
+        (Some(tag), Some(empty)) => holds_empty(tag, empty),
+        _ => false,
     }
+}
 
-    #[inline]
-    fn end_object_value<W: ?Sized>(&mut self, _writer: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.has_value = true;
-        Ok(())
+/// The tag of the elements of an array or set with this tag, if it is one.
+fn element_tag(tag: &str) -> Option<&str> {
+    if (tag.starts_with("A<") || tag.starts_with("S<")) && tag.ends_with('>') {
+        Some(&tag[2..tag.len() - 1])
+    } else {
+        None
     }
 }
 
-fn format_escaped_str<W: ?Sized, F: ?Sized>(
-    writer: &mut W,
-    formatter: &mut F,
-    value: &str,
-) -> io::Result<()>
-where
-    W: io::Write,
-    F: Formatter,
-{
-    try!(formatter.begin_string(writer));
-    try!(format_escaped_str_contents(writer, formatter, value));
-    try!(formatter.end_string(writer));
-    Ok(())
+fn already_tagged(name: &str) -> String {
+    format!("member name {:?} already has a tag", name)
 }
 
-fn format_escaped_str_contents<W: ?Sized, F: ?Sized>(
-    writer: &mut W,
-    formatter: &mut F,
-    value: &str,
-) -> io::Result<()>
-where
-    W: io::Write,
-    F: Formatter,
-{
-    let bytes = value.as_bytes();
+fn policy_error<T: fmt::Display>(msg: T, path: &str) -> Error {
+    if path.is_empty() {
+        ser::Error::custom(msg)
+    } else {
+        ser::Error::custom(format_args!("{} at {}", msg, path))
+    }
+}
 
-    let mut start = 0;
+/// The order in which `to_writer_with` writes the members of objects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// In the order the value being written gives them, as `to_writer` does
+    /// (the default).
+    AsGiven,
 
-    for (i, &byte) in bytes.iter().enumerate() {
-        let escape = ESCAPE[byte as usize];
-        if escape == 0 {
-            continue;
+    /// Sorted by name with the given rule.
+    Sorted(KeySortRule),
+}
+
+/// How member names are compared when they are sorted, by
+/// `KeyOrder::Sorted` and by `tjson::fs::Format::Canonical`.
+///
+/// The two rules disagree when one name is a prefix of the other and the
+/// longer one continues with a character before `:`, such as `"a:s"` and
+/// `"a-b:s"`. Signed or hashed output is only reproducible if the writer and
+/// the checker use the same rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeySortRule {
+    /// By the name without its tag, as Unicode code points, with members of
+    /// the same name ordered by their tags. This is TJSON's canonical order.
+    UntaggedName,
+
+    /// By the whole name as written, tag included, as Unicode code points,
+    /// which is how `Map` orders its keys without the `preserve_order`
+    /// feature. Some other implementations sort this way.
+    TaggedName,
+}
+
+impl KeySortRule {
+    /// Compare two member names under this rule.
+    ///
+    /// ```rust
+    /// use std::cmp::Ordering;
+    /// use tjson::ser::KeySortRule;
+    ///
+    /// assert_eq!(KeySortRule::UntaggedName.compare("a:s", "a-b:s"), Ordering::Less);
+    /// assert_eq!(KeySortRule::TaggedName.compare("a:s", "a-b:s"), Ordering::Greater);
+    /// assert_eq!(KeySortRule::UntaggedName.compare("b:i", "a:s"), Ordering::Greater);
+    /// ```
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match *self {
+            KeySortRule::UntaggedName => split_tag(a).cmp(&split_tag(b)),
+            KeySortRule::TaggedName => a.cmp(b),
         }
+    }
+}
 
-        if start < i {
-            try!(formatter.write_string_fragment(writer, &value[start..i]));
+/// A member name without its tag, and the tag, which is empty if there is
+/// none. Byte order of UTF-8 is the order of its code points.
+fn split_tag(name: &str) -> (&str, &str) {
+    match name.rfind(':') {
+        Some(colon) => (&name[..colon], &name[colon + 1..]),
+        None => (name, ""),
+    }
+}
+
+/// Writes a `Value` with the members of each object sorted.
+struct SortedValue<'a> {
+    value: &'a Value,
+    rule: KeySortRule,
+}
+
+impl<'a> ser::Serialize for SortedValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        let rule = self.rule;
+        let sorted = |value| SortedValue { value: value, rule: rule };
+        match *self.value {
+            Value::Object(ref map) => {
+                let mut members: Vec<(&String, &Value)> = map.iter().collect();
+                members.sort_by(|a, b| rule.compare(a.0, b.0));
+                let mut map = try!(serializer.serialize_map(Some(members.len())));
+                for (name, value) in members {
+                    try!(map.serialize_entry(name, &sorted(value)));
+                }
+                map.end()
+            }
+            Value::Array(ref elements) => {
+                let mut seq = try!(serializer.serialize_seq(Some(elements.len())));
+                for element in elements {
+                    try!(seq.serialize_element(&sorted(element)));
+                }
+                seq.end()
+            }
+            Value::Set(ref set) => {
+                let elements: Vec<SortedValue> = set.iter().map(sorted).collect();
+                serializer.serialize_newtype_struct(SET_TOKEN, &elements)
+            }
+            ref value => value.serialize(serializer),
         }
+    }
+}
 
-        let char_escape = CharEscape::from_escape_table(escape, byte);
-        try!(formatter.write_char_escape(writer, char_escape));
+/// Where serialization stopped because the output would have been longer
+/// than `SerializeConfig::max_output_bytes` allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputBudgetExceeded {
+    path: String,
+    written: usize,
+    max: usize,
+}
 
-        start = i + 1;
+impl OutputBudgetExceeded {
+    /// JSON Pointer to the value which was being written, or to the last one
+    /// written if the budget ran out between values. Empty for the whole
+    /// document.
+    pub fn path(&self) -> &str {
+        &self.path
     }
 
-    if start != bytes.len() {
-        try!(formatter.write_string_fragment(writer, &value[start..]));
+    /// Number of bytes written before the write which would have crossed the
+    /// budget.
+    pub fn written(&self) -> usize {
+        self.written
     }
 
-    Ok(())
+    /// The budget, in bytes.
+    pub fn max(&self) -> usize {
+        self.max
+    }
 }
 
-const BB: u8 = b'b'; // \x08
-const TT: u8 = b't'; // \x09
-const NN: u8 = b'n'; // \x0A
-const FF: u8 = b'f'; // \x0C
-const RR: u8 = b'r'; // \x0D
-const QU: u8 = b'"'; // \x22
-const BS: u8 = b'\\'; // \x5C
-const U: u8 = b'u'; // \x00...\x1F except the ones above
-
-// Lookup table of escape sequences. A value of b'x' at index i means that byte
-// i is escaped as "\x" in JSON. A value of 0 means that byte i is not escaped.
-#[cfg_attr(rustfmt, rustfmt_skip)]
-static ESCAPE: [u8; 256] = [
-    //  1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
-    U,  U,  U,  U,  U,  U,  U,  U, BB, TT, NN,  U, FF, RR,  U,  U, // 0
-    U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U,  U, // 1
-    0,  0, QU,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 2
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 3
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 4
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, BS,  0,  0,  0, // 5
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 6
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 7
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 8
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // 9
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // A
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // B
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // C
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // D
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // E
-    0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0,  0, // F
-];
+impl fmt::Display for OutputBudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(
+            f,
+            "output budget of {} bytes exceeded after writing {} bytes",
+            self.max,
+            self.written
+        ));
+        if !self.path.is_empty() {
+            try!(write!(f, " at {}", self.path));
+        }
+        Ok(())
+    }
+}
 
-#[inline]
-fn format_escaped_char<W: ?Sized, F: ?Sized>(
-    wr: &mut W,
-    formatter: &mut F,
-    value: char,
-) -> io::Result<()>
-where
-    W: io::Write,
-    F: Formatter,
-{
-    use std::io::Write;
-    // A char encoded as UTF-8 takes 4 bytes at most.
-    let mut buf = [0; 4];
-    write!(&mut buf[..], "{}", value).expect("write char to 4-byte buffer");
-    // Writing a char successfully always produce valid UTF-8.
-    // Once we do not support Rust <1.15 we will be able to just use
-    // the method `char::encode_utf8`.
-    // See https://github.com/serde-rs/json/issues/270.
-    let slice = unsafe { str::from_utf8_unchecked(&buf[0..value.len_utf8()]) };
-    format_escaped_str(wr, formatter, slice)
+impl error::Error for OutputBudgetExceeded {
+    fn description(&self) -> &str {
+        "TJSON output budget exceeded"
+    }
 }
 
-/// Serialize the given data structure as JSON into the IO stream.
+/// Serialize the given data structure as TJSON into the IO stream, with the
+/// given settings. Returns the number of bytes written.
 ///
 /// # Errors
 ///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_writer<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
+/// Fails with an `ErrorCode::SchemaViolation` error naming the first
+/// mismatch, if the config has a schema which the value doesn't match, in
+/// which case nothing has been written. Fails with an
+/// `ErrorCode::OutputBudgetExceeded` error if the document would be longer
+/// than the config's `max_output_bytes`, in which case the bytes within the
+/// budget may have been written. Otherwise this can fail for the same
+/// reasons as `to_writer`, but only an IO error can leave part of the
+/// document written.
+pub fn to_writer_with<W, T: ?Sized>(
+    mut writer: W,
+    config: &SerializeConfig,
+    value: &T,
+) -> Result<usize>
 where
     W: io::Write,
     T: ser::Serialize,
 {
-    let mut ser = Serializer::new(writer);
-    try!(value.serialize(&mut ser));
-    Ok(())
+    let retagged = !config.tags.is_empty();
+    if config.schema.is_none() && config.sort_keys.is_none() && !retagged {
+        return write_limited(writer, config, value);
+    }
+
+    let policy = config.duplicate_elements.unwrap_or(DuplicateElements::Error);
+    let mut tree = try!(to_value_with(value, policy));
+    if retagged {
+        tree = try!(config.tags.apply(tree, "", &mut String::new()));
+    }
+    if let Some(schema) = config.schema {
+        if let Err(mut violations) = schema.check(&tree) {
+            let first = violations.swap_remove(0);
+            return Err(Error::syntax(Code::SchemaViolation(Box::new(first)), 0, 0));
+        }
+    }
+
+    let sorted = config.sort_keys.map(|rule| SortedValue { value: &tree, rule: rule });
+    if config.schema.is_none() {
+        if let Some(ref sorted) = sorted {
+            return write_limited(writer, config, sorted);
+        }
+        return write_limited(writer, config, &tree);
+    }
+
+    let mut bytes = Vec::with_capacity(128);
+    match sorted {
+        Some(ref sorted) => try!(write_limited(&mut bytes, config, sorted)),
+        None => try!(write_limited(&mut bytes, config, &tree)),
+    };
+    try!(writer.write_all(&bytes).map_err(Error::io));
+    Ok(bytes.len())
 }
 
-/// Serialize the given data structure as pretty-printed JSON into the IO
-/// stream.
+/// Serialize the given data structure as a TJSON byte vector, with the given
+/// settings.
 ///
 /// # Errors
 ///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_writer_pretty<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
+/// Fails for the same reasons as `to_writer_with`.
+pub fn to_vec_with<T: ?Sized>(config: &SerializeConfig, value: &T) -> Result<Vec<u8>>
 where
-    W: io::Write,
     T: ser::Serialize,
 {
-    let mut ser = Serializer::pretty(writer);
-    try!(value.serialize(&mut ser));
-    Ok(())
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_with(&mut vec, config, value));
+    Ok(vec)
 }
 
-/// Serialize the given data structure as a JSON byte vector.
+/// Serialize the given data structure as a String of TJSON, with the given
+/// settings.
 ///
 /// # Errors
 ///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_vec<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+/// Fails for the same reasons as `to_writer_with`.
+pub fn to_string_with<T: ?Sized>(config: &SerializeConfig, value: &T) -> Result<String>
 where
     T: ser::Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    try!(to_writer(&mut writer, value));
-    Ok(writer)
+    let vec = try!(to_vec_with(config, value));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
 }
 
-/// Serialize the given data structure as a pretty-printed JSON byte vector.
+/// Serialize the given data structure as TJSON into the IO stream in
+/// canonical form: compact, with the members of every object in
+/// `KeySortRule::UntaggedName` order and the elements of every set in
+/// ascending order, including arrays written under a member tagged `S<...>`
+/// as a parsed `Value` holds them. Equal values always give the same bytes, which makes
+/// the output suitable for hashing and signing. Returns the number of bytes
+/// written.
 ///
 /// # Errors
 ///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_vec_pretty<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+/// Fails for the same reasons as `to_writer`, and also if `T` contains a set
+/// with duplicate elements.
+pub fn to_writer_canonical<W, T: ?Sized>(writer: W, value: &T) -> Result<usize>
 where
+    W: io::Write,
     T: ser::Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    try!(to_writer_pretty(&mut writer, value));
-    Ok(writer)
+    let canonical = canonicalize(try!(to_value_with(value, DuplicateElements::Error)));
+    let config = SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName));
+    to_writer_with(writer, &config, &canonical)
 }
 
-/// Serialize the given data structure as a String of JSON.
+/// Serialize the given data structure as a String of TJSON in canonical
+/// form, as `to_writer_canonical` writes it.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// let value = tjson!({ "b:i": 1, "a-b:s": "x", "a:S<i>": [3, 1, 2] });
+/// let output = tjson::to_string_canonical(&value).unwrap();
+/// assert_eq!(output, r#"{"a:S<i>":["1","2","3"],"a-b:s":"x","b:i":"1"}"#);
+/// # }
+/// ```
 ///
 /// # Errors
 ///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_string<T: ?Sized>(value: &T) -> Result<String>
+/// Fails for the same reasons as `to_writer_canonical`.
+pub fn to_string_canonical<T: ?Sized>(value: &T) -> Result<String>
 where
     T: ser::Serialize,
 {
-    let vec = try!(to_vec(value));
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_canonical(&mut vec, value));
     let string = unsafe {
         // We do not emit invalid UTF-8.
         String::from_utf8_unchecked(vec)
@@ -1811,32 +4447,213 @@ where
     Ok(string)
 }
 
-/// Serialize the given data structure as a pretty-printed String of JSON.
-///
-/// # Errors
-///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
-#[inline]
-pub fn to_string_pretty<T: ?Sized>(value: &T) -> Result<String>
+// Not public API. Should be pub(crate).
+/// Puts the elements of every set in `value` in ascending order, whether it
+/// is a `Value::Set` or an array written under a member tagged `S<...>`.
+/// Members are sorted as they are written.
+#[doc(hidden)]
+pub fn canonicalize(value: Value) -> Value {
+    canonicalize_tagged(value, "")
+}
+
+fn canonicalize_tagged(value: Value, tag: &str) -> Value {
+    let is_set = tag.starts_with("S<");
+    let inner = if (is_set || tag.starts_with("A<")) && tag.ends_with('>') {
+        &tag[2..tag.len() - 1]
+    } else {
+        ""
+    };
+    match value {
+        Value::Object(map) => {
+            let map: Map<String, Value> = map
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = canonicalize_tagged(value, split_tag(&name).1);
+                    (name, value)
+                })
+                .collect();
+            Value::Object(map)
+        }
+        Value::Array(elements) => {
+            let mut elements: Vec<Value> = elements
+                .into_iter()
+                .map(|element| canonicalize_tagged(element, inner))
+                .collect();
+            if is_set {
+                elements.sort();
+            }
+            Value::Array(elements)
+        }
+        Value::Set(set) => {
+            let mut elements: Vec<Value> = set
+                .into_iter()
+                .map(|element| canonicalize_tagged(element, inner))
+                .collect();
+            elements.sort();
+            let mut set = Set::new();
+            for element in elements {
+                set.insert(element);
+            }
+            Value::Set(set)
+        }
+        value => value,
+    }
+}
+
+/// Writes the value compactly, stopping at the budget if there is one.
+fn write_limited<W, T: ?Sized>(writer: W, config: &SerializeConfig, value: &T) -> Result<usize>
 where
+    W: io::Write,
     T: ser::Serialize,
 {
-    let vec = try!(to_vec_pretty(value));
-    let string = unsafe {
-        // We do not emit invalid UTF-8.
-        String::from_utf8_unchecked(vec)
+    let writer = LimitedWriter {
+        writer: writer,
+        count: 0,
+        budget: config.max_output_bytes.map(|max| (max, PathTracker::default())),
+        exceeded: false,
     };
-    Ok(string)
+    let policy = config.duplicate_elements.unwrap_or(DuplicateElements::Error);
+    let mut ser = Serializer::new(writer)
+        .duplicate_elements(policy)
+        .exponent_notation(config.floats.exponent)
+        .max_float_len(config.floats.max_len)
+        .quoted_integers(!config.bare_integers)
+        .infer_tags(config.tags.inference())
+        // Names are tagged by now if values were rewritten
+        .reject_tagged_names(config.tags.reject_tagged && config.tags.is_empty());
+    let result = value.serialize(&mut ser);
+    let writer = ser.into_inner();
+    match (result, writer.budget) {
+        (Err(_), Some((max, path))) if writer.exceeded => {
+            let exceeded = OutputBudgetExceeded {
+                path: path.pointer(),
+                written: writer.count,
+                max: max,
+            };
+            Err(Error::syntax(Code::OutputBudgetExceeded(Box::new(exceeded)), 0, 0))
+        }
+        (Err(err), _) => Err(err),
+        (Ok(()), _) => Ok(writer.count),
+    }
 }
 
-fn indent<W: ?Sized>(wr: &mut W, n: usize, s: &[u8]) -> io::Result<()>
+/// Counts the bytes written through it, and refuses any write which would
+/// take the count over the budget, if there is one.
+struct LimitedWriter<W> {
+    writer: W,
+    count: usize,
+    budget: Option<(usize, PathTracker)>,
+    exceeded: bool,
+}
+
+impl<W> io::Write for LimitedWriter<W>
 where
     W: io::Write,
 {
-    for _ in 0..n {
-        try!(wr.write_all(s));
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some((max, ref mut path)) = self.budget {
+            if buf.len() > max - self.count {
+                self.exceeded = true;
+                return Err(io::Error::new(io::ErrorKind::Other, "output budget exceeded"));
+            }
+            let written = try!(self.writer.write(buf));
+            path.feed(&buf[..written]);
+            self.count += written;
+            return Ok(written);
+        }
+        let written = try!(self.writer.write(buf));
+        self.count += written;
+        Ok(written)
     }
 
-    Ok(())
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Follows the TJSON written so far closely enough to tell the JSON Pointer
+/// of the value being written.
+#[derive(Default)]
+struct PathTracker {
+    /// The open arrays with the index of their current element, and the open
+    /// objects with the name of their current member once it is known.
+    frames: Vec<Frame>,
+    in_string: bool,
+    escaped: bool,
+    /// The raw text of the member name being written, if the string is one.
+    name: Option<Vec<u8>>,
+}
+
+enum Frame {
+    Array(usize),
+    Object(Option<String>),
+}
+
+impl PathTracker {
+    fn feed(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            if self.in_string {
+                if b == b'"' && !self.escaped {
+                    self.in_string = false;
+                    if let Some(raw) = self.name.take() {
+                        if let Some(&mut Frame::Object(ref mut name)) = self.frames.last_mut() {
+                            *name = Some(unescape_name(raw));
+                        }
+                    }
+                    continue;
+                }
+                self.escaped = !self.escaped && b == b'\\';
+                if let Some(ref mut raw) = self.name {
+                    raw.push(b);
+                }
+                continue;
+            }
+
+            match b {
+                b'"' => {
+                    self.in_string = true;
+                    if let Some(&Frame::Object(None)) = self.frames.last() {
+                        self.name = Some(Vec::new());
+                    }
+                }
+                b'[' => self.frames.push(Frame::Array(0)),
+                b'{' => self.frames.push(Frame::Object(None)),
+                b']' | b'}' => {
+                    self.frames.pop();
+                }
+                b',' => {
+                    match self.frames.last_mut() {
+                        Some(&mut Frame::Array(ref mut index)) => *index += 1,
+                        Some(&mut Frame::Object(ref mut name)) => *name = None,
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn pointer(&self) -> String {
+        let mut pointer = String::new();
+        for frame in &self.frames {
+            match *frame {
+                Frame::Array(index) => pointer.push_str(&format!("/{}", index)),
+                Frame::Object(Some(ref name)) => push_pointer_token(&mut pointer, name),
+                Frame::Object(None) => {}
+            }
+        }
+        pointer
+    }
+}
+
+/// The member name whose text between the quotes is `raw`.
+fn unescape_name(raw: Vec<u8>) -> String {
+    if !raw.contains(&b'\\') {
+        return String::from_utf8(raw).unwrap_or_default();
+    }
+    let mut quoted = Vec::with_capacity(raw.len() + 2);
+    quoted.push(b'"');
+    quoted.extend_from_slice(&raw);
+    quoted.push(b'"');
+    ::de::from_slice(&quoted).unwrap_or_default()
 }
@@ -15,26 +15,87 @@
 //!
 //! By default the map is backed by a [`BTreeMap`]. To preserve insertion
 //! order, enable the `preserve_order` feature which will use the
-//! [`LinkedHashMap`] type instead.
+//! [`LinkedHashMap`] type instead, or the `preserve_order_indexmap` feature
+//! which will use [`IndexMap`]. The two behave the same, down to how they
+//! compare and hash, and only one may be enabled.
 //!
 //! [`BTreeMap`]: https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
 //! [`LinkedHashMap`]: https://docs.rs/linked-hash-map/*/linked_hash_map/struct.LinkedHashMap.html
+//! [`IndexMap`]: https://docs.rs/indexmap/2/indexmap/map/struct.IndexMap.html
+//!
+//! Other sets, such as `HashSet` and `BTreeSet`, serialize like any other
+//! sequence. Mark a field holding one with
+//! `#[serde(with = "tjson::set")]` to have it treated as a TJSON set:
+//! `tjson::to_string` tags an untagged name `S<..>` after its elements, or
+//! `S<O>` if there are none to go by, `tjson::to_value` gives a
+//! `Value::Set` for it, and an element equal to an earlier one is an error
+//! when reading it from an untagged array too, rather than being dropped.
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde;
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use std::collections::BTreeSet;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Post {
+//!     #[serde(with = "tjson::set")]
+//!     tags: BTreeSet<String>,
+//! }
+//!
+//! let post = Post { tags: vec!["a".to_owned(), "b".to_owned()].into_iter().collect() };
+//!
+//! let output = tjson::to_string(&post).unwrap();
+//! assert_eq!(output, r#"{"tags:S<s>":["a","b"]}"#);
+//! assert_eq!(tjson::from_str::<Post>(&output).unwrap(), post);
+//!
+//! let err = tjson::from_str::<Post>(r#"{"tags": ["a", "b", "a"]}"#).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "duplicate set element \"a\" at index 2 of /tags at line 1 column 23"
+//! );
+//! # }
+//! ```
 
+#[cfg(feature = "preserve_order_indexmap")]
+use indexmap;
+#[cfg(feature = "preserve_order_indexmap")]
+use indexed_map::IndexedMap;
 #[cfg(feature = "preserve_order")]
 use linked_hash_map::{self, LinkedHashMap};
-use serde::ser;
-#[cfg(not(feature = "preserve_order"))]
+use serde::{de, ser};
+use serde::de::value::SeqAccessDeserializer;
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 use std::collections::btree_map::{self, BTreeMap};
-use std::fmt::Debug;
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug};
 use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use de::DuplicateElements;
+use error::{Code, Error};
+use map;
 use value::Value;
 
-#[cfg(not(feature = "preserve_order"))]
+// Not public API. Should be pub(crate).
+/// Name of the newtype struct around the elements of a serialized set, which
+/// serializers other than `tjson::describe` see through.
+#[doc(hidden)]
+pub const SET_TOKEN: &str = "$tjson::private::Set";
+
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type SetImpl<T> = BTreeMap<T, ()>;
 
 #[cfg(feature = "preserve_order")]
 type SetImpl<T> = LinkedHashMap<T, ()>;
 
+#[cfg(feature = "preserve_order_indexmap")]
+type SetImpl<T> = IndexedMap<T, ()>;
+
 /// Represents a TJSON set type.
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Set<T: Hash + Debug + Eq + PartialEq + PartialOrd + Ord> {
@@ -61,11 +122,319 @@ impl Set<Value> {
         self.set.is_empty()
     }
 
+    /// Returns true if the set contains an element equal to the value.
+    #[inline]
+    pub fn contains(&self, value: &Value) -> bool {
+        self.set.contains_key(value)
+    }
+
     /// Gets an iterator over the entries of the set.
     #[inline]
     pub fn iter(&self) -> Iter {
         Iter { iter: self.set.iter() }
     }
+
+    /// Removes the elements for which `pred` returns true and returns them,
+    /// in the set's iteration order. The remaining elements keep their order.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Set;
+    /// #
+    /// # fn main() {
+    /// let mut set = Set::new();
+    /// set.insert(tjson!(1));
+    /// set.insert(tjson!("a"));
+    /// set.insert(tjson!(2));
+    ///
+    /// assert_eq!(set.drain_filter(|value| value.is_number()), [tjson!(1), tjson!(2)]);
+    /// assert_eq!(set.len(), 1);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `pred` panics, the panic is passed on after the set is left
+    /// holding every element which had not been removed yet, including the
+    /// one `pred` was looking at. Elements removed before the panic are
+    /// dropped.
+    pub fn drain_filter<F>(&mut self, mut pred: F) -> Vec<Value>
+    where
+        F: FnMut(&Value) -> bool,
+    {
+        map::drain_filter(&mut self.set, |value, _| pred(value))
+            .into_iter()
+            .map(|(value, ())| value)
+            .collect()
+    }
+
+    /// Adds a value to the set. Returns true if the value was not already
+    /// present.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Set;
+    /// #
+    /// # fn main() {
+    /// let mut set = Set::new();
+    ///
+    /// assert!(set.insert(tjson!("a")));
+    /// assert!(set.insert(tjson!([1, 2])));
+    /// assert!(!set.insert(tjson!("a")));
+    /// assert_eq!(set.len(), 2);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// In builds with debug assertions, panics if an element already in the
+    /// set was mutated after insertion (for example through unsafe code) so
+    /// that it is no longer where the set expects it. The elements are
+    /// checked whenever the length of the set reaches a power of two, which
+    /// keeps the cost proportional to the number of insertions. Release builds
+    /// do not check.
+    pub fn insert(&mut self, value: Value) -> bool {
+        let inserted = self.set.insert(value, ()).is_none();
+
+        #[cfg(debug_assertions)]
+        {
+            if inserted && self.set.len().is_power_of_two() {
+                self.check_elements();
+            }
+        }
+
+        inserted
+    }
+
+    /// Removes the element equal to the value, if there is one. Returns
+    /// true if there was. The remaining elements keep their order.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Set;
+    /// #
+    /// # fn main() {
+    /// let mut set = Set::new();
+    /// set.insert(tjson!("a"));
+    ///
+    /// assert!(set.remove(&tjson!("a")));
+    /// assert!(!set.remove(&tjson!("a")));
+    /// assert!(set.is_empty());
+    /// # }
+    /// ```
+    #[inline]
+    pub fn remove(&mut self, value: &Value) -> bool {
+        self.set.remove(value).is_some()
+    }
+
+    /// Removes every element from the set.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.set.clear()
+    }
+
+    /// Replaces every element with what `f` returns for it. Returns how many
+    /// of the results were equal to an earlier one and so were dropped; the
+    /// first of the equal results is the one kept.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::{Set, Value};
+    /// #
+    /// # fn main() {
+    /// let mut set = Set::new();
+    /// for i in 1..5 {
+    ///     set.insert(tjson!(i));
+    /// }
+    ///
+    /// // 1 and 2 both become 0, as do 3 and 4
+    /// let collapsed = set.map_in_place(|value| match value.as_u64() {
+    ///     Some(n) => tjson!(n / 3),
+    ///     None => value,
+    /// });
+    /// assert_eq!(collapsed, 2);
+    /// assert_eq!(set.iter().cloned().collect::<Vec<Value>>(), [tjson!(0), tjson!(1)]);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `f` panics, the panic is passed on after the set is left holding
+    /// only the elements which had already been replaced. The element `f`
+    /// was given and those it had not got to yet are dropped.
+    pub fn map_in_place<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(Value) -> Value,
+    {
+        // Results go straight into the emptied set, so it is valid at every
+        // point `f` could panic
+        let elements = mem::take(&mut self.set);
+        let mut collapsed = 0;
+
+        for (element, ()) in elements {
+            if !self.insert_first(f(element)) {
+                collapsed += 1;
+            }
+        }
+
+        collapsed
+    }
+
+    /// Takes the elements out into a `SetEditor`, which can change them in
+    /// any way a `Vec` can and puts them back when it is finished or
+    /// dropped.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Set;
+    /// #
+    /// # fn main() {
+    /// let mut set = Set::new();
+    /// set.insert(tjson!("a"));
+    /// set.insert(tjson!("b"));
+    ///
+    /// let mut editor = set.edit();
+    /// for element in editor.iter_mut() {
+    ///     *element = tjson!("c");
+    /// }
+    /// editor.push(tjson!("d"));
+    /// assert_eq!(editor.finish(), 1);
+    ///
+    /// assert_eq!(set.len(), 2);
+    /// assert!(set.contains(&tjson!("c")));
+    /// # }
+    /// ```
+    ///
+    /// While the editor is alive the set itself is empty. If the editor is
+    /// leaked with `mem::forget`, the set stays empty.
+    pub fn edit(&mut self) -> SetEditor {
+        let elements = mem::take(&mut self.set).into_iter().map(|(element, ())| element).collect();
+        SetEditor {
+            set: self,
+            elements: elements,
+        }
+    }
+
+    /// Inserts a value unless an equal one is already present, in which
+    /// case the one present is kept where it is.
+    fn insert_first(&mut self, value: Value) -> bool {
+        if self.set.contains_key(&value) {
+            false
+        } else {
+            self.set.insert(value, ());
+            true
+        }
+    }
+
+    #[cfg(all(
+        debug_assertions,
+        not(any(feature = "preserve_order", feature = "preserve_order_indexmap"))
+    ))]
+    fn check_elements(&self) {
+        let mut elements = self.set.keys();
+        let mut previous = match elements.next() {
+            Some(first) => first,
+            None => return,
+        };
+
+        for element in elements {
+            if previous >= element {
+                misplaced_element(previous);
+            }
+            previous = element;
+        }
+    }
+
+    #[cfg(all(
+        debug_assertions,
+        any(feature = "preserve_order", feature = "preserve_order_indexmap")
+    ))]
+    fn check_elements(&self) {
+        for element in self.set.keys() {
+            if !self.set.contains_key(element) {
+                misplaced_element(element);
+            }
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+fn misplaced_element(element: &Value) -> ! {
+    panic!(
+        "tjson::Set element {:?} (fingerprint {:016x}) is no longer where the set expects it; \
+         was it mutated after being inserted?",
+        element,
+        element.hash_fingerprint()
+    )
+}
+
+/// The elements of a `Set` taken out for editing, returned by `Set::edit`.
+///
+/// Dereferences to a `Vec<Value>`, so elements can be changed, added,
+/// removed and reordered freely. When the editor is finished or dropped the
+/// elements are put back into the set in the order they are in; elements
+/// equal to an earlier one are dropped. Prefer `finish`, which also says how
+/// many were dropped.
+///
+/// The editor is dropped, and the set rebuilt, even if the code editing it
+/// panics, so the set never ends up holding duplicates.
+pub struct SetEditor<'a> {
+    set: &'a mut Set<Value>,
+    elements: Vec<Value>,
+}
+
+impl<'a> SetEditor<'a> {
+    /// Puts the elements back into the set. Returns how many were dropped
+    /// for being equal to an earlier one.
+    pub fn finish(mut self) -> usize {
+        self.rebuild()
+    }
+
+    fn rebuild(&mut self) -> usize {
+        let mut collapsed = 0;
+        for element in self.elements.drain(..) {
+            if !self.set.insert_first(element) {
+                collapsed += 1;
+            }
+        }
+        collapsed
+    }
+}
+
+impl<'a> Deref for SetEditor<'a> {
+    type Target = Vec<Value>;
+
+    fn deref(&self) -> &Vec<Value> {
+        &self.elements
+    }
+}
+
+impl<'a> DerefMut for SetEditor<'a> {
+    fn deref_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.elements
+    }
+}
+
+impl<'a> Drop for SetEditor<'a> {
+    fn drop(&mut self) {
+        self.rebuild();
+    }
+}
+
+impl<'a> Debug for SetEditor<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_tuple("SetEditor").field(&self.elements).finish()
+    }
 }
 
 impl ser::Serialize for Set<Value> {
@@ -74,10 +443,150 @@ impl ser::Serialize for Set<Value> {
         S: ser::Serializer,
     {
         let result: Vec<&Value> = self.iter().collect();
-        result.serialize(serializer)
+        serializer.serialize_newtype_struct(SET_TOKEN, &result)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Set<Value> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(SET_TOKEN, SetVisitor)
+    }
+}
+
+/// Collects the elements of a set. The deserializers of this crate apply
+/// their `DuplicateElements` policy before handing the elements on, so a
+/// duplicate only arrives here from some other deserializer, for which it
+/// is an error as by default.
+struct SetVisitor;
+
+impl<'de> de::Visitor<'de> for SetVisitor {
+    type Value = Set<Value>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a set")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Set<Value>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Set<Value>, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut set = Set::new();
+        let mut index = 0;
+        while let Some(element) = try!(seq.next_element()) {
+            if set.contains(&element) {
+                let code = Code::DuplicateElement(index, element.to_string(), String::new());
+                return Err(de::Error::custom(code));
+            }
+            set.insert(element);
+            index += 1;
+        }
+        Ok(set)
+    }
+}
+
+/// Writes a collection as a TJSON set.
+///
+/// The elements are written as the same sequence as without it, but
+/// converting to a `Value` gives a `Value::Set`, and two equal elements are
+/// an error unless the `DuplicateElements` policy merges them.
+pub fn serialize<'a, T: ?Sized, S>(collection: &'a T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    &'a T: IntoIterator,
+    <&'a T as IntoIterator>::Item: ser::Serialize,
+    S: ser::Serializer,
+{
+    let elements: Vec<_> = collection.into_iter().collect();
+    serializer.serialize_newtype_struct(SET_TOKEN, &elements)
+}
+
+/// Reads a collection from a TJSON set.
+///
+/// The TJSON deserializers check that the elements differ, whether or not the
+/// member is tagged `S<..>`, and handle those which don't by their
+/// `DuplicateElements` policy before the collection sees them. Other
+/// deserializers hand the sequence to the collection as it is.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: de::Deserialize<'de>,
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(SET_TOKEN, CollectionVisitor(PhantomData))
+}
+
+/// Hands the elements of a set on to the collection being read.
+struct CollectionVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for CollectionVisitor<T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a set")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<T, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<T, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        T::deserialize(SeqAccessDeserializer::new(seq))
     }
 }
 
+// Not public API. Should be pub(crate).
+/// Turns an array which was serialized or is being deserialized as a set
+/// into a `Value::Set`, handling elements equal to an earlier one by the
+/// policy. Anything else is returned as it is.
+#[doc(hidden)]
+pub fn from_elements(value: Value, policy: DuplicateElements) -> Result<Value, Error> {
+    let elements = match value {
+        Value::Array(elements) => elements,
+        other => return Ok(other),
+    };
+
+    let mut set = Set::new();
+    for (index, element) in elements.into_iter().enumerate() {
+        // Inserting an element again would move it to the end with
+        // `preserve_order`, rather than leaving the first in place
+        if !set.contains(&element) {
+            set.insert(element);
+        } else if policy == DuplicateElements::Error {
+            let code = Code::DuplicateElement(index, element.to_string(), String::new());
+            return Err(Error::syntax(code, 0, 0));
+        }
+    }
+    Ok(Value::Set(set))
+}
+
+// Not public API. Should be pub(crate).
+/// The index of the first of the elements equal to an earlier one, if any.
+#[doc(hidden)]
+pub fn first_duplicate<'a, I>(elements: I) -> Option<usize>
+where
+    I: IntoIterator<Item = &'a Value>,
+{
+    let mut seen = BTreeSet::new();
+    elements.into_iter().position(|element| !seen.insert(element))
+}
+
 //////////////////////////////////////////////////////////////////////////////
 
 impl<'a> IntoIterator for &'a Set<Value> {
@@ -95,12 +604,15 @@ pub struct Iter<'a> {
     iter: IterImpl<'a>,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type IterImpl<'a> = btree_map::Iter<'a, Value, ()>;
 
 #[cfg(feature = "preserve_order")]
 type IterImpl<'a> = linked_hash_map::Iter<'a, Value, ()>;
 
+#[cfg(feature = "preserve_order_indexmap")]
+type IterImpl<'a> = indexmap::map::Iter<'a, Value, ()>;
+
 impl<'a> Iterator for Iter<'a> {
     type Item = &'a Value;
     #[inline]
@@ -144,10 +656,12 @@ pub struct IntoIter {
     iter: IntoIterImpl,
 }
 
-#[cfg(not(feature = "preserve_order"))]
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 type IntoIterImpl = btree_map::IntoIter<Value, ()>;
 #[cfg(feature = "preserve_order")]
 type IntoIterImpl = linked_hash_map::IntoIter<Value, ()>;
+#[cfg(feature = "preserve_order_indexmap")]
+type IntoIterImpl = indexmap::map::IntoIter<Value, ()>;
 
 impl Iterator for IntoIter {
     type Item = Value;
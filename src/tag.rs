@@ -0,0 +1,296 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The type tags of TJSON member names
+//!
+//! A `Tag` says what the value of a member holds, and so how it is read. It
+//! is usually written after the last `:` of a member name, but documents
+//! which are a single value have no member to carry one, so
+//! `tjson::from_str_as` takes the tag separately:
+//!
+//! ```rust
+//! # extern crate chrono;
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use chrono::datetime::DateTime;
+//! use chrono::offset::utc::UTC;
+//! use tjson::Tag;
+//!
+//! let tag = Tag::parse("A<t>").unwrap();
+//! let times: Vec<DateTime<UTC>> =
+//!     tjson::from_str_as(r#"["2016-11-06T22:27:34Z"]"#, &tag).unwrap();
+//! assert_eq!(times[0].to_string(), "2016-11-06 22:27:34 UTC");
+//!
+//! let err = tjson::from_str_as::<Vec<String>>(r#"["a", 1]"#, &tag).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "expected a value tagged `t`, found string \"a\" at /0 at line 1 column 4"
+//! );
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+
+use ser::DataEncoding;
+use transform;
+use value::{Value, push_pointer_token};
+
+/// The type tag of a TJSON value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tag {
+    /// A boolean, tagged `b`.
+    Bool,
+
+    /// Data in the given encoding, tagged `d`, `d16`, `d32` or `d64`.
+    Data(DataEncoding),
+
+    /// A floating point number, tagged `f`.
+    Float,
+
+    /// A signed integer, tagged `i`.
+    Int,
+
+    /// An unsigned integer, tagged `u`.
+    UInt,
+
+    /// A string, tagged `s`.
+    String,
+
+    /// A timestamp, tagged `t`.
+    Timestamp,
+
+    /// An object, tagged `O`.
+    Object,
+
+    /// An array of values with the inner tag, tagged `A<..>`.
+    Array(Box<Tag>),
+
+    /// A set of values with the inner tag, tagged `S<..>`.
+    Set(Box<Tag>),
+}
+
+impl Tag {
+    /// Reads a tag as it is written in a member name, without the `:`.
+    /// Returns `None` if it isn't one.
+    ///
+    /// ```rust
+    /// use tjson::Tag;
+    /// use tjson::ser::DataEncoding;
+    ///
+    /// assert_eq!(Tag::parse("d16"), Some(Tag::Data(DataEncoding::Base16)));
+    /// assert_eq!(Tag::parse("S<A<i>>").unwrap().to_string(), "S<A<i>>");
+    /// assert_eq!(Tag::parse("A<>"), None);
+    /// ```
+    pub fn parse(text: &str) -> Option<Tag> {
+        let tag = match text {
+            "b" => Tag::Bool,
+            "d" => Tag::Data(DataEncoding::Plain),
+            "d16" => Tag::Data(DataEncoding::Base16),
+            "d32" => Tag::Data(DataEncoding::Base32),
+            "d64" => Tag::Data(DataEncoding::Base64Url),
+            "f" => Tag::Float,
+            "i" => Tag::Int,
+            "u" => Tag::UInt,
+            "s" => Tag::String,
+            "t" => Tag::Timestamp,
+            "O" => Tag::Object,
+            _ if text.starts_with("A<") && text.ends_with('>') => {
+                let inner = &text[2..text.len() - 1];
+                return Tag::parse(inner).map(|inner| Tag::Array(Box::new(inner)));
+            }
+            _ if text.starts_with("S<") && text.ends_with('>') => {
+                let inner = &text[2..text.len() - 1];
+                return Tag::parse(inner).map(|inner| Tag::Set(Box::new(inner)));
+            }
+            _ => return None,
+        };
+        Some(tag)
+    }
+
+    /// Returns true for the tags of values which aren't containers: all but
+    /// `O`, `A<..>` and `S<..>`.
+    pub fn is_scalar(&self) -> bool {
+        match *self {
+            Tag::Object | Tag::Array(_) | Tag::Set(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Checks that a value holds what this tag says, as it would be once
+    /// parsed: timestamps and data are strings unless they were converted,
+    /// sets are arrays, and integers tagged `i` or `u` must fit an `i64` or
+    /// a `u64`. Returns the first place which doesn't.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::Tag;
+    ///
+    /// let tag = Tag::parse("A<u>").unwrap();
+    /// assert!(tag.check(&tjson!([1, 2])).is_ok());
+    ///
+    /// let mismatch = tag.check(&tjson!([1, -2])).unwrap_err();
+    /// assert_eq!(mismatch.pointer(), "/1");
+    /// assert_eq!(mismatch.found(), "number -2");
+    /// # }
+    /// ```
+    pub fn check(&self, value: &Value) -> Result<(), TagMismatch> {
+        let mut pointer = String::new();
+        self.check_at(value, &mut pointer)
+    }
+
+    fn check_at(&self, value: &Value, pointer: &mut String) -> Result<(), TagMismatch> {
+        let matches = match (self, value) {
+            (&Tag::Bool, &Value::Bool(_)) |
+            (&Tag::Float, &Value::Number(_)) |
+            (&Tag::String, &Value::String(_)) |
+            (&Tag::Timestamp, &Value::Timestamp(_)) |
+            (&Tag::Object, &Value::Object(_)) => true,
+            (&Tag::Data(encoding), &Value::String(ref text)) => {
+                transform::decode(encoding.tag(), text).is_ok()
+            }
+            (&Tag::Data(_), &Value::Data(_)) => true,
+            (&Tag::Int, &Value::Number(ref n)) => n.as_i64().is_some(),
+            (&Tag::UInt, &Value::Number(ref n)) => n.as_u64().is_some(),
+            (&Tag::Timestamp, &Value::String(ref text)) => ::timestamp::parse(text).is_some(),
+            (&Tag::Array(ref inner), &Value::Array(ref elements)) |
+            (&Tag::Set(ref inner), &Value::Array(ref elements)) => {
+                try!(inner.check_elements(elements, pointer));
+                true
+            }
+            (&Tag::Set(ref inner), &Value::Set(ref elements)) => {
+                try!(inner.check_elements(elements, pointer));
+                true
+            }
+            _ => false,
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            Err(TagMismatch::new(self, value, pointer))
+        }
+    }
+
+    fn check_elements<'a, I>(&self, elements: I, pointer: &mut String) -> Result<(), TagMismatch>
+    where
+        I: IntoIterator<Item = &'a Value>,
+    {
+        for (index, element) in elements.into_iter().enumerate() {
+            let len = pointer.len();
+            push_pointer_token(pointer, &index.to_string());
+            try!(self.check_at(element, pointer));
+            pointer.truncate(len);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Tag::Bool => formatter.write_str("b"),
+            Tag::Data(encoding) => formatter.write_str(encoding.tag()),
+            Tag::Float => formatter.write_str("f"),
+            Tag::Int => formatter.write_str("i"),
+            Tag::UInt => formatter.write_str("u"),
+            Tag::String => formatter.write_str("s"),
+            Tag::Timestamp => formatter.write_str("t"),
+            Tag::Object => formatter.write_str("O"),
+            Tag::Array(ref inner) => write!(formatter, "A<{}>", inner),
+            Tag::Set(ref inner) => write!(formatter, "S<{}>", inner),
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Whether `Tag::parse` reads the text as a tag, without building it.
+#[doc(hidden)]
+pub fn is_tag(text: &str) -> bool {
+    match text {
+        "b" | "d" | "d16" | "d32" | "d64" | "f" | "i" | "u" | "s" | "t" | "O" => true,
+        _ if text.len() > 3 && text.ends_with('>') => {
+            (text.starts_with("A<") || text.starts_with("S<")) && is_tag(&text[2..text.len() - 1])
+        }
+        _ => false,
+    }
+}
+
+/// A place where a value doesn't hold what its `Tag` says, from
+/// `Tag::check` or the deserializer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagMismatch {
+    pointer: String,
+    expected: Tag,
+    found: String,
+}
+
+impl TagMismatch {
+    fn new(expected: &Tag, value: &Value, pointer: &str) -> Self {
+        let found = match *value {
+            Value::Bool(_) | Value::Number(_) | Value::String(_) | Value::Timestamp(_) => {
+                let text = ::ser::to_string(value).unwrap_or_default();
+                format!("{} {}", value.kind(), text)
+            }
+            _ => value.kind().to_string(),
+        };
+        TagMismatch {
+            pointer: pointer.to_owned(),
+            expected: expected.clone(),
+            found: found,
+        }
+    }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn from_parts(expected: Tag, found: String, pointer: String) -> Self {
+        TagMismatch {
+            pointer: pointer,
+            expected: expected,
+            found: found,
+        }
+    }
+
+    /// JSON Pointer to the offending value, empty if it is the value that
+    /// was checked.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// The tag the value should have matched.
+    pub fn expected(&self) -> &Tag {
+        &self.expected
+    }
+
+    /// The kind of the value, followed by its text if it isn't a
+    /// container, as in `string "x"`. A value which the deserializer
+    /// rejects before reading it gives only its kind.
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+impl fmt::Display for TagMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "expected a value tagged `{}`, found {}", self.expected, self.found));
+        if !self.pointer.is_empty() {
+            try!(write!(f, " at {}", self.pointer));
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for TagMismatch {
+    fn description(&self) -> &str {
+        "value doesn't match its tag"
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading and writing the text of TJSON timestamps
+//!
+//! `serialize` and `deserialize` read and write a `DateTime` field of a
+//! struct as a TJSON timestamp, for use with `#[serde(with =
+//! "tjson::timestamp")]`. Timestamps are written in UTC with a `Z`, and with
+//! a fraction of a second only if they have one, whatever chrono would
+//! write. `tjson::to_string` tags the field's name `t` if it has no tag, and
+//! `tjson::to_value` gives a `Value::Timestamp` for them whether or not it
+//! does. Other serializers see a string.
+//!
+//! ```rust
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde;
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use tjson::{DateTime, Value};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(rename = "at:t", with = "tjson::timestamp")]
+//!     at: DateTime,
+//!     #[serde(with = "tjson::timestamp")]
+//!     seen: DateTime,
+//! }
+//!
+//! let at: DateTime = "2016-11-06T22:27:34Z".parse().unwrap();
+//! let event = Event { at: at, seen: at };
+//!
+//! let output = tjson::to_string(&event).unwrap();
+//! assert_eq!(output, r#"{"at:t":"2016-11-06T22:27:34Z","seen:t":"2016-11-06T22:27:34Z"}"#);
+//! assert_eq!(tjson::from_str::<Event>(&output).unwrap(), event);
+//!
+//! let value = tjson::to_value(&event).unwrap();
+//! assert_eq!(value["seen"], Value::Timestamp(at));
+//! assert_eq!(tjson::from_value::<Event>(value).unwrap(), event);
+//! # }
+//! ```
+//!
+//! Reading fails on text which isn't an RFC 3339 timestamp, or which has an
+//! offset other than `Z`. The TJSON deserializer checks and converts
+//! timestamps under members tagged `t` before they get here, as
+//! `Deserializer::timestamp_offsets` says.
+
+use std::fmt;
+
+use chrono::datetime::DateTime as ChronoDateTime;
+use chrono::offset::fixed::FixedOffset;
+use chrono::offset::utc::UTC;
+use serde::{de, ser};
+
+use value::{DateTime, Value};
+
+// Not public API. Should be pub(crate).
+/// Name of the newtype struct around the text of a timestamp written by
+/// `serialize`, which serializers other than `tjson::to_value` see through.
+#[doc(hidden)]
+pub const TIMESTAMP_TOKEN: &str = "$tjson::private::Timestamp";
+
+/// Writes a timestamp as a TJSON timestamp.
+pub fn serialize<S>(timestamp: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    serializer.serialize_newtype_struct(TIMESTAMP_TOKEN, &format(timestamp))
+}
+
+/// Reads a TJSON timestamp.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(TIMESTAMP_TOKEN, TimestampVisitor)
+}
+
+struct TimestampVisitor;
+
+impl<'de> de::Visitor<'de> for TimestampVisitor {
+    type Value = DateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an RFC 3339 timestamp in UTC")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<DateTime, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+
+    fn visit_str<E>(self, text: &str) -> Result<DateTime, E>
+    where
+        E: de::Error,
+    {
+        match parse(text) {
+            Some((timestamp, "Z")) if !text.ends_with('z') => Ok(timestamp),
+            Some((_, offset)) => {
+                let offset = if offset == "Z" { "z" } else { offset };
+                Err(E::custom(format_args!("timestamp has offset `{}` rather than `Z`", offset)))
+            }
+            None => Err(E::invalid_value(de::Unexpected::Str(text), &self)),
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Parses an RFC 3339 timestamp with any offset, returning it in UTC along
+/// with its offset as written, which is `Z` for either `Z` or `z`.
+///
+/// The `T` and `Z` may be lowercase and the fraction of a second may have
+/// any number of digits or be left out, as RFC 3339 allows. An offset of
+/// `-00:00` is read as UTC.
+#[doc(hidden)]
+pub fn parse(text: &str) -> Option<(DateTime, &str)> {
+    let timestamp = match ChronoDateTime::<FixedOffset>::parse_from_rfc3339(text) {
+        Ok(timestamp) => timestamp.with_timezone(&UTC),
+        Err(_) => return None,
+    };
+
+    if text.ends_with('Z') || text.ends_with('z') {
+        Some((timestamp, "Z"))
+    } else {
+        // Always `+hh:mm` or `-hh:mm` since the text parsed
+        Some((timestamp, &text[text.len() - 6..]))
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Writes a timestamp as TJSON requires, in UTC with a `Z`, and with a
+/// fraction of a second only if it has one.
+#[doc(hidden)]
+pub fn format(timestamp: &DateTime) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
+}
+
+// Not public API. Should be pub(crate).
+/// Reads the strings in a value whose tag makes them timestamps, like `t`
+/// or `A<t>`, as `Value::Timestamp`.
+///
+/// Only strings which `format` would write the same way are converted, so
+/// that writing the value out again gives back the same text. Others, such
+/// as `2017-03-04T05:06:07.5Z` or ones which aren't RFC 3339 timestamps at
+/// all, are left as strings.
+#[doc(hidden)]
+pub fn read_tagged(tag: &str, value: Value) -> Value {
+    match value {
+        Value::String(text) if tag == "t" => {
+            match parse(&text) {
+                Some((timestamp, _)) if format(&timestamp) == text => Value::Timestamp(timestamp),
+                _ => Value::String(text),
+            }
+        }
+        Value::Array(elements) => {
+            if !(tag.starts_with("A<") || tag.starts_with("S<")) || !tag.ends_with('>') {
+                return Value::Array(elements);
+            }
+            let tag = &tag[2..tag.len() - 1];
+            let elements = elements.into_iter().map(|element| read_tagged(tag, element));
+            Value::Array(elements.collect())
+        }
+        value => value,
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Hooks which rewrite data and strings as they are parsed
+//!
+//! `Deserializer::transform_data` and `Deserializer::transform_string` (and
+//! the same methods of `Parser`) register a function to run over every value
+//! a `Matcher` picks out, such as decrypting or decompressing particular
+//! `:d` members, without walking the parsed `Value` a second time. Hooks run
+//! as values are read, so they apply alike when deserializing into `Value`
+//! and into derived structs.
+//!
+//! A hook can't change what kind of value it is given. Data hooks see the
+//! decoded bytes of values tagged `d`, `d64`, `d32` or `d16`, and what they return
+//! is read as the value, or encoded again for the same tag when
+//! `Deserializer::decode_data` is off. String hooks see the text of strings
+//! tagged `s` or untagged, never that of data or timestamps. If a hook fails,
+//! the error says which value it was given.
+//!
+//! ```rust
+//! # extern crate serde;
+//! # extern crate tjson;
+//! #
+//! # fn main() {
+//! use serde::Deserialize;
+//! use tjson::{Deserializer, Value};
+//! use tjson::transform::Matcher;
+//!
+//! let input = r#"{"users:A<O>": [{"name:s": "ann", "key:d16": "0f0f"}]}"#;
+//!
+//! let mut de = Deserializer::from_str(input)
+//!     .transform_data(Matcher::path("/users:A<O>/*/key:d16"), |bytes| {
+//!         Ok(bytes.iter().map(|b| b ^ 0xff).collect())
+//!     })
+//!     .transform_string(Matcher::tag("s"), |s| Ok(s.to_uppercase()));
+//! let value = Value::deserialize(&mut de).unwrap();
+//!
+//! assert_eq!(value["users:A<O>"][0]["key:d16"], Value::Data(vec![0xf0, 0xf0].into()));
+//! assert_eq!(value["users:A<O>"][0]["name:s"], "ANN");
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use encoding::{self, DecodeError};
+use error::{Code, Error};
+
+/// Which values a hook applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Matcher {
+    /// Values at the JSON pointers matching this pattern. Member names are
+    /// written with their tags and escaped as in any pointer, and a `*`
+    /// segment matches any one member or element, so `/users:A<O>/*/key:d`
+    /// matches the `key:d` member of every user.
+    Path(String),
+
+    /// Values with this tag, such as `d` or `s`, at any depth. Elements of
+    /// an array or set tagged `A<d>` or `S<d>` have the tag `d`.
+    Tag(String),
+}
+
+impl Matcher {
+    /// Matches the values at JSON pointers matching a pattern, as
+    /// `Matcher::Path`.
+    pub fn path(pattern: &str) -> Self {
+        Matcher::Path(pattern.to_owned())
+    }
+
+    /// Matches every value with a tag, as `Matcher::Tag`.
+    pub fn tag(tag: &str) -> Self {
+        Matcher::Tag(tag.to_owned())
+    }
+
+    fn matches(&self, pointer: &str, tag: Option<&str>) -> bool {
+        match *self {
+            Matcher::Path(ref pattern) => {
+                let mut segments = pointer.split('/');
+                let mut patterns = pattern.split('/');
+                loop {
+                    match (segments.next(), patterns.next()) {
+                        (Some(segment), Some(pattern)) => {
+                            if pattern != "*" && pattern != segment {
+                                return false;
+                            }
+                        }
+                        (None, None) => return true,
+                        _ => return false,
+                    }
+                }
+            }
+            Matcher::Tag(ref expected) => tag == Some(&**expected),
+        }
+    }
+}
+
+type DataHook = Arc<Fn(Vec<u8>) -> Result<Vec<u8>, Error> + Send + Sync>;
+type StringHook = Arc<Fn(String) -> Result<String, Error> + Send + Sync>;
+
+// Not public API. Should be pub(crate).
+/// The hooks registered with a `Deserializer` or `Parser`, in the order they
+/// run.
+#[doc(hidden)]
+#[derive(Clone, Default)]
+pub struct Transforms {
+    data: Vec<(Matcher, DataHook)>,
+    strings: Vec<(Matcher, StringHook)>,
+}
+
+impl Transforms {
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty() && self.strings.is_empty()
+    }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn add_data<F>(&mut self, matcher: Matcher, hook: F)
+    where
+        F: Fn(Vec<u8>) -> Result<Vec<u8>, Error> + Send + Sync + 'static,
+    {
+        self.data.push((matcher, Arc::new(hook)));
+    }
+
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn add_string<F>(&mut self, matcher: Matcher, hook: F)
+    where
+        F: Fn(String) -> Result<String, Error> + Send + Sync + 'static,
+    {
+        self.strings.push((matcher, Arc::new(hook)));
+    }
+
+    // Not public API. Should be pub(crate).
+    /// Whether any data hook applies to data with this tag at this pointer.
+    #[doc(hidden)]
+    pub fn matches_data(&self, pointer: &str, tag: &str) -> bool {
+        is_data(tag) && self.data.iter().any(|&(ref m, _)| m.matches(pointer, Some(tag)))
+    }
+
+    // Not public API. Should be pub(crate).
+    /// Decodes the text of data with this tag at this pointer, and runs
+    /// every data hook which applies to it over the bytes in turn.
+    #[doc(hidden)]
+    pub fn apply_data(&self, pointer: &str, tag: &str, text: &str) -> Result<Vec<u8>, Code> {
+        let decoded = decode(tag, text);
+        let decoded = decoded.map_err(|err| failed(pointer, Error::custom_boxed(Box::new(err))));
+        let mut bytes = try!(decoded);
+
+        for &(ref matcher, ref hook) in &self.data {
+            if matcher.matches(pointer, Some(tag)) {
+                bytes = try!(hook(bytes).map_err(|err| failed(pointer, err)));
+            }
+        }
+        Ok(bytes)
+    }
+
+    // Not public API. Should be pub(crate).
+    /// The text of the value at this pointer once every hook which applies
+    /// to it has run, or `None` if none does. Data is encoded again for its
+    /// tag; timestamps and other tagged strings are left alone.
+    #[doc(hidden)]
+    pub fn apply(
+        &self,
+        pointer: &str,
+        tag: Option<&str>,
+        text: &str,
+    ) -> Result<Option<String>, Code> {
+        match tag {
+            Some(tag) if is_data(tag) => {
+                if !self.matches_data(pointer, tag) {
+                    return Ok(None);
+                }
+                let bytes = try!(self.apply_data(pointer, tag, text));
+                Ok(Some(match tag {
+                    "d16" => encoding::hex_encode(&bytes),
+                    "d32" => encoding::b32_encode(&bytes),
+                    _ => encoding::b64url_encode(&bytes),
+                }))
+            }
+            Some("s") | None => {
+                let mut transformed: Option<String> = None;
+                for &(ref matcher, ref hook) in &self.strings {
+                    if matcher.matches(pointer, tag) {
+                        let input = transformed.take().unwrap_or_else(|| text.to_owned());
+                        transformed = Some(try!(hook(input).map_err(|err| failed(pointer, err))));
+                    }
+                }
+                Ok(transformed)
+            }
+            Some(_) => Ok(None),
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+/// Whether values with this tag are data.
+#[doc(hidden)]
+pub fn is_data(tag: &str) -> bool {
+    tag == "d" || tag == "d64" || tag == "d32" || tag == "d16"
+}
+
+// Not public API. Should be pub(crate).
+/// Decodes the text of data with this tag.
+#[doc(hidden)]
+pub fn decode(tag: &str, text: &str) -> Result<Vec<u8>, DecodeError> {
+    match tag {
+        "d16" => encoding::hex_decode(text),
+        "d32" => encoding::b32_decode(text),
+        _ => encoding::b64url_decode(text),
+    }
+}
+
+fn failed(pointer: &str, err: Error) -> Code {
+    Code::TransformFailed(pointer.to_owned(), err)
+}
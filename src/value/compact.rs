@@ -0,0 +1,374 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compact values which share repeated strings and subtrees
+
+use std::collections::HashSet;
+use std::mem;
+use std::sync::Arc;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use map::Map;
+use number::Number;
+use set::Set;
+use timestamp;
+use super::{DateTime, Value, parse_index, unescape_pointer_token};
+
+/// Strings and data at least this long are shared when repeated. Member names
+/// are always shared.
+const MIN_SHARED_LEN: usize = 8;
+
+/// Arrays, sets, and objects with at most this many values inside them,
+/// counting themselves, are shared when repeated.
+const MAX_SHARED_NODES: usize = 32;
+
+/// A read-only `Value` whose repeated strings and small subtrees share their
+/// storage.
+///
+/// Documents which repeat the same members and small objects many times over,
+/// as telemetry often does, can take a fraction of the memory in this form.
+/// Create one with `Value::compact`, and convert it back with `to_value`.
+/// Serializing a `CompactValue` produces exactly the same output as the
+/// `Value` it was made from.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum CompactValue {
+    /// Represents a TJSON undefined value.
+    Undefined,
+
+    /// Represents a TJSON boolean.
+    Bool(bool),
+
+    /// Represents TJSON binary data.
+    Data(Arc<[u8]>),
+
+    /// Represents a TJSON number, whether integer or floating point.
+    Number(Number),
+
+    /// Represents a TJSON string.
+    String(Arc<str>),
+
+    /// Represents a TJSON timestamp.
+    Timestamp(DateTime),
+
+    /// Represents a TJSON array.
+    Array(Arc<[CompactValue]>),
+
+    /// Represents a TJSON set, in the order the `Value`'s set iterated it.
+    Set(Arc<[CompactValue]>),
+
+    /// Represents a TJSON object, in the order the `Value`'s map iterated it.
+    Object(Arc<[(Arc<str>, CompactValue)]>),
+}
+
+/// What `Value::compact` managed to share.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactStats {
+    /// Number of strings, member names, and data values which reuse the
+    /// storage of an identical one seen earlier.
+    pub strings_shared: usize,
+
+    /// Number of arrays, sets, and objects which reuse the storage of an
+    /// identical one seen earlier.
+    pub subtrees_shared: usize,
+
+    /// Bytes of string, data, and container storage which would be needed if
+    /// nothing were shared, but aren't. Estimated from lengths rather than
+    /// allocated capacities.
+    pub bytes_saved: usize,
+}
+
+impl Value {
+    /// Converts the value into a `CompactValue`, which shares the storage of
+    /// repeated strings, member names, and small subtrees.
+    ///
+    /// Member names are always shared. Strings and data are shared if they are
+    /// at least 8 bytes long, and arrays, sets, and objects if they contain at
+    /// most 32 values in total.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let point = tjson!({ "unit": "milliseconds", "type": "gauge" });
+    /// let value = tjson!([point.clone(), point.clone(), point]);
+    ///
+    /// let (compact, stats) = value.compact();
+    /// assert_eq!(stats.subtrees_shared, 2);
+    /// assert_eq!(compact.to_value(), value);
+    /// assert_eq!(tjson::to_string(&compact).unwrap(), tjson::to_string(&value).unwrap());
+    /// # }
+    /// ```
+    pub fn compact(&self) -> (CompactValue, CompactStats) {
+        let mut compactor = Compactor {
+            strings: HashSet::new(),
+            data: HashSet::new(),
+            subtrees: HashSet::new(),
+        };
+        let (compact, _) = compactor.value(self);
+
+        // Count what ended up shared, rather than everything that was looked
+        // up along the way, since repeated strings inside a repeated subtree
+        // go unused once the whole subtree is shared
+        let mut stats = CompactStats::default();
+        let unshared = compact.unshared_storage(&mut HashSet::new(), &mut stats);
+        stats.bytes_saved = compact.storage() - unshared;
+        (compact, stats)
+    }
+}
+
+impl CompactValue {
+    /// Converts back into an ordinary `Value`.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            CompactValue::Undefined => Value::Undefined,
+            CompactValue::Bool(b) => Value::Bool(b),
+            CompactValue::Data(ref d) => Value::Data(d.to_vec().into()),
+            CompactValue::Number(n) => Value::Number(n),
+            CompactValue::String(ref s) => Value::String(s.to_string()),
+            CompactValue::Timestamp(ref t) => Value::Timestamp(*t),
+            CompactValue::Array(ref elements) => {
+                Value::Array(elements.iter().map(CompactValue::to_value).collect())
+            }
+            CompactValue::Set(ref elements) => {
+                let mut set = Set::new();
+                for element in elements.iter() {
+                    set.insert(element.to_value());
+                }
+                Value::Set(set)
+            }
+            CompactValue::Object(ref members) => {
+                let mut map = Map::new();
+                for &(ref name, ref value) in members.iter() {
+                    map.insert(name.to_string(), value.to_value());
+                }
+                Value::Object(map)
+            }
+        }
+    }
+
+    /// If the value is a string, returns it.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            CompactValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Looks up a value by a JSON Pointer, like `Value::pointer`.
+    pub fn pointer(&self, pointer: &str) -> Option<&CompactValue> {
+        if pointer == "" {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+
+        let mut target = self;
+        for token in pointer.split('/').skip(1) {
+            let target_opt = unescape_pointer_token(token).and_then(|token| match *target {
+                CompactValue::Object(ref members) => {
+                    members.iter().find(|member| *member.0 == *token).map(|member| &member.1)
+                }
+                CompactValue::Array(ref elements) => {
+                    parse_index(&token).and_then(|index| elements.get(index))
+                }
+                _ => None,
+            });
+            match target_opt {
+                Some(t) => target = t,
+                None => return None,
+            }
+        }
+        Some(target)
+    }
+
+    /// Bytes of storage behind this value, as if nothing were shared.
+    fn storage(&self) -> usize {
+        match *self {
+            CompactValue::Data(ref d) => d.len(),
+            CompactValue::String(ref s) => s.len(),
+            CompactValue::Array(ref elements) |
+            CompactValue::Set(ref elements) => {
+                let children: usize = elements.iter().map(CompactValue::storage).sum();
+                elements.len() * mem::size_of::<CompactValue>() + children
+            }
+            CompactValue::Object(ref members) => {
+                let children: usize = members
+                    .iter()
+                    .map(|&(ref name, ref value)| name.len() + value.storage())
+                    .sum();
+                members.len() * mem::size_of::<(Arc<str>, CompactValue)>() + children
+            }
+            _ => 0,
+        }
+    }
+
+    /// Bytes of storage behind this value which haven't been `seen` already,
+    /// counting what is shared in `stats`.
+    fn unshared_storage(&self, seen: &mut HashSet<usize>, stats: &mut CompactStats) -> usize {
+        match *self {
+            CompactValue::Data(ref d) => shared_len(seen, stats, d, d.len()),
+            CompactValue::String(ref s) => shared_len(seen, stats, s, s.len()),
+            CompactValue::Array(ref elements) |
+            CompactValue::Set(ref elements) => {
+                if !seen.insert(address(elements)) {
+                    stats.subtrees_shared += 1;
+                    return 0;
+                }
+                let children: usize = elements.iter().map(|e| e.unshared_storage(seen, stats)).sum();
+                elements.len() * mem::size_of::<CompactValue>() + children
+            }
+            CompactValue::Object(ref members) => {
+                if !seen.insert(address(members)) {
+                    stats.subtrees_shared += 1;
+                    return 0;
+                }
+                let mut children = 0;
+                for &(ref name, ref value) in members.iter() {
+                    children += shared_len(seen, stats, name, name.len());
+                    children += value.unshared_storage(seen, stats);
+                }
+                members.len() * mem::size_of::<(Arc<str>, CompactValue)>() + children
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn address<T: ?Sized>(shared: &Arc<T>) -> usize {
+    Arc::as_ptr(shared) as *const u8 as usize
+}
+
+/// The length of a string or data value, unless its storage was `seen`
+/// already.
+fn shared_len<T: ?Sized>(
+    seen: &mut HashSet<usize>,
+    stats: &mut CompactStats,
+    shared: &Arc<T>,
+    len: usize,
+) -> usize {
+    if seen.insert(address(shared)) {
+        len
+    } else {
+        stats.strings_shared += 1;
+        0
+    }
+}
+
+impl Serialize for CompactValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            CompactValue::Undefined => serializer.serialize_unit(),
+            CompactValue::Bool(b) => serializer.serialize_bool(b),
+            CompactValue::Data(ref d) => serializer.serialize_bytes(d),
+            CompactValue::Number(ref n) => n.serialize(serializer),
+            CompactValue::String(ref s) => serializer.serialize_str(s),
+            CompactValue::Timestamp(ref t) => serializer.serialize_str(&timestamp::format(t)),
+            CompactValue::Array(ref elements) |
+            CompactValue::Set(ref elements) => {
+                let mut seq = try!(serializer.serialize_seq(Some(elements.len())));
+                for element in elements.iter() {
+                    try!(seq.serialize_element(element));
+                }
+                seq.end()
+            }
+            CompactValue::Object(ref members) => {
+                let mut map = try!(serializer.serialize_map(Some(members.len())));
+                for &(ref name, ref value) in members.iter() {
+                    try!(map.serialize_key(&**name));
+                    try!(map.serialize_value(value));
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct Compactor {
+    strings: HashSet<Arc<str>>,
+    data: HashSet<Arc<[u8]>>,
+    subtrees: HashSet<CompactValue>,
+}
+
+impl Compactor {
+    fn string(&mut self, s: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(s) {
+            return shared.clone();
+        }
+
+        let s: Arc<str> = Arc::from(s);
+        self.strings.insert(s.clone());
+        s
+    }
+
+    fn data(&mut self, d: &[u8]) -> Arc<[u8]> {
+        if let Some(shared) = self.data.get(d) {
+            return shared.clone();
+        }
+
+        let d: Arc<[u8]> = Arc::from(d);
+        self.data.insert(d.clone());
+        d
+    }
+
+    /// Compacts the value, returning it along with the number of values in
+    /// it, counting itself.
+    fn value(&mut self, value: &Value) -> (CompactValue, usize) {
+        let mut nodes = 1;
+        let compact = match *value {
+            Value::Undefined => return (CompactValue::Undefined, nodes),
+            Value::Bool(b) => return (CompactValue::Bool(b), nodes),
+            Value::Number(n) => return (CompactValue::Number(n), nodes),
+            Value::Timestamp(ref t) => return (CompactValue::Timestamp(*t), nodes),
+            Value::Data(ref d) if d.len() >= MIN_SHARED_LEN => {
+                return (CompactValue::Data(self.data(d)), nodes)
+            }
+            Value::Data(ref d) => return (CompactValue::Data(Arc::from(&d[..])), nodes),
+            Value::String(ref s) if s.len() >= MIN_SHARED_LEN => {
+                return (CompactValue::String(self.string(s)), nodes)
+            }
+            Value::String(ref s) => return (CompactValue::String(Arc::from(&s[..])), nodes),
+            Value::Array(ref elements) => {
+                let elements: Vec<_> = elements.iter().map(|e| self.child(e, &mut nodes)).collect();
+                CompactValue::Array(Arc::from(elements))
+            }
+            Value::Set(ref elements) => {
+                let elements: Vec<_> = elements.iter().map(|e| self.child(e, &mut nodes)).collect();
+                CompactValue::Set(Arc::from(elements))
+            }
+            Value::Object(ref map) => {
+                let members: Vec<_> = map.iter()
+                    .map(|(name, value)| (self.string(name), self.child(value, &mut nodes)))
+                    .collect();
+                CompactValue::Object(Arc::from(members))
+            }
+        };
+
+        if nodes > MAX_SHARED_NODES {
+            return (compact, nodes);
+        }
+
+        if let Some(shared) = self.subtrees.get(&compact) {
+            return (shared.clone(), nodes);
+        }
+
+        self.subtrees.insert(compact.clone());
+        (compact, nodes)
+    }
+
+    fn child(&mut self, value: &Value, nodes: &mut usize) -> CompactValue {
+        let (compact, child_nodes) = self.value(value);
+        *nodes += child_nodes;
+        compact
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fallible conversions from `Value` into plain Rust types
+
+use std::convert::{Infallible, TryFrom};
+use std::error;
+use std::fmt;
+
+use super::{DateTime, Value, ValueKind};
+use map::Map;
+
+/// The error returned when a `Value` cannot be converted into a Rust type via
+/// `TryFrom`.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # use std::convert::TryFrom;
+/// # use tjson::value::ValueKind;
+/// #
+/// # fn main() {
+/// let v = tjson!([[1, 2], [3, "four"]]);
+/// let err = Vec::<Vec<u64>>::try_from(v).unwrap_err();
+///
+/// assert_eq!(err.found(), ValueKind::String);
+/// assert_eq!(err.path(), &[1, 1]);
+/// assert_eq!(err.to_string(), "expected u64, found string at [1][1]");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionError {
+    expected: &'static str,
+    found: ValueKind,
+    path: Vec<usize>,
+}
+
+impl ConversionError {
+    fn new(expected: &'static str, found: ValueKind) -> Self {
+        ConversionError {
+            expected: expected,
+            found: found,
+            path: Vec::new(),
+        }
+    }
+
+    fn at_index(mut self, index: usize) -> Self {
+        self.path.insert(0, index);
+        self
+    }
+
+    /// Description of the Rust type the conversion was attempting to produce.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// The kind of `Value` which was actually found.
+    pub fn found(&self) -> ValueKind {
+        self.found
+    }
+
+    /// Indices of the array or set elements leading to the value which failed
+    /// to convert, outermost first. Empty if the toplevel value failed.
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "expected {}, found {}", self.expected, self.found));
+
+        if !self.path.is_empty() {
+            try!(f.write_str(" at "));
+            for index in &self.path {
+                try!(write!(f, "[{}]", index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for ConversionError {
+    fn description(&self) -> &str {
+        "TJSON value conversion error"
+    }
+}
+
+impl From<Infallible> for ConversionError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+macro_rules! try_from_value {
+    ($ty:ty, $expected:expr, $owned:ident => $owned_body:expr, $borrowed:ident => $borrowed_body:expr) => {
+        impl TryFrom<Value> for $ty {
+            type Error = ConversionError;
+
+            fn try_from($owned: Value) -> Result<Self, ConversionError> {
+                let kind = $owned.kind();
+                $owned_body.ok_or_else(|| ConversionError::new($expected, kind))
+            }
+        }
+
+        impl<'a> TryFrom<&'a Value> for $ty {
+            type Error = ConversionError;
+
+            fn try_from($borrowed: &'a Value) -> Result<Self, ConversionError> {
+                $borrowed_body.ok_or_else(|| ConversionError::new($expected, $borrowed.kind()))
+            }
+        }
+    };
+}
+
+try_from_value!(bool, "bool", v => v.as_bool(), v => v.as_bool());
+try_from_value!(i64, "i64", v => v.as_i64(), v => v.as_i64());
+try_from_value!(u64, "u64", v => v.as_u64(), v => v.as_u64());
+try_from_value!(f64, "f64", v => v.as_f64(), v => v.as_f64());
+
+try_from_value!(
+    String,
+    "string",
+    v => match v {
+        Value::String(s) => Some(s),
+        _ => None,
+    },
+    v => v.as_str().map(ToOwned::to_owned)
+);
+
+try_from_value!(
+    Vec<u8>,
+    "data",
+    v => match v {
+        Value::Data(d) => Some(d.into_vec()),
+        _ => None,
+    },
+    v => match *v {
+        Value::Data(ref d) => Some(d.to_vec()),
+        _ => None,
+    }
+);
+
+try_from_value!(
+    DateTime,
+    "timestamp",
+    v => match v {
+        Value::Timestamp(t) => Some(t),
+        _ => None,
+    },
+    v => match *v {
+        Value::Timestamp(t) => Some(t),
+        _ => None,
+    }
+);
+
+try_from_value!(
+    Map<String, Value>,
+    "object",
+    v => match v {
+        Value::Object(map) => Some(map),
+        _ => None,
+    },
+    v => v.as_object().cloned()
+);
+
+/// Converts the elements of an array or set, reporting the index of the first
+/// element which fails to convert.
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value>,
+    T::Error: Into<ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Array(array) => collect_elements(array.into_iter()),
+            Value::Set(set) => collect_elements(set.into_iter()),
+            other => Err(ConversionError::new("array or set", other.kind())),
+        }
+    }
+}
+
+/// Converts the elements of an array or set, reporting the index of the first
+/// element which fails to convert.
+impl<'a, T> TryFrom<&'a Value> for Vec<T>
+where
+    T: TryFrom<&'a Value>,
+    T::Error: Into<ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(value: &'a Value) -> Result<Self, ConversionError> {
+        match *value {
+            Value::Array(ref array) => collect_elements(array.iter()),
+            Value::Set(ref set) => collect_elements(set.iter()),
+            ref other => Err(ConversionError::new("array or set", other.kind())),
+        }
+    }
+}
+
+fn collect_elements<I, V, T>(elements: I) -> Result<Vec<T>, ConversionError>
+where
+    I: ExactSizeIterator<Item = V>,
+    T: TryFrom<V>,
+    T::Error: Into<ConversionError>,
+{
+    let mut result = Vec::with_capacity(elements.len());
+
+    for (index, element) in elements.enumerate() {
+        match T::try_from(element) {
+            Ok(converted) => result.push(converted),
+            Err(err) => return Err(err.into().at_index(index)),
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,414 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Copy-on-write edits of borrowed values
+
+use std::borrow::Cow;
+use std::mem;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use map::Map;
+use super::{Value, parse_index, unescape_pointer_token};
+
+/// Edits of a borrowed `Value` which don't copy the parts left untouched.
+///
+/// Create one with `Value::cow_edit`. Only the arrays and objects along the
+/// paths of edits are opened up, and even those only hold references to
+/// their untouched members, so making a few edits to a large document costs
+/// little more than the edits themselves.
+///
+/// Reads see the edits, and serializing a `CowValue` writes the edited
+/// document without copying anything. `freeze` turns it into a `Value`, but
+/// has to clone the untouched parts of the original to do so.
+#[derive(Clone, Debug)]
+pub struct CowValue<'a> {
+    node: Node<'a>,
+}
+
+#[derive(Clone, Debug)]
+enum Node<'a> {
+    /// Part of the original value, untouched.
+    Base(&'a Value),
+
+    /// A value set by an edit.
+    Owned(Value),
+
+    /// An object with edits inside it, in the order of the map it will
+    /// become.
+    Object(Vec<(Cow<'a, str>, Node<'a>)>),
+
+    /// An array with edits inside it.
+    Array(Vec<Node<'a>>),
+}
+
+impl Value {
+    /// Start editing the value without copying it.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let config = tjson!({
+    ///     "server": { "host": "localhost", "port": 8080 },
+    ///     "workers": [{ "name": "a" }, { "name": "b" }],
+    /// });
+    ///
+    /// let mut variant = config.cow_edit();
+    /// variant.set("/server/port", tjson!(9090));
+    /// variant.remove("/workers/0");
+    ///
+    /// assert_eq!(variant.pointer("/server/port").unwrap().into_owned(), tjson!(9090));
+    /// assert_eq!(variant, tjson!({
+    ///     "server": { "host": "localhost", "port": 9090 },
+    ///     "workers": [{ "name": "b" }],
+    /// }));
+    ///
+    /// // The original is unchanged
+    /// assert_eq!(config["server"]["port"], tjson!(8080));
+    /// # }
+    /// ```
+    pub fn cow_edit(&self) -> CowValue<'_> {
+        CowValue { node: Node::Base(self) }
+    }
+}
+
+impl<'a> CowValue<'a> {
+    /// Set the value at a JSON Pointer, as `Value::pointer` understands them.
+    ///
+    /// The value's parent must already exist and be an object or an array.
+    /// In an object, this replaces the member of the pointer's last name in
+    /// place or adds a new one. In an array, it replaces the element at the
+    /// pointer's last index, or appends if the index is the array's length or
+    /// `-`. The empty pointer replaces the whole value.
+    ///
+    /// Returns false, and changes nothing, if there is no such parent or the
+    /// index is beyond the end of the array.
+    pub fn set(&mut self, pointer: &str, value: Value) -> bool {
+        if pointer.is_empty() {
+            self.node = Node::Owned(value);
+            return true;
+        }
+
+        let (parent, token) = match self.node.parent_mut(pointer) {
+            Some(found) => found,
+            None => return false,
+        };
+        match *parent {
+            Node::Object(ref mut members) => {
+                match position(members, &token) {
+                    Ok(index) => members[index].1 = Node::Owned(value),
+                    Err(index) => members.insert(index, (Cow::Owned(token), Node::Owned(value))),
+                }
+                true
+            }
+            Node::Array(ref mut elements) => {
+                let index = if token == "-" {
+                    elements.len()
+                } else {
+                    match parse_index(&token) {
+                        Some(index) if index <= elements.len() => index,
+                        _ => return false,
+                    }
+                };
+                if index == elements.len() {
+                    elements.push(Node::Owned(value));
+                } else {
+                    elements[index] = Node::Owned(value);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the object member or array element at a JSON Pointer. Later
+    /// elements of an array move down to fill the gap.
+    ///
+    /// Returns false, and changes nothing, if there is no such value. The
+    /// whole value can't be removed.
+    pub fn remove(&mut self, pointer: &str) -> bool {
+        let (parent, token) = match self.node.parent_mut(pointer) {
+            Some(found) => found,
+            None => return false,
+        };
+        match *parent {
+            Node::Object(ref mut members) => {
+                match position(members, &token) {
+                    Ok(index) => {
+                        members.remove(index);
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            Node::Array(ref mut elements) => {
+                match parse_index(&token) {
+                    Some(index) if index < elements.len() => {
+                        elements.remove(index);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Looks up a value by a JSON Pointer, seeing the edits made so far.
+    ///
+    /// Values which are untouched or were set by an edit are borrowed.
+    /// Arrays and objects with edits inside them are built on demand.
+    pub fn pointer(&self, pointer: &str) -> Option<Cow<'_, Value>> {
+        let mut node = &self.node;
+        let mut rest = pointer;
+
+        loop {
+            match *node {
+                Node::Base(value) => return value.pointer(rest).map(Cow::Borrowed),
+                Node::Owned(ref value) => return value.pointer(rest).map(Cow::Borrowed),
+                _ if rest.is_empty() => return Some(Cow::Owned(node.to_value())),
+                _ => {}
+            }
+            if !rest.starts_with('/') {
+                return None;
+            }
+
+            let end = rest[1..].find('/').map_or(rest.len(), |i| i + 1);
+            let token = &rest[1..end];
+            rest = &rest[end..];
+
+            match unescape_pointer_token(token).and_then(|token| node.child(&token)) {
+                Some(child) => node = child,
+                None => return None,
+            }
+        }
+    }
+
+    /// Looks up a member of an object, seeing the edits made so far, like
+    /// `pointer`.
+    pub fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        match self.node {
+            Node::Base(value) => value.get(name).map(Cow::Borrowed),
+            Node::Owned(ref value) => value.get(name).map(Cow::Borrowed),
+            Node::Object(ref members) => {
+                position(members, name).ok().map(|index| members[index].1.to_cow())
+            }
+            Node::Array(_) => None,
+        }
+    }
+
+    /// Build the edited value, cloning whatever of the original wasn't
+    /// replaced.
+    pub fn to_value(&self) -> Value {
+        self.node.to_value()
+    }
+
+    /// Turn the edits into a `Value`, cloning whatever of the original wasn't
+    /// replaced but moving the values which were set.
+    pub fn freeze(self) -> Value {
+        self.node.freeze()
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Turn a container into an `Object` or `Array` node so that edits can
+    /// be made inside it. Untouched members are borrowed, and values which
+    /// were set are moved, so nothing is cloned.
+    fn open(&mut self) {
+        let opened = match mem::replace(self, Node::Array(Vec::new())) {
+            Node::Base(value) => {
+                match *value {
+                    Value::Object(ref map) => {
+                        let members = map.iter()
+                            .map(|(name, value)| (Cow::Borrowed(name.as_str()), Node::Base(value)));
+                        Node::Object(members.collect())
+                    }
+                    Value::Array(ref array) => Node::Array(array.iter().map(Node::Base).collect()),
+                    _ => Node::Base(value),
+                }
+            }
+            Node::Owned(Value::Object(map)) => {
+                let members = map.into_iter()
+                    .map(|(name, value)| (Cow::Owned(name), Node::Owned(value)));
+                Node::Object(members.collect())
+            }
+            Node::Owned(Value::Array(array)) => {
+                Node::Array(array.into_iter().map(Node::Owned).collect())
+            }
+            node => node,
+        };
+        *self = opened;
+    }
+
+    /// The opened parent of the value a pointer refers to, along with the
+    /// pointer's last token.
+    fn parent_mut(&mut self, pointer: &str) -> Option<(&mut Node<'a>, String)> {
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        let mut tokens = Vec::new();
+        for token in pointer.split('/').skip(1) {
+            match unescape_pointer_token(token) {
+                Some(token) => tokens.push(token),
+                None => return None,
+            }
+        }
+        let last = tokens.pop().map(Cow::into_owned).unwrap_or_default();
+
+        let mut target = self;
+        for token in tokens {
+            target.open();
+            let target_once = target;
+            let target_opt = match *target_once {
+                Node::Object(ref mut members) => {
+                    match position(members, &token) {
+                        Ok(index) => Some(&mut members[index].1),
+                        Err(_) => None,
+                    }
+                }
+                Node::Array(ref mut elements) => {
+                    parse_index(&token).and_then(move |index| elements.get_mut(index))
+                }
+                _ => None,
+            };
+            match target_opt {
+                Some(t) => target = t,
+                None => return None,
+            }
+        }
+
+        target.open();
+        Some((target, last))
+    }
+
+    fn child(&self, token: &str) -> Option<&Node<'a>> {
+        match *self {
+            Node::Object(ref members) => {
+                position(members, token).ok().map(|index| &members[index].1)
+            }
+            Node::Array(ref elements) => parse_index(token).and_then(|index| elements.get(index)),
+            _ => None,
+        }
+    }
+
+    fn to_cow(&self) -> Cow<'_, Value> {
+        match *self {
+            Node::Base(value) => Cow::Borrowed(value),
+            Node::Owned(ref value) => Cow::Borrowed(value),
+            _ => Cow::Owned(self.to_value()),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match *self {
+            Node::Base(value) => value.clone(),
+            Node::Owned(ref value) => value.clone(),
+            Node::Object(ref members) => {
+                let map: Map<String, Value> = members
+                    .iter()
+                    .map(|(name, node)| (name.clone().into_owned(), node.to_value()))
+                    .collect();
+                Value::Object(map)
+            }
+            Node::Array(ref elements) => Value::Array(elements.iter().map(Node::to_value).collect()),
+        }
+    }
+
+    fn freeze(self) -> Value {
+        match self {
+            Node::Base(value) => value.clone(),
+            Node::Owned(value) => value,
+            Node::Object(members) => {
+                let map: Map<String, Value> = members
+                    .into_iter()
+                    .map(|(name, node)| (name.into_owned(), node.freeze()))
+                    .collect();
+                Value::Object(map)
+            }
+            Node::Array(elements) => Value::Array(elements.into_iter().map(Node::freeze).collect()),
+        }
+    }
+
+    fn eq_value(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Node::Base(value), _) => *value == other,
+            (Node::Owned(value), _) => value == other,
+            // Both are in the order of the map, whichever order that is
+            (Node::Object(members), Value::Object(map)) => {
+                members.len() == map.len() &&
+                    members
+                        .iter()
+                        .zip(map)
+                        .all(|((name, node), (other_name, other))| {
+                            name == other_name && node.eq_value(other)
+                        })
+            }
+            (Node::Array(elements), Value::Array(array)) => {
+                elements.len() == array.len() &&
+                    elements.iter().zip(array).all(|(node, other)| node.eq_value(other))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Where the member of the given name is, or would be inserted, in the
+/// members of an object node.
+fn position(members: &[(Cow<str>, Node)], name: &str) -> Result<usize, usize> {
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        match members.iter().position(|member| member.0 == name) {
+            Some(index) => Ok(index),
+            None => Err(members.len()),
+        }
+    } else {
+        members.binary_search_by(|member| (*member.0).cmp(name))
+    }
+}
+
+impl<'a> PartialEq<Value> for CowValue<'a> {
+    fn eq(&self, other: &Value) -> bool {
+        self.node.eq_value(other)
+    }
+}
+
+impl<'a> Serialize for CowValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.node.serialize(serializer)
+    }
+}
+
+impl<'a> Serialize for Node<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Node::Base(value) => value.serialize(serializer),
+            Node::Owned(ref value) => value.serialize(serializer),
+            Node::Object(ref members) => {
+                let mut map = try!(serializer.serialize_map(Some(members.len())));
+                for (name, node) in members {
+                    try!(map.serialize_entry(&**name, node));
+                }
+                map.end()
+            }
+            Node::Array(ref elements) => {
+                let mut seq = try!(serializer.serialize_seq(Some(elements.len())));
+                for node in elements {
+                    try!(seq.serialize_element(node));
+                }
+                seq.end()
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use set::{self, Set};
+use set::{self, SET_TOKEN, Set};
 use std::borrow::Cow;
 use std::fmt;
 use std::i64;
@@ -28,10 +28,13 @@ use serde::de::{
     Unexpected
 };
 
-use error::Error;
+use data::{Data, DATA_TOKEN};
+use de::{DuplicateElements, FieldMatcher, Normalization};
+use error::{Code, Error};
 use map::Map;
 use number::Number;
-use value::Value;
+use timestamp;
+use value::{Value, read_member};
 
 impl<'de> Deserialize<'de> for Value {
     #[inline]
@@ -81,6 +84,19 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::String(value))
             }
 
+            #[inline]
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_byte_buf(value.to_vec())
+            }
+
+            #[inline]
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Value, E> {
+                Ok(Value::Data(value.into()))
+            }
+
             #[inline]
             fn visit_none<E>(self) -> Result<Value, E> {
                 Ok(Value::Undefined)
@@ -117,10 +133,21 @@ impl<'de> Deserialize<'de> for Value {
             where
                 V: MapAccess<'de>,
             {
-                let mut values = Map::new();
+                let mut key = try!(visitor.next_key::<String>());
+
+                // Data read by `lazy_data`, which is checked again here
+                if key.as_ref().map_or(false, |key| key == DATA_TOKEN) {
+                    let text = try!(visitor.next_value::<String>());
+                    return Data::from_b64url(text)
+                        .map(Value::Data)
+                        .map_err(serde::de::Error::custom);
+                }
 
-                while let Some((key, value)) = try!(visitor.next_entry()) {
-                    values.insert(key, value);
+                let mut values = Map::new();
+                while let Some(name) = key {
+                    let value = read_member(&name, try!(visitor.next_value::<Value>()));
+                    values.insert(name, value);
+                    key = try!(visitor.next_key::<String>());
                 }
 
                 Ok(Value::Object(values))
@@ -164,32 +191,33 @@ impl fmt::Display for Value {
     ///
     /// // Compact format:
     /// //
-    /// // {"city":"London","street":"10 Downing Street"}
+    /// // {"city:s":"London","street:s":"10 Downing Street"}
     /// let compact = format!("{}", json);
     /// assert_eq!(compact,
-    ///     "{\"city\":\"London\",\"street\":\"10 Downing Street\"}");
+    ///     "{\"city:s\":\"London\",\"street:s\":\"10 Downing Street\"}");
     ///
     /// // Pretty format:
     /// //
     /// // {
-    /// //   "city": "London",
-    /// //   "street": "10 Downing Street"
+    /// //   "city:s": "London",
+    /// //   "street:s": "10 Downing Street"
     /// // }
     /// let pretty = format!("{:#}", json);
     /// assert_eq!(pretty,
-    ///     "{\n  \"city\": \"London\",\n  \"street\": \"10 Downing Street\"\n}");
+    ///     "{\n  \"city:s\": \"London\",\n  \"street:s\": \"10 Downing Street\"\n}");
     /// # }
     /// ```
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let alternate = f.alternate();
         let mut wr = WriterFormatter { inner: f };
-        if alternate {
+        let written = if alternate {
             // {:#}
-            super::super::ser::to_writer_pretty(&mut wr, self).map_err(|_| fmt::Error)
+            super::super::ser::to_writer_pretty(&mut wr, self)
         } else {
             // {}
-            super::super::ser::to_writer(&mut wr, self).map_err(|_| fmt::Error)
-        }
+            super::super::ser::to_writer(&mut wr, self)
+        };
+        written.map(|_| ()).map_err(|_| fmt::Error)
     }
 }
 
@@ -200,6 +228,102 @@ impl str::FromStr for Value {
     }
 }
 
+/// Passes the deserialization of numeric types on to the `Number`, which
+/// checks that the value fits the type.
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    Value::Number(n) => n.$method(visitor),
+                    other => other.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+/// Visits `value` as `Value::deserialize_any` does, matching the member
+/// names of any structs in it to their fields by `normalization` and
+/// building any sets in it by `duplicate_elements`.
+fn visit_value<'de, V>(
+    value: Value,
+    normalization: Normalization,
+    duplicate_elements: DuplicateElements,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    match value {
+        Value::Undefined => visitor.visit_unit(),
+        Value::Bool(v) => visitor.visit_bool(v),
+        Value::Data(d) => visitor.visit_bytes(d.as_slice()),
+        Value::Number(n) => serde::Deserializer::deserialize_any(n, visitor),
+        Value::String(v) => visitor.visit_string(v),
+        Value::Timestamp(t) => visitor.visit_string(timestamp::format(&t)),
+        Value::Array(v) => {
+            let len = v.len();
+            let mut deserializer = SeqDeserializer::new(v);
+            deserializer.normalization = normalization;
+            deserializer.duplicate_elements = duplicate_elements;
+            let seq = try!(visitor.visit_seq(&mut deserializer));
+            let remaining = deserializer.iter.len();
+            if remaining == 0 {
+                Ok(seq)
+            } else {
+                Err(serde::de::Error::invalid_length(len, &"fewer elements in array"),)
+            }
+        }
+        Value::Set(v) => {
+            let len = v.len();
+
+            // TODO: avoid allocating here, possibly with a SetDeserializer
+            let mut deserializer = SeqDeserializer::new(v.into_iter().collect());
+            deserializer.normalization = normalization;
+            deserializer.duplicate_elements = duplicate_elements;
+            let seq = try!(visitor.visit_seq(&mut deserializer));
+            let remaining = deserializer.iter.len();
+            if remaining == 0 {
+                Ok(seq)
+            } else {
+                Err(serde::de::Error::invalid_length(len, &"fewer elements in set"),)
+            }
+        }
+        Value::Object(v) => visit_object(v, normalization, duplicate_elements, None, visitor),
+    }
+}
+
+/// Visits the members of an object, matching their names to the given
+/// struct fields if there are any.
+fn visit_object<'de, V>(
+    object: Map<String, Value>,
+    normalization: Normalization,
+    duplicate_elements: DuplicateElements,
+    fields: Option<FieldMatcher>,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    let len = object.len();
+    let mut deserializer = MapDeserializer::new(object);
+    deserializer.normalization = normalization;
+    deserializer.duplicate_elements = duplicate_elements;
+    deserializer.fields = fields;
+    let map = try!(visitor.visit_map(&mut deserializer));
+    let remaining = deserializer.iter.len();
+    if remaining == 0 {
+        Ok(map)
+    } else {
+        Err(serde::de::Error::invalid_length(len, &"fewer elements in map"),)
+    }
+}
+
 impl<'de> serde::Deserializer<'de> for Value {
     type Error = Error;
 
@@ -208,50 +332,7 @@ impl<'de> serde::Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::Undefined => visitor.visit_unit(),
-            Value::Bool(v) => visitor.visit_bool(v),
-            Value::Data(d) => visitor.visit_bytes(d.as_slice()),
-            Value::Number(n) => n.deserialize_any(visitor),
-            Value::String(v) => visitor.visit_string(v),
-            // TODO(tarcieri): hax!
-            Value::Timestamp(t) => visitor.visit_string(t.to_rfc3339()),
-            Value::Array(v) => {
-                let len = v.len();
-                let mut deserializer = SeqDeserializer::new(v);
-                let seq = try!(visitor.visit_seq(&mut deserializer));
-                let remaining = deserializer.iter.len();
-                if remaining == 0 {
-                    Ok(seq)
-                } else {
-                    Err(serde::de::Error::invalid_length(len, &"fewer elements in array"),)
-                }
-            }
-            Value::Set(v) => {
-                let len = v.len();
-
-                // TODO: avoid allocating here, possibly with a SetDeserializer
-                let mut deserializer = SeqDeserializer::new(v.into_iter().collect());
-                let seq = try!(visitor.visit_seq(&mut deserializer));
-                let remaining = deserializer.iter.len();
-                if remaining == 0 {
-                    Ok(seq)
-                } else {
-                    Err(serde::de::Error::invalid_length(len, &"fewer elements in set"),)
-                }
-            }
-            Value::Object(v) => {
-                let len = v.len();
-                let mut deserializer = MapDeserializer::new(v);
-                let map = try!(visitor.visit_map(&mut deserializer));
-                let remaining = deserializer.iter.len();
-                if remaining == 0 {
-                    Ok(map)
-                } else {
-                    Err(serde::de::Error::invalid_length(len, &"fewer elements in map"),)
-                }
-            }
-        }
+        visit_value(self, Normalization::Exact, DuplicateElements::Error, visitor)
     }
 
     #[inline]
@@ -317,19 +398,38 @@ impl<'de> serde::Deserializer<'de> for Value {
     #[inline]
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
+        #[cfg(feature = "decimal")]
+        {
+            if let Value::Number(n) = self {
+                if name == ::number::DECIMAL_TOKEN {
+                    return n.deserialize_newtype_struct(name, visitor);
+                }
+            }
+        }
+
+        if name == SET_TOKEN {
+            let value = try!(set::from_elements(self, DuplicateElements::Error));
+            return visitor.visit_newtype_struct(value);
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
-        ignored_any
+        bool char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
     }
 }
 
@@ -411,11 +511,17 @@ impl<'de> VariantAccess<'de> for VariantDeserializer {
 
 struct SeqDeserializer {
     iter: vec::IntoIter<Value>,
+    normalization: Normalization,
+    duplicate_elements: DuplicateElements,
 }
 
 impl SeqDeserializer {
     fn new(vec: Vec<Value>) -> Self {
-        SeqDeserializer { iter: vec.into_iter() }
+        SeqDeserializer {
+            iter: vec.into_iter(),
+            normalization: Normalization::Exact,
+            duplicate_elements: DuplicateElements::Error,
+        }
     }
 }
 
@@ -456,7 +562,11 @@ impl<'de> SeqAccess<'de> for SeqDeserializer {
         T: DeserializeSeed<'de>,
     {
         match self.iter.next() {
-            Some(value) => seed.deserialize(value).map(Some),
+            Some(value) => {
+                NormalizedValue::new(value, self.normalization, self.duplicate_elements)
+                    .deserialize_seed(seed)
+                    .map(Some)
+            }
             None => Ok(None),
         }
     }
@@ -472,6 +582,10 @@ impl<'de> SeqAccess<'de> for SeqDeserializer {
 struct MapDeserializer {
     iter: <Map<String, Value> as IntoIterator>::IntoIter,
     value: Option<Value>,
+    normalization: Normalization,
+    duplicate_elements: DuplicateElements,
+    /// Set when deserializing a struct with a `Normalization`.
+    fields: Option<FieldMatcher>,
 }
 
 impl MapDeserializer {
@@ -479,6 +593,9 @@ impl MapDeserializer {
         MapDeserializer {
             iter: map.into_iter(),
             value: None,
+            normalization: Normalization::Exact,
+            duplicate_elements: DuplicateElements::Error,
+            fields: None,
         }
     }
 }
@@ -493,8 +610,17 @@ impl<'de> MapAccess<'de> for MapDeserializer {
         match self.iter.next() {
             Some((key, value)) => {
                 self.value = Some(value);
-                let key_de = MapKeyDeserializer { key: Cow::Owned(key) };
-                seed.deserialize(key_de).map(Some)
+                let field = match self.fields {
+                    Some(ref mut fields) => {
+                        try!(fields.resolve(&key).map_err(|code| Error::syntax(code, 0, 0)))
+                    }
+                    None => None,
+                };
+                let key = match field {
+                    Some(field) => Cow::Borrowed(field),
+                    None => Cow::Owned(key),
+                };
+                seed.deserialize(MapKeyDeserializer { key: key }).map(Some)
             }
             None => Ok(None),
         }
@@ -505,7 +631,10 @@ impl<'de> MapAccess<'de> for MapDeserializer {
         T: DeserializeSeed<'de>,
     {
         match self.value.take() {
-            Some(value) => seed.deserialize(value),
+            Some(value) => {
+                NormalizedValue::new(value, self.normalization, self.duplicate_elements)
+                    .deserialize_seed(seed)
+            }
             None => Err(serde::de::Error::custom("value is missing")),
         }
     }
@@ -536,6 +665,152 @@ impl<'de> serde::Deserializer<'de> for MapDeserializer {
     }
 }
 
+// Not public API. Should be pub(crate).
+/// A `Value` which matches the member names of the structs in it to their
+/// fields by a `Normalization`, as `tjson::value::from_value_normalized`
+/// does, and builds the sets in it by a `DuplicateElements` policy, as
+/// `tjson::value::from_value_with` does.
+#[doc(hidden)]
+pub struct NormalizedValue {
+    value: Value,
+    normalization: Normalization,
+    duplicate_elements: DuplicateElements,
+}
+
+impl NormalizedValue {
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn new(
+        value: Value,
+        normalization: Normalization,
+        duplicate_elements: DuplicateElements,
+    ) -> Self {
+        NormalizedValue {
+            value: value,
+            normalization: normalization,
+            duplicate_elements: duplicate_elements,
+        }
+    }
+
+    /// Hands the value to `seed`, unwrapped unless there is a normalization
+    /// or set policy to apply, so that the defaults go through `Value`
+    /// unchanged.
+    fn deserialize_seed<'de, T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match (self.normalization, self.duplicate_elements) {
+            (Normalization::Exact, DuplicateElements::Error) => seed.deserialize(self.value),
+            _ => seed.deserialize(self),
+        }
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($deserialize:ident)*) => {
+        $(
+            #[inline]
+            fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.value.$deserialize(visitor)
+            }
+        )*
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for NormalizedValue {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visit_value(self.value, self.normalization, self.duplicate_elements, visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Undefined => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Object(v) => {
+                let fields = FieldMatcher::new(fields, self.normalization);
+                visit_object(v, self.normalization, self.duplicate_elements, Some(fields), visitor)
+            }
+            value => visit_value(value, self.normalization, self.duplicate_elements, visitor),
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        #[cfg(feature = "decimal")]
+        {
+            if name == ::number::DECIMAL_TOKEN {
+                return self.value.deserialize_newtype_struct(name, visitor);
+            }
+        }
+
+        if name == SET_TOKEN {
+            let value = try!(set::from_elements(self.value, self.duplicate_elements));
+            let value = NormalizedValue::new(value, self.normalization, self.duplicate_elements);
+            return visitor.visit_newtype_struct(value);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    // The variants of enums are matched exactly
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_value! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+    }
+
+    forward_to_deserialize_any! {
+        bool char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map identifier ignored_any
+    }
+}
+
 impl<'de> serde::Deserializer<'de> for &'de Value {
     type Error = Error;
 
@@ -546,11 +821,10 @@ impl<'de> serde::Deserializer<'de> for &'de Value {
         match *self {
             Value::Undefined => visitor.visit_unit(),
             Value::Bool(v) => visitor.visit_bool(v),
-            Value::Data(ref d) => visitor.visit_borrowed_bytes(d),
+            Value::Data(ref d) => visitor.visit_borrowed_bytes(d.as_slice()),
             Value::Number(ref n) => n.deserialize_any(visitor),
             Value::String(ref v) => visitor.visit_borrowed_str(v),
-            // TODO(tarcieri): hax!
-            Value::Timestamp(ref _t) => visitor.visit_unit(),
+            Value::Timestamp(ref t) => visitor.visit_string(timestamp::format(t)),
             Value::Array(ref v) => {
                 let len = v.len();
                 let mut deserializer = SeqRefDeserializer::new(v);
@@ -648,19 +922,44 @@ impl<'de> serde::Deserializer<'de> for &'de Value {
     #[inline]
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
+        #[cfg(feature = "decimal")]
+        {
+            if let Value::Number(ref n) = *self {
+                if name == ::number::DECIMAL_TOKEN {
+                    return n.deserialize_newtype_struct(name, visitor);
+                }
+            }
+        }
+
+        // Borrowed elements can't be merged, so duplicates are always errors
+        if let Value::Array(ref elements) = *self {
+            if name == SET_TOKEN {
+                if let Some(index) = set::first_duplicate(elements) {
+                    let element = elements[index].to_string();
+                    let code = Code::DuplicateElement(index, element, String::new());
+                    return Err(Error::syntax(code, 0, 0));
+                }
+            }
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
-        ignored_any
+        bool char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
     }
 }
 
@@ -1011,7 +1310,7 @@ impl Value {
         match *self {
             Value::Undefined => Unexpected::Unit,
             Value::Bool(b) => Unexpected::Bool(b),
-            Value::Data(ref d) => Unexpected::Bytes(d),
+            Value::Data(ref d) => Unexpected::Bytes(d.as_slice()),
             Value::Number(ref n) => n.unexpected(),
             Value::String(ref s) => Unexpected::Str(s),
             Value::Timestamp(_) => Unexpected::Other("timestamp"),
@@ -0,0 +1,160 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Stable fingerprints of `Value`s
+
+use std::hash::Hasher;
+
+use super::Value;
+use number::Number;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Value {
+    /// A 64-bit fingerprint of the value which, unlike its `Hash`
+    /// implementation, does not depend on the platform, the Rust version, or
+    /// the hasher in use.
+    ///
+    /// Equal values always have equal fingerprints. Values which differ almost
+    /// always have different fingerprints, but this is not guaranteed, so the
+    /// fingerprint is suitable for detecting changes but not as an identity.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let a = tjson!({ "x": [1, 2, 3] });
+    /// let mut b = a.clone();
+    /// assert_eq!(a.hash_fingerprint(), b.hash_fingerprint());
+    ///
+    /// b["x"][0] = tjson!(4);
+    /// assert!(a.hash_fingerprint() != b.hash_fingerprint());
+    /// # }
+    /// ```
+    pub fn hash_fingerprint(&self) -> u64 {
+        let mut fnv = Fnv(FNV_OFFSET_BASIS);
+        self.hash_canonical(&mut fnv);
+        fnv.0
+    }
+
+    // Not public API. Should be pub(crate).
+    /// Feeds the encoding which `hash_fingerprint` hashes to another hasher,
+    /// so that equal values hash alike whatever the hasher.
+    #[doc(hidden)]
+    pub fn hash_canonical<H: Hasher>(&self, state: &mut H) {
+        Canonical(state).value(self);
+    }
+}
+
+/// FNV-1a.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Writes a canonical encoding of a value to a hasher: a type byte followed
+/// by fixed-width little endian lengths and scalars.
+struct Canonical<'a, H: Hasher + 'a>(&'a mut H);
+
+impl<'a, H: Hasher> Canonical<'a, H> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn u64(&mut self, n: u64) {
+        let mut bytes = [0; 8];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (n >> (8 * i)) as u8;
+        }
+        self.bytes(&bytes);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u64(len as u64);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.bytes(s.as_bytes());
+    }
+
+    fn number(&mut self, n: &Number) {
+        if let Some(u) = n.as_u64() {
+            self.bytes(&[0]);
+            self.u64(u);
+        } else if let Some(i) = n.as_i64() {
+            self.bytes(&[1]);
+            self.u64(i as u64);
+        } else {
+            // Negative zero compares equal to zero
+            let f = n.as_f64().unwrap_or(0.0);
+            let f = if f == 0.0 { 0.0 } else { f };
+            self.bytes(&[2]);
+            self.u64(f.to_bits());
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match *value {
+            Value::Undefined => self.bytes(&[0]),
+            Value::Bool(b) => self.bytes(&[1, b as u8]),
+            Value::Data(ref d) => {
+                self.bytes(&[2]);
+                self.len(d.len());
+                self.bytes(d);
+            }
+            Value::Number(ref n) => {
+                self.bytes(&[3]);
+                self.number(n);
+            }
+            Value::String(ref s) => {
+                self.bytes(&[4]);
+                self.str(s);
+            }
+            Value::Timestamp(ref t) => {
+                self.bytes(&[5]);
+                self.u64(t.timestamp() as u64);
+                self.u64(t.timestamp_subsec_nanos() as u64);
+            }
+            Value::Array(ref array) => {
+                self.bytes(&[6]);
+                self.len(array.len());
+                for element in array {
+                    self.value(element);
+                }
+            }
+            Value::Set(ref set) => {
+                self.bytes(&[7]);
+                self.len(set.len());
+                for element in set {
+                    self.value(element);
+                }
+            }
+            Value::Object(ref map) => {
+                self.bytes(&[8]);
+                self.len(map.len());
+                for (key, value) in map {
+                    self.str(key);
+                    self.value(value);
+                }
+            }
+        }
+    }
+}
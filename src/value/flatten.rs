@@ -0,0 +1,492 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Flattening `Value`s into JSON Pointers and their leaf values, and back
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Write};
+use std::mem;
+
+use map::Map;
+use set::Set;
+use super::{Value, parse_index, push_pointer_token, unescape_pointer_token};
+use super::stats::{Children, Token};
+
+/// How `Value::flatten_with` treats arrays and sets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArrayMode {
+    /// Flatten each element in turn, with its index as its token, like
+    /// `/list/0` (the default).
+    Positional,
+
+    /// Give each array or set as a single leaf at its own path, like `/list`.
+    Joined,
+}
+
+/// Settings for `Value::flatten_with`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlattenOptions {
+    arrays: ArrayMode,
+    sets: bool,
+    max_depth: Option<usize>,
+}
+
+impl FlattenOptions {
+    /// Settings which flatten as `Value::flatten` does: arrays and sets
+    /// positionally, to any depth.
+    pub fn new() -> Self {
+        FlattenOptions {
+            arrays: ArrayMode::Positional,
+            sets: true,
+            max_depth: None,
+        }
+    }
+
+    /// Set how arrays and sets are flattened.
+    pub fn array_mode(mut self, mode: ArrayMode) -> Self {
+        self.arrays = mode;
+        self
+    }
+
+    /// Set whether sets and everything inside them are included. They are
+    /// by default.
+    pub fn include_sets(mut self, include: bool) -> Self {
+        self.sets = include;
+        self
+    }
+
+    /// Give containers this many levels down as leaves rather than
+    /// flattening them. The members or elements of the value itself are one
+    /// level down, so a depth of 0 gives the whole value as one leaf.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions::new()
+    }
+}
+
+/// An iterator over the leaves of a `Value` and their JSON Pointers, created
+/// by `Value::flatten` and `Value::flatten_with`.
+///
+/// Leaves are found as they are asked for, walking the value with an
+/// explicit stack, so taking the first few leaves of a large value is cheap.
+pub struct Flatten<'a> {
+    options: FlattenOptions,
+    root: Option<&'a Value>,
+    stack: Vec<Frame<'a>>,
+    path: String,
+}
+
+struct Frame<'a> {
+    /// Length of the path to the container's parent
+    parent_len: usize,
+    children: Children<'a>,
+}
+
+impl<'a> Flatten<'a> {
+    /// Either yields the value at the end of the path as a leaf, or starts
+    /// walking inside it.
+    fn visit(&mut self, value: &'a Value, parent_len: usize) -> Option<(String, &'a Value)> {
+        let within_depth = self.options.max_depth.map_or(true, |max| self.stack.len() < max);
+        let positional = self.options.arrays == ArrayMode::Positional;
+        let children = match *value {
+            Value::Set(_) if !self.options.sets => {
+                self.path.truncate(parent_len);
+                return None;
+            }
+            Value::Object(ref map) if within_depth && !map.is_empty() => {
+                Children::Object(map.iter())
+            }
+            Value::Array(ref array) if within_depth && positional && !array.is_empty() => {
+                Children::Array(array.iter().enumerate())
+            }
+            Value::Set(ref set) if within_depth && positional && !set.is_empty() => {
+                Children::Set(set.iter().enumerate())
+            }
+            _ => {
+                let path = self.path.clone();
+                self.path.truncate(parent_len);
+                return Some((path, value));
+            }
+        };
+
+        self.stack.push(
+            Frame {
+                parent_len: parent_len,
+                children: children,
+            },
+        );
+        None
+    }
+}
+
+impl<'a> Iterator for Flatten<'a> {
+    type Item = (String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if let Some(leaf) = self.visit(root, 0) {
+                return Some(leaf);
+            }
+        }
+
+        loop {
+            let next = match self.stack.last_mut() {
+                Some(frame) => frame.children.next(),
+                None => return None,
+            };
+            let (token, value) = match next {
+                Some(child) => child,
+                None => {
+                    if let Some(frame) = self.stack.pop() {
+                        self.path.truncate(frame.parent_len);
+                    }
+                    continue;
+                }
+            };
+
+            let parent_len = self.path.len();
+            match token {
+                Token::Root => {}
+                Token::Index(index) => {
+                    let _ = write!(self.path, "/{}", index);
+                }
+                Token::Name(name) => push_pointer_token(&mut self.path, name),
+            }
+            if let Some(leaf) = self.visit(value, parent_len) {
+                return Some(leaf);
+            }
+        }
+    }
+}
+
+/// Why `Value::from_flat` could not build a value, and at which of the
+/// pointers it was given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlatError {
+    path: String,
+    kind: FlatErrorKind,
+}
+
+/// The reasons `Value::from_flat` can fail.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlatErrorKind {
+    /// The pointer was given before, or is inside a value given at a shorter
+    /// pointer, or a value was given at a shorter pointer after it.
+    Conflict,
+
+    /// The pointer isn't empty and doesn't start with `/`, or has a `~`
+    /// which isn't followed by `0` or `1`.
+    Syntax,
+
+    /// A member tagged as an array or set has an element whose token isn't
+    /// an index, or whose indices don't run from 0 without gaps.
+    BadIndex,
+}
+
+impl FlatError {
+    fn new(path: &str, kind: FlatErrorKind) -> Self {
+        FlatError {
+            path: path.to_owned(),
+            kind: kind,
+        }
+    }
+
+    /// The pointer at which the problem was found.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// What the problem is.
+    pub fn kind(&self) -> FlatErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for FlatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.kind {
+            FlatErrorKind::Conflict => "conflicting value",
+            FlatErrorKind::Syntax => "invalid JSON Pointer",
+            FlatErrorKind::BadIndex => "missing or invalid array index",
+        };
+        write!(f, "{} at {:?}", reason, self.path)
+    }
+}
+
+impl error::Error for FlatError {
+    fn description(&self) -> &str {
+        "flattened values do not form a document"
+    }
+}
+
+/// A value being rebuilt from flattened pairs.
+enum Node {
+    Leaf(Value),
+    Branch(Branch),
+}
+
+/// The children of a container, in the order they were first given.
+#[derive(Default)]
+struct Branch {
+    children: Vec<(String, Node)>,
+    index: HashMap<String, usize>,
+}
+
+impl Branch {
+    /// The child container with this token, created if there is no child
+    /// with it yet, or `None` if there is a leaf with it.
+    fn branch(&mut self, token: &str) -> Option<&mut Branch> {
+        let index = match self.index.get(token) {
+            Some(&index) => index,
+            None => {
+                self.index.insert(token.to_owned(), self.children.len());
+                self.children.push((token.to_owned(), Node::Branch(Branch::default())));
+                self.children.len() - 1
+            }
+        };
+        match self.children[index].1 {
+            Node::Branch(ref mut branch) => Some(branch),
+            Node::Leaf(_) => None,
+        }
+    }
+
+    /// Adds a leaf, unless there is already a child with this token.
+    fn leaf(&mut self, token: String, value: Value) -> bool {
+        if self.index.contains_key(&token) {
+            return false;
+        }
+        self.index.insert(token.clone(), self.children.len());
+        self.children.push((token, Node::Leaf(value)));
+        true
+    }
+}
+
+fn insert(root: &mut Option<Node>, pointer: &str, value: Value) -> Result<(), FlatError> {
+    let conflict = || FlatError::new(pointer, FlatErrorKind::Conflict);
+    if pointer.is_empty() {
+        if root.is_some() {
+            return Err(conflict());
+        }
+        *root = Some(Node::Leaf(value));
+        return Ok(());
+    }
+    if !pointer.starts_with('/') {
+        return Err(FlatError::new(pointer, FlatErrorKind::Syntax));
+    }
+
+    let mut branch = match *root.get_or_insert_with(|| Node::Branch(Branch::default())) {
+        Node::Branch(ref mut branch) => branch,
+        Node::Leaf(_) => return Err(conflict()),
+    };
+    let mut tokens = pointer[1..].split('/').peekable();
+    while let Some(token) = tokens.next() {
+        let token = match unescape_pointer_token(token) {
+            Some(token) => token,
+            None => return Err(FlatError::new(pointer, FlatErrorKind::Syntax)),
+        };
+        if tokens.peek().is_none() {
+            if branch.leaf(token.into_owned(), value) {
+                return Ok(());
+            }
+            return Err(conflict());
+        }
+        branch = match branch.branch(&token) {
+            Some(branch) => branch,
+            None => return Err(conflict()),
+        };
+    }
+    Ok(())
+}
+
+/// Builds the value of a member or element with the given tag, which is
+/// empty if it has none.
+fn build(node: Node, tag: &str, path: &mut String) -> Result<Value, FlatError> {
+    let branch = match node {
+        Node::Leaf(value) => return Ok(value),
+        Node::Branch(branch) => branch,
+    };
+
+    let sequence = tag.starts_with("A<") || tag.starts_with("S<");
+    if !sequence && (!tag.is_empty() || !is_sequence(&branch)) {
+        let mut map = Map::new();
+        for (name, node) in branch.children {
+            let len = path.len();
+            push_pointer_token(path, &name);
+            let tag = name.rfind(':').map_or("", |colon| &name[colon + 1..]);
+            let value = try!(build(node, tag, path));
+            path.truncate(len);
+            map.insert(name, value);
+        }
+        return Ok(Value::Object(map));
+    }
+
+    let mut elements = Vec::with_capacity(branch.children.len());
+    for (token, node) in branch.children {
+        match parse_index(&token) {
+            Some(index) => elements.push((index, node)),
+            None => {
+                push_pointer_token(path, &token);
+                return Err(FlatError::new(path, FlatErrorKind::BadIndex));
+            }
+        }
+    }
+    elements.sort_by_key(|&(index, _)| index);
+
+    let element_tag = if sequence && tag.ends_with('>') { &tag[2..tag.len() - 1] } else { "" };
+    let mut values = Vec::with_capacity(elements.len());
+    for (position, (index, node)) in elements.into_iter().enumerate() {
+        let len = path.len();
+        let _ = write!(path, "/{}", index);
+        if index != position {
+            return Err(FlatError::new(path, FlatErrorKind::BadIndex));
+        }
+        values.push(try!(build(node, element_tag, path)));
+        path.truncate(len);
+    }
+
+    if tag.starts_with("S<") {
+        let mut set = Set::new();
+        for value in values {
+            set.insert(value);
+        }
+        return Ok(Value::Set(set));
+    }
+    Ok(Value::Array(values))
+}
+
+/// Whether the tokens of an untagged container are the indices of an array.
+fn is_sequence(branch: &Branch) -> bool {
+    let len = branch.children.len();
+    let mut seen = vec![false; len];
+    for &(ref token, _) in &branch.children {
+        match parse_index(token) {
+            Some(index) if index < len && !mem::replace(&mut seen[index], true) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+impl Value {
+    /// Every leaf of the value, with its JSON Pointer, as accepted by
+    /// `Value::pointer`.
+    ///
+    /// Leaves are the values which aren't arrays, sets or objects, along
+    /// with empty arrays, sets and objects. They are given in document
+    /// order, and elements of arrays and sets have their index as their
+    /// token, like `/list/0`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let value = tjson!({ "name:s": "db", "ports:A<u>": [5432, 5433], "tags:O": {} });
+    ///
+    /// let flat: Vec<(String, &tjson::Value)> = value.flatten().collect();
+    /// assert_eq!(flat, vec![
+    ///     ("/name:s".to_owned(), &tjson!("db")),
+    ///     ("/ports:A<u>/0".to_owned(), &tjson!(5432)),
+    ///     ("/ports:A<u>/1".to_owned(), &tjson!(5433)),
+    ///     ("/tags:O".to_owned(), &tjson!({})),
+    /// ]);
+    /// # }
+    /// ```
+    pub fn flatten(&self) -> Flatten {
+        self.flatten_with(FlattenOptions::new())
+    }
+
+    /// Every leaf of the value, with its JSON Pointer, flattened with the
+    /// given settings.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::value::{ArrayMode, FlattenOptions};
+    ///
+    /// let value = tjson!({ "db:O": { "ports:A<u>": [5432, 5433], "tls:O": { "on:b": true } } });
+    ///
+    /// let options = FlattenOptions::new().array_mode(ArrayMode::Joined).max_depth(2);
+    /// let flat: Vec<(String, &tjson::Value)> = value.flatten_with(options).collect();
+    /// assert_eq!(flat, vec![
+    ///     ("/db:O/ports:A<u>".to_owned(), &tjson!([5432, 5433])),
+    ///     ("/db:O/tls:O".to_owned(), &tjson!({ "on:b": true })),
+    /// ]);
+    /// # }
+    /// ```
+    pub fn flatten_with(&self, options: FlattenOptions) -> Flatten {
+        Flatten {
+            options: options,
+            root: Some(self),
+            stack: Vec::new(),
+            path: String::new(),
+        }
+    }
+
+    /// Builds a value from JSON Pointers and the values at them, reversing
+    /// `Value::flatten`.
+    ///
+    /// Members are added in the order they are first given. A container's
+    /// kind comes from the tag of its member: `A<..>` gives an array, `S<..>`
+    /// a set, and any other tag an object. A container without a tag, like
+    /// the value itself, is an array if its tokens are exactly the indices
+    /// from 0, and otherwise an object. Giving no pairs at all builds an
+    /// empty object.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `FlatErrorKind::Conflict` if a pointer is given twice, or
+    /// a value is given both at a pointer and inside it, with
+    /// `FlatErrorKind::Syntax` if a pointer is malformed, and with
+    /// `FlatErrorKind::BadIndex` if an array or set tagged as one isn't
+    /// given the elements at 0 up to its length.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::Value;
+    /// use tjson::value::FlatErrorKind;
+    ///
+    /// let value = Value::from_flat(vec![
+    ///     ("/db:O/host:s", tjson!("localhost")),
+    ///     ("/db:O/ports:A<u>/0", tjson!(5432)),
+    /// ]).unwrap();
+    /// assert_eq!(value, tjson!({ "db:O": { "host:s": "localhost", "ports:A<u>": [5432] } }));
+    ///
+    /// let err = Value::from_flat(vec![("/a:s", tjson!("x")), ("/a:s/b:s", tjson!("y"))])
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), FlatErrorKind::Conflict);
+    /// assert_eq!(err.path(), "/a:s/b:s");
+    /// # }
+    /// ```
+    pub fn from_flat<I, S>(pairs: I) -> Result<Value, FlatError>
+    where
+        I: IntoIterator<Item = (S, Value)>,
+        S: AsRef<str>,
+    {
+        let mut root = None;
+        for (pointer, value) in pairs {
+            try!(insert(&mut root, pointer.as_ref(), value));
+        }
+        match root {
+            Some(root) => build(root, "", &mut String::new()),
+            None => Ok(Value::Object(Map::new())),
+        }
+    }
+}
@@ -8,7 +8,7 @@
 
 use std::borrow::Cow;
 
-use super::Value;
+use super::{DateTime, Value};
 use map::Map;
 use number::Number;
 
@@ -162,6 +162,33 @@ impl<'a> From<Cow<'a, str>> for Value {
     }
 }
 
+impl From<DateTime> for Value {
+    /// Convert a UTC `DateTime` to `Value`
+    ///
+    /// Unlike `tjson!(timestamp)`, which serializes the `DateTime` and so
+    /// gives a String unless it is the value of a member tagged `t`, this
+    /// always gives a Timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate chrono;
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use chrono::offset::utc::UTC;
+    /// use tjson::Value;
+    ///
+    /// let now = UTC::now();
+    /// let x: Value = now.into();
+    /// assert_eq!(x.as_timestamp(), Some(&now));
+    /// # }
+    /// ```
+    fn from(f: DateTime) -> Self {
+        Value::Timestamp(f)
+    }
+}
+
 impl From<Map<String, Value>> for Value {
     /// Convert map (with string keys) to `Value`
     ///
@@ -11,7 +11,6 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fmt;
 use std::ops;
 
 use super::Value;
@@ -37,6 +36,11 @@ pub trait Index: private::Sealed {
     /// object.
     #[doc(hidden)]
     fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value;
+
+    /// What the index points at, as in `member "a"` or `element 0`, for
+    /// errors.
+    #[doc(hidden)]
+    fn describe(&self) -> String;
 }
 
 impl Index for usize {
@@ -67,9 +71,12 @@ impl Index for usize {
                         },
                     )
             }
-            _ => panic!("cannot access index {} of JSON {}", self, Type(v)),
+            _ => panic!("cannot access index {} of JSON {}", self, v.kind()),
         }
     }
+    fn describe(&self) -> String {
+        format!("element {}", self)
+    }
 }
 
 impl Index for str {
@@ -93,9 +100,12 @@ impl Index for str {
         }
         match *v {
             Value::Object(ref mut map) => map.entry(self.to_owned()).or_insert(Value::Undefined),
-            _ => panic!("cannot access key {:?} in JSON {}", self, Type(v)),
+            _ => panic!("cannot access key {:?} in JSON {}", self, v.kind()),
         }
     }
+    fn describe(&self) -> String {
+        format!("member {:?}", self)
+    }
 }
 
 impl Index for String {
@@ -108,6 +118,9 @@ impl Index for String {
     fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
         self[..].index_or_insert(v)
     }
+    fn describe(&self) -> String {
+        self[..].describe()
+    }
 }
 
 impl<'a, T: ?Sized> Index for &'a T
@@ -123,6 +136,9 @@ where
     fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
         (**self).index_or_insert(v)
     }
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
 }
 
 // Prevent users from implementing the Index trait.
@@ -138,25 +154,6 @@ mod private {
     }
 }
 
-/// Used in panic messages.
-struct Type<'a>(&'a Value);
-
-impl<'a> fmt::Display for Type<'a> {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match *self.0 {
-            Value::Undefined => formatter.write_str("undefined"),
-            Value::Bool(_) => formatter.write_str("boolean"),
-            Value::Data(_) => formatter.write_str("data"),
-            Value::Number(_) => formatter.write_str("number"),
-            Value::String(_) => formatter.write_str("string"),
-            Value::Timestamp(_) => formatter.write_str("timestamp"),
-            Value::Array(_) => formatter.write_str("array"),
-            Value::Set(_) => formatter.write_str("set"),
-            Value::Object(_) => formatter.write_str("object"),
-        }
-    }
-}
-
 // The usual semantics of Index is to panic on invalid indexing.
 //
 // That said, the usual semantics are for things like Vec and BTreeMap which
@@ -0,0 +1,175 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lossy conversions between the kinds of `Value`, for reports and other
+//! places which want something to show rather than an error
+
+use std::borrow::Cow;
+use std::i64;
+
+use de;
+use encoding;
+use number::Number;
+use ser;
+use timestamp;
+use super::{Value, ValueKind};
+
+impl Value {
+    /// Converts the value into one of the given kind, if there is a sensible
+    /// way to, following this table. Rules higher up the table win.
+    ///
+    /// | From                 | To          | Result                                   |
+    /// |----------------------|-------------|------------------------------------------|
+    /// | any kind             | the same    | a copy of the value                      |
+    /// | array of one element | another     | that element converted                   |
+    /// | undefined            | another     | `None`                                   |
+    /// | boolean              | string      | `"true"` or `"false"`                    |
+    /// | number               | string      | the number as `tjson::to_string` writes it |
+    /// | timestamp            | string      | RFC 3339 in UTC, as TJSON writes it      |
+    /// | data                 | string      | unpadded base64url, as the `d` tag       |
+    /// | array, set or object | string      | the value as `tjson::to_string` writes it |
+    /// | string               | number      | the string parsed as a TJSON number      |
+    /// | string               | boolean     | `"true"` or `"1"`, `"false"` or `"0"`    |
+    /// | string               | timestamp   | the string parsed as RFC 3339, in UTC    |
+    /// | anything else        |             | `None`                                   |
+    ///
+    /// So a string which doesn't parse as the kind asked for gives `None`,
+    /// as do data, arrays, sets and objects asked to be anything but the
+    /// same kind or a string. Numbers stay as they are: to read a float
+    /// with no fractional part as an integer, use `as_i64_lossy`.
+    ///
+    /// Never panics, and leaves the value as it is.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::value::ValueKind;
+    ///
+    /// assert_eq!(tjson!(1.5).coerce_lossy(ValueKind::String), Some(tjson!("1.5")));
+    /// assert_eq!(tjson!(["7"]).coerce_lossy(ValueKind::Number), Some(tjson!(7)));
+    /// assert_eq!(tjson!("seven").coerce_lossy(ValueKind::Number), None);
+    /// # }
+    /// ```
+    pub fn coerce_lossy(&self, target: ValueKind) -> Option<Value> {
+        if self.kind() == target {
+            return Some(self.clone());
+        }
+        if let Value::Array(ref elements) = *self {
+            if elements.len() == 1 {
+                return elements[0].coerce_lossy(target);
+            }
+        }
+
+        match (self, target) {
+            (&Value::Undefined, _) => None,
+            (_, ValueKind::String) => self.lossy_string().map(Value::String),
+            (&Value::String(ref text), ValueKind::Number) => parse_number(text).map(Value::Number),
+            (&Value::String(ref text), ValueKind::Bool) => parse_bool(text).map(Value::Bool),
+            (&Value::String(ref text), ValueKind::Timestamp) => {
+                timestamp::parse(text).map(|(timestamp, _)| Value::Timestamp(timestamp))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value as a string, by `coerce_lossy`. Borrows strings, including
+    /// the one in an array of one string, rather than copying them.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::Value;
+    ///
+    /// assert_eq!(tjson!("x").as_str_lossy().unwrap(), "x");
+    /// assert_eq!(tjson!(true).as_str_lossy().unwrap(), "true");
+    /// assert_eq!(Value::Data(vec![1, 2].into()).as_str_lossy().unwrap(), "AQI");
+    /// # }
+    /// ```
+    pub fn as_str_lossy(&self) -> Option<Cow<str>> {
+        match *self {
+            Value::String(ref text) => Some(Cow::Borrowed(text)),
+            Value::Array(ref elements) if elements.len() == 1 => elements[0].as_str_lossy(),
+            _ => self.lossy_string().map(Cow::Owned),
+        }
+    }
+
+    /// The value as an `i64`, from a number or a string parsed as one, by
+    /// `coerce_lossy`. Floats with no fractional part which are in range
+    /// are converted; others give `None`, as do integers out of range.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// assert_eq!(tjson!(3.0).as_i64_lossy(), Some(3));
+    /// assert_eq!(tjson!("-12").as_i64_lossy(), Some(-12));
+    /// assert_eq!(tjson!(3.5).as_i64_lossy(), None);
+    /// # }
+    /// ```
+    pub fn as_i64_lossy(&self) -> Option<i64> {
+        match *self {
+            Value::Number(ref n) => integral_i64(n),
+            Value::String(ref text) => parse_number(text).and_then(|n| integral_i64(&n)),
+            Value::Array(ref elements) if elements.len() == 1 => elements[0].as_i64_lossy(),
+            _ => None,
+        }
+    }
+
+    /// The value as a `bool`, from a boolean or one of the strings `"true"`,
+    /// `"false"`, `"1"` and `"0"`, by `coerce_lossy`.
+    pub fn as_bool_lossy(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            Value::String(ref text) => parse_bool(text),
+            Value::Array(ref elements) if elements.len() == 1 => elements[0].as_bool_lossy(),
+            _ => None,
+        }
+    }
+
+    /// The text `coerce_lossy` gives for a value which isn't a string.
+    fn lossy_string(&self) -> Option<String> {
+        match *self {
+            Value::Undefined => None,
+            Value::String(ref text) => Some(text.clone()),
+            Value::Timestamp(ref timestamp) => Some(timestamp::format(timestamp)),
+            Value::Data(ref data) => Some(encoding::b64url_encode(data)),
+            Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Set(_) |
+            Value::Object(_) => ser::to_string(self).ok(),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<Number> {
+    de::from_str(text).ok()
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn integral_i64(n: &Number) -> Option<i64> {
+    if let Some(i) = n.as_i64() {
+        return Some(i);
+    }
+    match n.as_f64() {
+        // i64::MAX isn't exactly representable, so compare with 2^63
+        Some(f) if f.fract() == 0.0 && f >= i64::MIN as f64 && f < -(i64::MIN as f64) => {
+            Some(f as i64)
+        }
+        _ => None,
+    }
+}
@@ -113,17 +113,35 @@
 //! [from_reader]: https://docs.rs/tjson/de/fn.from_reader.html
 
 use serde::ser::Serialize;
-use serde::de::DeserializeOwned;
+use serde::de::{Deserialize, DeserializeOwned};
+use std::borrow::Cow;
+use std::fmt;
+use std::mem;
+use std::path::Path;
 
-use error::Error;
+use de::{DuplicateElements, Normalization};
+use error::{Code, Error};
+use fs::SaveOptions;
+use map;
+use set;
 pub use map::Map;
 pub use set::Set;
+pub use data::Data;
 pub use number::Number;
 
 use chrono::datetime::DateTime as ChronoDateTime;
+use chrono::offset::TimeZone;
 use chrono::offset::utc::UTC;
 
+pub use self::compact::{CompactStats, CompactValue};
+#[doc(hidden)]
+pub use self::de::NormalizedValue;
+pub use self::convert::ConversionError;
+pub use self::cow::CowValue;
+pub use self::flatten::{ArrayMode, FlatError, FlatErrorKind, Flatten, FlattenOptions};
 pub use self::index::Index;
+pub use self::pointer::{PointerError, PointerErrorKind};
+pub use self::stats::{Metric, StatViolation, StatsLimits, ValueStats};
 
 use self::ser::Serializer;
 
@@ -131,7 +149,56 @@ use self::ser::Serializer;
 pub type DateTime = ChronoDateTime<UTC>;
 
 /// Represents any valid TJSON value.
-#[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
+///
+/// Values are ordered first by kind, in this order, and then by what they
+/// hold, which is how a `Set` or a `BTreeMap` keyed by `Value` orders them:
+///
+/// 1. `Undefined`
+/// 2. `Bool`, `false` before `true`
+/// 3. `Data`, byte by byte
+/// 4. `Number`, integers by value before floats by value, and exact
+///    decimals last; a non-negative signed integer is the same as the
+///    unsigned one
+/// 5. `String`, byte by byte
+/// 6. `Timestamp`, earliest first
+/// 7. `Array`, element by element
+/// 8. `Set`, element by element in iteration order
+/// 9. `Object`, member by member in iteration order
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::Value;
+///
+/// assert!(Value::Undefined < tjson!(false));
+/// assert!(tjson!(true) < tjson!(-1));
+/// assert!(tjson!(u64::MAX) < tjson!(0.5));
+/// assert!(tjson!(99) < tjson!("1"));
+/// assert!(tjson!("z") < tjson!([]));
+/// # }
+/// ```
+///
+/// Equality between values is strict in the same way, so an integer never
+/// equals a float; `eq_numeric` compares numbers by value instead. Comparing
+/// a value with a Rust number, as in `value == 1` or `value == 1.0`, is by
+/// value, since the Rust number's type says nothing about how the value was
+/// written.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// assert_ne!(tjson!(1), tjson!(1.0));
+/// assert!(tjson!(1).eq_numeric(&tjson!(1.0)));
+///
+/// assert_eq!(tjson!(1), 1.0);
+/// assert_eq!(tjson!(1.0), 1);
+/// # }
+/// ```
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Value {
     /// Since TJSON is non-nullable, this indicates cases where a requested
     /// value is not present, e.g. for non-panicing `Index`
@@ -149,7 +216,8 @@ pub enum Value {
     /// ```
     Bool(bool),
 
-    /// Represents TJSON binary data (8-bit clean).
+    /// Represents TJSON binary data (8-bit clean), which `Data` holds either
+    /// decoded or, if read with `Deserializer::lazy_data`, as its text.
     ///
     /// ```rust
     /// # #[macro_use]
@@ -159,7 +227,7 @@ pub enum Value {
     /// let v = tjson!(b"a bytestring");
     /// # }
     /// ```
-    Data(Vec<u8>),
+    Data(Data),
 
     /// Represents a TJSON number: either a signed integer (`Number::Int`),
     /// unsigned integer (`Number::UInt`), or floating point (`Number::Float`)
@@ -189,14 +257,19 @@ pub enum Value {
 
     /// Represents a TJSON timestamp (always UTC).
     ///
+    /// The timestamp is held inline and is smaller than a `String`, so a
+    /// `Value::Timestamp` takes no more memory than a `Value` of any other
+    /// kind. Arrays of millions of them cost the same as arrays of as many
+    /// booleans.
+    ///
     /// ```rust
     /// # extern crate chrono;
-    /// # #[macro_use]
     /// # extern crate tjson;
     /// # use chrono::offset::utc::UTC;
+    /// # use tjson::Value;
     /// #
     /// # fn main() {
-    /// let v = tjson!(UTC::now());
+    /// let v = Value::from(UTC::now());
     /// # }
     Timestamp(DateTime),
 
@@ -216,7 +289,8 @@ pub enum Value {
     ///
     /// By default the set is backed by a `BTreeSet`. Enable the
     /// `preserve_order` feature of this crate to use `LinkedHashMap` instead,
-    /// which preserves entries in the order they are inserted into the set.
+    /// or `preserve_order_indexmap` to use `IndexMap`, either of which
+    /// preserves entries in the order they are inserted into the set.
     ///
     /// ```rust
     /// # #[macro_use]
@@ -233,7 +307,8 @@ pub enum Value {
     ///
     /// By default the map is backed by a `BTreeMap`. Enable the
     /// `preserve_order` feature of this crate to use `LinkedHashMap` instead,
-    /// which preserves entries in the order they are inserted into the map.
+    /// or `preserve_order_indexmap` to use `IndexMap`, either of which
+    /// preserves entries in the order they are inserted into the map.
     ///
     /// ```rust
     /// # #[macro_use]
@@ -246,14 +321,203 @@ pub enum Value {
     Object(Map<String, Value>),
 }
 
-fn parse_index(s: &str) -> Option<usize> {
+/// The kind of a `Value`, without any of its contents.
+///
+/// This is mostly useful for describing what was found where something else
+/// was expected, e.g. in a `ConversionError`.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # use tjson::value::ValueKind;
+/// #
+/// # fn main() {
+/// let v = tjson!({ "a": [1, 2, 3] });
+///
+/// assert_eq!(v.kind(), ValueKind::Object);
+/// assert_eq!(v["a"].kind(), ValueKind::Array);
+/// assert_eq!(v["a"].kind().to_string(), "array");
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum ValueKind {
+    /// `Value::Undefined`
+    Undefined,
+    /// `Value::Bool`
+    Bool,
+    /// `Value::Data`
+    Data,
+    /// `Value::Number`
+    Number,
+    /// `Value::String`
+    String,
+    /// `Value::Timestamp`
+    Timestamp,
+    /// `Value::Array`
+    Array,
+    /// `Value::Set`
+    Set,
+    /// `Value::Object`
+    Object,
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            match *self {
+                ValueKind::Undefined => "undefined",
+                ValueKind::Bool => "boolean",
+                ValueKind::Data => "data",
+                ValueKind::Number => "number",
+                ValueKind::String => "string",
+                ValueKind::Timestamp => "timestamp",
+                ValueKind::Array => "array",
+                ValueKind::Set => "set",
+                ValueKind::Object => "object",
+            },
+        )
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn push_pointer_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn parse_index(s: &str) -> Option<usize> {
     if s.starts_with('+') || (s.starts_with('0') && s.len() != 1) {
         return None;
     }
     s.parse().ok()
 }
 
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn unescape_pointer_token(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => unescaped.push('~'),
+            Some('1') => unescaped.push('/'),
+            _ => return None,
+        }
+    }
+    Some(Cow::Owned(unescaped))
+}
+
 impl Value {
+    /// Returns the kind of this `Value`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::value::ValueKind;
+    /// #
+    /// # fn main() {
+    /// assert_eq!(tjson!("a string").kind(), ValueKind::String);
+    /// assert_eq!(tjson!(42).kind(), ValueKind::Number);
+    /// # }
+    /// ```
+    pub fn kind(&self) -> ValueKind {
+        match *self {
+            Value::Undefined => ValueKind::Undefined,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Data(_) => ValueKind::Data,
+            Value::Number(_) => ValueKind::Number,
+            Value::String(_) => ValueKind::String,
+            Value::Timestamp(_) => ValueKind::Timestamp,
+            Value::Array(_) => ValueKind::Array,
+            Value::Set(_) => ValueKind::Set,
+            Value::Object(_) => ValueKind::Object,
+        }
+    }
+
+    /// Returns the size of the `Value`: the number of elements of an array
+    /// or set, the number of members of an object, or the number of bytes of
+    /// a string or data. Returns `None` for every other kind of value.
+    ///
+    /// A string is measured in bytes of UTF-8, not characters. Use
+    /// `char_len` to count its characters.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// assert_eq!(tjson!([1, 2, 3]).len(), Some(3));
+    /// assert_eq!(tjson!({ "a": 1 }).len(), Some(1));
+    /// assert_eq!(tjson!("héllo").len(), Some(6));
+    /// assert_eq!(tjson!(42).len(), None);
+    /// # }
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match *self {
+            Value::Data(ref data) => Some(data.len()),
+            Value::String(ref s) => Some(s.len()),
+            Value::Array(ref array) => Some(array.len()),
+            Value::Set(ref set) => Some(set.len()),
+            Value::Object(ref map) => Some(map.len()),
+            Value::Undefined | Value::Bool(_) | Value::Number(_) | Value::Timestamp(_) => None,
+        }
+    }
+
+    /// Returns whether `len` is zero, or `None` for kinds of value which have
+    /// no length.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// assert_eq!(tjson!([]).is_empty(), Some(true));
+    /// assert_eq!(tjson!("a").is_empty(), Some(false));
+    /// assert_eq!(tjson!(null).is_empty(), None);
+    /// # }
+    /// ```
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// If the `Value` is a string, returns its number of characters.
+    /// Returns `None` otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// assert_eq!(tjson!("héllo").char_len(), Some(5));
+    /// assert_eq!(tjson!(["héllo"]).char_len(), None);
+    /// # }
+    /// ```
+    pub fn char_len(&self) -> Option<usize> {
+        match *self {
+            Value::String(ref s) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+
     /// Index into a TJSON array or map. A string index can be used to access a
     /// value in a map, and a usize index can be used to access an element of an
     /// array.
@@ -327,6 +591,75 @@ impl Value {
         index.index_into_mut(self)
     }
 
+    /// Deserializes the member or element at `index`, borrowing from it
+    /// where `T` can. `Map::get_typed` describes how members are looked up.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::error::ErrorCode;
+    ///
+    /// let value = tjson!({ "tags:A<s>": ["a", "b"], "count:u": 2 });
+    /// let tags: Vec<&str> = value.get_typed("tags:A<s>").unwrap();
+    /// assert_eq!(tags, ["a", "b"]);
+    /// assert_eq!(value["tags:A<s>"].get_typed::<String, _>(1).unwrap(), "b");
+    ///
+    /// let err = value.get_typed::<u64, _>("count").unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::MissingMember);
+    /// assert_eq!(err.to_string(), "missing member \"count\"");
+    ///
+    /// let err = value.get_typed::<String, _>("count:u").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "member \"count:u\": invalid type: integer `2`, expected a string"
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorCode::MissingMember` if nothing is at `index`,
+    /// including when `self` isn't an object or array, and with the error
+    /// of deserializing what is there otherwise.
+    pub fn get_typed<'a, T, I>(&'a self, index: I) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+        I: Index,
+    {
+        typed_member(index.index_into(self), &index)
+            .and_then(|found| found.ok_or_else(|| missing_member(&index)))
+    }
+
+    /// Deserializes the member or element at `index` as `get_typed` does,
+    /// or returns `default` if nothing is there.
+    ///
+    /// # Errors
+    ///
+    /// Fails if something is at `index` but isn't a `T`.
+    pub fn get_typed_or<'a, T, I>(&'a self, index: I, default: T) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+        I: Index,
+    {
+        typed_member(index.index_into(self), &index).map(|found| found.unwrap_or(default))
+    }
+
+    /// Deserializes the member or element at `index` as `get_typed` does,
+    /// or returns `None` if nothing is there.
+    ///
+    /// # Errors
+    ///
+    /// Fails if something is at `index` but isn't a `T`.
+    pub fn get_typed_opt<'a, T, I>(&'a self, index: I) -> Result<Option<T>, Error>
+    where
+        T: Deserialize<'a>,
+        I: Index,
+    {
+        typed_member(index.index_into(self), &index)
+    }
+
     /// Returns true if the `Value` is an Object. Returns false otherwise.
     ///
     /// For any Value on which `is_object` returns true, `as_object` and
@@ -397,6 +730,153 @@ impl Value {
         }
     }
 
+    /// If the `Value` is an Object, returns its members in order as a list
+    /// of pairs. Returns the value back otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let pairs = tjson!({ "a": 1, "b": [2] }).into_pairs().unwrap();
+    /// assert_eq!(pairs[1], ("b".to_owned(), tjson!([2])));
+    ///
+    /// assert_eq!(tjson!([1]).into_pairs(), Err(tjson!([1])));
+    /// # }
+    /// ```
+    pub fn into_pairs(self) -> Result<Vec<(String, Value)>, Value> {
+        match self {
+            Value::Object(map) => Ok(map.into_pairs()),
+            other => Err(other),
+        }
+    }
+
+    /// Makes an Object of the given members, failing if two have the same
+    /// name. See `Map::from_pairs`.
+    pub fn from_pairs(pairs: Vec<(String, Value)>) -> Result<Value, Error> {
+        Map::from_pairs(pairs).map(Value::Object)
+    }
+
+    /// If the `Value` is an Object, returns an iterator over its member
+    /// names as the map stores them. Returns None otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a": 1, "b": [2] });
+    ///
+    /// assert_eq!(v.object_keys().unwrap().collect::<Vec<_>>(), ["a", "b"]);
+    /// assert!(v["b"].object_keys().is_none());
+    /// # }
+    /// ```
+    pub fn object_keys(&self) -> Option<map::Keys> {
+        self.as_object().map(Map::keys)
+    }
+
+    /// If the `Value` is an Object, keeps only the members for which `pred`
+    /// returns true. Does nothing otherwise.
+    ///
+    /// This is `Map::drain_filter` with the predicate inverted and the
+    /// removed members dropped.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "id": 7, "debug": true, "name": "x" });
+    ///
+    /// v.retain_members(|name, _| name != "debug");
+    /// assert_eq!(v, tjson!({ "id": 7, "name": "x" }));
+    /// # }
+    /// ```
+    pub fn retain_members<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&String, &mut Value) -> bool,
+    {
+        if let Value::Object(ref mut map) = *self {
+            map.drain_filter(|name, value| !pred(name, value));
+        }
+    }
+
+    /// Returns true if the `Value` is a Set. Returns false otherwise.
+    ///
+    /// For any Value on which `is_set` returns true, `as_set` and
+    /// `as_set_mut` are guaranteed to return the set.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let v: Value = tjson::from_str(r#"{"a:S<i>": ["1", "2"], "b:A<i>": ["1", "2"]}"#).unwrap();
+    ///
+    /// assert!(v["a:S<i>"].is_set());
+    ///
+    /// // An array, not a set.
+    /// assert!(!v["b:A<i>"].is_set());
+    /// # }
+    /// ```
+    pub fn is_set(&self) -> bool {
+        self.as_set().is_some()
+    }
+
+    /// If the `Value` is a Set, returns the associated `Set`. Returns None
+    /// otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let v: Value = tjson::from_str(r#"{"a:S<i>": ["1", "2"], "b:A<i>": ["1", "2"]}"#).unwrap();
+    ///
+    /// assert!(v["a:S<i>"].as_set().unwrap().contains(&tjson!(2)));
+    ///
+    /// // An array, not a set.
+    /// assert_eq!(v["b:A<i>"].as_set(), None);
+    /// # }
+    /// ```
+    pub fn as_set(&self) -> Option<&Set<Value>> {
+        match *self {
+            Value::Set(ref set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a Set, returns the associated mutable `Set`.
+    /// Returns None otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let mut v: Value = tjson::from_str(r#"{"a:S<i>": ["1", "2"]}"#).unwrap();
+    ///
+    /// {
+    ///     let set = v["a:S<i>"].as_set_mut().unwrap();
+    ///     set.insert(tjson!(3));
+    ///     set.remove(&tjson!(1));
+    /// }
+    /// assert_eq!(tjson::to_string(&v).unwrap(), r#"{"a:S<i>":["2","3"]}"#);
+    /// # }
+    /// ```
+    pub fn as_set_mut(&mut self) -> Option<&mut Set<Value>> {
+        match *self {
+            Value::Set(ref mut set) => Some(set),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `Value` is an Array. Returns false otherwise.
     ///
     /// For any Value on which `is_array` returns true, `as_array` and
@@ -471,41 +951,213 @@ impl Value {
     /// to return the string slice.
     ///
     /// ```rust
-    /// # #[macro_use]
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a": "some string", "b": false });
+    ///
+    /// assert!(v["a"].is_string());
+    ///
+    /// // The boolean `false` is not a string.
+    /// assert!(!v["b"].is_string());
+    /// # }
+    /// ```
+    pub fn is_string(&self) -> bool {
+        self.as_str().is_some()
+    }
+
+    /// If the `Value` is a String, returns the associated str. Returns None
+    /// otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a": "some string", "b": false });
+    ///
+    /// assert_eq!(v["a"].as_str(), Some("some string"));
+    ///
+    /// // The boolean `false` is not a string.
+    /// assert_eq!(v["b"].as_str(), None);
+    /// # }
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the `Value` is Data. Returns false otherwise.
+    ///
+    /// For any Value on which `is_data` returns true, `as_data` and
+    /// `as_data_mut` are guaranteed to return the bytes.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a:d": Value::Data(b"Hi".to_vec().into()), "b:s": "Hi" });
+    ///
+    /// assert!(v["a:d"].is_data());
+    ///
+    /// // The string `"Hi"` is a string, not data.
+    /// assert!(!v["b:s"].is_data());
+    /// # }
+    /// ```
+    pub fn is_data(&self) -> bool {
+        self.as_data().is_some()
+    }
+
+    /// If the `Value` is Data, returns the associated bytes. Returns None
+    /// otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a:d": Value::Data(b"Hi".to_vec().into()), "b:s": "Hi" });
+    ///
+    /// assert_eq!(v["a:d"].as_data(), Some(&b"Hi"[..]));
+    ///
+    /// // The string `"Hi"` is a string, not data.
+    /// assert_eq!(v["b:s"].as_data(), None);
+    /// # }
+    /// ```
+    pub fn as_data(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Data(ref bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is Data, returns the associated mutable bytes, after
+    /// which it is no longer written as the text it was read as. Returns
+    /// None otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "a:d": Value::Data(b"Hi".to_vec().into()) });
+    ///
+    /// v["a:d"].as_data_mut().unwrap().push(b'!');
+    /// assert_eq!(v["a:d"].as_data(), Some(&b"Hi!"[..]));
+    /// # }
+    /// ```
+    pub fn as_data_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match *self {
+            Value::Data(ref mut bytes) => Some(bytes.to_mut()),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is Data, returns its bytes. Returns the value back
+    /// otherwise.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let v = tjson!({ "a:d": Value::Data(b"Hi".to_vec().into()), "b:s": "Hi" });
+    ///
+    /// assert_eq!(v["a:d"].clone().into_data(), Ok(b"Hi".to_vec()));
+    /// assert_eq!(v["b:s"].clone().into_data(), Err(tjson!("Hi")));
+    /// # }
+    /// ```
+    pub fn into_data(self) -> Result<Vec<u8>, Value> {
+        match self {
+            Value::Data(bytes) => Ok(bytes.into_vec()),
+            other => Err(other),
+        }
+    }
+
+    /// Returns true if the `Value` is a Timestamp. Returns false otherwise.
+    ///
+    /// For any Value on which `is_timestamp` returns true, `as_timestamp` and
+    /// `as_timestamp_mut` are guaranteed to return the timestamp.
+    ///
+    /// ```rust
+    /// # extern crate tjson;
+    /// #
+    /// # use tjson::Value;
+    /// #
+    /// # fn main() {
+    /// let text = r#"{"at:t": "2017-03-04T05:06:07Z", "note:s": "2017-03-04T05:06:07Z"}"#;
+    /// let v: Value = tjson::from_str(text).unwrap();
+    ///
+    /// assert!(v["at:t"].is_timestamp());
+    ///
+    /// // A member tagged `s` is a string, whatever it looks like.
+    /// assert!(!v["note:s"].is_timestamp());
+    /// # }
+    /// ```
+    pub fn is_timestamp(&self) -> bool {
+        self.as_timestamp().is_some()
+    }
+
+    /// If the `Value` is a Timestamp, returns the associated `DateTime`.
+    /// Returns None otherwise.
+    ///
+    /// ```rust
     /// # extern crate tjson;
     /// #
+    /// # use tjson::Value;
+    /// #
     /// # fn main() {
-    /// let v = tjson!({ "a": "some string", "b": false });
+    /// let text = r#"{"at:t": "2017-03-04T05:06:07Z", "note:s": "2017-03-04T05:06:07Z"}"#;
+    /// let v: Value = tjson::from_str(text).unwrap();
     ///
-    /// assert!(v["a"].is_string());
+    /// assert_eq!(v["at:t"].as_timestamp().unwrap().timestamp(), 1488603967);
     ///
-    /// // The boolean `false` is not a string.
-    /// assert!(!v["b"].is_string());
+    /// // A member tagged `s` is a string, whatever it looks like.
+    /// assert_eq!(v["note:s"].as_timestamp(), None);
     /// # }
     /// ```
-    pub fn is_string(&self) -> bool {
-        self.as_str().is_some()
+    pub fn as_timestamp(&self) -> Option<&DateTime> {
+        match *self {
+            Value::Timestamp(ref timestamp) => Some(timestamp),
+            _ => None,
+        }
     }
 
-    /// If the `Value` is a String, returns the associated str. Returns None
-    /// otherwise.
+    /// If the `Value` is a Timestamp, returns the associated mutable
+    /// `DateTime`. Returns None otherwise.
     ///
     /// ```rust
-    /// # #[macro_use]
+    /// # extern crate chrono;
     /// # extern crate tjson;
     /// #
+    /// # use chrono::Duration;
+    /// # use tjson::Value;
+    /// #
     /// # fn main() {
-    /// let v = tjson!({ "a": "some string", "b": false });
-    ///
-    /// assert_eq!(v["a"].as_str(), Some("some string"));
+    /// let mut v: Value = tjson::from_str(r#"{"at:t": "2017-03-04T05:06:07Z"}"#).unwrap();
     ///
-    /// // The boolean `false` is not a string.
-    /// assert_eq!(v["b"].as_str(), None);
+    /// {
+    ///     let at = v["at:t"].as_timestamp_mut().unwrap();
+    ///     *at = *at + Duration::seconds(1);
+    /// }
+    /// assert_eq!(tjson::to_string(&v).unwrap(), r#"{"at:t":"2017-03-04T05:06:08Z"}"#);
     /// # }
     /// ```
-    pub fn as_str(&self) -> Option<&str> {
+    pub fn as_timestamp_mut(&mut self) -> Option<&mut DateTime> {
         match *self {
-            Value::String(ref s) => Some(s),
+            Value::Timestamp(ref mut timestamp) => Some(timestamp),
             _ => None,
         }
     }
@@ -690,6 +1342,78 @@ impl Value {
         }
     }
 
+    /// Whether the two values are numbers with the same mathematical value,
+    /// as `Number::eq_numeric` compares them, or are otherwise equal.
+    ///
+    /// Only the values themselves are compared this way: arrays, sets and
+    /// objects holding numbers still compare with `==`, which tells `1` from
+    /// `1.0`.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// assert_ne!(tjson!(1), tjson!(1.0));
+    /// assert!(tjson!(1).eq_numeric(&tjson!(1.0)));
+    /// assert!(!tjson!(1).eq_numeric(&tjson!(1.5)));
+    ///
+    /// assert!(tjson!("a").eq_numeric(&tjson!("a")));
+    /// assert!(!tjson!([1]).eq_numeric(&tjson!([1.0])));
+    /// # }
+    /// ```
+    pub fn eq_numeric(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Value::Number(ref a), &Value::Number(ref b)) => a.eq_numeric(b),
+            _ => self == other,
+        }
+    }
+
+    /// Replaces every floating point number in the value which is a whole
+    /// number with the integer it equals, as `Number::to_integer_lossless`
+    /// does, in nested arrays, sets and objects as well.
+    ///
+    /// Numbers in a set which become equal are merged.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "n": 3.0, "a": [-2.0, 0.5], "o": { "big": 1e20 } });
+    ///
+    /// v.normalize_integral_floats();
+    /// assert_eq!(v, tjson!({ "n": 3, "a": [-2, 0.5], "o": { "big": 1e20 } }));
+    /// assert!(v["n"].is_u64());
+    /// # }
+    /// ```
+    pub fn normalize_integral_floats(&mut self) {
+        match *self {
+            Value::Number(ref mut n) => {
+                if let Some(integer) = n.to_integer_lossless() {
+                    *n = integer;
+                }
+            }
+            Value::Array(ref mut elements) => {
+                for element in elements {
+                    element.normalize_integral_floats();
+                }
+            }
+            Value::Set(ref mut set) => {
+                for mut element in set.drain_filter(|_| true) {
+                    element.normalize_integral_floats();
+                    set.insert(element);
+                }
+            }
+            Value::Object(ref mut map) => {
+                for (_, value) in map.iter_mut() {
+                    value.normalize_integral_floats();
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Returns true if the `Value` is a Boolean. Returns false otherwise.
     ///
     /// For any Value on which `is_boolean` returns true, `as_bool` is
@@ -787,7 +1511,8 @@ impl Value {
     /// A Pointer is a Unicode string with the reference tokens separated by `/`.
     /// Inside tokens `/` is replaced by `~1` and `~` is replaced by `~0`. The
     /// addressed value is returned and if there is no such value `None` is
-    /// returned.
+    /// returned. Any other `~` is an error and so also gives `None`. Use
+    /// `pointer_checked` to find out why there is no value.
     ///
     /// For more information read [RFC6901](https://tools.ietf.org/html/rfc6901).
     ///
@@ -809,31 +1534,7 @@ impl Value {
     /// # }
     /// ```
     pub fn pointer<'a>(&'a self, pointer: &str) -> Option<&'a Value> {
-        if pointer == "" {
-            return Some(self);
-        }
-        if !pointer.starts_with('/') {
-            return None;
-        }
-        let tokens = pointer
-            .split('/')
-            .skip(1)
-            .map(|x| x.replace("~1", "/").replace("~0", "~"));
-        let mut target = self;
-
-        for token in tokens {
-            let target_opt = match *target {
-                Value::Object(ref map) => map.get(&token),
-                Value::Array(ref list) => parse_index(&token).and_then(|x| list.get(x)),
-                _ => return None,
-            };
-            if let Some(t) = target_opt {
-                target = t;
-            } else {
-                return None;
-            }
-        }
-        Some(target)
+        self.pointer_checked(pointer).ok()
     }
 
     /// Looks up a value by a JSON Pointer and returns a mutable reference to
@@ -855,7 +1556,6 @@ impl Value {
     /// extern crate tjson;
     ///
     /// use tjson::Value;
-    /// use std::mem;
     ///
     /// fn main() {
     ///     let s = r#"{"x": 1.0, "y": 2.0}"#;
@@ -868,10 +1568,14 @@ impl Value {
     ///     // Check that new value was written
     ///     assert_eq!(value.pointer("/x"), Some(&1.5.into()));
     ///
-    ///     // "Steal" ownership of a value. Can replace with any valid Value.
-    ///     let old_x = value.pointer_mut("/x").map(|x| mem::replace(x, Value::Undefined)).unwrap();
+    ///     // "Steal" ownership of a value, leaving Undefined in its place.
+    ///     let old_x = value.pointer_mut("/x").map(Value::take).unwrap();
     ///     assert_eq!(old_x, 1.5);
     ///     assert_eq!(value.pointer("/x").unwrap(), &Value::Undefined);
+    ///
+    ///     // Or put any other value in its place.
+    ///     let old_y = value.pointer_mut("/y").unwrap().replace(3.into());
+    ///     assert_eq!(old_y, 2.0);
     /// }
     /// ```
     pub fn pointer_mut<'a>(&'a mut self, pointer: &str) -> Option<&'a mut Value> {
@@ -881,23 +1585,19 @@ impl Value {
         if !pointer.starts_with('/') {
             return None;
         }
-        let tokens = pointer
-            .split('/')
-            .skip(1)
-            .map(|x| x.replace("~1", "/").replace("~0", "~"));
         let mut target = self;
 
-        for token in tokens {
+        for token in pointer.split('/').skip(1) {
             // borrow checker gets confused about `target` being mutably borrowed too many times because of the loop
             // this once-per-loop binding makes the scope clearer and circumvents the error
             let target_once = target;
-            let target_opt = match *target_once {
-                Value::Object(ref mut map) => map.get_mut(&token),
+            let target_opt = unescape_pointer_token(token).and_then(move |token| match *target_once {
+                Value::Object(ref mut map) => map.get_mut(&*token),
                 Value::Array(ref mut list) => {
                     parse_index(&token).and_then(move |x| list.get_mut(x))
                 }
-                _ => return None,
-            };
+                _ => None,
+            });
             if let Some(t) = target_opt {
                 target = t;
             } else {
@@ -906,10 +1606,126 @@ impl Value {
         }
         Some(target)
     }
+
+    /// Removes the value a JSON Pointer refers to from the object or array
+    /// holding it, and returns it. Returns None if there is no such value,
+    /// including for the empty pointer, as the whole value can't be removed
+    /// from itself.
+    ///
+    /// Removing an element of an array moves the elements after it down by
+    /// one, as `Vec::remove` does.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "a/b": { "c": [1, 2, 3] } });
+    ///
+    /// assert_eq!(v.pointer_remove("/a~1b/c/0"), Some(tjson!(1)));
+    /// assert_eq!(v, tjson!({ "a/b": { "c": [2, 3] } }));
+    ///
+    /// // A number has nothing in it to remove
+    /// assert_eq!(v.pointer_remove("/a~1b/c/0/x"), None);
+    /// assert_eq!(v.pointer_remove("/a~1b/d"), None);
+    /// # }
+    /// ```
+    pub fn pointer_remove(&mut self, pointer: &str) -> Option<Value> {
+        let split = match pointer.rfind('/') {
+            Some(split) => split,
+            None => return None,
+        };
+        let token = match unescape_pointer_token(&pointer[split + 1..]) {
+            Some(token) => token,
+            None => return None,
+        };
+        match self.pointer_mut(&pointer[..split]) {
+            Some(&mut Value::Object(ref mut map)) => map.remove(&*token),
+            Some(&mut Value::Array(ref mut list)) => {
+                match parse_index(&token) {
+                    Some(index) if index < list.len() => Some(list.remove(index)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Takes the value out, leaving `Value::Undefined` in its place.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "x": "y", "z": [1] });
+    ///
+    /// assert_eq!(v["x"].take(), tjson!("y"));
+    /// assert_eq!(v["z"][0].take(), tjson!(1));
+    /// assert_eq!(v, tjson!({ "x": null, "z": [null] }));
+    /// # }
+    /// ```
+    pub fn take(&mut self) -> Value {
+        mem::replace(self, Value::Undefined)
+    }
+
+    /// Puts the given value in place of this one, and returns this one.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut v = tjson!({ "x": "y" });
+    ///
+    /// assert_eq!(v["x"].replace(tjson!([1])), tjson!("y"));
+    /// assert_eq!(v, tjson!({ "x": [1] }));
+    /// # }
+    /// ```
+    pub fn replace(&mut self, value: Value) -> Value {
+        mem::replace(self, value)
+    }
+
+    /// Atomically replaces the file at the given path with the value, as
+    /// `tjson::fs::save` does.
+    pub fn save_to_path<P>(&self, path: P, options: &SaveOptions) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        ::fs::save(path, self, options)
+    }
+
+    /// Reads the value in the TJSON file at the given path, as
+    /// `tjson::fs::load` does.
+    ///
+    /// ```rust,no_run
+    /// use tjson::Value;
+    /// use tjson::fs::SaveOptions;
+    ///
+    /// let mut config = Value::load_from_path("config.tjson").unwrap();
+    /// config["runs:u"] = (config["runs:u"].as_u64().unwrap_or(0) + 1).into();
+    /// config.save_to_path("config.tjson", &SaveOptions::new()).unwrap();
+    /// ```
+    pub fn load_from_path<P>(path: P) -> Result<Value, Error>
+    where
+        P: AsRef<Path>,
+    {
+        ::fs::load(path)
+    }
 }
 
+mod compact;
+mod convert;
+mod cow;
+mod fingerprint;
+mod flatten;
 mod index;
+mod lossy;
+mod ord;
 mod partial_eq;
+mod patch;
+mod pointer;
+mod stats;
 mod from;
 mod ser;
 mod de;
@@ -1001,9 +1817,12 @@ impl Default for Value {
 /// # Errors
 ///
 /// This conversion can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map whose first key can be written as a string
+/// but a later key can't. Maps whose first key can't be written as a string
+/// become arrays of `[key, value]` pairs.
 ///
 /// ```rust
+/// # #[macro_use]
 /// extern crate tjson;
 ///
 /// use std::collections::BTreeMap;
@@ -1013,14 +1832,48 @@ impl Default for Value {
 ///     let mut map = BTreeMap::new();
 ///     map.insert(vec![32, 64], "x86");
 ///
-///     println!("{}", tjson::to_value(map).unwrap_err());
+///     assert_eq!(tjson::to_value(map).unwrap(), tjson!([[[32, 64], "x86"]]));
 /// }
 /// ```
 pub fn to_value<T>(value: T) -> Result<Value, Error>
 where
     T: Serialize,
 {
-    value.serialize(Serializer)
+    value.serialize(Serializer::new(DuplicateElements::Error))
+}
+
+/// Convert a `T` into `tjson::Value` as `to_value` does, handling sets with
+/// more than one equal element by the given policy.
+///
+/// Values which serialize as sets, such as `Set` itself, become
+/// `Value::Set`s. Sets from other crates may repeat an element, which by
+/// default is an error.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::{Set, Value};
+/// use tjson::de::DuplicateElements;
+///
+/// let mut set = Set::new();
+/// set.insert(tjson!(1));
+/// let value = tjson::value::to_value_with(&set, DuplicateElements::Error).unwrap();
+/// assert_eq!(value, Value::Set(set));
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Fails for the same reasons as `to_value`, and with an
+/// `ErrorCode::DuplicateElement` error for a set which repeats an element
+/// under `DuplicateElements::Error`.
+pub fn to_value_with<T>(value: T, duplicate_elements: DuplicateElements) -> Result<Value, Error>
+where
+    T: Serialize,
+{
+    value.serialize(Serializer::new(duplicate_elements))
 }
 
 /// Interpret a `tjson::Value` as an instance of type `T`.
@@ -1033,6 +1886,10 @@ where
 /// the TJSON map or some number is too big to fit in the expected primitive
 /// type.
 ///
+/// Member names are matched to the fields of structs with their tags left
+/// off, as `Normalization::Untagged` does, so a field `location` is read from
+/// a member `"location:s"`.
+///
 /// ```rust
 /// #[macro_use]
 /// extern crate tjson;
@@ -1063,5 +1920,259 @@ pub fn from_value<T>(value: Value) -> Result<T, Error>
 where
     T: DeserializeOwned,
 {
-    T::deserialize(value)
+    T::deserialize(NormalizedValue::new(value, Normalization::Untagged, DuplicateElements::Error))
+}
+
+/// Interpret a `tjson::Value` as an instance of type `T` as `from_value`
+/// does, matching member names to the fields of structs by the given
+/// normalization, as `Deserializer::member_name_normalization` does.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tjson;
+///
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// extern crate serde;
+///
+/// use tjson::de::Normalization;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct User {
+///     #[serde(rename = "location:s")]
+///     location: String,
+/// }
+///
+/// fn main() {
+///     let j = tjson!({ "Location:s": "Menlo Park, CA" });
+///
+///     let u: User = tjson::value::from_value_normalized(j, Normalization::CaseInsensitive)
+///         .unwrap();
+///     assert_eq!(u.location, "Menlo Park, CA");
+/// }
+/// ```
+pub fn from_value_normalized<T>(value: Value, normalization: Normalization) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(NormalizedValue::new(value, normalization, DuplicateElements::Error))
+}
+
+/// Interpret a `tjson::Value` as an instance of type `T` as `from_value`
+/// does, handling sets with more than one equal element by the given
+/// policy, as `Deserializer::duplicate_elements` does.
+///
+/// A `Set<Value>` can be read from an array as well as from a `Value::Set`.
+/// The array's elements must be distinct unless the policy merges them.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::{Set, Value};
+/// use tjson::de::DuplicateElements;
+///
+/// let value = tjson!(["a", "b", "a"]);
+///
+/// let err = tjson::from_value::<Set<Value>>(value.clone()).unwrap_err();
+/// assert_eq!(err.to_string(), "duplicate set element \"a\" at index 2");
+///
+/// let set: Set<Value> =
+///     tjson::value::from_value_with(value, DuplicateElements::Merge).unwrap();
+/// assert_eq!(set.len(), 2);
+/// # }
+/// ```
+pub fn from_value_with<T>(value: Value, duplicate_elements: DuplicateElements) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(NormalizedValue::new(value, Normalization::Untagged, duplicate_elements))
+}
+
+// Not public API. Should be pub(crate).
+//
+// Deserializes what `get_typed` and its relatives found at `index`, if
+// anything other than `Undefined`, naming `index` if it isn't a `T`.
+#[doc(hidden)]
+pub fn typed_member<'a, T, I>(found: Option<&'a Value>, index: &I) -> Result<Option<T>, Error>
+where
+    T: Deserialize<'a>,
+    I: Index + ?Sized,
+{
+    match found {
+        None | Some(&Value::Undefined) => Ok(None),
+        Some(value) => {
+            T::deserialize(value)
+                .map(Some)
+                .map_err(|err| Error::syntax(Code::InvalidMember(index.describe(), err), 0, 0))
+        }
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub fn missing_member<I>(index: &I) -> Error
+where
+    I: Index + ?Sized,
+{
+    Error::syntax(Code::MissingMember(index.describe()), 0, 0)
+}
+
+// Not public API. Should be pub(crate).
+//
+// Builds a timestamp from seconds and nanoseconds since the epoch, for the
+// code `tjson_macros::include_tjson!` expands to, which can't name chrono.
+#[doc(hidden)]
+pub fn timestamp_from_parts(secs: i64, nanos: u32) -> Value {
+    Value::Timestamp(UTC.timestamp(secs, nanos))
+}
+
+// Not public API. Should be pub(crate).
+//
+// Reads the value of a member as its tag says, wherever a `Value` is built:
+// strings tagged `t` become timestamps, integers tagged `i` or `u` are stored
+// as signed or unsigned, and arrays tagged `S<..>` become sets. Used by
+// `tjson!` so that interpolated values end up the same as they would be when
+// parsed.
+#[doc(hidden)]
+pub fn read_member(name: &str, value: Value) -> Value {
+    match name.rfind(':') {
+        Some(colon) => read_tagged(&name[colon + 1..], value),
+        None => value,
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// Reads a value as `read_member` does, given the tag rather than the name it
+// is in, such as that of the elements of a set.
+#[doc(hidden)]
+pub fn read_tagged(tag: &str, value: Value) -> Value {
+    let value = ::timestamp::read_tagged(tag, value);
+    read_sets(tag, read_integers(tag, value))
+}
+
+/// Stores the integers of a value tagged `i` or `u`, or `A<i>` and so on,
+/// as the tag says.
+fn read_integers(tag: &str, value: Value) -> Value {
+    match value {
+        Value::Number(n) if tag == "i" => {
+            match n.as_i64() {
+                Some(i) => Value::Number(Number::from_i64_signed(i)),
+                None => Value::Number(n),
+            }
+        }
+        Value::Number(n) if tag == "u" => {
+            match n.as_u64() {
+                Some(u) => Value::Number(u.into()),
+                None => Value::Number(n),
+            }
+        }
+        Value::Array(elements) => {
+            if !(tag.starts_with("A<") || tag.starts_with("S<")) || !tag.ends_with('>') {
+                return Value::Array(elements);
+            }
+            let tag = &tag[2..tag.len() - 1];
+            let elements = elements.into_iter().map(|element| read_integers(tag, element));
+            Value::Array(elements.collect())
+        }
+        value => value,
+    }
+}
+
+/// Makes sets of the arrays tagged `S<..>` in a value, innermost first so
+/// that sets of sets compare their elements as sets. An array with an
+/// element equal to an earlier one is left as it is, for writing it out to
+/// report.
+fn read_sets(tag: &str, value: Value) -> Value {
+    match value {
+        Value::Array(elements) => {
+            if !(tag.starts_with("A<") || tag.starts_with("S<")) || !tag.ends_with('>') {
+                return Value::Array(elements);
+            }
+            let inner = &tag[2..tag.len() - 1];
+            let elements: Vec<_> =
+                elements.into_iter().map(|element| read_sets(inner, element)).collect();
+            if !tag.starts_with("S<") || set::first_duplicate(&elements).is_some() {
+                return Value::Array(elements);
+            }
+            let mut set = Set::new();
+            for element in elements {
+                set.insert(element);
+            }
+            Value::Set(set)
+        }
+        value => value,
+    }
+}
+
+// Not public API. Should be pub(crate).
+//
+// An expression interpolated by `try_tjson!`. Inherent methods take priority
+// over trait methods, so `Result`s and `Option`s get the inherent methods
+// below while everything else gets the `Interpolate` ones.
+#[doc(hidden)]
+pub struct Interpolated<T>(pub T);
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub trait Interpolate {
+    fn into_value(self) -> Result<Value, Error>;
+    fn into_member(self) -> Result<Option<Value>, Error>;
+}
+
+impl<T> Interpolate for Interpolated<T>
+where
+    T: Serialize,
+{
+    fn into_value(self) -> Result<Value, Error> {
+        to_value(self.0)
+    }
+
+    fn into_member(self) -> Result<Option<Value>, Error> {
+        to_value(self.0).map(Some)
+    }
+}
+
+#[doc(hidden)]
+impl<T, E> Interpolated<Result<T, E>>
+where
+    T: Serialize,
+    E: Into<Box<::std::error::Error + Send + Sync>>,
+{
+    pub fn into_value(self) -> Result<Value, Error> {
+        match self.0 {
+            Ok(value) => to_value(value),
+            Err(err) => Err(Error::custom_boxed(err.into())),
+        }
+    }
+
+    pub fn into_member(self) -> Result<Option<Value>, Error> {
+        self.into_value().map(Some)
+    }
+}
+
+#[doc(hidden)]
+impl<T> Interpolated<Option<T>>
+where
+    T: Serialize,
+{
+    pub fn into_value(self) -> Result<Value, Error> {
+        match self.0 {
+            Some(value) => to_value(value),
+            None => {
+                let msg = "`None` can only be interpolated as the value of an object member";
+                Err(::serde::ser::Error::custom(msg))
+            }
+        }
+    }
+
+    pub fn into_member(self) -> Result<Option<Value>, Error> {
+        match self.0 {
+            Some(value) => to_value(value).map(Some),
+            None => Ok(None),
+        }
+    }
 }
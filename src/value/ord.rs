@@ -0,0 +1,53 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp::Ordering;
+
+use super::Value;
+
+impl Value {
+    /// Where values of this kind go in the order of `Value`, before values
+    /// of every kind with a higher rank. Spelled out rather than taken from
+    /// the order of the variants, so that reordering them can't reorder sets.
+    fn rank(&self) -> u8 {
+        match *self {
+            Value::Undefined => 0,
+            Value::Bool(_) => 1,
+            Value::Data(_) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Timestamp(_) => 5,
+            Value::Array(_) => 6,
+            Value::Set(_) => 7,
+            Value::Object(_) => 8,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (&Value::Undefined, &Value::Undefined) => Ordering::Equal,
+            (&Value::Bool(a), &Value::Bool(b)) => a.cmp(&b),
+            (&Value::Data(ref a), &Value::Data(ref b)) => a.cmp(b),
+            (&Value::Number(ref a), &Value::Number(ref b)) => a.cmp(b),
+            (&Value::String(ref a), &Value::String(ref b)) => a.cmp(b),
+            (&Value::Timestamp(ref a), &Value::Timestamp(ref b)) => a.cmp(b),
+            (&Value::Array(ref a), &Value::Array(ref b)) => a.cmp(b),
+            (&Value::Set(ref a), &Value::Set(ref b)) => a.cmp(b),
+            (&Value::Object(ref a), &Value::Object(ref b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use super::Value;
+use super::{Number, Value};
 
 impl PartialEq<str> for Value {
     fn eq(&self, other: &str) -> bool {
@@ -45,30 +45,55 @@ impl PartialEq<Value> for String {
     }
 }
 
+// Comparisons with Rust numbers are by value, as `Number::eq_numeric`
+// compares, rather than strict like comparisons between values: `1.0` equals
+// `tjson!(1)` and `1` equals `tjson!(1.0)`. Floats aren't converted to
+// integers or the reverse along the way, so `2^53 + 1` doesn't equal the
+// float `2^53`, and NaN equals nothing.
+
+fn eq_number(value: &Value, other: Option<Number>) -> bool {
+    match (value, other) {
+        (&Value::Number(ref n), Some(ref other)) => n.eq_numeric(other),
+        _ => false,
+    }
+}
+
+fn eq_i64(value: &Value, other: i64) -> bool {
+    eq_number(value, Some(Number::from(other)))
+}
+
+fn eq_u64(value: &Value, other: u64) -> bool {
+    eq_number(value, Some(Number::from(other)))
+}
+
+fn eq_f64(value: &Value, other: f64) -> bool {
+    eq_number(value, Number::from_f64(other))
+}
+
 macro_rules! partialeq_numeric {
     ($([$($ty:ty)*], $conversion:ident, $base:ty)*) => {
         $($(
             impl PartialEq<$ty> for Value {
                 fn eq(&self, other: &$ty) -> bool {
-                    self.$conversion().map_or(false, |i| i == (*other as $base))
+                    $conversion(self, *other as $base)
                 }
             }
 
             impl PartialEq<Value> for $ty {
                 fn eq(&self, other: &Value) -> bool {
-                    other.$conversion().map_or(false, |i| i == (*self as $base))
+                    $conversion(other, *self as $base)
                 }
             }
 
             impl<'a> PartialEq<$ty> for &'a Value {
                 fn eq(&self, other: &$ty) -> bool {
-                    self.$conversion().map_or(false, |i| i == (*other as $base))
+                    $conversion(*self, *other as $base)
                 }
             }
 
             impl<'a> PartialEq<$ty> for &'a mut Value {
                 fn eq(&self, other: &$ty) -> bool {
-                    self.$conversion().map_or(false, |i| i == (*other as $base))
+                    $conversion(*self, *other as $base)
                 }
             }
         )*)*
@@ -76,7 +101,7 @@ macro_rules! partialeq_numeric {
 }
 
 partialeq_numeric! {
-    [i8 i16 i32 i64 isize], as_i64, i64
-    [u8 u16 u32 u64 usize], as_u64, u64
-    [f32 f64], as_f64, f64
+    [i8 i16 i32 i64 isize], eq_i64, i64
+    [u8 u16 u32 u64 usize], eq_u64, u64
+    [f32 f64], eq_f64, f64
 }
@@ -0,0 +1,220 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Applying JSON Patch and JSON Merge Patch documents, as RFC 6902 and
+//! RFC 7386 describe them
+
+use error::{Code, Error};
+use map::Map;
+use super::Value;
+use super::pointer::invalid_pointer;
+
+impl Value {
+    /// Applies a JSON Patch, an array of `add`, `remove`, `replace`, `move`,
+    /// `copy`, and `test` operations as RFC 6902 describes them.
+    ///
+    /// The members of an operation may be tagged, as in `"op:s"`. Either all
+    /// of the operations are applied or, if one fails, none of them are.
+    ///
+    /// Fails with `ErrorCode::PatchTestFailed` if a `test` operation finds a
+    /// different value, and with `ErrorCode::PatchFailed` if an operation is
+    /// malformed or its paths can't be followed, such as `add` past the end
+    /// of an array or `move` into the moved value's own children. The error's
+    /// cause says why. `Error::patch_operation` says which operation failed.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::error::ErrorCode;
+    ///
+    /// let mut doc = tjson!({ "name": "a", "tags": ["x"] });
+    ///
+    /// doc.patch(&tjson!([
+    ///     { "op": "replace", "path": "/name", "value": "b" },
+    ///     { "op": "add", "path": "/tags/0", "value": "w" },
+    ///     { "op": "copy", "from": "/name", "path": "/alias" },
+    /// ])).unwrap();
+    /// assert_eq!(doc, tjson!({ "name": "b", "tags": ["w", "x"], "alias": "b" }));
+    ///
+    /// let err = doc.patch(&tjson!([
+    ///     { "op": "remove", "path": "/alias" },
+    ///     { "op": "test", "path": "/name", "value": "a" },
+    /// ])).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::PatchTestFailed);
+    /// assert_eq!(err.patch_operation(), Some(1));
+    /// assert_eq!(doc["alias"], tjson!("b"));
+    /// # }
+    /// ```
+    pub fn patch(&mut self, patch: &Value) -> Result<(), Error> {
+        let operations = match *patch {
+            Value::Array(ref operations) => operations,
+            _ => return Err(message("a JSON Patch must be an array of operations")),
+        };
+
+        let mut patched = self.clone();
+        for (index, operation) in operations.iter().enumerate() {
+            try!(apply(&mut patched, index, operation));
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Applies a JSON Merge Patch, as RFC 7386 describes it.
+    ///
+    /// If the patch is an object, each of its members is merged into the
+    /// member of the same name, turning this value into an object first if
+    /// it isn't one. Since TJSON has no null, a member holding
+    /// `Value::Undefined` removes the member of that name. Any other patch,
+    /// arrays included, replaces this value.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// let mut doc = tjson!({
+    ///     "title": "Hello",
+    ///     "author": { "given": "John", "family": "Doe" },
+    ///     "tags": ["example", "sample"],
+    /// });
+    ///
+    /// doc.merge(&tjson!({
+    ///     "title": "Goodbye",
+    ///     "author": { "family": null },
+    ///     "tags": ["example"],
+    ///     "phone": "+01-123-456-7890",
+    /// }));
+    /// assert_eq!(doc, tjson!({
+    ///     "title": "Goodbye",
+    ///     "author": { "given": "John" },
+    ///     "tags": ["example"],
+    ///     "phone": "+01-123-456-7890",
+    /// }));
+    /// # }
+    /// ```
+    pub fn merge(&mut self, patch: &Value) {
+        let members = match *patch {
+            Value::Object(ref members) => members,
+            _ => {
+                *self = patch.clone();
+                return;
+            }
+        };
+
+        if !self.is_object() {
+            *self = Value::Object(Map::new());
+        }
+        let map = self.as_object_mut().unwrap();
+        for (name, value) in members {
+            match *value {
+                Value::Undefined => {
+                    map.remove(name);
+                }
+                _ => map.entry(name.as_str()).or_insert(Value::Undefined).merge(value),
+            }
+        }
+    }
+}
+
+fn message<S>(msg: S) -> Error
+where
+    S: Into<String>,
+{
+    Error::syntax(Code::Message(msg.into()), 0, 0)
+}
+
+fn apply(doc: &mut Value, index: usize, operation: &Value) -> Result<(), Error> {
+    let failed = |err| Error::syntax(Code::PatchFailed(index, err), 0, 0);
+    let op = try!(string_member(operation, "op").map_err(&failed));
+    let path = try!(string_member(operation, "path").map_err(&failed));
+
+    if op == "test" {
+        let value = try!(member(operation, "value").map_err(&failed));
+        if try!(found(doc, path).map_err(&failed)) != value {
+            return Err(Error::syntax(Code::PatchTestFailed(index, path.to_owned()), 0, 0));
+        }
+        return Ok(());
+    }
+    change(doc, op, path, operation).map_err(failed)
+}
+
+fn change(doc: &mut Value, op: &str, path: &str, operation: &Value) -> Result<(), Error> {
+    match op {
+        "add" => add(doc, path, try!(member(operation, "value")).clone()),
+        "remove" => remove(doc, path).map(drop),
+        "replace" => {
+            let value = try!(member(operation, "value")).clone();
+            try!(found(doc, path));
+            *doc.pointer_mut(path).unwrap() = value;
+            Ok(())
+        }
+        "move" => {
+            let from = try!(string_member(operation, "from"));
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(message(format!("cannot move {:?} into itself at {:?}", from, path)));
+            }
+            if path == from {
+                return found(doc, from).map(drop);
+            }
+            let value = try!(remove(doc, from));
+            add(doc, path, value)
+        }
+        "copy" => {
+            let from = try!(string_member(operation, "from"));
+            let value = try!(found(doc, from)).clone();
+            add(doc, path, value)
+        }
+        _ => Err(message(format!("unknown patch operation {:?}", op))),
+    }
+}
+
+/// Adds a value where only the last token of the path may be missing, since
+/// unlike `Value::pointer_insert` the operation doesn't create objects.
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), Error> {
+    if let Some(split) = path.rfind('/') {
+        try!(
+            doc.pointer_checked(&path[..split])
+                .map_err(|err| invalid_pointer(path, err.token(), err.kind()))
+        );
+    }
+    doc.pointer_insert(path, value).map(drop)
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, Error> {
+    try!(found(doc, path));
+    doc.pointer_remove(path).ok_or_else(|| message("cannot remove the whole document"))
+}
+
+fn found<'a>(doc: &'a Value, path: &str) -> Result<&'a Value, Error> {
+    doc.pointer_checked(path).map_err(|err| invalid_pointer(path, err.token(), err.kind()))
+}
+
+/// The member of an operation with the given name, whether or not it is
+/// tagged.
+fn member<'a>(operation: &'a Value, name: &str) -> Result<&'a Value, Error> {
+    let map = match *operation {
+        Value::Object(ref map) => map,
+        _ => return Err(message("a patch operation must be an object")),
+    };
+    if let Some(value) = map.get(name) {
+        return Ok(value);
+    }
+    map.iter()
+        .find(|&(key, _)| key.starts_with(name) && key[name.len()..].starts_with(':'))
+        .map(|(_, value)| value)
+        .ok_or_else(|| message(format!("patch operation has no member {:?}", name)))
+}
+
+fn string_member<'a>(operation: &'a Value, name: &str) -> Result<&'a str, Error> {
+    let value = try!(member(operation, name));
+    value
+        .as_str()
+        .ok_or_else(|| message(format!("patch operation member {:?} must be a string", name)))
+}
@@ -0,0 +1,271 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+
+use error::{Code, Error};
+use map::Map;
+use super::{Value, parse_index, unescape_pointer_token};
+
+/// Why `Value::pointer_checked` found nothing, and at which token of the
+/// pointer.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tjson;
+/// #
+/// # fn main() {
+/// use tjson::value::PointerErrorKind;
+///
+/// let value = tjson!({ "servers": [{ "name": "a" }] });
+///
+/// let err = value.pointer_checked("/servers/3/name").unwrap_err();
+/// assert_eq!(err.kind(), PointerErrorKind::IndexOutOfBounds);
+/// assert_eq!(err.token(), 1);
+/// assert_eq!(err.to_string(), "index out of bounds at token 1 of the pointer");
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PointerError {
+    token: usize,
+    kind: PointerErrorKind,
+}
+
+/// The reasons a JSON Pointer can fail to find a value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerErrorKind {
+    /// An object has no member of the token's name, or the token isn't an
+    /// array index.
+    NotFound,
+
+    /// The token is an array index, but past the end of the array. This
+    /// includes `-`, which refers to the element after the last, and indices
+    /// too large for a `usize`.
+    IndexOutOfBounds,
+
+    /// The value reached before the token is not an array or object, so has
+    /// nothing inside it.
+    WrongType,
+
+    /// The token has a `~` which isn't followed by `0` or `1`.
+    BadEscape,
+
+    /// The pointer isn't empty and doesn't start with `/`. The token is
+    /// always 0.
+    Syntax,
+}
+
+impl PointerError {
+    /// Position of the token which failed among the pointer's tokens,
+    /// counting from 0.
+    pub fn token(&self) -> usize {
+        self.token
+    }
+
+    /// Why the token failed.
+    pub fn kind(&self) -> PointerErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.kind {
+            PointerErrorKind::NotFound => "not found",
+            PointerErrorKind::IndexOutOfBounds => "index out of bounds",
+            PointerErrorKind::WrongType => "not an array or object",
+            PointerErrorKind::BadEscape => "invalid `~` escape",
+            PointerErrorKind::Syntax => "pointer must be empty or start with `/`",
+        };
+        write!(f, "{} at token {} of the pointer", reason, self.token)
+    }
+}
+
+impl error::Error for PointerError {
+    fn description(&self) -> &str {
+        "JSON Pointer did not match"
+    }
+}
+
+/// Whether an array token is an index as RFC 6901 spells them, however
+/// large.
+fn is_index_syntax(token: &str) -> bool {
+    token == "-" ||
+        !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) &&
+            (token == "0" || !token.starts_with('0'))
+}
+
+/// Where in an array of `len` elements a value is inserted for `token`.
+fn insert_index(token: &str, len: usize) -> Result<usize, PointerErrorKind> {
+    if token == "-" {
+        return Ok(len);
+    }
+    match parse_index(token) {
+        Some(index) if index <= len => Ok(index),
+        _ if is_index_syntax(token) => Err(PointerErrorKind::IndexOutOfBounds),
+        _ => Err(PointerErrorKind::NotFound),
+    }
+}
+
+// Not public API. Should be pub(crate).
+pub fn invalid_pointer(pointer: &str, token: usize, kind: PointerErrorKind) -> Error {
+    let err = PointerError { token: token, kind: kind };
+    Error::syntax(Code::InvalidPointer(pointer.to_owned(), err), 0, 0)
+}
+
+impl Value {
+    /// Looks up a value by a JSON Pointer like `pointer`, but says why there
+    /// is no such value.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::value::PointerErrorKind;
+    ///
+    /// let value = tjson!({ "a/b": { "c": [1, 2] } });
+    /// assert_eq!(value.pointer_checked("/a~1b/c/1").unwrap(), &tjson!(2));
+    ///
+    /// let kind = |pointer| value.pointer_checked(pointer).unwrap_err().kind();
+    /// assert_eq!(kind("/a~1b/d"), PointerErrorKind::NotFound);
+    /// assert_eq!(kind("/a~1b/c/-"), PointerErrorKind::IndexOutOfBounds);
+    /// assert_eq!(kind("/a~1b/c/0/x"), PointerErrorKind::WrongType);
+    /// assert_eq!(kind("/a~2b"), PointerErrorKind::BadEscape);
+    /// # }
+    /// ```
+    pub fn pointer_checked<'a>(&'a self, pointer: &str) -> Result<&'a Value, PointerError> {
+        if pointer.is_empty() {
+            return Ok(self);
+        }
+        if !pointer.starts_with('/') {
+            return Err(PointerError { token: 0, kind: PointerErrorKind::Syntax });
+        }
+
+        let mut target = self;
+        for (i, raw) in pointer.split('/').skip(1).enumerate() {
+            let fail = |kind| Err(PointerError { token: i, kind: kind });
+            let token = match unescape_pointer_token(raw) {
+                Some(token) => token,
+                None => return fail(PointerErrorKind::BadEscape),
+            };
+
+            target = match *target {
+                Value::Object(ref map) => {
+                    match map.get(&*token) {
+                        Some(member) => member,
+                        None => return fail(PointerErrorKind::NotFound),
+                    }
+                }
+                Value::Array(ref list) => {
+                    match parse_index(&token).and_then(|index| list.get(index)) {
+                        Some(element) => element,
+                        None if is_index_syntax(&token) => {
+                            return fail(PointerErrorKind::IndexOutOfBounds)
+                        }
+                        None => return fail(PointerErrorKind::NotFound),
+                    }
+                }
+                _ => return fail(PointerErrorKind::WrongType),
+            };
+        }
+        Ok(target)
+    }
+    /// Puts a value at the place a JSON Pointer names, the way an RFC 6902
+    /// `add` operation does, and returns the value it replaced, if any.
+    ///
+    /// Objects are created for members missing along the way. In an array,
+    /// the value is inserted before the element at the token's index, and
+    /// `-` appends it. The empty pointer replaces the whole value.
+    ///
+    /// Fails with `ErrorCode::InvalidPointer`, leaving the value unchanged,
+    /// if the pointer goes through anything other than an array or object,
+    /// or past the end of an array. `Error::pointer_error` says which token
+    /// failed.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::error::ErrorCode;
+    /// use tjson::value::PointerErrorKind;
+    ///
+    /// let mut value = tjson!({ "name": "a", "tags": ["x"] });
+    ///
+    /// assert_eq!(value.pointer_insert("/name", tjson!("b")).unwrap(), Some(tjson!("a")));
+    /// assert_eq!(value.pointer_insert("/tags/-", tjson!("z")).unwrap(), None);
+    /// assert_eq!(value.pointer_insert("/tags/1", tjson!("y")).unwrap(), None);
+    /// assert_eq!(value.pointer_insert("/limits/cpu", tjson!(2)).unwrap(), None);
+    /// assert_eq!(value, tjson!({
+    ///     "name": "b",
+    ///     "tags": ["x", "y", "z"],
+    ///     "limits": { "cpu": 2 },
+    /// }));
+    ///
+    /// let err = value.pointer_insert("/name/first", tjson!("c")).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::InvalidPointer);
+    /// assert_eq!(err.pointer_error().unwrap().kind(), PointerErrorKind::WrongType);
+    /// # }
+    /// ```
+    pub fn pointer_insert(&mut self, pointer: &str, value: Value) -> Result<Option<Value>, Error> {
+        if pointer.is_empty() {
+            return Ok(Some(mem::replace(self, value)));
+        }
+        if !pointer.starts_with('/') {
+            return Err(invalid_pointer(pointer, 0, PointerErrorKind::Syntax));
+        }
+
+        // Unescaping every token first means nothing is created along the
+        // way for a pointer that turns out to be bad
+        let mut tokens = Vec::new();
+        for (i, raw) in pointer.split('/').skip(1).enumerate() {
+            match unescape_pointer_token(raw) {
+                Some(token) => tokens.push(token),
+                None => return Err(invalid_pointer(pointer, i, PointerErrorKind::BadEscape)),
+            }
+        }
+        let last = tokens.len() - 1;
+
+        let mut target = self;
+        for (i, token) in tokens[..last].iter().enumerate() {
+            target = match *target {
+                Value::Object(ref mut map) => {
+                    map.entry(&**token).or_insert_with(|| Value::Object(Map::new()))
+                }
+                Value::Array(ref mut list) => {
+                    let index = match insert_index(token, list.len()) {
+                        Ok(index) => index,
+                        Err(kind) => return Err(invalid_pointer(pointer, i, kind)),
+                    };
+                    if index == list.len() {
+                        list.push(Value::Object(Map::new()));
+                    }
+                    &mut list[index]
+                }
+                _ => return Err(invalid_pointer(pointer, i, PointerErrorKind::WrongType)),
+            };
+        }
+
+        match *target {
+            Value::Object(ref mut map) => Ok(map.insert(tokens[last].to_string(), value)),
+            Value::Array(ref mut list) => {
+                match insert_index(&tokens[last], list.len()) {
+                    Ok(index) => {
+                        list.insert(index, value);
+                        Ok(None)
+                    }
+                    Err(kind) => Err(invalid_pointer(pointer, last, kind)),
+                }
+            }
+            _ => Err(invalid_pointer(pointer, last, PointerErrorKind::WrongType)),
+        }
+    }
+}
@@ -8,10 +8,15 @@
 
 use serde::{self, Serialize};
 
-use error::{Error, ErrorCode};
+use data::{Data, DATA_TOKEN};
+use de::DuplicateElements;
+use error::{Code, Error};
+use ser::is_member_name;
 use map::Map;
 use number::Number;
-use value::{Value, to_value};
+use set::{self, SET_TOKEN};
+use timestamp::{self, TIMESTAMP_TOKEN};
+use value::{Value, read_member};
 
 impl Serialize for Value {
     #[inline]
@@ -22,10 +27,12 @@ impl Serialize for Value {
         match *self {
             Value::Undefined => serializer.serialize_unit(),
             Value::Bool(b) => serializer.serialize_bool(b),
-            Value::Data(ref d) => serializer.serialize_bytes(d),
+            Value::Data(ref d) => d.serialize(serializer),
             Value::Number(ref n) => n.serialize(serializer),
             Value::String(ref s) => serializer.serialize_str(s),
-            Value::Timestamp(ref t) => serializer.serialize_str(&t.to_rfc3339()),
+            Value::Timestamp(ref t) => {
+                serializer.serialize_newtype_struct(TIMESTAMP_TOKEN, &timestamp::format(t))
+            }
             Value::Array(ref v) => v.serialize(serializer),
             Value::Set(ref s) => s.serialize(serializer),
             Value::Object(ref m) => {
@@ -41,7 +48,18 @@ impl Serialize for Value {
     }
 }
 
-pub struct Serializer;
+/// Builds a `Value` from anything serializable, building the sets in it by a
+/// `DuplicateElements` policy.
+#[derive(Copy, Clone)]
+pub struct Serializer {
+    duplicate_elements: DuplicateElements,
+}
+
+impl Serializer {
+    pub fn new(duplicate_elements: DuplicateElements) -> Self {
+        Serializer { duplicate_elements: duplicate_elements }
+    }
+}
 
 impl serde::Serializer for Serializer {
     type Ok = Value;
@@ -76,7 +94,7 @@ impl serde::Serializer for Serializer {
     }
 
     fn serialize_i64(self, value: i64) -> Result<Value, Error> {
-        Ok(Value::Number(value.into()))
+        Ok(Value::Number(Number::from_i64_signed(value)))
     }
 
     #[inline]
@@ -122,7 +140,7 @@ impl serde::Serializer for Serializer {
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Value, Error> {
-        Ok(Value::Data(value.to_owned()))
+        Ok(Value::Data(value.into()))
     }
 
     #[inline]
@@ -148,12 +166,53 @@ impl serde::Serializer for Serializer {
     #[inline]
     fn serialize_newtype_struct<T: ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Value, Error>
     where
         T: Serialize,
     {
+        // Exact decimals arrive as their text
+        #[cfg(feature = "decimal")]
+        {
+            if name == ::number::DECIMAL_TOKEN {
+                return match try!(value.serialize(self)) {
+                    Value::String(text) => {
+                        ::number::parse_decimal(&text)
+                            .map(|d| Value::Number(Number::from_decimal(d)))
+                            .map_err(|_| serde::ser::Error::custom("number out of range"))
+                    }
+                    _ => Err(serde::ser::Error::custom("expected the text of a decimal")),
+                };
+            }
+        }
+
+        if name == SET_TOKEN {
+            return set::from_elements(try!(value.serialize(self)), self.duplicate_elements);
+        }
+
+        // Data held as text stays that way
+        if name == DATA_TOKEN {
+            return match try!(value.serialize(self)) {
+                Value::String(text) => {
+                    Data::from_b64url(text)
+                        .map(Value::Data)
+                        .map_err(serde::ser::Error::custom)
+                }
+                _ => Err(serde::ser::Error::custom("expected the text of data")),
+            };
+        }
+
+        if name == TIMESTAMP_TOKEN {
+            return match try!(value.serialize(self)) {
+                Value::String(text) => match timestamp::parse(&text) {
+                    Some((timestamp, _)) => Ok(Value::Timestamp(timestamp)),
+                    None => Ok(Value::String(text)),
+                },
+                other => Ok(other),
+            };
+        }
+
         value.serialize(self)
     }
 
@@ -168,7 +227,8 @@ impl serde::Serializer for Serializer {
         T: Serialize,
     {
         let mut values = Map::new();
-        values.insert(String::from(variant), try!(to_value(&value)));
+        let value = read_member(variant, try!(value.serialize(self)));
+        values.insert(String::from(variant), value);
         Ok(Value::Object(values))
     }
 
@@ -186,7 +246,12 @@ impl serde::Serializer for Serializer {
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
-        Ok(SerializeVec { vec: Vec::with_capacity(len.unwrap_or(0)) })
+        Ok(
+            SerializeVec {
+                ser: self,
+                vec: Vec::with_capacity(len.unwrap_or(0)),
+            },
+        )
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
@@ -210,6 +275,7 @@ impl serde::Serializer for Serializer {
     ) -> Result<Self::SerializeTupleVariant, Error> {
         Ok(
             SerializeTupleVariant {
+                ser: self,
                 name: String::from(variant),
                 vec: Vec::with_capacity(len),
             },
@@ -219,8 +285,10 @@ impl serde::Serializer for Serializer {
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
         Ok(
             SerializeMap {
+                ser: self,
                 map: Map::new(),
                 next_key: None,
+                pairs: None,
             },
         )
     }
@@ -242,6 +310,7 @@ impl serde::Serializer for Serializer {
     ) -> Result<Self::SerializeStructVariant, Error> {
         Ok(
             SerializeStructVariant {
+                ser: self,
                 name: String::from(variant),
                 map: Map::new(),
             },
@@ -251,23 +320,30 @@ impl serde::Serializer for Serializer {
 
 #[doc(hidden)]
 pub struct SerializeVec {
+    ser: Serializer,
     vec: Vec<Value>,
 }
 
 #[doc(hidden)]
 pub struct SerializeTupleVariant {
+    ser: Serializer,
     name: String,
     vec: Vec<Value>,
 }
 
 #[doc(hidden)]
 pub struct SerializeMap {
+    ser: Serializer,
     map: Map<String, Value>,
     next_key: Option<String>,
+    /// The `[key, value]` pairs so far, if the first key couldn't be written
+    /// as a member name.
+    pairs: Option<Vec<Value>>,
 }
 
 #[doc(hidden)]
 pub struct SerializeStructVariant {
+    ser: Serializer,
     name: String,
     map: Map<String, Value>,
 }
@@ -280,7 +356,7 @@ impl serde::ser::SerializeSeq for SerializeVec {
     where
         T: Serialize,
     {
-        self.vec.push(try!(to_value(&value)));
+        self.vec.push(try!(value.serialize(self.ser)));
         Ok(())
     }
 
@@ -329,7 +405,7 @@ impl serde::ser::SerializeTupleVariant for SerializeTupleVariant {
     where
         T: Serialize,
     {
-        self.vec.push(try!(to_value(&value)));
+        self.vec.push(try!(value.serialize(self.ser)));
         Ok(())
     }
 
@@ -350,16 +426,24 @@ impl serde::ser::SerializeMap for SerializeMap {
     where
         T: Serialize,
     {
-        match try!(to_value(&key)) {
+        if self.map.is_empty() && self.pairs.is_none() && !is_member_name(key) {
+            self.pairs = Some(Vec::new());
+        }
+        if let Some(ref mut pairs) = self.pairs {
+            pairs.push(Value::Array(vec![try!(key.serialize(self.ser))]));
+            return Ok(());
+        }
+
+        match try!(key.serialize(self.ser)) {
             Value::String(s) => self.next_key = Some(s),
             Value::Number(n) => {
                 if n.is_u64() || n.is_i64() {
                     self.next_key = Some(n.to_string())
                 } else {
-                    return Err(Error::syntax(ErrorCode::KeyMustBeAString, 0, 0));
+                    return Err(Error::syntax(Code::KeyMustBeAString, 0, 0));
                 }
             }
-            _ => return Err(Error::syntax(ErrorCode::KeyMustBeAString, 0, 0)),
+            _ => return Err(Error::syntax(Code::KeyMustBeAString, 0, 0)),
         };
         Ok(())
     }
@@ -368,16 +452,29 @@ impl serde::ser::SerializeMap for SerializeMap {
     where
         T: Serialize,
     {
+        if let Some(ref mut pairs) = self.pairs {
+            let value = try!(value.serialize(self.ser));
+            match pairs.last_mut() {
+                Some(&mut Value::Array(ref mut pair)) if pair.len() == 1 => pair.push(value),
+                _ => panic!("serialize_value called before serialize_key"),
+            }
+            return Ok(());
+        }
+
         let key = self.next_key.take();
         // Panic because this indicates a bug in the program rather than an
         // expected failure.
         let key = key.expect("serialize_value called before serialize_key");
-        self.map.insert(key, try!(to_value(&value)));
+        let value = read_member(&key, try!(value.serialize(self.ser)));
+        self.map.insert(key, value);
         Ok(())
     }
 
     fn end(self) -> Result<Value, Error> {
-        Ok(Value::Object(self.map))
+        match self.pairs {
+            Some(pairs) => Ok(Value::Array(pairs)),
+            None => Ok(Value::Object(self.map)),
+        }
     }
 }
 
@@ -406,8 +503,8 @@ impl serde::ser::SerializeStructVariant for SerializeStructVariant {
     where
         T: Serialize,
     {
-        self.map
-            .insert(String::from(key), try!(to_value(&value)));
+        let value = read_member(key, try!(value.serialize(self.ser)));
+        self.map.insert(String::from(key), value);
         Ok(())
     }
 
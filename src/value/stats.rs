@@ -0,0 +1,418 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Size and shape statistics of `Value`s
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::iter::Enumerate;
+use std::slice;
+
+use map;
+use set;
+use super::{Value, ValueKind, push_pointer_token};
+
+/// The size and shape of a `Value`, as measured by `Value::stats`.
+///
+/// Paths are JSON Pointers, as accepted by `Value::pointer`. When several
+/// containers or names tie for the largest, the path is that of the first one
+/// in document order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValueStats {
+    /// Number of arrays, sets, and objects nested inside each other at the
+    /// deepest point. A scalar has depth 0 and `[]` has depth 1.
+    pub max_depth: usize,
+
+    /// Path to the first container at `max_depth`, or `None` if there are no
+    /// containers.
+    pub deepest_path: Option<String>,
+
+    /// Number of values, counting the value itself and everything inside it.
+    pub nodes: usize,
+
+    /// Number of values of each kind. Kinds which don't occur are absent.
+    pub kinds: BTreeMap<ValueKind, usize>,
+
+    /// Total length in bytes of all strings, not counting member names.
+    pub string_bytes: usize,
+
+    /// Total length in bytes of all binary data.
+    pub data_bytes: usize,
+
+    /// Number of elements or members in the largest array, set, or object.
+    pub largest_container: usize,
+
+    /// Path to the largest container, or `None` if there are no containers.
+    pub largest_container_path: Option<String>,
+
+    /// Length in bytes of the longest member name, including its tag.
+    pub longest_name: usize,
+
+    /// Path to the member with the longest name, or `None` if there are no
+    /// members.
+    pub longest_name_path: Option<String>,
+}
+
+impl ValueStats {
+    /// Number of values of the given kind.
+    pub fn count(&self, kind: ValueKind) -> usize {
+        self.kinds.get(&kind).cloned().unwrap_or(0)
+    }
+
+    /// The first limit these statistics exceed, if any.
+    ///
+    /// Limits are checked in the order the `Metric` variants are declared.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::value::{Metric, StatsLimits};
+    ///
+    /// let value = tjson!({ "users": [{ "name": "a" }, { "name": "b" }, { "name": "c" }] });
+    /// let limits = StatsLimits::new().max_depth(8).max_container_len(2);
+    ///
+    /// let violation = value.stats().exceeds(&limits).unwrap();
+    /// assert_eq!(violation.metric(), Metric::ContainerLen);
+    /// assert_eq!(violation.path(), Some("/users"));
+    /// assert_eq!(violation.to_string(), "container length 3 exceeds the limit of 2 at /users");
+    /// # }
+    /// ```
+    pub fn exceeds(&self, limits: &StatsLimits) -> Option<StatViolation> {
+        let checks = [
+            (Metric::Depth, limits.max_depth, self.max_depth, &self.deepest_path),
+            (Metric::Nodes, limits.max_nodes, self.nodes, &None),
+            (Metric::StringBytes, limits.max_string_bytes, self.string_bytes, &None),
+            (Metric::DataBytes, limits.max_data_bytes, self.data_bytes, &None),
+            (
+                Metric::ContainerLen,
+                limits.max_container_len,
+                self.largest_container,
+                &self.largest_container_path,
+            ),
+            (Metric::NameLen, limits.max_name_len, self.longest_name, &self.longest_name_path),
+        ];
+
+        for &(metric, limit, actual, path) in &checks {
+            if let Some(limit) = limit {
+                if actual > limit {
+                    return Some(StatViolation {
+                        metric: metric,
+                        limit: limit,
+                        actual: actual,
+                        path: path.clone(),
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Upper bounds on `ValueStats`, for use with `ValueStats::exceeds`.
+///
+/// Every metric is unbounded unless a limit is set for it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsLimits {
+    max_depth: Option<usize>,
+    max_nodes: Option<usize>,
+    max_string_bytes: Option<usize>,
+    max_data_bytes: Option<usize>,
+    max_container_len: Option<usize>,
+    max_name_len: Option<usize>,
+}
+
+impl StatsLimits {
+    /// Limits which accept values of any size.
+    pub fn new() -> Self {
+        StatsLimits::default()
+    }
+
+    /// Limit how deeply containers may be nested.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Limit the total number of values.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Limit the total length of all strings.
+    pub fn max_string_bytes(mut self, bytes: usize) -> Self {
+        self.max_string_bytes = Some(bytes);
+        self
+    }
+
+    /// Limit the total length of all binary data.
+    pub fn max_data_bytes(mut self, bytes: usize) -> Self {
+        self.max_data_bytes = Some(bytes);
+        self
+    }
+
+    /// Limit the number of elements or members in any one container.
+    pub fn max_container_len(mut self, len: usize) -> Self {
+        self.max_container_len = Some(len);
+        self
+    }
+
+    /// Limit the length of any one member name.
+    pub fn max_name_len(mut self, len: usize) -> Self {
+        self.max_name_len = Some(len);
+        self
+    }
+}
+
+/// A statistic measured by `ValueStats` which `StatsLimits` can bound.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Metric {
+    /// `ValueStats::max_depth`
+    Depth,
+    /// `ValueStats::nodes`
+    Nodes,
+    /// `ValueStats::string_bytes`
+    StringBytes,
+    /// `ValueStats::data_bytes`
+    DataBytes,
+    /// `ValueStats::largest_container`
+    ContainerLen,
+    /// `ValueStats::longest_name`
+    NameLen,
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            match *self {
+                Metric::Depth => "depth",
+                Metric::Nodes => "node count",
+                Metric::StringBytes => "string bytes",
+                Metric::DataBytes => "data bytes",
+                Metric::ContainerLen => "container length",
+                Metric::NameLen => "member name length",
+            },
+        )
+    }
+}
+
+/// A limit which a value's statistics exceed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatViolation {
+    metric: Metric,
+    limit: usize,
+    actual: usize,
+    path: Option<String>,
+}
+
+impl StatViolation {
+    // Not public API. Should be pub(crate).
+    #[doc(hidden)]
+    pub fn new(metric: Metric, limit: usize, actual: usize, path: Option<String>) -> Self {
+        StatViolation {
+            metric: metric,
+            limit: limit,
+            actual: actual,
+            path: path,
+        }
+    }
+
+    /// The statistic which is over its limit.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// The limit which was exceeded.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The measured value of the statistic.
+    pub fn actual(&self) -> usize {
+        self.actual
+    }
+
+    /// JSON Pointer to the offending container or member, for the metrics
+    /// which have one. Totals over the whole value have no path.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+}
+
+impl fmt::Display for StatViolation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        try!(
+            write!(
+                formatter,
+                "{} {} exceeds the limit of {}",
+                self.metric,
+                self.actual,
+                self.limit
+            )
+        );
+        match self.path {
+            Some(ref path) if path.is_empty() => formatter.write_str(" at the root"),
+            Some(ref path) => write!(formatter, " at {}", path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Value {
+    /// Measure the size and shape of the value.
+    ///
+    /// This walks the value once with an explicit stack rather than by
+    /// recursion, so it is safe to use on arbitrarily deep values.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate tjson;
+    /// #
+    /// # fn main() {
+    /// use tjson::value::ValueKind;
+    ///
+    /// let stats = tjson!({ "tags": ["a", "bc"], "nested": { "deep": [[]] } }).stats();
+    /// assert_eq!(stats.max_depth, 4);
+    /// assert_eq!(stats.deepest_path, Some("/nested/deep/0".to_owned()));
+    /// assert_eq!(stats.nodes, 7);
+    /// assert_eq!(stats.count(ValueKind::Array), 3);
+    /// assert_eq!(stats.string_bytes, 3);
+    /// # }
+    /// ```
+    pub fn stats(&self) -> ValueStats {
+        let mut walk = Walk::default();
+        walk.run(self);
+        walk.stats
+    }
+}
+
+/// How a value was reached from its parent.
+#[derive(Copy, Clone)]
+pub enum Token<'a> {
+    Root,
+    Index(usize),
+    Name(&'a str),
+}
+
+/// The elements or members of a container, with their tokens.
+pub enum Children<'a> {
+    Array(Enumerate<slice::Iter<'a, Value>>),
+    Set(Enumerate<set::Iter<'a>>),
+    Object(map::Iter<'a>),
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = (Token<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            Children::Array(ref mut iter) => iter.next().map(|(i, v)| (Token::Index(i), v)),
+            Children::Set(ref mut iter) => iter.next().map(|(i, v)| (Token::Index(i), v)),
+            Children::Object(ref mut iter) => iter.next().map(|(k, v)| (Token::Name(k), v)),
+        }
+    }
+}
+
+struct Frame<'a> {
+    /// Length of the path to the container's parent
+    parent_len: usize,
+    children: Children<'a>,
+}
+
+/// An explicit stack of the containers being walked, along with the path to
+/// the innermost one.
+#[derive(Default)]
+struct Walk<'a> {
+    stats: ValueStats,
+    stack: Vec<Frame<'a>>,
+    path: String,
+
+    /// Whether the innermost container is the deepest one seen so far. Its
+    /// path is only copied once it's known that nothing below it is deeper,
+    /// so that long chains of containers don't copy ever longer paths.
+    deepest_pending: bool,
+}
+
+impl<'a> Walk<'a> {
+    fn run(&mut self, root: &'a Value) {
+        self.visit(Token::Root, root);
+
+        while let Some(frame) = self.stack.last_mut() {
+            match frame.children.next() {
+                Some((token, value)) => self.visit(token, value),
+                None => self.leave(),
+            }
+        }
+    }
+
+    fn visit(&mut self, token: Token<'a>, value: &'a Value) {
+        self.stats.nodes += 1;
+        *self.stats.kinds.entry(value.kind()).or_insert(0) += 1;
+
+        let parent_len = self.path.len();
+        match token {
+            Token::Root => {}
+            Token::Index(index) => {
+                let _ = write!(self.path, "/{}", index);
+            }
+            Token::Name(name) => {
+                push_pointer_token(&mut self.path, name);
+                if name.len() > self.stats.longest_name || self.stats.longest_name_path.is_none() {
+                    self.stats.longest_name = name.len();
+                    self.stats.longest_name_path = Some(self.path.clone());
+                }
+            }
+        }
+
+        let children = match *value {
+            Value::Array(ref array) => Children::Array(array.iter().enumerate()),
+            Value::Set(ref set) => Children::Set(set.iter().enumerate()),
+            Value::Object(ref map) => Children::Object(map.iter()),
+            _ => {
+                let bytes = value.len().unwrap_or(0);
+                match value.kind() {
+                    ValueKind::String => self.stats.string_bytes += bytes,
+                    ValueKind::Data => self.stats.data_bytes += bytes,
+                    _ => {}
+                }
+                self.path.truncate(parent_len);
+                return;
+            }
+        };
+        let len = value.len().unwrap_or(0);
+
+        let depth = self.stack.len() + 1;
+        if depth > self.stats.max_depth {
+            self.stats.max_depth = depth;
+            self.deepest_pending = true;
+        }
+        if len > self.stats.largest_container || self.stats.largest_container_path.is_none() {
+            self.stats.largest_container = len;
+            self.stats.largest_container_path = Some(self.path.clone());
+        }
+
+        self.stack.push(
+            Frame {
+                parent_len: parent_len,
+                children: children,
+            },
+        );
+    }
+
+    fn leave(&mut self) {
+        if self.deepest_pending {
+            self.stats.deepest_path = Some(self.path.clone());
+            self.deepest_pending = false;
+        }
+        if let Some(frame) = self.stack.pop() {
+            self.path.truncate(frame.parent_len);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "bytes")]
+
+extern crate bytes;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use bytes::Bytes;
+use serde::Deserialize;
+use tjson::{Data, Deserializer, Value};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Message<'a> {
+    name: &'a str,
+    payload: &'a [u8],
+}
+
+fn points_into(buf: &Bytes, s: &[u8]) -> bool {
+    let start = buf.as_ptr() as usize;
+    let ptr = s.as_ptr() as usize;
+    ptr >= start && ptr + s.len() <= start + buf.len()
+}
+
+#[test]
+fn test_from_bytes_borrows_from_buffer() {
+    let payload = "x".repeat(1 << 16);
+    let buf = Bytes::from(format!(r#"{{"name": "big", "payload": "{}"}}"#, payload));
+
+    let message: Message = tjson::from_bytes(&buf).unwrap();
+
+    assert_eq!(message.name, "big");
+    assert_eq!(message.payload, payload.as_bytes());
+    assert!(points_into(&buf, message.name.as_bytes()));
+    assert!(points_into(&buf, message.payload));
+}
+
+#[test]
+fn test_from_bytes_slice_of_larger_buffer() {
+    let frame = Bytes::from(&b"HEAD[\"a\",\"b\"]TAIL"[..]);
+    let body = frame.slice(4..13);
+
+    let strings: Vec<&str> = tjson::from_bytes(&body).unwrap();
+    assert_eq!(strings, ["a", "b"]);
+    assert!(strings.iter().all(|s| points_into(&frame, s.as_bytes())));
+
+    let err = tjson::from_bytes::<Vec<&str>>(&frame).unwrap_err();
+    assert_eq!(err.to_string(), "expected value at line 1 column 1");
+}
+
+#[test]
+fn test_to_bytes_round_trip() {
+    let value = tjson!({
+        "a:A<f>": [1.0, -2.0, 3.5],
+        "b:O": { "c:s": "d" },
+        "e:b": true,
+    });
+
+    let buf = tjson::to_bytes(&value).unwrap();
+    assert_eq!(&buf[..], &tjson::to_vec(&value).unwrap()[..]);
+
+    let parsed: Value = tjson::from_bytes(&buf).unwrap();
+    assert_eq!(parsed, value);
+}
+
+#[test]
+fn test_data_shares_bytes() {
+    let buf = Bytes::from(vec![0xAB; 1 << 16]);
+
+    let value = Value::Data(Data::from(buf.clone()));
+    assert_eq!(value.as_data().unwrap().as_ptr(), buf.as_ptr());
+    assert!(!buf.is_unique());
+
+    let shared = match value {
+        Value::Data(data) => data.into_bytes(),
+        _ => unreachable!(),
+    };
+    assert_eq!(shared.as_ptr(), buf.as_ptr());
+    drop(shared);
+    assert!(buf.is_unique());
+}
+
+#[test]
+fn test_data_into_bytes_moves_vec() {
+    let vec = vec![1u8, 2, 3];
+    let ptr = vec.as_ptr();
+
+    let buf = Data::from(vec).into_bytes();
+    assert_eq!(buf.as_ptr(), ptr);
+    assert_eq!(&buf[..], [1, 2, 3]);
+}
+
+#[test]
+fn test_data_to_mut_copies_bytes() {
+    let buf = Bytes::from(vec![1u8, 2, 3]);
+    let mut value = Value::Data(Data::from(buf.clone()));
+
+    value.as_data_mut().unwrap().push(4);
+    assert!(buf.is_unique());
+    assert_ne!(value.as_data().unwrap().as_ptr(), buf.as_ptr());
+    assert_eq!(&buf[..], [1, 2, 3]);
+    assert_eq!(value.as_data(), Some(&[1, 2, 3, 4][..]));
+}
+
+#[test]
+fn test_data_bytes_round_trip() {
+    let buf = Bytes::from(b"BINARY".to_vec());
+    let value = tjson!({ "raw:d": Value::Data(Data::from(buf.clone())) });
+
+    let out = tjson::to_bytes(&value).unwrap();
+    assert_eq!(&out[..], &br#"{"raw:d":"QklOQVJZ"}"#[..]);
+
+    // Data is decoded out of the input, or its text copied, never borrowed
+    let parsed: Value = tjson::from_bytes(&out).unwrap();
+    assert_eq!(parsed, value);
+    assert!(!points_into(&out, parsed["raw:d"].as_data().unwrap()));
+
+    let mut de = Deserializer::from_slice(&out).lazy_data(true);
+    let lazy = Value::deserialize(&mut de).unwrap();
+    assert_eq!(lazy, value);
+    assert!(!points_into(&out, lazy["raw:d"].as_data().unwrap()));
+    assert_eq!(tjson::to_bytes(&lazy).unwrap(), out);
+
+    let data = match parsed["raw:d"] {
+        Value::Data(ref data) => data.clone().into_bytes(),
+        _ => unreachable!(),
+    };
+    assert_eq!(data, buf);
+}
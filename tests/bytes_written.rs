@@ -0,0 +1,126 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate tjson;
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+use tjson::{Serializer, Set, Value};
+use tjson::ser::{ArrayWrap, PrettyFormatter};
+
+fn corpus() -> Vec<Value> {
+    let mut set = Set::new();
+    set.insert(tjson!("b"));
+    set.insert(tjson!("a"));
+
+    vec![
+        Value::Undefined,
+        tjson!(-9007199254740993i64),
+        tjson!(0.1),
+        tjson!([]),
+        tjson!({}),
+        Value::Set(Set::new()),
+        Value::Data(vec![0, 127, 255].into()),
+        Value::Timestamp("2017-03-04T05:06:07Z".parse().unwrap()),
+        tjson!(["line one\r\n", "tab\there \"quoted\"", "\u{0}\u{1f}", "caf\u{e9} \u{1f600}"]),
+        tjson!({
+            "name:s": "ann",
+            "scores:A<f>": [1.5, 2.0, 1e300],
+            "profile:O": { "age:u": 30, "tags:S<s>": ["z", "a"], "empty:A<O>": [] },
+        }),
+        Value::Set(set),
+    ]
+}
+
+#[test]
+fn test_counted_matches_to_vec() {
+    for value in corpus() {
+        let mut writer = Vec::new();
+        let written = tjson::to_writer(&mut writer, &value).unwrap();
+        assert_eq!(writer, tjson::to_vec(&value).unwrap());
+        assert_eq!(written, writer.len(), "{:?}", value);
+
+        let mut writer = Vec::new();
+        let written = tjson::to_writer_pretty(&mut writer, &value).unwrap();
+        assert_eq!(writer, tjson::to_vec_pretty(&value).unwrap());
+        assert_eq!(written, writer.len(), "{:?}", value);
+
+        let mut writer = Vec::new();
+        let written = tjson::to_writer_canonical(&mut writer, &value).unwrap();
+        assert_eq!(writer, tjson::to_string_canonical(&value).unwrap().into_bytes());
+        assert_eq!(written, writer.len(), "{:?}", value);
+    }
+}
+
+fn tabbed() -> PrettyFormatter<'static> {
+    PrettyFormatter::with_indent(b"\t").array_wrap(ArrayWrap::Fill(20)).trailing_newline(true)
+}
+
+#[test]
+fn test_custom_formatter_and_several_values() {
+    let mut ser = Serializer::with_formatter(Vec::new(), tabbed());
+    assert_eq!(ser.bytes_written(), 0);
+
+    let mut expected = 0;
+    for value in corpus() {
+        let mut alone = Serializer::with_formatter(Vec::new(), tabbed());
+        value.serialize(&mut alone).unwrap();
+        expected += alone.into_inner().len();
+
+        // The count carries on across values written with one serializer
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(ser.bytes_written(), expected, "{:?}", value);
+    }
+    assert_eq!(ser.into_inner().len(), expected);
+}
+
+/// Accepts `room` bytes, the last of them in a short write, then fails.
+struct FailingWriter {
+    accepted: Vec<u8>,
+    room: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let left = self.room - self.accepted.len();
+        if left == 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+        }
+        let n = buf.len().min(left);
+        self.accepted.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_failing_writer() {
+    let value = corpus().swap_remove(9);
+    let full = tjson::to_vec_pretty(&value).unwrap();
+
+    for room in &[0, 1, 7, full.len() - 1] {
+        let writer = FailingWriter { accepted: Vec::new(), room: *room };
+        let mut ser = Serializer::pretty(writer);
+        let err = value.serialize(&mut ser).unwrap_err();
+        assert!(err.is_io(), "{}", err);
+
+        // Exactly what the writer took, cut off partway through a token
+        assert_eq!(ser.bytes_written(), *room);
+        let writer = ser.into_inner();
+        assert_eq!(writer.accepted, &full[..*room]);
+    }
+
+    let writer = FailingWriter { accepted: Vec::new(), room: full.len() };
+    assert_eq!(tjson::to_writer_pretty(writer, &value).unwrap(), full.len());
+}
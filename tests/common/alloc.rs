@@ -0,0 +1,84 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+//! A global allocator which counts allocations, for the tests which measure
+//! them and include this module with
+//! `#[path = "common/alloc.rs"] mod alloc;`. It is kept out of `common` so
+//! that tests which only need the other helpers keep the system allocator.
+
+// Each test binary uses only some of the helpers
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::cmp;
+
+/// Counts allocations made by the current thread, so tests running in
+/// parallel don't disturb each other's counts
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        let _ = ALLOCATED.try_with(|n| n.set(n.get() + layout.size()));
+        let _ = LIVE_BYTES.try_with(
+            |n| {
+                n.set(n.get() + layout.size() as isize);
+                let _ = PEAK_BYTES.try_with(|peak| peak.set(cmp::max(peak.get(), n.get())));
+            },
+        );
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE_BYTES.try_with(|n| n.set(n.get() - layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// The number of allocations `f` makes on this thread
+pub fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|n| n.get());
+    f();
+    ALLOCATIONS.with(|n| n.get()) - before
+}
+
+/// The total bytes `f` allocates on this thread, whether or not it frees them
+pub fn allocated_bytes<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATED.with(|n| n.get());
+    f();
+    ALLOCATED.with(|n| n.get()) - before
+}
+
+/// Bytes still allocated by whatever `f` returns
+pub fn retained<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let before = LIVE_BYTES.with(|n| n.get());
+    let result = f();
+    let after = LIVE_BYTES.with(|n| n.get());
+    (result, (after - before) as usize)
+}
+
+/// The most bytes this thread had allocated at once while running `f`,
+/// beyond what it had allocated before
+pub fn peak_bytes<F: FnOnce()>(f: F) -> usize {
+    let before = LIVE_BYTES.with(|n| n.get());
+    PEAK_BYTES.with(|peak| peak.set(before));
+    f();
+    (PEAK_BYTES.with(|peak| peak.get()) - before) as usize
+}
@@ -0,0 +1,89 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+//! Helpers shared by the tests which include this module with `mod common;`
+
+// Each test binary uses only some of the helpers
+#![allow(dead_code)]
+
+extern crate serde;
+
+use tjson::{Deserializer, Result};
+use tjson::de::{DuplicateKeys, IntegerSignedness, Normalization, OffsetPolicy, StrRead};
+
+/// An option of `Deserializer` which `parse` sets
+pub trait Setting {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>>;
+}
+
+impl Setting for OffsetPolicy {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>> {
+        de.timestamp_offsets(self)
+    }
+}
+
+impl Setting for DuplicateKeys {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>> {
+        de.duplicate_keys(self)
+    }
+}
+
+impl Setting for Normalization {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>> {
+        de.member_name_normalization(self)
+    }
+}
+
+impl Setting for IntegerSignedness {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>> {
+        de.integer_signedness(self)
+    }
+}
+
+/// The order of `Deserializer::field_order`, if any
+impl<'b> Setting for Option<&'b [&'b str]> {
+    fn apply<'a>(self, de: Deserializer<StrRead<'a>>) -> Deserializer<StrRead<'a>> {
+        match self {
+            Some(order) => de.field_order(order),
+            None => de,
+        }
+    }
+}
+
+/// Deserializes all of `input` with `setting`
+pub fn parse<'a, T, S>(input: &'a str, setting: S) -> Result<T>
+where
+    T: serde::Deserialize<'a>,
+    S: Setting,
+{
+    let mut de = setting.apply(Deserializer::from_str(input));
+    let value = try!(T::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
+
+/// A small xorshift generator, so generated inputs are the same on every run
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::mem;
+use std::sync::Arc;
+
+use tjson::Value;
+use tjson::value::{CompactStats, CompactValue};
+
+fn telemetry(points: usize) -> Value {
+    let points = (0..points)
+        .map(
+            |i| {
+                tjson!({
+                    "timestamp": i,
+                    "value": i as f64 / 4.0,
+                    "host": "ingest-worker-eu-west-1",
+                    "metric": { "unit": "milliseconds", "type": "gauge", "scale": 1000 },
+                })
+            },
+        )
+        .collect();
+    Value::Array(points)
+}
+
+#[test]
+fn test_compact_reduces_heap() {
+    let (value, value_bytes) = alloc::retained(|| telemetry(20_000));
+    let ((compact, stats), compact_bytes) = alloc::retained(|| value.compact());
+
+    assert!(
+        compact_bytes * 3 < value_bytes,
+        "compact {} bytes, value {} bytes",
+        compact_bytes,
+        value_bytes
+    );
+
+    // Every point after the first shares its metric object, host, and member
+    // names
+    assert_eq!(stats.subtrees_shared, 19_999);
+    assert_eq!(stats.strings_shared, 19_999 * 5);
+    assert!(stats.bytes_saved > 19_999 * "ingest-worker-eu-west-1".len());
+
+    assert_eq!(compact.to_value(), value);
+    assert_eq!(tjson::to_string(&compact).unwrap(), tjson::to_string(&value).unwrap());
+    assert_eq!(
+        tjson::to_string_pretty(&compact).unwrap(),
+        tjson::to_string_pretty(&value).unwrap()
+    );
+}
+
+#[test]
+fn test_compact_stats() {
+    let point = tjson!({ "unit": "milliseconds", "type": "gauge" });
+    let (_, stats) = tjson!([point.clone(), point.clone(), point]).compact();
+    assert_eq!(
+        stats,
+        CompactStats {
+            strings_shared: 0,
+            subtrees_shared: 2,
+            bytes_saved: 2 *
+                (2 * mem::size_of::<(Arc<str>, CompactValue)>() +
+                     "unittypemillisecondsgauge".len()),
+        }
+    );
+
+    let (_, stats) = tjson!(["a long string", "a long string", "short", "short"]).compact();
+    assert_eq!(stats.strings_shared, 1);
+    assert_eq!(stats.subtrees_shared, 0);
+    assert_eq!(stats.bytes_saved, "a long string".len());
+
+    let (_, stats) = tjson!([{ "name": 1 }, { "name": 2 }]).compact();
+    assert_eq!(stats.strings_shared, 1);
+    assert_eq!(stats.bytes_saved, "name".len());
+
+    let (_, stats) = tjson!({ "a": [], "b": {}, "c": null }).compact();
+    assert_eq!(stats, CompactStats::default());
+}
+
+#[test]
+fn test_compact_round_trip() {
+    let value: Value = tjson::from_str(
+        r#"{"z": [1, -2, 3.5, true, null, "x"], "a": {"b": [[], {}]}, "m": [{"k": 1}, {"k": 1}]}"#,
+    ).unwrap();
+    let (compact, _) = value.compact();
+
+    assert_eq!(compact.to_value(), value);
+    assert_eq!(compact.pointer("/z/5").and_then(|v| v.as_str()), Some("x"));
+    assert_eq!(compact.pointer("/m/1/k").unwrap().to_value(), tjson!(1));
+    assert!(compact.pointer("/m/2").is_none());
+    assert!(compact.pointer("/a/c").is_none());
+}
@@ -0,0 +1,92 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+#[macro_use]
+extern crate tjson;
+
+use std::convert::TryFrom;
+
+use chrono::offset::utc::UTC;
+use chrono::offset::TimeZone;
+
+use tjson::{DateTime, Map, Value};
+use tjson::value::ValueKind;
+
+#[test]
+fn test_try_from_scalars() {
+    assert_eq!(bool::try_from(tjson!(true)), Ok(true));
+    assert_eq!(i64::try_from(tjson!(-42)), Ok(-42));
+    assert_eq!(u64::try_from(tjson!(42)), Ok(42));
+    assert_eq!(f64::try_from(tjson!(4.5)), Ok(4.5));
+    assert_eq!(String::try_from(tjson!("foo")), Ok("foo".to_owned()));
+    assert_eq!(Vec::<u8>::try_from(Value::Data(vec![1, 2, 3].into())), Ok(vec![1, 2, 3]));
+
+    let err = u64::try_from(tjson!(-1)).unwrap_err();
+    assert_eq!(err.expected(), "u64");
+    assert_eq!(err.found(), ValueKind::Number);
+    assert!(err.path().is_empty());
+
+    let err = String::try_from(&tjson!(false)).unwrap_err();
+    assert_eq!(err.found(), ValueKind::Bool);
+    assert_eq!(err.to_string(), "expected string, found boolean");
+}
+
+#[test]
+fn test_try_from_timestamp() {
+    let t: DateTime = UTC.ymd(2016, 11, 6).and_hms(22, 27, 34);
+    let v = Value::Timestamp(t);
+
+    assert_eq!(DateTime::try_from(&v), Ok(t));
+    assert_eq!(DateTime::try_from(v), Ok(t));
+
+    let err = DateTime::try_from(tjson!("2016-11-06T22:27:34Z")).unwrap_err();
+    assert_eq!(err.expected(), "timestamp");
+    assert_eq!(err.found(), ValueKind::String);
+}
+
+#[test]
+fn test_try_from_object() {
+    let v = tjson!({ "a": 1 });
+    let map = Map::try_from(&v).unwrap();
+    assert_eq!(map["a"], 1);
+    assert_eq!(Map::try_from(v), Ok(map));
+
+    let err = Map::try_from(tjson!([1])).unwrap_err();
+    assert_eq!(err.found(), ValueKind::Array);
+}
+
+#[test]
+fn test_try_from_nested_vec() {
+    let v = tjson!([[1, 2], [], [3]]);
+    let expected: Vec<Vec<u64>> = vec![vec![1, 2], vec![], vec![3]];
+
+    assert_eq!(Vec::<Vec<u64>>::try_from(&v), Ok(expected.clone()));
+    assert_eq!(Vec::<Vec<u64>>::try_from(v), Ok(expected));
+
+    let values = Vec::<Value>::try_from(tjson!([1, "two"])).unwrap();
+    assert_eq!(values, vec![tjson!(1), tjson!("two")]);
+}
+
+#[test]
+fn test_try_from_element_error_index() {
+    let v = tjson!(["a", "b", "c", 4, "e"]);
+    let err = Vec::<String>::try_from(&v).unwrap_err();
+
+    assert_eq!(err.path(), &[3]);
+    assert_eq!(err.found(), ValueKind::Number);
+    assert_eq!(err.to_string(), "expected string, found number at [3]");
+
+    let v = tjson!([[1], [2, 3, 4, "x"]]);
+    let err = Vec::<Vec<u64>>::try_from(v).unwrap_err();
+    assert_eq!(err.path(), &[1, 3]);
+
+    let err = Vec::<u64>::try_from(tjson!({})).unwrap_err();
+    assert_eq!(err.expected(), "array or set");
+    assert_eq!(err.found(), ValueKind::Object);
+}
@@ -0,0 +1,167 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::borrow::Cow;
+
+use tjson::{Map, Value};
+
+/// A config-like document of about 100,000 values
+fn large_config() -> Value {
+    let mut map = Map::new();
+    for i in 0..1000 {
+        map.insert(
+            format!("service{}", i),
+            tjson!({
+                "name": format!("service number {}", i),
+                "enabled": i % 2 == 0,
+                "ports": (0..100).collect::<Vec<_>>(),
+            }),
+        );
+    }
+    Value::Object(map)
+}
+
+#[test]
+fn test_cow_edit_copies_little() {
+    let config = large_config();
+    assert!(config.stats().nodes > 100_000);
+
+    let (edited, edit_bytes) = alloc::retained(|| {
+        let mut edited = config.cow_edit();
+        assert!(edited.set("/service500/enabled", tjson!(false)));
+        assert!(edited.set("/service7/ports/3", tjson!(8443)));
+        edited
+    });
+    let (mut eager, clone_bytes) = alloc::retained(|| config.clone());
+
+    // Only the top level and the two services along the edited paths are
+    // opened up, and nothing inside them is cloned
+    assert!(
+        edit_bytes * 50 < clone_bytes,
+        "edits took {} bytes, a deep clone {} bytes",
+        edit_bytes,
+        clone_bytes
+    );
+
+    *eager.pointer_mut("/service500/enabled").unwrap() = tjson!(false);
+    *eager.pointer_mut("/service7/ports/3").unwrap() = tjson!(8443);
+
+    // Serializing doesn't need to freeze first
+    assert_eq!(tjson::to_string(&edited).unwrap(), tjson::to_string(&eager).unwrap());
+    assert_eq!(
+        tjson::to_string_pretty(&edited).unwrap(),
+        tjson::to_string_pretty(&eager).unwrap()
+    );
+
+    assert!(edited == eager);
+    assert_eq!(edited.freeze(), eager);
+}
+
+#[test]
+fn test_cow_edit_reads() {
+    let base = tjson!({
+        "a": { "b": [1, 2, 3], "c": "x" },
+        "d/e": { "~": true },
+        "f": 0,
+    });
+    let mut edited = base.cow_edit();
+
+    assert!(edited.set("/a/b/1", tjson!(20)));
+    assert!(edited.set("/a/b/-", tjson!(4)));
+    assert!(edited.set("/a/b/4", tjson!(5)));
+    assert!(edited.set("/a/new", tjson!({ "n": [] })));
+    assert!(edited.set("/a/new/n/0", tjson!("inside a set value")));
+    assert!(edited.remove("/a/c"));
+    assert!(edited.set("/d~1e/~0", tjson!(false)));
+    assert!(edited.remove("/f"));
+
+    assert_eq!(edited.pointer("/a/b").unwrap().into_owned(), tjson!([1, 20, 3, 4, 5]));
+    assert_eq!(edited.pointer("/a/b/1").unwrap().into_owned(), tjson!(20));
+    assert_eq!(edited.pointer("/a/new/n/0").unwrap().into_owned(), tjson!("inside a set value"));
+    assert_eq!(edited.pointer("/d~1e/~0").unwrap().into_owned(), tjson!(false));
+    assert_eq!(edited.pointer("/a/c"), None);
+    assert_eq!(edited.pointer("/f"), None);
+    assert_eq!(edited.pointer("/a/b/5"), None);
+    assert_eq!(edited.pointer("a"), None);
+    assert_eq!(edited.get("f"), None);
+    assert_eq!(edited.get("d/e").unwrap().into_owned(), tjson!({ "~": false }));
+
+    // Untouched values and values which were set are borrowed, edited
+    // containers are built
+    match edited.pointer("/a/b/0") {
+        Some(Cow::Borrowed(value)) => assert!(value as *const Value == &base["a"]["b"][0]),
+        other => panic!("{:?}", other),
+    }
+    match edited.pointer("/a/b/1") {
+        Some(Cow::Borrowed(_)) => {}
+        other => panic!("{:?}", other),
+    }
+    match edited.pointer("/a/new") {
+        Some(Cow::Owned(_)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    // Edits which don't apply change nothing
+    let before = edited.to_value();
+    assert!(!edited.set("/missing/x", tjson!(1)));
+    assert!(!edited.set("/a/b/9", tjson!(1)));
+    assert!(!edited.set("/a/b/x", tjson!(1)));
+    assert!(!edited.set("/a/b/0/x", tjson!(1)));
+    assert!(!edited.set("a", tjson!(1)));
+    assert!(!edited.remove("/a/b/5"));
+    assert!(!edited.remove("/missing"));
+    assert!(!edited.remove(""));
+    assert!(edited == before);
+
+    let expected = tjson!({
+        "a": { "b": [1, 20, 3, 4, 5], "new": { "n": ["inside a set value"] } },
+        "d/e": { "~": false },
+    });
+    assert!(edited == expected);
+    assert_eq!(edited.to_value(), expected);
+    assert_eq!(tjson::to_string(&edited).unwrap(), tjson::to_string(&expected).unwrap());
+
+    // The base is untouched
+    assert_eq!(base["a"]["b"], tjson!([1, 2, 3]));
+    assert_eq!(base["f"], tjson!(0));
+
+    // Replacing the whole value
+    let mut edited = base.cow_edit();
+    assert!(edited.set("", tjson!([0])));
+    assert!(edited.set("/0", tjson!(1)));
+    assert_eq!(edited.freeze(), tjson!([1]));
+}
+
+#[test]
+fn test_cow_edit_member_order() {
+    let base: Value = tjson::from_str(r#"{"z": 1, "a": 2}"#).unwrap();
+    let mut edited = base.cow_edit();
+    assert!(edited.set("/m", tjson!(3)));
+    assert!(edited.set("/z", tjson!(4)));
+
+    // New members go where the map would put them and existing ones are
+    // replaced in place
+    let mut eager = base.clone();
+    eager.as_object_mut().unwrap().insert("m".to_owned(), tjson!(3));
+    *eager.pointer_mut("/z").unwrap() = tjson!(4);
+
+    let text = tjson::to_string(&edited).unwrap();
+    assert_eq!(text, tjson::to_string(&eager).unwrap());
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(text, r#"{"z:i":"4","a:u":"2","m:i":"3"}"#);
+    } else {
+        assert_eq!(text, r#"{"a:u":"2","m:i":"3","z:i":"4"}"#);
+    }
+    assert_eq!(edited.freeze(), eager);
+}
@@ -0,0 +1,92 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::Value;
+use tjson::cst;
+
+const CORPUS: &[&str] = &[
+    "0",
+    "  \"x\"  ",
+    "\n\r\t[\n\r\t]\n\r\t",
+    "{}",
+    "{ \t }",
+    "[1,2 , 3 ,4]",
+    "{\"a\" :1 ,\"b\": [ true,false , null ] ,\n\n\"c\":{ \"d\" : -1.5e+10 }}",
+    "[[[[ ]]], [[{}]], {\"\":[]}]",
+    "{\n    \"escaped \\\"name\\\"\": \"\\u0041\\n\\/\",\n    \"unicode\": \"\u{e9}\\u00e9\"\n}\n",
+    "[\"a\\\\\", \"b\\\\\\\"\", 1e3, 0.0, -0]",
+];
+
+#[test]
+fn test_cst_identity() {
+    for input in CORPUS {
+        let document = cst::parse(input).unwrap();
+        assert_eq!(document.to_string(), *input);
+        assert_eq!(document.value().unwrap(), tjson::from_str::<Value>(input).unwrap());
+    }
+}
+
+#[test]
+fn test_cst_edit_leaves_other_bytes() {
+    let input = "{\n  \"name\" : \"tjson\",\n  \"deps\":[ \"serde\" ,\"chrono\" ],\n  \"x\": \"\\u0041\"\n}\n";
+    let mut document = cst::parse(input).unwrap();
+
+    document.pointer_mut("/deps/1").unwrap().replace("itoa").unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\n  \"name\" : \"tjson\",\n  \"deps\":[ \"serde\" ,\"itoa\" ],\n  \"x\": \"\\u0041\"\n}\n"
+    );
+
+    document.pointer_mut("/name").unwrap().replace(&tjson!({ "a": [1, 2] })).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "{\n  \"name\" : {\"a:A<i>\":[\"1\",\"2\"]},\n  \"deps\":[ \"serde\" ,\"itoa\" ],\n  \
+         \"x\": \"\\u0041\"\n}\n"
+    );
+
+    // The replacement is a real subtree which can itself be navigated
+    assert_eq!(document.pointer("/name/a:A<i>/1").unwrap().to_string(), "\"2\"");
+    // The node doesn't know its tag, so the integer is given quoted
+    document.pointer_mut("/name/a:A<i>/1").unwrap().replace(&"3").unwrap();
+    assert_eq!(
+        document.value().unwrap(),
+        tjson!({ "name": { "a:A<i>": [1, 3] }, "deps": ["serde", "itoa"], "x": "A" })
+    );
+}
+
+#[test]
+fn test_cst_pointer() {
+    let document = cst::parse(r#"{"a/b": {"~": [10, 20]}, "one": 1, "two": 2}"#).unwrap();
+    let root = document.root();
+
+    assert!(root.is_object());
+    assert_eq!(root.len(), 3);
+    assert_eq!(root.member_names(), ["a/b", "one", "two"]);
+
+    assert_eq!(document.pointer("/a~1b/~0/1").unwrap().to_string(), "20");
+    assert!(document.pointer("/a~1b/~0").unwrap().is_array());
+    assert_eq!(document.pointer("/two").unwrap().to_string(), "2");
+    assert_eq!(document.pointer("").unwrap().to_string(), document.to_string());
+
+    assert!(document.pointer("/a~1b/~0/2").is_none());
+    assert!(document.pointer("/a~1b/~0/01").is_none());
+    assert!(document.pointer("/two/0").is_none());
+    assert!(document.pointer("two").is_none());
+}
+
+#[test]
+fn test_cst_errors_match_parser() {
+    for input in &["", "[1,]", "{\"a\" 1}", "[\"unterminated", "1 2", "{\"a\": tru}", "{\"dup\": 1, \"dup:i\": 2}"] {
+        let cst_err = cst::parse(input).unwrap_err();
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(cst_err.to_string(), err.to_string());
+    }
+}
@@ -0,0 +1,384 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+use tjson::{Data, Deserializer, Error, Set, Value};
+use tjson::encoding::{b64url_decode, b64url_encode};
+use tjson::error::ErrorCode;
+use tjson::ser::{SerializeConfig, TagInference};
+
+fn encode() -> SerializeConfig<'static> {
+    SerializeConfig::new().encode_data(true)
+}
+
+fn undecoded(input: &str) -> Result<Value, Error> {
+    let mut de = Deserializer::from_str(input).decode_data(false);
+    Value::deserialize(&mut de)
+}
+
+fn lazy(input: &str) -> Result<Value, Error> {
+    let mut de = Deserializer::from_str(input).lazy_data(true);
+    Value::deserialize(&mut de)
+}
+
+fn data(value: &Value) -> &Data {
+    match *value {
+        Value::Data(ref data) => data,
+        ref other => panic!("expected data, got {:?}", other),
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Blob {
+    #[serde(rename = "raw:d")]
+    raw: ByteBuf,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Attachment {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "body:d", with = "serde_bytes")]
+    body: Vec<u8>,
+    #[serde(rename = "hash:d16", with = "serde_bytes")]
+    hash: Vec<u8>,
+    #[serde(rename = "parts:A<d32>")]
+    parts: Vec<ByteBuf>,
+    #[serde(with = "serde_bytes")]
+    raw: Vec<u8>,
+}
+
+#[test]
+fn test_byte_fields() {
+    let attachment = Attachment {
+        name: "a.bin".to_owned(),
+        body: b"BINARY".to_vec(),
+        hash: vec![0xfb, 0xff],
+        parts: vec![ByteBuf::from(vec![]), ByteBuf::from(b"f".to_vec())],
+        raw: vec![1, 2],
+    };
+
+    let output = tjson::to_string(&attachment).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"{"name:s":"a.bin","body:d":"QklOQVJZ","hash:d16":"fbff","#,
+            r#""parts:A<d32>":["","my"],"raw:d":"AQI"}"#
+        )
+    );
+    assert_eq!(tjson::from_str::<Attachment>(&output).unwrap(), attachment);
+
+    let pretty = tjson::to_string_pretty(&attachment).unwrap();
+    assert_eq!(tjson::from_str::<Attachment>(&pretty).unwrap(), attachment);
+
+    let value = tjson::to_value(&attachment).unwrap();
+    assert_eq!(value["body:d"], Value::Data(b"BINARY".to_vec().into()));
+    assert_eq!(value["parts:A<d32>"][0], Value::Data(Data::new()));
+    let output = tjson::to_string(&value).unwrap();
+    assert!(output.contains(r#""body:d":"QklOQVJZ""#), "{}", output);
+    assert_eq!(tjson::from_str::<Attachment>(&output).unwrap(), attachment);
+    assert_eq!(tjson::from_value::<Attachment>(value).unwrap(), attachment);
+}
+
+#[test]
+fn test_empty_byte_fields() {
+    let attachment = Attachment {
+        name: String::new(),
+        body: vec![],
+        hash: vec![],
+        parts: vec![],
+        raw: vec![],
+    };
+    let output = tjson::to_string(&attachment).unwrap();
+    assert_eq!(
+        output,
+        r#"{"name:s":"","body:d":"","hash:d16":"","parts:A<d32>":[],"raw:d":""}"#
+    );
+    assert_eq!(tjson::from_str::<Attachment>(&output).unwrap(), attachment);
+}
+
+#[test]
+fn test_untagged_byte_field() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Packet {
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    }
+
+    let packet = Packet { data: vec![1, 2, 3] };
+    let output = tjson::to_string(&packet).unwrap();
+    assert_eq!(output, r#"{"data:d":"AQID"}"#);
+    assert_eq!(tjson::from_str::<Packet>(&output).unwrap(), packet);
+
+    // Without inference the name is left alone, but the bytes are still a
+    // string
+    let config = SerializeConfig::new().infer_tags(TagInference::Off);
+    let output = tjson::to_string_with(&config, &packet).unwrap();
+    assert_eq!(output, r#"{"data":"AQID"}"#);
+}
+
+#[test]
+fn test_round_trip() {
+    // Lengths which leave every number of bytes in the last group
+    for len in 0..7 {
+        let data: Vec<u8> = (0..len).map(|i| 0xf0 | i as u8).collect();
+        let mut value = tjson!({});
+        let raw = Value::Data(data.clone().into());
+        value.as_object_mut().unwrap().insert("raw:d".to_owned(), raw);
+
+        let output = tjson::to_string_with(&encode(), &value).unwrap();
+        assert_eq!(output, format!(r#"{{"raw:d":"{}"}}"#, b64url_encode(&data)));
+        assert!(!output.contains('='), "{}", output);
+
+        assert_eq!(tjson::from_str::<Value>(&output).unwrap(), value);
+        let blob: Blob = tjson::from_str(&output).unwrap();
+        assert_eq!(Vec::from(blob.raw), data);
+    }
+
+    let output = tjson::to_string_with(&encode(), &tjson!({ "raw:d": [] })).unwrap();
+    assert_eq!(output, r#"{"raw:d":""}"#);
+}
+
+#[test]
+fn test_other_encodings() {
+    let input = r#"{"a:d16": "fbff", "b:d32": "7p7q", "c:d64": "-_8", "d:A<d32>": ["", "my"]}"#;
+    let value: Value = tjson::from_str(input).unwrap();
+    assert_eq!(value["a:d16"], Value::Data(vec![0xfb, 0xff].into()));
+    assert_eq!(value["b:d32"], Value::Data(vec![0xfb, 0xff].into()));
+    assert_eq!(value["c:d64"], Value::Data(vec![0xfb, 0xff].into()));
+    assert_eq!(
+        value["d:A<d32>"],
+        tjson!([Value::Data(Data::new()), Value::Data(b"f".to_vec().into())])
+    );
+
+    // Each is written in the encoding of its own tag
+    let output = tjson::to_string_with(&encode(), &value).unwrap();
+    assert_eq!(output, r#"{"a:d16":"fbff","b:d32":"7p7q","c:d64":"-_8","d:A<d32>":["","my"]}"#);
+
+    // With the option off data is read as the text of its encoding
+    let value = undecoded(input).unwrap();
+    assert_eq!(value["b:d32"], "7p7q");
+}
+
+#[test]
+fn test_undecoded_round_trip() {
+    let data: Vec<u8> = (0..100_000).map(|i| i as u8).collect();
+    let input = format!(r#"{{"name:s":"a.bin","raw:d":"{}"}}"#, b64url_encode(&data));
+
+    // Written back out as it was read, without decoding it
+    let value = undecoded(&input).unwrap();
+    assert_eq!(tjson::to_string(&value).unwrap(), input);
+
+    // Decoded when needed, it is the data read with the option on
+    let eager: Value = tjson::from_str(&input).unwrap();
+    let decoded = b64url_decode(value["raw:d"].as_str().unwrap()).unwrap();
+    assert_eq!(eager["raw:d"], Value::Data(decoded.into()));
+}
+
+#[test]
+fn test_lazy_equals_eager() {
+    // Lengths which leave every number of bytes in the last group
+    for len in 0..7 {
+        let bytes: Vec<u8> = (0..len).map(|i| 0xf0 | i as u8).collect();
+        let input = format!(
+            r#"{{"a:d":"{0}","b:d64":"{0}","c:A<d>":["{0}"],"d:S<d>":["{0}"]}}"#,
+            b64url_encode(&bytes)
+        );
+        let eager: Value = tjson::from_str(&input).unwrap();
+
+        let value = lazy(&input).unwrap();
+        assert!(!data(&value["a:d"]).is_decoded());
+        assert_eq!(data(&value["a:d"]).len(), len);
+        assert_eq!(hash(&value), hash(&eager));
+        assert_eq!(value.cmp(&eager), Ordering::Equal);
+        assert_eq!(value, eager);
+        assert!(data(&value["a:d"]).is_decoded());
+
+        // Either way round, and against bytes held decoded
+        let value = lazy(&input).unwrap();
+        assert_eq!(eager, value);
+        assert_eq!(value["b:d64"], Value::Data(bytes.clone().into()));
+        assert_eq!(*data(&value["c:A<d>"][0]), bytes);
+
+        let mut set = Set::new();
+        assert!(set.insert(value["a:d"].clone()));
+        assert!(!set.insert(eager["a:d"].clone()));
+    }
+
+    // Different bytes differ however they are held
+    let value = lazy(r#"{"a:d":"AQI","b:d":"AQM"}"#).unwrap();
+    assert_ne!(value["a:d"], value["b:d"]);
+    assert_ne!(value["a:d"], Value::Data(vec![1, 3].into()));
+    assert!(value["a:d"] < value["b:d"]);
+    assert_ne!(hash(&value["a:d"]), hash(&value["b:d"]));
+}
+
+#[test]
+fn test_lazy_round_trip() {
+    let bytes: Vec<u8> = (0..100_000).map(|i| i as u8).collect();
+    let input = format!(r#"{{"name:s":"a.bin","raw:d":"{}"}}"#, b64url_encode(&bytes));
+
+    // Written back out as it was read, without decoding it
+    let value = lazy(&input).unwrap();
+    assert_eq!(tjson::to_string(&value).unwrap(), input);
+    assert!(!data(&value["raw:d"]).is_decoded());
+
+    // Or after looking at it
+    assert_eq!(value["raw:d"].as_data(), Some(&bytes[..]));
+    assert_eq!(tjson::to_string(&value).unwrap(), input);
+
+    // Copies stay undecoded
+    let copy = tjson::to_value(&value).unwrap();
+    assert!(!data(&copy["raw:d"]).is_decoded());
+    assert_eq!(copy, value);
+
+    // Once changed it is encoded again
+    let mut value = value;
+    value["raw:d"].as_data_mut().unwrap().truncate(2);
+    assert_eq!(tjson::to_string(&value).unwrap(), r#"{"name:s":"a.bin","raw:d":"AAE"}"#);
+    assert_eq!(value["raw:d"].clone().into_data(), Ok(vec![0, 1]));
+}
+
+#[test]
+fn test_lazy_other_encodings() {
+    let value = lazy(r#"{"a:d16":"fbff","b:d32":"7p7q","c:d64":"-_8"}"#).unwrap();
+    assert!(data(&value["a:d16"]).is_decoded());
+    assert!(data(&value["b:d32"]).is_decoded());
+    assert!(!data(&value["c:d64"]).is_decoded());
+
+    // Decoded to be written in another encoding, and tagged as decoded data
+    // would be where the name is untagged
+    let mut moved = tjson!({});
+    moved.as_object_mut().unwrap().insert("c:d16".to_owned(), value["c:d64"].clone());
+    moved.as_object_mut().unwrap().insert("d".to_owned(), value["c:d64"].clone());
+    assert_eq!(tjson::to_string(&moved).unwrap(), r#"{"c:d16":"fbff","d:d":"-_8"}"#);
+
+    // Off by default
+    let eager: Value = tjson::from_str(r#"{"c:d64":"-_8"}"#).unwrap();
+    assert!(data(&eager["c:d64"]).is_decoded());
+}
+
+#[test]
+fn test_invalid_data() {
+    let cases = &[
+        (r#"{"raw:d": "QklOQVJZ=="}"#, "invalid padding at offset 8"),
+        (r#"{"raw:d": "QklOQVI="}"#, "invalid padding at offset 7"),
+        (r#"{"raw:d": "QklO QVJZ"}"#, "invalid byte at offset 4"),
+        (r#"{"raw:d": "QklOQVJZ\n"}"#, "invalid byte at offset 8"),
+        (r#"{"raw:d": "a+b/"}"#, "invalid byte at offset 1"),
+        (r#"{"raw:d": "QklOQVJ"}"#, "nonzero trailing bits at offset 6"),
+        (r#"{"raw:d": "Q"}"#, "incomplete group at offset 0"),
+        (r#"{"raw:d16": "FBFF"}"#, "invalid byte at offset 0"),
+        (r#"{"raw:d32": "7P7Q"}"#, "invalid byte at offset 1"),
+        (r#"{"raw:d32": "7p7q===="}"#, "invalid padding at offset 4"),
+        (r#"{"raw:d32": "7p7"}"#, "incomplete group at offset 0"),
+    ];
+    for &(input, message) in cases {
+        // At the end of the string
+        let column = input.len() - 1;
+        let expected = format!("{} at line 1 column {}", message, column);
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{}", input);
+        // Left undecoded, it still isn't what its tag says
+        let err = undecoded(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", input);
+        // Checked without being decoded, it fails as decoding it does
+        let err = lazy(input).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{}", input);
+    }
+
+    // Decoded the same way into bytes with or without the option
+    let mut de = Deserializer::from_str(r#"{"raw:d": "QklOQVJZ=="}"#).decode_data(false);
+    let err = Blob::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.to_string(), "invalid padding at offset 8 at line 1 column 22");
+}
+
+#[test]
+fn test_serialize_errors() {
+    let err = tjson::to_string_with(&encode(), &tjson!({ "a:O": { "b:d": [1, 256] } }))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "data must be bytes at /a:O/b:d");
+
+    let err = tjson::to_string_with(&encode(), &tjson!({ "a:d": "QklOQVJZ==" })).unwrap_err();
+    assert_eq!(err.to_string(), "invalid padding at offset 8 at /a:d");
+
+    // Strings which are already encoded are kept
+    let output = tjson::to_string_with(&encode(), &tjson!({ "a:d": "QklOQVJZ" })).unwrap();
+    assert_eq!(output, r#"{"a:d":"QklOQVJZ"}"#);
+}
+
+#[test]
+fn test_integer_elements() {
+    // Bytes of either signedness
+    let value = tjson!({ "a:d16": [251, 255], "b:d16": [251u64, 255u64] });
+    let output = tjson::to_string_with(&encode(), &value).unwrap();
+    assert_eq!(output, r#"{"a:d16":"fbff","b:d16":"fbff"}"#);
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"a:d16":[251,255],"b:d16":[251,255]}"#);
+
+    // Integers which aren't bytes are rejected without the option as well
+    let err = tjson::to_string(&tjson!({ "a:d": [1u64, 256u64] })).unwrap_err();
+    assert_eq!(err.to_string(), "member \"a:d\" is tagged `d` but its value is `A<u>`");
+    let err = tjson::to_string(&tjson!({ "a:d": [1, 256] })).unwrap_err();
+    assert_eq!(err.to_string(), "member \"a:d\" is tagged `d` but its value is `A<i>`");
+    let err = tjson::to_string(&tjson!({ "a:d": [-1] })).unwrap_err();
+    assert_eq!(err.to_string(), "member \"a:d\" is tagged `d` but its value is `A<i>`");
+}
+
+/// Run with `cargo test --release --test data -- --ignored`.
+///
+/// Reads and writes back a document holding 8 MB of data which nothing
+/// looks at, decoding it as usual and then with `lazy_data`, which only
+/// checks the text on the way in and copies it on the way out. The test
+/// requires that to take under half the time of decoding and encoding it.
+#[test]
+#[ignore]
+fn bench_lazy_data() {
+    let bytes: Vec<u8> = (0..8 << 20).map(|i| (i * 7) as u8).collect();
+    let input = format!(r#"{{"name:s":"a.bin","raw:d":"{}"}}"#, b64url_encode(&bytes));
+    let iterations = 10;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let value: Value = tjson::from_str(&input).unwrap();
+        assert_eq!(tjson::to_string(&value).unwrap().len(), input.len());
+    }
+    let eager = start.elapsed() / iterations;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let value = lazy(&input).unwrap();
+        assert_eq!(tjson::to_string(&value).unwrap().len(), input.len());
+    }
+    let deferred = start.elapsed() / iterations;
+
+    assert!(
+        deferred * 2 < eager,
+        "eager {:?} per document, lazy {:?}",
+        eager,
+        deferred
+    );
+}
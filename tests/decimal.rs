@@ -0,0 +1,159 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[cfg(feature = "decimal")]
+extern crate rust_decimal;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+#[cfg(feature = "decimal")]
+mod decimal {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+
+    use tjson::{self, Number, Value};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Payment {
+        #[serde(with = "tjson::ext::decimal")]
+        amount: Decimal,
+    }
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_sum_is_exact() {
+        let a: Payment = tjson::from_str(r#"{"amount": 0.1}"#).unwrap();
+        let b: Payment = tjson::from_str(r#"{"amount": 0.2}"#).unwrap();
+        let total = Payment { amount: a.amount + b.amount };
+
+        assert_eq!(total.amount, decimal("0.3"));
+        assert_eq!(tjson::to_string(&total).unwrap(), r#"{"amount:f":0.3}"#);
+
+        let reparsed: Payment = tjson::from_str(&tjson::to_string(&total).unwrap()).unwrap();
+        assert_eq!(reparsed, total);
+    }
+
+    #[test]
+    fn test_decimal_28_digits_round_trip() {
+        for text in &[
+            "1234567890.123456789012345678",
+            "-0.0000000000000000000000000001",
+            "7922816251426433759354395033.5",
+        ] {
+            let json = format!(r#"{{"amount:f":{}}}"#, text);
+            let payment: Payment = tjson::from_str(&json).unwrap();
+
+            assert_eq!(payment.amount, decimal(text));
+            assert_eq!(tjson::to_string(&payment).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_decimal_integers_and_exponents() {
+        let payment: Payment = tjson::from_str(r#"{"amount": 42}"#).unwrap();
+        assert_eq!(payment.amount, decimal("42"));
+        assert_eq!(tjson::to_string(&payment).unwrap(), r#"{"amount:f":42.0}"#);
+
+        let payment: Payment = tjson::from_str(r#"{"amount": 1.5e-3}"#).unwrap();
+        assert_eq!(payment.amount, decimal("0.0015"));
+    }
+
+    #[test]
+    fn test_decimal_out_of_range() {
+        let too_big = r#"{"amount": 79228162514264337593543950336}"#;
+        let err = tjson::from_str::<Payment>(too_big).unwrap_err();
+        assert_eq!(err.to_string(), "number out of range at line 1 column 40");
+
+        let too_precise = r#"{"amount": 0.12345678901234567890123456789}"#;
+        let err = tjson::from_str::<Payment>(too_precise).unwrap_err();
+        assert_eq!(err.to_string(), "number out of range at line 1 column 42");
+    }
+
+    #[test]
+    fn test_decimal_invalid_syntax() {
+        let err = tjson::from_str::<Payment>(r#"{"amount": 01}"#).unwrap_err();
+        assert_eq!(err.to_string(), "invalid number at line 1 column 13");
+
+        let err = tjson::from_str::<Payment>(r#"{"amount": 1.}"#).unwrap_err();
+        assert_eq!(err.to_string(), "invalid number at line 1 column 14");
+
+        let err = tjson::from_str::<Payment>(r#"{"amount": true}"#).unwrap_err();
+        assert!(err.to_string().starts_with("invalid type: boolean"));
+    }
+
+    #[test]
+    fn test_decimal_number() {
+        let n = Number::from_decimal(decimal("0.30"));
+
+        assert!(n.is_f64());
+        assert_eq!(n.as_f64(), Some(0.3));
+        assert_eq!(n.as_decimal(), Some(decimal("0.3")));
+        assert_eq!(n.to_string(), "0.30");
+        assert_eq!(tjson::to_string(&n).unwrap(), "0.30");
+
+        let n: Number = tjson::from_str("0.1").unwrap();
+        assert_eq!(n.as_decimal(), Some(decimal("0.1")));
+    }
+
+    #[test]
+    fn test_decimal_through_value() {
+        let payment = Payment { amount: decimal("1234567890.123456789012345678") };
+        let value = tjson::to_value(&payment).unwrap();
+
+        assert_eq!(
+            value["amount"],
+            Value::Number(Number::from_decimal(payment.amount))
+        );
+        assert_eq!(tjson::to_string(&value).unwrap(), tjson::to_string(&payment).unwrap());
+        assert_eq!(tjson::from_value::<Payment>(value).unwrap(), payment);
+
+        // Floating point values convert from their shortest representation
+        let value: Value = tjson::from_str(r#"{"amount": 0.1}"#).unwrap();
+        let payment: Payment = tjson::from_value(value).unwrap();
+        assert_eq!(payment.amount, decimal("0.1"));
+    }
+
+    #[test]
+    fn test_decimal_to_integer_lossless() {
+        let lossless = |text: &str| Number::from_decimal(decimal(text)).to_integer_lossless();
+
+        assert_eq!(lossless("12.000"), Some(Number::from(12)));
+        assert_eq!(lossless("-12.0"), Some(Number::from(-12)));
+        assert_eq!(lossless("-0.0"), Some(Number::from(0)));
+        assert_eq!(lossless("12.5"), None);
+        assert_eq!(lossless("79228162514264337593543950335"), None);
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+mod float_fallback {
+    use tjson;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Payment {
+        amount: f64,
+    }
+
+    /// Without the `decimal` feature the same documents can still be read,
+    /// but numbers are rounded to the nearest `f64`.
+    #[test]
+    fn test_decimal_text_reads_as_f64() {
+        let payment: Payment = tjson::from_str(r#"{"amount": 1234567890.123456789012345678}"#)
+            .unwrap();
+        assert_eq!(payment.amount, 1234567890.1234567);
+
+        let a: Payment = tjson::from_str(r#"{"amount": 0.1}"#).unwrap();
+        let b: Payment = tjson::from_str(r#"{"amount": 0.2}"#).unwrap();
+        assert!(a.amount + b.amount != 0.3);
+    }
+}
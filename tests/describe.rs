@@ -0,0 +1,284 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tjson::{DateTime, Set, Value};
+use tjson::describe::{Describe, as_set, describe};
+use tjson::value::ValueKind;
+
+#[derive(Serialize)]
+struct Order {
+    id: u64,
+    #[serde(rename = "customer:s")]
+    customer: String,
+    placed: DateTime,
+    note: Option<String>,
+    lines: Vec<Line>,
+    #[serde(serialize_with = "serde_bytes::serialize")]
+    signature: Vec<u8>,
+    labels: Set<Value>,
+    #[serde(serialize_with = "as_set")]
+    codes: BTreeSet<u32>,
+    totals: BTreeMap<String, f64>,
+    shipping: Shipping,
+    status: Status,
+    position: (f64, f64),
+    matrix: Vec<Vec<i32>>,
+    billing: Option<Address>,
+}
+
+#[derive(Serialize)]
+struct Line {
+    sku: String,
+    quantity: u32,
+    gift: bool,
+}
+
+#[derive(Serialize)]
+struct Address {
+    street: String,
+    zip: Option<u32>,
+}
+
+#[derive(Serialize)]
+enum Shipping {
+    Courier { address: Address, tracking: Vec<String> },
+}
+
+#[derive(Serialize)]
+enum Status {
+    Open,
+}
+
+fn sample() -> Order {
+    let mut labels = Set::new();
+    labels.insert(tjson!("fragile"));
+
+    let mut totals = BTreeMap::new();
+    totals.insert("net".to_owned(), 10.5);
+    totals.insert("gross".to_owned(), 12.6);
+
+    Order {
+        id: 1,
+        customer: "Alice".to_owned(),
+        placed: "2017-03-04T05:06:07Z".parse().unwrap(),
+        note: Some("leave at the door".to_owned()),
+        lines: vec![
+            Line {
+                sku: "A-1".to_owned(),
+                quantity: 2,
+                gift: false,
+            },
+        ],
+        signature: vec![0, 1, 2],
+        labels: labels,
+        codes: vec![7, 8].into_iter().collect(),
+        totals: totals,
+        shipping: Shipping::Courier {
+            address: Address {
+                street: "1 Main St".to_owned(),
+                zip: Some(12345),
+            },
+            tracking: vec!["Z1".to_owned()],
+        },
+        status: Status::Open,
+        position: (1.0, -2.5),
+        matrix: vec![vec![1, -1]],
+        billing: Some(Address {
+            street: "2 Side St".to_owned(),
+            zip: Some(54321),
+        }),
+    }
+}
+
+#[test]
+fn test_describe_struct() {
+    // The names of kinds aren't values of their tags, so the description
+    // isn't a document the parser accepts and is compared as a `Value`
+    let expected = tjson!({
+        "id:u": "unsigned",
+        "customer:s": "string",
+        "placed:t": "timestamp",
+        "note:s": "optional<string>",
+        "lines:A<O>": {
+            "array": {
+                "sku:s": "string",
+                "quantity:u": "unsigned",
+                "gift:b": "boolean",
+            },
+        },
+        "signature:d": "data",
+        "labels:S<s>": "set<string>",
+        "codes:S<u>": "set<unsigned>",
+        "totals:O": "map<float>",
+        "shipping:O": {
+            "Courier:O": {
+                "address:O": {
+                    "street:s": "string",
+                    "zip:u": "optional<unsigned>",
+                },
+                "tracking:A<s>": "array<string>",
+            },
+        },
+        "status:s": "string",
+        "position:A<f>": "array<float>",
+        "matrix:A<A<i>>": "array<array<integer>>",
+        "billing:O": {
+            "optional": {
+                "street:s": "string",
+                "zip:u": "optional<unsigned>",
+            },
+        },
+    });
+
+    let description = describe(&sample()).unwrap();
+    assert_eq!(description, expected);
+    // In the order of the fields, as kept with preserve_order
+    #[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+    {
+        let names = |value: &Value| -> Vec<String> {
+            value.as_object().unwrap().keys().cloned().collect()
+        };
+        assert_eq!(names(&description), names(&expected));
+    }
+    assert_eq!(sample().describe().unwrap(), description);
+}
+
+/// Checks that `value` is what `tag` and its `description` say it is.
+fn check(tag: &str, description: &Value, value: &Value) {
+    if tag.starts_with("A<") || tag.starts_with("S<") {
+        let element_tag = &tag[2..tag.len() - 1];
+        let element = match *description {
+            Value::String(ref s) => Value::String(s[s.find('<').unwrap() + 1..s.len() - 1].to_owned()),
+            Value::Object(ref map) => map.values().next().unwrap().clone(),
+            _ => panic!("bad description {}", description),
+        };
+        let elements: Vec<&Value> = match (&tag[..2], value) {
+            ("A<", &Value::Array(ref elements)) => elements.iter().collect(),
+            ("S<", &Value::Set(ref set)) => set.iter().collect(),
+            _ => panic!("{} is not {}", value, tag),
+        };
+        assert!(!elements.is_empty());
+        for value in elements {
+            check(element_tag, &element, value);
+        }
+        return;
+    }
+
+    match tag {
+        "s" => assert!(value.is_string(), "{}", value),
+        "t" => assert!(value.as_str().unwrap().parse::<DateTime>().is_ok()),
+        "i" => assert!(value.is_i64(), "{}", value),
+        "u" => assert!(value.is_u64(), "{}", value),
+        "f" => assert!(value.is_f64(), "{}", value),
+        "b" => assert!(value.is_boolean(), "{}", value),
+        "d" => assert_eq!(value.kind(), ValueKind::Data),
+        "O" => {
+            let value = value.as_object().expect("object");
+            let members = match *description {
+                Value::Object(ref members) => members,
+                // A map, whose values are all described the same way
+                Value::String(_) => return,
+                _ => panic!("bad description {}", description),
+            };
+            let members = match members.get("optional") {
+                Some(&Value::Object(ref inner)) => inner,
+                _ => members,
+            };
+            assert_eq!(members.len(), value.len());
+            for (name, description) in members {
+                let i = name.rfind(':').unwrap();
+                let member = value.get(name).or_else(|| value.get(&name[..i])).expect(name);
+                check(&name[i + 1..], description, member);
+            }
+        }
+        _ => panic!("unknown tag {}", tag),
+    }
+}
+
+#[test]
+fn test_description_matches_serialization() {
+    let order = sample();
+    let description = describe(&order).unwrap();
+    let value = tjson::to_value(&order).unwrap();
+    check("O", &description, &value);
+
+    // The attribute and the token tag the member as a set in the output,
+    // and both give sets in a Value
+    assert_eq!(value["codes"].kind(), ValueKind::Set);
+    assert_eq!(value["codes"].len(), Some(2));
+    assert_eq!(value["labels"].kind(), ValueKind::Set);
+    assert!(tjson::to_string(&order).unwrap().contains(r#""codes:S<u>":["7","8"],"#));
+}
+
+#[test]
+fn test_describe_scalars() {
+    assert_eq!(describe(&1i8).unwrap(), "integer");
+    assert_eq!(describe(&'c').unwrap(), "string");
+    assert_eq!(describe(&Some(1.5f32)).unwrap(), "optional<float>");
+    assert_eq!(describe(&vec![Some(true)]).unwrap(), "array<optional<boolean>>");
+    assert_eq!(describe(&tjson!({ "a": [1] })).unwrap(), "map<array<integer>>");
+
+    let mut map = BTreeMap::new();
+    map.insert(1, vec![Line {
+        sku: "B-2".to_owned(),
+        quantity: 1,
+        gift: true,
+    }]);
+    assert_eq!(
+        describe(&map).unwrap(),
+        tjson!({
+            "map": { "array": { "sku:s": "string", "quantity:u": "unsigned", "gift:b": "boolean" } }
+        })
+    );
+}
+
+#[test]
+fn test_describe_errors() {
+    let mut order = sample();
+    order.billing.as_mut().unwrap().zip = None;
+    let err = describe(&order).unwrap_err();
+    assert_eq!(err.to_string(), "cannot describe `None`, the sample needs `Some` value at /billing/zip");
+
+    let mut order = sample();
+    order.lines.clear();
+    let err = describe(&order).unwrap_err();
+    assert_eq!(err.to_string(), "cannot describe an empty array, the sample needs an element at /lines");
+
+    let mut order = sample();
+    order.totals.clear();
+    let err = describe(&order).unwrap_err();
+    assert_eq!(err.to_string(), "cannot describe an empty map, the sample needs an entry at /totals");
+
+    // Arrays have a single element type
+    let err = describe(&(1, "a")).unwrap_err();
+    assert_eq!(err.to_string(), "elements have different tags, `i` and `s` at /1");
+    let err = describe(&tjson!({ "a": 1u64, "b": -1 })).unwrap_err();
+    assert_eq!(err.to_string(), "values have different tags, `u` and `i` at /b");
+
+    #[derive(Serialize)]
+    struct Mistagged {
+        #[serde(rename = "count:s")]
+        count: u32,
+    }
+    let err = describe(&Mistagged { count: 1 }).unwrap_err();
+    assert_eq!(err.to_string(), "member is tagged `s` but its value is `u` at /count:s");
+
+    let mut pairs = BTreeMap::new();
+    pairs.insert((1, 2), 3);
+    assert!(describe(&pairs).is_err());
+    assert!(describe(&()).is_err());
+}
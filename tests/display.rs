@@ -0,0 +1,148 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::{Set, Value};
+use tjson::display::{self, DisplayConfig};
+
+fn every_kind() -> Value {
+    let mut set = Set::new();
+    set.insert(tjson!("b"));
+    set.insert(tjson!("a"));
+
+    let mut value = tjson!({
+        "undefined": null,
+        "bool:b": true,
+        "numbers:A<f>": [1, -2, 3.5],
+        "strings": ["plain text", "", "true", "42", "- dash", "key: value", "line\nbreak"],
+        "data:d": Value::Data(vec![0, 127, 255].into()),
+        "long data:d": Value::Data((0..40).collect()),
+        "empty": { "array": [], "object": {} },
+        "nested": [{ "a": 1, "b": [true, false] }, [[1], []]],
+        "needs quotes": { "": 1, "#hash": 2, "trailing:": 3 },
+    });
+
+    // tjson! would serialize these as an array and a string
+    value["timestamp:t"] = Value::Timestamp("2017-03-04T05:06:07Z".parse().unwrap());
+    value["set:S<s>"] = Value::Set(set);
+    value["empty"]["set"] = Value::Set(Set::new());
+    value
+}
+
+#[test]
+fn test_every_kind() {
+    let expected = r##"bool:b: true
+data:d: !data (3 bytes) 00 7f ff
+empty:
+  array: []
+  object: {}
+  set: !set []
+long data:d: !data (40 bytes) 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f …
+needs quotes:
+  "": 1
+  "#hash": 2
+  "trailing:": 3
+nested:
+  - a: 1
+    b:
+      - true
+      - false
+  - - - 1
+    - []
+numbers:A<f>:
+  - 1
+  - -2
+  - 3.5
+set:S<s>:
+  + a
+  + b
+strings:
+  - plain text
+  - ""
+  - "true"
+  - "42"
+  - "- dash"
+  - "key: value"
+  - "line\nbreak"
+timestamp:t: !timestamp 2017-03-04T05:06:07Z
+undefined: null
+"##;
+    let config = DisplayConfig::new().sorted(true);
+    assert_eq!(display::to_human_string_with(&config, &every_kind()), expected);
+
+    // Without sorting the members are in map order, which is name order
+    // unless `preserve_order` is enabled
+    if !cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(display::to_human_string(&every_kind()), expected);
+    }
+}
+
+#[test]
+fn test_sorted_is_stable() {
+    // Built in reverse name order, so preserve_order would show it that way
+    let value = tjson!({ "z": { "y": 1, "x": 2 }, "a": [{ "c": 3, "b": 4 }] });
+    let config = DisplayConfig::new().sorted(true);
+    assert_eq!(
+        display::to_human_string_with(&config, &value),
+        "a:\n  - b: 4\n    c: 3\nz:\n  x: 2\n  y: 1\n"
+    );
+
+    let unsorted = display::to_human_string(&value);
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(unsorted, "z:\n  y: 1\n  x: 2\na:\n  - c: 3\n    b: 4\n");
+    } else {
+        assert_eq!(unsorted, "a:\n  - b: 4\n    c: 3\nz:\n  x: 2\n  y: 1\n");
+    }
+}
+
+#[test]
+fn test_truncation() {
+    let value = tjson!({
+        "short": "abc",
+        "long": "abcdefghij",
+        "unicode": "\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}",
+        "quoted": "true story",
+        "deep": { "array": [1, 2], "object": { "a": 1 }, "empty": [] },
+    });
+
+    let config = DisplayConfig::new().sorted(true).max_string_len(4).max_depth(1);
+    assert_eq!(
+        display::to_human_string_with(&config, &value),
+        r#"deep: {… 3 members}
+long: abcd… (10 chars)
+quoted: "true"… (10 chars)
+short: abc
+unicode: éééé… (6 chars)
+"#
+    );
+
+    let config = DisplayConfig::new().sorted(true).max_depth(2);
+    assert_eq!(
+        display::to_human_string_with(&config, &value["deep"]),
+        "array:\n  - 1\n  - 2\nempty: []\nobject:\n  a: 1\n"
+    );
+    assert_eq!(
+        display::to_human_string_with(&config, &tjson!({ "a": { "b": [1], "c": { "d": 1 } } })),
+        "a:\n  b: [… 1 element]\n  c: {… 1 member}\n"
+    );
+
+    // At depth 0 the top level is summarized too
+    let config = DisplayConfig::new().max_depth(0);
+    assert_eq!(display::to_human_string_with(&config, &tjson!([1, 2, 3])), "[… 3 elements]\n");
+    assert_eq!(display::to_human_string_with(&config, &tjson!("s")), "s\n");
+}
+
+#[test]
+fn test_scalars() {
+    assert_eq!(display::to_human_string(&tjson!(null)), "null\n");
+    assert_eq!(display::to_human_string(&tjson!("null")), "\"null\"\n");
+    assert_eq!(display::to_human_string(&tjson!([])), "[]\n");
+    assert_eq!(display::to_human_string(&tjson!(["a", ["b"]])), "- a\n- - b\n");
+}
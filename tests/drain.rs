@@ -0,0 +1,146 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use tjson::{Data, Map, Set, Value};
+
+fn mixed() -> Map<String, Value> {
+    let mut map = Map::new();
+    map.insert("z".to_owned(), Value::Data(vec![1, 2].into()));
+    map.insert("name".to_owned(), tjson!("x"));
+    map.insert("blob".to_owned(), Value::Data(Data::new()));
+    map.insert("n".to_owned(), tjson!(3));
+    map.insert("a".to_owned(), Value::Data(vec![255].into()));
+    map
+}
+
+fn is_data(value: &Value) -> bool {
+    match *value {
+        Value::Data(_) => true,
+        _ => false,
+    }
+}
+
+fn names(map: &Map<String, Value>) -> Vec<&str> {
+    map.keys().map(String::as_str).collect()
+}
+
+#[test]
+fn test_drain_data_members() {
+    let mut map = mixed();
+    let drained = map.drain_filter(|_, value| is_data(value));
+
+    let drained_names: Vec<&str> = drained.iter().map(|(name, _)| name.as_str()).collect();
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(drained_names, ["z", "blob", "a"]);
+        assert_eq!(names(&map), ["name", "n"]);
+    } else {
+        assert_eq!(drained_names, ["a", "blob", "z"]);
+        assert_eq!(names(&map), ["n", "name"]);
+    }
+    assert!(drained.iter().all(|(_, value)| is_data(value)));
+
+    // The predicate may edit what it keeps
+    let mut map = mixed();
+    map.drain_filter(
+        |_, value| {
+            if let Value::Number(_) = *value {
+                *value = tjson!(4);
+            }
+            false
+        },
+    );
+    assert_eq!(map["n"], tjson!(4));
+    assert_eq!(map.len(), 5);
+}
+
+#[test]
+fn test_drain_empty() {
+    let mut map = Map::new();
+    let mut calls = 0;
+    assert!(map.drain_filter(|_, _| { calls += 1; true }).is_empty());
+    assert_eq!(calls, 0);
+    assert!(map.is_empty());
+
+    let mut set = Set::new();
+    assert!(set.drain_filter(|_| true).is_empty());
+}
+
+#[test]
+fn test_drain_panic_keeps_undrained() {
+    let mut map = mixed();
+    let mut seen = 0;
+
+    let result = panic::catch_unwind(
+        AssertUnwindSafe(
+            || {
+                map.drain_filter(
+                    |_, _| {
+                        seen += 1;
+                        if seen == 3 {
+                            panic!("predicate failed");
+                        }
+                        seen == 1
+                    },
+                )
+            },
+        ),
+    );
+    assert!(result.is_err());
+
+    // Only the first entry was drained; the rest, including the one being
+    // looked at, are still there in order
+    let expected: &[&str] =
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            &["name", "blob", "n", "a"]
+        } else {
+            &["blob", "n", "name", "z"]
+        };
+    assert_eq!(names(&map), expected);
+
+    // And the map is still usable
+    map.insert("b".to_owned(), tjson!(null));
+    assert_eq!(map.len(), 5);
+}
+
+#[test]
+fn test_set_drain_filter() {
+    let mut set = Set::new();
+    for value in [tjson!(3), tjson!("b"), tjson!(1), tjson!("a"), tjson!(2)] {
+        set.insert(value);
+    }
+
+    let drained = set.drain_filter(|value| value.is_string());
+    let survivors: Vec<&Value> = set.iter().collect();
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(drained, [tjson!("b"), tjson!("a")]);
+        assert_eq!(survivors, [&tjson!(3), &tjson!(1), &tjson!(2)]);
+    } else {
+        assert_eq!(drained, [tjson!("a"), tjson!("b")]);
+        assert_eq!(survivors, [&tjson!(1), &tjson!(2), &tjson!(3)]);
+    }
+
+    // Drained elements can be inserted again
+    assert!(set.insert(tjson!("a")));
+}
+
+#[test]
+fn test_retain_members() {
+    let mut value = Value::Object(mixed());
+    value.retain_members(|name, value| name.len() > 1 || value.is_number());
+    assert_eq!(names(value.as_object().unwrap()).len(), 3);
+    assert!(value.get("n").is_some() && value.get("z").is_none());
+
+    let mut array = tjson!([1, 2]);
+    array.retain_members(|_, _| false);
+    assert_eq!(array, tjson!([1, 2]));
+}
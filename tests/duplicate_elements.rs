@@ -0,0 +1,240 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use tjson::{Deserializer, Set, Value};
+use tjson::de::{DuplicateElements, Parser};
+use tjson::error::ErrorCode;
+use tjson::ser::SerializeConfig;
+
+/// The first repeated element is at index 2
+const TAGS: &[&str] = &["a", "b", "a", "c", "b"];
+const TEXT: &str = r#"{"tags:S<s>": ["a", "b", "a", "c", "b"]}"#;
+
+#[derive(Debug, Serialize)]
+struct Outgoing {
+    #[serde(rename = "tags:S<s>", serialize_with = "tjson::describe::as_set")]
+    tags: Vec<&'static str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Incoming {
+    #[serde(rename = "tags:S<s>")]
+    tags: Set<Value>,
+}
+
+fn outgoing() -> Outgoing {
+    Outgoing { tags: TAGS.to_vec() }
+}
+
+fn merged() -> Set<Value> {
+    let mut set = Set::new();
+    for tag in &["a", "b", "c"] {
+        set.insert(tjson!(tag));
+    }
+    set
+}
+
+/// Each path's result, as the set it accepted or the error it gave
+fn paths(policy: DuplicateElements) -> Vec<(&'static str, Result<Set<Value>, tjson::Error>)> {
+    let config = SerializeConfig::new().duplicate_elements(policy);
+    let text_ser = tjson::to_string_with(&config, &outgoing())
+        .map(|text| tjson::from_str::<Value>(&text).unwrap()["tags:S<s>"].clone())
+        .map(|value| tjson::value::from_value_with(value, DuplicateElements::Error).unwrap());
+
+    let value_ser = tjson::value::to_value_with(&outgoing(), policy).map(|value| {
+        match value["tags:S<s>"] {
+            Value::Set(ref set) => set.clone(),
+            ref other => panic!("not a set: {}", other),
+        }
+    });
+
+    let mut de = Deserializer::from_str(TEXT).duplicate_elements(policy);
+    let text_de = Incoming::deserialize(&mut de).map(|incoming| incoming.tags);
+
+    let value = tjson!({ "tags:S<s>": ["a", "b", "a", "c", "b"] });
+    let value_de =
+        tjson::value::from_value_with::<Incoming>(value, policy).map(|incoming| incoming.tags);
+
+    vec![
+        ("text ser", text_ser),
+        ("value ser", value_ser),
+        ("text de", text_de),
+        ("value de", value_de),
+    ]
+}
+
+#[test]
+fn test_error_on_every_path() {
+    for (path, result) in paths(DuplicateElements::Error) {
+        let err = result.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DuplicateElement, "{}", path);
+        assert!(err.is_syntax());
+        let message = err.to_string();
+        let expected = "duplicate set element \"a\" at index 2";
+        assert!(message.starts_with(expected), "{}: {}", path, message);
+    }
+
+    // Text reports where the repeated element ends
+    let mut de = Deserializer::from_str(TEXT).duplicate_elements(DuplicateElements::Error);
+    let err = Incoming::deserialize(&mut de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element \"a\" at index 2 of /tags:S<s> at line 1 column 28"
+    );
+}
+
+#[test]
+fn test_merge_on_every_path() {
+    for (path, result) in paths(DuplicateElements::Merge) {
+        assert_eq!(result.unwrap(), merged(), "{}", path);
+    }
+
+    // Merging keeps the first occurrence in place
+    let config = SerializeConfig::new().duplicate_elements(DuplicateElements::Merge);
+    let text = tjson::to_string_with(&config, &outgoing()).unwrap();
+    assert_eq!(text, r#"{"tags:S<s>":["a","b","c"]}"#);
+}
+
+#[test]
+fn test_defaults() {
+    // Every entry point without a policy rejects duplicates
+    assert_eq!(tjson::to_string(&outgoing()).unwrap_err().code(), ErrorCode::DuplicateElement);
+    assert_eq!(tjson::to_value(&outgoing()).unwrap_err().code(), ErrorCode::DuplicateElement);
+    let err = tjson::from_str::<Incoming>(TEXT).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+    let value = tjson!({ "tags:S<s>": ["a", "b", "a", "c", "b"] });
+    let err = Incoming::deserialize(&value).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate set element \"a\" at index 2");
+    let err = tjson::from_value::<Incoming>(value).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate set element \"a\" at index 2");
+
+    // As do other crates' deserializers
+    let err = Set::<Value>::deserialize(TAGS.to_vec().into_deserializer()).unwrap_err();
+    let err: serde::de::value::Error = err;
+    assert_eq!(err.to_string(), "duplicate set element \"a\" at index 2");
+}
+
+#[test]
+fn test_distinct_elements() {
+    let text = r#"{"tags:S<s>": ["a", "b", "c"]}"#;
+    assert_eq!(tjson::from_str::<Incoming>(text).unwrap().tags, merged());
+
+    // A Set is a Value::Set from to_value, and read back from one
+    let value = tjson::to_value(&merged()).unwrap();
+    assert_eq!(value, Value::Set(merged()));
+    assert_eq!(tjson::from_value::<Set<Value>>(value.clone()).unwrap(), merged());
+    assert_eq!(Set::<Value>::deserialize(&value).unwrap(), merged());
+    assert_eq!(tjson!({ "s:S<s>": Value::Set(merged()) })["s:S<s>"], value);
+}
+
+#[test]
+fn test_parser_policy() {
+    let mut parser = Parser::new().duplicate_elements(DuplicateElements::Merge);
+    for _ in 0..2 {
+        let incoming: Incoming = parser.parse_str(TEXT).unwrap();
+        assert_eq!(incoming.tags, merged());
+    }
+
+    // Values keep the array as an array, with only its distinct elements
+    let value: Value = parser.parse_str(TEXT).unwrap();
+    assert_eq!(value, tjson!({ "tags:S<s>": ["a", "b", "c"] }));
+
+    let mut parser = Parser::new();
+    assert!(parser.parse_str::<Incoming>(TEXT).is_err());
+    assert!(parser.parse_str::<Value>(TEXT).is_err());
+}
+
+#[test]
+fn test_nested_sets() {
+    let mut inner = Set::new();
+    inner.insert(tjson!(1));
+    let nested = vec![vec![1, 1]];
+
+    #[derive(Serialize)]
+    struct Nested {
+        #[serde(serialize_with = "tjson::describe::as_set")]
+        outer: Vec<Wrapped>,
+    }
+
+    #[derive(Serialize)]
+    struct Wrapped(#[serde(serialize_with = "tjson::describe::as_set")] Vec<i32>);
+
+    let value = Nested { outer: nested.into_iter().map(Wrapped).collect() };
+    let err = tjson::to_value(&value).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate set element 1 at index 1");
+
+    let merged = tjson::value::to_value_with(&value, DuplicateElements::Merge).unwrap();
+    let mut outer = Set::new();
+    outer.insert(Value::Set(inner));
+    assert_eq!(merged, tjson!({ "outer": Value::Set(outer) }));
+}
+
+#[test]
+fn test_tagged_arrays_in_values() {
+    let cases = &[
+        (
+            r#"{"ids:S<i>": ["1", "-2", "1"]}"#,
+            "duplicate set element 1 at index 2 of /ids:S<i> at line 1 column 28",
+        ),
+        (
+            r#"{"o:O": {"names:S<s>": ["x", "y", "y"]}}"#,
+            "duplicate set element \"y\" at index 2 of /o:O/names:S<s> at line 1 column 37",
+        ),
+        (
+            r#"{"points:S<O>": [{"x:i": "1", "y:A<i>": ["2"]}, {"x:i":"1","y:A<i>":[ "2" ]}]}"#,
+            "duplicate set element {\"x:i\":\"1\",\"y:A<i>\":[\"2\"]} at index 1 of /points:S<O> \
+             at line 1 column 76",
+        ),
+        (
+            r#"{"grid:A<S<u>>": [["1"], ["2", "2"]]}"#,
+            "duplicate set element 2 at index 1 of /grid:A<S<u>>/1 at line 1 column 34",
+        ),
+    ];
+    for &(input, message) in cases {
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DuplicateElement, "{}", input);
+        assert_eq!(err.to_string(), message);
+    }
+
+    // Equal only as the same element, not by their text
+    let value: Value = tjson::from_str(r#"{"v:S<s>": ["a", "ab", "ab "]}"#).unwrap();
+    assert_eq!(value["v:S<s>"].len(), Some(3));
+    assert!(tjson::from_str::<Value>(r#"{"v:S<s>": ["ab", "ab"]}"#).is_err());
+
+    // Arrays under other tags may repeat elements
+    let value: Value = tjson::from_str(r#"{"v:A<i>": ["1", "1"], "w": [1, 1]}"#).unwrap();
+    assert_eq!(value, tjson!({ "v:A<i>": [1, 1], "w": [1, 1] }));
+}
+
+#[test]
+fn test_tagged_arrays_in_structs() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Tagged {
+        #[serde(rename = "ids:S<u>")]
+        ids: Vec<u64>,
+    }
+
+    let err = tjson::from_str::<Tagged>(r#"{"ids:S<u>": ["3", "4", "3"]}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element 3 at index 2 of /ids:S<u> at line 1 column 27"
+    );
+
+    let mut de = Deserializer::from_str(r#"{"ids:S<u>": ["3", "4", "3"]}"#)
+        .duplicate_elements(DuplicateElements::Merge);
+    assert_eq!(Tagged::deserialize(&mut de).unwrap(), Tagged { ids: vec![3, 4] });
+}
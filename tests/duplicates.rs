@@ -0,0 +1,178 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+mod common;
+
+use common::parse;
+use tjson::Value;
+use tjson::de::{DuplicateKeys, Parser};
+use tjson::error::ErrorCode;
+
+const THREE: &str = r#"{"a:u": "1", "b:s": "x", "a:u": "2", "a:u": "3"}"#;
+const NESTED: &str = r#"[{"o:O": {"k:s": "1", "k:s": "2", "k:s": "3"}}]"#;
+
+#[test]
+fn test_policies_on_values() {
+    let cases = vec![
+        (DuplicateKeys::FirstWins, tjson!({ "a:u": 1, "b:s": "x" }), tjson!([{ "o:O": { "k:s": "1" } }])),
+        (DuplicateKeys::LastWins, tjson!({ "a:u": 3, "b:s": "x" }), tjson!([{ "o:O": { "k:s": "3" } }])),
+        (
+            DuplicateKeys::Collect,
+            tjson!({ "a:u": [1, 2, 3], "b:s": "x" }),
+            tjson!([{ "o:O": { "k:s": ["1", "2", "3"] } }]),
+        ),
+    ];
+
+    for (policy, three, nested) in cases {
+        assert_eq!(parse::<Value, _>(THREE, policy).unwrap(), three, "{:?}", policy);
+        assert_eq!(parse::<Value, _>(NESTED, policy).unwrap(), nested, "{:?}", policy);
+    }
+
+    // Without a policy, duplicates are an error
+    let err = tjson::from_str::<Value>(THREE).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+}
+
+#[test]
+fn test_error_policy() {
+    let err = parse::<Value, _>(THREE, DuplicateKeys::Error).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"a:u\" at line 1 column 30");
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+    assert!(err.is_syntax());
+
+    let err = parse::<Value, _>(NESTED, DuplicateKeys::Error).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"k:s\" at line 1 column 27");
+
+    // Names are compared without their tags, and only within one object
+    let err = parse::<Value, _>(r#"{"id:s": "1", "id:u": "1"}"#, DuplicateKeys::Error).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"id:u\" at line 1 column 20");
+
+    let input = r#"{"a:O": {"a:O": {}}, "b:A<O>": [{"a:u": "1"}, {"a:u": "2"}]}"#;
+    assert_eq!(
+        parse::<Value, _>(input, DuplicateKeys::Error).unwrap(),
+        tjson::from_str::<Value>(input).unwrap()
+    );
+}
+
+#[test]
+fn test_tag_divergent_duplicates() {
+    let input = r#"{"n:u": "1", "n:i": "-1", "n:u": "2"}"#;
+
+    let err = parse::<Value, _>(input, DuplicateKeys::Collect).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot collect member \"n:i\" with \"n:u\" since their tags differ at line 1 column 24"
+    );
+    assert_eq!(err.code(), ErrorCode::CollectedTagMismatch);
+
+    assert_eq!(parse::<Value, _>(input, DuplicateKeys::FirstWins).unwrap(), tjson!({ "n:u": 1 }));
+    assert_eq!(parse::<Value, _>(input, DuplicateKeys::LastWins).unwrap(), tjson!({ "n:u": 2 }));
+
+    let input = r#"{"n:u": "1", "n:i": "-1"}"#;
+    assert_eq!(parse::<Value, _>(input, DuplicateKeys::LastWins).unwrap(), tjson!({ "n:i": -1 }));
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Reading {
+    sensor: String,
+    value: u32,
+    inner: Inner,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    unit: String,
+}
+
+#[test]
+fn test_policies_on_structs() {
+    let input = r#"{"sensor": "a", "value": 1, "inner": {"unit": "ms", "unit": "s"}, "value": 2, "value": 3}"#;
+    let reading = |value, unit: &str| {
+        Reading {
+            sensor: "a".to_owned(),
+            value: value,
+            inner: Inner { unit: unit.to_owned() },
+        }
+    };
+
+    assert_eq!(parse::<Reading, _>(input, DuplicateKeys::FirstWins).unwrap(), reading(1, "ms"));
+    assert_eq!(parse::<Reading, _>(input, DuplicateKeys::LastWins).unwrap(), reading(3, "s"));
+
+    let err = parse::<Reading, _>(input, DuplicateKeys::Error).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"unit\" at line 1 column 58");
+
+    // Collecting would change the type of a field
+    let err = parse::<Reading, _>(input, DuplicateKeys::Collect).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+    assert_eq!(err.to_string(), "duplicate member \"unit\" at line 1 column 58");
+
+    // Without a policy, derived structs reject them the same way
+    let err = tjson::from_str::<Reading>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"unit\" at line 1 column 58");
+
+    // Collect still applies to maps inside structs
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Wrapper {
+        extra: Value,
+    }
+    let input = r#"{"extra": {"x": 1, "x": 2}}"#;
+    assert_eq!(
+        parse::<Wrapper, _>(input, DuplicateKeys::Collect).unwrap(),
+        Wrapper { extra: tjson!({ "x": [1, 2] }) }
+    );
+}
+
+#[test]
+fn test_parser_policy() {
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::Collect);
+    for _ in 0..2 {
+        let value: Value = parser.parse_str(THREE).unwrap();
+        assert_eq!(value, tjson!({ "a:u": [1, 2, 3], "b:s": "x" }));
+    }
+
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::Error);
+    assert!(parser.parse_str::<Value>(THREE).is_err());
+    let value: Value = parser.parse_str(r#"{"a": {"a": 1}}"#).unwrap();
+    assert_eq!(value, tjson!({ "a": { "a": 1 } }));
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Tagged {
+    #[serde(rename = "foo:s")]
+    foo: String,
+}
+
+#[test]
+fn test_strict_by_default() {
+    // A struct sees names which differ only in their tags as the same member,
+    // though serde alone would skip the second as an unknown field
+    let input = r#"{"foo:s": "a", "foo:i": "1"}"#;
+    let err = tjson::from_str::<Tagged>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"foo:i\" at line 1 column 22");
+    let err = tjson::from_str::<Value>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"foo:i\" at line 1 column 22");
+    assert!(Parser::new().parse_str::<Value>(input).is_err());
+
+    // Large objects are checked as well as small ones, around the objects
+    // inside them
+    let members: Vec<String> =
+        (0..100).map(|i| format!(r#""m{}:O": {{"m{}:u": "1"}}"#, i, i)).collect();
+    let input = format!("{{{}}}", members.join(", "));
+    assert_eq!(tjson::from_str::<Value>(&input).unwrap().as_object().unwrap().len(), 100);
+
+    let input = format!(r#"{{{}, "m50:s": "x"}}"#, members.join(", "));
+    let err = tjson::from_str::<Value>(&input).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+    assert_eq!(err.column(), input.len() - 6);
+}
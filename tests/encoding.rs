@@ -0,0 +1,216 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate tjson;
+
+mod common;
+
+use common::Rng;
+use tjson::encoding::{DecodeError, DecodeErrorKind, Decoder, Encoder, b32_decode, b32_encode,
+                      b64url_decode, b64url_encode, b64url_pad_decode, b64url_pad_encode,
+                      hex_decode, hex_encode};
+
+// The test vectors of RFC 4648 section 10, in the base64url alphabet
+const VECTORS: &[(&str, &str, &str, &str)] = &[
+    ("", "", "", ""),
+    ("f", "Zg", "Zg==", "66"),
+    ("fo", "Zm8", "Zm8=", "666f"),
+    ("foo", "Zm9v", "Zm9v", "666f6f"),
+    ("foob", "Zm9vYg", "Zm9vYg==", "666f6f62"),
+    ("fooba", "Zm9vYmE", "Zm9vYmE=", "666f6f6261"),
+    ("foobar", "Zm9vYmFy", "Zm9vYmFy", "666f6f626172"),
+];
+
+// The same vectors in lowercase unpadded base32
+const BASE32_VECTORS: &[(&str, &str)] = &[
+    ("", ""),
+    ("f", "my"),
+    ("fo", "mzxq"),
+    ("foo", "mzxw6"),
+    ("foob", "mzxw6yq"),
+    ("fooba", "mzxw6ytb"),
+    ("foobar", "mzxw6ytboi"),
+];
+
+fn check_err(result: Result<Vec<u8>, DecodeError>, offset: usize, kind: DecodeErrorKind) {
+    let err = result.unwrap_err();
+    assert_eq!((err.offset(), err.kind()), (offset, kind), "{}", err);
+}
+
+#[test]
+fn test_rfc4648_vectors() {
+    for &(data, b64url, padded, hex) in VECTORS {
+        assert_eq!(b64url_encode(data.as_bytes()), b64url);
+        assert_eq!(b64url_pad_encode(data.as_bytes()), padded);
+        assert_eq!(hex_encode(data.as_bytes()), hex);
+
+        assert_eq!(b64url_decode(b64url).unwrap(), data.as_bytes());
+        assert_eq!(b64url_pad_decode(padded).unwrap(), data.as_bytes());
+        assert_eq!(hex_decode(hex).unwrap(), data.as_bytes());
+    }
+}
+
+#[test]
+fn test_url_alphabet() {
+    assert_eq!(b64url_encode(&[0xfb, 0xff]), "-_8");
+    assert_eq!(b64url_pad_encode(&[0xfb, 0xff, 0xbf]), "-_-_");
+    assert_eq!(b64url_decode("-_-_").unwrap(), [0xfb, 0xff, 0xbf]);
+    assert_eq!(hex_encode(&[0x00, 0xab, 0xff]), "00abff");
+
+    // Bytes as well as strings
+    assert_eq!(b64url_decode(&b"Zm8"[..]).unwrap(), b"fo");
+    assert_eq!(hex_decode(&b"666f".to_vec()).unwrap(), b"fo");
+}
+
+#[test]
+fn test_round_trip() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for len in 0..1001 {
+        let data = rng.bytes(len);
+
+        let encoded = b64url_encode(&data);
+        assert_eq!(encoded.len(), (len * 4 + 2) / 3);
+        assert_eq!(b64url_decode(&encoded).unwrap(), data);
+
+        let encoded = b64url_pad_encode(&data);
+        assert_eq!(encoded.len() % 4, 0);
+        assert!(encoded.len() - b64url_encode(&data).len() < 3);
+        assert_eq!(b64url_pad_decode(&encoded).unwrap(), data);
+
+        let encoded = hex_encode(&data);
+        assert_eq!(encoded.len(), len * 2);
+        assert_eq!(hex_decode(&encoded).unwrap(), data);
+
+        let encoded = b32_encode(&data);
+        assert_eq!(encoded.len(), (len * 8 + 4) / 5);
+        assert_eq!(b32_decode(&encoded).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_base32() {
+    for &(data, b32) in BASE32_VECTORS {
+        assert_eq!(b32_encode(data.as_bytes()), b32);
+        assert_eq!(b32_decode(b32).unwrap(), data.as_bytes());
+    }
+    assert_eq!(b32_encode(&[0xff; 5]), "77777777");
+
+    // Lowercase only, and without padding
+    check_err(b32_decode("MZXQ"), 0, DecodeErrorKind::InvalidByte);
+    check_err(b32_decode("mzx1"), 3, DecodeErrorKind::InvalidByte);
+    check_err(b32_decode("my======"), 2, DecodeErrorKind::InvalidPadding);
+
+    // Only 2, 4, 5 or 7 characters can end the data
+    check_err(b32_decode("m"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b32_decode("mzxw6ytbm"), 8, DecodeErrorKind::InvalidLength);
+    check_err(b32_decode("mzx"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b32_decode("mzxw6y"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b32_decode("mz"), 1, DecodeErrorKind::TrailingBits);
+    check_err(b32_decode("mzxw6ytboj"), 9, DecodeErrorKind::TrailingBits);
+}
+
+#[test]
+fn test_streaming_base32() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let data = rng.bytes(301);
+    for size in 1..12 {
+        let mut encoder = Encoder::b32();
+        let mut encoded = String::new();
+        for piece in data.chunks(size) {
+            encoder.encode(piece, &mut encoded);
+        }
+        encoder.finish(&mut encoded);
+        assert_eq!(encoded, b32_encode(&data));
+
+        let mut decoder = Decoder::b32();
+        let mut decoded = Vec::new();
+        for piece in encoded.as_bytes().chunks(size) {
+            decoder.decode(piece, &mut decoded).unwrap();
+        }
+        decoder.finish(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+fn test_rejects_standard_alphabet() {
+    check_err(b64url_decode("+/8"), 0, DecodeErrorKind::InvalidByte);
+    check_err(b64url_decode("-/8"), 1, DecodeErrorKind::InvalidByte);
+    check_err(b64url_pad_decode("Zm9v+_8="), 4, DecodeErrorKind::InvalidByte);
+}
+
+#[test]
+fn test_rejects_whitespace() {
+    for input in &["Zm9v Yg", "Zm9v\nYg", "Zm9vYg\t", " Zm9vYg"] {
+        assert_eq!(b64url_decode(input).unwrap_err().kind(), DecodeErrorKind::InvalidByte);
+    }
+    check_err(b64url_decode("Zm9v\r\nYg"), 4, DecodeErrorKind::InvalidByte);
+    check_err(b64url_pad_decode("Zm8= "), 4, DecodeErrorKind::InvalidByte);
+    check_err(hex_decode("66 6f"), 2, DecodeErrorKind::InvalidByte);
+    check_err(hex_decode("666f\n"), 4, DecodeErrorKind::InvalidByte);
+}
+
+#[test]
+fn test_padding() {
+    // None at all without padding
+    check_err(b64url_decode("Zg=="), 2, DecodeErrorKind::InvalidPadding);
+    check_err(b64url_decode("Zm8="), 3, DecodeErrorKind::InvalidPadding);
+
+    // Exactly enough to complete the last group with it
+    check_err(b64url_pad_decode("Zg"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b64url_pad_decode("Zg="), 0, DecodeErrorKind::InvalidLength);
+    check_err(b64url_pad_decode("Zm8"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b64url_pad_decode("Zm9vYg"), 4, DecodeErrorKind::InvalidLength);
+    check_err(b64url_pad_decode("Zm8=="), 4, DecodeErrorKind::InvalidPadding);
+    check_err(b64url_pad_decode("Zm9v===="), 4, DecodeErrorKind::InvalidPadding);
+    check_err(b64url_pad_decode("===="), 0, DecodeErrorKind::InvalidPadding);
+    check_err(b64url_pad_decode("Z==="), 0, DecodeErrorKind::InvalidLength);
+
+    // Only at the end
+    check_err(b64url_pad_decode("Zg==Zg=="), 2, DecodeErrorKind::InvalidPadding);
+    check_err(b64url_pad_decode("Zg=a"), 2, DecodeErrorKind::InvalidPadding);
+}
+
+#[test]
+fn test_trailing_bits() {
+    // `Zh` and `Zm9` carry bits past the end of the data
+    check_err(b64url_decode("Zh"), 1, DecodeErrorKind::TrailingBits);
+    check_err(b64url_decode("Zm9"), 2, DecodeErrorKind::TrailingBits);
+    check_err(b64url_pad_decode("Zh=="), 1, DecodeErrorKind::TrailingBits);
+    check_err(b64url_decode("Zm9vZm9"), 6, DecodeErrorKind::TrailingBits);
+}
+
+#[test]
+fn test_length() {
+    check_err(b64url_decode("Z"), 0, DecodeErrorKind::InvalidLength);
+    check_err(b64url_decode("Zm9vY"), 4, DecodeErrorKind::InvalidLength);
+    check_err(hex_decode("6"), 0, DecodeErrorKind::InvalidLength);
+    check_err(hex_decode("666"), 2, DecodeErrorKind::InvalidLength);
+
+    // A bad byte is reported before the length
+    check_err(hex_decode("66g"), 2, DecodeErrorKind::InvalidByte);
+}
+
+#[test]
+fn test_uppercase_hex() {
+    check_err(hex_decode("48656C6C6F"), 5, DecodeErrorKind::InvalidByte);
+    check_err(hex_decode("Ab"), 0, DecodeErrorKind::InvalidByte);
+    check_err(hex_decode("0x10"), 1, DecodeErrorKind::InvalidByte);
+    assert_eq!(hex_decode("48656c6c6f").unwrap(), b"Hello");
+}
+
+#[test]
+fn test_error() {
+    assert_eq!(hex_decode("abc").unwrap_err().to_string(), "incomplete group at offset 2");
+    assert_eq!(b64url_decode("Zm8=").unwrap_err().to_string(), "invalid padding at offset 3");
+    assert_eq!(
+        b64url_decode("Zh").unwrap_err().to_string(),
+        "nonzero trailing bits at offset 1"
+    );
+    assert_eq!(hex_decode("6G").unwrap_err().to_string(), "invalid byte at offset 1");
+}
@@ -0,0 +1,125 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use tjson::Value;
+use tjson::envelope::{Envelope, peek_kind};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+}
+
+fn user() -> User {
+    User {
+        name: "alice".to_owned(),
+        age: 30,
+    }
+}
+
+#[test]
+fn test_envelope_round_trip() {
+    let envelope = Envelope::new(2, "user", user());
+    let text = tjson::to_string(&envelope).unwrap();
+    assert_eq!(
+        text,
+        r#"{"v:u":"2","kind:s":"user","payload:O":{"name:s":"alice","age:u":"30"}}"#
+    );
+
+    let typed: Envelope<User> = tjson::from_str(&text).unwrap();
+    assert_eq!(typed, envelope);
+
+    let sealed: Envelope<Value> = tjson::from_str(&text).unwrap();
+    assert_eq!(sealed.payload, tjson!({ "name:s": "alice", "age:u": 30u32 }));
+    assert_eq!(sealed.open_expecting::<User>("user", 2).unwrap(), envelope);
+
+    // Member order and unknown members don't matter
+    let reordered = r#"{"payload:O": {"name": "alice", "age": 30}, "x": [1], "kind:s": "user", "v:u": "2"}"#;
+    assert_eq!(tjson::from_str::<Envelope<User>>(reordered).unwrap(), envelope);
+}
+
+#[test]
+fn test_envelope_open_expecting_errors() {
+    let sealed = || Envelope::new(3, "user", tjson!({ "name": "alice", "age": 30 }));
+
+    let err = sealed().open_expecting::<User>("group", 3).unwrap_err();
+    assert_eq!(err.to_string(), r#"unexpected envelope kind "user", expected "group""#);
+    assert!(err.is_data());
+
+    let err = sealed().open_expecting::<User>("user", 2).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#""user" envelope version 3 is newer than the newest supported version 2"#
+    );
+
+    let bad = Envelope::new(1, "user", tjson!({ "name": "alice" }));
+    let err = bad.open_expecting::<User>("user", 1).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid "user" envelope payload: missing field `age`"#);
+}
+
+#[test]
+fn test_envelope_malformed() {
+    let cases = &[
+        (r#"{"kind:s": "user", "payload:O": {}}"#, "missing field `v:u` at line 1 column 35"),
+        (
+            r#"{"v:u": "01", "kind:s": "user", "payload:O": {}}"#,
+            "expected a value tagged `u`, found string \"01\" at /v:u at line 1 column 12",
+        ),
+        (
+            r#"{"v:u": "-1", "kind:s": "user", "payload:O": {}}"#,
+            "number out of range at line 1 column 12",
+        ),
+        (
+            r#"{"v:u": true, "kind:s": "user", "payload:O": {}}"#,
+            "expected a value tagged `u`, found boolean at /v:u at line 1 column 9",
+        ),
+        (
+            r#"{"v:u": "1", "v:u": "2", "kind:s": "user", "payload:O": {}}"#,
+            "duplicate member \"v:u\" at line 1 column 18",
+        ),
+        (r#"["1", "user", {}]"#, "invalid type: sequence, expected an envelope object at line 1 column 1"),
+    ];
+
+    for &(text, message) in cases {
+        let err = tjson::from_str::<Envelope<Value>>(text).unwrap_err();
+        assert_eq!(err.to_string(), message, "{}", text);
+    }
+}
+
+#[test]
+fn test_peek_kind_does_not_build_payload() {
+    let items: Vec<Value> = (0..10_000)
+        .map(|i| tjson!({ "id": i, "tags": ["a", "b", format!("item {}", i)] }))
+        .collect();
+    let text = tjson::to_vec(&Envelope::new(7, "batch", tjson!({ "items": items }))).unwrap();
+
+    let mut peeked = None;
+    let peek_allocations = alloc::allocations(|| peeked = Some(peek_kind(&text).unwrap()));
+    assert_eq!(peeked, Some((7, "batch".to_owned())));
+
+    let parse_allocations = alloc::allocations(|| {
+        tjson::from_slice::<Envelope<Value>>(&text).unwrap();
+    });
+
+    // The header strings, the parser's scratch buffers and stacks and the
+    // buffer which matches member names to fields, but nothing per item
+    assert!(peek_allocations < 18, "{} allocations", peek_allocations);
+    assert!(parse_allocations > 10_000, "{} allocations", parse_allocations);
+
+    let err = peek_kind(br#"{"v:u": "1", "kind:s": "batch", "payload:O": {"n:u": "1", }"#)
+        .unwrap_err();
+    assert!(err.is_syntax());
+}
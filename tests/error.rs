@@ -0,0 +1,179 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate tjson;
+
+use std::io;
+
+use tjson::Value;
+use tjson::error::ErrorCode;
+
+fn parse_error(input: &str) -> tjson::Error {
+    tjson::from_str::<Value>(input).unwrap_err()
+}
+
+#[test]
+fn test_error_codes() {
+    let deep = "[".repeat(200);
+    let cases = vec![
+        (parse_error("[1] 2"), ErrorCode::TrailingCharacters, "trailing_characters"),
+        (parse_error("{\"a\" 1}"), ErrorCode::ExpectedColon, "expected_colon"),
+        (parse_error("[1 2]"), ErrorCode::ExpectedListCommaOrEnd, "expected_list_comma_or_end"),
+        (
+            parse_error("{\"a\": 1 \"b\": 2}"),
+            ErrorCode::ExpectedObjectCommaOrEnd,
+            "expected_object_comma_or_end",
+        ),
+        (parse_error("[,]"), ErrorCode::ExpectedSomeValue, "expected_some_value"),
+        (parse_error("tru"), ErrorCode::ExpectedSomeIdent, "expected_some_ident"),
+        (parse_error("{1: 2}"), ErrorCode::KeyMustBeAString, "key_must_be_a_string"),
+        (parse_error("\"\\q\""), ErrorCode::InvalidEscape, "invalid_escape"),
+        (parse_error("-"), ErrorCode::InvalidNumber, "invalid_number"),
+        (parse_error("1e999"), ErrorCode::NumberOutOfRange, "number_out_of_range"),
+        (parse_error("[1,"), ErrorCode::EofWhileParsingList, "eof_while_parsing_list"),
+        (parse_error("{\"a\":"), ErrorCode::EofWhileParsingValue, "eof_while_parsing_value"),
+        (parse_error("{\"a\": {"), ErrorCode::EofWhileParsingObject, "eof_while_parsing_object"),
+        (parse_error("\"abc"), ErrorCode::EofWhileParsingString, "eof_while_parsing_string"),
+        (parse_error(&deep), ErrorCode::RecursionLimitExceeded, "recursion_limit_exceeded"),
+        (
+            tjson::from_slice::<Value>(b"\xff\xfe[\x001\x00]\x00").unwrap_err(),
+            ErrorCode::UnsupportedEncoding,
+            "unsupported_encoding",
+        ),
+        (tjson::from_str::<u8>("\"x\"").unwrap_err(), ErrorCode::Data, "data"),
+        (
+            tjson::from_reader::<_, Value>(FailingReader).unwrap_err(),
+            ErrorCode::Io,
+            "io",
+        ),
+    ];
+
+    for (err, code, name) in cases {
+        assert_eq!(err.code(), code, "{}", err);
+        assert_eq!(code.as_str(), name);
+        assert_eq!(code.to_string(), name);
+    }
+}
+
+struct FailingReader;
+
+impl io::Read for FailingReader {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "connection reset"))
+    }
+}
+
+#[test]
+fn test_serialize_error() {
+    let cases = vec![
+        (
+            parse_error("[1] 2"),
+            tjson!({
+                "code:s": "trailing_characters",
+                "message:s": "trailing characters",
+                "line:u": "1",
+                "column:u": "5",
+            }),
+        ),
+        (
+            parse_error("{\n  \"a\": [1,\n"),
+            tjson!({
+                "code:s": "eof_while_parsing_list",
+                "message:s": "unexpected end of input while parsing an array started at line 2 \
+                              column 8 (1 unclosed array and 1 unclosed object)",
+                "line:u": "3",
+                "column:u": "0",
+            }),
+        ),
+        (
+            tjson::from_str::<u8>("300").unwrap_err(),
+            tjson!({
+                "code:s": "data",
+                "message:s": "invalid value: integer `300`, expected u8",
+                "line:u": "1",
+                "column:u": "3",
+            }),
+        ),
+        (
+            tjson::from_value::<u8>(tjson!("x")).unwrap_err(),
+            tjson!({
+                "code:s": "data",
+                "message:s": "invalid type: string \"x\", expected u8",
+            }),
+        ),
+        (
+            tjson::from_reader::<_, Value>(FailingReader).unwrap_err(),
+            tjson!({
+                "code:s": "io",
+                "message:s": "connection reset",
+            }),
+        ),
+    ];
+
+    for (err, expected) in cases {
+        assert_eq!(tjson::to_value(&err).unwrap(), expected);
+    }
+
+    let err = parse_error("[1] 2");
+    assert_eq!(
+        tjson::to_string(&err).unwrap(),
+        r#"{"code:s":"trailing_characters","message:s":"trailing characters","line:u":"1","column:u":"5"}"#
+    );
+}
+
+fn assert_same(a: &tjson::Error, b: &tjson::Error) {
+    assert_eq!(a.code(), b.code());
+    assert_eq!(a.classify(), b.classify());
+    assert_eq!(a.line(), b.line());
+    assert_eq!(a.column(), b.column());
+    assert_eq!(a.byte_offset(), b.byte_offset());
+    assert_eq!(a.path(), b.path());
+    assert_eq!(a.to_string(), b.to_string());
+}
+
+#[test]
+fn test_clone() {
+    let errors = vec![
+        parse_error("[1 2]"),
+        parse_error("{\n  \"a\": [1,\n"),
+        tjson::from_str::<u8>("300").unwrap_err(),
+        tjson::from_reader::<_, Value>(FailingReader).unwrap_err(),
+        parse_error("[1] 2").with_path("config.tjson"),
+        tjson::from_str::<Vec<u8>>(r#"{"a:d64": "!"}"#).unwrap_err(),
+    ];
+
+    for err in &errors {
+        assert_same(err, &err.clone());
+    }
+
+    let io = io::Error::from(errors[3].clone());
+    assert_eq!(io.kind(), io::ErrorKind::Other);
+    assert_eq!(io.to_string(), "connection reset");
+}
+
+#[test]
+fn test_display() {
+    let cases = vec![
+        (parse_error("[1 2]"), "expected `,` or `]` at line 1 column 4"),
+        (parse_error("{\"a\" 1}"), "expected `:` at line 1 column 6"),
+        (parse_error("[1] 2"), "trailing characters at line 1 column 5"),
+        (parse_error("1e999"), "number out of range at line 1 column 5"),
+        (
+            tjson::from_str::<u8>("300").unwrap_err(),
+            "invalid value: integer `300`, expected u8 at line 1 column 3",
+        ),
+        (tjson::from_reader::<_, Value>(FailingReader).unwrap_err(), "connection reset"),
+    ];
+
+    for (err, expected) in cases {
+        assert_eq!(err.to_string(), expected);
+        assert_eq!(err.clone().to_string(), expected);
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::mem;
+
+use tjson::error::{Code, ErrorCode};
+
+#[test]
+fn test_syntax_errors_do_not_allocate() {
+    assert!(mem::size_of::<tjson::Error>() <= 2 * mem::size_of::<usize>());
+
+    let count = alloc::allocations(
+        || for column in 0..1000 {
+            let err = tjson::Error::syntax(Code::ExpectedColon, 1, column)
+                .with_byte_offset(column);
+            assert_eq!(err.code(), ErrorCode::ExpectedColon);
+            assert_eq!(err.byte_offset(), Some(column));
+            let copy = err.clone();
+            assert_eq!(copy.column(), column);
+        },
+    );
+    assert_eq!(count, 0);
+
+    let err = tjson::Error::syntax(Code::Message("boxed".to_owned()), 1, 1);
+    assert!(alloc::allocations(|| drop(err.clone())) > 0);
+}
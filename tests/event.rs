@@ -0,0 +1,358 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate tjson;
+
+use std::borrow::Cow;
+use std::iter::Peekable;
+
+use tjson::{Map, Number, Value};
+use tjson::error::ErrorCode;
+use tjson::event::{self, Event};
+
+const DOCUMENT: &str = r#"{
+                    "name": "John Doe",
+                    "age": 43,
+                    "phones": [
+                      "+44 1234567",
+                      "+44 2345678"
+                    ]
+                  }"#;
+
+fn events<'a>(input: &'a str) -> Vec<Event<'a>> {
+    event::parse(input).collect::<Result<_, _>>().unwrap()
+}
+
+fn write(events: Vec<Event>) -> tjson::Result<String> {
+    let mut output = Vec::new();
+    try!(event::write(&mut output, events));
+    Ok(String::from_utf8(output).unwrap())
+}
+
+/// Builds a `Value` the way a consumer of events would
+fn build<'a, I>(events: &mut Peekable<I>) -> Value
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    match events.next().unwrap() {
+        Event::ObjectStart => {
+            let mut map = Map::new();
+            loop {
+                match events.next().unwrap() {
+                    Event::Key(name) => {
+                        let value = build(events);
+                        map.insert(name.into_owned(), value);
+                    }
+                    Event::ObjectEnd => return Value::Object(map),
+                    event => panic!("unexpected {:?}", event),
+                }
+            }
+        }
+        Event::ArrayStart => {
+            let mut vec = Vec::new();
+            while events.peek() != Some(&Event::ArrayEnd) {
+                vec.push(build(events));
+            }
+            events.next();
+            Value::Array(vec)
+        }
+        Event::Null => Value::Undefined,
+        Event::Bool(b) => Value::Bool(b),
+        Event::Int(i) => Value::Number(i.into()),
+        Event::UInt(u) => Value::Number(u.into()),
+        Event::Float(f) => Value::Number(Number::from_f64(f).unwrap()),
+        Event::Str(s) => Value::String(s.into_owned()),
+        event => panic!("unexpected {:?}", event),
+    }
+}
+
+#[test]
+fn test_round_trip_doc_example() {
+    let parsed = events(DOCUMENT);
+    assert_eq!(
+        parsed,
+        [
+            Event::ObjectStart,
+            Event::Key("name".into()),
+            Event::Str("John Doe".into()),
+            Event::Key("age".into()),
+            Event::UInt(43),
+            Event::Key("phones".into()),
+            Event::ArrayStart,
+            Event::Str("+44 1234567".into()),
+            Event::Str("+44 2345678".into()),
+            Event::ArrayEnd,
+            Event::ObjectEnd,
+        ]
+    );
+
+    let written = write(parsed).unwrap();
+    assert_eq!(written, r#"{"name":"John Doe","age":43,"phones":["+44 1234567","+44 2345678"]}"#);
+    assert_eq!(events(&written), events(DOCUMENT));
+}
+
+#[test]
+fn test_value_from_events_matches_from_str() {
+    let inputs = &[
+        DOCUMENT,
+        r#"{"a": [1, -2, 3.5, true, null, "x\n"], "b": {"c": [[], {}]}, "d": {}}"#,
+        r#"[[[["deep"]]], [], 0]"#,
+        r#"  "\"escaped\""  "#,
+        "-1.5e3",
+        "null",
+    ];
+
+    for input in inputs {
+        let value = build(&mut events(input).into_iter().peekable());
+        assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+
+        let written = write(events(input)).unwrap();
+        assert_eq!(tjson::from_str::<Value>(&written).unwrap(), value);
+    }
+}
+
+#[test]
+fn test_strings_borrow_unless_escaped() {
+    let parsed = events(r#"{"plain": "text", "tab\t": "line\n"}"#);
+
+    match (&parsed[1], &parsed[2]) {
+        (&Event::Key(Cow::Borrowed("plain")), &Event::Str(Cow::Borrowed("text"))) => {}
+        other => panic!("expected borrowed strings, got {:?}", other),
+    }
+    match (&parsed[3], &parsed[4]) {
+        (&Event::Key(Cow::Owned(ref name)), &Event::Str(Cow::Owned(ref value))) => {
+            assert_eq!(name, "tab\t");
+            assert_eq!(value, "line\n");
+        }
+        other => panic!("expected owned strings, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let inputs = &[
+        r#"{"a": [1, {"b": [2, {"c""#,
+        r#"[1, 2"#,
+        r#"[1,]"#,
+        r#"{"a" 1}"#,
+        r#"{1: 2}"#,
+        r#"[1] 2"#,
+        r#"{"a": "\uD800"}"#,
+        "",
+    ];
+
+    for input in inputs {
+        let results: Vec<_> = event::parse(input).collect();
+
+        // Every event up to the error, then the error, then nothing
+        let (last, before) = results.split_last().unwrap();
+        assert!(before.iter().all(Result::is_ok), "{:?}", results);
+        let err = last.as_ref().unwrap_err();
+        assert_eq!(err.to_string(), tjson::from_str::<Value>(input).unwrap_err().to_string());
+    }
+
+    let mut iter = event::parse("[1 2]");
+    assert_eq!(iter.next().unwrap().unwrap(), Event::ArrayStart);
+    assert_eq!(iter.next().unwrap().unwrap(), Event::UInt(1));
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_write_rejects_invalid_sequences() {
+    let cases = vec![
+        (vec![Event::Key("a".into())], "Key event outside of an object or array"),
+        (vec![Event::ArrayEnd], "ArrayEnd event outside of an object or array"),
+        (vec![Event::ObjectStart, Event::Null], "Null event where an object member name was expected"),
+        (vec![Event::ObjectStart, Event::ArrayEnd], "ArrayEnd event where an object member name was expected"),
+        (
+            vec![Event::ObjectStart, Event::Key("a".into()), Event::ObjectEnd],
+            "ObjectEnd event where an object member value was expected",
+        ),
+        (
+            vec![Event::ObjectStart, Event::Key("a".into()), Event::Key("b".into())],
+            "Key event where an object member value was expected",
+        ),
+        (vec![Event::ArrayStart, Event::Key("a".into())], "Key event outside of an object"),
+        (vec![Event::ArrayStart, Event::ObjectEnd], "ObjectEnd event closing an array"),
+        (vec![Event::Null, Event::Null], "Null event after the end of the document"),
+        (
+            vec![Event::ArrayStart, Event::ArrayEnd, Event::ArrayStart],
+            "ArrayStart event after the end of the document",
+        ),
+        (vec![Event::ArrayStart, Event::ObjectStart], "events ended with 2 unclosed containers"),
+        (vec![Event::ArrayStart], "events ended with 1 unclosed container"),
+        (vec![], "events ended before any value"),
+    ];
+
+    for (events, message) in cases {
+        let err = write(events).unwrap_err();
+        assert_eq!(err.to_string(), message);
+        assert_eq!(err.code(), ErrorCode::InvalidEventSequence);
+        assert!(err.is_data());
+    }
+}
+
+#[test]
+fn test_write_scalars() {
+    assert_eq!(write(vec![Event::Int(-7)]).unwrap(), "-7");
+    assert_eq!(write(vec![Event::UInt(u64::MAX)]).unwrap(), "18446744073709551615");
+    assert_eq!(write(vec![Event::Bool(false)]).unwrap(), "false");
+    assert_eq!(write(vec![Event::Str("tab\t".into())]).unwrap(), r#""tab\t""#);
+    assert_eq!(
+        write(vec![Event::ArrayStart, Event::Float(0.5), Event::Float(f64::NAN), Event::ArrayEnd])
+            .unwrap(),
+        "[0.5,null]"
+    );
+}
+
+fn chunked(input: &str, chunk_size: usize) -> tjson::Result<Vec<Event>> {
+    event::parse(input).chunked_data(chunk_size).collect()
+}
+
+/// The bytes of the data in a sequence of events, and how many chunks
+/// they came in.
+fn data(events: &[Event]) -> (Vec<u8>, usize) {
+    let mut bytes = Vec::new();
+    let mut chunks = 0;
+    for event in events {
+        if let Event::DataChunk(ref chunk) = *event {
+            assert!(!chunk.is_empty());
+            bytes.extend_from_slice(chunk);
+            chunks += 1;
+        }
+    }
+    (bytes, chunks)
+}
+
+#[test]
+fn test_chunked_data() {
+    let bytes: Vec<u8> = (0..50u8).map(|b| b.wrapping_mul(37)).collect();
+    let input = format!(
+        r#"{{"a:d":"{}","b:A<d16>":["{}",""],"c:s":"AQ"}}"#,
+        tjson::encoding::b64url_encode(&bytes),
+        tjson::encoding::hex_encode(&bytes)
+    );
+
+    // Every chunk size, including those which don't line up with groups of
+    // base64url or hex, gives the same bytes and writes the same document
+    for chunk_size in 1..60 {
+        let events = chunked(&input, chunk_size).unwrap();
+        let (all, chunks) = data(&events);
+        assert_eq!(all.len(), bytes.len() * 2);
+        assert_eq!(&all[..50], &bytes[..]);
+        assert_eq!(&all[50..], &bytes[..]);
+        let per_string = 50 / chunk_size + if 50 % chunk_size == 0 { 0 } else { 1 };
+        assert_eq!(chunks, per_string * 2, "chunk size {}", chunk_size);
+        assert_eq!(write(events).unwrap(), input);
+    }
+
+    let events = chunked(&input, 1).unwrap();
+    assert_eq!(&events[..4], &[
+        Event::ObjectStart,
+        Event::Key("a:d".into()),
+        Event::DataStart(None),
+        Event::DataChunk(vec![0].into()),
+    ]);
+    // Empty data and other tags
+    let tail = &events[events.len() - 6..];
+    assert_eq!(tail, &[
+        Event::DataStart(None),
+        Event::DataEnd,
+        Event::ArrayEnd,
+        Event::Key("c:s".into()),
+        Event::Str("AQ".into()),
+        Event::ObjectEnd,
+    ]);
+
+    // Without chunked_data, data is a string as usual
+    let plain: Vec<Event> = event::parse(&input).collect::<Result<_, _>>().unwrap();
+    assert_eq!(plain[2], Event::Str(tjson::encoding::b64url_encode(&bytes).into()));
+}
+
+#[test]
+fn test_chunked_data_from_reader() {
+    let input = br#"[{"a:d64": "_-8"}]"#;
+    let events = event::parse_reader(&input[..])
+        .chunked_data(1)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(data(&events), (vec![0xff, 0xef], 2));
+}
+
+#[test]
+fn test_chunked_data_errors() {
+    // An invalid character well into the data, after chunks have been produced
+    let mut text = tjson::encoding::b64url_encode(&[7; 300]);
+    text.insert(301, '+');
+    let input = format!("{{\n\"a:d\": \"{}\"}}", text);
+    let results: Vec<_> = event::parse(&input).chunked_data(16).collect();
+    let err = results.last().unwrap().as_ref().unwrap_err();
+    assert_eq!(err.to_string(), "invalid byte at offset 301 at line 2 column 310");
+    let produced = results[..results.len() - 1]
+        .iter()
+        .map(|result| result.as_ref().unwrap().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(data(&produced).0, vec![7; 224]);
+
+    let cases = [
+        (r#"{"a:d": "AQID=="}"#, "invalid padding at offset 4 at line 1 column 14"),
+        (r#"{"a:d": "A"}"#, "incomplete group at offset 0 at line 1 column 11"),
+        (r#"{"a:d": "AB"}"#, "nonzero trailing bits at offset 1 at line 1 column 12"),
+        (r#"{"a:d16": "0A"}"#, "invalid byte at offset 1 at line 1 column 13"),
+        (r#"{"a:d": "A\u0051"}"#, "invalid byte at offset 1 at line 1 column 11"),
+        (
+            r#"{"a:d": "AQ"#,
+            "unexpected end of input while parsing a string started at line 1 column 9 \
+             (1 unclosed object) at line 1 column 11",
+        ),
+    ];
+    for &(input, message) in &cases {
+        let err = chunked(input, 4).unwrap_err();
+        assert_eq!(err.to_string(), message, "{}", input);
+    }
+}
+
+#[test]
+fn test_write_chunked_data_rejects_invalid_sequences() {
+    let cases = vec![
+        (vec![Event::DataChunk(vec![1].into())], "DataChunk event outside of data"),
+        (vec![Event::ArrayStart, Event::DataEnd], "DataEnd event outside of data"),
+        (
+            vec![Event::DataStart(None), Event::Str("x".into())],
+            "Str event inside data",
+        ),
+        (vec![Event::DataStart(None)], "events ended with 1 unclosed container"),
+        (
+            vec![Event::ObjectStart, Event::DataStart(None)],
+            "DataStart event where an object member name was expected",
+        ),
+    ];
+    for (events, message) in cases {
+        assert_eq!(write(events).unwrap_err().to_string(), message);
+    }
+
+    // Data can be written anywhere, encoded by its tag
+    let events = vec![
+        Event::ArrayStart,
+        Event::DataStart(Some(2)),
+        Event::DataChunk(vec![0xfb].into()),
+        Event::DataChunk(vec![0xff].into()),
+        Event::DataEnd,
+        Event::ObjectStart,
+        Event::Key("x:S<d16>".into()),
+        Event::ArrayStart,
+        Event::DataStart(None),
+        Event::DataChunk(vec![0xfb, 0xff].into()),
+        Event::DataEnd,
+        Event::ArrayEnd,
+        Event::ObjectEnd,
+        Event::ArrayEnd,
+    ];
+    assert_eq!(write(events).unwrap(), r#"["-_8",{"x:S<d16>":["fbff"]}]"#);
+}
@@ -0,0 +1,143 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+extern crate tjson;
+
+use std::f64;
+
+use serde::Serialize;
+use tjson::Serializer;
+use tjson::ser::{ExponentPolicy, SerializeConfig};
+
+const CASES: &[f64] = &[
+    0.0,
+    -0.0,
+    1.0,
+    -2.5,
+    1e-7,
+    -1.5e-7,
+    1e21,
+    1.25e21,
+    123456.789,
+    0.1,
+    1e-6,
+    1e20,
+    123456789012345680000.0,
+    f64::MIN_POSITIVE,
+    f64::EPSILON,
+    1e100,
+    -1e-100,
+];
+
+fn write(config: &SerializeConfig, value: f64) -> String {
+    tjson::to_string_with(config, &value).unwrap()
+}
+
+fn assert_round_trips(config: &SerializeConfig, value: f64) {
+    let text = write(config, value);
+    let back: f64 = tjson::from_str(&text).unwrap();
+    assert_eq!(back.to_bits(), value.to_bits(), "{} read back as {}", text, back);
+}
+
+#[test]
+fn test_allow() {
+    let config = SerializeConfig::new();
+    assert_eq!(write(&config, 1e-7), "1e-7");
+    assert_eq!(write(&config, 1e21), "1e21");
+    assert_eq!(write(&config, 1.5e-6), "0.0000015");
+
+    let config = config.exponent_notation(ExponentPolicy::Allow).max_float_len(3);
+    assert_eq!(write(&config, 1e100), "1e100");
+}
+
+#[test]
+fn test_never() {
+    let config = SerializeConfig::new().exponent_notation(ExponentPolicy::Never);
+    assert_eq!(write(&config, 1e-7), "0.0000001");
+    assert_eq!(write(&config, -1.5e-7), "-0.00000015");
+    assert_eq!(write(&config, 1e21), "1000000000000000000000.0");
+    assert_eq!(write(&config, 1.25e21), "1250000000000000000000.0");
+    assert_eq!(write(&config, 0.1), "0.1");
+    assert_eq!(write(&config, -0.0), "-0.0");
+}
+
+#[test]
+fn test_threshold() {
+    let policy = ExponentPolicy::Threshold {
+        min_exp: -7,
+        max_exp: 3,
+    };
+    let config = SerializeConfig::new().exponent_notation(policy);
+    assert_eq!(write(&config, 1e-7), "0.0000001");
+    assert_eq!(write(&config, 1e-8), "1e-8");
+    assert_eq!(write(&config, 1e21), "1e21");
+    assert_eq!(write(&config, 1234.5), "1234.5");
+    assert_eq!(write(&config, 12345.0), "1.2345e4");
+    assert_eq!(write(&config, -12345.0), "-1.2345e4");
+
+    let policy = ExponentPolicy::Threshold {
+        min_exp: 0,
+        max_exp: 21,
+    };
+    let config = SerializeConfig::new().exponent_notation(policy);
+    assert_eq!(write(&config, 1e-7), "1e-7");
+    assert_eq!(write(&config, 0.5), "5e-1");
+    assert_eq!(write(&config, 1e21), "1000000000000000000000.0");
+    assert_eq!(write(&config, 0.0), "0.0");
+}
+
+#[test]
+fn test_expansion_too_long() {
+    let config = SerializeConfig::new().exponent_notation(ExponentPolicy::Never);
+    let err = tjson::to_string_with(&config, &1e300).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "float 1e300 would be 303 bytes long without an exponent, more than the 64 allowed"
+    );
+
+    let config = config.max_float_len(9);
+    assert_eq!(write(&config, 1e-7), "0.0000001");
+    assert!(tjson::to_string_with(&config, &1e-8).is_err());
+    assert!(tjson::to_string_with(&config, &vec![1.0, 1e9]).is_err());
+}
+
+#[test]
+fn test_serializer_option() {
+    let mut ser = Serializer::new(Vec::new()).exponent_notation(ExponentPolicy::Never);
+    vec![1e-7f64, 1e21].serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), b"[0.0000001,1000000000000000000000.0]");
+
+    let mut ser = Serializer::new(Vec::new()).exponent_notation(ExponentPolicy::Never);
+    1e-7f32.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), b"0.0000001");
+}
+
+#[test]
+fn test_output_reads_back() {
+    let policies = [
+        ExponentPolicy::Allow,
+        ExponentPolicy::Never,
+        ExponentPolicy::Threshold {
+            min_exp: -3,
+            max_exp: 3,
+        },
+        ExponentPolicy::Threshold {
+            min_exp: 5,
+            max_exp: 0,
+        },
+    ];
+    for &policy in &policies {
+        let config = SerializeConfig::new()
+            .exponent_notation(policy)
+            .max_float_len(400);
+        for &value in CASES {
+            assert_round_trips(&config, value);
+        }
+    }
+}
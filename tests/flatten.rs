@@ -0,0 +1,225 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use tjson::{Set, Value};
+use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig};
+use tjson::value::{ArrayMode, FlatErrorKind, FlattenOptions};
+
+fn nested() -> Value {
+    let mut set = Set::new();
+    set.insert(tjson!("ops"));
+    set.insert(tjson!("dev"));
+    tjson!({
+        "name:s": "api",
+        "replicas:u": 3,
+        "owners:S<s>": Value::Set(set),
+        "env:O": { "LOG/LEVEL:s": "debug", "tilde~:s": "~" },
+        "ports:A<O>": [{ "port:u": 80, "tls:b": false }, { "port:u": 443, "tls:b": true }],
+        "matrix:A<A<i>>": [[1, 2], [], [3]],
+        "labels:O": {},
+        "note:s": "",
+    })
+}
+
+fn pointers(flat: &[(String, &Value)]) -> Vec<String> {
+    flat.iter().map(|&(ref pointer, _)| pointer.clone()).collect()
+}
+
+fn rebuild<'a, I>(flat: I) -> Value
+where
+    I: IntoIterator<Item = (String, &'a Value)>,
+{
+    Value::from_flat(flat.into_iter().map(|(pointer, value)| (pointer, value.clone()))).unwrap()
+}
+
+#[test]
+fn test_round_trip() {
+    let value = nested();
+    let flat: Vec<_> = value.flatten().collect();
+    for &(ref pointer, leaf) in &flat {
+        // Pointers don't reach into sets, whose elements are numbered in order
+        if let Some(index) = pointer.strip_prefix("/owners:S<s>/") {
+            let index: usize = index.parse().unwrap();
+            match value["owners:S<s>"] {
+                Value::Set(ref owners) => assert_eq!(owners.iter().nth(index), Some(leaf)),
+                _ => unreachable!(),
+            }
+        } else {
+            assert_eq!(value.pointer(pointer), Some(leaf), "{}", pointer);
+        }
+    }
+    assert!(flat.iter().any(|&(ref pointer, _)| pointer == "/env:O/LOG~1LEVEL:s"));
+    assert!(flat.iter().any(|&(ref pointer, _)| pointer == "/env:O/tilde~0:s"));
+    assert!(flat.iter().any(|&(ref pointer, _)| pointer == "/matrix:A<A<i>>/1"));
+    assert_eq!(rebuild(flat), value);
+
+    // Also in any order, and with other settings
+    let mut flat: Vec<_> = value.flatten().collect();
+    flat.reverse();
+    // Member order follows the input with preserve_order
+    let sorted = SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName));
+    assert_eq!(
+        tjson::to_string_with(&sorted, &rebuild(flat)).unwrap(),
+        tjson::to_string_with(&sorted, &value).unwrap()
+    );
+    let options = FlattenOptions::new().array_mode(ArrayMode::Joined);
+    assert_eq!(rebuild(value.flatten_with(options)), value);
+    for depth in 0..4 {
+        assert_eq!(rebuild(value.flatten_with(FlattenOptions::new().max_depth(depth))), value);
+    }
+}
+
+#[test]
+fn test_untagged() {
+    let value = tjson!([{ "a": [1, { "b": null }] }, 2, { "0": "x", "1": "y" }]);
+    let flat: Vec<_> = value.flatten().collect();
+    assert_eq!(pointers(&flat), ["/0/a/0", "/0/a/1/b", "/1", "/2/0", "/2/1"]);
+
+    // Without tags, index-like names can't be told from elements
+    let rebuilt = rebuild(flat);
+    assert_eq!(rebuilt[0], value[0]);
+    assert_eq!(rebuilt[2], tjson!(["x", "y"]));
+
+    // But a tagged object keeps them as names
+    let value = tjson!({ "o:O": { "0": "x", "1": "y" } });
+    assert_eq!(rebuild(value.flatten()), value);
+}
+
+#[test]
+fn test_array_modes() {
+    let value = tjson!({
+        "list:A<i>": [1, 2],
+        "empty:A<i>": [],
+        "tags:S<s>": Value::Set(Set::new()),
+    });
+
+    let mut positional: Vec<_> = value.flatten().collect();
+    positional.sort();
+    let empty_set = Value::Set(Set::new());
+    assert_eq!(
+        positional,
+        vec![
+            ("/empty:A<i>".to_owned(), &tjson!([])),
+            ("/list:A<i>/0".to_owned(), &tjson!(1)),
+            ("/list:A<i>/1".to_owned(), &tjson!(2)),
+            ("/tags:S<s>".to_owned(), &empty_set),
+        ]
+    );
+
+    let options = FlattenOptions::new().array_mode(ArrayMode::Joined);
+    let joined: Vec<_> = value.flatten_with(options).collect();
+    assert!(joined.contains(&("/list:A<i>".to_owned(), &tjson!([1, 2]))));
+    assert_eq!(joined.len(), 3);
+}
+
+#[test]
+fn test_sets() {
+    let value = nested();
+    let with_sets: Vec<_> = value.flatten().collect();
+    let owners = with_sets.iter().filter(|item| item.0.starts_with("/owners:S<s>/"));
+    assert_eq!(owners.count(), 2);
+
+    let options = FlattenOptions::new().include_sets(false);
+    let without: Vec<_> = value.flatten_with(options).collect();
+    assert_eq!(without.len(), with_sets.len() - 2);
+    assert!(without.iter().all(|item| !item.0.starts_with("/owners")));
+
+    // Joined sets are single leaves, unless left out
+    let options = FlattenOptions::new().array_mode(ArrayMode::Joined);
+    let owners = |flat: tjson::value::Flatten| flat.filter(|item| item.0 == "/owners:S<s>").count();
+    assert_eq!(owners(value.flatten_with(options)), 1);
+    let options = FlattenOptions::new().array_mode(ArrayMode::Joined).include_sets(false);
+    assert_eq!(owners(value.flatten_with(options)), 0);
+}
+
+#[test]
+fn test_max_depth() {
+    let value = tjson!({ "a:O": { "b:O": { "c:i": 1 } }, "d:i": 2 });
+
+    let flat: Vec<_> = value.flatten_with(FlattenOptions::new().max_depth(0)).collect();
+    assert_eq!(flat, vec![(String::new(), &value)]);
+
+    let flat: Vec<_> = value.flatten_with(FlattenOptions::new().max_depth(1)).collect();
+    assert_eq!(pointers(&flat), ["/a:O", "/d:i"]);
+    assert_eq!(flat[0].1, &tjson!({ "b:O": { "c:i": 1 } }));
+
+    let flat: Vec<_> = value.flatten_with(FlattenOptions::new().max_depth(2)).collect();
+    assert_eq!(pointers(&flat), ["/a:O/b:O", "/d:i"]);
+
+    let flat: Vec<_> = value.flatten_with(FlattenOptions::new().max_depth(3)).collect();
+    assert_eq!(pointers(&flat), ["/a:O/b:O/c:i", "/d:i"]);
+}
+
+#[test]
+fn test_conflicts() {
+    let check = |pairs: Vec<(&str, Value)>, path: &str, kind: FlatErrorKind| {
+        let err = Value::from_flat(pairs).unwrap_err();
+        assert_eq!((err.path(), err.kind()), (path, kind), "{}", err);
+    };
+
+    let conflict = FlatErrorKind::Conflict;
+    check(vec![("/a:s", tjson!("x")), ("/a:s", tjson!("y"))], "/a:s", conflict);
+    check(vec![("/a:O", tjson!({})), ("/a:O/b:s", tjson!("y"))], "/a:O/b:s", conflict);
+    check(vec![("/a:O/b:s", tjson!("y")), ("/a:O", tjson!({}))], "/a:O", conflict);
+    check(vec![("", tjson!({})), ("/a:s", tjson!("x"))], "/a:s", conflict);
+    check(vec![("/a:s", tjson!("x")), ("", tjson!({}))], "", conflict);
+
+    check(vec![("a:s", tjson!("x"))], "a:s", FlatErrorKind::Syntax);
+    check(vec![("/a~2:s", tjson!("x"))], "/a~2:s", FlatErrorKind::Syntax);
+
+    check(vec![("/l:A<i>/x", tjson!(1))], "/l:A<i>/x", FlatErrorKind::BadIndex);
+    let gap = vec![("/l:A<i>/0", tjson!(1)), ("/l:A<i>/2", tjson!(3))];
+    check(gap, "/l:A<i>/2", FlatErrorKind::BadIndex);
+    check(vec![("/l:S<i>/01", tjson!(1))], "/l:S<i>/01", FlatErrorKind::BadIndex);
+
+    let err = Value::from_flat(vec![("/a:s", tjson!("x")), ("/a:s", tjson!("y"))]).unwrap_err();
+    assert_eq!(err.to_string(), "conflicting value at \"/a:s\"");
+}
+
+#[test]
+fn test_from_flat() {
+    let empty: Vec<(String, Value)> = Vec::new();
+    assert_eq!(Value::from_flat(empty).unwrap(), tjson!({}));
+    assert_eq!(Value::from_flat(vec![("", tjson!([1]))]).unwrap(), tjson!([1]));
+
+    // Members stay in the order they were first given, with preserve_order
+    let value = Value::from_flat(vec![
+        ("/b:O/y:i", tjson!(1)),
+        ("/a:i", tjson!(2)),
+        ("/b:O/x:i", tjson!(3)),
+    ]).unwrap();
+    assert_eq!(value, tjson!({ "b:O": { "y:i": 1, "x:i": 3 }, "a:i": 2 }));
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        let output = tjson::to_string(&value).unwrap();
+        assert_eq!(output, r#"{"b:O":{"y:i":"1","x:i":"3"},"a:i":"2"}"#);
+    }
+}
+
+#[test]
+fn test_lazy() {
+    let huge: Vec<_> =
+        (0..200_000).map(|i| tjson!({ "id:u": i, "tags:A<s>": ["a", "b"] })).collect();
+    let huge = Value::Array(huge);
+    let mut first = None;
+    let allocated = alloc::allocated_bytes(|| first = huge.flatten().next());
+    assert_eq!(first, Some(("/0/id:u".to_owned(), &tjson!(0))));
+    // A few frames and paths, not one per leaf
+    assert!(allocated < 1024, "allocated {} bytes", allocated);
+
+    let mut flat = huge.flatten().skip(599_997);
+    assert_eq!(flat.next(), Some(("/199999/id:u".to_owned(), &tjson!(199_999))));
+    assert_eq!(flat.next().unwrap().0, "/199999/tags:A<s>/0");
+    assert_eq!(flat.next().unwrap().0, "/199999/tags:A<s>/1");
+    assert_eq!(flat.next(), None);
+}
@@ -0,0 +1,167 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+mod common;
+
+use std::collections::hash_map::DefaultHasher;
+use std::f64;
+use std::hash::{Hash, Hasher};
+
+use common::Rng;
+use tjson::{Number, Set, Value};
+use tjson::de::Parser;
+
+const CASES: &[f64] = &[
+    0.0,
+    -0.0,
+    5e-324,
+    -5e-324,
+    2.225073858507201e-308,
+    f64::MIN_POSITIVE,
+    f64::MAX,
+    f64::MIN,
+    f64::EPSILON,
+    0.1,
+    0.30000000000000004,
+    1.0 / 3.0,
+    9007199254740991.0,
+    9007199254740992.0,
+    9007199254740994.0,
+    1e23,
+    123456789012345680.0,
+];
+
+/// Writes `f` as text and as a `Value`, which must both read back as the
+/// same bits.
+fn check(f: f64) {
+    let text = tjson::to_string(&f).unwrap();
+    let back: f64 = tjson::from_str(&text).unwrap();
+    assert_eq!(back.to_bits(), f.to_bits(), "{:?} written as {}", f, text);
+
+    let value: Value = tjson::from_str(&text).unwrap();
+    let back = value.as_f64().unwrap();
+    assert_eq!(back.to_bits(), f.to_bits(), "{:?} read as {}", f, value);
+
+    let text = tjson::to_string_pretty(&tjson!({ "f:f": f })).unwrap();
+    let value: Value = tjson::from_str(&text).unwrap();
+    let back = value["f:f"].as_f64().unwrap();
+    assert_eq!(back.to_bits(), f.to_bits(), "{:?} written as {}", f, text);
+}
+
+fn hash<T: Hash>(t: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    t.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_targeted_floats() {
+    for &f in CASES {
+        check(f);
+        check(-f);
+    }
+}
+
+#[test]
+fn test_random_floats() {
+    let mut rng = Rng(0x243f_6a88_85a3_08d3);
+    let mut checked = 0;
+    while checked < 100_000 {
+        let f = f64::from_bits(rng.next());
+        if f.is_finite() {
+            check(f);
+            checked += 1;
+        }
+    }
+}
+
+#[test]
+fn test_correctly_rounded_parsing() {
+    // Each needs all of its digits, or lies near a halfway point
+    let inputs = [
+        "0.30000000000000007",
+        "9007199254740993",
+        "9007199254740993.0",
+        "2.2250738585072011e-308",
+        "2.4703282292062328e-324",
+        "1.7976931348623157e308",
+        "8.98846567431158e307",
+        "1e23",
+        "4.35679556785433e-307",
+        "-51071632272407190.0",
+    ];
+    for input in &inputs {
+        let parsed: f64 = tjson::from_str(input).unwrap();
+        let expected: f64 = input.parse().unwrap();
+        assert_eq!(parsed.to_bits(), expected.to_bits(), "{}", input);
+    }
+
+    // Still out of range rather than infinite
+    assert!(tjson::from_str::<f64>("1.8e308").is_err());
+    assert_eq!(tjson::from_str::<f64>("1e-400").unwrap(), 0.0);
+}
+
+#[test]
+fn test_parsing_floats_does_not_allocate() {
+    // Each needs more than the exact fast path
+    let inputs = &["0.30000000000000004", "5e-324", "1.7976931348623157e308", "1e23"];
+    let mut parser = Parser::new();
+    parser.parse_str::<f64>("0").unwrap();
+
+    let count = alloc::allocations(
+        || for input in inputs {
+            parser.parse_str::<f64>(input).unwrap();
+        },
+    );
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_negative_zero() {
+    assert_eq!(tjson::to_string(&-0.0).unwrap(), "-0.0");
+    assert_eq!(tjson::to_string(&0.0).unwrap(), "0.0");
+    assert_eq!(tjson::to_string(&tjson!({ "z:f": -0.0 })).unwrap(), r#"{"z:f":-0.0}"#);
+
+    let zero: f64 = tjson::from_str("-0.0").unwrap();
+    assert!(zero.is_sign_negative());
+    let zero: f64 = tjson::from_str("-0e5").unwrap();
+    assert!(zero.is_sign_negative());
+
+    // Without a fraction or exponent it is the integer zero
+    let zero: Value = tjson::from_str("-0").unwrap();
+    assert_eq!(zero.as_u64(), Some(0));
+}
+
+#[test]
+fn test_negative_zero_equality() {
+    let neg = Number::from_f64(-0.0).unwrap();
+    let pos = Number::from_f64(0.0).unwrap();
+    assert_eq!(neg, pos);
+    assert_eq!(neg.cmp(&pos), std::cmp::Ordering::Equal);
+    assert_eq!(hash(&neg), hash(&pos));
+
+    let (neg, pos) = (tjson!(-0.0), tjson!(0.0));
+    assert_eq!(neg, pos);
+    assert_eq!(hash(&neg), hash(&pos));
+    assert_eq!(neg.hash_fingerprint(), pos.hash_fingerprint());
+
+    // The integer zero is a different number
+    assert_ne!(tjson!(0), pos);
+
+    // A set holds only one of them
+    let mut set = Set::new();
+    assert!(set.insert(pos));
+    assert!(!set.insert(neg.clone()));
+    assert_eq!(set.len(), 1);
+    assert!(set.contains(&neg));
+}
@@ -0,0 +1,193 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::ser::{Error as SerError, Serialize, SerializeSeq, Serializer};
+use tjson::Value;
+use tjson::fs::{Format, SaveOptions, load, save};
+
+/// A directory of its own for each test, removed afterwards.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("tjson-fs-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn entries(&self) -> Vec<String> {
+        let mut entries: Vec<String> = fs::read_dir(&self.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct State {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "runs:u")]
+    runs: u64,
+}
+
+#[test]
+fn test_save_load() {
+    let dir = TempDir::new("round-trip");
+    let path = dir.0.join("state.tjson");
+
+    let state = State {
+        name: "worker".to_owned(),
+        runs: 3,
+    };
+    save(&path, &state, &SaveOptions::new()).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"name:s":"worker","runs:u":"3"}"#);
+    assert_eq!(load::<State, _>(&path).unwrap(), state);
+
+    // Replacing the file
+    let state = State {
+        name: "worker".to_owned(),
+        runs: 4,
+    };
+    let options = SaveOptions::new().format(Format::Pretty);
+    save(&path, &state, &options).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "{\n  \"name:s\": \"worker\",\n  \"runs:u\": \"4\"\n}\n"
+    );
+    assert_eq!(load::<State, _>(&path).unwrap(), state);
+    assert_eq!(dir.entries(), ["state.tjson"]);
+
+    // Through Value
+    let value = Value::load_from_path(&path).unwrap();
+    assert_eq!(value["runs:u"], 4);
+    value.save_to_path(&path, &SaveOptions::new()).unwrap();
+    assert_eq!(load::<State, _>(&path).unwrap(), state);
+}
+
+#[test]
+fn test_save_canonical() {
+    let dir = TempDir::new("canonical");
+    let path = dir.0.join("canonical.tjson");
+
+    let value = tjson!({ "b": [{ "y": 1, "x": 2 }], "a": true });
+    let options = SaveOptions::new().format(Format::Canonical);
+    save(&path, &value, &options).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        r#"{"a:b":true,"b:A<O>":[{"x:i":"2","y:i":"1"}]}"#
+    );
+}
+
+/// Serializes `count` elements, then fails.
+struct FailsAfter {
+    count: usize,
+}
+
+impl Serialize for FailsAfter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = try!(serializer.serialize_seq(None));
+        for i in 0..self.count {
+            try!(seq.serialize_element(&format!("element {}", i)));
+        }
+        Err(S::Error::custom("interrupted"))
+    }
+}
+
+#[test]
+fn test_interrupted_save() {
+    let dir = TempDir::new("interrupted");
+    let path = dir.0.join("state.tjson");
+    save(&path, &tjson!(["original"]), &SaveOptions::new()).unwrap();
+
+    // Enough to have been written to the temporary file before failing
+    let err = save(&path, &FailsAfter { count: 100_000 }, &SaveOptions::new()).unwrap_err();
+    assert_eq!(err.to_string(), format!("{}: interrupted", path.display()));
+    assert_eq!(err.path(), Some(path.as_path()));
+
+    assert_eq!(load::<Value, _>(&path).unwrap(), tjson!(["original"]));
+    assert_eq!(dir.entries(), ["state.tjson"]);
+
+    // Nothing is created if saving a new file fails
+    let new_path = dir.0.join("new.tjson");
+    assert!(save(&new_path, &FailsAfter { count: 1 }, &SaveOptions::new()).is_err());
+    assert_eq!(dir.entries(), ["state.tjson"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("permissions");
+    let path = dir.0.join("secret.tjson");
+    let mode = |path: &PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    let options = SaveOptions::new().mode(0o640);
+    save(&path, &tjson!({ "token:s": "x" }), &options).unwrap();
+    assert_eq!(mode(&path), 0o640);
+
+    // Kept when replacing the file
+    save(&path, &tjson!({ "token:s": "y" }), &SaveOptions::new()).unwrap();
+    assert_eq!(mode(&path), 0o640);
+
+    save(&path, &tjson!({}), &SaveOptions::new().mode(0o600)).unwrap();
+    assert_eq!(mode(&path), 0o600);
+}
+
+#[test]
+fn test_load_errors() {
+    let dir = TempDir::new("errors");
+    let missing = dir.0.join("missing.tjson");
+
+    let err = load::<Value, _>(&missing).unwrap_err();
+    assert!(err.is_io());
+    assert_eq!(err.path(), Some(missing.as_path()));
+    assert!(err.to_string().starts_with(&format!("{}: ", missing.display())));
+    let err: io::Error = err.into();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let broken = dir.0.join("broken.tjson");
+    fs::write(&broken, "{\"a\": 1,\n \"b\" 2}").unwrap();
+    let err = load::<Value, _>(&broken).unwrap_err();
+    assert!(err.is_syntax());
+    assert_eq!((err.line(), err.column()), (2, 6));
+    assert_eq!(
+        err.to_string(),
+        format!("{}: expected `:` at line 2 column 6", broken.display())
+    );
+    let err: io::Error = err.into();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Saving into a directory which doesn't exist
+    let err = save(dir.0.join("no/such.tjson"), &tjson!([]), &SaveOptions::new()).unwrap_err();
+    assert!(err.is_io());
+}
@@ -0,0 +1,185 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "testgen")]
+
+extern crate tjson;
+
+use tjson::Value;
+use tjson::gen::Generator;
+use tjson::json::to_json;
+use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig, TagInference};
+use tjson::value::ValueKind;
+
+fn generators() -> Vec<Generator> {
+    let mut generators: Vec<Generator> = (0..20).map(Generator::new).collect();
+    generators.push(Generator::realistic_telemetry().breadth(50));
+    generators.push(Generator::config_like());
+    generators.push(Generator::config_like().seed(1).depth(6).breadth(12));
+    generators.push(Generator::binary_heavy().breadth(4));
+    generators
+}
+
+/// Call `f` on the value and everything inside it, along with member names
+/// as strings
+fn visit<F>(value: &Value, f: &mut F)
+where
+    F: FnMut(&Value),
+{
+    f(value);
+    match *value {
+        Value::Array(ref array) => {
+            for element in array {
+                visit(element, f);
+            }
+        }
+        Value::Set(ref set) => {
+            for element in set {
+                visit(element, f);
+            }
+        }
+        Value::Object(ref map) => {
+            for (name, value) in map {
+                f(&Value::String(name.clone()));
+                visit(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn test_same_seed_same_document() {
+    for (a, b) in generators().iter().zip(generators()) {
+        let (a, b) = (a.build(), b.build());
+        assert_eq!(a.hash_fingerprint(), b.hash_fingerprint());
+        assert_eq!(a, b);
+    }
+
+    // Different seeds give different documents
+    let a = Generator::new(1).build();
+    assert!(a.hash_fingerprint() != Generator::new(2).build().hash_fingerprint());
+    let a = Generator::config_like().build();
+    assert!(a.hash_fingerprint() != Generator::config_like().seed(1).build().hash_fingerprint());
+
+    // The documents don't depend on the platform or the run. Map ordering
+    // affects the fingerprint of objects.
+    let expected = if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        0x3c05_fe5d_6b41_207e
+    } else {
+        0xe1eb_c438_d839_e564
+    };
+    assert_eq!(Generator::new(7).build().hash_fingerprint(), expected);
+}
+
+#[test]
+fn test_size_knobs() {
+    let (mut deepest, mut widest) = (0, 0);
+
+    for seed in 0..100 {
+        let generator = Generator::new(seed)
+            .depth(3)
+            .breadth(5)
+            .string_len(2..6)
+            .data_len(8..16)
+            .kinds(&[ValueKind::Object, ValueKind::Set, ValueKind::String, ValueKind::Data]);
+        let value = generator.build();
+
+        let stats = value.stats();
+        assert!(stats.max_depth <= 3, "{:?}", stats);
+        assert!(stats.largest_container <= 5, "{:?}", stats);
+        assert_eq!(stats.count(ValueKind::Number) + stats.count(ValueKind::Array), 0);
+        deepest = deepest.max(stats.max_depth);
+        widest = widest.max(stats.largest_container);
+
+        visit(
+            &value,
+            &mut |value| match *value {
+                Value::String(ref s) => assert!(2 <= s.chars().count() && s.chars().count() < 6),
+                Value::Data(ref d) => assert!(8 <= d.len() && d.len() < 16),
+                _ => {}
+            },
+        );
+    }
+
+    // The limits are reached, not just respected
+    assert_eq!(deepest, 3);
+    assert_eq!(widest, 5);
+
+    // Scalars only
+    let value = Generator::new(3).depth(0).build();
+    assert_eq!(value.stats().max_depth, 0);
+
+    // Containers only, so the deepest ones are empty
+    let value = Generator::new(3).depth(2).kinds(&[ValueKind::Array]).build();
+    let stats = value.stats();
+    assert_eq!(stats.count(ValueKind::Array), stats.nodes);
+    assert!(stats.max_depth <= 2);
+}
+
+#[test]
+fn test_corpora() {
+    let telemetry = Generator::realistic_telemetry().breadth(200).build();
+    let points = telemetry.as_array().unwrap();
+    assert_eq!(points.len(), 200);
+    for point in points {
+        assert!(point["timestamp"].is_u64());
+        assert!(point["host"].is_string());
+        assert!(point["value"].is_number());
+        assert!(point["metric"]["unit"].is_string());
+    }
+
+    let config = Generator::config_like().depth(3).build();
+    assert!(config.is_object());
+    assert!(config.stats().max_depth <= 3);
+
+    let binary = Generator::binary_heavy().breadth(10).data_len(100..200).build();
+    let stats = binary.stats();
+    assert_eq!(stats.count(ValueKind::Data), 10);
+    assert!(100 * 10 <= stats.data_bytes && stats.data_bytes < 200 * 10);
+}
+
+#[test]
+fn test_round_trip() {
+    // Documents of arbitrary structure have nulls, and arrays and sets of
+    // mixed elements, which have no tag, so those members are left untagged
+    let config = SerializeConfig::new().infer_tags(TagInference::WhereKnown);
+    for generator in generators() {
+        let value = generator.build();
+        let text = tjson::to_string_with(&config, &value).unwrap();
+        let parsed: Value = tjson::from_str(&text).unwrap();
+
+        // Names are read back with the tags they were written with, and
+        // untagged data, sets and timestamps as strings and arrays, so
+        // compare them as JSON
+        assert_eq!(to_json(parsed).unwrap(), to_json(value).unwrap());
+    }
+
+    // The corpora are tagged throughout
+    let corpora = &[
+        Generator::realistic_telemetry().breadth(50),
+        Generator::config_like().seed(1).depth(6).breadth(12),
+        Generator::binary_heavy().breadth(4),
+    ];
+    for generator in corpora {
+        let value = generator.build();
+        let text = tjson::to_string(&value).unwrap();
+        let parsed: Value = tjson::from_str(&text).unwrap();
+        assert_eq!(tjson::to_string(&parsed).unwrap(), text);
+    }
+
+    // Integers are quoted
+    let point = Generator::realistic_telemetry().breadth(1).build();
+    let sorted = SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName));
+    let expected = concat!(
+        r#"[{"host:s":"ingest-worker-eu-west-2","#,
+        r#""metric:O":{"name:s":"http.request.duration","type:s":"histogram","#,
+        r#""unit:s":"milliseconds"},"timestamp:i":"1558607542","value:f":33902.75}]"#,
+    );
+    assert_eq!(tjson::to_string_with(&sorted, &point).unwrap(), expected);
+}
@@ -0,0 +1,165 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+
+use chrono::datetime::DateTime;
+use chrono::offset::utc::UTC;
+use tjson::{Map, Value};
+use tjson::error::{Category, ErrorCode};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Point {
+    #[serde(rename = "x:i")]
+    x: i64,
+    #[serde(rename = "y:i")]
+    y: i64,
+}
+
+fn sample() -> Value {
+    tjson::from_str(
+        r#"{
+            "count:u": "42",
+            "created:t": "2017-03-04T05:06:07Z",
+            "name:s": "widget",
+            "tags:A<s>": ["a", "b"],
+            "points:A<O>": [{"x:i": "1", "y:i": "2"}, {"x:i": "-3", "y:i": "4"}],
+            "limits:O": {"low:i": "-1", "high:i": "1"}
+        }"#,
+    )
+        .unwrap()
+}
+
+fn map(value: &Value) -> &Map<String, Value> {
+    value.as_object().unwrap()
+}
+
+#[test]
+fn test_get_typed() {
+    let value = sample();
+    let map = map(&value);
+
+    assert_eq!(map.get_typed::<u64>("count:u").unwrap(), 42);
+    assert_eq!(map.get_typed::<i32>("count:u").unwrap(), 42);
+    let created: DateTime<UTC> = map.get_typed("created:t").unwrap();
+    assert_eq!(created.to_string(), "2017-03-04 05:06:07 UTC");
+    assert_eq!(map.get_typed::<String>("name:s").unwrap(), "widget");
+    assert_eq!(map.get_typed::<&str>("name:s").unwrap(), "widget");
+    assert_eq!(map.get_typed::<Vec<String>>("tags:A<s>").unwrap(), ["a", "b"]);
+}
+
+#[test]
+fn test_get_typed_nested() {
+    let value = sample();
+    let map = map(&value);
+
+    let points: Vec<Point> = map.get_typed("points:A<O>").unwrap();
+    assert_eq!(points, [Point { x: 1, y: 2 }, Point { x: -3, y: 4 }]);
+
+    let limits: BTreeMap<String, i64> = map.get_typed("limits:O").unwrap();
+    assert_eq!(limits["low:i"], -1);
+    assert_eq!(limits["high:i"], 1);
+
+    let point: Point = value["points:A<O>"].get_typed(1).unwrap();
+    assert_eq!(point, Point { x: -3, y: 4 });
+    assert_eq!(value["points:A<O>"][0].get_typed::<i64, _>("y:i").unwrap(), 2);
+}
+
+#[test]
+fn test_get_typed_missing() {
+    let value = sample();
+    let map = map(&value);
+
+    let err = map.get_typed::<u64>("count").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::MissingMember);
+    assert_eq!(err.classify(), Category::Data);
+    assert_eq!(err.to_string(), "missing member \"count\"");
+    #[allow(deprecated)]
+    let cause = err.cause();
+    assert!(cause.is_none());
+
+    let err = value.get_typed::<u64, _>("absent:u").unwrap_err();
+    assert_eq!(err.to_string(), "missing member \"absent:u\"");
+
+    let err = value["tags:A<s>"].get_typed::<String, _>(2).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::MissingMember);
+    assert_eq!(err.to_string(), "missing element 2");
+
+    let err = value["count:u"].get_typed::<u64, _>("count:u").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::MissingMember);
+
+    let value = tjson!({"gone": Value::Undefined});
+    let err = value.get_typed::<u64, _>("gone").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::MissingMember);
+}
+
+#[test]
+fn test_get_typed_mismatch() {
+    let value = sample();
+    let map = map(&value);
+
+    let err = map.get_typed::<String>("count:u").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::Data);
+    assert_eq!(
+        err.to_string(),
+        "member \"count:u\": invalid type: integer `42`, expected a string"
+    );
+    #[allow(deprecated)]
+    let cause = err.cause().unwrap().to_string();
+    assert_eq!(cause, "invalid type: integer `42`, expected a string");
+
+    let err = value["limits:O"].get_typed::<u8, _>("low:i").unwrap_err();
+    assert_eq!(err.to_string(), "member \"low:i\": invalid value: integer `-1`, expected u8");
+
+    let err = map.get_typed::<Vec<Point>>("tags:A<s>").unwrap_err();
+    assert!(err.to_string().starts_with("member \"tags:A<s>\": "), "{}", err);
+
+    let err = value["tags:A<s>"].get_typed::<u64, _>(0).unwrap_err();
+    assert_eq!(err.to_string(), "element 0: invalid type: string \"a\", expected u64");
+}
+
+#[test]
+fn test_get_typed_or() {
+    let value = sample();
+    let map = map(&value);
+
+    assert_eq!(map.get_typed_or("count:u", 7u64).unwrap(), 42);
+    assert_eq!(map.get_typed_or("count", 7u64).unwrap(), 7);
+    assert_eq!(value.get_typed_or("count:u", 7u64).unwrap(), 42);
+    assert_eq!(value.get_typed_or("other:u", 7u64).unwrap(), 7);
+
+    let err = map.get_typed_or("name:s", 7u64).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "member \"name:s\": invalid type: string \"widget\", expected u64"
+    );
+    assert!(value.get_typed_or("name:s", 7u64).is_err());
+}
+
+#[test]
+fn test_get_typed_opt() {
+    let value = sample();
+    let map = map(&value);
+
+    assert_eq!(map.get_typed_opt::<u64>("count:u").unwrap(), Some(42));
+    assert_eq!(map.get_typed_opt::<u64>("count").unwrap(), None);
+    assert_eq!(value.get_typed_opt::<Vec<&str>, _>("tags:A<s>").unwrap(), Some(vec!["a", "b"]));
+    assert_eq!(value["tags:A<s>"].get_typed_opt::<&str, _>(5).unwrap(), None);
+
+    let err = map.get_typed_opt::<bool>("name:s").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::Data);
+    assert!(err.to_string().starts_with("member \"name:s\": "));
+    assert!(value.get_typed_opt::<bool, _>("name:s").is_err());
+}
@@ -0,0 +1,204 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Pins the output of `tjson::to_string_pretty_stable` to the files in
+//! `tests/golden`. If a formatting change is intended, regenerate them with
+//! `TJSON_BLESS=1 cargo test --test golden` and review the diff.
+
+extern crate serde;
+#[macro_use]
+extern crate tjson;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tjson::{Data, Serializer, Set, Value};
+use tjson::ser::{ArrayWrap, PrettyFormatter};
+
+fn corpus() -> Vec<(&'static str, Value)> {
+    let mut set = Set::new();
+    set.insert(tjson!("a"));
+    set.insert(tjson!("b"));
+
+    let timestamp = "2017-03-04T05:06:07Z".parse().unwrap();
+
+    let mut nested_set = Set::new();
+    let mut other = Set::new();
+    other.insert(tjson!("c"));
+    nested_set.insert(Value::Set(set.clone()));
+    nested_set.insert(Value::Set(other));
+
+    vec![
+        ("null", Value::Undefined),
+        ("true", tjson!(true)),
+        ("integer", tjson!(-9007199254740993i64)),
+        ("float", tjson!(0.1)),
+        ("string", tjson!("plain")),
+        ("empty_array", tjson!([])),
+        ("empty_object", tjson!({})),
+        ("empty_set", Value::Set(Set::new())),
+        ("data", Value::Data(vec![0, 127, 255].into())),
+        ("timestamp", Value::Timestamp(timestamp)),
+        (
+            "escapes",
+            tjson!([
+                "line one\r\nline two\r\n",
+                "tab\there \"quoted\" back\\slash",
+                "\u{0}\u{1f}\u{7f}",
+                "caf\u{e9} \u{1f600}",
+            ]),
+        ),
+        (
+            "long_string",
+            Value::String(
+                "The quick brown fox jumps over the lazy dog. ".repeat(8) + "\r\n",
+            ),
+        ),
+        (
+            "kinds",
+            tjson!({
+                "array": [1, -2, 3.5],
+                "bool": false,
+                "data": Value::Data(b"hi".to_vec().into()),
+                "number": 18446744073709551615u64,
+                "set": Value::Set(set.clone()),
+                "string": "s",
+                "timestamp": Value::Timestamp(timestamp),
+            }),
+        ),
+        (
+            "nested",
+            tjson!({
+                "a": [[], [{}], [{ "b": [1] }]],
+                "c": { "d": { "e": { "f": [true, false] } } },
+                "empty": { "array:A<i>": [], "object": {} },
+                "row\r\nkey": "value",
+            }),
+        ),
+        (
+            "sets_and_data",
+            tjson!({
+                "depth1": {
+                    "data": Value::Data((0..40).collect()),
+                    "empty_data": Value::Data(Data::new()),
+                    "empty_set:S<s>": Value::Set(Set::new()),
+                    "set": Value::Set(set.clone()),
+                    "timestamp": Value::Timestamp(timestamp),
+                },
+                "depth2": [
+                    [Value::Set(set.clone()), Value::Set(Set::new())],
+                    [],
+                    [Value::Set(Set::new())],
+                ],
+                "depth3": [
+                    { "data": Value::Data(vec![255; 3].into()), "inner": [Value::Set(nested_set)] },
+                ],
+            }),
+        ),
+    ]
+}
+
+/// The corpus entries which are also pinned with a custom indent.
+const CUSTOM_INDENT: &[&str] = &["kinds", "nested", "sets_and_data"];
+
+fn golden_path(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("golden");
+    path.push(format!("{}.tjson", name));
+    path
+}
+
+/// Compares `output` with the golden file called `name`, or overwrites the
+/// file with it when blessing.
+fn check_golden(name: &str, output: &str) {
+    let path = golden_path(name);
+
+    if env::var_os("TJSON_BLESS").is_some() {
+        File::create(&path).unwrap().write_all(output.as_bytes()).unwrap();
+        return;
+    }
+
+    let mut golden = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut golden))
+        .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    assert_eq!(output, golden, "{} differs from the output", path.display());
+}
+
+#[test]
+fn test_pretty_stable_goldens() {
+    for (name, value) in corpus() {
+        let output = tjson::to_string_pretty_stable(&value).unwrap();
+        check_golden(name, &output);
+
+        // The goldens are the same as to_string_pretty's output, which doesn't
+        // end in a newline by default
+        assert_eq!(tjson::to_string_pretty(&value).unwrap() + "\n", output);
+
+        let compact = tjson::to_string(&value).unwrap();
+        assert_eq!(
+            tjson::from_str::<Value>(&output).unwrap(),
+            tjson::from_str::<Value>(&compact).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_pretty_custom_indent_goldens() {
+    for (name, value) in corpus() {
+        if !CUSTOM_INDENT.contains(&name) {
+            continue;
+        }
+
+        let formatter = PrettyFormatter::with_indent(b"\t").trailing_newline(true);
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+        let output = String::from_utf8(ser.into_inner()).unwrap();
+        check_golden(&format!("tab_{}", name), &output);
+
+        // Only the indentation differs from the default
+        let default = tjson::to_string_pretty_stable(&value).unwrap();
+        assert_eq!(output.replace('\t', "  "), default);
+    }
+}
+
+#[test]
+fn test_pretty_no_carriage_returns() {
+    for (name, value) in corpus() {
+        let output = tjson::to_string_pretty_stable(&value).unwrap();
+        assert!(!output.contains('\r'), "{}", name);
+        assert!(output.ends_with("\n") && !output.ends_with("\n\n"), "{}", name);
+
+        for line in output.lines() {
+            assert!(!line.ends_with(' '), "{}: {:?}", name, line);
+        }
+    }
+
+    let output = tjson::to_string_pretty(&tjson!({ "a\r\nb": ["\r\n", "\r"] })).unwrap();
+    assert_eq!(output, "{\n  \"a\\r\\nb:A<s>\": [\n    \"\\r\\n\",\n    \"\\r\"\n  ]\n}");
+}
+
+#[test]
+fn test_pretty_data_is_one_line() {
+    let value = tjson!([1, Value::Data(vec![2; 8].into()), 3, Value::Set(Set::new())]);
+
+    // A string, packed like any other scalar when filling
+    let formatter = PrettyFormatter::new().array_wrap(ArrayWrap::Fill(10));
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(ser.into_inner()).unwrap(),
+        "[\n  1,\n  \"AgICAgICAgI\",\n  3,\n  []\n]"
+    );
+
+    assert_eq!(tjson::to_string(&value).unwrap(), r#"[1,"AgICAgICAgI",3,[]]"#);
+}
@@ -0,0 +1,89 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "http")]
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::Value;
+use tjson::http::{self, Options};
+
+#[test]
+fn test_respond() {
+    let (content_type, body) = http::respond(&tjson!({ "a": [1, 2] })).unwrap();
+    assert_eq!(content_type.to_str().unwrap(), http::content_type());
+    assert_eq!(body, br#"{"a:A<i>":["1","2"]}"#);
+}
+
+#[test]
+fn test_media_type_parameters() {
+    let accepted = &[
+        "application/tjson",
+        "Application/TJSON",
+        " application/tjson ",
+        "application/tjson;charset=utf-8",
+        "application/tjson; charset=UTF-8",
+        "application/tjson; charset=\"utf-8\"",
+        "application/tjson; charset=utf8",
+        "application/tjson; profile=\"x\"; charset=utf-8",
+        "application/tjson;",
+    ];
+
+    for content_type in accepted {
+        let value: Value = http::parse_request_body(content_type, b"[true]").unwrap();
+        assert_eq!(value, tjson!([true]));
+    }
+}
+
+#[test]
+fn test_wrong_media_type() {
+    let rejected = &[
+        "",
+        "application/json",
+        "text/plain; charset=utf-8",
+        "application/tjson+zip",
+        "application/tjson; charset=utf-16",
+        "application/tjson; charset=iso-8859-1",
+        "application/tjson; charset",
+    ];
+
+    for content_type in rejected {
+        // The body is never looked at, so a bad one doesn't change the error
+        let err = http::parse_request_body::<Value>(content_type, b"[").unwrap_err();
+        assert!(err.is_unsupported_media_type(), "{}", content_type);
+        assert!(!err.is_syntax() && !err.is_eof());
+        assert_eq!(
+            err.to_string(),
+            format!("unsupported media type {:?}, expected \"application/tjson\"", content_type)
+        );
+    }
+
+    let err = http::parse_request_body::<Value>("application/tjson", b"[").unwrap_err();
+    assert!(!err.is_unsupported_media_type());
+    assert!(err.is_eof());
+}
+
+#[test]
+fn test_body_too_large() {
+    let options = Options::new().max_body_len(8);
+
+    let value: Value = options.parse_request_body("application/tjson", b"[1,2,3] ").unwrap();
+    assert_eq!(value, tjson!([1, 2, 3]));
+
+    let err = options
+        .parse_request_body::<Value>("application/tjson", b"[1, 2, 3]")
+        .unwrap_err();
+    assert!(err.is_body_too_large());
+    assert!(!err.is_unsupported_media_type());
+    assert_eq!(err.to_string(), "request body of 9 bytes is larger than the limit of 8 bytes");
+
+    // The media type is checked first
+    let err = options.parse_request_body::<Value>("text/plain", b"[1, 2, 3]").unwrap_err();
+    assert!(err.is_unsupported_media_type());
+}
@@ -0,0 +1,87 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+use tjson::Deserializer;
+use tjson::de::Normalization;
+
+#[derive(Deserialize)]
+struct Small {
+    #[serde(rename = "counts:A<u>")]
+    counts: Vec<u64>,
+    #[serde(rename = "names:S<s>")]
+    names: BTreeSet<String>,
+    #[serde(rename = "id:u")]
+    id: u64,
+    #[serde(rename = "ok:b")]
+    ok: bool,
+    #[serde(rename = "ratio:f")]
+    ratio: f64,
+}
+
+const STRUCTS: usize = 100_000;
+
+fn input(member: &str) -> String {
+    let mut input = String::from("[");
+    for i in 0..STRUCTS {
+        if i > 0 {
+            input.push(',');
+        }
+        input.push_str(r#"{"counts:A<u>": [], "names:S<s>": [], "#);
+        input.push_str(&format!(r#""{}": "{}", "ok:b": true, "ratio:f": 0.5}}"#, member, i));
+    }
+    input.push(']');
+    input
+}
+
+/// Deserializes the structs, returning how many allocations it took.
+fn deserialize(input: &str, normalization: Normalization) -> usize {
+    let mut structs = Vec::new();
+    let allocations = alloc::allocations(
+        || {
+            let mut de = Deserializer::from_str(input).member_name_normalization(normalization);
+            structs = Vec::<Small>::deserialize(&mut de).unwrap();
+        },
+    );
+
+    assert_eq!(structs.len(), STRUCTS);
+    assert!(structs.iter().all(|s| s.ok && s.ratio == 0.5));
+    assert!(structs.iter().all(|s| s.counts.is_empty() && s.names.is_empty()));
+    assert_eq!(structs[STRUCTS - 1].id, STRUCTS as u64 - 1);
+    allocations
+}
+
+#[test]
+fn test_member_names_do_not_allocate() {
+    // Only growing the vector of structs allocates
+    let exact = input("id:u");
+    assert!(deserialize(&exact, Normalization::Exact) < 100);
+
+    // An escaped name is unescaped into the scratch buffer
+    let escaped = input("i\\u0064:u");
+    assert!(deserialize(&escaped, Normalization::Exact) < 100);
+
+    // Matching by normalization keeps track of the names which matched each
+    // field, which only takes allocating for the names which weren't exact.
+    // Those are compared with the `A<u>` and `S<s>` fields in place too.
+    let per_struct = STRUCTS + 100;
+    assert!(deserialize(&exact, Normalization::CaseInsensitive) < per_struct);
+    assert!(deserialize(&exact, Normalization::TrimAndCaseInsensitive) < per_struct);
+    assert!(deserialize(&input("id:i"), Normalization::TagKinds) < 2 * per_struct);
+    assert!(deserialize(&input(" ID :u"), Normalization::TrimAndCaseInsensitive) < 2 * per_struct);
+}
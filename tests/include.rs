@@ -0,0 +1,54 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+#[macro_use]
+extern crate tjson_macros;
+
+use tjson::Value;
+
+fn parsed(path: &str) -> Value {
+    let text = std::fs::read_to_string(path).unwrap();
+    tjson::from_str(&text).unwrap()
+}
+
+#[test]
+fn test_include_matches_from_str() {
+    let included = include_tjson!("tests/include/config.tjson");
+    let parsed = parsed("tests/include/config.tjson");
+    assert_eq!(included, parsed);
+    assert_eq!(tjson::to_string(&included).unwrap(), tjson::to_string(&parsed).unwrap());
+}
+
+#[test]
+fn test_include_kinds() {
+    let value = include_tjson!("tests/include/config.tjson");
+
+    assert_eq!(value["name:s"], "service \"one\"\n");
+    assert_eq!(value["retries:u"], tjson!(3));
+    assert_eq!(value["floor:i"].as_i64(), Some(i64::MIN));
+    assert_eq!(value["ratio:f"].as_f64(), Some(0.1));
+    assert_eq!(value["enabled:b"], tjson!(true));
+    assert_eq!(value["key:d16"], Value::Data(vec![0, 0xff, 0x7f].into()));
+    assert_eq!(value["raw:d"], Value::Data(b"hi".to_vec().into()));
+    assert_eq!(value["ports:S<u>"].len(), Some(2));
+    assert_eq!(value["limits:O"]["a/b~c:A<O>"], tjson!([]));
+
+    let started = match value["started:t"] {
+        Value::Timestamp(ref t) => t.to_rfc3339(),
+        ref other => panic!("expected a timestamp, found {:?}", other),
+    };
+    assert_eq!(started, "2017-03-04T05:06:07.123456789+00:00");
+
+    let signed = match value["offset:i"] {
+        Value::Number(ref n) => n.is_signed(),
+        _ => false,
+    };
+    assert!(signed);
+}
@@ -0,0 +1,314 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::io;
+
+use tjson::{Set, Value};
+use tjson::json::{from_json, to_json};
+use tjson::ser::{DataEncoding, IntegerTag, SerializeConfig, TagInference};
+
+fn infer() -> SerializeConfig<'static> {
+    SerializeConfig::new().infer_tags(TagInference::Required)
+}
+
+fn tree() -> Value {
+    let mut tags = Set::new();
+    tags.insert(tjson!("b"));
+    tags.insert(tjson!("a"));
+
+    let mut tree = tjson!({
+        "name": "ann",
+        "age": 43,
+        "big": u64::max_value(),
+        "ratio": 0.5,
+        "ok": false,
+        "ids": [1, 2],
+        "grid": [[1], [2, 3]],
+        "home": { "city": "Paris", "rooms": [{ "size": 1.5 }] },
+    });
+    let at = Value::Timestamp("2017-03-04T05:06:07.5Z".parse().unwrap());
+    {
+        let map = tree.as_object_mut().unwrap();
+        map.insert("tags".to_owned(), Value::Set(tags));
+        map.insert("at:t".to_owned(), at);
+    }
+    tree
+}
+
+#[test]
+fn test_round_trip() {
+    let tree = tree();
+    let output = tjson::to_string_with(&infer(), &tree).unwrap();
+    assert!(output.contains(r#""at:t":"2017-03-04T05:06:07.500Z""#), "{}", output);
+
+    let parsed: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(parsed["name:s"], "ann");
+    assert_eq!(parsed["age:i"], 43);
+    assert_eq!(parsed["big:u"], u64::max_value());
+    assert_eq!(parsed["at:t"], tree["at:t"]);
+    assert_eq!(parsed["grid:A<A<i>>"], tjson!([[1], [2, 3]]));
+    assert_eq!(parsed["tags:S<s>"], tree["tags"]);
+    assert_eq!(parsed["home:O"]["rooms:A<O>"][0]["size:f"], 1.5);
+
+    // The same tree once the tags are taken off again
+    assert_eq!(to_json(parsed).unwrap(), to_json(tree).unwrap());
+}
+
+#[test]
+fn test_round_trip_by_default() {
+    let mut tree = tree();
+    {
+        let map = tree.as_object_mut().unwrap();
+        let at = map.remove("at:t").unwrap();
+        map.insert("at".to_owned(), at);
+    }
+    let output = tjson::to_string(&tree).unwrap();
+
+    let parsed: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(parsed["age:i"], 43);
+    assert_eq!(parsed["big:u"], u64::max_value());
+    assert_eq!(parsed["at:t"], tree["at"]);
+    assert_eq!(parsed["ids:A<i>"], tjson!([1, 2]));
+    assert_eq!(parsed["home:O"]["rooms:A<O>"][0]["size:f"], 1.5);
+
+    // Once tagged the tree is written back exactly as it was read
+    let again = tjson::to_string(&parsed).unwrap();
+    assert_eq!(again, output);
+    assert_eq!(tjson::from_str::<Value>(&again).unwrap(), parsed);
+    assert_eq!(to_json(parsed).unwrap(), to_json(tree.clone()).unwrap());
+
+    // A member which can't be tagged is left untagged, unless tags are
+    // required
+    tree.as_object_mut().unwrap().insert("none".to_owned(), tjson!(null));
+    let output = tjson::to_string(&tree).unwrap();
+    assert!(output.contains(r#""none":null"#), "{}", output);
+    let err = tjson::to_string_with(&infer(), &tree).unwrap_err();
+    assert_eq!(err.to_string(), "member \"none\" can't be tagged, as its value is null");
+}
+
+#[test]
+fn test_same_tags_as_from_json() {
+    let mut tree = tree();
+    tree.as_object_mut().unwrap().remove("at:t");
+    let output = tjson::to_string_with(&infer(), &tree).unwrap();
+    assert_eq!(output, tjson::to_string(&from_json(tree).unwrap()).unwrap());
+
+    // Timestamps keep their tag
+    let mut value = tjson!({});
+    let at = Value::Timestamp("2017-03-04T05:06:07Z".parse().unwrap());
+    value.as_object_mut().unwrap().insert("at".to_owned(), at);
+    let output = tjson::to_string_with(&infer(), &value).unwrap();
+    assert_eq!(output, r#"{"at:t":"2017-03-04T05:06:07Z"}"#);
+}
+
+#[test]
+fn test_tagged_names_are_kept() {
+    let value = tjson!({ "a:u": 1, "b": 1, "o:O": { "x": true, "y:s": "z" } });
+    let output = tjson::to_string_with(&infer(), &value).unwrap();
+    assert_eq!(output, r#"{"a:u":"1","b:i":"1","o:O":{"x:b":true,"y:s":"z"}}"#);
+
+    // Unlike `from_json`, which tags every name
+    let output = tjson::to_string(&from_json(tjson!({ "a:u": 1 })).unwrap()).unwrap();
+    assert_eq!(output, r#"{"a:u:i":"1"}"#);
+
+    // With the option off names are written as they are
+    let config = SerializeConfig::new().infer_tags(TagInference::Off);
+    let output = tjson::to_string_with(&config, &value).unwrap();
+    assert_eq!(output, r#"{"a:u":"1","b":1,"o:O":{"x":true,"y:s":"z"}}"#);
+}
+
+#[test]
+fn test_integers_and_floats_together() {
+    let output = tjson::to_string_with(&infer(), &tjson!({ "xs": [[1], [2.5]] })).unwrap();
+    assert_eq!(output, r#"{"xs:A<A<f>>":[[1.0],[2.5]]}"#);
+}
+
+#[test]
+fn test_unsigned_integers() {
+    #[derive(Serialize)]
+    struct Ids {
+        ids: Vec<u64>,
+        #[serde(rename = "n:u")]
+        n: i64,
+    }
+
+    let ids = Ids { ids: vec![1, u64::max_value()], n: 2 };
+    let output = tjson::to_string(&ids).unwrap();
+    assert_eq!(output, r#"{"ids:A<u>":["1","18446744073709551615"],"n:u":"2"}"#);
+
+    // Unless an integer is negative
+    let err = tjson::to_string(&Ids { n: -2, ..ids }).unwrap_err();
+    assert_eq!(err.to_string(), "member \"n:u\" is tagged `u` but its value is `i`");
+    let value = tjson!({ "ids": [-1, u64::max_value()] });
+    let err = tjson::to_string_with(&infer(), &value).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "member \"ids\" can't be tagged, as its value is an array with elements tagged `i` \
+         and `u`"
+    );
+}
+
+#[test]
+fn test_integers_tagged_by_type() {
+    #[derive(Serialize)]
+    struct Counts {
+        small: u64,
+        large: u64,
+        signed: i64,
+    }
+
+    // The tag of a `u64` doesn't depend on how large it is
+    let counts = Counts { small: 5, large: u64::max_value(), signed: 5 };
+    let output = tjson::to_string(&counts).unwrap();
+    assert_eq!(output, r#"{"small:u":"5","large:u":"18446744073709551615","signed:i":"5"}"#);
+
+    // Through a `Value`, each number keeps the tag of what it holds
+    match tjson::to_value(5i64).unwrap() {
+        Value::Number(ref n) => assert!(n.is_signed()),
+        other => panic!("{:?}", other),
+    }
+    let value = tjson!({ "x": Value::from(5u64), "y": 5i64 });
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"x:u":"5","y:i":"5"}"#);
+
+    // Elements of both kinds share `i` while they fit in an `i64`
+    let value: Value = tjson::from_str(r#"{"xs": [-1, 2], "ys": [[3], [-4]]}"#).unwrap();
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"xs:A<i>":["-1","2"],"ys:A<A<i>>":[["3"],["-4"]]}"#);
+}
+
+#[test]
+fn test_composes_with_tag_policies() {
+    let config = infer()
+        .default_data_encoding(DataEncoding::Base16)
+        .integer_tag_policy(IntegerTag::AlwaysSigned);
+    let value = tjson!({ "raw": Value::Data(vec![0xab].into()), "n": 7, "ids": [1, 2] });
+    let output = tjson::to_string_with(&config, &value).unwrap();
+    let written: Value = tjson::from_str(&output).unwrap();
+    let raw = Value::Data(vec![0xab].into());
+    assert_eq!(written, tjson!({ "raw:d16": raw, "n:i": 7, "ids:A<i>": [1, 2] }));
+
+    let err = tjson::to_string_with(&config, &tjson!({ "n": u64::max_value() })).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer 18446744073709551615 is above i64::MAX and can't be tagged `i` at /n"
+    );
+}
+
+#[test]
+fn test_values_without_a_tag() {
+    let cases = vec![
+        (tjson!({ "a": null }), "member \"a\" can't be tagged, as its value is null"),
+        (
+            tjson!({ "a": ["x", []] }),
+            "member \"a\" can't be tagged, as its value is an array with elements tagged `s` \
+             and `A<O>`",
+        ),
+        (
+            tjson!({ "a": [[[]], [1]] }),
+            "member \"a\" can't be tagged, as its value is an array with elements tagged `A<i>` \
+             and `A<A<O>>`",
+        ),
+        (
+            tjson!({ "a": [1, "x"] }),
+            "member \"a\" can't be tagged, as its value is an array with elements tagged `i` \
+             and `s`",
+        ),
+        (
+            tjson!({ "a": [[1], ["x"]] }),
+            "member \"a\" can't be tagged, as its value is an array with elements tagged `A<i>` \
+             and `A<s>`",
+        ),
+    ];
+    for (value, message) in cases {
+        let err = tjson::to_string_with(&infer(), &value).unwrap_err();
+        assert_eq!(err.to_string(), message);
+    }
+
+    // An empty array under a tagged name is fine
+    let output = tjson::to_string_with(&infer(), &tjson!({ "a:A<s>": [] })).unwrap();
+    assert_eq!(output, r#"{"a:A<s>":[]}"#);
+
+    // By default they're left untagged
+    let value = tjson!({ "a": null, "b": [1, "x"], "c": [[1], [2.5]] });
+    let config = SerializeConfig::new().infer_tags(TagInference::WhereKnown);
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"a":null,"b":[1,"x"],"c:A<A<f>>":[[1.0],[2.5]]}"#);
+    assert_eq!(tjson::to_string_with(&config, &value).unwrap(), output);
+}
+
+#[test]
+fn test_empty_arrays() {
+    // Without elements to go by an empty array is tagged `A<O>`, nested as
+    // deep as its empty elements, and a set `S<O>`
+    let value = tjson!({
+        "a": [],
+        "b": [[], [[]]],
+        "c": [[], [1]],
+        "d": Value::Set(Set::new()),
+        "e:A<s>": []
+    });
+    let expected = concat!(
+        r#"{"a:A<O>":[],"b:A<A<A<O>>>":[[],[[]]],"c:A<A<i>>":[[],["1"]],"#,
+        r#""d:S<O>":[],"e:A<s>":[]}"#
+    );
+    for config in vec![infer(), SerializeConfig::new()] {
+        assert_eq!(tjson::to_string_with(&config, &value).unwrap(), expected);
+    }
+
+    // Each reads back as a sequence of anything
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Read {
+        a: Vec<u64>,
+        b: Vec<Vec<Vec<String>>>,
+        c: Vec<Vec<i64>>,
+        d: Vec<bool>,
+        e: Vec<String>,
+    }
+    let read: Read = tjson::from_str(expected).unwrap();
+    assert_eq!(
+        read,
+        Read {
+            a: vec![],
+            b: vec![vec![], vec![vec![]]],
+            c: vec![vec![], vec![1]],
+            d: vec![],
+            e: vec![],
+        }
+    );
+    let parsed: Value = tjson::from_str(expected).unwrap();
+    assert!(parsed["d:S<O>"].is_set());
+}
+
+#[test]
+fn test_probe_allocations() {
+    #[derive(Serialize)]
+    struct Grid {
+        rows: Vec<Vec<u64>>,
+    }
+
+    // Elements with the tag of the ones before them keep it rather than
+    // working it out again, so tagging allocates per array, not per element
+    let narrow = Grid { rows: vec![vec![1; 10]; 100] };
+    let wide = Grid { rows: vec![vec![1; 1000]; 100] };
+    let narrow = alloc::allocations(|| {
+        tjson::to_writer(io::sink(), &narrow).unwrap();
+    });
+    let wide = alloc::allocations(|| {
+        tjson::to_writer(io::sink(), &wide).unwrap();
+    });
+    assert_eq!(wide, narrow);
+}
@@ -0,0 +1,221 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks the crate against a corpus of TJSON documents in `tests/interop`,
+//! covering every tag and binary encoding of the spec. The documents were
+//! transcribed by hand from the examples and rules of the spec, not
+//! generated by the reference Ruby or Go implementations. Each is written in
+//! canonical compact form, so it is both parsed and compared with this
+//! crate's output for the same value. The documents in `tests/interop/invalid` break a rule of the
+//! spec and must be rejected by a strict parser.
+//!
+//! Members are in name order, which is how this crate writes them without
+//! the `preserve_order` feature and how `tjson!` builds them with it.
+//!
+//! Where the crate doesn't follow the spec yet, the case is listed in one of
+//! the exception tables below along with the reason. Those cases are checked
+//! to still diverge, so a fix has to remove its exception.
+
+#[macro_use]
+extern crate tjson;
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+
+use tjson::{Set, Value};
+use tjson::de::{DuplicateElements, DuplicateKeys, Parser};
+
+/// Documents which parse to the given value
+fn valid() -> Vec<(&'static str, Value)> {
+    let hello = Value::Data(b"Hello, world!".to_vec().into());
+    let timestamp = "2016-10-02T07:31:51Z".parse().unwrap();
+    let mut set = Set::new();
+    for s in &["a", "b", "c"] {
+        set.insert(tjson!(s));
+    }
+
+    vec![
+        ("empty_array", tjson!([])),
+        ("empty_object", tjson!({})),
+        ("string", tjson!({ "example:s": "foobar" })),
+        ("unicode_string", tjson!({ "example:s": "Hello, \u{4e16}\u{754c} \u{1f600}" })),
+        ("escaped_string", tjson!({ "example:s": "tab\tquote\"backslash\\newline\n" })),
+        ("boolean", tjson!({ "f:b": false, "t:b": true })),
+        ("float", tjson!({ "example:f": 0.42 })),
+        ("signed_int", tjson!({ "example:i": -42 })),
+        ("signed_int_min", tjson!({ "example:i": i64::MIN })),
+        ("unsigned_int", tjson!({ "example:u": u64::MAX })),
+        ("binary", tjson!({ "example:d": hello.clone() })),
+        ("binary16", tjson!({ "example:d16": hello.clone() })),
+        ("binary32", tjson!({ "example:d32": hello.clone() })),
+        ("binary64", tjson!({ "example:d64": hello })),
+        ("timestamp", tjson!({ "example:t": Value::Timestamp(timestamp) })),
+        ("object", tjson!({ "example:O": { "a:s": "b", "c:b": true } })),
+        ("array_of_objects", tjson!([{ "a:s": "b" }, { "c:f": 1.5 }])),
+        ("nested_arrays", tjson!({ "example:A<A<s>>": [["a", "b"], []] })),
+        ("integer_array", tjson!({ "example:A<i>": [1, -2, 3] })),
+        ("set", tjson!({ "example:S<s>": Value::Set(set) })),
+    ]
+}
+
+/// Documents a strict parser must reject
+const INVALID: &[&str] = &[
+    "duplicate_name",
+    "toplevel_string",
+    "toplevel_number",
+    "untagged_name",
+    "unknown_tag",
+    "bare_integer",
+    "integer_overflow",
+    "padded_base64",
+    "uppercase_hex",
+    "offset_timestamp",
+    "duplicate_set_element",
+];
+
+/// Valid documents which `tjson::from_str` doesn't parse to the expected
+/// value
+const PARSE_EXCEPTIONS: &[(&str, &str)] = &[];
+
+/// Valid documents which `strict_parse` doesn't parse to the expected value
+const STRICT_PARSE_EXCEPTIONS: &[(&str, &str)] = &[];
+
+/// Valid documents which differ from the crate's output for their value
+const WRITE_EXCEPTIONS: &[(&str, &str)] = &[];
+
+/// Invalid documents which a strict parser accepts
+const REJECT_EXCEPTIONS: &[(&str, &str)] = &[];
+
+fn interop_dir() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("interop");
+    path
+}
+
+/// The document without the trailing newline of the file
+fn document(path: PathBuf) -> String {
+    let mut text = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    assert!(text.ends_with('\n'), "{}", path.display());
+    text.pop();
+    text
+}
+
+fn valid_document(name: &str) -> String {
+    document(interop_dir().join(format!("{}.tjson", name)))
+}
+
+fn invalid_document(name: &str) -> String {
+    document(interop_dir().join("invalid").join(format!("{}.tjson", name)))
+}
+
+/// Parses with every option which holds documents to the spec
+fn strict_parse(text: &str) -> tjson::Result<Value> {
+    Parser::new()
+        .duplicate_keys(DuplicateKeys::Error)
+        .duplicate_elements(DuplicateElements::Error)
+        .strict_tags(true)
+        .decode_data(true)
+        .parse_str(text)
+}
+
+fn is_exception(exceptions: &[(&str, &str)], name: &str) -> bool {
+    exceptions.iter().any(|&(exception, _)| exception == name)
+}
+
+#[test]
+fn test_interop_parse() {
+    for (name, expected) in valid() {
+        let text = valid_document(name);
+        let lenient: Value =
+            tjson::from_str(&text).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        let strict = strict_parse(&text).unwrap_or_else(|err| panic!("{}: {}", name, err));
+
+        let parses = [(lenient, PARSE_EXCEPTIONS), (strict, STRICT_PARSE_EXCEPTIONS)];
+        for &(ref value, exceptions) in &parses {
+            if is_exception(exceptions, name) {
+                assert!(
+                    *value != expected,
+                    "{} parses as expected now, remove its exception",
+                    name
+                );
+            } else {
+                assert_eq!(*value, expected, "{}", name);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_interop_write() {
+    for (name, value) in valid() {
+        let text = valid_document(name);
+        let output = tjson::to_string(&value).unwrap();
+
+        if is_exception(WRITE_EXCEPTIONS, name) {
+            assert!(output != text, "{} is written as expected now, remove its exception", name);
+        } else {
+            assert_eq!(output, text, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_interop_reject() {
+    for &name in INVALID {
+        let text = invalid_document(name);
+        let result = strict_parse(&text);
+
+        if is_exception(REJECT_EXCEPTIONS, name) {
+            assert!(result.is_ok(), "{} is rejected now, remove its exception", name);
+        } else {
+            assert!(result.is_err(), "{} was accepted as {:?}", name, result.unwrap());
+        }
+    }
+
+    // Relaxing the check keeps the last of the repeated members
+    let text = invalid_document("duplicate_name");
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::LastWins);
+    let value: Value = parser.parse_str(&text).unwrap();
+    assert_eq!(value, tjson!({ "a:s": "c" }));
+}
+
+#[test]
+fn test_interop_corpus_complete() {
+    let names = |dir: PathBuf| -> BTreeSet<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .map(|path| {
+                assert_eq!(path.extension().unwrap(), "tjson", "{}", path.display());
+                path.file_stem().unwrap().to_str().unwrap().to_owned()
+            })
+            .collect()
+    };
+
+    // Every document has a case, and the other way round
+    let valid_names: BTreeSet<_> = valid().into_iter().map(|(name, _)| name.to_owned()).collect();
+    assert_eq!(names(interop_dir()), valid_names);
+    let invalid_names: BTreeSet<_> = INVALID.iter().map(|&name| name.to_owned()).collect();
+    assert_eq!(names(interop_dir().join("invalid")), invalid_names);
+
+    // Every exception is for a case of the right kind
+    let valid_exceptions = PARSE_EXCEPTIONS.iter().chain(STRICT_PARSE_EXCEPTIONS);
+    for &(name, _) in valid_exceptions.chain(WRITE_EXCEPTIONS) {
+        assert!(valid_names.contains(name), "{}", name);
+    }
+    for &(name, _) in REJECT_EXCEPTIONS {
+        assert!(invalid_names.contains(name), "{}", name);
+    }
+}
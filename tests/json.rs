@@ -0,0 +1,107 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::{Set, Value};
+use tjson::json::{from_json, to_json};
+
+fn plain(input: &str) -> Value {
+    tjson::from_str(input).unwrap()
+}
+
+#[test]
+fn test_from_json_tags() {
+    let mut value = plain(
+        r#"{
+            "s": "x",
+            "b": true,
+            "i": -3,
+            "u": 18446744073709551615,
+            "f": 0.5,
+            "o": { "n": 1 },
+            "a": [[1], [2.5, 3]]
+        }"#,
+    );
+    // A name with a colon but no tag isn't read as plain JSON, yet a value
+    // may still hold one
+    value.as_object_mut().unwrap().insert("odd:name".to_owned(), tjson!("y"));
+    assert_eq!(
+        from_json(value).unwrap(),
+        tjson!({
+            "s:s": "x",
+            "b:b": true,
+            "i:i": -3,
+            "u:u": 18446744073709551615u64,
+            "f:f": 0.5,
+            "o:O": { "n:i": 1 },
+            "a:A<A<f>>": [[1.0], [2.5, 3.0]],
+            "odd:name:s": "y",
+        })
+    );
+}
+
+#[test]
+fn test_from_json_sets() {
+    let mut set = Set::new();
+    set.insert(tjson!(1));
+    set.insert(tjson!(2.5));
+    let tagged = from_json(tjson!({ "s": Value::Set(set) })).unwrap();
+
+    let mut expected = Set::new();
+    expected.insert(tjson!(1.0));
+    expected.insert(tjson!(2.5));
+    assert_eq!(tagged, tjson!({ "s:S<f>": Value::Set(expected) }));
+}
+
+#[test]
+fn test_from_json_errors() {
+    let err = from_json(plain(r#"{"a": [{"b": null}]}"#)).unwrap_err();
+    assert_eq!(err.to_string(), "TJSON has no null at /a/0/b");
+
+    let err = from_json(plain(r#"{"a": {"b": []}}"#)).unwrap_err();
+    assert_eq!(err.to_string(), "cannot infer the tag of the elements of an empty array at /a/b");
+
+    let err = from_json(plain(r#"{"a": [1, "x"]}"#)).unwrap_err();
+    assert_eq!(err.to_string(), "array has elements tagged `i` and `s` at /a");
+
+    let err = from_json(plain(r#"{"a": [[1], ["x"]]}"#)).unwrap_err();
+    assert_eq!(err.to_string(), "array has elements tagged `A<i>` and `A<s>` at /a");
+}
+
+#[test]
+fn test_to_json() {
+    let timestamp: Value = tjson::from_str(r#"{"t:t": "2017-03-04T05:06:07Z"}"#).unwrap();
+    let mut set = Set::new();
+    set.insert(tjson!(1));
+    let value = tjson!({
+        "s:s": "x",
+        "o:O": { "d:d": Value::Data(vec![0xfb, 0xff].into()), "t:t": timestamp["t:t"].clone() },
+        "set:S<i>": Value::Set(set),
+        "untagged": [true],
+    });
+    assert_eq!(
+        to_json(value).unwrap(),
+        tjson!({
+            "s": "x",
+            "o": { "d": "-_8", "t": "2017-03-04T05:06:07Z" },
+            "set": [1],
+            "untagged": [true],
+        })
+    );
+
+    let err = to_json(tjson!({ "o:O": { "a:s": "x", "a:i": 1 } })).unwrap_err();
+    assert!(err.to_string().ends_with(" have the same name at /o:O"));
+}
+
+#[test]
+fn test_round_trip() {
+    let value = plain(r#"{"a": [{"b": "c"}], "n": {"m": [1, 2]}}"#);
+    assert_eq!(to_json(from_json(value.clone()).unwrap()).unwrap(), value);
+}
@@ -0,0 +1,168 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+
+use tjson::{Map, Set, Value};
+use tjson::fs::{Format, SaveOptions};
+use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig};
+
+fn sorted(rule: KeySortRule) -> SerializeConfig<'static> {
+    SerializeConfig::new().key_order(KeyOrder::Sorted(rule))
+}
+
+/// A document whose members are in a different order under each rule.
+fn disputed() -> Value {
+    tjson!({
+        "a-b:s": "hyphen",
+        "a:s": "plain",
+        "a0:i": 0,
+        "a:O": { "z.y:s": "", "z:i": 1 },
+    })
+}
+
+#[test]
+fn test_rules_differ() {
+    let value = disputed();
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &value).unwrap(),
+        r#"{"a:O":{"z:i":"1","z.y:s":""},"a:s":"plain","a-b:s":"hyphen","a0:i":"0"}"#
+    );
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::TaggedName), &value).unwrap(),
+        r#"{"a-b:s":"hyphen","a0:i":"0","a:O":{"z.y:s":"","z:i":"1"},"a:s":"plain"}"#
+    );
+}
+
+#[test]
+fn test_compare() {
+    let untagged = KeySortRule::UntaggedName;
+    // Names first, then tags
+    assert_eq!(untagged.compare("b:i", "a:s"), Ordering::Greater);
+    assert_eq!(untagged.compare("a:s", "a:O"), Ordering::Greater);
+    assert_eq!(untagged.compare("a:A<i>", "a:A<s>"), Ordering::Less);
+    // Only the last `:` starts the tag
+    assert_eq!(untagged.compare("a:b:s", "a:s"), Ordering::Greater);
+    assert_eq!(untagged.compare("a:b:s", "a;:s"), Ordering::Less);
+    // Untagged names sort as if their tag were empty
+    assert_eq!(untagged.compare("a", "a:s"), Ordering::Less);
+    assert_eq!(untagged.compare("a:s", "a:s"), Ordering::Equal);
+    // Code points, not UTF-16 code units
+    assert_eq!(untagged.compare("\u{ff61}:s", "\u{1f600}:s"), Ordering::Less);
+    assert_eq!(untagged.compare("\u{e9}:s", "z:s"), Ordering::Greater);
+
+    assert_eq!(KeySortRule::TaggedName.compare("a:s", "a-b:s"), Ordering::Greater);
+    assert_eq!(KeySortRule::TaggedName.compare("b:i", "a:s"), Ordering::Greater);
+}
+
+#[test]
+fn test_nested_and_sets() {
+    let mut set = Set::new();
+    set.insert(tjson!({ "b:i": 1, "a:i": 2 }));
+    let value = tjson!({ "x:A<O>": [{ "y-z:s": "", "y:s": "" }], "s:S<O>": Value::Set(set) });
+
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &value).unwrap(),
+        r#"{"s:S<O>":[{"a:i":"2","b:i":"1"}],"x:A<O>":[{"y:s":"","y-z:s":""}]}"#
+    );
+}
+
+#[test]
+fn test_as_given() {
+    #[derive(Serialize)]
+    struct Record {
+        #[serde(rename = "b:s")]
+        b: &'static str,
+        #[serde(rename = "a:s")]
+        a: &'static str,
+    }
+
+    let record = Record { b: "1", a: "2" };
+    let config = SerializeConfig::new().key_order(KeyOrder::AsGiven);
+    assert_eq!(tjson::to_string_with(&config, &record).unwrap(), r#"{"b:s":"1","a:s":"2"}"#);
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &record).unwrap(),
+        r#"{"a:s":"2","b:s":"1"}"#
+    );
+}
+
+#[test]
+fn test_same_with_either_map() {
+    // Inserted in an order neither rule uses, so that with `preserve_order`
+    // the map's own order would show, and without it the map's string order
+    // would show for the untagged rule
+    let mut map = Map::new();
+    map.insert("a0:i".to_owned(), tjson!(0));
+    for name in &["a:s", "a-b:s"] {
+        map.insert(name.to_string(), tjson!("0"));
+    }
+    let value = Value::Object(map);
+
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &value).unwrap(),
+        r#"{"a:s":"0","a-b:s":"0","a0:i":"0"}"#
+    );
+    assert_eq!(
+        tjson::to_string_with(&sorted(KeySortRule::TaggedName), &value).unwrap(),
+        r#"{"a-b:s":"0","a0:i":"0","a:s":"0"}"#
+    );
+}
+
+#[test]
+fn test_canonical_save() {
+    let dir = env::temp_dir().join(format!("tjson-key-order-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("canonical.tjson");
+
+    let options = SaveOptions::new().format(Format::Canonical);
+    tjson::fs::save(&path, &disputed(), &options).unwrap();
+    let untagged = fs::read_to_string(&path).unwrap();
+    let expected = tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &disputed()).unwrap();
+    assert_eq!(untagged, expected);
+
+    let options = options.key_sort_rule(KeySortRule::TaggedName);
+    tjson::fs::save(&path, &disputed(), &options).unwrap();
+    let tagged = fs::read_to_string(&path).unwrap();
+    let expected = tjson::to_string_with(&sorted(KeySortRule::TaggedName), &disputed()).unwrap();
+    assert_eq!(tagged, expected);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_interop_corpus() {
+    // The reference implementations write members in canonical order, so
+    // each document of the corpus is written back unchanged
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/interop");
+    let mut checked = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+        // Data is written as an array of byte values, see tests/interop.rs
+        if path.extension().map_or(true, |ext| ext != "tjson") || name.starts_with("binary") {
+            continue;
+        }
+        let mut text = String::new();
+        File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+        let value: Value = tjson::from_str(&text).unwrap();
+
+        let output = tjson::to_string_with(&sorted(KeySortRule::UntaggedName), &value).unwrap();
+        assert_eq!(output, text.trim_end(), "{}", name);
+        checked += 1;
+    }
+    assert!(checked > 10);
+}
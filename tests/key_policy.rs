@@ -0,0 +1,180 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use tjson::{Deserializer, Map, Value};
+use tjson::de::Parser;
+use tjson::error::ErrorCode;
+use tjson::map::{Entry, KeyPolicy};
+
+const POLICIES: &[KeyPolicy] = &[
+    KeyPolicy::AnyUnicode,
+    KeyPolicy::NoControlChars,
+    KeyPolicy::Identifier,
+];
+
+fn allows(policy: KeyPolicy, key: &str) -> bool {
+    policy.check(key).is_ok()
+}
+
+#[test]
+fn test_nul_is_always_rejected() {
+    for &policy in POLICIES {
+        for key in &["\0", "a\0", "\0:s", "name:s\0"] {
+            let err = policy.check(key).unwrap_err();
+            assert_eq!(err.key(), *key);
+            assert_eq!(err.policy(), policy);
+            assert!(err.to_string().ends_with(" contains NUL"), "{}", err);
+        }
+    }
+}
+
+#[test]
+fn test_any_unicode() {
+    for key in &["", "name:s", "with space", "line\nbreak\u{7f}", "😀:s", ":", "\u{fffd}"] {
+        assert!(allows(KeyPolicy::AnyUnicode, key), "{:?}", key);
+    }
+    assert_eq!(KeyPolicy::default(), KeyPolicy::AnyUnicode);
+}
+
+#[test]
+fn test_no_control_chars() {
+    for key in &["", "name:s", "with space", "😀:s", "tab\\t"] {
+        assert!(allows(KeyPolicy::NoControlChars, key), "{:?}", key);
+    }
+    for key in &["line\nbreak", "tab\t", "\u{7f}", "\u{85}:s"] {
+        let err = KeyPolicy::NoControlChars.check(key).unwrap_err();
+        assert_eq!(err.to_string(), format!("member name {:?} contains a control character", key));
+    }
+}
+
+#[test]
+fn test_identifier() {
+    for key in &["a", "_", "user_id", "userId:s", "_x9:A<O>", "ids:S<A<i>>", "x:d16"] {
+        assert!(allows(KeyPolicy::Identifier, key), "{:?}", key);
+    }
+    for key in &["", ":s", "9lives:i", "user id:s", "user-id", "a:", "a:A<i> ", "😀:s", "a:b:s"] {
+        let err = KeyPolicy::Identifier.check(key).unwrap_err();
+        assert_eq!(err.to_string(), format!("member name {:?} is not an identifier", key));
+    }
+}
+
+#[test]
+fn test_try_insert() {
+    let mut map = Map::new();
+    assert_eq!(map.try_insert("a:i".to_owned(), tjson!(1), KeyPolicy::Identifier), Ok(None));
+    assert_eq!(
+        map.try_insert("a:i".to_owned(), tjson!(2), KeyPolicy::Identifier),
+        Ok(Some(tjson!(1)))
+    );
+    assert!(map.try_insert("b i".to_owned(), tjson!(3), KeyPolicy::Identifier).is_err());
+    assert!(map.try_insert("b\0".to_owned(), tjson!(3), KeyPolicy::AnyUnicode).is_err());
+    assert_eq!(map.len(), 1);
+
+    // `insert` takes anything
+    map.insert("b\0".to_owned(), tjson!(3));
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_try_entry() {
+    let mut map = Map::new();
+    match map.try_entry("count:i", KeyPolicy::NoControlChars).unwrap() {
+        Entry::Vacant(vacant) => {
+            vacant.insert(tjson!(1));
+        }
+        Entry::Occupied(_) => unreachable!(),
+    }
+    *map.try_entry("count:i", KeyPolicy::Identifier).unwrap().or_insert(tjson!(0)) =
+        tjson!(2);
+    assert_eq!(map["count:i"], 2);
+
+    let err = map.try_entry("count\n:i", KeyPolicy::NoControlChars).err().unwrap();
+    assert_eq!(err.key(), "count\n:i");
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_macro_runtime_check() {
+    // A key which isn't a literal can't be checked until it is inserted
+    let key = "a\0b";
+    let result = std::panic::catch_unwind(|| tjson!({ key: 1 }));
+    assert!(result.is_err());
+
+    fn build(key: &str) -> tjson::Result<Value> {
+        try_tjson!({ key: 1, "ok": 2 })
+    }
+    let err = build(key).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidMemberName);
+    assert_eq!(err.to_string(), "member name \"a\\0b\" contains NUL");
+    assert_eq!(build("a b").unwrap(), tjson!({ "a b": 1, "ok": 2 }));
+
+    // Literals with escapes which aren't NUL are fine
+    let value = tjson!({ "\\0": 1, "\x01": 2, "\u{100}": 3, r"\0": 4 });
+    assert_eq!(value.as_object().unwrap().len(), 3);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct User {
+    #[serde(rename = "userId:u")]
+    user_id: u64,
+}
+
+#[test]
+fn test_deserializer() {
+    // Not checked by default
+    let input = r#"{"a\u0000": 1, "b c": 2}"#;
+    let value: Value = tjson::from_str(input).unwrap();
+    assert_eq!(value.as_object().unwrap().len(), 2);
+
+    let mut de = Deserializer::from_str(input).key_policy(KeyPolicy::AnyUnicode);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidMemberName);
+    assert!(err.is_data());
+    assert_eq!(err.to_string(), "member name \"a\\0\" contains NUL at line 1 column 10");
+
+    // Maps and structs as well as values
+    let mut de = Deserializer::from_str(r#"{"b c": 2}"#).key_policy(KeyPolicy::Identifier);
+    assert!(BTreeMap::<String, u64>::deserialize(&mut de).is_err());
+
+    let mut de = Deserializer::from_str(r#"{"userId:u": "7"}"#).key_policy(KeyPolicy::Identifier);
+    assert_eq!(User::deserialize(&mut de).unwrap(), User { user_id: 7 });
+    let input = r#"{"userId:u": "7", "extra\n": 1}"#;
+    let mut de = Deserializer::from_str(input).key_policy(KeyPolicy::NoControlChars);
+    let err = User::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidMemberName);
+
+    let mut parser = Parser::new().key_policy(KeyPolicy::Identifier);
+    assert!(parser.parse_str::<Value>(r#"[{"ok": 1}]"#).is_ok());
+    assert!(parser.parse_str::<Value>(r#"[{"ok": {"not ok": 1}}]"#).is_err());
+}
+
+#[test]
+fn test_unusual_keys_round_trip() {
+    let mut map = Map::new();
+    for key in &["😀:s", "with space:s", " :s", "ünïcödé:s", "\u{200b}:s"] {
+        map.try_insert(key.to_string(), tjson!("x"), KeyPolicy::NoControlChars).unwrap();
+    }
+    let value = Value::Object(map);
+
+    let output = tjson::to_string(&value).unwrap();
+    let mut de = Deserializer::from_str(&output).key_policy(KeyPolicy::NoControlChars);
+    assert_eq!(Value::deserialize(&mut de).unwrap(), value);
+
+    let pretty = tjson::to_string_pretty(&value).unwrap();
+    let back: Value = tjson::from_str(&pretty).unwrap();
+    assert_eq!(back, value);
+}
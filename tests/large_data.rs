@@ -0,0 +1,134 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reads and writes a document with 100MB of data through the event API,
+//! checking that memory use doesn't grow with the data.
+
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use tjson::encoding;
+use tjson::event::{self, Event};
+
+const DATA_LEN: usize = 100 * 1024 * 1024;
+
+/// Bytes of the data, in blocks whose base64url encodings can be joined.
+const BLOCK: usize = 3 * 1024;
+
+fn data_byte(index: usize) -> u8 {
+    (index.wrapping_mul(2_654_435_761) >> 13) as u8
+}
+
+/// Produces `{"blob:d":"<base64url of DATA_LEN bytes>"}` without ever
+/// holding more than a block of it.
+struct Document {
+    encoded: Vec<u8>,
+    pos: usize,
+    /// Bytes of the data encoded so far.
+    data: usize,
+    ended: bool,
+}
+
+impl Document {
+    fn new() -> Self {
+        Document {
+            encoded: br#"{"blob:d":""#.to_vec(),
+            pos: 0,
+            data: 0,
+            ended: false,
+        }
+    }
+}
+
+impl Read for Document {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.encoded.len() {
+            self.pos = 0;
+            self.encoded.clear();
+            if self.data < DATA_LEN {
+                let end = cmp::min(self.data + BLOCK, DATA_LEN);
+                let block: Vec<u8> = (self.data..end).map(data_byte).collect();
+                self.encoded.extend_from_slice(encoding::b64url_encode(&block).as_bytes());
+                self.data = end;
+            } else if !self.ended {
+                self.encoded.extend_from_slice(br#""}"#);
+                self.ended = true;
+            } else {
+                return Ok(0);
+            }
+        }
+        let n = cmp::min(buf.len(), self.encoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.encoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Checks that what is written is the same as another `Document`.
+struct Compare {
+    expected: Document,
+    written: usize,
+}
+
+impl Write for Compare {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut expected = vec![0; buf.len()];
+        self.expected.read_exact(&mut expected).unwrap();
+        assert!(expected == buf, "output differs after {} bytes", self.written);
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_data_larger_than_memory_budget() {
+    let mut data_len = 0;
+    let mut mismatch = None;
+    let mut compare = Compare {
+        expected: Document::new(),
+        written: 0,
+    };
+    let peak = alloc::peak_bytes(
+        || {
+            let events = event::parse_reader(io::BufReader::new(Document::new()))
+                .chunked_data(64 * 1024)
+                .map(|event| {
+                    let event = event.unwrap();
+                    if let Event::DataChunk(ref chunk) = event {
+                        for (i, &byte) in chunk.iter().enumerate() {
+                            if mismatch.is_none() && byte != data_byte(data_len + i) {
+                                mismatch = Some(data_len + i);
+                            }
+                        }
+                        data_len += chunk.len();
+                    }
+                    event
+                });
+            event::write(&mut compare, events).unwrap();
+        },
+    );
+
+    assert_eq!(mismatch, None);
+    assert_eq!(data_len, DATA_LEN);
+    assert_eq!(compare.written, 11 + (DATA_LEN * 4 + 2) / 3 + 2);
+    let mut rest = Vec::new();
+    compare.expected.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+
+    // Chunks, buffers and blocks of the document, but nowhere near the data
+    assert!(peak < 1024 * 1024, "peak of {} bytes allocated", peak);
+}
@@ -0,0 +1,144 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::value::Metric;
+
+const DOCUMENT: &str = r#"{
+    "items:A<O>": [
+        {"id:u": "1", "a_rather_long_name:s": "x", "c:s": "y"},
+        {"id:u": "2"}
+    ],
+    "nested:O": {"deeper:O": {"deepest:A<A<u>>": [["1"], ["2"]]}},
+    "another_long_name:s": "z"
+}"#;
+
+fn warnings(de: &Deserializer<tjson::de::StrRead>) -> Vec<(Metric, String, usize, usize)> {
+    de.warnings()
+        .as_slice()
+        .iter()
+        .map(|w| (w.metric(), w.path().unwrap().to_owned(), w.actual(), w.limit()))
+        .collect()
+}
+
+#[test]
+fn test_warnings_at_several_paths() {
+    let mut de = Deserializer::from_str(DOCUMENT)
+        .warn_member_count(2)
+        .warn_key_length(16)
+        .warn_depth(3);
+    let value = Value::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    assert_eq!(value["items:A<O>"][1]["id:u"], 2);
+
+    assert_eq!(
+        warnings(&de),
+        vec![
+            (Metric::NameLen, "/items:A<O>/0/a_rather_long_name:s".to_owned(), 20, 16),
+            (Metric::ContainerLen, "/items:A<O>/0".to_owned(), 3, 2),
+            (Metric::Depth, "/nested:O/deeper:O/deepest:A<A<u>>".to_owned(), 4, 3),
+            (Metric::NameLen, "/another_long_name:s".to_owned(), 19, 16),
+            (Metric::ContainerLen, "".to_owned(), 3, 2),
+        ]
+    );
+    assert_eq!(de.warnings().omitted(), 0);
+    assert_eq!(
+        de.warnings().as_slice()[1].to_string(),
+        "container length 3 exceeds the limit of 2 at /items:A<O>/0"
+    );
+}
+
+#[test]
+fn test_warnings_do_not_fail_structs() {
+    #[derive(Deserialize)]
+    struct Only {
+        #[serde(rename = "another_long_name:s")]
+        name: String,
+    }
+
+    // Members which are skipped are still measured
+    let mut de = Deserializer::from_str(DOCUMENT).warn_key_length(16);
+    let only = Only::deserialize(&mut de).unwrap();
+    assert_eq!(only.name, "z");
+    assert_eq!(de.warnings().as_slice().len(), 2);
+}
+
+#[test]
+fn test_warning_cap() {
+    let input = format!("[{}]", vec!["[[]]"; 5].join(","));
+    let mut de = Deserializer::from_str(&input).warn_depth(2).max_warnings(2);
+    Value::deserialize(&mut de).unwrap();
+
+    let paths: Vec<_> = warnings(&de).into_iter().map(|(_, path, _, _)| path).collect();
+    assert_eq!(paths, vec!["/0/0", "/1/0"]);
+    assert_eq!(de.warnings().omitted(), 3);
+    assert_eq!(
+        de.warnings().to_string(),
+        "depth 3 exceeds the limit of 2 at /0/0\n\
+         depth 3 exceeds the limit of 2 at /1/0\n\
+         3 more"
+    );
+
+    let mut de = Deserializer::from_str(&input).warn_depth(2).max_warnings(0);
+    Value::deserialize(&mut de).unwrap();
+    assert!(de.warnings().as_slice().is_empty());
+    assert!(!de.warnings().is_empty());
+    assert_eq!(de.warnings().to_string(), "5 more");
+}
+
+#[test]
+fn test_no_warnings_without_options() {
+    let mut de = Deserializer::from_str(DOCUMENT);
+    Value::deserialize(&mut de).unwrap();
+    assert!(de.warnings().is_empty());
+    assert_eq!(de.warnings().to_string(), "");
+
+    let mut de = Deserializer::from_str(DOCUMENT)
+        .warn_member_count(3)
+        .warn_key_length(20)
+        .warn_depth(5);
+    Value::deserialize(&mut de).unwrap();
+    assert!(de.warnings().is_empty(), "{}", de.warnings());
+}
+
+#[test]
+fn test_warning_cost() {
+    let mut input = String::from("[");
+    for i in 0..2000 {
+        if i > 0 {
+            input.push(',');
+        }
+        input.push_str(&format!(r#"{{"id:u": "{}", "tags:A<s>": ["a", "b"], "n:O": {{}}}}"#, i));
+    }
+    input.push(']');
+
+    // Without options the checks are a branch on each container and member,
+    // and with limits nothing goes over they only measure, so they allocate
+    // nothing more
+    let parse = |warn: bool| {
+        let mut de = Deserializer::from_str(&input);
+        if warn {
+            de = de.warn_member_count(10).warn_key_length(64).warn_depth(8);
+        }
+        drop(Value::deserialize(&mut de).unwrap());
+        assert!(de.warnings().is_empty());
+    };
+    let plain = alloc::allocations(|| parse(false));
+    let warned = alloc::allocations(|| parse(true));
+    assert!(plain > 0);
+    assert_eq!(warned, plain);
+}
@@ -0,0 +1,248 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::borrow::Cow;
+
+use tjson::{Set, Value};
+use tjson::value::ValueKind;
+
+const KINDS: [ValueKind; 9] = [
+    ValueKind::Undefined,
+    ValueKind::Bool,
+    ValueKind::Data,
+    ValueKind::Number,
+    ValueKind::String,
+    ValueKind::Timestamp,
+    ValueKind::Array,
+    ValueKind::Set,
+    ValueKind::Object,
+];
+
+const AT: &str = "2017-03-04T05:06:07Z";
+
+fn timestamp() -> Value {
+    tjson::from_str::<Value>(&format!(r#"{{"at:t": "{}"}}"#, AT)).unwrap()["at:t"].clone()
+}
+
+fn set() -> Value {
+    let mut set = Set::new();
+    set.insert(tjson!(1));
+    set.insert(tjson!(2));
+    Value::Set(set)
+}
+
+/// Checks one row of the table: what `value` converts to for each of
+/// `KINDS`, with `None` for each kind not listed.
+fn assert_row(value: Value, expected: Vec<(ValueKind, Value)>) {
+    let before = value.clone();
+    for &kind in &KINDS {
+        let cell = expected.iter().find(|&&(k, _)| k == kind).map(|&(_, ref v)| v.clone());
+        assert_eq!(value.coerce_lossy(kind), cell, "{:?} as {}", value, kind);
+    }
+    assert_eq!(value, before);
+}
+
+#[test]
+fn test_undefined() {
+    assert_row(Value::Undefined, vec![(ValueKind::Undefined, Value::Undefined)]);
+}
+
+#[test]
+fn test_bool() {
+    assert_row(
+        tjson!(true),
+        vec![(ValueKind::Bool, tjson!(true)), (ValueKind::String, tjson!("true"))],
+    );
+    assert_row(
+        tjson!(false),
+        vec![(ValueKind::Bool, tjson!(false)), (ValueKind::String, tjson!("false"))],
+    );
+}
+
+#[test]
+fn test_data() {
+    assert_row(
+        Value::Data(vec![0xfb, 0xff].into()),
+        vec![
+            (ValueKind::Data, Value::Data(vec![0xfb, 0xff].into())),
+            (ValueKind::String, tjson!("-_8")),
+        ],
+    );
+}
+
+#[test]
+fn test_number() {
+    assert_row(
+        tjson!(1.5),
+        vec![(ValueKind::Number, tjson!(1.5)), (ValueKind::String, tjson!("1.5"))],
+    );
+    assert_row(
+        tjson!(-7),
+        vec![(ValueKind::Number, tjson!(-7)), (ValueKind::String, tjson!("-7"))],
+    );
+    assert_row(
+        tjson!(2.0),
+        vec![(ValueKind::Number, tjson!(2.0)), (ValueKind::String, tjson!("2.0"))],
+    );
+}
+
+#[test]
+fn test_string() {
+    assert_row(tjson!("x"), vec![(ValueKind::String, tjson!("x"))]);
+    assert_row(
+        tjson!("42"),
+        vec![(ValueKind::Number, tjson!(42)), (ValueKind::String, tjson!("42"))],
+    );
+    assert_row(
+        tjson!("-0.25"),
+        vec![(ValueKind::Number, tjson!(-0.25)), (ValueKind::String, tjson!("-0.25"))],
+    );
+    for &(text, b) in &[("true", true), ("1", true), ("false", false), ("0", false)] {
+        let mut row = vec![(ValueKind::Bool, tjson!(b)), (ValueKind::String, tjson!(text))];
+        if text.len() == 1 {
+            row.push((ValueKind::Number, tjson!(if b { 1 } else { 0 })));
+        }
+        assert_row(tjson!(text), row);
+    }
+    assert_row(
+        tjson!(AT),
+        vec![(ValueKind::Timestamp, timestamp()), (ValueKind::String, tjson!(AT))],
+    );
+    // Converted to UTC
+    assert_row(
+        tjson!("2017-03-04T06:06:07+01:00"),
+        vec![
+            (ValueKind::Timestamp, timestamp()),
+            (ValueKind::String, tjson!("2017-03-04T06:06:07+01:00")),
+        ],
+    );
+
+    // Only exactly these spellings are booleans, and only whole strings
+    // are parsed
+    for text in &["True", "yes", "", "1 2", "[1]", "0x10", "2017-03-04"] {
+        assert_row(tjson!(*text), vec![(ValueKind::String, tjson!(*text))]);
+    }
+}
+
+#[test]
+fn test_timestamp() {
+    assert_row(
+        timestamp(),
+        vec![(ValueKind::Timestamp, timestamp()), (ValueKind::String, tjson!(AT))],
+    );
+}
+
+#[test]
+fn test_array() {
+    assert_row(
+        tjson!([1, "a"]),
+        vec![(ValueKind::Array, tjson!([1, "a"])), (ValueKind::String, tjson!(r#"[1,"a"]"#))],
+    );
+    assert_row(tjson!([]), vec![(ValueKind::Array, tjson!([])), (ValueKind::String, tjson!("[]"))]);
+
+    // An array of one element converts as the element does
+    assert_row(
+        tjson!(["5"]),
+        vec![
+            (ValueKind::Array, tjson!(["5"])),
+            (ValueKind::Number, tjson!(5)),
+            (ValueKind::String, tjson!("5")),
+        ],
+    );
+    assert_row(
+        tjson!([[true]]),
+        vec![
+            (ValueKind::Array, tjson!([[true]])),
+            (ValueKind::Bool, tjson!(true)),
+            (ValueKind::String, tjson!("true")),
+        ],
+    );
+    assert_row(
+        tjson!([{}]),
+        vec![
+            (ValueKind::Array, tjson!([{}])),
+            (ValueKind::Object, tjson!({})),
+            (ValueKind::String, tjson!("{}")),
+        ],
+    );
+    assert_row(
+        tjson!([Value::Undefined]),
+        vec![
+            (ValueKind::Undefined, Value::Undefined),
+            (ValueKind::Array, tjson!([Value::Undefined])),
+        ],
+    );
+}
+
+#[test]
+fn test_set() {
+    assert_row(set(), vec![(ValueKind::Set, set()), (ValueKind::String, tjson!("[1,2]"))]);
+}
+
+#[test]
+fn test_object() {
+    assert_row(
+        tjson!({ "a:u": 1 }),
+        vec![
+            (ValueKind::Object, tjson!({ "a:u": 1 })),
+            (ValueKind::String, tjson!(r#"{"a:u":"1"}"#)),
+        ],
+    );
+}
+
+#[test]
+fn test_as_str_lossy() {
+    let value = tjson!("x");
+    match value.as_str_lossy() {
+        Some(Cow::Borrowed(text)) => assert_eq!(text, "x"),
+        other => panic!("{:?}", other),
+    }
+    match tjson!([["y"]]).as_str_lossy() {
+        Some(Cow::Borrowed(text)) => assert_eq!(text, "y"),
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(tjson!(1.5).as_str_lossy().unwrap(), "1.5");
+    assert_eq!(timestamp().as_str_lossy().unwrap(), AT);
+    assert_eq!(tjson!({}).as_str_lossy().unwrap(), "{}");
+    assert_eq!(Value::Undefined.as_str_lossy(), None);
+}
+
+#[test]
+fn test_as_i64_lossy() {
+    assert_eq!(tjson!(-3).as_i64_lossy(), Some(-3));
+    assert_eq!(tjson!(3.0).as_i64_lossy(), Some(3));
+    assert_eq!(tjson!(-9223372036854775808.0).as_i64_lossy(), Some(i64::MIN));
+    assert_eq!(tjson!("12").as_i64_lossy(), Some(12));
+    assert_eq!(tjson!("12.0").as_i64_lossy(), Some(12));
+    assert_eq!(tjson!([["4"]]).as_i64_lossy(), Some(4));
+
+    assert_eq!(tjson!(3.5).as_i64_lossy(), None);
+    assert_eq!(tjson!(9223372036854775808.0).as_i64_lossy(), None);
+    assert_eq!(tjson!(1e300).as_i64_lossy(), None);
+    assert_eq!(tjson!(u64::MAX).as_i64_lossy(), None);
+    assert_eq!(tjson!("twelve").as_i64_lossy(), None);
+    assert_eq!(tjson!(true).as_i64_lossy(), None);
+    assert_eq!(tjson!([1, 2]).as_i64_lossy(), None);
+    assert_eq!(Value::Undefined.as_i64_lossy(), None);
+}
+
+#[test]
+fn test_as_bool_lossy() {
+    assert_eq!(tjson!(true).as_bool_lossy(), Some(true));
+    assert_eq!(tjson!("1").as_bool_lossy(), Some(true));
+    assert_eq!(tjson!("false").as_bool_lossy(), Some(false));
+    assert_eq!(tjson!(["0"]).as_bool_lossy(), Some(false));
+
+    assert_eq!(tjson!(1).as_bool_lossy(), None);
+    assert_eq!(tjson!("TRUE").as_bool_lossy(), None);
+    assert_eq!(tjson!({}).as_bool_lossy(), None);
+}
@@ -0,0 +1,103 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "mmap")]
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use tjson::{MappedDocument, Value};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Entry<'a> {
+    name: &'a str,
+    size: u64,
+}
+
+/// A file in the temp directory which is removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &[u8]) -> TempFile {
+        let path = env::temp_dir().join(format!("tjson-mmap-{}-{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(contents).unwrap();
+        TempFile(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn test_from_file_mmap_matches_from_reader() {
+    let entries: Vec<Value> = (0..20_000)
+        .map(|i| {
+            tjson!({ "name:s": format!("entry \"{}\"", i), "size:i": i * 31, "ok:b": i % 3 == 0 })
+        })
+        .collect();
+    let file = TempFile::new("large", &tjson::to_vec(&entries).unwrap());
+
+    let mapped: Value = tjson::from_file_mmap(&file.0).unwrap();
+    let read: Value = tjson::from_reader(File::open(&file.0).unwrap()).unwrap();
+
+    assert_eq!(mapped, read);
+    assert_eq!(mapped, Value::Array(entries));
+}
+
+#[test]
+fn test_mapped_document_borrows() {
+    let file = TempFile::new("borrow", br#"[{"name": "a", "size": 1}, {"name": "b", "size": 2}]"#);
+    let document = MappedDocument::open(&file.0).unwrap();
+    assert_eq!(document.path(), file.0.as_path());
+
+    let bytes = document.as_bytes();
+    let entries: Vec<Entry> = document.get().unwrap();
+    assert_eq!(entries, [Entry { name: "a", size: 1 }, Entry { name: "b", size: 2 }]);
+
+    let start = bytes.as_ptr() as usize;
+    let name = entries[0].name.as_ptr() as usize;
+    assert!(name >= start && name < start + bytes.len());
+
+    assert_eq!(document.value().unwrap()[1]["size"], tjson!(2));
+}
+
+#[test]
+fn test_mmap_errors_include_path() {
+    let file = TempFile::new("invalid", b"{\"name\": \"\xff\"}");
+    let err = tjson::from_file_mmap::<Value, _>(&file.0).unwrap_err();
+
+    assert_eq!(err.path(), Some(file.0.as_path()));
+    assert_eq!(
+        err.to_string(),
+        format!("{}: invalid unicode code point at line 1 column 12", file.0.display())
+    );
+    assert_eq!(err.line(), 1);
+
+    let file = TempFile::new("empty", b"");
+    let err = MappedDocument::open(&file.0).unwrap().value().unwrap_err();
+    assert!(err.is_eof());
+    assert_eq!(err.path(), Some(file.0.as_path()));
+
+    let missing = env::temp_dir().join("tjson-mmap-does-not-exist");
+    let err = tjson::from_file_mmap::<Value, _>(&missing).unwrap_err();
+    assert!(err.is_io());
+    assert!(err.to_string().starts_with(&format!("{}: ", missing.display())));
+
+    let err = tjson::from_str::<Value>("[").unwrap_err();
+    assert_eq!(err.path(), None);
+}
@@ -0,0 +1,229 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tjson::{DateTime, Value};
+use tjson::de::DuplicateKeys;
+use tjson::error::ErrorCode;
+use tjson::json::from_json;
+
+/// The document from the crate documentation in `src/lib.rs`
+const DOC_EXAMPLE: &str = r#"{
+  "array-example:A<O>": [
+    {
+      "string-example:s": "foobar",
+      "binary-example:d": "QklOQVJZ",
+      "float-example:f": 0.42,
+      "int-example:i": "42",
+      "timestamp-example:t": "2016-11-06T22:27:34Z",
+      "boolean-example:b": true
+    }
+  ],
+  "set-example:S<i>": ["1", "2", "3"]
+}"#;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DocExample {
+    array_example: Vec<DocElement>,
+    #[serde(with = "tjson::set")]
+    set_example: BTreeSet<i64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DocElement {
+    string_example: String,
+    #[serde(with = "serde_bytes")]
+    binary_example: Vec<u8>,
+    float_example: f64,
+    int_example: i64,
+    #[serde(with = "tjson::timestamp")]
+    timestamp_example: DateTime,
+    boolean_example: bool,
+}
+
+/// The untagged document from the `tjson::value` module documentation
+const VALUE_DOC_EXAMPLE: &str = r#"{
+                    "name": "John Doe",
+                    "age": 43,
+                    "phones": [
+                      "+44 1234567",
+                      "+44 2345678"
+                    ]
+                  }"#;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Person {
+    name: String,
+    age: u8,
+    phones: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Example {
+    #[serde(rename = "array-example:A<O>")]
+    array_example: Vec<Member>,
+    #[serde(rename = "string-example:s")]
+    string_example: String,
+    favourite_colour: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Member {
+    #[serde(rename = "dotted.name:s")]
+    dotted: String,
+    #[serde(rename = "with space:s")]
+    spaced: String,
+    #[serde(rename = "naïve-café:s")]
+    accented: String,
+    #[serde(rename = "a:b:s")]
+    colons: String,
+    #[serde(rename = "host:port")]
+    address: String,
+}
+
+fn compact(text: &str) -> String {
+    tjson::to_string(&tjson::from_str::<Value>(text).unwrap()).unwrap()
+}
+
+#[test]
+fn test_doc_example_round_trips_through_struct() {
+    // Kept in step with the crate documentation
+    let documented = DOC_EXAMPLE.lines().map(|line| format!("//! {}", line));
+    let documented = documented.collect::<Vec<_>>().join("\n");
+    assert!(include_str!("../src/lib.rs").contains(&documented));
+
+    let example: DocExample = tjson::from_str(DOC_EXAMPLE).unwrap();
+    assert_eq!(example.array_example[0].binary_example, b"BINARY");
+    assert_eq!(example.array_example[0].int_example, 42);
+    assert_eq!(example.set_example.iter().collect::<Vec<_>>(), [&1, &2, &3]);
+
+    // The tags are inferred from the types, and give back the document
+    // byte for byte once its whitespace is dropped
+    let written = tjson::to_string(&example).unwrap();
+    assert_eq!(written, DOC_EXAMPLE.split_whitespace().collect::<String>());
+    assert_eq!(tjson::from_str::<DocExample>(&written).unwrap(), example);
+    assert_eq!(
+        tjson::from_str::<Value>(&written).unwrap(),
+        tjson::from_str::<Value>(DOC_EXAMPLE).unwrap()
+    );
+}
+
+#[test]
+fn test_value_doc_example_round_trips_through_struct() {
+    let person: Person = tjson::from_str(VALUE_DOC_EXAMPLE).unwrap();
+    assert_eq!(person.phones, ["+44 1234567", "+44 2345678"]);
+
+    let written = tjson::to_string(&person).unwrap();
+    assert_eq!(
+        written,
+        r#"{"name:s":"John Doe","age:u":"43","phones:A<s>":["+44 1234567","+44 2345678"]}"#
+    );
+
+    // Same members as the original once it is tagged, modulo key order,
+    // with the unsigned age tagged `u` after its type
+    let mut tagged = from_json(tjson::from_str::<Value>(VALUE_DOC_EXAMPLE).unwrap()).unwrap();
+    {
+        let map = tagged.as_object_mut().unwrap();
+        let age = map.remove("age:i").unwrap();
+        map.insert("age:u".to_owned(), age);
+    }
+    assert_eq!(tjson::from_str::<Value>(&written).unwrap(), tagged);
+}
+
+#[test]
+fn test_renamed_members_round_trip() {
+    let input = r#"{
+        "array-example:A<O>": [
+            {
+                "dotted.name:s": "a", "with space:s": "b", "naïve-café:s": "c", "a:b:s": "d",
+                "host:port:s": "example.com:80"
+            }
+        ],
+        "string-example:s": "foobar",
+        "favourite-colour:s": "blue"
+    }"#;
+
+    let example: Example = tjson::from_str(input).unwrap();
+    assert_eq!(example.array_example[0].colons, "d");
+    assert_eq!(example.array_example[0].address, "example.com:80");
+    assert_eq!(example.favourite_colour, "blue");
+
+    // Names are written as renamed, with a tag added to those without one,
+    // so the text round-trips
+    let written = tjson::to_string(&example).unwrap();
+    assert_eq!(
+        written,
+        "{\"array-example:A<O>\":[{\"dotted.name:s\":\"a\",\"with space:s\":\"b\",\
+         \"naïve-café:s\":\"c\",\"a:b:s\":\"d\",\"host:port:s\":\"example.com:80\"}],\
+         \"string-example:s\":\"foobar\",\"favourite-colour:s\":\"blue\"}"
+    );
+    assert_eq!(tjson::from_str::<Example>(&written).unwrap(), example);
+    if !cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(compact(&written), compact(input));
+    }
+}
+
+#[test]
+fn test_names_compare_at_final_colon() {
+    // "a:b:s" is the name "a:b" with tag "s", so it collides with "a:b:u"
+    // but not with "a:s"
+    let input = r#"{"a:b:s": "1", "a:s": "2", "a:b:u": "3"}"#;
+    let mut de = tjson::Deserializer::from_str(input).duplicate_keys(DuplicateKeys::Error);
+    let err = serde::Deserialize::deserialize(&mut de).map(|v: Value| v).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"a:b:u\" at line 1 column 34");
+
+    let input = r#"{"a-b:s": "1", "a:s": "2", "a-b-c": "3", "a.b:s": "4"}"#;
+    let mut de = tjson::Deserializer::from_str(input).duplicate_keys(DuplicateKeys::Error);
+    let value: Value = serde::Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(value.as_object().unwrap().len(), 4);
+}
+
+#[test]
+fn test_control_characters_in_names() {
+    let mut map = BTreeMap::new();
+    map.insert("tab\there\u{1}:s", "x");
+    map.insert("nul\u{0}:s", "y");
+
+    // Written escaped...
+    let written = tjson::to_string(&map).unwrap();
+    assert_eq!(written, r#"{"nul\u0000:s":"y","tab\there\u0001:s":"x"}"#);
+
+    // ...which parses back to the same names
+    let parsed: BTreeMap<String, String> = tjson::from_str(&written).unwrap();
+    assert_eq!(parsed.keys().collect::<Vec<_>>(), ["nul\u{0}:s", "tab\there\u{1}:s"]);
+
+    // Raw control characters are rejected, in names and values alike
+    for input in &["{\"tab\there:s\": \"x\"}", "{\"a:s\": \"new\nline\"}"] {
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ControlCharacterWhileParsingString);
+        assert!(err.is_syntax());
+
+        let err = tjson::from_slice::<Value>(input.as_bytes()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ControlCharacterWhileParsingString);
+
+        let err = tjson::from_reader::<_, Value>(input.as_bytes()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ControlCharacterWhileParsingString);
+    }
+
+    let input = "{\"tab\there:s\": \"x\"}";
+    let message =
+        "control character (\\u0000-\\u001F) found while parsing a string at line 1 column 6";
+    assert_eq!(tjson::from_str::<Value>(input).unwrap_err().to_string(), message);
+    assert_eq!(tjson::from_reader::<_, Value>(input.as_bytes()).unwrap_err().to_string(), message);
+}
@@ -0,0 +1,347 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+mod common;
+
+use common::parse;
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::de::{DuplicateKeys, Normalization, Parser};
+use tjson::error::ErrorCode;
+use tjson::value::from_value_normalized;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Account {
+    #[serde(rename = "userId:u")]
+    user_id: u64,
+    #[serde(rename = "displayName:s")]
+    display_name: String,
+    #[serde(rename = "home:O", default)]
+    home: Option<Address>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Address {
+    #[serde(rename = "city:s")]
+    city: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+    #[serde(rename = "name:s")]
+    name: String,
+}
+
+fn account(city: Option<&str>) -> Account {
+    Account {
+        user_id: 7,
+        display_name: "Ada".to_owned(),
+        home: city.map(|city| Address { city: city.to_owned() }),
+    }
+}
+
+#[test]
+fn test_exact() {
+    let exact = r#"{"userId:u": "7", "displayName:s": "Ada"}"#;
+    assert_eq!(parse::<Account, _>(exact, Normalization::Exact).unwrap(), account(None));
+
+    let input = r#"{"userid:u": "7", "displayName:s": "Ada"}"#;
+    let err = parse::<Account, _>(input, Normalization::Exact).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `userId:u` at line 1 column 41");
+}
+
+#[test]
+fn test_case_insensitive() {
+    let input = r#"{"USERID:u": "7", "displayname:s": "Ada", "Home:O": {"CITY:s": "London"}}"#;
+    assert_eq!(
+        parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap(),
+        account(Some("London"))
+    );
+
+    // Tags are compared exactly, so `U` isn't one, and whitespace isn't ignored
+    let input = r#"{"userId:U": "7", "displayName:s": "Ada"}"#;
+    let err = parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "member name \"userId:U\" has invalid tag \"U\" at line 1 column 11"
+    );
+    let input = r#"{" userId:u": "7", "displayName:s": "Ada"}"#;
+    let err = parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `userId:u` at line 1 column 42");
+}
+
+#[test]
+fn test_trim_and_case_insensitive() {
+    let input = r#"{" UserId :u": "7", "displayName: s ": "Ada", "home:O": {"  city:s": "Paris"}}"#;
+    assert_eq!(
+        parse::<Account, _>(input, Normalization::TrimAndCaseInsensitive).unwrap(),
+        account(Some("Paris"))
+    );
+}
+
+#[test]
+fn test_tag_kinds() {
+    let input = r#"{"userId:i": "7", "displayName:s": "Ada"}"#;
+    assert_eq!(parse::<Account, _>(input, Normalization::TagKinds).unwrap(), account(None));
+
+    #[derive(Debug, Deserialize)]
+    struct Keys {
+        #[serde(rename = "keys:A<d>")]
+        keys: Vec<Vec<u8>>,
+    }
+    let keys: Keys = parse(r#"{"keys:A<d16>": ["0102", "ff"]}"#, Normalization::TagKinds).unwrap();
+    assert_eq!(keys.keys, vec![vec![1, 2], vec![255]]);
+
+    // Names are still compared exactly, and tags of other kinds don't match
+    let input = r#"{"userid:i": "7", "displayName:s": "Ada"}"#;
+    let err = parse::<Account, _>(input, Normalization::TagKinds).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `userId:u` at line 1 column 41");
+    let input = r#"{"userId:u": "7", "displayName:d": "QWRh"}"#;
+    let err = parse::<Account, _>(input, Normalization::TagKinds).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `displayName:s` at line 1 column 42");
+}
+
+#[test]
+fn test_ambiguous_member() {
+    let input = r#"{"userId:u": "7", "displayName:s": "Ada", "USERID:u": "8"}"#;
+    let err = parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "members \"userId:u\" and \"USERID:u\" both match field \"userId:u\" at line 1 column 52"
+    );
+    assert_eq!(err.code(), ErrorCode::AmbiguousMember);
+    assert!(err.is_data());
+
+    // Both in nested structs and through a Value
+    let input = concat!(
+        r#"{"userId:u": "7", "displayName:s": "Ada", "#,
+        r#""home:O": {"city:s": "A", " City:s": "B"}}"#,
+    );
+    let err = parse::<Account, _>(input, Normalization::TrimAndCaseInsensitive).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::AmbiguousMember);
+    let value: Value = tjson::from_str(input).unwrap();
+    let err = from_value_normalized::<Account>(value, Normalization::TrimAndCaseInsensitive)
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::AmbiguousMember);
+    // In the order the object keeps its members in
+    assert!(err.to_string().contains(r#""city:s" and " City:s""#) ||
+        err.to_string().contains(r#"" City:s" and "city:s""#));
+
+    // A name repeated exactly is a duplicate as usual
+    let input = r#"{"userid:u": "7", "displayName:s": "Ada", "userid:u": "8"}"#;
+    let err = parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"userid:u\" at line 1 column 52");
+}
+
+#[test]
+fn test_deny_unknown_fields() {
+    let strict = parse::<Strict, _>(r#"{"NAME:s": "x"}"#, Normalization::CaseInsensitive).unwrap();
+    assert_eq!(strict.name, "x");
+
+    // Unknown members are reported as written
+    let input = r#"{"Name:s": "x", "Extra:s": "y"}"#;
+    let err = parse::<Strict, _>(input, Normalization::CaseInsensitive).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown field `Extra:s`, expected `name:s` at line 1 column 25"
+    );
+}
+
+#[test]
+fn test_values_are_unaffected() {
+    let input = r#"{"UserId:u": "7", " userid:u": "8"}"#;
+    let value: Value = parse(input, Normalization::TrimAndCaseInsensitive).unwrap();
+    assert_eq!(value, tjson!({ "UserId:u": 7, " userid:u": 8 }));
+
+    // Nor are the keys of maps
+    let map: std::collections::BTreeMap<String, u64> =
+        parse(input, Normalization::TrimAndCaseInsensitive).unwrap();
+    assert_eq!(map.keys().collect::<Vec<_>>(), [" userid:u", "UserId:u"]);
+}
+
+#[test]
+fn test_parser_and_value() {
+    let input = r#"[{"USERID:u": "7", "DisplayName:s": "Ada"}]"#;
+    let mut parser = Parser::new().member_name_normalization(Normalization::CaseInsensitive);
+    let accounts: Vec<Account> = parser.parse_str(input).unwrap();
+    assert_eq!(accounts, [account(None)]);
+
+    let value: Value = tjson::from_str(input).unwrap();
+    let accounts: Vec<Account> = from_value_normalized(value.clone(), Normalization::CaseInsensitive)
+        .unwrap();
+    assert_eq!(accounts, [account(None)]);
+    assert!(tjson::from_value::<Vec<Account>>(value).is_err());
+}
+
+#[test]
+fn test_with_duplicate_keys() {
+    let input = r#"{"userid:u": "7", "displayName:s": "Ada", "userid:u": "8"}"#;
+    let mut de = Deserializer::from_str(input)
+        .duplicate_keys(DuplicateKeys::FirstWins)
+        .member_name_normalization(Normalization::CaseInsensitive);
+    assert_eq!(Account::deserialize(&mut de).unwrap(), account(None));
+
+    let mut de = Deserializer::from_str(input)
+        .duplicate_keys(DuplicateKeys::Error)
+        .member_name_normalization(Normalization::CaseInsensitive);
+    assert!(Account::deserialize(&mut de).is_err());
+}
+
+#[test]
+fn test_escaped_member_names() {
+    let input = r#"{"userId:u": "7", "displayName:s": "Ada"}"#;
+    assert_eq!(parse::<Account, _>(input, Normalization::Exact).unwrap(), account(None));
+    assert_eq!(parse::<Account, _>(input, Normalization::CaseInsensitive).unwrap(), account(None));
+
+    let input = r#"[{"USERID:u": "7", "displayName:s": "Ada", "Été:s": "x"}]"#;
+    let mut de = Deserializer::from_slice(input.as_bytes())
+        .member_name_normalization(Normalization::CaseInsensitive);
+    assert_eq!(Vec::<Account>::deserialize(&mut de).unwrap(), [account(None)]);
+
+    let mut de = Deserializer::from_reader(input.as_bytes())
+        .member_name_normalization(Normalization::TrimAndCaseInsensitive);
+    assert_eq!(Vec::<Account>::deserialize(&mut de).unwrap(), [account(None)]);
+
+    // Unknown members keep their escapes decoded, borrowed or not
+    let err = parse::<Strict, _>(r#"{"name:s": "x", "Été:s": "y"}"#, Normalization::Exact)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown field `\u{c9}t\u{e9}:s`, expected `name:s` at line 1 column 25"
+    );
+    let err = parse::<Strict, _>(r#"{"name:s": "x", "extra:s": "y"}"#, Normalization::TagKinds)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "unknown field `extra:s`, expected `name:s` at line 1 column 25");
+}
+
+#[test]
+fn test_case_insensitive_beyond_ascii() {
+    #[derive(Debug, Deserialize)]
+    struct Summer {
+        #[serde(rename = "\u{e9}t\u{e9}:s")]
+        summer: String,
+    }
+
+    let summer: Summer = parse(r#"{"ÉTÉ:s": "x"}"#, Normalization::CaseInsensitive).unwrap();
+    assert_eq!(summer.summer, "x");
+    assert!(parse::<Summer, _>(r#"{"ETE:s": "x"}"#, Normalization::CaseInsensitive).is_err());
+}
+
+#[test]
+fn test_nested_tag_kinds() {
+    #[derive(Debug, Deserialize)]
+    struct Nested {
+        #[serde(rename = "ids:S<A<u>>")]
+        ids: Vec<Vec<u64>>,
+    }
+
+    let input = r#"{"ids:S<A<i>>": [["1", "2"]]}"#;
+    let nested: Nested = parse(input, Normalization::TagKinds).unwrap();
+    assert_eq!(nested.ids, vec![vec![1, 2]]);
+    for tag in &["A<A<i>>", "S<i>", "S<A<s>>", "S<A<i>"] {
+        let input = format!(r#"{{"ids:{}": [[1, 2]]}}"#, tag);
+        assert!(parse::<Nested, _>(&input, Normalization::TagKinds).is_err(), "{}", tag);
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Person {
+    name: String,
+    age: u64,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TaggedPerson {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "age:u")]
+    age: u64,
+    #[serde(rename = "tags:A<s>")]
+    tags: Vec<String>,
+}
+
+fn person() -> Person {
+    Person {
+        name: "John".to_owned(),
+        age: 43,
+        tags: vec!["a".to_owned()],
+    }
+}
+
+#[test]
+fn test_untagged() {
+    let tagged = TaggedPerson {
+        name: "John".to_owned(),
+        age: 43,
+        tags: vec!["a".to_owned()],
+    };
+    let text = tjson::to_string(&tagged).unwrap();
+    assert_eq!(text, r#"{"name:s":"John","age:u":"43","tags:A<s>":["a"]}"#);
+
+    let person: Person = parse(&text, Normalization::Untagged).unwrap();
+    assert_eq!(person, self::person());
+    assert!(parse::<Person, _>(&text, Normalization::Exact).is_err());
+
+    // Untagged names match as they are, and tagged fields as with `Exact`
+    let text = tjson::to_string(&person).unwrap();
+    assert_eq!(parse::<Person, _>(&text, Normalization::Untagged).unwrap(), person);
+    let input = r#"{"name": "John", "age:u": "43", "tags:A<s>": []}"#;
+    let err = parse::<TaggedPerson, _>(input, Normalization::Untagged).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `name:s` at line 1 column 48");
+}
+
+#[test]
+fn test_untagged_by_default() {
+    let person = tjson::from_str::<Person>(r#"{"name:s":"John","age:u":"43"}"#).unwrap();
+    assert_eq!((person.name.as_str(), person.age), ("John", 43));
+
+    // The tag says how the integer is read
+    let err = tjson::from_str::<Person>(r#"{"name:s":"John","age:u":"-43"}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    let err = tjson::from_str::<Person>(r#"{"name:s":"John","age:s":"43"}"#).unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: string \"43\", expected u64 at line 1 column 29");
+
+    let value = tjson!({ "name:s": "John", "age:u": 43, "tags:A<s>": ["a"] });
+    assert_eq!(tjson::from_value::<Person>(value).unwrap(), self::person());
+}
+
+#[test]
+fn test_untagged_values_follow_the_member_tag() {
+    #[derive(Debug, Deserialize)]
+    struct Key {
+        id: u64,
+        bytes: Vec<u8>,
+    }
+
+    // The tags are left off to match the fields, and still say how the
+    // values are read
+    let input = r#"{"id:u": "43", "bytes:d16": "00ff"}"#;
+    let key: Key = tjson::from_str(input).unwrap();
+    assert_eq!((key.id, key.bytes), (43, vec![0, 255]));
+
+    let value = tjson!({ "id:u": 7, "bytes:A<u>": [0, 1, 255] });
+    let key: Key = from_value_normalized(value, Normalization::Untagged).unwrap();
+    assert_eq!(key.bytes, [0, 1, 255]);
+
+    let input = r#"{"name:s": "John", "age:u": "43", "tags:A<s>": [], "age:i": "44"}"#;
+    let err = parse::<Person, _>(input, Normalization::Untagged).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::AmbiguousMember);
+    assert_eq!(
+        err.to_string(),
+        "members \"age:u\" and \"age:i\" both match field \"age\" at line 1 column 58"
+    );
+}
@@ -0,0 +1,358 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::fmt::Debug;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use tjson::{Deserializer, Number, Set, Value};
+use tjson::de::Parser;
+use tjson::error::ErrorCode;
+
+/// Deserializes `value` into `T` through each of `Number`, `&Number`, `Value`
+/// and `&Value`, which must all agree.
+fn narrow<T>(value: Value) -> Result<T, String>
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let number: Number = tjson::from_value(value.clone()).unwrap();
+
+    let results: Vec<Result<T, String>> = vec![
+        T::deserialize(number),
+        T::deserialize(&number),
+        T::deserialize(value.clone()),
+        T::deserialize(&value),
+    ]
+            .into_iter()
+            .map(|result| result.map_err(|err| err.to_string()))
+            .collect();
+
+    for result in &results[1..] {
+        assert_eq!(*result, results[0]);
+    }
+    results.into_iter().next().unwrap()
+}
+
+macro_rules! assert_boundaries {
+    ($($ty:ident)*) => {
+        $(
+            assert_eq!(narrow::<$ty>(tjson!($ty::MIN)), Ok($ty::MIN));
+            assert_eq!(narrow::<$ty>(tjson!($ty::MAX)), Ok($ty::MAX));
+
+            let below = $ty::MIN as i64 - 1;
+            assert_eq!(
+                narrow::<$ty>(tjson!(below)),
+                Err(format!("invalid value: integer `{}`, expected {}", below, stringify!($ty)))
+            );
+
+            let above = $ty::MAX as u64 + 1;
+            assert_eq!(
+                narrow::<$ty>(tjson!(above)),
+                Err(format!("invalid value: integer `{}`, expected {}", above, stringify!($ty)))
+            );
+        )*
+    };
+}
+
+#[test]
+fn test_integer_boundaries() {
+    assert_boundaries!(i8 i16 i32 u8 u16 u32);
+
+    assert_eq!(narrow::<i64>(tjson!(i64::MIN)), Ok(i64::MIN));
+    assert_eq!(narrow::<i64>(tjson!(i64::MAX)), Ok(i64::MAX));
+    assert_eq!(
+        narrow::<i64>(tjson!(i64::MAX as u64 + 1)),
+        Err("invalid value: integer `9223372036854775808`, expected i64".to_owned())
+    );
+
+    assert_eq!(narrow::<u64>(tjson!(u64::MAX)), Ok(u64::MAX));
+    assert_eq!(
+        narrow::<u64>(tjson!(-1)),
+        Err("invalid value: integer `-1`, expected u64".to_owned())
+    );
+}
+
+#[test]
+fn test_negative_into_unsigned() {
+    let err = narrow::<u8>(tjson!(-42)).unwrap_err();
+    assert_eq!(err, "invalid value: integer `-42`, expected u8");
+
+    let err = tjson::from_value::<Vec<u32>>(tjson!([1, -7])).unwrap_err();
+    assert_eq!(err.to_string(), "invalid value: integer `-7`, expected u32");
+}
+
+#[test]
+fn test_float_precision() {
+    assert_eq!(narrow::<f64>(tjson!(1u64 << 53)), Ok(9007199254740992.0));
+    assert_eq!(narrow::<f64>(tjson!(-(1i64 << 53))), Ok(-9007199254740992.0));
+    assert_eq!(narrow::<f64>(tjson!(i64::MIN)), Ok(-9223372036854775808.0));
+
+    // Large integers that happen to be exactly representable are fine
+    assert_eq!(narrow::<f64>(tjson!(1u64 << 63)), Ok(9223372036854775808.0));
+
+    assert_eq!(
+        narrow::<f64>(tjson!((1u64 << 53) + 1)),
+        Err("invalid value: integer `9007199254740993`, expected f64 without loss of precision"
+                .to_owned())
+    );
+    assert_eq!(
+        narrow::<f64>(tjson!(u64::MAX)),
+        Err("invalid value: integer `18446744073709551615`, expected f64 without loss of \
+             precision"
+                .to_owned())
+    );
+    assert_eq!(
+        narrow::<f64>(tjson!(i64::MAX)),
+        Err("invalid value: integer `9223372036854775807`, expected f64 without loss of \
+             precision"
+                .to_owned())
+    );
+
+    assert_eq!(narrow::<f32>(tjson!(1 << 24)), Ok(16777216.0));
+    assert_eq!(
+        narrow::<f32>(tjson!((1 << 24) + 1)),
+        Err("invalid value: integer `16777217`, expected f32 without loss of precision".to_owned())
+    );
+
+    // Floats are converted as before
+    assert_eq!(narrow::<f64>(tjson!(0.1)), Ok(0.1));
+    assert_eq!(narrow::<f32>(tjson!(0.1)), Ok(0.1));
+}
+
+#[test]
+fn test_float_into_integer() {
+    assert_eq!(
+        narrow::<u8>(tjson!(1.5)),
+        Err("invalid type: floating point `1.5`, expected u8".to_owned())
+    );
+    assert_eq!(
+        narrow::<i32>(tjson!(-2.0)),
+        Err("invalid type: floating point `-2.0`, expected i32".to_owned())
+    );
+}
+
+#[test]
+fn test_to_integer_lossless() {
+    let lossless = |f: f64| Number::from_f64(f).unwrap().to_integer_lossless();
+
+    assert_eq!(lossless(3.0), Some(Number::from(3u8)));
+    assert_eq!(lossless(-3.0), Some(Number::from(-3)));
+    assert_eq!(lossless(-0.0), Some(Number::from(0)));
+    assert_eq!(lossless(1e15), Some(Number::from(1_000_000_000_000_000u64)));
+    assert_eq!(lossless(9007199254740992.0), Some(Number::from(1u64 << 53)));
+    assert_eq!(lossless(-9223372036854775808.0), Some(Number::from(i64::MIN)));
+    assert_eq!(lossless(18446744073709549568.0), Some(Number::from(u64::MAX - 2047)));
+
+    assert_eq!(lossless(3.5), None);
+    assert_eq!(lossless(-0.25), None);
+    assert_eq!(lossless(18446744073709551616.0), None);
+    assert_eq!(lossless(-9223372036854777856.0), None);
+
+    // Integers are integers already
+    assert_eq!(Number::from(7).to_integer_lossless(), Some(Number::from(7)));
+    assert_eq!(Number::from(-7).to_integer_lossless(), Some(Number::from(-7)));
+}
+
+#[test]
+fn test_normalize_integral_floats() {
+    let mut value = tjson!({
+        "a": 3.0,
+        "b": [1.0, 1.5, -0.0, { "c": 2.0 }],
+        "d": "3.0",
+        "e": 1e300,
+    });
+    value.normalize_integral_floats();
+    assert_eq!(
+        value,
+        tjson!({
+            "a": 3,
+            "b": [1, 1.5, 0, { "c": 2 }],
+            "d": "3.0",
+            "e": 1e300,
+        })
+    );
+
+    let mut set = Set::new();
+    set.insert(tjson!(2.0));
+    set.insert(tjson!(2));
+    set.insert(tjson!(2.5));
+    let mut value = Value::Set(set);
+    value.normalize_integral_floats();
+    assert_eq!(value.len(), Some(2));
+    assert_eq!(tjson::to_string(&value).unwrap(), "[2,2.5]");
+}
+
+#[derive(Debug, Deserialize)]
+struct Dimensions {
+    #[serde(rename = "x:f")]
+    x: u32,
+    #[serde(rename = "y:f", default)]
+    y: Option<i64>,
+}
+
+fn coerce<'a, T>(input: &'a str) -> tjson::Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input).integral_float_coercion(true);
+    let value = try!(T::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
+
+#[test]
+fn test_integral_float_coercion() {
+    let input = r#"{"x:f": 3.0}"#;
+    assert_eq!(coerce::<Dimensions>(input).unwrap().x, 3);
+    let err = tjson::from_str::<Dimensions>(input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: floating point `3.0`, expected u32 at line 1 column 11"
+    );
+
+    let dims: Dimensions = coerce(r#"{"x:f": 4e2, "y:f": -0.0}"#).unwrap();
+    assert_eq!((dims.x, dims.y), (400, Some(0)));
+    let dims: Dimensions = coerce(r#"{"x:f": 0, "y:f": -9.0E15}"#).unwrap();
+    assert_eq!(dims.y, Some(-9_000_000_000_000_000));
+
+    let mut parser = Parser::new().integral_float_coercion(true);
+    let values: Vec<u64> = parser.parse_str("[1e15, 1.0e0, 25000e-3]").unwrap();
+    assert_eq!(values, [1_000_000_000_000_000, 1, 25]);
+    // Other types are unaffected
+    assert_eq!(parser.parse_str::<Vec<f64>>("[3.0, 1e300]").unwrap(), [3.0, 1e300]);
+    assert_eq!(parser.parse_str::<Value>("[3.0]").unwrap(), tjson!([3.0]));
+}
+
+#[test]
+fn test_integral_float_precision() {
+    // 2^53 and below are exact, as are larger integers with few enough
+    // significant bits
+    assert_eq!(coerce::<u64>("9007199254740992.0").unwrap(), 1 << 53);
+    assert_eq!(coerce::<i64>("-9007199254740991.0").unwrap(), -(1 << 53) + 1);
+    assert_eq!(coerce::<u64>("9007199254740994.0").unwrap(), (1 << 53) + 2);
+    assert_eq!(coerce::<i64>("-9223372036854775808.0").unwrap(), i64::MIN);
+
+    let inexact = &[
+        "9007199254740993.0",
+        "-9007199254740993.0",
+        "18446744073709551615.0",
+        "1152921504606846977e0",
+    ];
+    for input in inexact {
+        let err = coerce::<i64>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InexactInteger, "{}", input);
+        assert!(err.is_data());
+    }
+    let err = coerce::<Dimensions>(r#"{"x:f": 9007199254740993.0}"#).unwrap_err();
+    assert_eq!(err.to_string(), "number is not exactly an integer at line 1 column 26");
+
+    // Fractions and numbers out of range are the wrong type as before
+    let cases = &[
+        ("3.5", "invalid type: floating point `3.5`, expected u32 at line 1 column 3"),
+        (
+            "3.0000000000000000000001",
+            "invalid type: floating point `3.0`, expected u32 at line 1 column 24",
+        ),
+        ("1e-400", "invalid type: floating point `0.0`, expected u32 at line 1 column 6"),
+        (
+            "18446744073709551616.0",
+            "invalid type: floating point `18446744073709552000.0`, expected u32 at line 1 \
+             column 22",
+        ),
+        ("-1.0", "invalid value: integer `-1`, expected u32 at line 1 column 4"),
+        ("4294967296.0", "invalid value: integer `4294967296`, expected u32 at line 1 column 12"),
+        ("\"3\"", "invalid type: string \"3\", expected u32 at line 1 column 3"),
+    ];
+    for &(input, message) in cases {
+        let err = coerce::<u32>(input).unwrap_err();
+        assert_eq!(err.to_string(), message, "{}", input);
+    }
+}
+
+fn is_signed(value: &Value) -> bool {
+    match *value {
+        Value::Number(ref n) => n.is_signed(),
+        _ => panic!("not a number: {:?}", value),
+    }
+}
+
+#[test]
+fn test_integer_tags_choose_storage() {
+    let input = r#"{"a:i":"5","b:i":"-5","c:u":"5","d:A<i>":["0","7"]}"#;
+    let value: Value = tjson::from_str(input).unwrap();
+    assert!(is_signed(&value["a:i"]));
+    assert!(is_signed(&value["b:i"]));
+    assert!(!is_signed(&value["c:u"]));
+    assert!(is_signed(&value["d:A<i>"][0]));
+    assert!(is_signed(&value["d:A<i>"][1]));
+
+    // An unquoted number under `i` doesn't match its tag
+    let err = tjson::from_str::<Value>(r#"{"e:i":18446744073709551615}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+    let value: Value = tjson::from_str(input).unwrap();
+
+    // Written back the same, and kept through serde
+    assert_eq!(tjson::to_string(&value).unwrap(), input);
+    let copy = tjson::to_value(&value).unwrap();
+    assert!(is_signed(&copy["a:i"]));
+    assert!(!is_signed(&copy["c:u"]));
+
+    // Including through tjson!
+    let built = tjson!({ "a:i": 5, "c:u": value["a:i"].clone() });
+    assert!(is_signed(&built["a:i"]));
+    assert!(!is_signed(&built["c:u"]));
+}
+
+#[test]
+fn test_signed_and_unsigned_are_equal() {
+    use std::collections::HashSet;
+
+    let signed = Number::from_i64_signed(5);
+    let unsigned = Number::from(5u64);
+    assert_eq!(signed, unsigned);
+    assert_eq!(signed.cmp(&unsigned), std::cmp::Ordering::Equal);
+    assert!(Number::from_i64_signed(4) < unsigned);
+    assert!(Number::from_i64_signed(-1) < Number::from(0u64));
+
+    let mut numbers = HashSet::new();
+    numbers.insert(signed);
+    assert!(numbers.contains(&unsigned));
+
+    let mut set = Set::new();
+    set.insert(Value::Number(signed));
+    assert!(!set.insert(Value::Number(unsigned)));
+    assert_eq!(
+        Value::Number(signed).hash_fingerprint(),
+        Value::Number(unsigned).hash_fingerprint()
+    );
+}
+
+#[test]
+fn test_signed_struct_fields() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Counter {
+        #[serde(rename = "count:i")]
+        count: i64,
+        #[serde(rename = "total:u")]
+        total: u64,
+    }
+
+    let counter = Counter { count: 5, total: 5 };
+    let value = tjson::to_value(&counter).unwrap();
+    assert!(is_signed(&value["count:i"]));
+    assert!(!is_signed(&value["total:u"]));
+    assert_eq!(tjson::to_string(&value).unwrap(), r#"{"count:i":"5","total:u":"5"}"#);
+    assert_eq!(tjson::from_value::<Counter>(value).unwrap(), counter);
+}
@@ -0,0 +1,170 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::{f64, i64, u64};
+
+use tjson::{Number, Value};
+
+const TWO_53: u64 = 1 << 53;
+
+fn int(i: i64) -> Value {
+    Value::Number(Number::from_i64_signed(i))
+}
+
+fn uint(u: u64) -> Value {
+    Value::Number(Number::from(u))
+}
+
+fn float(f: f64) -> Value {
+    Value::Number(Number::from_f64(f).unwrap())
+}
+
+/// Asserts that `value == primitive` is `expected` whichever side each is on
+/// and however the value is borrowed.
+macro_rules! check {
+    ($value:expr, $primitive:expr, $expected:expr) => {{
+        let mut value: Value = $value;
+        let primitive = $primitive;
+        let expected: bool = $expected;
+        let context = format!("{:?} == {:?}", value, primitive);
+        assert_eq!(value == primitive, expected, "{}", context);
+        assert_eq!(primitive == value, expected, "{} reversed", context);
+        assert_eq!(&value == primitive, expected, "&{}", context);
+        assert_eq!(&mut value == primitive, expected, "&mut {}", context);
+        assert_eq!(value != primitive, !expected, "{} with !=", context);
+    }};
+}
+
+#[test]
+fn test_i64() {
+    check!(int(3), 3i64, true);
+    check!(int(-3), -3i64, true);
+    check!(int(i64::MIN), i64::MIN, true);
+    check!(int(3), 4i64, false);
+    check!(uint(3), 3i64, true);
+    check!(uint(i64::MAX as u64), i64::MAX, true);
+    check!(uint(u64::MAX), -1i64, false);
+    check!(uint(i64::MAX as u64 + 1), i64::MIN, false);
+    check!(float(3.0), 3i64, true);
+    check!(float(-3.0), -3i64, true);
+    check!(float(-0.0), 0i64, true);
+    check!(float(3.5), 3i64, false);
+    check!(float(-9223372036854775808.0), i64::MIN, true);
+    check!(float(TWO_53 as f64), TWO_53 as i64 + 1, false);
+    check!(float(9223372036854775808.0), i64::MAX, false);
+    check!(tjson!("3"), 3i64, false);
+    check!(Value::Undefined, 0i64, false);
+}
+
+#[test]
+fn test_u64() {
+    check!(int(3), 3u64, true);
+    check!(int(0), 0u64, true);
+    check!(int(-3), u64::MAX - 2, false);
+    check!(uint(3), 3u64, true);
+    check!(uint(u64::MAX), u64::MAX, true);
+    check!(uint(3), 4u64, false);
+    check!(float(3.0), 3u64, true);
+    check!(float(-0.0), 0u64, true);
+    check!(float(3.5), 3u64, false);
+    check!(float(18446744073709549568.0), u64::MAX - 2047, true);
+    check!(float(18446744073709551616.0), u64::MAX, false);
+    check!(float(TWO_53 as f64), TWO_53 + 1, false);
+    check!(tjson!(true), 1u64, false);
+}
+
+#[test]
+fn test_f64() {
+    check!(int(3), 3.0f64, true);
+    check!(int(-3), -3.0f64, true);
+    check!(int(0), -0.0f64, true);
+    check!(int(3), 3.5f64, false);
+    check!(int(i64::MIN), -9223372036854775808.0f64, true);
+    check!(int(TWO_53 as i64 + 1), TWO_53 as f64, false);
+    check!(uint(3), 3.0f64, true);
+    check!(uint(u64::MAX), 18446744073709551616.0f64, false);
+    check!(uint(u64::MAX - 2047), 18446744073709549568.0f64, true);
+    check!(uint(TWO_53 + 1), TWO_53 as f64, false);
+    check!(float(3.0), 3.0f64, true);
+    check!(float(-0.0), 0.0f64, true);
+    check!(float(0.1), 0.1f64, true);
+    check!(float(0.1), 0.2f64, false);
+    check!(float(3.0), f64::NAN, false);
+    check!(float(f64::MAX), f64::INFINITY, false);
+    check!(tjson!("3.0"), 3.0f64, false);
+}
+
+#[test]
+fn test_narrow_primitives() {
+    check!(int(-3), -3i8, true);
+    check!(float(200.0), 200u8, true);
+    check!(uint(65535), 65535u16, true);
+    check!(float(0.5), 0.5f32, true);
+    check!(float(0.1), 0.1f32, false);
+    check!(int(1), 1.0f32, true);
+    check!(uint(7), 7usize, true);
+    check!(int(-7), -7isize, true);
+}
+
+#[test]
+fn test_values() {
+    // Numbers paired with their mathematical value, as a string so that
+    // numbers beyond what a float holds exactly can be told apart
+    let numbers = vec![
+        (int(-3), "-3"),
+        (float(-3.0), "-3"),
+        (int(0), "0"),
+        (float(-0.0), "0"),
+        (int(3), "3"),
+        (uint(3), "3"),
+        (float(3.0), "3"),
+        (float(3.5), "3.5"),
+        (int(TWO_53 as i64 + 1), "2^53 + 1"),
+        (float(TWO_53 as f64), "2^53"),
+        (uint(u64::MAX), "2^64 - 1"),
+        (float(18446744073709551616.0), "2^64"),
+    ];
+
+    for &(ref a, a_math) in &numbers {
+        for &(ref b, b_math) in &numbers {
+            let numeric = a_math == b_math;
+            let strict = numeric && a.is_f64() == b.is_f64();
+            assert_eq!(a == b, strict, "{:?} == {:?}", a, b);
+            assert_eq!(a.eq_numeric(b), numeric, "{:?} numerically equal to {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn test_values_not_numbers() {
+    assert!(tjson!("a").eq_numeric(&tjson!("a")));
+    assert!(!tjson!("1").eq_numeric(&tjson!(1)));
+    assert!(!tjson!(1).eq_numeric(&tjson!("1")));
+    assert!(Value::Undefined.eq_numeric(&Value::Undefined));
+    assert!(tjson!([1]).eq_numeric(&tjson!([1])));
+    assert!(!tjson!([1]).eq_numeric(&tjson!([1.0])));
+    assert!(!tjson!({"a": 1}).eq_numeric(&tjson!({"a": 1.0})));
+}
+
+#[test]
+fn test_numbers() {
+    let n = |f| Number::from_f64(f).unwrap();
+
+    assert!(Number::from(3).eq_numeric(&n(3.0)));
+    assert!(n(3.0).eq_numeric(&Number::from(3)));
+    assert!(Number::from_i64_signed(3).eq_numeric(&Number::from(3u64)));
+    assert!(n(-0.0).eq_numeric(&n(0.0)));
+    assert!(!Number::from(3).eq_numeric(&n(3.5)));
+    assert!(!Number::from(u64::MAX).eq_numeric(&n(18446744073709551616.0)));
+    assert!(!Number::from(i64::MIN).eq_numeric(&Number::from(u64::MAX)));
+
+    assert_ne!(Number::from(3), n(3.0));
+}
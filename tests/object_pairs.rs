@@ -0,0 +1,110 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use tjson::{Map, Value};
+use tjson::error::ErrorCode;
+
+fn pairs() -> Vec<(String, Value)> {
+    vec![
+        ("z:s".to_owned(), tjson!("last")),
+        ("a:u".to_owned(), tjson!(1)),
+        ("m:O".to_owned(), tjson!({ "k:A<b>": [true] })),
+    ]
+}
+
+#[test]
+fn test_round_trip() {
+    let value = Value::from_pairs(pairs()).unwrap();
+    assert_eq!(value["a:u"], 1);
+
+    let back = value.clone().into_pairs().unwrap();
+    let names: Vec<&str> = back.iter().map(|&(ref name, _)| name.as_str()).collect();
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(back, pairs());
+    } else {
+        assert_eq!(names, ["a:u", "m:O", "z:s"]);
+    }
+
+    let map = Map::from_pairs(pairs()).unwrap();
+    assert_eq!(Value::Object(map.clone()), value);
+    assert_eq!(map.into_pairs(), back);
+
+    assert_eq!(Value::from_pairs(Vec::new()).unwrap(), tjson!({}));
+    assert_eq!(tjson!(null).into_pairs(), Err(tjson!(null)));
+}
+
+#[test]
+fn test_duplicates() {
+    let mut duplicated = pairs();
+    duplicated.push(("b".to_owned(), tjson!(2)));
+    duplicated.push(("a:u".to_owned(), tjson!(3)));
+
+    let err = Value::from_pairs(duplicated.clone()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+    assert_eq!(err.to_string(), "duplicate key in pair 4");
+
+    let err = Map::from_pairs(duplicated).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key in pair 4");
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    #[serde(rename = "id:u")]
+    id: u64,
+    #[serde(rename = "fields:O", with = "tjson::ext::pairs")]
+    fields: Vec<(String, Value)>,
+}
+
+#[test]
+fn test_with_module() {
+    let record = Record {
+        id: 7,
+        fields: pairs(),
+    };
+
+    let text = tjson::to_string(&record).unwrap();
+    assert_eq!(
+        text,
+        r#"{"id:u":"7","fields:O":{"z:s":"last","a:u":"1","m:O":{"k:A<b>":[true]}}}"#
+    );
+    let back: Record = tjson::from_str(&text).unwrap();
+    assert_eq!(back, record);
+
+    // Through a Value, whose objects keep their order only with the
+    // preserve_order feature
+    let value = tjson::to_value(&record).unwrap();
+    let back: Record = tjson::from_value(value).unwrap();
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(back, record);
+    } else {
+        assert_eq!(back.fields[0].0, "a:u");
+    }
+}
+
+#[test]
+fn test_with_module_duplicates() {
+    let mut record = Record {
+        id: 7,
+        fields: pairs(),
+    };
+    record.fields.push(("z:s".to_owned(), tjson!("again")));
+    let err = tjson::to_string(&record).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key in pair 3");
+
+    let err = tjson::from_str::<Record>(r#"{"id:u":"7","fields:O":{"a:u":"1","a:u":"2"}}"#)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"a:u\" at line 1 column 39");
+
+    let err = tjson::from_str::<Record>(r#"{"id:u":"7","fields:O":[]}"#).unwrap_err();
+    assert!(err.is_data());
+}
@@ -0,0 +1,162 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::io::{self, Write};
+
+use tjson::Value;
+use tjson::error::ErrorCode;
+use tjson::schema;
+use tjson::ser::SerializeConfig;
+
+#[derive(Serialize)]
+struct Tenant {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "rows:A<O>")]
+    rows: Vec<Row>,
+}
+
+#[derive(Serialize)]
+struct Row {
+    #[serde(rename = "id:u")]
+    id: u64,
+    #[serde(rename = "note:s")]
+    note: String,
+}
+
+fn tenant(rows: u64) -> Tenant {
+    Tenant {
+        name: "acme".to_owned(),
+        rows: (0..rows).map(|id| Row { id: id, note: "x".repeat(10) }).collect(),
+    }
+}
+
+/// Records how many bytes reach it, and in how many writes.
+#[derive(Default)]
+struct CountingWriter {
+    bytes: usize,
+    writes: usize,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes += buf.len();
+        self.writes += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_exact_boundary() {
+    let value = tenant(3);
+    let full = tjson::to_vec(&value).unwrap();
+
+    let config = SerializeConfig::new().max_output_bytes(full.len());
+    assert_eq!(tjson::to_vec_with(&config, &value).unwrap(), full);
+    assert_eq!(tjson::to_string_with(&config, &value).unwrap().as_bytes(), &full[..]);
+    let mut writer = Vec::new();
+    assert_eq!(tjson::to_writer_with(&mut writer, &config, &value).unwrap(), full.len());
+    assert_eq!(writer, full);
+}
+
+#[test]
+fn test_one_byte_over() {
+    let value = tenant(3);
+    let full = tjson::to_vec(&value).unwrap();
+    let config = SerializeConfig::new().max_output_bytes(full.len() - 1);
+
+    let err = tjson::to_vec_with(&config, &value).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::OutputBudgetExceeded);
+    assert!(err.is_data());
+    let exceeded = err.output_budget_exceeded().unwrap();
+    // The closing `}` of the document is what doesn't fit, just after the
+    // value of its last member
+    assert_eq!(exceeded.written(), full.len() - 1);
+    assert_eq!(exceeded.max(), full.len() - 1);
+    assert_eq!(exceeded.path(), "/rows:A<O>");
+
+    assert!(tjson::to_string_with(&config, &value).is_err());
+    let mut writer = Vec::new();
+    let err = tjson::to_writer_with(&mut writer, &config, &value).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::OutputBudgetExceeded);
+    assert_eq!(writer, &full[..full.len() - 1]);
+}
+
+#[test]
+fn test_path() {
+    let value = tenant(1000);
+    let config = SerializeConfig::new().max_output_bytes(1000);
+    let err = tjson::to_vec_with(&config, &value).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "output budget of 1000 bytes exceeded after writing 1000 bytes at /rows:A<O>/27/id:u"
+    );
+    assert_eq!(err.output_budget_exceeded().unwrap().path(), "/rows:A<O>/27/id:u");
+
+    // Names are unescaped then escaped as pointer tokens
+    let value = tjson!({ "a/b\"c~": ["0123456789"] });
+    let config = SerializeConfig::new().max_output_bytes(20);
+    let err = tjson::to_vec_with(&config, &value).unwrap_err();
+    assert_eq!(err.output_budget_exceeded().unwrap().path(), "/a~1b\"c~0:A<s>/0");
+
+    // Before the first member's name is written
+    let config = SerializeConfig::new().max_output_bytes(1);
+    let err = tjson::to_vec_with(&config, &value).unwrap_err();
+    assert_eq!(err.output_budget_exceeded().unwrap().path(), "");
+    assert_eq!(err.output_budget_exceeded().unwrap().written(), 1);
+}
+
+#[test]
+fn test_streaming_writer() {
+    let value = tenant(100_000);
+    let full = tjson::to_vec(&value).unwrap().len();
+
+    for &max in &[0, 1, 100, 4096, 65_536] {
+        let mut writer = CountingWriter::default();
+        let config = SerializeConfig::new().max_output_bytes(max);
+        let err = tjson::to_writer_with(&mut writer, &config, &value).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::OutputBudgetExceeded);
+        assert!(writer.bytes <= max, "{} bytes reached the writer", writer.bytes);
+        assert_eq!(err.output_budget_exceeded().unwrap().written(), writer.bytes);
+        // Aborted long before the end of the document
+        assert!(writer.writes < max + 10 && writer.bytes < full);
+    }
+}
+
+#[test]
+fn test_with_schema() {
+    let schema = schema::infer(&[tjson!({ "id": 1, "name": "alice" })]);
+    let value = tjson!({ "id": 2, "name": "bob" });
+    let config = SerializeConfig::new().validate_against(&schema).max_output_bytes(24);
+
+    let mut writer = Vec::new();
+    let err = tjson::to_writer_with(&mut writer, &config, &value).unwrap_err();
+    assert_eq!(err.output_budget_exceeded().unwrap().path(), "/name:s");
+    // Nothing is written unless it all fits, as with any schema check
+    assert!(writer.is_empty());
+
+    // A schema violation is reported first
+    let err = tjson::to_vec_with(&config, &tjson!({ "id": "x" })).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SchemaViolation);
+}
+
+#[test]
+fn test_unlimited() {
+    let value: Value = tjson!({ "a": [1, 2, 3] });
+    let output = tjson::to_vec_with(&SerializeConfig::new(), &value).unwrap();
+    assert_eq!(output, br#"{"a:A<i>":["1","2","3"]}"#);
+}
@@ -0,0 +1,166 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::de::Parser;
+use tjson::error::ErrorCode;
+use tjson::ser::{SerializeConfig, TagInference};
+
+fn from_pairs<'a, T>(input: &'a str) -> tjson::Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input).map_as_pairs(true);
+    let value = try!(T::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
+
+#[test]
+fn test_tuple_keys() {
+    let mut map = HashMap::new();
+    for x in 0..10u32 {
+        map.insert((x, x * x), format!("cell {}", x));
+    }
+
+    let text = tjson::to_string(&map).unwrap();
+    assert!(text.starts_with("[[["), "{}", text);
+    let back: HashMap<(u32, u32), String> = from_pairs(&text).unwrap();
+    assert_eq!(back, map);
+
+    // Through a Value as well
+    let value = tjson::to_value(&map).unwrap();
+    assert_eq!(value.len(), Some(10));
+    assert_eq!(tjson::to_string(&value).unwrap().len(), text.len());
+    let back: HashMap<(u32, u32), String> = from_pairs(&tjson::to_string(&value).unwrap()).unwrap();
+    assert_eq!(back, map);
+
+    let mut map = BTreeMap::new();
+    map.insert((1u8, 2u8), 3u8);
+    assert_eq!(tjson::to_string(&map).unwrap(), "[[[1,2],3]]");
+    assert_eq!(tjson::to_value(&map).unwrap(), tjson!([[[1, 2], 3]]));
+    assert_eq!(
+        tjson::to_string_pretty(&map).unwrap(),
+        tjson::to_string_pretty(&tjson!([[[1, 2], 3]])).unwrap()
+    );
+}
+
+#[test]
+fn test_value_keys() {
+    let mut map = BTreeMap::new();
+    map.insert(tjson!(true), tjson!("yes"));
+    map.insert(tjson!(1.5), tjson!({ "a:s": "b" }));
+    map.insert(tjson!("name"), tjson!(null));
+    map.insert(tjson!([1, 2]), tjson!([]));
+    map.insert(tjson!({ "k:s": "v" }), tjson!(false));
+
+    let text = tjson::to_string(&map).unwrap();
+    assert_eq!(
+        text,
+        r#"[[true,"yes"],[1.5,{"a:s":"b"}],["name",null],[[1,2],[]],[{"k:s":"v"},false]]"#
+    );
+    let back: BTreeMap<Value, Value> = from_pairs(&text).unwrap();
+    assert_eq!(back, map);
+
+    // Keys which can all be member names make an object, which is still
+    // read back
+    let mut map = BTreeMap::new();
+    map.insert(tjson!("a:u"), tjson!(1));
+    map.insert(tjson!("b:u"), tjson!(2));
+    let text = tjson::to_string(&map).unwrap();
+    assert_eq!(text, r#"{"a:u":"1","b:u":"2"}"#);
+    let back: BTreeMap<Value, Value> = from_pairs(&text).unwrap();
+    assert_eq!(back, map);
+
+    // Empty maps are empty objects
+    let empty: BTreeMap<Value, Value> = BTreeMap::new();
+    assert_eq!(tjson::to_string(&empty).unwrap(), "{}");
+    assert_eq!(from_pairs::<BTreeMap<Value, Value>>("{}").unwrap(), empty);
+    assert_eq!(from_pairs::<BTreeMap<Value, Value>>("[]").unwrap(), empty);
+}
+
+#[test]
+fn test_nested_pairs() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Board {
+        name: String,
+        cells: BTreeMap<(i32, i32), char>,
+    }
+
+    let mut board = Board {
+        name: "go".to_owned(),
+        cells: BTreeMap::new(),
+    };
+    board.cells.insert((-1, 0), 'x');
+    board.cells.insert((3, 4), 'o');
+
+    // Pairs have no tag, so the member is left untagged, or is an error
+    // where tags are required
+    let config = SerializeConfig::new().infer_tags(TagInference::Required);
+    let err = tjson::to_string_with(&config, &board).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"member "cells" can't be tagged, as its value is an array of pairs"#
+    );
+    let text = tjson::to_string(&board).unwrap();
+    assert_eq!(text, r#"{"name:s":"go","cells":[[[-1,0],"x"],[[3,4],"o"]]}"#);
+    assert_eq!(from_pairs::<Board>(&text).unwrap(), board);
+
+    let mut parser = Parser::new().map_as_pairs(true);
+    for _ in 0..2 {
+        assert_eq!(parser.parse_str::<Board>(&text).unwrap(), board);
+    }
+
+    // Without the option the pairs are the wrong type for a map
+    let err = tjson::from_str::<Board>(&text).unwrap_err();
+    assert!(err.to_string().starts_with("invalid type: sequence, expected a map"), "{}", err);
+}
+
+#[test]
+fn test_duplicate_keys() {
+    let input = r#"[[[1, 2], "a"], [[3, 4], "b"], [[1, 2], "c"]]"#;
+    let err = from_pairs::<HashMap<(u32, u32), String>>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key in pair 2 at line 1 column 38");
+    assert_eq!(err.code(), ErrorCode::DuplicateMember);
+    assert!(err.is_syntax());
+
+    // Keys are compared as values rather than text, before being converted
+    let input = r#"[[["a", 1], 1], [[ "a",1 ], 2]]"#;
+    let err = from_pairs::<BTreeMap<Value, u32>>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key in pair 1 at line 1 column 26");
+
+    let input = r#"[[1, "a"], [1.0, "b"]]"#;
+    assert_eq!(from_pairs::<BTreeMap<Value, String>>(input).unwrap().len(), 2);
+}
+
+#[test]
+fn test_malformed_pairs() {
+    let cases = &[
+        (r#"[1]"#, "expected an array of a key and a value at line 1 column 2"),
+        (r#"[[]]"#, "expected an array of a key and a value at line 1 column 3"),
+        (r#"[[1]]"#, "expected an array of a key and a value at line 1 column 4"),
+        (r#"[[1, 2, 3]]"#, "expected an array of a key and a value at line 1 column 7"),
+        (r#"[[1 2]]"#, "expected `,` or `]` at line 1 column 5"),
+        (r#"[[1, 2] [3, 4]]"#, "expected `,` or `]` at line 1 column 9"),
+        (r#"[["x", 2]]"#, "invalid type: string \"x\", expected u32 at line 1 column 5"),
+        (r#"[[1, 2], [3"#, "unexpected end of input while parsing an array started at line 1 column 10"),
+    ];
+    for &(input, message) in cases {
+        let err = from_pairs::<BTreeMap<u32, u32>>(input).unwrap_err();
+        assert!(err.to_string().starts_with(message), "{}: {}", input, err);
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "rayon")]
+
+#[macro_use]
+extern crate tjson;
+
+mod common;
+
+use common::Rng;
+use tjson::{Map, Value, to_vec, to_vec_parallel};
+
+fn random_value(rng: &mut Rng, depth: usize) -> Value {
+    let kind = rng.below(if depth == 0 { 5 } else { 7 });
+    random_value_of_kind(rng, depth, kind)
+}
+
+/// Kinds of the elements of arrays, which are all of one kind so that the
+/// array has a tag. Arrays of arrays are left out, as their elements could
+/// hold different kinds.
+const ELEMENT_KINDS: &[u64] = &[0, 1, 2, 3, 4, 6];
+
+fn element_kind(rng: &mut Rng, depth: usize) -> u64 {
+    // Objects only above the deepest level
+    let kinds = if depth == 0 { 5 } else { 6 };
+    ELEMENT_KINDS[rng.below(kinds) as usize]
+}
+
+fn random_value_of_kind(rng: &mut Rng, depth: usize, kind: u64) -> Value {
+    match kind {
+        0 => Value::Bool(rng.below(2) == 0),
+        1 => tjson!(rng.next()),
+        2 => tjson!(-(rng.below(1 << 40) as i64)),
+        3 => tjson!(rng.below(1_000_000) as f64 / 7.0),
+        4 => Value::String(format!("s\"{}\n", rng.next())),
+        5 => {
+            // Not empty, so that the elements have a tag
+            let len = 1 + rng.below(7);
+            let kind = element_kind(rng, depth - 1);
+            Value::Array((0..len).map(|_| random_value_of_kind(rng, depth - 1, kind)).collect())
+        }
+        _ => {
+            let len = rng.below(8);
+            let mut map = Map::new();
+            for _ in 0..len {
+                map.insert(format!("k{}", rng.below(1000)), random_value(rng, depth - 1));
+            }
+            Value::Object(map)
+        }
+    }
+}
+
+fn random_document(seed: u64, width: usize) -> Value {
+    let mut rng = Rng(seed);
+    let mut map = Map::new();
+
+    for i in 0..width {
+        let kind = element_kind(&mut rng, 3);
+        let children = (0..width).map(|_| random_value_of_kind(&mut rng, 3, kind)).collect();
+        map.insert(format!("member-{}", i), Value::Array(children));
+    }
+
+    let elements = (0..width * 4).map(|_| random_value_of_kind(&mut rng, 4, 6)).collect();
+    map.insert("elements".to_owned(), Value::Array(elements));
+
+    Value::Object(map)
+}
+
+#[test]
+fn test_parallel_matches_sequential() {
+    for seed in 1..6 {
+        let value = random_document(seed, 64);
+        let output = to_vec_parallel(&value).unwrap();
+        assert_eq!(output, to_vec(&value).unwrap());
+        assert!(String::from_utf8(output).unwrap().contains(r#""elements:A<O>":["#));
+    }
+
+    // Members are tagged, and their integers quoted, on every thread
+    let value = tjson!({ "ids": (0..2000).collect::<Vec<u32>>(), "ratios": [1, 2.5] });
+    let output = to_vec_parallel(&value).unwrap();
+    assert_eq!(output, to_vec(&value).unwrap());
+    assert!(output.starts_with(br#"{"ids:A<i>":["0","1","#));
+    assert!(output.ends_with(br#""ratios:A<f>":[1.0,2.5]}"#));
+}
+
+#[test]
+fn test_parallel_large_toplevel_array() {
+    let mut rng = Rng(42);
+    let value = Value::Array((0..5000).map(|_| random_value(&mut rng, 3)).collect());
+
+    assert_eq!(to_vec_parallel(&value).unwrap(), to_vec(&value).unwrap());
+}
+
+#[test]
+fn test_parallel_small_and_scalar_documents() {
+    let values = vec![
+        tjson!(null),
+        tjson!("foo"),
+        tjson!([]),
+        tjson!({}),
+        tjson!({ "a": [{ "b": [1, 2] }, {}], "c": 1.0 }),
+    ];
+
+    for value in values {
+        assert_eq!(to_vec_parallel(&value).unwrap(), to_vec(&value).unwrap());
+    }
+}
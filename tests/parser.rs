@@ -0,0 +1,150 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use tjson::Value;
+use tjson::de::Parser;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Message<'a> {
+    id: u64,
+    topic: &'a str,
+    urgent: bool,
+    origin: Origin<'a>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Origin<'a> {
+    host: &'a str,
+    port: u16,
+}
+
+fn messages() -> Vec<String> {
+    (0..100_000)
+        .map(
+            |i| {
+                format!(
+                    r#"{{"id": {}, "topic": "t{}", "urgent": {}, "origin": {{"host": "h", "port": 80}}}}"#,
+                    i,
+                    i % 7,
+                    i % 2 == 0
+                )
+            },
+        )
+        .collect()
+}
+
+#[test]
+fn test_parser_reuses_buffers() {
+    let messages = messages();
+    let mut parser = Parser::new();
+
+    let reused = alloc::allocations(
+        || for (i, message) in messages.iter().enumerate() {
+            let message: Message = parser.parse_str(message).unwrap();
+            assert_eq!(message.id, i as u64);
+        },
+    );
+    let one_shot = alloc::allocations(
+        || for message in &messages {
+            tjson::from_str::<Message>(message).unwrap();
+        },
+    );
+
+    assert!(reused < 10, "{} allocations", reused);
+    assert!(one_shot >= messages.len(), "{} allocations", one_shot);
+}
+
+#[test]
+fn test_parser_matches_one_shot() {
+    let mut parser = Parser::new();
+    let inputs = &[
+        r#"{"a": [1, 2, {"b": "é\n"}], "c": null}"#,
+        r#"["x", [[[]]], {}]"#,
+        r#"  "\"escaped\""  "#,
+        "-1.5e3",
+    ];
+
+    for input in inputs {
+        let value: Value = parser.parse_str(input).unwrap();
+        assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+
+        let value: Value = parser.parse_slice(input.as_bytes()).unwrap();
+        assert_eq!(value, tjson::from_slice::<Value>(input.as_bytes()).unwrap());
+    }
+}
+
+#[test]
+fn test_parser_errors_do_not_leak() {
+    let mut parser = Parser::new();
+
+    let inputs = &[
+        r#"{"a": [1, {"b": [2, {"c""#,
+        r#"[1, 2"#,
+        r#"{"config": "#,
+        r#"[1] 2"#,
+        r#"{"a": "\uD800"}"#,
+        "",
+    ];
+
+    for input in inputs {
+        let err = parser.parse_str::<Value>(input).unwrap_err();
+        assert_eq!(err.to_string(), tjson::from_str::<Value>(input).unwrap_err().to_string());
+
+        // Nothing left open by the failed parse shows up in the next one
+        let value: Value = parser.parse_str(r#"{"ok": [true]}"#).unwrap();
+        assert_eq!(value, tjson!({ "ok": [true] }));
+
+        let err = parser.parse_str::<Value>(r#"{"x": ["#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of input while parsing an array (1 unclosed array and 1 unclosed \
+             object) at line 1 column 7"
+        );
+    }
+
+    // Failing partway through a typed value
+    let input = r#"{"id": 1, "topic": "t", "urgent": true, "origin": {"host": "h", "port": 70000}}"#;
+    let err = parser.parse_str::<Message>(input).unwrap_err();
+    assert_eq!(err.to_string(), tjson::from_str::<Message>(input).unwrap_err().to_string());
+
+    let input = r#"{"id": 1, "topic": "t", "urgent": true, "origin": {"host": "h", "port": 80}}"#;
+    assert_eq!(parser.parse_str::<Message>(input).unwrap().origin.port, 80);
+}
+
+#[test]
+fn test_parser_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Parser>();
+}
+
+#[test]
+fn test_parser_warnings() {
+    let mut parser = Parser::new().warn_depth(1).warn_key_length(3).max_warnings(1);
+    assert!(parser.warnings().is_empty());
+
+    let _: Value = parser.parse_str(r#"{"long:s": "x", "a": [[1]]}"#).unwrap();
+    assert_eq!(
+        parser.warnings().to_string(),
+        "member name length 6 exceeds the limit of 3 at /long:s\n1 more"
+    );
+
+    // Each parse has warnings of its own, with the same limits
+    let _: Value = parser.parse_str(r#"{"a": [[1]]}"#).unwrap();
+    assert_eq!(parser.warnings().to_string(), "depth 2 exceeds the limit of 1 at /a");
+
+    let _: Value = parser.parse_str(r#"{"a": 1}"#).unwrap();
+    assert!(parser.warnings().is_empty());
+}
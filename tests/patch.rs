@@ -0,0 +1,244 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::error::Error as StdError;
+
+use tjson::{Error, Value};
+use tjson::error::ErrorCode;
+
+/// Applies a patch which should fail, checking that it leaves the document
+/// as it was.
+fn patch_error(doc: &mut Value, patch: Value) -> Error {
+    let before = doc.clone();
+    let err = doc.patch(&patch).unwrap_err();
+    assert_eq!(*doc, before, "{}", err);
+    err
+}
+
+/// What made the operation fail, and which operation it was.
+fn cause(err: &Error) -> (String, Option<usize>) {
+    assert_eq!(err.code(), ErrorCode::PatchFailed, "{}", err);
+    (err.cause().unwrap().to_string(), err.patch_operation())
+}
+
+fn pointer_cause(pointer: &str, reason: &str, token: usize) -> String {
+    format!("pointer {:?}: {} at token {} of the pointer", pointer, reason, token)
+}
+
+#[test]
+fn test_patch_operations() {
+    let mut doc = tjson!({
+        "name": "ann",
+        "tags": ["a", "b"],
+        "address": { "city": "x", "zip": "1" },
+    });
+
+    doc.patch(&tjson!([
+        { "op": "add", "path": "/tags/1", "value": "c" },
+        { "op": "add", "path": "/tags/-", "value": "d" },
+        { "op": "add", "path": "/age", "value": 30 },
+        { "op": "remove", "path": "/address/zip" },
+        { "op": "replace", "path": "/name", "value": "bob" },
+        { "op": "move", "from": "/address/city", "path": "/city" },
+        { "op": "copy", "from": "/tags", "path": "/address/tags" },
+        { "op": "test", "path": "/tags/2", "value": "b" },
+        { "op": "move", "from": "/tags/0", "path": "/tags/-" },
+        { "op": "move", "from": "/age", "path": "/age" },
+    ]))
+        .unwrap();
+
+    assert_eq!(doc, tjson!({
+        "name": "bob",
+        "tags": ["c", "b", "d", "a"],
+        "address": { "tags": ["a", "c", "b", "d"] },
+        "age": 30,
+        "city": "x",
+    }));
+
+    // The whole document
+    doc.patch(&tjson!([{ "op": "replace", "path": "", "value": [1] }])).unwrap();
+    assert_eq!(doc, tjson!([1]));
+    doc.patch(&tjson!([])).unwrap();
+    assert_eq!(doc, tjson!([1]));
+}
+
+#[test]
+fn test_patch_tagged_members() {
+    let patch: Value = tjson::from_str(
+        r#"[
+            {"op:s":"add","path:s":"/a~1b","value:A<s>":["x"]},
+            {"op:s":"test","path:s":"/a~1b/0","value:s":"x"}
+        ]"#,
+    ).unwrap();
+    let mut doc = tjson!({});
+    doc.patch(&patch).unwrap();
+    assert_eq!(doc, tjson!({ "a/b": ["x"] }));
+}
+
+#[test]
+fn test_patch_test_failure_changes_nothing() {
+    let mut doc = tjson!({ "n": 1, "list": [1, 2] });
+
+    let err = patch_error(
+        &mut doc,
+        tjson!([
+            { "op": "remove", "path": "/list/0" },
+            { "op": "add", "path": "/m", "value": 2 },
+            { "op": "test", "path": "/n", "value": 2 },
+            { "op": "remove", "path": "/n" },
+        ]),
+    );
+    assert_eq!(err.code(), ErrorCode::PatchTestFailed);
+    assert_eq!(err.patch_operation(), Some(2));
+    assert_eq!(err.to_string(), "patch operation 2 found a different value at \"/n\"");
+
+    // Testing for something which isn't there
+    let err = patch_error(&mut doc, tjson!([{ "op": "test", "path": "/x", "value": 1 }]));
+    assert_eq!(cause(&err), (pointer_cause("/x", "not found", 0), Some(0)));
+}
+
+#[test]
+fn test_patch_move_into_itself() {
+    let mut doc = tjson!({ "a": { "b": { "c": 1 } }, "ab": 2 });
+
+    let err = patch_error(&mut doc, tjson!([{ "op": "move", "from": "/a", "path": "/a/b" }]));
+    assert_eq!(cause(&err).0, "cannot move \"/a\" into itself at \"/a/b\"");
+    let err = patch_error(&mut doc, tjson!([{ "op": "move", "from": "", "path": "/a" }]));
+    assert_eq!(cause(&err).0, "cannot move \"\" into itself at \"/a\"");
+
+    // A sibling whose name starts the same is not a child
+    doc.patch(&tjson!([{ "op": "move", "from": "/a", "path": "/ab" }])).unwrap();
+    assert_eq!(doc, tjson!({ "ab": { "b": { "c": 1 } } }));
+}
+
+#[test]
+fn test_patch_array_bounds() {
+    let mut doc = tjson!({ "list": [1, 2] });
+
+    for &(ref patch, pointer) in &[
+        (tjson!([{ "op": "add", "path": "/list/3", "value": 0 }]), "/list/3"),
+        (tjson!([{ "op": "remove", "path": "/list/2" }]), "/list/2"),
+        (tjson!([{ "op": "remove", "path": "/list/-" }]), "/list/-"),
+        (tjson!([{ "op": "replace", "path": "/list/2", "value": 0 }]), "/list/2"),
+        (tjson!([{ "op": "copy", "from": "/list/2", "path": "/x" }]), "/list/2"),
+        // After the element moved is removed, there is only one left
+        (tjson!([{ "op": "move", "from": "/list/0", "path": "/list/2" }]), "/list/2"),
+    ] {
+        let err = patch_error(&mut doc, patch.clone());
+        let expected = pointer_cause(pointer, "index out of bounds", 1);
+        assert_eq!(cause(&err), (expected, Some(0)));
+    }
+
+    // An index equal to the length appends
+    doc.patch(&tjson!([{ "op": "add", "path": "/list/2", "value": 3 }])).unwrap();
+    assert_eq!(doc, tjson!({ "list": [1, 2, 3] }));
+}
+
+#[test]
+fn test_patch_errors() {
+    let mut doc = tjson!({ "a": 1 });
+
+    // Unlike pointer_insert, add doesn't create objects along the way
+    let err = patch_error(&mut doc, tjson!([{ "op": "add", "path": "/b/c", "value": 0 }]));
+    assert_eq!(cause(&err), (pointer_cause("/b/c", "not found", 0), Some(0)));
+    let err = patch_error(&mut doc, tjson!([{ "op": "add", "path": "/a/c", "value": 0 }]));
+    assert_eq!(cause(&err), (pointer_cause("/a/c", "not an array or object", 1), Some(0)));
+
+    let err = patch_error(
+        &mut doc,
+        tjson!([
+            { "op": "add", "path": "/b", "value": 0 },
+            { "op": "remove", "path": "/c" },
+        ]),
+    );
+    assert_eq!(cause(&err), (pointer_cause("/c", "not found", 0), Some(1)));
+    assert_eq!(
+        err.to_string(),
+        "patch operation 1 failed: pointer \"/c\": not found at token 0 of the pointer"
+    );
+
+    // Malformed operations
+    for &(ref patch, expected) in &[
+        (tjson!([{ "op": "frobnicate", "path": "/a" }]), "unknown patch operation \"frobnicate\""),
+        (tjson!([{ "path": "/a" }]), "patch operation has no member \"op\""),
+        (tjson!([{ "op": "add", "path": "/b" }]), "patch operation has no member \"value\""),
+        (tjson!([{ "op": "move", "path": "/b" }]), "patch operation has no member \"from\""),
+        (tjson!([{ "op": 1, "path": "/a" }]), "patch operation member \"op\" must be a string"),
+        (tjson!([{ "op": "remove", "path": "" }]), "cannot remove the whole document"),
+        (tjson!(["remove"]), "a patch operation must be an object"),
+    ] {
+        let err = patch_error(&mut doc, patch.clone());
+        assert_eq!(cause(&err), (expected.to_owned(), Some(0)));
+    }
+
+    let err = patch_error(&mut doc, tjson!({ "op": "remove", "path": "/a" }));
+    assert_eq!(err.code(), ErrorCode::Data);
+    assert_eq!(err.patch_operation(), None);
+}
+
+#[test]
+fn test_merge_nested_objects() {
+    let mut doc = tjson!({ "a": { "b": { "c": 1, "d": 2 }, "e": 3 }, "f": 4 });
+
+    doc.merge(&tjson!({ "a": { "b": { "c": 5, "g": 6 } }, "h": { "i": 7 } }));
+    assert_eq!(doc, tjson!({
+        "a": { "b": { "c": 5, "d": 2, "g": 6 }, "e": 3 },
+        "f": 4,
+        "h": { "i": 7 },
+    }));
+
+    // An empty object changes nothing
+    doc.merge(&tjson!({ "a": {} }));
+    assert_eq!(doc["a"], tjson!({ "b": { "c": 5, "d": 2, "g": 6 }, "e": 3 }));
+}
+
+#[test]
+fn test_merge_replaces_arrays() {
+    let mut doc = tjson!({ "list": [1, 2, 3], "objects": [{ "a": 1 }] });
+
+    doc.merge(&tjson!({ "list": [4], "objects": [{ "b": 2 }] }));
+    assert_eq!(doc, tjson!({ "list": [4], "objects": [{ "b": 2 }] }));
+
+    // An object patch makes an array into an object
+    doc.merge(&tjson!({ "list": { "a": 1 } }));
+    assert_eq!(doc["list"], tjson!({ "a": 1 }));
+}
+
+#[test]
+fn test_merge_deletes_with_undefined() {
+    let mut doc = tjson!({ "a": 1, "b": { "c": 2, "d": 3 } });
+
+    doc.merge(&tjson!({ "a": null, "b": { "c": null }, "x": null }));
+    assert_eq!(doc, tjson!({ "b": { "d": 3 } }));
+
+    // Nothing nested under a new member is kept as Undefined
+    doc.merge(&tjson!({ "e": { "f": null, "g": { "h": null } } }));
+    assert_eq!(doc, tjson!({ "b": { "d": 3 }, "e": { "g": {} } }));
+}
+
+#[test]
+fn test_merge_into_non_objects() {
+    let mut doc = tjson!([1, 2]);
+    doc.merge(&tjson!({ "a": 1, "b": null }));
+    assert_eq!(doc, tjson!({ "a": 1 }));
+
+    let mut doc = tjson!("text");
+    doc.merge(&tjson!(["x"]));
+    assert_eq!(doc, tjson!(["x"]));
+
+    let mut doc = tjson!({ "a": 1 });
+    doc.merge(&tjson!("text"));
+    assert_eq!(doc, tjson!("text"));
+
+    let mut doc = Value::Undefined;
+    doc.merge(&tjson!({ "a": { "b": 1 } }));
+    assert_eq!(doc, tjson!({ "a": { "b": 1 } }));
+}
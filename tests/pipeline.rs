@@ -0,0 +1,160 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate tjson;
+
+use std::io::{self, BufReader, Read};
+
+use tjson::{Error, Value};
+use tjson::de::DuplicateKeys;
+use tjson::error::ErrorCode;
+use tjson::pipeline::{ErrorPolicy, Pipeline, Stage};
+use tjson::ser::{KeyOrder, KeySortRule, SerializeConfig};
+
+const STREAM: &str = concat!(
+    r#"{"name:s": "ann", "token:s": "a1", "id:u": "1"}"#,
+    "\n",
+    "\n",
+    r#"{"name:s": "bob", "token:s": "b2", "id:u": "2", "id:u": "3"}"#,
+    "\n",
+    r#"{"name:s": "cat", "token:s": "c3", "id:u": "4""#,
+    "\n",
+    r#"{"name:s": "dan", "token:s": "d4", "id:u": "5"}"#,
+);
+
+/// Parses with the last of repeated members winning, redacts tokens,
+/// rejects ids over 4 and writes members sorted
+fn pipeline() -> Pipeline<'static> {
+    Pipeline::new()
+        .parse(|de| de.duplicate_keys(DuplicateKeys::LastWins))
+        .transform_paths(
+            "/token:s",
+            |value| {
+                *value = Value::from("-");
+                Ok(())
+            },
+        )
+        .transform(
+            |value| match value["id:u"].as_u64() {
+                Some(id) if id > 4 => {
+                    Err(Error::custom_boxed(format!("id {} too large", id).into()))
+                }
+                _ => Ok(()),
+            },
+        )
+        .serialize(SerializeConfig::new().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName)))
+}
+
+#[test]
+fn test_skip_and_collect() {
+    let pipeline = pipeline().error_policy(ErrorPolicy::SkipAndCollect);
+    let mut output = Vec::new();
+    let report = pipeline.run_stream(STREAM.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        concat!(
+            r#"{"id:u":"1","name:s":"ann","token:s":"-"}"#,
+            "\n",
+            r#"{"id:u":"3","name:s":"bob","token:s":"-"}"#,
+            "\n",
+        )
+    );
+    assert_eq!(report.documents(), 4);
+    assert_eq!(report.written(), 2);
+
+    let failures = report.failures();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].document(), 2);
+    assert_eq!(failures[0].stage(), Stage::Parse);
+    assert_eq!(failures[0].error().code(), ErrorCode::EofWhileParsingObject);
+    assert_eq!(failures[1].document(), 3);
+    assert_eq!(failures[1].stage(), Stage::Transform(1));
+    assert_eq!(failures[1].to_string(), "document 3: transform 1 failed: id 5 too large");
+}
+
+#[test]
+fn test_fail_fast() {
+    let pipeline = pipeline();
+    let mut output = Vec::new();
+    let err = pipeline.run_stream(STREAM.as_bytes(), &mut output).unwrap_err();
+
+    assert_eq!(err.document(), 2);
+    assert_eq!(err.stage(), Stage::Parse);
+    assert!(err.to_string().starts_with("document 2: parse failed: "), "{}", err);
+    assert_eq!(output.iter().filter(|&&b| b == b'\n').count(), 2);
+}
+
+#[test]
+fn test_run_str() {
+    let pipeline = pipeline();
+    let output = pipeline.run_str(r#"{"token:s": "x", "id:u": "1", "name:s": "eve"}"#).unwrap();
+    assert_eq!(output, r#"{"id:u":"1","name:s":"eve","token:s":"-"}"#);
+
+    let output = pipeline.run_slice(br#"{"id:u": "2"}"#).unwrap();
+    assert_eq!(output, br#"{"id:u":"2"}"#);
+
+    let err = pipeline.run_str(r#"{"id:u": "9"}"#).unwrap_err();
+    assert_eq!(err.document(), 0);
+    assert_eq!(err.stage(), Stage::Transform(1));
+    assert_eq!(err.into_error().to_string(), "id 9 too large");
+
+    let err = pipeline.run_str(r#"{"id:u": "1"} 2"#).unwrap_err();
+    assert_eq!(err.stage(), Stage::Parse);
+    assert_eq!(err.error().code(), ErrorCode::TrailingCharacters);
+}
+
+#[test]
+fn test_serialize_stage() {
+    let pipeline = Pipeline::new().serialize(SerializeConfig::new().max_output_bytes(11));
+    assert_eq!(pipeline.run_str(r#"{"a:u": "1"}"#).unwrap(), r#"{"a:u":"1"}"#);
+
+    let err = pipeline.run_str(r#"{"a:u": "10"}"#).unwrap_err();
+    assert_eq!(err.stage(), Stage::Serialize);
+    assert_eq!(err.error().code(), ErrorCode::OutputBudgetExceeded);
+}
+
+#[test]
+fn test_transform_paths() {
+    let upper = |value: &mut Value| {
+        let upper = value.as_str().map(str::to_uppercase);
+        if let Some(upper) = upper {
+            *value = Value::from(upper);
+        }
+        Ok(())
+    };
+    let input = r#"{"a/b:A<O>": [{"n:s": "x"}, {"m:s": "z", "n:s": "y"}], "n:s": "w"}"#;
+
+    let pipeline = Pipeline::new().transform_paths("/a~1b:A<O>/*/n:s", upper);
+    let output = pipeline.run_str(input).unwrap();
+    assert_eq!(output, r#"{"a/b:A<O>":[{"n:s":"X"},{"m:s":"z","n:s":"Y"}],"n:s":"w"}"#);
+
+    let pipeline = Pipeline::new().transform_paths("/a~1b:A<O>/1/*", upper);
+    let output = pipeline.run_str(input).unwrap();
+    assert_eq!(output, r#"{"a/b:A<O>":[{"n:s":"x"},{"m:s":"Z","n:s":"Y"}],"n:s":"w"}"#);
+
+    let pipeline = Pipeline::new().transform_paths("/missing/*", upper);
+    let unchanged: Value = tjson::from_str(input).unwrap();
+    assert_eq!(pipeline.run_str(input).unwrap(), tjson::to_string(&unchanged).unwrap());
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "disk on fire"))
+    }
+}
+
+#[test]
+fn test_read_error() {
+    let pipeline = Pipeline::new().error_policy(ErrorPolicy::SkipAndCollect);
+    let err = pipeline.run_stream(BufReader::new(FailingReader), Vec::new()).unwrap_err();
+    assert_eq!(err.stage(), Stage::Read);
+    assert_eq!(err.error().code(), ErrorCode::Io);
+}
@@ -0,0 +1,247 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::time::Instant;
+
+use tjson::{Map, Value};
+use tjson::error::ErrorCode;
+use tjson::value::{PointerError, PointerErrorKind};
+
+fn kind(value: &Value, pointer: &str) -> (usize, PointerErrorKind) {
+    let err: PointerError = value.pointer_checked(pointer).unwrap_err();
+    assert_eq!(value.pointer(pointer), None, "{}", pointer);
+    (err.token(), err.kind())
+}
+
+#[test]
+fn test_pointer_deep() {
+    let mut value = tjson!("leaf");
+    let mut pointer = String::new();
+    // Nested by hand, as `tjson!` would copy the whole value at each level
+    for i in 0..1000 {
+        value = if i % 2 == 0 {
+            Value::Array(vec![value])
+        } else {
+            let mut map = Map::new();
+            map.insert("a~b/c".to_owned(), value);
+            Value::Object(map)
+        };
+    }
+    for i in 0..1000 {
+        pointer.push_str(if i % 2 == 0 { "/a~0b~1c" } else { "/0" });
+    }
+
+    let start = Instant::now();
+    for _ in 0..100 {
+        assert_eq!(value.pointer(&pointer), Some(&tjson!("leaf")));
+        assert_eq!(value.pointer_checked(&pointer), Ok(&tjson!("leaf")));
+    }
+    assert!(value.pointer_mut(&pointer).is_some());
+    let elapsed = start.elapsed();
+    assert!(elapsed.as_secs() < 5, "{:?}", elapsed);
+
+    // The failing token is counted from the root
+    pointer.push_str("/x");
+    assert_eq!(kind(&value, &pointer), (1000, PointerErrorKind::WrongType));
+}
+
+#[test]
+fn test_pointer_escapes() {
+    let value = tjson!({
+        "~": 1,
+        "/": 2,
+        "~1": 3,
+        "~/": 4,
+        "": 5,
+        "a~": 6,
+    });
+
+    assert_eq!(value.pointer("/~0"), Some(&tjson!(1)));
+    assert_eq!(value.pointer("/~1"), Some(&tjson!(2)));
+    // `~01` is `~` followed by `1`, not `/`
+    assert_eq!(value.pointer("/~01"), Some(&tjson!(3)));
+    assert_eq!(value.pointer("/~0~1"), Some(&tjson!(4)));
+    assert_eq!(value.pointer("/"), Some(&tjson!(5)));
+    assert_eq!(value.pointer("/a~0"), Some(&tjson!(6)));
+
+    // A `~` must be followed by 0 or 1
+    for pointer in &["/~", "/a~", "/~2", "/~a", "/~~0", "/~/"] {
+        assert_eq!(kind(&value, pointer), (0, PointerErrorKind::BadEscape), "{}", pointer);
+    }
+    // Even below a scalar, where there is nothing to look up
+    assert_eq!(kind(&value, "/~0/~"), (1, PointerErrorKind::BadEscape));
+    assert_eq!(kind(&value, "/~0/~0"), (1, PointerErrorKind::WrongType));
+
+    let mut value = value;
+    assert_eq!(value.pointer_mut("/a~"), None);
+    assert_eq!(value.pointer_mut("/~2"), None);
+    assert_eq!(value.pointer_mut("/~01"), Some(&mut tjson!(3)));
+
+    let base = tjson!({ "~": [1] });
+    let mut cow = base.cow_edit();
+    assert!(cow.pointer("/~0/0").is_some());
+    assert!(cow.pointer("/~/0").is_none());
+    assert!(!cow.set("/~2", tjson!(2)));
+    assert!(cow.set("/~0/0", tjson!(2)));
+    assert_eq!(cow.to_value(), tjson!({ "~": [2] }));
+}
+
+#[test]
+fn test_pointer_index() {
+    let value = tjson!([10, 11, 12]);
+    assert_eq!(value.pointer("/2"), Some(&tjson!(12)));
+
+    // Indices past the end, however large, are out of bounds
+    let large = format!("/{}", "9".repeat(25));
+    assert_eq!(kind(&value, &large), (0, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(kind(&value, &format!("/{}", usize::MAX)), (0, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(kind(&value, "/3"), (0, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(kind(&value, "/-"), (0, PointerErrorKind::IndexOutOfBounds));
+
+    // Anything else isn't an index at all
+    for pointer in &["/01", "/+1", "/-1", "/1.0", "/ 1", "/", "/a"] {
+        assert_eq!(kind(&value, pointer), (0, PointerErrorKind::NotFound), "{}", pointer);
+    }
+
+    // Digits are just names in objects
+    let value = tjson!({ "0": { "01": true } });
+    assert_eq!(value.pointer("/0/01"), Some(&tjson!(true)));
+    assert_eq!(kind(&value, "/1"), (0, PointerErrorKind::NotFound));
+}
+
+#[test]
+fn test_pointer_checked_errors() {
+    let value = tjson!({ "a": [{ "b": "c" }], "n": 1 });
+    assert_eq!(value.pointer_checked(""), Ok(&value));
+    assert_eq!(value.pointer_checked("/a/0/b"), Ok(&tjson!("c")));
+
+    assert_eq!(kind(&value, "/x"), (0, PointerErrorKind::NotFound));
+    assert_eq!(kind(&value, "/a/0/x"), (2, PointerErrorKind::NotFound));
+    assert_eq!(kind(&value, "/a/1/b"), (1, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(kind(&value, "/n/0"), (1, PointerErrorKind::WrongType));
+    assert_eq!(kind(&value, "/a/0/b/c"), (3, PointerErrorKind::WrongType));
+    assert_eq!(kind(&value, "/a/~3"), (1, PointerErrorKind::BadEscape));
+    assert_eq!(kind(&value, "a"), (0, PointerErrorKind::Syntax));
+
+    let err = value.pointer_checked("/a/0/x").unwrap_err();
+    assert_eq!(err.to_string(), "not found at token 2 of the pointer");
+    let err = value.pointer_checked("/n/0").unwrap_err();
+    assert_eq!(err.to_string(), "not an array or object at token 1 of the pointer");
+    let err = value.pointer_checked("/~").unwrap_err();
+    assert_eq!(err.to_string(), "invalid `~` escape at token 0 of the pointer");
+    let err = value.pointer_checked("a").unwrap_err();
+    assert_eq!(err.to_string(), "pointer must be empty or start with `/` at token 0 of the pointer");
+}
+
+#[test]
+fn test_pointer_remove() {
+    let mut value = tjson!({
+        "users": [
+            { "name": "ann", "tags": ["a", "b", "c"] },
+            { "name": "bob", "tags": [] },
+        ],
+        "a~b/c": 1,
+        "n": 2,
+    });
+
+    // An array inside an object inside an array inside an object
+    assert_eq!(value.pointer_remove("/users/0/tags/1"), Some(tjson!("b")));
+    assert_eq!(value.pointer("/users/0/tags"), Some(&tjson!(["a", "c"])));
+
+    // The last element, and then the elements after a removed one move down
+    assert_eq!(value.pointer_remove("/users/0/tags/1"), Some(tjson!("c")));
+    assert_eq!(value.pointer_remove("/users/0/tags/1"), None);
+    assert_eq!(value.pointer_remove("/users/0"), Some(tjson!({ "name": "ann", "tags": ["a"] })));
+    assert_eq!(value.pointer("/users/0/name"), Some(&tjson!("bob")));
+
+    assert_eq!(value.pointer_remove("/a~0b~1c"), Some(tjson!(1)));
+    assert_eq!(value.pointer_remove("/a~0b~1c"), None);
+
+    // Nothing to remove, or nothing to remove it from
+    assert_eq!(value.pointer_remove(""), None);
+    assert_eq!(value.pointer_remove("n"), None);
+    assert_eq!(value.pointer_remove("/n/0"), None);
+    assert_eq!(value.pointer_remove("/missing/0"), None);
+    assert_eq!(value.pointer_remove("/users/-"), None);
+    assert_eq!(value.pointer_remove("/users/01"), None);
+    assert_eq!(value.pointer_remove("/users/~2"), None);
+
+    assert_eq!(value, tjson!({ "users": [{ "name": "bob", "tags": [] }], "n": 2 }));
+}
+
+fn insert_error(value: &mut Value, pointer: &str) -> (usize, PointerErrorKind) {
+    let before = value.clone();
+    let err = value.pointer_insert(pointer, tjson!(0)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidPointer);
+    assert_eq!(*value, before, "{}", pointer);
+    let err = err.pointer_error().unwrap();
+    (err.token(), err.kind())
+}
+
+#[test]
+fn test_pointer_insert() {
+    let mut value = tjson!({ "users": [{ "name": "ann" }] });
+
+    // Missing members become objects, and `-` appends
+    assert_eq!(value.pointer_insert("/config/db/port", tjson!(5432)).unwrap(), None);
+    assert_eq!(value.pointer_insert("/users/-/name", tjson!("bob")).unwrap(), None);
+    assert_eq!(value.pointer_insert("/users/0/tags", tjson!([])).unwrap(), None);
+    assert_eq!(value.pointer_insert("/users/0/tags/-", tjson!("a")).unwrap(), None);
+    assert_eq!(value.pointer_insert("/users/0/tags/0", tjson!("b")).unwrap(), None);
+    assert_eq!(value.pointer_insert("/users/0/tags/2", tjson!("c")).unwrap(), None);
+    assert_eq!(value.pointer_insert("/a~0b~1c", tjson!(1)).unwrap(), None);
+
+    // Members are replaced, and the old value returned
+    assert_eq!(value.pointer_insert("/users/1/name", tjson!("cy")).unwrap(), Some(tjson!("bob")));
+    assert_eq!(value.pointer_insert("/a~0b~1c", tjson!(2)).unwrap(), Some(tjson!(1)));
+
+    assert_eq!(value, tjson!({
+        "users": [
+            { "name": "ann", "tags": ["b", "a", "c"] },
+            { "name": "cy" },
+        ],
+        "config": { "db": { "port": 5432 } },
+        "a~b/c": 2,
+    }));
+
+    let mut root = tjson!([1]);
+    assert_eq!(root.pointer_insert("", tjson!("x")).unwrap(), Some(tjson!([1])));
+    assert_eq!(root, tjson!("x"));
+}
+
+#[test]
+fn test_pointer_insert_errors() {
+    let mut value = tjson!({ "name": "ann", "n": 1, "list": [[1]], "at": null });
+    value["when"] = Value::Timestamp("2017-03-04T05:06:07Z".parse().unwrap());
+
+    // Scalars have nothing inside them, and aren't replaced
+    assert_eq!(insert_error(&mut value, "/name/first"), (1, PointerErrorKind::WrongType));
+    assert_eq!(insert_error(&mut value, "/n/0/x"), (1, PointerErrorKind::WrongType));
+    assert_eq!(insert_error(&mut value, "/when/x"), (1, PointerErrorKind::WrongType));
+    assert_eq!(insert_error(&mut value, "/at/x"), (1, PointerErrorKind::WrongType));
+
+    // Past the end of an array, or not an index at all
+    assert_eq!(insert_error(&mut value, "/list/2"), (1, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(insert_error(&mut value, "/list/0/2"), (2, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(insert_error(&mut value, "/list/2/x"), (1, PointerErrorKind::IndexOutOfBounds));
+    assert_eq!(insert_error(&mut value, "/list/01"), (1, PointerErrorKind::NotFound));
+    assert_eq!(insert_error(&mut value, "/list/x/y"), (1, PointerErrorKind::NotFound));
+
+    // Nothing is created for a pointer which is bad further along
+    assert_eq!(insert_error(&mut value, "/new/x~2"), (1, PointerErrorKind::BadEscape));
+    assert_eq!(insert_error(&mut value, "name"), (0, PointerErrorKind::Syntax));
+
+    let err = value.pointer_insert("/n/0", tjson!(0)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "pointer \"/n/0\": not an array or object at token 1 of the pointer"
+    );
+}
@@ -0,0 +1,172 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::io::{self, Cursor, Read};
+
+use tjson::Value;
+use tjson::de::{TrailingWhitespace, from_reader_prefix, from_reader_prefix_with,
+                from_slice_prefix, from_slice_prefix_with};
+use tjson::error::ErrorCode;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Header {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "sizes:A<u>")]
+    sizes: Vec<u64>,
+}
+
+const DOCUMENT: &str = r#"  {"name:s": "chunk", "sizes:A<u>": ["1", "2"]}"#;
+
+fn embedded() -> Vec<u8> {
+    let mut input = DOCUMENT.as_bytes().to_vec();
+    input.extend_from_slice(b"\n\t\xFF\xFF\x00\x01");
+    input
+}
+
+/// A reader which hands out at most one byte per call and counts them, to
+/// catch any reading past the document.
+struct Trickle<'a> {
+    input: &'a [u8],
+    read: usize,
+}
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.input.get(self.read) {
+            Some(&b) if !buf.is_empty() => {
+                buf[0] = b;
+                self.read += 1;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_slice_prefix() {
+    let input = embedded();
+    let (header, len) = from_slice_prefix::<Header>(&input).unwrap();
+    assert_eq!(header.name, "chunk");
+    assert_eq!(header.sizes, [1, 2]);
+    assert_eq!(len, DOCUMENT.len());
+    assert_eq!(input[len], b'\n');
+
+    let (value, len) = from_slice_prefix_with::<Value>(&input, TrailingWhitespace::Consume)
+        .unwrap();
+    assert_eq!(value["name:s"], "chunk");
+    assert_eq!(len, DOCUMENT.len() + 2);
+    assert_eq!(&input[len..], b"\xFF\xFF\x00\x01");
+
+    // Documents back to back
+    let input = b"[1][2] {}";
+    let (first, len) = from_slice_prefix::<Value>(input).unwrap();
+    let (second, rest) = from_slice_prefix::<Value>(&input[len..]).unwrap();
+    let (third, _) = from_slice_prefix::<Value>(&input[len + rest..]).unwrap();
+    assert_eq!((first, second, third), (tjson!([1]), tjson!([2]), tjson!({})));
+
+    // Consuming whitespace at the end of the input
+    let (_, len) = from_slice_prefix_with::<Value>(b"[] \n", TrailingWhitespace::Consume).unwrap();
+    assert_eq!(len, 4);
+}
+
+#[test]
+fn test_reader_prefix() {
+    let input = embedded();
+    let mut reader = Cursor::new(&input);
+    let (header, len) = from_reader_prefix::<_, Header>(&mut reader).unwrap();
+    assert_eq!(header.sizes.len(), 2);
+    assert_eq!(len, DOCUMENT.len());
+    assert_eq!(reader.position() as usize, len);
+
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"\n\t\xFF\xFF\x00\x01");
+
+    // Nothing past the closing bracket is ever read
+    let mut trickle = Trickle { input: &input, read: 0 };
+    let (value, len) = from_reader_prefix::<_, Value>(&mut trickle).unwrap();
+    assert_eq!(value["sizes:A<u>"][1], 2);
+    assert_eq!(trickle.read, len);
+    let mut next = [0];
+    trickle.read_exact(&mut next).unwrap();
+    assert_eq!(next, [b'\n']);
+
+    // Consuming the whitespace reads one byte more, which is handed back
+    let mut trickle = Trickle { input: &input, read: 0 };
+    let (_, len, peeked) =
+        from_reader_prefix_with::<_, Value>(&mut trickle, TrailingWhitespace::Consume).unwrap();
+    assert_eq!(len, DOCUMENT.len() + 2);
+    assert_eq!(peeked, Some(0xFF));
+    assert_eq!(trickle.read, len + 1);
+    trickle.read_exact(&mut next).unwrap();
+    assert_eq!(next, [0xFF]);
+
+    let (_, len, peeked) = from_reader_prefix_with::<_, Value>(
+        &b"{} "[..],
+        TrailingWhitespace::Consume,
+    ).unwrap();
+    assert_eq!((len, peeked), (3, None));
+    let (_, len, peeked) = from_reader_prefix_with::<_, Value>(
+        &b"{} x"[..],
+        TrailingWhitespace::Leave,
+    ).unwrap();
+    assert_eq!((len, peeked), (2, None));
+}
+
+#[test]
+fn test_prefix_errors() {
+    let mut input = br#"{"name:s": "chunk", "sizes:A<u>": ["1" "2"]}"#.to_vec();
+    input.extend_from_slice(b"\xFF");
+
+    let err = from_slice_prefix::<Value>(&input).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ExpectedListCommaOrEnd);
+    assert_eq!(err.byte_offset(), Some(39));
+    assert_eq!(input[39], b'"');
+    assert_eq!(err.column(), 40);
+
+    let err = from_reader_prefix::<_, Value>(Cursor::new(&input)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ExpectedListCommaOrEnd);
+    assert_eq!(err.byte_offset(), Some(39));
+
+    // Data errors carry the offset as well
+    let err = from_slice_prefix::<Header>(br#"{"name:s": 1}"#).unwrap_err();
+    assert_eq!(err.byte_offset(), Some(11));
+    assert_eq!(err.column(), 12);
+
+    // The document must be an object or an array
+    let err = from_slice_prefix::<Value>(b" 12\xFF").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ExpectedObjectOrArray);
+    assert_eq!(err.byte_offset(), Some(1));
+    let err = from_slice_prefix::<Value>(b"\xFF{}").unwrap_err();
+    assert_eq!(err.byte_offset(), Some(0));
+
+    let err = from_reader_prefix::<_, Value>(&b"  "[..]).unwrap_err();
+    assert!(err.is_eof());
+    assert_eq!(err.byte_offset(), Some(2));
+    let err = from_reader_prefix::<_, Value>(&b"[1, "[..]).unwrap_err();
+    assert!(err.is_eof());
+    assert_eq!(err.byte_offset(), Some(4));
+
+    // The other parsing functions report it too, unlike errors without a
+    // position in the input
+    let err = tjson::from_slice::<Value>(&input).unwrap_err();
+    assert_eq!(err.byte_offset(), Some(39));
+    let err = tjson::from_str::<Value>("[\"\u{e9}\\x\"]").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidEscape);
+    assert_eq!(err.byte_offset(), Some(5));
+    let err = tjson::from_value::<u8>(tjson!("x")).unwrap_err();
+    assert_eq!(err.byte_offset(), None);
+}
@@ -6,21 +6,312 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+#[macro_use]
 extern crate tjson;
 
-use tjson::{from_str, Value};
+use tjson::{from_str, Map, Value};
+use tjson::map::{Entry, MapError, ReorderError};
 
 #[test]
 fn test_map_order() {
     // Sorted order
-    #[cfg(not(feature = "preserve_order"))]
+    #[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
     const EXPECTED: &'static [&'static str] = &["a", "b", "c"];
 
     // Insertion order
-    #[cfg(feature = "preserve_order")]
+    #[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
     const EXPECTED: &'static [&'static str] = &["b", "a", "c"];
 
     let v: Value = from_str(r#"{"b":null,"a":null,"c":null}"#).unwrap();
     let keys: Vec<_> = v.as_object().unwrap().keys().collect();
     assert_eq!(keys, EXPECTED);
 }
+
+fn keys(map: &Map<String, Value>) -> Vec<&str> {
+    map.keys().map(String::as_str).collect()
+}
+
+fn sample() -> Map<String, Value> {
+    let value: Value = from_str(r#"{"c":1,"a":2,"b":3}"#).unwrap();
+    match value {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_replacing_a_value_keeps_its_place() {
+    let mut map = sample();
+    let before = keys(&map).iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(map.insert("c".to_owned(), tjson!(10)), Some(tjson!(1)));
+    match map.entry("a") {
+        Entry::Occupied(mut occupied) => assert_eq!(occupied.insert(tjson!(20)), tjson!(2)),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    *map.get_mut("b").unwrap() = tjson!(30);
+    map.extend(vec![("c".to_owned(), tjson!(100)), ("d".to_owned(), tjson!(4))]);
+
+    let mut expected = before;
+    expected.push("d".to_owned());
+    assert_eq!(keys(&map), expected);
+    assert_eq!(map["c"], tjson!(100));
+
+    let collected: Map<String, Value> =
+        vec![("b".to_owned(), tjson!(1)), ("a".to_owned(), tjson!(2)), ("b".to_owned(), tjson!(3))]
+            .into_iter()
+            .collect();
+    let expected: &[&str] =
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            &["b", "a"]
+        } else {
+            &["a", "b"]
+        };
+    assert_eq!(keys(&collected), expected);
+    assert_eq!(collected["b"], tjson!(3));
+}
+
+#[test]
+fn test_entry_counters() {
+    let mut doc = tjson!({});
+    for word in &["b", "a", "b", "c", "b"] {
+        let count = doc.as_object_mut().unwrap().entry(*word).or_insert(tjson!(0));
+        *count = (count.as_u64().unwrap() + 1).into();
+    }
+    assert_eq!((&doc["a"], &doc["b"], &doc["c"]), (&tjson!(1), &tjson!(3), &tjson!(1)));
+
+    let map = doc.as_object_mut().unwrap();
+    let mut calls = 0;
+    map.entry("a").or_insert_with(|| {
+        calls += 1;
+        tjson!(0)
+    });
+    map.entry("d").or_insert_with(|| {
+        calls += 1;
+        tjson!(0)
+    });
+    assert_eq!(calls, 1);
+    assert_eq!(map.entry("e").key(), "e");
+    assert!(!map.contains_key("e"));
+
+    let expected: &[&str] =
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            &["b", "a", "c", "d"]
+        } else {
+            &["a", "b", "c", "d"]
+        };
+    assert_eq!(keys(map), expected);
+}
+
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+#[test]
+fn test_removing_keeps_the_rest_in_order() {
+    let mut map = sample();
+    map.insert("d".to_owned(), tjson!(4));
+
+    assert_eq!(map.remove("c"), Some(tjson!(1)));
+    assert_eq!(keys(&map), ["a", "b", "d"]);
+    match map.entry("a") {
+        Entry::Occupied(occupied) => assert_eq!(occupied.remove(), tjson!(2)),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert_eq!(keys(&map), ["b", "d"]);
+
+    map.drain_filter(|key, _| key == "b");
+    map.insert("e".to_owned(), tjson!(5));
+    assert_eq!(keys(&map), ["d", "e"]);
+}
+
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+#[test]
+fn test_move_and_insert_before() {
+    let mut map = sample();
+
+    map.move_to_end("c").unwrap();
+    assert_eq!(keys(&map), ["a", "b", "c"]);
+    map.move_to_front("b").unwrap();
+    assert_eq!(keys(&map), ["b", "a", "c"]);
+
+    assert_eq!(map.insert_before("c", "d".to_owned(), tjson!(4)), Ok(None));
+    assert_eq!(keys(&map), ["b", "a", "d", "c"]);
+
+    // An existing member is moved, and its old value returned
+    assert_eq!(map.insert_before("a", "c".to_owned(), tjson!(5)), Ok(Some(tjson!(1))));
+    assert_eq!(keys(&map), ["b", "c", "a", "d"]);
+
+    // Inserting before itself replaces the value in place
+    assert_eq!(map.insert_before("a", "a".to_owned(), tjson!(6)), Ok(Some(tjson!(2))));
+    assert_eq!(keys(&map), ["b", "c", "a", "d"]);
+    assert_eq!(map["a"], tjson!(6));
+}
+
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+#[test]
+fn test_reorder() {
+    let mut map = sample();
+    map.insert("d".to_owned(), tjson!(4));
+
+    map.reorder(&["b", "d", "b"]).unwrap();
+    assert_eq!(keys(&map), ["b", "d", "c", "a"]);
+
+    map.reorder(&[]).unwrap();
+    assert_eq!(keys(&map), ["b", "d", "c", "a"]);
+}
+
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+#[test]
+fn test_reorder_missing_key_changes_nothing() {
+    let mut map = sample();
+
+    let missing = Err(ReorderError::MissingKey("x".to_owned()));
+    assert_eq!(map.move_to_end("x"), missing);
+    assert_eq!(map.move_to_front("x"), missing);
+    assert_eq!(map.insert_before("x", "d".to_owned(), tjson!(4)), missing.clone().map(|()| None));
+    assert_eq!(map.reorder(&["a", "x"]), missing);
+
+    assert_eq!(keys(&map), ["c", "a", "b"]);
+    assert!(!map.contains_key("d"));
+    assert_eq!(
+        ReorderError::MissingKey("x".to_owned()).to_string(),
+        "no member named \"x\""
+    );
+}
+
+#[cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
+#[test]
+fn test_reorder_sorted_map() {
+    let mut map = sample();
+
+    assert_eq!(map.move_to_end("a"), Err(ReorderError::Sorted));
+    assert_eq!(map.move_to_front("c"), Err(ReorderError::Sorted));
+    assert_eq!(map.insert_before("a", "d".to_owned(), tjson!(4)), Err(ReorderError::Sorted));
+    assert_eq!(map.reorder(&["c", "b", "a"]), Err(ReorderError::Sorted));
+
+    assert_eq!(keys(&map), ["a", "b", "c"]);
+    assert!(!map.contains_key("d"));
+}
+
+#[cfg(any(feature = "preserve_order", feature = "preserve_order_indexmap"))]
+#[test]
+fn test_serialized_order_follows_reorder() {
+    use tjson::ser::{KeyOrder, SerializeConfig};
+
+    let mut value = Value::Object(sample());
+    {
+        let map = value.as_object_mut().unwrap();
+        map.reorder(&["b"]).unwrap();
+        map.insert("c".to_owned(), tjson!(7));
+    }
+
+    let expected = r#"{"b:i":"3","c:i":"7","a:i":"2"}"#;
+    assert_eq!(tjson::to_string(&value).unwrap(), expected);
+    let config = SerializeConfig::new().key_order(KeyOrder::AsGiven);
+    assert_eq!(tjson::to_string_with(&config, &value).unwrap(), expected);
+}
+
+#[test]
+fn test_get_key_value() {
+    let map = sample();
+
+    let (key, value) = map.get_key_value("a").unwrap();
+    assert_eq!(key, "a");
+    assert_eq!(*value, tjson!(2));
+    assert!(map.keys().any(|stored| stored as *const String == key as *const String));
+
+    assert_eq!(map.get_key_value("d"), None);
+    assert_eq!(Map::new().get_key_value("a"), None);
+}
+
+#[test]
+fn test_key_stays_put_across_value_mutation() {
+    let mut map = sample();
+    let before = map.get_key_value("b").unwrap().0 as *const String;
+    let buffer = map.get_key_value("b").unwrap().0.as_ptr();
+
+    *map.get_mut("b").unwrap() = tjson!([1, 2, 3]);
+    for (_, value) in map.iter_mut() {
+        *value = tjson!(null);
+    }
+    map.insert("b".to_owned(), tjson!("x"));
+
+    let (key, value) = map.get_key_value("b").unwrap();
+    assert_eq!(key as *const String, before);
+    assert_eq!(key.as_ptr(), buffer);
+    assert_eq!(*value, tjson!("x"));
+}
+
+#[test]
+fn test_values_mut_follows_keys() {
+    let mut map = sample();
+    let order: Vec<String> = keys(&map).iter().map(|key| key.to_string()).collect();
+
+    let mut values = map.values_mut();
+    assert_eq!(values.len(), 3);
+    *values.next_back().unwrap() = tjson!("last");
+    assert_eq!(values.len(), 2);
+    for value in values {
+        *value = tjson!("rest");
+    }
+
+    assert_eq!(map[&order[2]], tjson!("last"));
+    assert_eq!(map[&order[0]], tjson!("rest"));
+    assert_eq!(map.values().rev().next(), Some(&tjson!("last")));
+    for (key, value) in &mut map {
+        if key == "a" {
+            *value = tjson!(0);
+        }
+    }
+    assert_eq!(map.into_iter().find(|entry| entry.0 == "a"), Some(("a".to_owned(), tjson!(0))));
+}
+
+#[test]
+fn test_object_keys() {
+    let value = Value::Object(sample());
+    let expected: &[&str] =
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            &["c", "a", "b"]
+        } else {
+            &["a", "b", "c"]
+        };
+    let names: Vec<&String> = value.object_keys().unwrap().collect();
+    assert_eq!(names, expected);
+
+    assert!(tjson!([1]).object_keys().is_none());
+    assert_eq!(tjson!({}).object_keys().unwrap().count(), 0);
+}
+
+#[test]
+fn test_replace_key() {
+    let mut map = sample();
+
+    map.replace_key("a", "z".to_owned()).unwrap();
+    assert_eq!(map["z"], tjson!(2));
+    assert!(!map.contains_key("a"));
+    let expected: &[&str] =
+        if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+            &["c", "z", "b"]
+        } else {
+            &["b", "c", "z"]
+        };
+    assert_eq!(keys(&map), expected);
+
+    map.replace_key("z", "z".to_owned()).unwrap();
+    assert_eq!(keys(&map), expected);
+}
+
+#[test]
+fn test_replace_key_errors() {
+    let mut map = sample();
+
+    let err = map.replace_key("a", "b".to_owned()).unwrap_err();
+    assert_eq!(err, MapError::KeyExists("b".to_owned()));
+    assert_eq!(err.to_string(), "a member named \"b\" already exists");
+
+    let err = map.replace_key("d", "e".to_owned()).unwrap_err();
+    assert_eq!(err, MapError::MissingKey("d".to_owned()));
+    assert_eq!(err.to_string(), "no member named \"d\"");
+
+    assert_eq!(map, sample());
+    assert_eq!(map["a"], tjson!(2));
+    assert_eq!(map["b"], tjson!(3));
+}
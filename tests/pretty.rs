@@ -0,0 +1,143 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate tjson;
+
+use serde::Serialize;
+
+use tjson::{Serializer, Value};
+use tjson::ser::{ArrayWrap, PrettyFormatter};
+
+fn to_string_filled<T: Serialize>(value: &T, indent: &[u8], width: usize) -> String {
+    let formatter = PrettyFormatter::with_indent(indent).array_wrap(ArrayWrap::Fill(width));
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
+#[test]
+fn test_fill_float_array() {
+    let floats: Vec<f64> = (0..1000).map(|i| i as f64 * 0.25).collect();
+    let output = to_string_filled(&floats, b"  ", 80);
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[0], "[");
+    assert_eq!(
+        lines[1],
+        "  0.0, 0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 2.25, 2.5, 2.75, 3.0, 3.25,"
+    );
+    assert_eq!(lines[lines.len() - 1], "]");
+
+    for window in lines[1..lines.len() - 1].windows(2) {
+        let (line, next) = (window[0], window[1]);
+        assert!(line.len() <= 80, "line too long: {:?}", line);
+        assert!(line.starts_with("  ") && !line.starts_with("   "));
+        assert!(!line.ends_with(' '));
+        assert!(line.ends_with(','));
+
+        // Lines are packed: the first element of the next line did not fit
+        let next_element = next.trim().split(", ").next().unwrap();
+        assert!(line.len() + 1 + next_element.len() + 1 > 80);
+    }
+
+    let last = lines[lines.len() - 2];
+    assert!(last.ends_with("249.75"));
+
+    let parsed: Vec<f64> = tjson::from_str(&output).unwrap();
+    assert_eq!(parsed, floats);
+}
+
+#[test]
+fn test_fill_respects_indent() {
+    let value = tjson!({ "a": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10] });
+    let expected = "{\n\t\"a:A<i>\": [\n\t\t\"1\", \"2\", \"3\",\n\t\t\"4\", \"5\", \"6\",\n\
+                    \t\t\"7\", \"8\", \"9\",\n\t\t\"10\"\n\t]\n}";
+
+    // Tabs count as one column each
+    assert_eq!(to_string_filled(&value, b"\t", 16), expected);
+}
+
+#[test]
+fn test_fill_long_elements() {
+    let value = tjson!(["a long string element", "b", "c"]);
+    let expected = "[\n  \"a long string element\",\n  \"b\", \"c\"\n]";
+
+    assert_eq!(to_string_filled(&value, b"  ", 10), expected);
+}
+
+#[test]
+fn test_fill_falls_back_for_nested_containers() {
+    let value = tjson!([1, 2, { "x": [3, 4] }, 5]);
+    let expected = r#"[
+  1,
+  2,
+  {
+    "x:A<i>": [
+      "3", "4"
+    ]
+  },
+  5
+]"#;
+
+    assert_eq!(to_string_filled(&value, b"  ", 80), expected);
+
+    let value = tjson!([[1, 2], []]);
+    let expected = "[\n  [\n    1, 2\n  ],\n  []\n]";
+
+    assert_eq!(to_string_filled(&value, b"  ", 80), expected);
+}
+
+#[test]
+fn test_fill_empty_array() {
+    assert_eq!(to_string_filled(&tjson!([]), b"  ", 80), "[]");
+    assert_eq!(
+        to_string_filled(&tjson!({ "a:A<i>": [] }), b"  ", 80),
+        "{\n  \"a:A<i>\": []\n}"
+    );
+}
+
+#[test]
+fn test_fill_idempotent() {
+    let value = tjson!({
+        "matrix:A<A<f>>": [[1.5, 2.5, 3.5], [4.5, 5.5, 6.5]],
+        "names:A<s>": ["alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta"],
+        "nested:O": { "flags:A<b>": [true, false, true, true, false] }
+    });
+
+    let first = to_string_filled(&value, b"  ", 30);
+    let reparsed: Value = tjson::from_str(&first).unwrap();
+    assert_eq!(reparsed, value);
+
+    let second = to_string_filled(&reparsed, b"  ", 30);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_trailing_newline() {
+    let cases = vec![
+        (tjson!(1), ArrayWrap::OnePerLine, "1\n"),
+        (tjson!("a"), ArrayWrap::OnePerLine, "\"a\"\n"),
+        (tjson!([]), ArrayWrap::OnePerLine, "[]\n"),
+        (
+            tjson!({ "a": ["b"] }),
+            ArrayWrap::OnePerLine,
+            "{\n  \"a:A<s>\": [\n    \"b\"\n  ]\n}\n",
+        ),
+        (tjson!([1, 2]), ArrayWrap::Fill(80), "[\n  1, 2\n]\n"),
+        (tjson!([[1], 2]), ArrayWrap::Fill(80), "[\n  [\n    1\n  ],\n  2\n]\n"),
+    ];
+
+    for (value, array_wrap, expected) in cases {
+        let formatter = PrettyFormatter::new().array_wrap(array_wrap).trailing_newline(true);
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), expected);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::error::ErrorCode;
+use tjson::repair;
+
+const LEGACY: &str = include_str!("repair/legacy.tjson");
+
+const REPAIRED: &str = concat!(
+    r#"{"a-count:u":"42","b-big:i":"9007199254740993","c-ids:A<u>":["1","2","3"],"#,
+    r#""d-at:t":"2017-03-04T04:06:07Z","e-name:s":"new","f-label:s":"42","#,
+    r#""g-nested:O":{"n:i":"-7","ratio:f":0.5}}"#
+);
+
+#[derive(Debug, Deserialize)]
+struct Counter {
+    #[serde(rename = "count:i")]
+    count: i64,
+}
+
+#[test]
+fn test_rewrite_fixture() {
+    assert_eq!(repair::rewrite(LEGACY).unwrap(), REPAIRED);
+
+    // The output needs no further repair, and reads back strictly
+    let (output, repairs) = repair::rewrite_with_report(REPAIRED).unwrap();
+    assert_eq!(output, REPAIRED);
+    assert!(repairs.is_empty());
+    let value: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(value["g-nested:O"]["n:i"], -7);
+}
+
+#[test]
+fn test_repair_report() {
+    let (_, repairs) = repair::rewrite_with_report(LEGACY).unwrap();
+    let report: Vec<String> = repairs.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        report,
+        [
+            "integer 42 wasn't quoted at /a-count:u",
+            "integer 9007199254740993 wasn't quoted at /b-big:i",
+            "integer 1 wasn't quoted at /c-ids:A<u>/0",
+            "integer 3 wasn't quoted at /c-ids:A<u>/2",
+            "timestamp 2017-03-04T05:06:07+01:00 converted to UTC at /d-at:t",
+            "duplicate member, kept the last at /e-name:s",
+            "integer -7 wasn't quoted at /g-nested:O/n:i",
+        ]
+    );
+    assert_eq!(repairs[2].pointer(), "/c-ids:A<u>/0");
+    assert_eq!(repairs[2].description(), "integer 1 wasn't quoted");
+}
+
+#[test]
+fn test_strict_rejects_original() {
+    // The first integer which wasn't quoted
+    let err = tjson::from_str::<Value>(LEGACY).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+
+    // Without `quoted_integers`, a quoted integer is only a string
+    let input = r#"{"count:i": "42"}"#;
+    let mut de = Deserializer::from_str(input).quoted_integers(false);
+    assert_eq!(Counter::deserialize(&mut de).unwrap_err().code(), ErrorCode::Data);
+    let mut de = Deserializer::from_str(input).quoted_integers(false);
+    assert_eq!(Value::deserialize(&mut de).unwrap()["count:i"], "42");
+}
+
+#[test]
+fn test_quoted_integer_exactness() {
+    let (output, _) = repair::rewrite_with_report(r#"{"n:u": 9007199254740993}"#).unwrap();
+    assert_eq!(output, r#"{"n:u":"9007199254740993"}"#);
+    let value: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(value["n:u"].as_u64(), Some(9007199254740993));
+
+    let input = r#"{"count:i": "-9007199254740993"}"#;
+    assert_eq!(tjson::from_str::<Counter>(input).unwrap().count, -9007199254740993);
+
+    let input = r#"{"count:i": "9223372036854775807"}"#;
+    assert_eq!(tjson::from_str::<Counter>(input).unwrap().count, i64::max_value());
+}
+
+#[test]
+fn test_quoted_integer_errors() {
+    for input in &[r#"{"n:i": "9223372036854775808"}"#, r#"{"n:u": "-1"}"#] {
+        let err = repair::rewrite(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    }
+
+    // Strings which aren't integers don't match the tag, and aren't repaired
+    for text in &["4.0", "+4", "04", "", "-", "1e3", " 4"] {
+        let input = format!(r#"{{"n:i": "{}"}}"#, text);
+        let err = repair::rewrite(&input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", text);
+    }
+}
+
+#[test]
+fn test_parser_option() {
+    let mut parser = tjson::de::Parser::new();
+    let counter: Counter = parser.parse_str(r#"{"count:i": "7"}"#).unwrap();
+    assert_eq!(counter.count, 7);
+
+    let mut parser = tjson::de::Parser::new().quoted_integers(false);
+    assert!(parser.parse_str::<Counter>(r#"{"count:i": "7"}"#).is_err());
+}
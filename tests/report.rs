@@ -0,0 +1,129 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::de::ParseReport;
+
+const DOCUMENT: &str = concat!(
+    r#"{"a:A<A<u>>": [["1"], ["2", "3"]], "#,
+    r#""b:O": {"c:s": "x\ny", "d:s": "\u00e9\ud83d\ude00"}, "#,
+    r#""\u0065:s": ""}"#,
+    " \n",
+);
+
+fn expected() -> ParseReport {
+    ParseReport {
+        // The object, the array of a and the arrays inside it
+        max_depth: 3,
+        // a, b, c, d and e
+        members: 5,
+        // [1], [2, 3], 1, 2 and 3
+        elements: 5,
+        bytes: DOCUMENT.len(),
+        // \n, é, the surrogate pair and the e of a name
+        escapes: 4,
+        // Two bytes for the e with an acute accent and four for the emoji
+        longest_string: 6,
+    }
+}
+
+#[test]
+fn test_report() {
+    let (value, report) = tjson::from_str_with_report::<Value>(DOCUMENT).unwrap();
+    assert_eq!(value["e:s"], tjson!(""));
+    assert_eq!(report, expected());
+
+    let (_, report) = tjson::from_slice_with_report::<Value>(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(report, expected());
+
+    let (_, report) = tjson::from_reader_with_report::<_, Value>(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(report, expected());
+
+    // Values which are skipped are still counted
+    #[derive(Deserialize)]
+    struct OnlyE {
+        #[serde(rename = "e:s")]
+        e: String,
+    }
+    let (only, report) = tjson::from_str_with_report::<OnlyE>(DOCUMENT).unwrap();
+    assert_eq!(only.e, "");
+    assert_eq!(report, expected());
+
+    let (_, report) = tjson::from_str_with_report::<Value>("\"scalar\"").unwrap();
+    assert_eq!(
+        report,
+        ParseReport {
+            bytes: 8,
+            longest_string: 6,
+            ..ParseReport::default()
+        }
+    );
+}
+
+#[test]
+fn test_report_from_deserializer() {
+    let mut de = Deserializer::from_str("[[1], {}] [2]");
+    assert_eq!(de.report(), ParseReport::default());
+
+    let first = Value::deserialize(&mut de).unwrap();
+    assert_eq!(first, tjson!([[1], {}]));
+    let report = de.report();
+    assert_eq!((report.max_depth, report.elements, report.bytes), (2, 3, 9));
+
+    // Counts carry on across values
+    Value::deserialize(&mut de).unwrap();
+    let report = de.report();
+    assert_eq!((report.max_depth, report.elements, report.bytes), (2, 4, 13));
+
+    // And show how far a failed parse got
+    let mut de = Deserializer::from_str(r#"{"a": [1, 2, "three"]}"#);
+    let err = <(String, Vec<u8>)>::deserialize(&mut de).unwrap_err();
+    assert!(err.is_data());
+    let report = de.report();
+    assert_eq!((report.members, report.elements), (0, 0));
+
+    let mut de = Deserializer::from_str(r#"{"a": [1, 2, "three"]}"#);
+    assert!(<std::collections::BTreeMap<String, Vec<u8>>>::deserialize(&mut de).is_err());
+    let report = de.report();
+    assert_eq!((report.max_depth, report.members, report.elements), (2, 1, 3));
+}
+
+#[test]
+fn test_report_cost() {
+    let mut input = String::from("[");
+    for i in 0..2000 {
+        if i > 0 {
+            input.push(',');
+        }
+        input.push_str(&format!(r#"{{"id:u": "{}", "tags:A<s>": ["a\tb", "c"], "n:O": {{}}}}"#, i));
+    }
+    input.push(']');
+
+    // The counters are kept either way, so asking for them costs nothing
+    // more than building the report at the end
+    let plain = alloc::allocations(|| drop(tjson::from_str::<Value>(&input).unwrap()));
+    let reported =
+        alloc::allocations(|| drop(tjson::from_str_with_report::<Value>(&input).unwrap()));
+    assert!(plain > 0);
+    assert_eq!(reported, plain);
+
+    let (_, report) = tjson::from_str_with_report::<Value>(&input).unwrap();
+    assert_eq!(report.members, 6000);
+    assert_eq!(report.elements, 2000 * 3);
+    assert_eq!(report.escapes, 2000);
+}
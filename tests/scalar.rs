@@ -0,0 +1,117 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+extern crate serde_bytes;
+#[macro_use]
+extern crate tjson;
+
+use chrono::datetime::DateTime;
+use chrono::offset::utc::UTC;
+use serde_bytes::ByteBuf;
+use tjson::{Tag, Value};
+use tjson::error::ErrorCode;
+use tjson::ser::DataEncoding;
+
+fn tag(text: &str) -> Tag {
+    Tag::parse(text).unwrap()
+}
+
+#[test]
+fn test_timestamp() {
+    let at: DateTime<UTC> =
+        tjson::from_str_scalar(r#" "2016-11-06T22:27:34Z" "#, Tag::Timestamp).unwrap();
+    assert_eq!(at, "2016-11-06T22:27:34Z".parse::<DateTime<UTC>>().unwrap());
+
+    // Offsets are rejected as they are in members
+    let input = r#""2016-11-06T22:27:34+01:00""#;
+    let err = tjson::from_str_scalar::<DateTime<UTC>>(input, Tag::Timestamp).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NonUtcTimestamp);
+}
+
+#[test]
+fn test_data() {
+    let bytes: ByteBuf = tjson::from_str_scalar(r#""AQID""#, Tag::Data(DataEncoding::Plain))
+        .unwrap();
+    assert_eq!(bytes.as_ref(), [1, 2, 3]);
+    let bytes: Vec<u8> = tjson::from_str_scalar(r#""0a0b""#, Tag::Data(DataEncoding::Base16))
+        .unwrap();
+    assert_eq!(bytes, [10, 11]);
+
+    let nested: Vec<Vec<u8>> = tjson::from_str_as(r#"["AQ", "Ag"]"#, &tag("A<d64>")).unwrap();
+    assert_eq!(nested, [vec![1], vec![2]]);
+
+    let err = tjson::from_str_scalar::<Vec<u8>>(r#""zz""#, Tag::Data(DataEncoding::Base16))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "invalid byte at offset 0 at line 1 column 4");
+}
+
+#[test]
+fn test_integers() {
+    assert_eq!(tjson::from_str_scalar::<i64>(r#""-7""#, Tag::Int).unwrap(), -7);
+    let max: u64 = tjson::from_str_scalar(r#""18446744073709551615""#, Tag::UInt).unwrap();
+    assert_eq!(max, u64::max_value());
+    assert_eq!(tjson::from_str_scalar::<u8>(" \"7\"\n", Tag::UInt).unwrap(), 7);
+
+    // Integers are written as strings, so numbers don't match the tag
+    let err = tjson::from_str_scalar::<i64>("-7", Tag::UInt).unwrap_err();
+    assert_eq!(err.to_string(), "expected a value tagged `u`, found number -7 at line 1 column 2");
+    let err = tjson::from_str_scalar::<i64>("18446744073709551615", Tag::Int).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+}
+
+#[test]
+fn test_mismatch() {
+    let err = tjson::from_str_scalar::<String>("true", Tag::Timestamp).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+    assert!(err.is_data());
+
+    let mismatch = err.tag_mismatch().unwrap();
+    assert_eq!(*mismatch.expected(), Tag::Timestamp);
+    // Rejected before it is read, so only its kind
+    assert_eq!(mismatch.found(), "boolean");
+    assert_eq!(mismatch.pointer(), "");
+
+    let err = tjson::from_str_as::<Value>(r#"{"a:s": "b"}"#, &tag("A<s>")).unwrap_err();
+    assert_eq!(err.to_string(), "expected a value tagged `A<s>`, found object at line 1 column 1");
+    let err = tjson::from_str_as::<Value>(r#"[["a"], ["b", 2]]"#, &tag("S<A<s>>")).unwrap_err();
+    assert_eq!(err.tag_mismatch().unwrap().pointer(), "/1/1");
+
+    // Syntax errors come first
+    let err = tjson::from_str_scalar::<String>(r#""open"#, Tag::String).unwrap_err();
+    assert!(err.is_eof());
+}
+
+#[test]
+fn test_parse_non_ascii() {
+    for text in &["€x>", "A€>", "S<€>", "A<€", "é", "A<<>>", "A<>"] {
+        assert_eq!(Tag::parse(text), None, "{:?}", text);
+    }
+    assert!(tjson::from_str::<Value>(r#"{"a:€x>":1}"#).is_err());
+}
+
+#[test]
+#[should_panic(expected = "`O` is not the tag of a scalar")]
+fn test_scalar_of_container_tag() {
+    let _ = tjson::from_str_scalar::<Value>("{}", Tag::Object);
+}
+
+#[test]
+fn test_untagged_entry_points_unchanged() {
+    // Without a tag, a timestamp is only a string and data can't be decoded
+    let value: Value = tjson::from_str(r#""2016-11-06T22:27:34Z""#).unwrap();
+    assert_eq!(value, tjson!("2016-11-06T22:27:34Z"));
+    assert!(tjson::from_str::<Vec<u8>>(r#""AQID""#).is_err());
+    assert!(tjson::from_str::<String>("true").is_err());
+
+    // Nor do documents which aren't a single value start to parse
+    for input in &["", "1 2", r#""a" "b""#] {
+        assert!(tjson::from_str::<Value>(input).is_err(), "{:?}", input);
+        assert!(tjson::from_str_as::<Value>(input, &Tag::String).is_err(), "{:?}", input);
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::Value;
+use tjson::schema::{self, Problem, Schema};
+use tjson::value::ValueKind;
+
+fn samples() -> Vec<Value> {
+    vec![
+        tjson!({
+            "id": 1,
+            "name": "alice",
+            "roles": ["admin", "ops"],
+            "address": { "city": "London", "postcode": "SW1A" },
+        }),
+        tjson!({
+            "id": "b-2",
+            "name": "bob",
+            "roles": [],
+            "address": { "city": "Paris" },
+            "manager": 1,
+        }),
+        tjson!({
+            "id": 3,
+            "name": "carol",
+            "roles": ["dev", { "team": "core" }],
+            "address": { "city": "Berlin", "postcode": "10115" },
+        }),
+    ]
+}
+
+/// Violation messages, sorted since member order depends on the map backing
+fn messages(schema: &Schema, value: &Value) -> Vec<String> {
+    let mut messages: Vec<String> = match schema.check(value) {
+        Ok(()) => Vec::new(),
+        Err(violations) => violations.iter().map(ToString::to_string).collect(),
+    };
+    messages.sort();
+    messages
+}
+
+#[test]
+fn test_infer() {
+    let schema = schema::infer(&samples());
+
+    assert_eq!(schema.kinds().iter().cloned().collect::<Vec<_>>(), [ValueKind::Object]);
+
+    // Conflicting kinds become a union
+    let id = schema.member("id").unwrap();
+    assert!(!id.is_optional());
+    assert_eq!(
+        id.schema().kinds().iter().cloned().collect::<Vec<_>>(),
+        [ValueKind::Number, ValueKind::String]
+    );
+
+    assert!(schema.member("manager").unwrap().is_optional());
+    assert!(!schema.member("address").unwrap().is_optional());
+
+    let address = schema.member("address").unwrap().schema();
+    assert!(!address.member("city").unwrap().is_optional());
+    assert!(address.member("postcode").unwrap().is_optional());
+
+    let roles = schema.member("roles").unwrap().schema().items().unwrap();
+    assert_eq!(
+        roles.kinds().iter().cloned().collect::<Vec<_>>(),
+        [ValueKind::String, ValueKind::Object]
+    );
+    assert!(roles.member("team").is_some());
+
+    for sample in &samples() {
+        assert!(schema.check(sample).is_ok());
+    }
+}
+
+#[test]
+fn test_check_conforming() {
+    let schema = schema::infer(&samples());
+
+    let value = tjson!({
+        "id": "d-4",
+        "name": "dave",
+        "roles": [{ "team": "infra" }, "ops", "dev"],
+        "address": { "city": "Oslo" },
+        "manager": 3,
+    });
+    assert_eq!(messages(&schema, &value), Vec::<String>::new());
+}
+
+#[test]
+fn test_check_violating() {
+    let schema = schema::infer(&samples());
+
+    let value = tjson!({
+        "id": true,
+        "roles": ["dev", 7, { "team": "x", "lead": true }],
+        "address": "Rome",
+        "a/b": 1,
+    });
+    assert_eq!(
+        messages(&schema, &value),
+        [
+            "expected number or string, found boolean at /id",
+            "expected object, found string at /address",
+            "expected string or object, found number at /roles/1",
+            "missing member at /name",
+            "unexpected member at /a~1b",
+            "unexpected member at /roles/2/lead",
+        ]
+    );
+
+    let violations = schema.check(&tjson!([])).unwrap_err();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].path(), "");
+    assert_eq!(
+        *violations[0].problem(),
+        Problem::Kind {
+            expected: vec![ValueKind::Object],
+            found: ValueKind::Array,
+        }
+    );
+    assert_eq!(violations[0].to_string(), "expected object, found array");
+}
+
+#[test]
+fn test_empty_schema_accepts_anything() {
+    let schema = schema::infer(&[]);
+    assert!(schema.check(&tjson!({ "anything": [1, "at", "all"] })).is_ok());
+
+    // Nothing is known about the elements of arrays which were always empty
+    let schema = schema::infer(&[tjson!({ "list": [] })]);
+    assert!(schema.check(&tjson!({ "list": [1, "two"] })).is_ok());
+}
+
+#[test]
+fn test_schema_round_trip() {
+    let schema = schema::infer(&samples());
+
+    let text = tjson::to_string(&schema).unwrap();
+    let parsed: Schema = tjson::from_str(&text).unwrap();
+    assert_eq!(parsed, schema);
+
+    let text = tjson::to_string_pretty(&schema).unwrap();
+    let parsed: Schema = tjson::from_str(&text).unwrap();
+    assert_eq!(parsed, schema);
+
+    let simple = schema::infer(&[tjson!({ "n": 1 })]);
+    let expected = r#"{"kinds:A<s>":["object"],"members:A<O>":[{"name:s":"n","optional:b":false,"schema:O":{"kinds:A<s>":["number"]}}]}"#;
+    assert_eq!(tjson::to_string(&simple).unwrap(), expected);
+    assert_eq!(tjson::from_str::<Schema>(expected).unwrap(), simple);
+}
+
+#[test]
+fn test_invalid_schema_document() {
+    let cases = &[
+        ("[]", "invalid schema: expected a schema object, found array"),
+        (r#"{"kinds:A<s>": ["integer"]}"#, "invalid schema: unknown kind \"integer\""),
+        (
+            r#"{"kinds:A<s>": "object"}"#,
+            "expected a value tagged `A<s>`, found string at /kinds:A<s> at line 1 column 16",
+        ),
+        (
+            r#"{"members:A<O>": [{"name:s": "a", "optional:b": false}]}"#,
+            "invalid schema: expected a schema object, found undefined",
+        ),
+    ];
+
+    for &(input, message) in cases {
+        let err = tjson::from_str::<Schema>(input).unwrap_err();
+        assert_eq!(err.to_string(), message);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compile-time checks of which public types can be shared between and sent
+//! to other threads. A type losing one of these bounds fails to build this
+//! file rather than a user's service.
+
+#[macro_use]
+extern crate tjson;
+
+use std::fs::File;
+use std::io;
+use std::net::TcpStream;
+
+use tjson::{DateTime, Deserializer, Error, Map, Number, Serializer, Set, StreamDeserializer,
+            Value};
+use tjson::cst::{Document, Node};
+use tjson::de::{DuplicateKeys, Events, IoRead, ParseReport, Parser, SliceRead, StrRead};
+use tjson::display::DisplayConfig;
+use tjson::envelope::Envelope;
+use tjson::error::{Category, ErrorCode};
+use tjson::event::Event;
+#[cfg(not(feature = "preserve_order"))]
+use tjson::map::Entry;
+use tjson::schema::{Schema, SchemaViolation};
+use tjson::ser::{CompactFormatter, OutputBudgetExceeded, PrettyFormatter, SerializeConfig};
+use tjson::transform::Matcher;
+use tjson::value::{CompactValue, ConversionError, CowValue, PointerError, StatsLimits,
+                   ValueKind, ValueStats};
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_values_are_send_sync() {
+    assert_send_sync::<Value>();
+    assert_send_sync::<Map<String, Value>>();
+    assert_send_sync::<Set<Value>>();
+    assert_send_sync::<Number>();
+    assert_send_sync::<DateTime>();
+    assert_send_sync::<ValueKind>();
+    assert_send_sync::<CompactValue>();
+    assert_send_sync::<CowValue<'static>>();
+    // linked_hash_map entries hold raw pointers, see the docs of `Entry`
+    #[cfg(not(feature = "preserve_order"))]
+    assert_send_sync::<Entry<'static>>();
+    assert_send_sync::<Envelope<Value>>();
+    assert_send_sync::<Event<'static>>();
+    assert_send_sync::<Document>();
+    assert_send_sync::<Node>();
+    assert_send_sync::<Schema>();
+    assert_send_sync::<ValueStats>();
+    assert_send_sync::<StatsLimits>();
+}
+
+#[test]
+fn test_errors_are_send_sync() {
+    // Needed to box them as `Box<std::error::Error + Send + Sync>` and to
+    // return them from spawned threads and futures
+    assert_send_sync::<Error>();
+    assert_send_sync::<ErrorCode>();
+    assert_send_sync::<Category>();
+    assert_send_sync::<ConversionError>();
+    assert_send_sync::<PointerError>();
+    assert_send_sync::<SchemaViolation>();
+    assert_send_sync::<OutputBudgetExceeded>();
+
+    let err: Box<std::error::Error + Send + Sync> =
+        Box::new(tjson::from_str::<Value>("[").unwrap_err());
+    let err: io::Error = io::Error::new(io::ErrorKind::InvalidData, err);
+    assert!(err.to_string().starts_with("unexpected end of input"), "{}", err);
+}
+
+#[test]
+fn test_readers_are_send() {
+    fn assert_deserializer_send<R: io::Read + Send>() {
+        assert_send::<Deserializer<IoRead<R>>>();
+        assert_send::<StreamDeserializer<'static, IoRead<R>, Value>>();
+        assert_send::<Events<'static, IoRead<R>>>();
+    }
+    assert_deserializer_send::<File>();
+    assert_deserializer_send::<TcpStream>();
+    assert_deserializer_send::<io::Cursor<Vec<u8>>>();
+
+    assert_send_sync::<Deserializer<SliceRead<'static>>>();
+    assert_send_sync::<Deserializer<StrRead<'static>>>();
+    assert_send_sync::<Parser>();
+    assert_send_sync::<ParseReport>();
+    assert_send_sync::<DuplicateKeys>();
+    assert_send_sync::<Matcher>();
+}
+
+#[test]
+fn test_writers_are_send() {
+    assert_send::<Serializer<Vec<u8>>>();
+    assert_send::<Serializer<File, PrettyFormatter<'static>>>();
+    assert_sync::<Serializer<Vec<u8>, CompactFormatter>>();
+    assert_send_sync::<SerializeConfig<'static>>();
+    assert_send_sync::<DisplayConfig>();
+}
+
+#[test]
+fn test_send_across_threads() {
+    let value = tjson::from_str::<Value>(r#"{"a:A<u>": ["1", "2"]}"#).unwrap();
+    let handle = std::thread::spawn(move || {
+        let err = tjson::from_str::<Value>("{").unwrap_err();
+        (tjson::to_string(&value).unwrap(), err)
+    });
+    let (text, err) = handle.join().unwrap();
+
+    // The alternative to moving an `Entry` into a thread
+    let mut map = Map::new();
+    map.insert("n".to_owned(), tjson!(0));
+    let map = std::thread::spawn(move || {
+        if let Some(n) = map.get_mut("n") {
+            *n = tjson!(1);
+        }
+        map
+    }).join()
+        .unwrap();
+    assert_eq!(map["n"], 1);
+    assert_eq!(text, r#"{"a:A<u>":["1","2"]}"#);
+    assert!(err.is_eof());
+}
+
+#[test]
+fn test_prelude() {
+    use tjson::prelude::*;
+
+    let value: Value = from_str(r#"{"n:i": "1"}"#).unwrap();
+    let result: Result<Number, Error> = tjson::from_value(tjson!(1));
+    assert_eq!(value["n:i"], 1);
+    assert!(result.is_ok());
+    let map: Map<String, Value> = Map::new();
+    let set: Set<Value> = Set::new();
+    assert_eq!(to_string(&tjson!({ "a:A<i>": [] })).unwrap(), r#"{"a:A<i>":[]}"#);
+    assert_send_sync::<(Value, Map<String, Value>, Set<Value>, Number, Error)>();
+    drop((map, set));
+}
@@ -0,0 +1,202 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::{Set, Value};
+
+#[test]
+fn test_set_insert() {
+    let mut set = Set::new();
+
+    for i in 0..100 {
+        assert!(set.insert(tjson!(i % 50 + 1)) == (i < 50));
+    }
+    assert!(set.insert(tjson!({ "a": [1, 2] })));
+    assert!(!set.insert(tjson!({ "a": [1, 2] })));
+
+    assert_eq!(set.len(), 51);
+    assert!(set.contains(&tjson!(50)));
+    assert!(set.contains(&tjson!({ "a": [1, 2] })));
+    assert!(!set.contains(&tjson!(51)));
+}
+
+#[test]
+fn test_set_remove_and_clear() {
+    let mut set = Set::new();
+    for i in 0..5 {
+        set.insert(tjson!(i));
+    }
+
+    assert!(set.remove(&tjson!(2)));
+    assert!(!set.remove(&tjson!(2)));
+    assert!(!set.contains(&tjson!(2)));
+    assert_eq!(elements(&set), [tjson!(0), tjson!(1), tjson!(3), tjson!(4)]);
+
+    set.clear();
+    assert!(set.is_empty());
+    assert!(set.insert(tjson!(2)));
+}
+
+#[test]
+fn test_edit_parsed_set_member() {
+    let mut value: Value = tjson::from_str(r#"{"ids:S<i>": ["3", "1"]}"#).unwrap();
+
+    {
+        let ids = value["ids:S<i>"].as_set_mut().unwrap();
+        assert!(ids.insert(tjson!(2)));
+        assert!(!ids.insert(tjson!(3)));
+        assert!(ids.remove(&tjson!(1)));
+    }
+    assert!(value["ids:S<i>"].is_set());
+    assert_eq!(value["ids:S<i>"].as_set().unwrap().len(), 2);
+
+    assert_eq!(tjson::to_string(&value).unwrap(), r#"{"ids:S<i>":["2","3"]}"#);
+}
+
+#[test]
+fn test_hash_fingerprint_is_stable() {
+    // These must not change between releases, platforms, or Rust versions
+    assert_eq!(tjson!(true).hash_fingerprint(), 0x082f_2307_b4e8_8e77);
+    assert_eq!(tjson!("foo").hash_fingerprint(), 0x6220_4172_43fd_5e08);
+    assert_eq!(
+        tjson!({ "a": [1, -2, 3.5] }).hash_fingerprint(),
+        0xccd2_20f4_ffe0_f175
+    );
+}
+
+#[test]
+fn test_hash_fingerprint_matches_equality() {
+    let parsed: Value = tjson::from_str(r#"{"a": {}, "b": [1, 2.5, "x"]}"#).unwrap();
+    let built = tjson!({ "a": {}, "b": [1, 2.5, "x"] });
+
+    assert_eq!(parsed, built);
+    assert_eq!(parsed.hash_fingerprint(), built.hash_fingerprint());
+
+    assert_eq!(tjson!(0.0), tjson!(-0.0));
+    assert_eq!(tjson!(0.0).hash_fingerprint(), tjson!(-0.0).hash_fingerprint());
+
+    assert!(tjson!([1, 2]).hash_fingerprint() != tjson!([2, 1]).hash_fingerprint());
+    assert!(tjson!("1").hash_fingerprint() != tjson!(1).hash_fingerprint());
+    assert!(tjson!([[]]).hash_fingerprint() != tjson!([[], []]).hash_fingerprint());
+}
+
+fn elements(set: &Set<Value>) -> Vec<Value> {
+    set.iter().cloned().collect()
+}
+
+fn letters(letters: &[&str]) -> Set<Value> {
+    let mut set = Set::new();
+    for letter in letters {
+        set.insert(tjson!(letter));
+    }
+    set
+}
+
+#[test]
+fn test_map_in_place() {
+    let mut set = letters(&["a", "b", "c"]);
+    let collapsed = set.map_in_place(|value| tjson!(format!("{}!", value.as_str().unwrap())));
+    assert_eq!(collapsed, 0);
+    assert_eq!(set, letters(&["a!", "b!", "c!"]));
+
+    // Equal results are collapsed into one
+    let collapsed = set.map_in_place(|_| tjson!("same"));
+    assert_eq!(collapsed, 2);
+    assert_eq!(elements(&set), [tjson!("same")]);
+
+    let mut empty = Set::new();
+    assert_eq!(empty.map_in_place(|value| value), 0);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_map_in_place_panic() {
+    let mut set = letters(&["d", "c", "b", "a"]);
+    let mut seen = 0;
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        set.map_in_place(|value| {
+            seen += 1;
+            if seen == 3 {
+                panic!("transform failed");
+            }
+            tjson!([value])
+        })
+    }));
+    assert!(result.is_err());
+
+    // Only the elements already replaced are left, and the set still works
+    assert_eq!(set.len(), 2);
+    assert!(set.iter().all(|value| value.is_array()));
+    assert!(!set.insert(elements(&set)[0].clone()));
+    assert!(set.insert(tjson!("e")));
+}
+
+#[test]
+fn test_edit() {
+    let mut set = letters(&["a", "b", "c"]);
+    {
+        let mut editor = set.edit();
+        assert_eq!(editor.len(), 3);
+        editor.retain(|value| value != "b");
+        for element in editor.iter_mut() {
+            *element = tjson!({ "letter": element.clone() });
+        }
+        editor.push(tjson!({ "letter": "a" }));
+        editor.push(tjson!(1));
+        assert_eq!(editor.finish(), 1);
+    }
+    assert_eq!(set.len(), 3);
+    assert!(set.contains(&tjson!({ "letter": "c" })));
+    assert!(set.contains(&tjson!(1)));
+
+    // Dropping the editor puts the elements back too
+    {
+        let mut editor = set.edit();
+        editor.clear();
+        editor.extend(vec![tjson!(2), tjson!(2), tjson!(3)]);
+    }
+    assert_eq!(elements(&set), [tjson!(2), tjson!(3)]);
+}
+
+#[test]
+fn test_edit_panic() {
+    let mut set = letters(&["a", "b"]);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut editor = set.edit();
+        editor[0] = tjson!("b");
+        panic!("edit failed");
+    }));
+    assert!(result.is_err());
+
+    // The editor was dropped while unwinding, collapsing the duplicate
+    assert_eq!(elements(&set), [tjson!("b")]);
+}
+
+#[test]
+fn test_order_of_survivors() {
+    let mut set = letters(&["c", "a", "b", "d"]);
+    let collapsed = set.map_in_place(|value| match value.as_str() {
+        Some("b") | Some("d") => tjson!("a"),
+        _ => value,
+    });
+    assert_eq!(collapsed, 2);
+
+    let mut editor = set.edit();
+    editor.insert(0, tjson!("z"));
+    editor.push(tjson!("c"));
+    assert_eq!(editor.finish(), 1);
+
+    // The first of the equal elements is kept where it was
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(elements(&set), [tjson!("z"), tjson!("c"), tjson!("a")]);
+    } else {
+        assert_eq!(elements(&set), [tjson!("a"), tjson!("c"), tjson!("z")]);
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+extern crate tjson;
+
+#[path = "common/alloc.rs"]
+mod alloc;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+use tjson::{Deserializer, Set, Value};
+use tjson::de::{DuplicateElements, Parser, SetUniqueness};
+use tjson::error::ErrorCode;
+use tjson::transform::Matcher;
+
+const ELEMENTS: usize = 20_000;
+
+/// A set of large distinct strings
+fn large_set() -> String {
+    let elements: Vec<String> = (0..ELEMENTS).map(|i| format!("\"{:0200}\"", i)).collect();
+    format!(r#"{{"ids:S<s>": [{}]}}"#, elements.join(", "))
+}
+
+fn check(input: &str, uniqueness: SetUniqueness) -> tjson::Result<()> {
+    let mut de = Deserializer::from_str(input).set_uniqueness(uniqueness);
+    try!(IgnoredAny::deserialize(&mut de));
+    de.end()
+}
+
+/// Reads with every element's hash colliding with every other's
+fn colliding<'a, T>(input: &'a str, policy: DuplicateElements) -> tjson::Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input)
+        .set_uniqueness(SetUniqueness::Hashed)
+        .duplicate_elements(policy)
+        .set_hash_bits(0);
+    let value = try!(T::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
+
+#[test]
+fn test_hashed_memory() {
+    let input = large_set();
+
+    let exact = alloc::peak_bytes(|| check(&input, SetUniqueness::Exact).unwrap());
+    let hashed = alloc::peak_bytes(|| check(&input, SetUniqueness::Hashed).unwrap());
+
+    // Every 200 byte element is kept, or only its hash and offset
+    assert!(exact > ELEMENTS * 200, "{} bytes", exact);
+    assert!(hashed < ELEMENTS * 100, "{} bytes", hashed);
+}
+
+#[test]
+fn test_hashed_duplicate() {
+    let input = r#"{"ids:S<s>": ["a", "b", "c", "b"], "more:S<A<i>>": [["1"], ["1", "2"]]}"#;
+    let exact = check(input, SetUniqueness::Exact).unwrap_err();
+    let hashed = check(input, SetUniqueness::Hashed).unwrap_err();
+    assert_eq!(hashed.code(), ErrorCode::DuplicateElement);
+    assert_eq!(hashed.to_string(), exact.to_string());
+    assert_eq!(
+        hashed.to_string(),
+        "duplicate set element \"b\" at index 3 of /ids:S<s> at line 1 column 32"
+    );
+
+    // Elements which are themselves arrays, read into a `Value`
+    let input = r#"{"ids:S<A<i>>": [["1"], ["1", "2"], ["1"]]}"#;
+    let mut de = Deserializer::from_str(input).set_uniqueness(SetUniqueness::Hashed);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+
+    let mut parser = Parser::new().set_uniqueness(SetUniqueness::Hashed);
+    let err = parser.parse_str::<Value>(r#"{"ids:S<u>": ["1", "2", "1"]}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element 1 at index 2 of /ids:S<u> at line 1 column 27"
+    );
+}
+
+#[test]
+fn test_collision_fallback_on_slices() {
+    // Distinct elements are read again to tell them apart
+    let input = r#"{"ids:S<s>": ["a", "b", "c"], "nested:S<S<i>>": [["1"], ["2", "1"], ["1", "2", "3"]]}"#;
+    let value: Value = colliding(input, DuplicateElements::Error).unwrap();
+    assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+
+    // Equal ones are still duplicates, however many they collide with
+    let err = colliding::<Value>(r#"{"ids:S<s>": ["a", "b", "c", "b"]}"#, DuplicateElements::Error)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element \"b\" at index 3 of /ids:S<s> at line 1 column 32"
+    );
+    let input = r#"{"nested:S<S<i>>": [["1", "2"], ["3"], ["2", "1"]]}"#;
+    let err = colliding::<Value>(input, DuplicateElements::Error).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+
+    let set: Set<Value> = colliding(r#"["a", "b", "a", "c", "b"]"#, DuplicateElements::Merge).unwrap();
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn test_collision_on_readers() {
+    // A reader can't be read again, so elements whose hashes collide can't
+    // be told apart, whatever the policy
+    for &policy in &[DuplicateElements::Error, DuplicateElements::Merge] {
+        let input: &[u8] = br#"{"ids:S<s>": ["a", "b"]}"#;
+        let mut de = Deserializer::from_reader(input)
+            .set_uniqueness(SetUniqueness::Hashed)
+            .duplicate_elements(policy)
+            .set_hash_bits(0);
+        let err = Value::deserialize(&mut de).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnresolvedHashCollision);
+        assert_eq!(
+            err.to_string(),
+            "set element at index 1 of /ids:S<s> hashes the same as an earlier one which can't \
+             be read again at line 1 column 22"
+        );
+    }
+
+    // Without collisions readers find duplicates as slices do
+    let input: &[u8] = br#"{"ids:S<s>": ["a", "b", "a"]}"#;
+    let mut de = Deserializer::from_reader(input).set_uniqueness(SetUniqueness::Hashed);
+    let err = IgnoredAny::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+
+    let input: &[u8] = br#"{"ids:S<s>": ["a", "b", "c"]}"#;
+    let mut de = Deserializer::from_reader(input).set_uniqueness(SetUniqueness::Hashed);
+    IgnoredAny::deserialize(&mut de).unwrap();
+}
+
+#[test]
+fn test_collision_with_transforms() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let parse = |input: &str| -> tjson::Result<Value> {
+        let runs = runs.clone();
+        let mut de = Deserializer::from_str(input)
+            .set_uniqueness(SetUniqueness::Hashed)
+            .set_hash_bits(0)
+            .transform_string(Matcher::path("/ids:S<s>/*"), move |s| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(s.to_uppercase())
+            });
+        Value::deserialize(&mut de)
+    };
+
+    // Hooks run once per element, and not on elements read again
+    let err = parse(r#"{"ids:S<s>": ["a", "b", "a"]}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::UnresolvedHashCollision);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    // Elements equal as written are still duplicates
+    let err = parse(r#"{"ids:S<s>": ["a", "a"]}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+}
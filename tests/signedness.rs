@@ -0,0 +1,151 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+mod common;
+
+use std::{i64, u64};
+
+use common::parse;
+use tjson::Value;
+use tjson::de::{IntegerSignedness, Parser};
+use tjson::error::ErrorCode;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Signed {
+    n: i64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Unsigned {
+    n: u64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Small {
+    n: u32,
+}
+
+#[test]
+fn test_unsigned_into_signed() {
+    let input = r#"{"n:u": "7"}"#;
+    assert_eq!(parse::<Signed, _>(input, IntegerSignedness::Coerce).unwrap(), Signed { n: 7 });
+
+    let err = parse::<Signed, _>(input, IntegerSignedness::Strict).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+    assert_eq!(
+        err.to_string(),
+        "integer tagged u can't be read into i64 with IntegerSignedness::Strict at /n:u \
+         at line 1 column 9"
+    );
+}
+
+#[test]
+fn test_signed_into_unsigned() {
+    let input = r#"{"n:i": "7"}"#;
+    assert_eq!(parse::<Unsigned, _>(input, IntegerSignedness::Coerce).unwrap(), Unsigned { n: 7 });
+    assert_eq!(parse::<Small, _>(input, IntegerSignedness::Coerce).unwrap(), Small { n: 7 });
+
+    let err = parse::<Small, _>(input, IntegerSignedness::Strict).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+    assert_eq!(
+        err.to_string(),
+        "integer tagged i can't be read into u32 with IntegerSignedness::Strict at /n:i \
+         at line 1 column 9"
+    );
+}
+
+#[test]
+fn test_matching_signedness() {
+    for &signedness in &[IntegerSignedness::Strict, IntegerSignedness::Coerce] {
+        let signed = parse::<Signed, _>(r#"{"n:i": "-7"}"#, signedness).unwrap();
+        assert_eq!(signed, Signed { n: -7 });
+        let unsigned = parse::<Unsigned, _>(r#"{"n:u": "7"}"#, signedness).unwrap();
+        assert_eq!(unsigned, Unsigned { n: 7 });
+    }
+}
+
+#[test]
+fn test_limits() {
+    let max_i = format!(r#"{{"n:i": "{}"}}"#, i64::MAX);
+    let max_u = format!(r#"{{"n:u": "{}"}}"#, u64::MAX);
+    let above_i = format!(r#"{{"n:u": "{}"}}"#, i64::MAX as u64 + 1);
+
+    let coerce = IntegerSignedness::Coerce;
+    assert_eq!(parse::<Signed, _>(&max_i, coerce).unwrap(), Signed { n: i64::MAX });
+    assert_eq!(parse::<Unsigned, _>(&max_i, coerce).unwrap(), Unsigned { n: i64::MAX as u64 });
+    assert_eq!(parse::<Unsigned, _>(&max_u, coerce).unwrap(), Unsigned { n: u64::MAX });
+
+    let err = parse::<Signed, _>(&max_u, coerce).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    let err = parse::<Signed, _>(&above_i, coerce).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    let err = parse::<Small, _>(&max_i, coerce).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+
+    let strict = IntegerSignedness::Strict;
+    assert_eq!(parse::<Signed, _>(&max_i, strict).unwrap(), Signed { n: i64::MAX });
+    assert_eq!(parse::<Unsigned, _>(&max_u, strict).unwrap(), Unsigned { n: u64::MAX });
+    let err = parse::<Unsigned, _>(&max_i, strict).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+}
+
+#[test]
+fn test_negative_into_unsigned() {
+    let input = r#"{"n:i": "-1"}"#;
+
+    // Out of range whether or not the signedness is coerced
+    let err = parse::<Small, _>(input, IntegerSignedness::Coerce).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberOutOfRange);
+    assert!(err.to_string().starts_with("number out of range"), "{}", err);
+
+    let err = parse::<Small, _>(input, IntegerSignedness::Strict).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+}
+
+#[test]
+fn test_elements() {
+    let input = r#"{"ns:A<u>": ["1", "2"]}"#;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Elements {
+        ns: Vec<i32>,
+    }
+
+    let coerced = parse::<Elements, _>(input, IntegerSignedness::Coerce).unwrap();
+    assert_eq!(coerced, Elements { ns: vec![1, 2] });
+
+    let err = parse::<Elements, _>(input, IntegerSignedness::Strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer tagged u can't be read into i32 with IntegerSignedness::Strict at /ns:A<u>/0 \
+         at line 1 column 14"
+    );
+}
+
+#[test]
+fn test_parser() {
+    let mut parser = Parser::new().integer_signedness(IntegerSignedness::Strict);
+    let err = parser.parse_str::<Signed>(r#"{"n:u": "7"}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SignednessMismatch);
+
+    let mut parser = Parser::new();
+    assert_eq!(parser.parse_str::<Signed>(r#"{"n:u": "7"}"#).unwrap(), Signed { n: 7 });
+}
+
+#[test]
+fn test_from_value_coerces() {
+    let value: Value = tjson::from_str(r#"{"u:u": "7", "i:i": "7", "neg:i": "-7"}"#).unwrap();
+    assert_eq!(tjson::from_value::<i64>(value["u:u"].clone()).unwrap(), 7);
+    assert_eq!(tjson::from_value::<u32>(value["i:i"].clone()).unwrap(), 7);
+    assert!(tjson::from_value::<u32>(value["neg:i"].clone()).is_err());
+}
@@ -0,0 +1,168 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::collections::BTreeMap;
+
+use tjson::Value;
+use tjson::value::{Metric, StatsLimits, ValueKind, ValueStats};
+
+fn small() -> Value {
+    tjson::from_str(
+        r#"{"name:s": "ab", "tags:A<s>": ["x", "yz", "~/"], "meta:O": {"n:i": "1", "ok": true}}"#,
+    ).unwrap()
+}
+
+#[test]
+fn test_stats_small_document() {
+    let stats = small().stats();
+
+    let mut kinds = BTreeMap::new();
+    kinds.insert(ValueKind::Object, 2);
+    kinds.insert(ValueKind::String, 4);
+    kinds.insert(ValueKind::Number, 1);
+    kinds.insert(ValueKind::Array, 1);
+    kinds.insert(ValueKind::Bool, 1);
+
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(stats.nodes, 9);
+    assert_eq!(stats.kinds, kinds);
+    assert_eq!(stats.count(ValueKind::Number), 1);
+    assert_eq!(stats.string_bytes, "ab".len() + "xyz~/".len());
+    assert_eq!(stats.data_bytes, 0);
+    assert_eq!(stats.largest_container, 3);
+    assert_eq!(stats.longest_name, "tags:A<s>".len());
+    assert_eq!(stats.longest_name_path.as_deref(), Some("/tags:A<s>"));
+    assert_eq!(stats.largest_container_path.as_deref(), Some(""));
+
+    // Which container at depth 2 comes first depends on the map's ordering
+    let deepest = if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        "/tags:A<s>"
+    } else {
+        "/meta:O"
+    };
+    assert_eq!(stats.deepest_path.as_deref(), Some(deepest));
+}
+
+#[test]
+fn test_stats_scalars() {
+    let mut kinds = BTreeMap::new();
+    kinds.insert(ValueKind::Data, 1);
+    assert_eq!(
+        Value::Data(vec![1, 2, 3].into()).stats(),
+        ValueStats {
+            nodes: 1,
+            kinds: kinds,
+            data_bytes: 3,
+            ..ValueStats::default()
+        }
+    );
+
+    let stats = tjson!([]).stats();
+    assert_eq!(stats.max_depth, 1);
+    assert_eq!(stats.deepest_path, Some(String::new()));
+    assert_eq!(stats.largest_container, 0);
+    assert_eq!(stats.largest_container_path, Some(String::new()));
+    assert_eq!(stats.longest_name_path, None);
+}
+
+#[test]
+fn test_stats_wide_and_deep() {
+    const WIDTH: usize = 1_000;
+    const DEPTH: usize = 100_000;
+
+    // A wide array at the top, with a very deep chain of single-member
+    // objects at its last element
+    let mut deep = tjson!("bottom");
+    for _ in 0..DEPTH {
+        let mut map = tjson::Map::new();
+        map.insert("d".to_owned(), deep);
+        deep = Value::Object(map);
+    }
+
+    let mut wide: Vec<Value> = (0..WIDTH).map(|i| tjson!(i)).collect();
+    wide.push(deep);
+    let value = Value::Array(wide);
+
+    let stats = value.stats();
+    assert_eq!(stats.max_depth, DEPTH + 1);
+    assert_eq!(stats.nodes, 1 + WIDTH + DEPTH + 1);
+    assert_eq!(stats.count(ValueKind::Number), WIDTH);
+    assert_eq!(stats.count(ValueKind::Object), DEPTH);
+    assert_eq!(stats.string_bytes, "bottom".len());
+    assert_eq!(stats.largest_container, WIDTH + 1);
+    assert_eq!(stats.largest_container_path, Some(String::new()));
+    assert_eq!(stats.longest_name, 1);
+    assert_eq!(stats.longest_name_path, Some(format!("/{}/d", WIDTH)));
+
+    let deepest = stats.deepest_path.unwrap();
+    assert_eq!(deepest.len(), format!("/{}", WIDTH).len() + (DEPTH - 1) * "/d".len());
+    assert_eq!(value.pointer(&deepest), Some(&tjson!({ "d": "bottom" })));
+
+    // Dropping a value this deep recurses, so take it apart by hand
+    let mut next = value;
+    while let Some(inner) = match next {
+        Value::Array(mut array) => array.pop(),
+        Value::Object(mut map) => map.remove("d"),
+        _ => None,
+    } {
+        next = inner;
+    }
+}
+
+#[test]
+fn test_stats_limits() {
+    let value = tjson!({
+        "users": [{ "id": 7, "name": "a", "avatar": Value::Data(vec![0; 64].into()) }],
+        "a/very~long/name": { "x": [[1]] },
+    });
+    let stats = value.stats();
+
+    assert_eq!(stats.exceeds(&StatsLimits::new()), None);
+
+    let violation = stats.exceeds(&StatsLimits::new().max_depth(3)).unwrap();
+    assert_eq!(violation.metric(), Metric::Depth);
+    assert_eq!(violation.limit(), 3);
+    assert_eq!(violation.actual(), 4);
+    assert_eq!(violation.path(), Some("/a~1very~0long~1name/x/0"));
+    assert_eq!(
+        violation.to_string(),
+        "depth 4 exceeds the limit of 3 at /a~1very~0long~1name/x/0"
+    );
+    assert_eq!(value.pointer(violation.path().unwrap()), Some(&tjson!([1])));
+
+    let violation = stats.exceeds(&StatsLimits::new().max_name_len(8)).unwrap();
+    assert_eq!(violation.metric(), Metric::NameLen);
+    assert_eq!(violation.actual(), "a/very~long/name".len());
+    assert_eq!(violation.path(), Some("/a~1very~0long~1name"));
+
+    let violation = stats.exceeds(&StatsLimits::new().max_container_len(2)).unwrap();
+    assert_eq!(violation.metric(), Metric::ContainerLen);
+    assert_eq!(violation.path(), Some("/users/0"));
+    assert_eq!(violation.to_string(), "container length 3 exceeds the limit of 2 at /users/0");
+
+    // Totals over the whole value have no path
+    let violation = stats.exceeds(&StatsLimits::new().max_data_bytes(63)).unwrap();
+    assert_eq!(violation.metric(), Metric::DataBytes);
+    assert_eq!(violation.path(), None);
+    assert_eq!(violation.to_string(), "data bytes 64 exceeds the limit of 63");
+
+    let violation = stats.exceeds(&StatsLimits::new().max_nodes(5).max_string_bytes(0)).unwrap();
+    assert_eq!(violation.metric(), Metric::Nodes);
+    assert_eq!(violation.actual(), 10);
+
+    let violation = stats.exceeds(&StatsLimits::new().max_nodes(10).max_string_bytes(0)).unwrap();
+    assert_eq!(violation.metric(), Metric::StringBytes);
+
+    let violation = tjson!([[]]).stats().exceeds(&StatsLimits::new().max_depth(1)).unwrap();
+    assert_eq!(violation.to_string(), "depth 2 exceeds the limit of 1 at /0");
+    let violation = tjson!([[]]).stats().exceeds(&StatsLimits::new().max_container_len(0)).unwrap();
+    assert_eq!(violation.to_string(), "container length 1 exceeds the limit of 0 at the root");
+}
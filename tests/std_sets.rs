@@ -0,0 +1,185 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::collections::{BTreeSet, HashSet};
+
+use serde::Deserialize;
+use tjson::{Deserializer, Value};
+use tjson::de::DuplicateElements;
+use tjson::error::ErrorCode;
+use tjson::ser::{SerializeConfig, TagInference};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Account {
+    #[serde(rename = "ids:S<u>", with = "tjson::set")]
+    ids: HashSet<u64>,
+    #[serde(with = "tjson::set")]
+    roles: BTreeSet<String>,
+}
+
+fn account() -> Account {
+    Account {
+        ids: vec![7, 1, 3].into_iter().collect(),
+        roles: vec!["admin".to_owned(), "dev".to_owned()].into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let account = account();
+
+    let output = tjson::to_string(&account).unwrap();
+    assert!(output.contains(r#""roles:S<s>":["admin","dev"]"#), "{}", output);
+    assert_eq!(tjson::from_str::<Account>(&output).unwrap(), account);
+
+    let value = tjson::to_value(&account).unwrap();
+    assert!(value["ids:S<u>"].is_set());
+    assert!(value["roles"].is_set());
+    assert_eq!(tjson::to_string(&value["roles"]).unwrap(), r#"["admin","dev"]"#);
+    assert_eq!(tjson::from_value::<Account>(value).unwrap(), account);
+
+    // An empty set is read back as one
+    let empty = Account {
+        ids: HashSet::new(),
+        roles: BTreeSet::new(),
+    };
+    // Without a tag in its name there are no elements to go by, so it is
+    // tagged `S<O>`, which reads back as a set of anything
+    let output = tjson::to_string(&empty).unwrap();
+    assert_eq!(output, r#"{"ids:S<u>":[],"roles:S<O>":[]}"#);
+    assert_eq!(tjson::from_str::<Account>(&output).unwrap(), empty);
+    let config = SerializeConfig::new().infer_tags(TagInference::Required);
+    assert_eq!(tjson::to_string_with(&config, &empty).unwrap(), output);
+}
+
+#[test]
+fn test_inferred_tags() {
+    let config = SerializeConfig::new().infer_tags(TagInference::Required);
+    let output = tjson::to_string_with(&config, &account()).unwrap();
+    let value: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(value["roles:S<s>"], tjson::to_value(account()).unwrap()["roles"]);
+    assert_eq!(value["ids:S<u>"].len(), Some(3));
+
+    // Without the attribute a set is just a sequence
+    #[derive(Serialize)]
+    struct Plain {
+        roles: BTreeSet<String>,
+    }
+    let plain = Plain { roles: account().roles };
+    let output = tjson::to_string_with(&config, &plain).unwrap();
+    assert_eq!(output, r#"{"roles:A<s>":["admin","dev"]}"#);
+}
+
+#[test]
+fn test_duplicate_elements() {
+    let cases = &[
+        (
+            r#"{"ids:S<u>": ["1", "2", "1"], "roles": []}"#,
+            "duplicate set element 1 at index 2 of /ids:S<u> at line 1 column 27",
+        ),
+        // Untagged, but the field is marked as a set
+        (
+            r#"{"ids:S<u>": [], "roles": ["dev", "dev"]}"#,
+            "duplicate set element \"dev\" at index 1 of /roles at line 1 column 39",
+        ),
+    ];
+    for &(input, message) in cases {
+        let err = tjson::from_str::<Account>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DuplicateElement, "{}", input);
+        assert_eq!(err.to_string(), message);
+    }
+
+    // From a Value too
+    let value = tjson!({ "ids:S<u>": [4, 4], "roles": [] });
+    let err = tjson::from_value::<Account>(value.clone()).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate set element 4 at index 1");
+    let merged: Account = tjson::value::from_value_with(value, DuplicateElements::Merge).unwrap();
+    assert_eq!(merged.ids, vec![4].into_iter().collect());
+
+    // A set under a member tagged `S<..>` needs no attribute to be checked
+    #[derive(Debug, Deserialize)]
+    struct Tagged {
+        #[serde(rename = "ids:S<u>")]
+        ids: HashSet<u64>,
+    }
+    let input = r#"{"ids:S<u>": ["5", "6", "5"]}"#;
+    let err = tjson::from_str::<Tagged>(input).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+    let mut de = Deserializer::from_str(input).duplicate_elements(DuplicateElements::Merge);
+    assert_eq!(Tagged::deserialize(&mut de).unwrap().ids.len(), 2);
+
+    // Writing one out is checked as well
+    #[derive(Serialize)]
+    struct Listed {
+        #[serde(rename = "ids:S<u>", with = "tjson::set")]
+        ids: Vec<u64>,
+    }
+    let err = tjson::to_string(&Listed { ids: vec![1, 1] }).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Groups {
+    #[serde(rename = "members:S<S<u>>", with = "tjson::set")]
+    members: BTreeSet<BTreeSet<u64>>,
+}
+
+#[test]
+fn test_nested_sets() {
+    let groups = Groups {
+        members: vec![
+            vec![1, 2].into_iter().collect(),
+            vec![3].into_iter().collect(),
+            BTreeSet::new(),
+        ].into_iter()
+            .collect(),
+    };
+    let output = tjson::to_string(&groups).unwrap();
+    assert_eq!(output, r#"{"members:S<S<u>>":[[],["1","2"],["3"]]}"#);
+    assert_eq!(tjson::from_str::<Groups>(&output).unwrap(), groups);
+
+    let value = tjson::to_value(&groups).unwrap();
+    assert_eq!(tjson::from_value::<Groups>(value).unwrap(), groups);
+
+    // Inner sets are held to the tag
+    let err = tjson::from_str::<Groups>(r#"{"members:S<S<u>>": [["1"], ["2", "2"]]}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element 2 at index 1 of /members:S<S<u>>/1 at line 1 column 37"
+    );
+
+    // As are outer ones
+    let input = r#"{"members:S<S<u>>": [["1"], ["2"], ["1"]]}"#;
+    let err = tjson::from_str::<Groups>(input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element [1] at index 2 of /members:S<S<u>> at line 1 column 40"
+    );
+
+    // Whatever order their elements are in
+    let input = r#"{"members:S<S<u>>": [["1", "2"], ["2", "1"]]}"#;
+    let err = tjson::from_str::<Groups>(input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate set element [1,2] at index 1 of /members:S<S<u>> at line 1 column 43"
+    );
+    let err = tjson::from_str::<Value>(input).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateElement);
+
+    // Parsed as a `Value` they are sets of sets
+    let value: Value = tjson::from_str(&output).unwrap();
+    let members = value["members:S<S<u>>"].as_set().unwrap();
+    assert_eq!(members.len(), 3);
+    assert!(members.iter().all(Value::is_set));
+}
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#![cfg(not(feature = "preserve_order"))]
+#![cfg(not(any(feature = "preserve_order", feature = "preserve_order_indexmap")))]
 extern crate serde;
 
 #[macro_use]
@@ -118,3 +118,66 @@ fn test_json_stream_primitive() {
         }
     );
 }
+
+#[test]
+fn test_json_stream_skip_line() {
+    let data = "{\"x\":1}\n{\"x\" 2} {\"x\":3}\n[\"s:open\n{\"x\":4}\ntrue\n{\"x\":5}";
+
+    test_stream!(
+        data, Value, |stream| {
+            assert_eq!(stream.next().unwrap().unwrap()["x"], 1);
+            assert_eq!(stream.byte_offset(), 7);
+
+            // The rest of the line goes, even the document after the error
+            assert!(stream.next().unwrap().unwrap_err().is_syntax());
+            assert_eq!(stream.byte_offset(), 8);
+            stream.skip_line().unwrap();
+            assert_eq!(stream.byte_offset(), 24);
+
+            // An error found on the newline leaves the next line whole
+            assert!(stream.next().unwrap().unwrap_err().is_syntax());
+            assert_eq!(stream.byte_offset(), 24);
+            stream.skip_line().unwrap();
+            assert_eq!(stream.next().unwrap().unwrap()["x"], 4);
+            assert_eq!(stream.byte_offset(), 40);
+
+            // Until skipped, the stream is stuck on the error
+            assert!(stream.next().unwrap().is_err());
+            assert!(stream.next().unwrap().is_err());
+            stream.skip_line().unwrap();
+            assert_eq!(stream.byte_offset(), 46);
+            assert_eq!(stream.next().unwrap().unwrap()["x"], 5);
+            assert!(stream.next().is_none());
+
+            // Skipping at the end is harmless
+            stream.skip_line().unwrap();
+            assert!(stream.next().is_none());
+            assert_eq!(stream.byte_offset(), data.len());
+        }
+    );
+}
+
+#[test]
+fn test_json_stream_skip_partial_document() {
+    let data = "{\"x\":1}\r\n\t[{\"x\":\n}\n[2]  {\"x\":";
+
+    test_stream!(
+        data, Value, |stream| {
+            assert_eq!(stream.next().unwrap().unwrap()["x"], 1);
+
+            // The error is found on the line after the one the document
+            // starts on, so that is the line skipped
+            let err = stream.next().unwrap().unwrap_err();
+            assert!(err.is_syntax(), "{}", err);
+            stream.skip_line().unwrap();
+            assert_eq!(stream.next().unwrap().unwrap(), tjson!([2]));
+            assert_eq!(stream.byte_offset(), 22);
+
+            // A trailing partial document is an EOF error
+            assert!(stream.next().unwrap().unwrap_err().is_eof());
+            assert_eq!(stream.byte_offset(), 24);
+            stream.skip_line().unwrap();
+            assert!(stream.next().is_none());
+        }
+    );
+}
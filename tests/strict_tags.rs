@@ -0,0 +1,220 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use serde::Deserialize;
+use tjson::{Deserializer, Error, Tag, Value};
+use tjson::de::Parser;
+use tjson::error::ErrorCode;
+
+fn strict(input: &str) -> Result<Value, Error> {
+    let mut de = Deserializer::from_str(input).strict_tags(true);
+    Value::deserialize(&mut de)
+}
+
+#[test]
+fn test_valid_documents() {
+    let input = r#"{
+        "name:s": "ann",
+        "id:i": "-9223372036854775808",
+        "count:u": "18446744073709551615",
+        "ratio:f": 1.5,
+        "whole:f": 2,
+        "ok:b": true,
+        "token:d": "AQI",
+        "hex:d16": "0102",
+        "at:t": "2017-03-04T05:06:07Z",
+        "ids:A<u>": ["1", "2"],
+        "grid:A<A<i>>": [["1"], ["-2"]],
+        "tags:S<s>": ["a", "b"],
+        "nested:O": { "inner:A<O>": [{ "x:i": "1" }] }
+    }"#;
+    let value = strict(input).unwrap();
+    assert_eq!(value, tjson::from_str::<Value>(input).unwrap());
+
+    // Arrays are documents too
+    assert_eq!(strict(r#"[{"a:u": "1"}]"#).unwrap(), tjson!([{ "a:u": 1 }]));
+}
+
+#[test]
+fn test_invalid_member_names() {
+    for name in &["name", "name:", "name:Z", "name:A<", "name:A<>", "name:A<Z>", "name:s "] {
+        let input = format!(r#"{{"ok:O": {{ {:?}: 1 }}}}"#, name);
+        let err = strict(&input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidTag, "{}", name);
+        assert!(err.is_syntax());
+        assert_eq!(err.column(), 13 + name.len());
+        assert!(err.to_string().starts_with(&format!("member name {:?} has ", name)));
+
+        // Without the option only names with no tag at all are read
+        let read = tjson::from_str::<Value>(&input);
+        assert_eq!(read.is_ok(), !name.contains(':'), "{}", name);
+    }
+
+    assert_eq!(
+        strict(r#"{"a:i": "1", "b": 2}"#).unwrap_err().to_string(),
+        "member name \"b\" has no tag at line 1 column 16"
+    );
+    assert_eq!(
+        strict(r#"{"foo:A<": []}"#).unwrap_err().to_string(),
+        "member name \"foo:A<\" has invalid tag \"A<\" at line 1 column 9"
+    );
+}
+
+#[test]
+fn test_values_of_the_wrong_kind() {
+    let cases = &[
+        (r#"{"a:s": 1}"#, "expected a value tagged `s`, found number at /a:s"),
+        (r#"{"a:i": true}"#, "expected a value tagged `i`, found boolean at /a:i"),
+        (r#"{"a:f": true}"#, "expected a value tagged `f`, found boolean at /a:f"),
+        (r#"{"a:b": null}"#, "expected a value tagged `b`, found null at /a:b"),
+        (r#"{"a:O": []}"#, "expected a value tagged `O`, found array at /a:O"),
+        (r#"{"a:A<s>": {}}"#, "expected a value tagged `A<s>`, found object at /a:A<s>"),
+        (r#"{"a:A<s>": ["x", 2]}"#, "expected a value tagged `s`, found number at /a:A<s>/1"),
+        (r#"{"a:t": [1]}"#, "expected a value tagged `t`, found array at /a:t"),
+    ];
+    for &(input, message) in cases {
+        let err = strict(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", input);
+        // At the start of the value
+        let column = input.rfind(|c| c == ' ' || c == ',').unwrap() + 2;
+        assert_eq!(err.to_string(), format!("{} at line 1 column {}", message, column));
+        // With or without the option
+        assert_eq!(tjson::from_str::<Value>(input).unwrap_err().to_string(), err.to_string());
+    }
+}
+
+#[test]
+fn test_scalars_which_dont_match_their_tag() {
+    let cases = &[
+        (r#"{"a:i": 1}"#, "expected a value tagged `i`, found number 1"),
+        (r#"{"a:u": 0}"#, "expected a value tagged `u`, found number 0"),
+        (r#"{"a:i": 1.5}"#, "expected a value tagged `i`, found number 1.5"),
+        (r#"{"a:i": 1e3}"#, "expected a value tagged `i`, found number 1000.0"),
+        (
+            r#"{"a:i": 9223372036854775808}"#,
+            "expected a value tagged `i`, found number 9223372036854775808",
+        ),
+        (r#"{"a:u": -1}"#, "expected a value tagged `u`, found number -1"),
+        (
+            r#"{"a:u": 18446744073709551616}"#,
+            "expected a value tagged `u`, found number 1.8446744073709552e19",
+        ),
+        (r#"{"a:t": "yesterday"}"#, "expected a value tagged `t`, found string \"yesterday\""),
+    ];
+    for &(input, message) in cases {
+        let err = strict(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", input);
+        assert!(err.to_string().starts_with(message), "{}", err);
+        // Which is the same without the option
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", input);
+    }
+
+    // Data is decoded either way, so only strings left as text are checked
+    let cases = &[
+        (r#"{"a:d": "a+b"}"#, "expected a value tagged `d`, found string \"a+b\""),
+        (r#"{"a:d16": "0g"}"#, "expected a value tagged `d16`, found string \"0g\""),
+    ];
+    for &(input, message) in cases {
+        assert_eq!(strict(input).unwrap_err().code(), ErrorCode::Data, "{}", input);
+        let mut de = Deserializer::from_str(input).strict_tags(true).decode_data(false);
+        let err = Value::deserialize(&mut de).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", input);
+        assert!(err.to_string().starts_with(message), "{}", err);
+    }
+
+    // Quoted integers out of range fail the same way either way
+    for input in &[r#"{"a:u": "-1"}"#, r#"{"a:i": "9223372036854775808"}"#] {
+        let err = strict(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NumberOutOfRange, "{}", input);
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NumberOutOfRange, "{}", input);
+    }
+
+    let err = strict(r#"{"a:S<i>": ["1", "-1", 0.5]}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a value tagged `i`, found number 0.5 at /a:S<i>/2 at line 1 column 26"
+    );
+}
+
+#[test]
+fn test_quoted_integers() {
+    let input = r#"{"a:u": "7", "b:i": "x"}"#;
+    let mut de = Deserializer::from_str(input).strict_tags(true);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a value tagged `i`, found string \"x\" at /b:i at line 1 column 23"
+    );
+
+    assert_eq!(strict(r#"{"a:u": "7"}"#).unwrap(), tjson!({ "a:u": 7 }));
+
+    // Which are left as strings with the option off
+    let mut de = Deserializer::from_str(r#"{"a:u": "7"}"#).strict_tags(true).quoted_integers(false);
+    assert_eq!(Value::deserialize(&mut de).unwrap(), tjson!({ "a:u": "7" }));
+}
+
+#[test]
+fn test_scalar_documents() {
+    for input in &["\"x\"", "1", "true", "null"] {
+        let err = strict(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ExpectedObjectOrArray, "{}", input);
+        assert_eq!(err.column(), 1);
+        assert!(tjson::from_str::<Value>(input).is_ok(), "{}", input);
+    }
+    let mut de = Deserializer::from_str("null").strict_tags(true);
+    assert!(Option::<u64>::deserialize(&mut de).is_err());
+
+    // Unless the document has a tag of its own, which it must hold
+    let mut parser = Parser::new().strict_tags(true).root_tag(&Tag::UInt);
+    assert_eq!(parser.parse_str::<u64>(r#""5""#).unwrap(), 5);
+    let err = parser.parse_str::<u64>("5").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Reading {
+    #[serde(rename = "sensor:s")]
+    sensor: String,
+    #[serde(rename = "values:A<f>")]
+    values: Vec<f64>,
+}
+
+#[test]
+fn test_structs_and_parser() {
+    let mut de = Deserializer::from_str(r#"{"sensor:s": "t1", "values:A<f>": [1, 2.5]}"#)
+        .strict_tags(true);
+    let reading = Reading::deserialize(&mut de).unwrap();
+    assert_eq!(reading.values, [1.0, 2.5]);
+
+    let mut de = Deserializer::from_str(r#"{"sensor:s": "t1", "values:A<f>": ["1"]}"#)
+        .strict_tags(true);
+    let err = Reading::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TagMismatch);
+
+    let mut parser = Parser::new().strict_tags(true);
+    let err = parser.parse_str::<Value>(r#"{"sensor": "t1"}"#).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidTag);
+    assert!(parser.parse_str::<Reading>(r#"{"sensor:s": "t1", "values:A<f>": []}"#).is_ok());
+}
+
+#[test]
+fn test_mismatch_parts() {
+    let err = strict(r#"{"a:O": {"b:A<u>": [-1]}}"#).unwrap_err();
+    let mismatch = err.tag_mismatch().unwrap();
+    assert_eq!(mismatch.expected(), &Tag::UInt);
+    assert_eq!(mismatch.found(), "number -1");
+    assert_eq!(mismatch.pointer(), "/a:O/b:A<u>/0");
+}
@@ -0,0 +1,225 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use chrono::datetime::DateTime;
+use chrono::offset::utc::UTC;
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+use tjson::{Deserializer, Value};
+use tjson::de::Normalization;
+use tjson::encoding::hex_encode;
+use tjson::error::ErrorCode;
+use tjson::ser::{DataEncoding, IntegerTag, KeyOrder, KeySortRule, SerializeConfig,
+                 TimestampPrecision};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    #[serde(rename = "token:d")]
+    token: Vec<u8>,
+    #[serde(rename = "digest:d16")]
+    digest: ByteBuf,
+    #[serde(rename = "count:u")]
+    count: u64,
+    #[serde(rename = "ids:A<u>")]
+    ids: Vec<u64>,
+    #[serde(rename = "at:t")]
+    at: DateTime<UTC>,
+    #[serde(rename = "history:A<t>")]
+    history: Vec<DateTime<UTC>>,
+    #[serde(rename = "extra:O")]
+    extra: Value,
+}
+
+/// How a value is written on its own.
+fn text(value: &Value) -> String {
+    tjson::to_string(value).unwrap()
+}
+
+fn org_policy() -> SerializeConfig<'static> {
+    SerializeConfig::new()
+        .default_data_encoding(DataEncoding::Base64Url)
+        .integer_tag_policy(IntegerTag::AlwaysSigned)
+        .timestamp_precision(TimestampPrecision::Millis)
+}
+
+fn record() -> Record {
+    Record {
+        token: vec![0xfb, 0xff, 0x00],
+        digest: ByteBuf::from(vec![0x01, 0x02]),
+        count: 7,
+        ids: vec![1, 2, 3],
+        at: "2017-03-04T05:06:07.123Z".parse().unwrap(),
+        history: vec!["2017-03-04T05:06:07Z".parse().unwrap()],
+        extra: tjson!({
+            "key:d16": hex_encode(b"\x0a\x0b"),
+            "raw:d": Value::Data(vec![0xff].into()),
+            "n:u": 9,
+            "at:t": "2017-03-04T05:06:07.5Z",
+            "untouched:s": "d",
+        }),
+    }
+}
+
+#[test]
+fn test_member_by_member() {
+    let output = tjson::to_string_with(&org_policy(), &record()).unwrap();
+    let written: Value = tjson::from_str(&output).unwrap();
+    let members = written.as_object().unwrap();
+    assert_eq!(members.len(), 7);
+
+    assert_eq!(written["token:d64"], Value::Data(vec![0xfb, 0xff, 0x00].into()));
+    assert_eq!(written["digest:d64"], Value::Data(vec![0x01, 0x02].into()));
+    assert!(output.contains(r#""token:d64":"-_8A""#));
+    assert_eq!(written["count:i"], 7);
+    assert_eq!(written["ids:A<i>"], tjson!([1, 2, 3]));
+    assert_eq!(text(&written["at:t"]), r#""2017-03-04T05:06:07.123Z""#);
+    assert_eq!(written["history:A<t>"], tjson!(["2017-03-04T05:06:07.000Z"]));
+
+    // Inside a Value tree as well
+    let extra = &written["extra:O"];
+    assert_eq!(extra["key:d64"], Value::Data(vec![0x0a, 0x0b].into()));
+    assert_eq!(extra["raw:d64"], Value::Data(vec![0xff].into()));
+    assert_eq!(extra["n:i"], 9);
+    assert_eq!(text(&extra["at:t"]), r#""2017-03-04T05:06:07.500Z""#);
+    assert_eq!(extra["untouched:s"], "d");
+}
+
+#[test]
+fn test_round_trip() {
+    let record = record();
+    let output = tjson::to_string_with(&org_policy(), &record).unwrap();
+    let mut de = Deserializer::from_str(&output)
+        .member_name_normalization(Normalization::TagKinds);
+    let back = Record::deserialize(&mut de).unwrap();
+
+    assert_eq!(back.token, record.token);
+    assert_eq!(back.digest, record.digest);
+    assert_eq!(back.count, record.count);
+    assert_eq!(back.ids, record.ids);
+    assert_eq!(back.at, record.at);
+    assert_eq!(back.history, record.history);
+    assert_eq!(back.extra["key:d64"], Value::Data(vec![0x0a, 0x0b].into()));
+
+    // The deserializer reads any encoding, and bytes written as arrays
+    let plain = tjson::to_string(&record).unwrap();
+    assert!(plain.contains(r#""digest:d16":"0102""#));
+    let back: Record = tjson::from_str(&plain).unwrap();
+    assert_eq!(back.token, record.token);
+    let array = plain.replace("\"digest:d16\":\"0102\"", "\"digest:d16\":[1,2]");
+    let back: Record = tjson::from_str(&array).unwrap();
+    assert_eq!(back.digest, record.digest);
+}
+
+#[test]
+fn test_always_signed_overflow() {
+    let mut record = record();
+    record.ids.push(u64::MAX);
+    let err = tjson::to_string_with(&org_policy(), &record).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer 18446744073709551615 is above i64::MAX and can't be tagged `i` at /ids:A<u>/3"
+    );
+
+    // Natural tags are left alone
+    let output = tjson::to_string_with(&SerializeConfig::new(), &record).unwrap();
+    assert!(output.contains("18446744073709551615"));
+    assert!(output.contains("\"count:u\":\"7\""));
+}
+
+#[test]
+fn test_composes_with_sorting() {
+    let config = org_policy().key_order(KeyOrder::Sorted(KeySortRule::UntaggedName));
+    let value = tjson!({ "b:u": 1, "a:d16": "ff" });
+    let output = tjson::to_string_with(&config, &value).unwrap();
+    assert_eq!(output, r#"{"a:d64":"_w","b:i":"1"}"#);
+}
+
+#[test]
+fn test_errors() {
+    let config = org_policy();
+
+    let err = tjson::to_string_with(&config, &tjson!({ "a:d16": "zz" })).unwrap_err();
+    assert!(err.to_string().ends_with(" at /a:d16"), "{}", err);
+
+    let err = tjson::to_string_with(&config, &tjson!({ "a:A<d>": [[256]] })).unwrap_err();
+    assert_eq!(err.to_string(), "data must be bytes at /a:A<d>/0");
+
+    let value = tjson!({ "a:d": "AQ", "a:d64": "Ag" });
+    let err = tjson::to_string_with(&config, &value).unwrap_err();
+    assert!(err.to_string().starts_with("two members would be written as \"a:d64\""));
+
+    let err = tjson::from_str::<Record>(r#"{"token:d": "not base64!"}"#).unwrap_err();
+    assert_eq!(err.to_string(), "invalid byte at offset 3 at line 1 column 25");
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Extremes {
+    #[serde(rename = "max:i")]
+    max: i64,
+    #[serde(rename = "min:i")]
+    min: i64,
+    #[serde(rename = "ratio:f")]
+    ratio: f64,
+    #[serde(rename = "umax:u")]
+    umax: u64,
+}
+
+#[test]
+fn test_quoted_integers_round_trip() {
+    let extremes = Extremes {
+        max: i64::MAX,
+        min: i64::MIN,
+        ratio: 0.5,
+        umax: u64::MAX,
+    };
+    let output = tjson::to_string(&extremes).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"{"max:i":"9223372036854775807","min:i":"-9223372036854775808","#,
+            r#""ratio:f":0.5,"umax:u":"18446744073709551615"}"#
+        )
+    );
+
+    assert_eq!(tjson::from_str::<Extremes>(&output).unwrap(), extremes);
+    let value: Value = tjson::from_str(&output).unwrap();
+    assert_eq!(value["min:i"], i64::MIN);
+    assert_eq!(value["umax:u"], u64::MAX);
+}
+
+#[test]
+fn test_quoted_integers_past_the_boundaries() {
+    let cases = &[
+        r#"{"n:i": "-9223372036854775809"}"#,
+        r#"{"n:i": "9223372036854775808"}"#,
+        r#"{"n:u": "18446744073709551616"}"#,
+        r#"{"n:u": "-1"}"#,
+    ];
+    for input in cases {
+        let err = tjson::from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NumberOutOfRange, "{}", input);
+    }
+
+    // Quoted with the tag they are written under, after `AlwaysSigned`
+    let config = SerializeConfig::new().integer_tag_policy(IntegerTag::AlwaysSigned);
+    let output = tjson::to_string_with(&config, &tjson!({ "n:u": 1 })).unwrap();
+    assert_eq!(output, r#"{"n:i":"1"}"#);
+    let err = tjson::to_string_with(&config, &tjson!({ "n:u": u64::MAX })).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer 18446744073709551615 is above i64::MAX and can't be tagged `i` at /n:u"
+    );
+}
@@ -0,0 +1,126 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use tjson::ser::{IntegerTag, SerializeConfig};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "logins:u")]
+    logins: u64,
+}
+
+fn user() -> User {
+    User {
+        name: "ann".to_owned(),
+        logins: 3,
+    }
+}
+
+#[test]
+fn test_matching_tag_is_kept() {
+    let output = tjson::to_string(&user()).unwrap();
+    assert_eq!(output, r#"{"name:s":"ann","logins:u":"3"}"#);
+    assert_eq!(tjson::from_str::<User>(&output).unwrap(), user());
+
+    // Through a `Value`, and with a tag which also fits
+    let value = tjson!({ "at:t": "2017-03-04T05:06:07Z", "n:u": 2, "ratio:f": 1 });
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"at:t":"2017-03-04T05:06:07Z","n:u":"2","ratio:f":1.0}"#);
+}
+
+#[test]
+fn test_mismatched_tag() {
+    #[derive(Serialize)]
+    struct Count {
+        #[serde(rename = "n:s")]
+        n: u64,
+    }
+
+    let err = tjson::to_string(&Count { n: 7 }).unwrap_err();
+    assert_eq!(err.to_string(), "member \"n:s\" is tagged `s` but its value is `u`");
+
+    let err = tjson::to_string(&tjson!({ "ids:A<s>": [1, 2] })).unwrap_err();
+    assert_eq!(err.to_string(), "member \"ids:A<s>\" is tagged `A<s>` but its value is `A<i>`");
+
+    let err = tjson::to_string(&tjson!({ "a:i": null })).unwrap_err();
+    assert_eq!(err.to_string(), "member \"a:i\" is tagged `i` but its value is null");
+}
+
+#[test]
+fn test_colon_without_a_tag() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Endpoint {
+        #[serde(rename = "host:port")]
+        address: String,
+        #[serde(rename = "a:b:A<x>")]
+        weights: Vec<f64>,
+    }
+
+    let endpoint = Endpoint {
+        address: "a:80".to_owned(),
+        weights: vec![0.5],
+    };
+    let output = tjson::to_string(&endpoint).unwrap();
+    assert_eq!(output, r#"{"host:port:s":"a:80","a:b:A<x>:A<f>":[0.5]}"#);
+
+    // The tag parsed back is the one appended
+    let value: tjson::Value = tjson::from_str(&output).unwrap();
+    assert_eq!(value["host:port:s"], "a:80");
+
+    // And the fields match their names without it
+    assert_eq!(tjson::from_str::<Endpoint>(&output).unwrap(), endpoint);
+}
+
+#[test]
+fn test_round_trip_with_alias() {
+    // Fields renamed with a tag still read documents using their plain names
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Migrated {
+        #[serde(rename = "name:s", alias = "name")]
+        name: String,
+        #[serde(rename = "logins:u", alias = "logins")]
+        logins: u64,
+    }
+
+    let migrated = Migrated {
+        name: "ann".to_owned(),
+        logins: 3,
+    };
+    let output = tjson::to_string(&migrated).unwrap();
+    assert_eq!(output, r#"{"name:s":"ann","logins:u":"3"}"#);
+    assert_eq!(tjson::from_str::<Migrated>(&output).unwrap(), migrated);
+
+    // As written before the fields were renamed
+    let untagged = r#"{"name":"ann","logins":3}"#;
+    assert_eq!(tjson::from_str::<Migrated>(untagged).unwrap(), migrated);
+}
+
+#[test]
+fn test_reject_tagged_names() {
+    let config = SerializeConfig::new().reject_tagged_names(true);
+    let err = tjson::to_string_with(&config, &user()).unwrap_err();
+    assert_eq!(err.to_string(), "member name \"name:s\" already has a tag");
+
+    // Untagged names, and names without a valid tag, are tagged as usual
+    let value = tjson!({ "host:port": "a:80", "n": 1 });
+    let output = tjson::to_string_with(&config, &value).unwrap();
+    assert_eq!(output, r#"{"host:port:s":"a:80","n:i":"1"}"#);
+
+    // Also where values are rewritten first, with the member's pointer
+    let config = config.integer_tag_policy(IntegerTag::AlwaysSigned);
+    assert_eq!(tjson::to_string_with(&config, &value).unwrap(), output);
+    let err = tjson::to_string_with(&config, &tjson!({ "o": { "n:u": 1 } })).unwrap_err();
+    assert_eq!(err.to_string(), "member name \"n:u\" already has a tag at /o/n:u");
+}
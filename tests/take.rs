@@ -0,0 +1,64 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use tjson::{Set, Value};
+
+#[test]
+fn test_take_from_nested_arrays() {
+    let mut value = tjson!([[1, [2, 3]], 4]);
+
+    assert_eq!(value[0][1][0].take(), tjson!(2));
+    assert_eq!(value.pointer_mut("/0/1").map(Value::take), Some(tjson!([null, 3])));
+    assert_eq!(value, tjson!([[1, null], 4]));
+
+    // Taking what was already taken gives Undefined
+    assert_eq!(value[0][1].take(), Value::Undefined);
+}
+
+#[test]
+fn test_take_from_nested_objects() {
+    let mut value = tjson!({ "a:O": { "b:O": { "c:s": "x" } }, "d:i": 1 });
+
+    assert_eq!(value["a:O"]["b:O"]["c:s"].take(), tjson!("x"));
+    let b = value.pointer_mut("/a:O/b:O").unwrap().replace(tjson!(2));
+    assert_eq!(b, tjson!({ "c:s": null }));
+    assert_eq!(value, tjson!({ "a:O": { "b:O": 2 }, "d:i": 1 }));
+
+    // The member stays, holding Undefined
+    assert_eq!(value["d:i"].take(), tjson!(1));
+    assert!(value.as_object().unwrap().contains_key("d:i"));
+    assert_eq!(value.pointer_mut("/missing").map(Value::take), None);
+}
+
+#[test]
+fn test_take_from_sets() {
+    let mut set = Set::new();
+    set.insert(tjson!([1, 2]));
+    set.insert(tjson!("a"));
+    let mut value = tjson!({ "s:S<A<i>>": Value::Set(set) });
+
+    // Elements can only be changed through an editor, which puts back
+    // together a set of what is left
+    let taken = {
+        let mut editor = value["s:S<A<i>>"].as_set_mut().unwrap().edit();
+        let taken = editor.iter_mut().find(|element| element.is_array()).unwrap().take();
+        editor.finish();
+        taken
+    };
+    assert_eq!(taken, tjson!([1, 2]));
+    let set = value["s:S<A<i>>"].as_set().unwrap();
+    assert!(set.contains(&Value::Undefined));
+    assert!(!set.contains(&tjson!([1, 2])));
+
+    let whole = value["s:S<A<i>>"].take();
+    assert_eq!(whole.as_set().map(Set::len), Some(2));
+    assert_eq!(value, tjson!({ "s:S<A<i>>": null }));
+}
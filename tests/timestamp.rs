@@ -0,0 +1,291 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+mod common;
+
+use common::parse;
+use tjson::{DateTime, Map, Set, Value};
+use tjson::de::{OffsetPolicy, Parser};
+use tjson::error::ErrorCode;
+use tjson::ser::{SerializeConfig, TagInference};
+
+fn instant(text: &str) -> DateTime {
+    text.parse().unwrap()
+}
+
+fn object(name: &str, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(name.to_owned(), value);
+    Value::Object(map)
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Event {
+    #[serde(rename = "at:t")]
+    at: DateTime,
+    #[serde(rename = "raw:t")]
+    raw: String,
+}
+
+#[test]
+fn test_offset_rejected() {
+    let input = r#"{"at:t": "2017-03-04T07:06:07+02:00"}"#;
+    let err = parse::<Value, _>(input, OffsetPolicy::RejectNonUtc).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "timestamp has offset `+02:00` rather than `Z` at /at:t at line 1 column 36"
+    );
+    assert_eq!(err.code(), ErrorCode::NonUtcTimestamp);
+    assert!(err.is_syntax());
+
+    // The default, also for typed fields and through a Parser
+    assert!(tjson::from_str::<Value>(input).is_err());
+    let input = r#"{"at:t": "2017-03-04T05:06:07Z", "raw:t": "2017-03-04T05:06:07+00:00"}"#;
+    let err = tjson::from_str::<Event>(input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "timestamp has offset `+00:00` rather than `Z` at /raw:t at line 1 column 69"
+    );
+    assert!(Parser::new().parse_str::<Value>(input).is_err());
+}
+
+#[test]
+fn test_offset_normalized() {
+    let input = r#"{"at:t": "2017-03-04T07:06:07+02:00"}"#;
+    let value: Value = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    assert_eq!(value, object("at:t", Value::Timestamp(instant("2017-03-04T05:06:07Z"))));
+
+    // Typed fields read the timestamp converted to UTC
+    let input = r#"{"at:t": "2017-03-04T00:30:00-05:00", "raw:t": "2017-03-04T00:30:00.25-05:00"}"#;
+    let event: Event = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    assert_eq!(event.at, instant("2017-03-04T05:30:00Z"));
+    assert_eq!(event.raw, "2017-03-04T05:30:00.250Z");
+
+    let mut parser = Parser::new().timestamp_offsets(OffsetPolicy::NormalizeToUtc);
+    let event: Event = parser.parse_str(input).unwrap();
+    assert_eq!(event.raw, "2017-03-04T05:30:00.250Z");
+}
+
+#[test]
+fn test_unknown_local_offset() {
+    // RFC 3339 uses -00:00 for a time in UTC whose local offset is unknown
+    let input = r#"{"at:t": "2017-03-04T05:06:07-00:00"}"#;
+    let err = parse::<Value, _>(input, OffsetPolicy::RejectNonUtc).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "timestamp has offset `-00:00` rather than `Z` at /at:t at line 1 column 36"
+    );
+
+    let value: Value = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    assert_eq!(value, object("at:t", Value::Timestamp(instant("2017-03-04T05:06:07Z"))));
+}
+
+#[test]
+fn test_rfc3339_edge_cases() {
+    let cases = [
+        ("2017-03-04t05:06:07Z", "2017-03-04T05:06:07Z", "2017-03-04T05:06:07Z"),
+        ("2017-03-04t05:06:07z", "2017-03-04T05:06:07Z", "2017-03-04T05:06:07Z"),
+        ("2017-03-04T05:06:07.5z", "2017-03-04T05:06:07.5Z", "2017-03-04T05:06:07.500Z"),
+        ("2017-03-04T05:06:07.5Z", "2017-03-04T05:06:07.5Z", "2017-03-04T05:06:07.500Z"),
+        (
+            "2017-03-04T05:06:07.123456789Z",
+            "2017-03-04T05:06:07.123456789Z",
+            "2017-03-04T05:06:07.123456789Z",
+        ),
+    ];
+
+    #[derive(Deserialize)]
+    struct At {
+        #[serde(rename = "at:t")]
+        at: DateTime,
+    }
+
+    for &policy in &[OffsetPolicy::RejectNonUtc, OffsetPolicy::NormalizeToUtc] {
+        for &(text, upper, utc) in &cases {
+            let input = format!(r#"{{"at:t":"{}"}}"#, text);
+            if text.ends_with('z') && policy == OffsetPolicy::RejectNonUtc {
+                assert!(parse::<At, _>(&input, policy).is_err(), "{}", text);
+                continue;
+            }
+            assert_eq!(parse::<At, _>(&input, policy).unwrap().at, instant(utc), "{}", text);
+
+            // A lowercase `t`, or `z` if allowed, is read as uppercase. Values keep the
+            // text unless it's written that way already.
+            let value: Value = parse(&input, policy).unwrap();
+            if upper == utc {
+                assert_eq!(value["at:t"], Value::Timestamp(instant(utc)));
+            } else {
+                assert_eq!(value["at:t"], upper);
+            }
+            let output = format!(r#"{{"at:t":"{}"}}"#, upper);
+            assert_eq!(tjson::to_string(&value).unwrap(), output);
+        }
+    }
+
+    // Strings which aren't timestamps at all don't match the tag in either mode
+    for text in &["2017-03-04T05:06Z", "2017-03-04T05:06:07.Z", "2017-02-30T05:06:07Z", "yesterday"] {
+        let input = format!(r#"{{"at:t": "{}"}}"#, text);
+        let err = parse::<Value, _>(&input, OffsetPolicy::RejectNonUtc).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", text);
+        let err = parse::<Value, _>(&input, OffsetPolicy::NormalizeToUtc).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TagMismatch, "{}", text);
+    }
+}
+
+#[test]
+fn test_lowercase_z() {
+    let input = r#"{"log:O": {"at:A<t>": ["2017-03-04T05:06:07Z", "2017-03-04T05:06:07.5z"]}}"#;
+    let err = parse::<Value, _>(input, OffsetPolicy::RejectNonUtc).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NonUtcTimestamp);
+    assert_eq!(
+        err.to_string(),
+        "timestamp has offset `z` rather than `Z` at /log:O/at:A<t>/1 at line 1 column 71"
+    );
+
+    let value: Value = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    assert_eq!(value["log:O"]["at:A<t>"][1], "2017-03-04T05:06:07.5Z");
+}
+
+#[test]
+fn test_fractions_are_kept() {
+    let texts = [
+        "2017-03-04T05:06:07.250Z",
+        "2017-03-04T05:06:07.000001Z",
+        "2017-03-04T05:06:07.123456789Z",
+    ];
+    for text in &texts {
+        let input = format!(r#"{{"at:t":"{}"}}"#, text);
+        let value: Value = tjson::from_str(&input).unwrap();
+        assert_eq!(value["at:t"], Value::Timestamp(instant(text)));
+        assert_eq!(tjson::to_string(&value).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_written_with_z() {
+    let value = object("at:t", Value::Timestamp(instant("2017-03-04T07:06:07.5+02:00")));
+    assert_eq!(tjson::to_string(&value).unwrap(), r#"{"at:t":"2017-03-04T05:06:07.500Z"}"#);
+
+    let timestamps = vec![
+        Value::Timestamp(instant("2017-03-04T05:06:07Z")),
+        Value::Timestamp(instant("2017-03-04T05:06:07.123456789Z")),
+    ];
+    let value = object("log:A<t>", Value::Array(timestamps));
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(
+        output,
+        r#"{"log:A<t>":["2017-03-04T05:06:07Z","2017-03-04T05:06:07.123456789Z"]}"#
+    );
+    assert_eq!(tjson::from_str::<Value>(&output).unwrap(), value);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Logged {
+    #[serde(rename = "at:t", with = "tjson::timestamp")]
+    at: DateTime,
+    #[serde(with = "tjson::timestamp")]
+    seen: DateTime,
+    #[serde(rename = "note:s")]
+    note: String,
+}
+
+fn logged() -> Logged {
+    Logged {
+        at: instant("2017-03-04T05:06:07Z"),
+        seen: instant("2017-03-04T05:06:07.123456789Z"),
+        note: "2017-03-04T05:06:07Z".to_owned(),
+    }
+}
+
+#[test]
+fn test_with_timestamp_module() {
+    let logged = logged();
+    let output = tjson::to_string(&logged).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"{"at:t":"2017-03-04T05:06:07Z","seen:t":"2017-03-04T05:06:07.123456789Z","#,
+            r#""note:s":"2017-03-04T05:06:07Z"}"#
+        )
+    );
+    assert_eq!(tjson::from_str::<Logged>(&output).unwrap(), logged);
+
+    // Without inference the name is written as it is
+    let config = SerializeConfig::new().infer_tags(TagInference::Off);
+    let output = tjson::to_string_with(&config, &logged).unwrap();
+    assert!(output.contains(r#","seen":"2017-03-04T05:06:07.123456789Z","#));
+
+    let value = tjson::to_value(&logged).unwrap();
+    assert_eq!(value["at:t"], Value::Timestamp(logged.at));
+    assert_eq!(value["seen"], Value::Timestamp(logged.seen));
+    assert_eq!(value["note:s"], "2017-03-04T05:06:07Z");
+    assert_eq!(tjson::from_value::<Logged>(value).unwrap(), logged);
+}
+
+#[test]
+fn test_with_timestamp_module_rejects_offsets() {
+    let cases = &[
+        ("2017-03-04T07:06:07+02:00", "timestamp has offset `+02:00` rather than `Z`"),
+        ("2017-03-04T05:06:07z", "timestamp has offset `z` rather than `Z`"),
+        ("yesterday", "invalid value: string \"yesterday\", expected an RFC 3339 timestamp in UTC"),
+    ];
+    for &(text, message) in cases {
+        let mut value = tjson::to_value(&logged()).unwrap();
+        value["seen"] = Value::String(text.to_owned());
+        let err = tjson::from_value::<Logged>(value).unwrap_err();
+        assert_eq!(err.to_string(), message);
+
+        let input = format!(
+            r#"{{"at:t": "2017-03-04T05:06:07Z", "seen": "{}", "note:s": ""}}"#,
+            text
+        );
+        let err = tjson::from_str::<Logged>(&input).unwrap_err();
+        assert!(err.to_string().starts_with(message), "{}", err);
+    }
+}
+
+#[test]
+fn test_tags() {
+    // Arrays and sets of timestamps are checked too
+    let input = r#"{"log:A<S<t>>": [["2017-03-04T07:06:07+02:00"]]}"#;
+    assert!(parse::<Value, _>(input, OffsetPolicy::RejectNonUtc).is_err());
+    let value: Value = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    let mut timestamps = Set::new();
+    timestamps.insert(Value::Timestamp(instant("2017-03-04T05:06:07Z")));
+    let log = Value::Array(vec![Value::Set(timestamps)]);
+    assert_eq!(value, object("log:A<S<t>>", log));
+
+    // Other strings aren't
+    let input = r#"{"at:s": "2017-03-04T07:06:07+02:00", "log:A<s>": ["x"], "t": "x"}"#;
+    let value: Value = parse(input, OffsetPolicy::RejectNonUtc).unwrap();
+    assert_eq!(value["at:s"], "2017-03-04T07:06:07+02:00");
+
+    // Also when parsing a Map
+    let map: Map<String, Value> = tjson::from_str(r#"{"at:t": "2017-03-04T05:06:07Z"}"#).unwrap();
+    assert_eq!(map["at:t"], Value::Timestamp(instant("2017-03-04T05:06:07Z")));
+}
+
+#[test]
+fn test_normalization_idempotent() {
+    let input = r#"{"a:t": "2017-03-04T07:06:07.5+02:00", "b:t": "2016-12-31T23:59:60Z"}"#;
+    let value: Value = parse(input, OffsetPolicy::NormalizeToUtc).unwrap();
+    let output = tjson::to_string(&value).unwrap();
+    assert_eq!(output, r#"{"a:t":"2017-03-04T05:06:07.500Z","b:t":"2016-12-31T23:59:60Z"}"#);
+
+    // Normalized output is read strictly as the same value, and written the same
+    let again: Value = parse(&output, OffsetPolicy::RejectNonUtc).unwrap();
+    assert_eq!(again, value);
+    assert_eq!(tjson::to_string(&again).unwrap(), output);
+    let again: Value = parse(&output, OffsetPolicy::NormalizeToUtc).unwrap();
+    assert_eq!(tjson::to_string(&again).unwrap(), output);
+}
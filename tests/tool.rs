@@ -0,0 +1,193 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runs the `tjson_tool` example over the files in `tests/tool`.
+
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::Once;
+
+/// The features this test was built with, for building the example.
+const FEATURES: &[(&str, bool)] = &[
+    ("bytes", cfg!(feature = "bytes")),
+    ("decimal", cfg!(feature = "decimal")),
+    ("http", cfg!(feature = "http")),
+    ("mmap", cfg!(feature = "mmap")),
+    ("preserve_order", cfg!(feature = "preserve_order")),
+    ("preserve_order_indexmap", cfg!(feature = "preserve_order_indexmap")),
+    ("rayon", cfg!(feature = "rayon")),
+    ("testgen", cfg!(feature = "testgen")),
+    ("utf16", cfg!(feature = "utf16")),
+];
+
+/// The example binary, built first in case it is missing or out of date.
+fn tool() -> Command {
+    static BUILD: Once = Once::new();
+
+    // target/<profile>/deps/tool-<hash>
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push("examples");
+    path.push(format!("tjson_tool{}", env::consts::EXE_SUFFIX));
+
+    BUILD.call_once(|| {
+        let features: Vec<&str> = FEATURES
+            .iter()
+            .filter(|&&(_, enabled)| enabled)
+            .map(|&(name, _)| name)
+            .collect();
+        let mut cargo = Command::new(env!("CARGO"));
+        cargo.args(&["build", "--quiet", "--example", "tjson_tool"]);
+        cargo.arg("--features").arg(features.join(" "));
+        if !cfg!(debug_assertions) {
+            cargo.arg("--release");
+        }
+        let output = cargo.current_dir(env!("CARGO_MANIFEST_DIR")).output().unwrap();
+        assert!(
+            output.status.success(),
+            "building tjson_tool failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    });
+
+    Command::new(path)
+}
+
+fn fixture(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("tool");
+    path.push(name);
+    path
+}
+
+fn run(args: &[&str], file: &str) -> Output {
+    tool().args(args).arg(fixture(file)).output().unwrap()
+}
+
+fn run_stdin(args: &[&str], input: &str) -> Output {
+    let mut child = tool()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_validate() {
+    let output = run(&["validate"], "doc.tjson");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let output = run(&["validate"], "invalid.tjson");
+    assert_eq!(output.status.code(), Some(1));
+    let path = fixture("invalid.tjson");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}:2:11: syntax error (expected_colon): expected `:` at line 2 column 11\n",
+            path.display()
+        )
+    );
+
+    let output = run_stdin(&["validate"], "{\"a:i\": \"1\"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("<stdin>:1:11: eof error (eof_while_parsing_object): "));
+}
+
+#[test]
+fn test_pretty() {
+    let output = run(&["pretty", "--indent", "1"], "plain.json");
+    let expected = "{\n \"admin\": false,\n \"name\": \"ann\",\n \"profile\": {\n  \"age\": \
+                    30,\n  \"tags\": [\n   \"a\",\n   \"b\"\n  ]\n },\n \"scores\": [\n  1,\n  \
+                    2.5\n ]\n}\n";
+    if cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        assert_eq!(stdout(&output).len(), expected.len());
+    } else {
+        assert_eq!(stdout(&output), expected);
+    }
+
+    let output = run_stdin(&["pretty"], r#"{"a:A<i>": ["1"]}"#);
+    assert_eq!(stdout(&output), "{\n  \"a:A<i>\": [\n    \"1\"\n  ]\n}\n");
+}
+
+#[test]
+fn test_canonical() {
+    let output = run(&["canonical"], "doc.tjson");
+    assert_eq!(
+        stdout(&output),
+        "{\"admin:b\":false,\"ids:S<i>\":[\"1\",\"2\",\"3\"],\"name:s\":\"ann\",\
+         \"profile:O\":{\"age:i\":\"30\",\"tags:A<s>\":[\"a\",\"b\"]}}\n"
+    );
+}
+
+#[test]
+fn test_json_interop() {
+    let output = run(&["from-json"], "plain.json");
+    let tagged = stdout(&output);
+    assert_eq!(
+        tagged,
+        "{\"admin:b\":false,\"name:s\":\"ann\",\"profile:O\":{\"age:i\":\"30\",\
+         \"tags:A<s>\":[\"a\",\"b\"]},\"scores:A<f>\":[1.0,2.5]}\n"
+    );
+
+    let output = run_stdin(&["to-json"], &tagged);
+    assert_eq!(
+        stdout(&output),
+        "{\"admin\":false,\"name\":\"ann\",\"profile\":{\"age\":30,\"tags\":[\"a\",\"b\"]},\
+         \"scores\":[1.0,2.5]}\n"
+    );
+
+    let output = run(&["from-json"], "null.json");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).ends_with(": data error (data): TJSON has no null at /name\n"));
+}
+
+#[test]
+fn test_digest() {
+    let digest = stdout(&run(&["digest"], "doc.tjson"));
+    assert_eq!(digest.len(), 17);
+
+    // The same document written differently
+    let canonical = stdout(&run(&["canonical"], "doc.tjson"));
+    assert_eq!(stdout(&run_stdin(&["digest"], &canonical)), digest);
+    let pretty = stdout(&run(&["pretty"], "doc.tjson"));
+    assert_eq!(stdout(&run_stdin(&["digest", "-"], &pretty)), digest);
+
+    let changed = canonical.replace("ann", "bob");
+    assert!(stdout(&run_stdin(&["digest"], &changed)) != digest);
+}
+
+#[test]
+fn test_usage_errors() {
+    let output = run(&["bogus"], "doc.tjson");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("tjson_tool: unknown command `bogus`\n\nusage: "));
+
+    let output = run(&["pretty", "--indent"], "doc.tjson");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["validate"], "missing.tjson");
+    assert_eq!(output.status.code(), Some(2));
+}
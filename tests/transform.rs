@@ -0,0 +1,195 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate tjson;
+
+use std::error::Error as StdError;
+
+use serde::Deserialize;
+use serde::de::Error as DeError;
+use tjson::{Deserializer, Value};
+use tjson::de::Parser;
+use tjson::encoding::{b64url_encode, hex_encode};
+use tjson::error::ErrorCode;
+use tjson::transform::Matcher;
+
+const KEY: u8 = 0x5a;
+
+fn xor(bytes: Vec<u8>) -> tjson::Result<Vec<u8>> {
+    Ok(bytes.into_iter().map(|b| b ^ KEY).collect())
+}
+
+fn encrypt(plain: &str) -> String {
+    b64url_encode(&xor(plain.as_bytes().to_vec()).unwrap())
+}
+
+fn input() -> String {
+    tjson!({
+        "secret:d": encrypt("one"),
+        "users:A<O>": [
+            { "name:s": "ann", "token:d": encrypt("two") },
+            { "name:s": "bob", "token:d": encrypt("three") },
+        ],
+        "plain:d": b64url_encode(b"left alone"),
+        "hashes:A<d16>": [hex_encode(b"\x01\x02"), hex_encode(b"\x03")],
+        "at:t": "2017-03-04T05:06:07Z",
+    }).to_string()
+}
+
+#[test]
+fn test_data_on_matched_paths() {
+    let input = input();
+    let mut de = Deserializer::from_str(&input)
+        .transform_data(Matcher::path("/secret:d"), xor)
+        .transform_data(Matcher::path("/users:A<O>/*/token:d"), xor);
+    let value = Value::deserialize(&mut de).unwrap();
+
+    assert_eq!(value["secret:d"], Value::Data(b"one".to_vec().into()));
+    assert_eq!(value["users:A<O>"][0]["token:d"], Value::Data(b"two".to_vec().into()));
+    assert_eq!(value["users:A<O>"][1]["token:d"], Value::Data(b"three".to_vec().into()));
+
+    // Everything else is as written
+    let written: Value = tjson::from_str(&input).unwrap();
+    assert_eq!(value["plain:d"], written["plain:d"]);
+    assert_eq!(value["hashes:A<d16>"], written["hashes:A<d16>"]);
+    assert_eq!(value["users:A<O>"][1]["name:s"], "bob");
+}
+
+#[test]
+fn test_error_carries_pointer() {
+    let input = input();
+    let fail = |_: Vec<u8>| -> tjson::Result<Vec<u8>> { Err(tjson::Error::custom("bad key")) };
+    let mut de = Deserializer::from_str(&input)
+        .transform_data(Matcher::path("/users:A<O>/1/token:d"), fail);
+    let err = Value::deserialize(&mut de).unwrap_err();
+
+    assert_eq!(err.code(), ErrorCode::TransformFailed);
+    assert!(err.is_data());
+    assert_eq!(err.transform_pointer(), Some("/users:A<O>/1/token:d"));
+    assert!(err.to_string().starts_with("transform of \"/users:A<O>/1/token:d\" failed: bad key"));
+    #[allow(deprecated)]
+    let cause = err.cause().unwrap();
+    assert_eq!(cause.to_string(), "bad key");
+
+    // Data which isn't validly encoded can't be given to a hook
+    let input = r#"{"a:d": "not base64!"}"#;
+    let mut de = Deserializer::from_str(input).transform_data(Matcher::tag("d"), xor);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.transform_pointer(), Some("/a:d"));
+    assert_eq!(
+        err.to_string(),
+        "transform of \"/a:d\" failed: invalid byte at offset 3 at line 1 column 21"
+    );
+}
+
+#[test]
+fn test_tag_matches_all_data() {
+    let input = input();
+    let reverse = |mut bytes: Vec<u8>| -> tjson::Result<Vec<u8>> {
+        bytes.reverse();
+        Ok(bytes)
+    };
+    let mut de = Deserializer::from_str(&input)
+        .transform_data(Matcher::tag("d"), reverse)
+        .transform_data(Matcher::tag("d16"), reverse);
+    let value = Value::deserialize(&mut de).unwrap();
+
+    let mut secret = xor(b"one".to_vec()).unwrap();
+    secret.reverse();
+    assert_eq!(value["secret:d"], Value::Data(secret.into()));
+    assert_eq!(value["plain:d"], Value::Data(b"enola tfel".to_vec().into()));
+
+    // Elements of arrays of data have the tag inside
+    let hashes = vec![Value::Data(vec![2, 1].into()), Value::Data(vec![3].into())];
+    assert_eq!(value["hashes:A<d16>"], Value::Array(hashes));
+}
+
+#[test]
+fn test_hooks_run_in_order() {
+    let input = r#"{"a:d": "AQI"}"#;
+    let add = |bytes: Vec<u8>| -> tjson::Result<Vec<u8>> {
+        Ok(bytes.into_iter().map(|b| b + 1).collect())
+    };
+    let double = |bytes: Vec<u8>| -> tjson::Result<Vec<u8>> {
+        Ok(bytes.into_iter().map(|b| b * 2).collect())
+    };
+    let mut de = Deserializer::from_str(input)
+        .transform_data(Matcher::path("/a:d"), add)
+        .transform_data(Matcher::tag("d"), double);
+    let value = Value::deserialize(&mut de).unwrap();
+    assert_eq!(value["a:d"], Value::Data(vec![4, 6].into()));
+}
+
+#[test]
+fn test_typed_bytes() {
+    #[derive(Deserialize)]
+    struct User {
+        #[serde(rename = "name:s")]
+        name: String,
+        #[serde(rename = "token:d")]
+        token: Vec<u8>,
+    }
+
+    #[derive(Deserialize)]
+    struct Doc {
+        #[serde(rename = "secret:d")]
+        secret: Vec<u8>,
+        #[serde(rename = "users:A<O>")]
+        users: Vec<User>,
+        #[serde(rename = "plain:d")]
+        plain: Vec<u8>,
+    }
+
+    let input = input();
+    let mut de = Deserializer::from_str(&input)
+        .transform_data(Matcher::path("/secret:d"), xor)
+        .transform_data(Matcher::path("/users:A<O>/*/token:d"), xor)
+        .transform_string(Matcher::path("/users:A<O>/*/name:s"), |s| Ok(s.to_uppercase()));
+    let doc = Doc::deserialize(&mut de).unwrap();
+
+    assert_eq!(doc.secret, b"one");
+    assert_eq!(doc.users[0].token, b"two");
+    assert_eq!(doc.users[1].token, b"three");
+    assert_eq!(doc.users[1].name, "BOB");
+    assert_eq!(doc.plain, b"left alone");
+}
+
+#[test]
+fn test_kinds_are_kept() {
+    let input = input();
+    let mut de = Deserializer::from_str(&input)
+        .transform_string(Matcher::path("/*"), |_| Ok("changed".to_owned()))
+        .transform_string(Matcher::path("/users:A<O>/0/name:s"), |s| Ok(s + "!"));
+    let value = Value::deserialize(&mut de).unwrap();
+
+    // String hooks don't see data or timestamps
+    let written: Value = tjson::from_str(&input).unwrap();
+    assert_eq!(value["secret:d"], written["secret:d"]);
+    assert_eq!(value["at:t"], written["at:t"]);
+    assert_eq!(value["users:A<O>"][0]["name:s"], "ann!");
+
+    // Untagged strings are strings
+    let mut de = Deserializer::from_str(r#"{"a": "x", "b": ["y"]}"#)
+        .transform_string(Matcher::path("/b/0"), |s| Ok(s.repeat(2)));
+    let value = Value::deserialize(&mut de).unwrap();
+    assert_eq!(value, tjson!({ "a": "x", "b": ["yy"] }));
+}
+
+#[test]
+fn test_parser_keeps_hooks() {
+    let mut parser = Parser::new().transform_data(Matcher::tag("d"), xor);
+    let input = input();
+    for _ in 0..2 {
+        let value: Value = parser.parse_str(&input).unwrap();
+        assert_eq!(value["secret:d"], Value::Data(b"one".to_vec().into()));
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate tjson;
+
+use std::fmt;
+use std::io;
+
+use tjson::{Error, Result, Value};
+use tjson::error::ErrorCode;
+
+fn contact(email: Option<&str>, phone: Option<u64>) -> Result<Value> {
+    try_tjson!({
+        "name": "ann",
+        "email": email,
+        "phone": phone
+    })
+}
+
+#[test]
+fn test_try_tjson_options() {
+    assert_eq!(
+        contact(Some("ann@example.com"), Some(5550100)).unwrap(),
+        tjson!({ "name": "ann", "email": "ann@example.com", "phone": 5550100 })
+    );
+    assert_eq!(
+        contact(Some("ann@example.com"), None).unwrap(),
+        tjson!({ "name": "ann", "email": "ann@example.com" })
+    );
+    assert_eq!(contact(None, Some(5550100)).unwrap(), tjson!({ "name": "ann", "phone": 5550100 }));
+    assert_eq!(contact(None, None).unwrap(), tjson!({ "name": "ann" }));
+
+    // `None` has nowhere to go outside of an object
+    let none: Option<u64> = None;
+    let err = try_tjson!([1, none, 3]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::Data);
+    assert_eq!(
+        err.to_string(),
+        "`None` can only be interpolated as the value of an object member"
+    );
+    assert!(try_tjson!(none).is_err());
+    assert_eq!(try_tjson!([Some(2)]).unwrap(), tjson!([2]));
+}
+
+#[derive(Debug)]
+struct Unavailable;
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("service unavailable")
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+fn evaluate(evaluated: &mut bool) -> u8 {
+    *evaluated = true;
+    2
+}
+
+#[test]
+fn test_try_tjson_results() {
+    let ok: std::result::Result<u8, Unavailable> = Ok(1);
+    assert_eq!(try_tjson!({ "a": [ok] }).unwrap(), tjson!({ "a": [1] }));
+
+    let mut evaluated = false;
+    let err = try_tjson!({
+        "a": [Err::<u8, _>(Unavailable)],
+        "b": evaluate(&mut evaluated),
+    }).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::Data);
+    assert_eq!(err.to_string(), "service unavailable");
+    #[allow(deprecated)]
+    let cause = std::error::Error::cause(&err).unwrap().to_string();
+    assert_eq!(cause, "service unavailable");
+    // Members are evaluated in order and the first error stops construction
+    assert!(!evaluated);
+
+    let err = try_tjson!([Err::<(), _>("no such user".to_owned())]).unwrap_err();
+    assert_eq!(err.to_string(), "no such user");
+
+    let err = try_tjson!(Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full")))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+
+    // tjson::Errors keep their code and position
+    let err = try_tjson!({ "a": tjson::from_str::<Value>("[1,]") }).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ExpectedSomeValue);
+    assert_eq!(err.to_string(), "expected value at line 1 column 4");
+
+    // Options inside Results are not unwrapped a second time
+    let nested: std::result::Result<Option<u8>, Error> = Ok(None);
+    assert_eq!(try_tjson!({ "a": nested }).unwrap(), tjson!({ "a": null }));
+}
+
+fn tags(raw: &str) -> Result<Value> {
+    try_tjson!({
+        "count": raw.split(',').count(),
+        "tags": try_tjson!(
+            raw.split(',')
+                .map(|tag| if tag.is_empty() { Err("empty tag") } else { Ok(tag) })
+                .collect::<std::result::Result<Vec<_>, _>>()
+        ),
+    })
+}
+
+#[test]
+fn test_try_tjson_nested() {
+    assert_eq!(tags("a,b").unwrap(), tjson!({ "count": 2, "tags": ["a", "b"] }));
+    assert_eq!(tags("a,,b").unwrap_err().to_string(), "empty tag");
+
+    let inner = try_tjson!({ "x": Err::<u8, _>("bad x") });
+    assert_eq!(try_tjson!([[inner]]).unwrap_err().to_string(), "bad x");
+
+    // tjson! doesn't know about errors, so it serializes the Result itself
+    let value = tjson!({ "inner": try_tjson!([1]) });
+    assert_eq!(value, tjson!({ "inner": { "Ok": [1] } }));
+    let value = tjson!({ "inner": try_tjson!(Err::<u8, _>("bad")) });
+    assert_eq!(value["inner"]["Err"]["message:s"], tjson!("bad"));
+}
+
+#[test]
+fn test_try_tjson_matches_tjson() {
+    let code = 200;
+    let features = ["serde", "tjson"];
+
+    assert_eq!(try_tjson!(null).unwrap(), tjson!(null));
+    assert_eq!(try_tjson!(true).unwrap(), tjson!(true));
+    assert_eq!(try_tjson!([]).unwrap(), tjson!([]));
+    assert_eq!(try_tjson!({}).unwrap(), tjson!({}));
+    assert_eq!(try_tjson!(code).unwrap(), tjson!(code));
+    assert_eq!(
+        try_tjson!({
+            "code": code,
+            "success": code == 200,
+            "nothing": null,
+            "payload": {
+                features[0]: features[1],
+                "list": [1, -2.5, "three", [false], {}, null],
+            },
+        }).unwrap(),
+        tjson!({
+            "code": code,
+            "success": code == 200,
+            "nothing": null,
+            "payload": {
+                features[0]: features[1],
+                "list": [1, -2.5, "three", [false], {}, null],
+            },
+        })
+    );
+
+    // Results whose error type doesn't convert are serialized as usual, and
+    // the same tricky token sequences as tjson! are accepted
+    assert_eq!(
+        try_tjson!([
+            <std::result::Result<(), ()> as Clone>::clone(&Ok(())),
+            <std::result::Result<(), ()> as Clone>::clone(&Err(()))
+        ]).unwrap(),
+        tjson!([
+            <std::result::Result<(), ()> as Clone>::clone(&Ok(())),
+            <std::result::Result<(), ()> as Clone>::clone(&Err(()))
+        ])
+    );
+    assert_eq!(
+        try_tjson!({
+            (<std::result::Result<&str, ()> as Clone>::clone(&Ok("")).unwrap()): "ok",
+        }).unwrap(),
+        tjson!({ "": "ok" })
+    );
+}
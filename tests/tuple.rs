@@ -0,0 +1,135 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+mod common;
+
+use common::parse;
+use serde::Deserialize;
+use tjson::{DateTime, Deserializer};
+use tjson::de::{DuplicateKeys, Parser};
+
+type Row = (String, u64, DateTime);
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Point(i32, i32);
+
+fn timestamp() -> DateTime {
+    "2017-03-04T05:06:07Z".parse().unwrap()
+}
+
+#[test]
+fn test_tuple_from_array() {
+    let row: Row = parse(r#"["disk", 3, "2017-03-04T05:06:07Z"]"#, None).unwrap();
+    assert_eq!(row, ("disk".to_owned(), 3, timestamp()));
+
+    // The field order doesn't matter for arrays
+    let order = Some(&["count", "name", "ts"][..]);
+    let row: Row = parse(r#"["disk", 3, "2017-03-04T05:06:07Z"]"#, order).unwrap();
+    assert_eq!(row, ("disk".to_owned(), 3, timestamp()));
+
+    assert_eq!(parse::<Point, _>("[1, -2]", None).unwrap(), Point(1, -2));
+    assert_eq!(parse::<(), _>("null", None).unwrap(), ());
+}
+
+#[test]
+fn test_tuple_from_object() {
+    let order = Some(&["name", "count", "ts"][..]);
+    let input =
+        r#"{"ts:t": "2017-03-04T05:06:07Z", "extra:s": "x", "name:s": "disk", "count:u": "3"}"#;
+    let row: Row = parse(input, order).unwrap();
+    assert_eq!(row, ("disk".to_owned(), 3, timestamp()));
+
+    // Tuple structs too, and names may be given with their tags
+    let point: Point = parse(r#"{"y:i": "2", "x:i": "1"}"#, Some(&["x:i", "y"][..])).unwrap();
+    assert_eq!(point, Point(1, 2));
+
+    // Nested inside other values, and the order is reused by a parser
+    let mut parser = Parser::new().field_order(&["x", "y"]);
+    for _ in 0..2 {
+        let points: Vec<Point> = parser.parse_str(r#"[{"x": 1, "y": 2}, [3, 4]]"#).unwrap();
+        assert_eq!(points, vec![Point(1, 2), Point(3, 4)]);
+    }
+
+    // Only the first names are used by shorter tuples
+    let pair: (String, u64) = parse(input, order).unwrap();
+    assert_eq!(pair, ("disk".to_owned(), 3));
+
+    // Duplicates are resolved first, by the policy
+    let input = r#"{"x": 1, "y": 2, "x": 3}"#;
+    let err = parse::<Point, _>(input, Some(&["x", "y"][..])).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate member \"x\" at line 1 column 20");
+    let mut de = Deserializer::from_str(input)
+        .field_order(&["x", "y"])
+        .duplicate_keys(DuplicateKeys::FirstWins);
+    assert_eq!(Point::deserialize(&mut de).unwrap(), Point(1, 2));
+    let mut de = Deserializer::from_str(input)
+        .field_order(&["x", "y"])
+        .duplicate_keys(DuplicateKeys::LastWins);
+    assert_eq!(Point::deserialize(&mut de).unwrap(), Point(3, 2));
+}
+
+#[test]
+fn test_tuple_from_object_errors() {
+    let err = parse::<Row, _>(r#"{"name:s": "disk"}"#, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot deserialize tuple from object without field_order at line 1 column 1"
+    );
+    assert!(err.is_data());
+
+    let order = Some(&["name", "count", "ts"][..]);
+    let err = parse::<Row, _>(r#"{"name:s": "disk", "ts:t": "2017-03-04T05:06:07Z"}"#, order)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "missing member `count` at line 1 column 50");
+
+    // More elements than names
+    let input = r#"{"name:s": "disk", "count:u": "3"}"#;
+    let err = parse::<Row, _>(input, Some(&["name", "count"][..])).unwrap_err();
+    assert!(err.to_string().starts_with("invalid length 2"), "{}", err);
+
+    // The members still have to be the right types
+    let err = parse::<Point, _>(r#"{"x": 1, "y": "2"}"#, Some(&["x", "y"][..])).unwrap_err();
+    assert!(err.to_string().starts_with("invalid type: string \"2\""), "{}", err);
+}
+
+#[test]
+fn test_fixed_size_arrays() {
+    assert_eq!(parse::<[u8; 3], _>("[1, 2, 3]", None).unwrap(), [1, 2, 3]);
+    assert_eq!(parse::<[u8; 0], _>("[]", None).unwrap(), [0u8; 0]);
+    assert_eq!(parse::<[[u8; 2]; 2], _>("[[1, 2], [3, 4]]", None).unwrap(), [[1, 2], [3, 4]]);
+
+    let err = parse::<[u8; 3], _>("[1, 2]", None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid length 2, expected an array of length 3 at line 1 column 6"
+    );
+
+    let err = parse::<[u8; 3], _>("[1, 2, 3, 4, 5]", None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid length 5, expected an array of 3 elements at line 1 column 15"
+    );
+    assert!(err.is_data());
+
+    let err = parse::<(u8, u8), _>("[1, 2, 3]", None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid length 3, expected an array of 2 elements at line 1 column 9"
+    );
+
+    // Serde asks for fixed-size arrays as tuples, so they can come from
+    // objects as well
+    let input = r#"{"b": 2, "a": 1}"#;
+    assert_eq!(parse::<[u8; 2], _>(input, Some(&["a", "b"][..])).unwrap(), [1, 2]);
+    assert!(parse::<[u8; 2], _>(input, None).is_err());
+}
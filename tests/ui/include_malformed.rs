@@ -0,0 +1,15 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate tjson;
+#[macro_use]
+extern crate tjson_macros;
+
+fn main() {
+    include_tjson!("tests/include/malformed.tjson");
+}
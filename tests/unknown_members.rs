@@ -0,0 +1,78 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Members a struct doesn't know about, kept with `#[serde(flatten)]` so
+//! that writing the struct out again doesn't lose them.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tjson;
+
+use tjson::{Map, Value};
+use tjson::value::ValueKind;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Config {
+    #[serde(rename = "name:s")]
+    name: String,
+    #[serde(rename = "retries:u", default)]
+    retries: u64,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+/// Members from a newer version of `Config`, in name order so that they are
+/// written back in the same order with or without `preserve_order`.
+const UNKNOWN: &[&str] = &[
+    r#""key:d64":"SGVsbG8sIHdvcmxkIQ""#,
+    r#""limits:O":{"at:t":"2017-03-04T05:06:07.5Z","ids:S<i>":["-1","2"],"ratio:f":0.25}"#,
+    r#""mac:d16":"48656c6c6f""#,
+    r#""seen:A<t>":["2017-03-04T05:06:07Z","2017-03-05T05:06:07.250Z"]"#,
+    r#""tag:d32":"jbswy3dp""#,
+];
+
+fn document() -> String {
+    format!(r#"{{"name:s":"worker","retries:u":"3",{}}}"#, UNKNOWN.join(","))
+}
+
+#[test]
+fn test_unknown_members_round_trip() {
+    let input = document();
+    let config: Config = tjson::from_str(&input).unwrap();
+    assert_eq!((config.name.as_str(), config.retries), ("worker", 3));
+    assert_eq!(config.rest.len(), UNKNOWN.len());
+
+    let output = tjson::to_string(&config).unwrap();
+    for member in UNKNOWN {
+        assert!(output.contains(member), "{} not in {}", member, output);
+    }
+    assert_eq!(output, input);
+
+    // Timestamps which are written as they would be are read as timestamps
+    assert_eq!(config.rest["seen:A<t>"][0].kind(), ValueKind::Timestamp);
+    assert_eq!(config.rest["seen:A<t>"][1].kind(), ValueKind::Timestamp);
+    assert!(config.rest["limits:O"]["at:t"].is_string());
+}
+
+#[test]
+fn test_unknown_members_pretty() {
+    let config: Config = tjson::from_str(&document()).unwrap();
+    let pretty = tjson::to_string_pretty(&config).unwrap();
+    let again: Config = tjson::from_str(&pretty).unwrap();
+    assert_eq!(again.rest, config.rest);
+    assert_eq!(tjson::to_string(&again).unwrap(), document());
+}
+
+#[test]
+fn test_unknown_members_through_value() {
+    let value: Value = tjson::from_str(&document()).unwrap();
+    let config: Config = tjson::from_value(value.clone()).unwrap();
+    assert_eq!(tjson::to_value(&config).unwrap(), value);
+    assert_eq!(tjson::to_string(&config).unwrap(), document());
+}
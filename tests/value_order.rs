@@ -0,0 +1,184 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+#[macro_use]
+extern crate tjson;
+
+mod common;
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use chrono::offset::TimeZone;
+use chrono::offset::utc::UTC;
+use common::Rng;
+use tjson::{Data, Map, Set, Value};
+
+fn set(values: Vec<Value>) -> Value {
+    let mut set = Set::new();
+    for value in values {
+        set.insert(value);
+    }
+    Value::Set(set)
+}
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    let mut map = Map::new();
+    for (name, value) in members {
+        map.insert(name.to_owned(), value);
+    }
+    Value::Object(map)
+}
+
+/// One or two values of each kind, in the order `Value` sorts them
+fn canonical() -> Vec<Value> {
+    vec![
+        Value::Undefined,
+        tjson!(false),
+        tjson!(true),
+        Value::Data(Data::new()),
+        Value::Data(vec![0, 255].into()),
+        tjson!(i64::MIN),
+        tjson!(-1),
+        tjson!(0),
+        tjson!(u64::MAX),
+        tjson!(-0.5),
+        tjson!(0.5),
+        tjson!(""),
+        tjson!("a"),
+        tjson!("b"),
+        Value::Timestamp(UTC.timestamp(0, 0)),
+        Value::Timestamp(UTC.timestamp(1_500_000_000, 0)),
+        tjson!([]),
+        tjson!([1]),
+        tjson!([1, 2]),
+        set(vec![]),
+        set(vec![tjson!(1)]),
+        tjson!({}),
+        tjson!({"a": 1}),
+    ]
+}
+
+#[test]
+fn test_canonical_order() {
+    let values = canonical();
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            assert_eq!(a.cmp(b), i.cmp(&j), "{:?} against {:?}", a, b);
+            assert_eq!(a.partial_cmp(b), Some(i.cmp(&j)));
+        }
+    }
+}
+
+#[test]
+fn test_set_iteration_order() {
+    let mut values = canonical();
+    values.reverse();
+    let sorted: Vec<Value> = values.iter().cloned().collect::<BTreeSet<Value>>()
+        .into_iter()
+        .collect();
+    assert_eq!(sorted, canonical());
+
+    if !cfg!(any(feature = "preserve_order", feature = "preserve_order_indexmap")) {
+        let iterated: Vec<Value> = match set(values) {
+            Value::Set(set) => set.iter().cloned().collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(iterated, canonical());
+    }
+}
+
+#[test]
+fn test_numbers_across_representations() {
+    let signed: Value = tjson::from_str(r#"{"n:i": "7"}"#).unwrap();
+    let unsigned: Value = tjson::from_str(r#"{"n:u": "7"}"#).unwrap();
+    let (signed, unsigned) = (&signed["n:i"], &unsigned["n:u"]);
+    assert_eq!(signed.cmp(unsigned), Ordering::Equal);
+    assert_eq!(signed, unsigned);
+
+    assert_eq!(tjson!(-0.0).cmp(&tjson!(0.0)), Ordering::Equal);
+    assert!(tjson!(1) < tjson!(0.5));
+    assert!(tjson!(-1) < tjson!(0));
+}
+
+/// A random value drawn from few enough choices that equal values of
+/// different provenance turn up often
+fn random_value(rng: &mut Rng, depth: usize) -> Value {
+    let kinds = if depth == 0 { 6 } else { 9 };
+    match rng.below(kinds) {
+        0 => Value::Undefined,
+        1 => Value::Bool(rng.below(2) == 0),
+        2 => Value::Data((0..rng.below(3)).map(|_| rng.below(3) as u8).collect()),
+        3 => {
+            match rng.below(4) {
+                0 => tjson!(rng.below(3) as i64 - 1),
+                1 => tjson!(rng.below(3)),
+                2 => tjson!(rng.below(3) as f64 - 1.0),
+                _ => tjson!(-0.0),
+            }
+        }
+        4 => Value::String(["", "a", "b", "ab"][rng.below(4) as usize].to_owned()),
+        5 => Value::Timestamp(UTC.timestamp(rng.below(3) as i64, 0)),
+        6 => Value::Array((0..rng.below(3)).map(|_| random_value(rng, depth - 1)).collect()),
+        7 => set((0..rng.below(3)).map(|_| random_value(rng, depth - 1)).collect()),
+        _ => {
+            let members = (0..rng.below(3))
+                .map(|_| (["a", "b"][rng.below(2) as usize], random_value(rng, depth - 1)))
+                .collect();
+            object(members)
+        }
+    }
+}
+
+fn hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_random_values_consistent() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let values: Vec<Value> = (0..150).map(|_| random_value(&mut rng, 2)).collect();
+
+    for a in &values {
+        assert_eq!(a.cmp(a), Ordering::Equal);
+        for b in &values {
+            let order = a.cmp(b);
+            assert_eq!(order == Ordering::Equal, a == b, "{:?} against {:?}", a, b);
+            assert_eq!(b.cmp(a), order.reverse());
+            assert_eq!(a.partial_cmp(b), Some(order));
+            if a == b {
+                assert_eq!(hash(a), hash(b));
+            }
+            for c in &values {
+                if order != Ordering::Greater && b.cmp(c) != Ordering::Greater {
+                    assert_ne!(a.cmp(c), Ordering::Greater, "{:?} <= {:?} <= {:?}", a, b, c);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_random_values_sort() {
+    let mut rng = Rng(7);
+    let mut values: Vec<Value> = (0..500).map(|_| random_value(&mut rng, 3)).collect();
+    values.sort();
+    for pair in values.windows(2) {
+        assert!(pair[0] <= pair[1]);
+    }
+
+    let distinct: BTreeSet<Value> = values.iter().cloned().collect();
+    let mut deduped = values.clone();
+    deduped.dedup();
+    assert_eq!(distinct.len(), deduped.len());
+}
@@ -0,0 +1,181 @@
+// Copyright 2017 Tony Arcieri
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Embedding TJSON documents at compile time
+//!
+//! `include_tjson!` reads a file as it compiles, as `include_str!` does,
+//! and parses it with `tjson::from_str`. A document which doesn't parse is
+//! a compile error which says where, and one which does expands to code
+//! building the same `tjson::Value`, so nothing is parsed at runtime:
+//!
+//! ```rust,ignore
+//! #[macro_use]
+//! extern crate tjson;
+//! #[macro_use]
+//! extern crate tjson_macros;
+//!
+//! fn main() {
+//!     let defaults = include_tjson!("config/defaults.tjson");
+//!     assert_eq!(defaults["retries:u"], tjson!(3));
+//! }
+//! ```
+//!
+//! The path is relative to the directory holding the `Cargo.toml` of the
+//! crate being built, rather than to the file the macro is used in, and
+//! must be a plain string literal. The crate is rebuilt when the file
+//! changes.
+//!
+//! Every kind of value is built directly, timestamps from their seconds
+//! and nanoseconds and floats from their bits, so the value is equal to
+//! what `tjson::from_str` reads from the same file, integers stored as
+//! signed included.
+
+extern crate chrono;
+extern crate proc_macro;
+extern crate tjson;
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Timelike;
+use proc_macro::{TokenStream, TokenTree};
+use tjson::Value;
+
+/// Reads and parses a TJSON file at compile time, expanding to an
+/// expression which builds its `tjson::Value`. See the crate documentation.
+#[proc_macro]
+pub fn include_tjson(input: TokenStream) -> TokenStream {
+    let code = match expand(input) {
+        Ok(code) => code,
+        Err(message) => format!("compile_error!({:?})", message),
+    };
+    code.parse().expect("include_tjson! expanded to invalid tokens")
+}
+
+fn expand(input: TokenStream) -> Result<String, String> {
+    let relative = try!(path_literal(input));
+    let mut path = env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+    path.push(&relative);
+
+    let text = try!(
+        fs::read_to_string(&path).map_err(|err| format!("couldn't read {}: {}", relative, err))
+    );
+    let value: Value = try!(tjson::from_str(&text).map_err(|err| format!("{}: {}", relative, err)));
+
+    let path = try!(path.to_str().ok_or_else(|| format!("{} isn't valid UTF-8", relative)));
+    let mut code = String::new();
+    // Refers to the file so that the crate is rebuilt when it changes
+    write!(code, "{{ const _: &'static [u8] = include_bytes!({:?}); ", path).unwrap();
+    build(&value, &mut code);
+    code.push_str(" }");
+    Ok(code)
+}
+
+/// The text of the single string literal the macro was given.
+fn path_literal(input: TokenStream) -> Result<String, String> {
+    let usage = "include_tjson! takes the path of a file as a string literal";
+    let mut tokens = input.into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal.to_string(),
+        _ => return Err(usage.to_owned()),
+    };
+
+    if literal.len() < 2 || !literal.starts_with('"') || !literal.ends_with('"') {
+        return Err(usage.to_owned());
+    }
+    let mut path = String::with_capacity(literal.len());
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some(c @ '\\') | Some(c @ '"') => path.push(c),
+                    _ => return Err(format!("{}, without escapes other than \\\\", usage)),
+                }
+            }
+            c => path.push(c),
+        }
+    }
+    Ok(path)
+}
+
+/// Writes an expression which builds `value`.
+fn build(value: &Value, code: &mut String) {
+    match *value {
+        Value::Undefined => code.push_str("::tjson::Value::Undefined"),
+        Value::Bool(b) => write!(code, "::tjson::Value::Bool({})", b).unwrap(),
+        Value::Data(ref bytes) => {
+            code.push_str("::tjson::Value::Data(::tjson::Data::from(&b\"");
+            for byte in bytes.iter() {
+                write!(code, "\\x{:02x}", byte).unwrap();
+            }
+            code.push_str("\"[..]))");
+        }
+        Value::Number(ref n) => {
+            if let Some(u) = n.as_u64().filter(|_| !n.is_signed()) {
+                write!(code, "::tjson::Value::from({}u64)", u).unwrap();
+            } else if let Some(i) = n.as_i64() {
+                write!(
+                    code,
+                    "::tjson::Value::Number(::tjson::Number::from_i64_signed({}i64))",
+                    i
+                )
+                    .unwrap();
+            } else {
+                let bits = n.as_f64().map_or(0, f64::to_bits);
+                write!(
+                    code,
+                    "::tjson::Value::Number(::tjson::Number::from_f64(f64::from_bits({:#x}))\
+                     .unwrap())",
+                    bits
+                )
+                    .unwrap();
+            }
+        }
+        Value::String(ref s) => {
+            write!(code, "::tjson::Value::String(::std::string::String::from({:?}))", s).unwrap()
+        }
+        Value::Timestamp(ref t) => {
+            write!(
+                code,
+                "::tjson::value::timestamp_from_parts({}, {})",
+                t.timestamp(),
+                t.nanosecond()
+            )
+                .unwrap();
+        }
+        Value::Array(ref elements) => {
+            code.push_str("::tjson::Value::Array(vec![");
+            for element in elements {
+                build(element, code);
+                code.push_str(", ");
+            }
+            code.push_str("])");
+        }
+        Value::Set(ref set) => {
+            code.push_str("{ let mut set = ::tjson::Set::new(); ");
+            for element in set.iter() {
+                code.push_str("set.insert(");
+                build(element, code);
+                code.push_str("); ");
+            }
+            code.push_str("::tjson::Value::Set(set) }");
+        }
+        Value::Object(ref map) => {
+            code.push_str("{ let mut map = ::tjson::Map::new(); ");
+            for (name, member) in map.iter() {
+                write!(code, "map.insert(::std::string::String::from({:?}), ", name).unwrap();
+                build(member, code);
+                code.push_str("); ");
+            }
+            code.push_str("::tjson::Value::Object(map) }");
+        }
+    }
+}